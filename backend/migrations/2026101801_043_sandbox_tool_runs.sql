@@ -0,0 +1,18 @@
+-- Isolated sandbox backends for the sandbox_execute_code tool
+-- Tool-path runs are attributed to the chat session / execution that triggered them.
+
+ALTER TABLE ch_sandbox_executions ADD COLUMN IF NOT EXISTS chat_session_id UUID;
+ALTER TABLE ch_sandbox_executions ADD COLUMN IF NOT EXISTS execution_id TEXT;
+ALTER TABLE ch_sandbox_executions ADD COLUMN IF NOT EXISTS backend TEXT NOT NULL DEFAULT 'docker';
+ALTER TABLE ch_sandbox_executions ADD COLUMN IF NOT EXISTS memory_mb INTEGER;
+ALTER TABLE ch_sandbox_executions ADD COLUMN IF NOT EXISTS cpus DOUBLE PRECISION;
+ALTER TABLE ch_sandbox_executions ADD COLUMN IF NOT EXISTS network_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE ch_sandbox_executions ADD COLUMN IF NOT EXISTS workspace_mounted BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS idx_ch_sandbox_executions_chat_session
+    ON ch_sandbox_executions (chat_session_id, executed_at DESC)
+    WHERE chat_session_id IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_ch_sandbox_executions_execution
+    ON ch_sandbox_executions (execution_id)
+    WHERE execution_id IS NOT NULL;
@@ -0,0 +1,118 @@
+-- Multilingual full-text search (Polish + English, unaccented) and tool-interaction indexing
+-- Replaces the hardcoded 'english' search_vector from migration 028.
+
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- 1. Text search configurations — all strip diacritics via unaccent
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'ch_simple') THEN
+        CREATE TEXT SEARCH CONFIGURATION ch_simple (COPY = simple);
+        ALTER TEXT SEARCH CONFIGURATION ch_simple
+            ALTER MAPPING FOR hword, hword_part, word WITH unaccent, simple;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'ch_english') THEN
+        CREATE TEXT SEARCH CONFIGURATION ch_english (COPY = english);
+        ALTER TEXT SEARCH CONFIGURATION ch_english
+            ALTER MAPPING FOR hword, hword_part, word WITH unaccent, english_stem;
+    END IF;
+
+    -- Stock PostgreSQL ships no Polish stemmer. Use an installed ispell/hunspell
+    -- dictionary named 'polish' when present, otherwise unaccented simple tokens.
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'ch_polish') THEN
+        CREATE TEXT SEARCH CONFIGURATION ch_polish (COPY = simple);
+        IF EXISTS (SELECT 1 FROM pg_ts_dict WHERE dictname = 'polish') THEN
+            ALTER TEXT SEARCH CONFIGURATION ch_polish
+                ALTER MAPPING FOR hword, hword_part, word WITH unaccent, polish, simple;
+        ELSE
+            ALTER TEXT SEARCH CONFIGURATION ch_polish
+                ALTER MAPPING FOR hword, hword_part, word WITH unaccent, simple;
+        END IF;
+    END IF;
+END $$;
+
+-- 2. Language code → text search configuration
+CREATE OR REPLACE FUNCTION ch_search_config(lang TEXT) RETURNS regconfig AS $$
+    SELECT CASE lang
+        WHEN 'pl' THEN 'ch_polish'::regconfig
+        WHEN 'en' THEN 'ch_english'::regconfig
+        ELSE 'ch_simple'::regconfig
+    END;
+$$ LANGUAGE sql IMMUTABLE;
+
+-- 3. Lightweight per-message language detection (diacritics + stopwords),
+--    falling back to the UI language from ch_settings.
+CREATE OR REPLACE FUNCTION ch_detect_language(content TEXT) RETURNS TEXT AS $$
+DECLARE
+    body TEXT := lower(COALESCE(content, ''));
+    fallback TEXT;
+BEGIN
+    IF body ~ '[ąćęłńóśźż]'
+        OR body ~ '\m(nie|jest|się|że|czy|jak|dla|oraz|jestem|proszę|dlaczego)\M' THEN
+        RETURN 'pl';
+    END IF;
+    IF body ~ '\m(the|is|are|and|with|what|how|please|this|that)\M' THEN
+        RETURN 'en';
+    END IF;
+    SELECT language INTO fallback FROM ch_settings WHERE id = 1;
+    RETURN CASE WHEN fallback IN ('pl', 'en') THEN fallback ELSE 'simple' END;
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+-- 4. Per-message language + language-aware search_vector
+ALTER TABLE ch_messages ADD COLUMN IF NOT EXISTS search_language TEXT;
+
+CREATE OR REPLACE FUNCTION ch_messages_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    IF NEW.search_language IS NULL
+        OR (TG_OP = 'UPDATE' AND NEW.content IS DISTINCT FROM OLD.content
+            AND NEW.search_language IS NOT DISTINCT FROM OLD.search_language) THEN
+        NEW.search_language := ch_detect_language(NEW.content);
+    END IF;
+    NEW.search_vector := to_tsvector(ch_search_config(NEW.search_language), COALESCE(NEW.content, ''));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Re-create the trigger so a search_language change also refreshes the vector
+DROP TRIGGER IF EXISTS trg_ch_messages_search_vector ON ch_messages;
+CREATE TRIGGER trg_ch_messages_search_vector
+    BEFORE INSERT OR UPDATE OF content, search_language ON ch_messages
+    FOR EACH ROW
+    EXECUTE FUNCTION ch_messages_search_vector_update();
+
+CREATE INDEX IF NOT EXISTS idx_ch_messages_search_language ON ch_messages(search_language);
+
+-- Backfill: detect language + rebuild vectors for existing messages
+UPDATE ch_messages
+SET search_language = ch_detect_language(content)
+WHERE search_language IS NULL;
+
+-- 5. Tool interactions: name + input + result are searchable
+ALTER TABLE ch_tool_interactions ADD COLUMN IF NOT EXISTS search_vector tsvector;
+
+CREATE INDEX IF NOT EXISTS idx_ch_ti_search ON ch_tool_interactions USING GIN(search_vector);
+
+CREATE OR REPLACE FUNCTION ch_tool_interactions_search_vector_update() RETURNS trigger AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('ch_simple', COALESCE(NEW.tool_name, '')), 'A')
+        || setweight(to_tsvector('ch_simple', COALESCE(NEW.tool_input::text, '')), 'B')
+        || setweight(to_tsvector('ch_simple', LEFT(COALESCE(NEW.result, ''), 100000)), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_ch_tool_interactions_search_vector ON ch_tool_interactions;
+CREATE TRIGGER trg_ch_tool_interactions_search_vector
+    BEFORE INSERT OR UPDATE OF tool_name, tool_input, result ON ch_tool_interactions
+    FOR EACH ROW
+    EXECUTE FUNCTION ch_tool_interactions_search_vector_update();
+
+UPDATE ch_tool_interactions
+SET search_vector =
+    setweight(to_tsvector('ch_simple', COALESCE(tool_name, '')), 'A')
+    || setweight(to_tsvector('ch_simple', COALESCE(tool_input::text, '')), 'B')
+    || setweight(to_tsvector('ch_simple', LEFT(COALESCE(result, ''), 100000)), 'C')
+WHERE search_vector IS NULL;
@@ -0,0 +1,32 @@
+-- Hybrid session search: track which messages have been embedded into Qdrant
+-- (collection ch_session_messages). NULL = pending for the incremental sweep / backfill.
+
+ALTER TABLE ch_messages ADD COLUMN IF NOT EXISTS embedded_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_ch_messages_embedding_pending
+    ON ch_messages (created_at)
+    WHERE embedded_at IS NULL;
+
+-- Qdrant points of deleted messages (directly or with their session). The
+-- session index sweep removes the points and then the rows. Only messages that
+-- have a point are recorded: indexed and at least 20 characters once trimmed
+-- (session_index::HAS_VECTOR_SQL).
+CREATE TABLE IF NOT EXISTS ch_message_embedding_deletions (
+    message_id UUID PRIMARY KEY,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE OR REPLACE FUNCTION ch_messages_embedding_after_delete() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO ch_message_embedding_deletions (message_id) VALUES (OLD.id)
+    ON CONFLICT DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_ch_messages_embedding_after_delete ON ch_messages;
+CREATE TRIGGER trg_ch_messages_embedding_after_delete
+    AFTER DELETE ON ch_messages
+    FOR EACH ROW
+    WHEN (OLD.embedded_at IS NOT NULL AND char_length(btrim(OLD.content, E' \t\r\n')) >= 20)
+    EXECUTE FUNCTION ch_messages_embedding_after_delete();
@@ -0,0 +1,113 @@
+-- Session branching: messages form a tree per session, sessions can be forked.
+--
+-- ch_messages.parent_message_id links each message to the turn it answers/follows.
+-- ch_sessions.active_leaf_message_id is the head of the branch currently shown and
+-- fed to the model; ch_messages.on_active_path caches "is an ancestor of the head"
+-- so that list/search queries stay simple.
+--
+-- Inserts that do not specify a parent are appended to the active branch, so the
+-- existing linear writers (WS store, shared handlers) keep working unchanged.
+-- A NULL head means "next message starts a new root" (edit of the first turn).
+
+ALTER TABLE ch_sessions
+    ADD COLUMN IF NOT EXISTS parent_session_id UUID REFERENCES ch_sessions(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS forked_from_message_id UUID,
+    ADD COLUMN IF NOT EXISTS active_leaf_message_id UUID;
+
+ALTER TABLE ch_messages
+    ADD COLUMN IF NOT EXISTS parent_message_id UUID REFERENCES ch_messages(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS on_active_path BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE INDEX IF NOT EXISTS idx_ch_messages_parent ON ch_messages (parent_message_id);
+CREATE INDEX IF NOT EXISTS idx_ch_sessions_parent ON ch_sessions (parent_session_id)
+    WHERE parent_session_id IS NOT NULL;
+
+-- ── Backfill: existing sessions are a single linear branch ──────────────────
+
+UPDATE ch_messages m
+SET parent_message_id = sub.prev_id
+FROM (
+    SELECT id, LAG(id) OVER (PARTITION BY session_id ORDER BY created_at, id) AS prev_id
+    FROM ch_messages
+) sub
+WHERE m.id = sub.id
+  AND m.parent_message_id IS NULL
+  AND sub.prev_id IS NOT NULL;
+
+UPDATE ch_sessions s
+SET active_leaf_message_id = (
+    SELECT id FROM ch_messages
+    WHERE session_id = s.id
+    ORDER BY created_at DESC, id DESC
+    LIMIT 1
+)
+WHERE active_leaf_message_id IS NULL;
+
+ALTER TABLE ch_sessions
+    DROP CONSTRAINT IF EXISTS ch_sessions_active_leaf_fk;
+ALTER TABLE ch_sessions
+    ADD CONSTRAINT ch_sessions_active_leaf_fk
+    FOREIGN KEY (active_leaf_message_id) REFERENCES ch_messages(id) ON DELETE SET NULL;
+
+-- ── Checkout: make `p_leaf` the head and recompute on_active_path ───────────
+
+CREATE OR REPLACE FUNCTION ch_checkout_message(p_session UUID, p_leaf UUID) RETURNS void AS $$
+BEGIN
+    UPDATE ch_sessions SET active_leaf_message_id = p_leaf WHERE id = p_session;
+
+    UPDATE ch_messages m
+    SET on_active_path = m.id IN (
+        WITH RECURSIVE path AS (
+            SELECT id, parent_message_id FROM ch_messages
+            WHERE id = p_leaf AND session_id = p_session
+            UNION ALL
+            SELECT c.id, c.parent_message_id
+            FROM ch_messages c JOIN path p ON c.id = p.parent_message_id
+            WHERE c.session_id = p_session
+        )
+        SELECT id FROM path
+    )
+    WHERE m.session_id = p_session;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ── Insert triggers ─────────────────────────────────────────────────────────
+
+CREATE OR REPLACE FUNCTION ch_messages_branch_before_insert() RETURNS trigger AS $$
+BEGIN
+    IF NEW.parent_message_id IS NULL THEN
+        SELECT active_leaf_message_id INTO NEW.parent_message_id
+        FROM ch_sessions WHERE id = NEW.session_id;
+    END IF;
+    NEW.on_active_path := TRUE;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION ch_messages_branch_after_insert() RETURNS trigger AS $$
+DECLARE
+    current_leaf UUID;
+BEGIN
+    SELECT active_leaf_message_id INTO current_leaf
+    FROM ch_sessions WHERE id = NEW.session_id;
+
+    IF current_leaf IS NOT DISTINCT FROM NEW.parent_message_id THEN
+        -- Common case: extending the active branch.
+        UPDATE ch_sessions SET active_leaf_message_id = NEW.id WHERE id = NEW.session_id;
+    ELSE
+        -- New sibling branch: it becomes the active one.
+        PERFORM ch_checkout_message(NEW.session_id, NEW.id);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_ch_messages_branch_before_insert ON ch_messages;
+CREATE TRIGGER trg_ch_messages_branch_before_insert
+    BEFORE INSERT ON ch_messages
+    FOR EACH ROW EXECUTE FUNCTION ch_messages_branch_before_insert();
+
+DROP TRIGGER IF EXISTS trg_ch_messages_branch_after_insert ON ch_messages;
+CREATE TRIGGER trg_ch_messages_branch_after_insert
+    AFTER INSERT ON ch_messages
+    FOR EACH ROW EXECUTE FUNCTION ch_messages_branch_after_insert();
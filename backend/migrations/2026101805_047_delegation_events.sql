@@ -0,0 +1,12 @@
+-- Delegation lifecycle events: link each delegation to the call that issued it
+-- (nested call_agent) and to the top-level chat execution.
+-- New terminal statuses: 'timed_out' and 'cancelled' (status is free-form TEXT).
+
+ALTER TABLE ch_a2a_tasks
+    ADD COLUMN IF NOT EXISTS parent_task_id UUID,
+    ADD COLUMN IF NOT EXISTS execution_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_ch_a2a_tasks_execution ON ch_a2a_tasks(execution_id)
+    WHERE execution_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_ch_a2a_tasks_parent ON ch_a2a_tasks(parent_task_id)
+    WHERE parent_task_id IS NOT NULL;
@@ -0,0 +1,97 @@
+-- Named A/B experiments with sticky per-session arm assignment and exposure logging.
+--
+-- An arm overrides any of: model, temperature, system prompt variant (extra
+-- instructions appended to the server-side system prompt). At most one
+-- experiment runs at a time. Every chat request resolved while an experiment
+-- runs logs one exposure; ch_agent_usage rows point back at it so results can
+-- be computed from latency/tokens, tool success and message ratings.
+--
+-- Supersedes ch_settings.ab_model_b / ab_split (migrated below, no longer read).
+
+CREATE TABLE IF NOT EXISTS ch_experiments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    status TEXT NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'running', 'stopped')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    stopped_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_ch_experiments_single_running
+    ON ch_experiments ((status)) WHERE status = 'running';
+
+CREATE TABLE IF NOT EXISTS ch_experiment_arms (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    experiment_id UUID NOT NULL REFERENCES ch_experiments(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    model TEXT,
+    temperature DOUBLE PRECISION,
+    system_prompt_variant TEXT,
+    weight INT NOT NULL DEFAULT 1 CHECK (weight > 0),
+    UNIQUE (experiment_id, name)
+);
+
+-- Sticky assignment: a session keeps its arm for the lifetime of the experiment.
+CREATE TABLE IF NOT EXISTS ch_experiment_assignments (
+    experiment_id UUID NOT NULL REFERENCES ch_experiments(id) ON DELETE CASCADE,
+    session_id UUID NOT NULL REFERENCES ch_sessions(id) ON DELETE CASCADE,
+    arm_id UUID NOT NULL REFERENCES ch_experiment_arms(id) ON DELETE CASCADE,
+    assigned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (experiment_id, session_id)
+);
+
+-- One row per request that was served by an arm. completed_at stays NULL when
+-- the request failed or was cancelled.
+CREATE TABLE IF NOT EXISTS ch_experiment_exposures (
+    id BIGSERIAL PRIMARY KEY,
+    experiment_id UUID NOT NULL REFERENCES ch_experiments(id) ON DELETE CASCADE,
+    arm_id UUID NOT NULL REFERENCES ch_experiment_arms(id) ON DELETE CASCADE,
+    session_id UUID REFERENCES ch_sessions(id) ON DELETE SET NULL,
+    execution_id TEXT,
+    model TEXT NOT NULL,
+    message_id UUID REFERENCES ch_messages(id) ON DELETE SET NULL,
+    tool_calls INT NOT NULL DEFAULT 0,
+    tool_errors INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_ch_experiment_exposures_arm
+    ON ch_experiment_exposures (experiment_id, arm_id);
+CREATE INDEX IF NOT EXISTS idx_ch_experiment_exposures_message
+    ON ch_experiment_exposures (message_id) WHERE message_id IS NOT NULL;
+
+ALTER TABLE ch_agent_usage
+    ADD COLUMN IF NOT EXISTS exposure_id BIGINT
+        REFERENCES ch_experiment_exposures(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_ch_agent_usage_exposure
+    ON ch_agent_usage (exposure_id) WHERE exposure_id IS NOT NULL;
+
+-- ── Migrate the legacy settings-based A/B split ─────────────────────────────
+
+DO $$
+DECLARE
+    legacy_model TEXT;
+    legacy_split REAL;
+    exp_id UUID;
+BEGIN
+    SELECT ab_model_b, ab_split INTO legacy_model, legacy_split
+    FROM ch_settings WHERE id = 1;
+
+    IF COALESCE(legacy_model, '') <> '' AND legacy_split > 0 AND legacy_split < 1 THEN
+        INSERT INTO ch_experiments (name, description, status, started_at)
+        VALUES ('legacy-ab-model-b', 'Migrated from ch_settings.ab_model_b / ab_split',
+                'running', NOW())
+        ON CONFLICT (name) DO NOTHING
+        RETURNING id INTO exp_id;
+
+        IF exp_id IS NOT NULL THEN
+            INSERT INTO ch_experiment_arms (experiment_id, name, weight)
+            VALUES (exp_id, 'control', GREATEST(1, ROUND((1 - legacy_split) * 100))::INT);
+            INSERT INTO ch_experiment_arms (experiment_id, name, model, weight)
+            VALUES (exp_id, 'model_b', legacy_model, GREATEST(1, ROUND(legacy_split * 100))::INT);
+        END IF;
+    END IF;
+END $$;
@@ -0,0 +1,31 @@
+-- Auto-tier routing: one row per routed request (no explicit model), with the
+-- signals and reasons behind the decision and, for transports that report it,
+-- the outcome. Recent outcomes feed back into later decisions per session and
+-- per complexity class.
+
+CREATE TABLE IF NOT EXISTS ch_routing_decisions (
+    id BIGSERIAL PRIMARY KEY,
+    session_id UUID REFERENCES ch_sessions(id) ON DELETE SET NULL,
+    tier TEXT NOT NULL,
+    model TEXT NOT NULL,
+    complexity TEXT NOT NULL,
+    score INT NOT NULL,
+    signals JSONB NOT NULL DEFAULT '{}'::jsonb,
+    reasons JSONB NOT NULL DEFAULT '[]'::jsonb,
+    -- FALSE for transports that never report completion (NDJSON); those rows
+    -- are kept for tuning but not used as feedback.
+    outcome_tracked BOOLEAN NOT NULL DEFAULT FALSE,
+    execution_id TEXT,
+    success BOOLEAN,
+    latency_ms INT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_ch_routing_decisions_session
+    ON ch_routing_decisions (session_id, created_at DESC) WHERE session_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_ch_routing_decisions_learning
+    ON ch_routing_decisions (complexity, created_at DESC) WHERE outcome_tracked;
+
+-- Daily spend above which routing caps the tier (NULL = no ceiling).
+ALTER TABLE ch_settings ADD COLUMN IF NOT EXISTS routing_cost_ceiling_usd DOUBLE PRECISION DEFAULT NULL;
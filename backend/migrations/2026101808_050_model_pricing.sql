@@ -0,0 +1,58 @@
+-- Versioned model pricing catalogue for cost analytics.
+--
+-- A price applies to usage of models of `provider` matching `model_pattern`
+-- (`*` = any characters) from `effective_from` until a newer row for the same
+-- pattern takes over; the most specific (longest) matching pattern wins.
+-- Rates are per million tokens; NULL cache rates fall back to the input rate.
+
+CREATE TABLE IF NOT EXISTS ch_model_pricing (
+    id BIGSERIAL PRIMARY KEY,
+    provider TEXT NOT NULL,
+    model_pattern TEXT NOT NULL,
+    effective_from TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    input_per_mtok NUMERIC(12, 6) NOT NULL,
+    output_per_mtok NUMERIC(12, 6) NOT NULL,
+    cache_read_per_mtok NUMERIC(12, 6),
+    cache_write_per_mtok NUMERIC(12, 6),
+    currency TEXT NOT NULL DEFAULT 'USD',
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, model_pattern, effective_from)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ch_model_pricing_lookup
+    ON ch_model_pricing (provider, effective_from DESC);
+
+-- Provider of a model id (matches the gateway's provider names).
+CREATE OR REPLACE FUNCTION ch_model_provider(p_model TEXT) RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN p_model ILIKE 'claude%' THEN 'anthropic'
+        WHEN p_model ILIKE 'gemini%' THEN 'google'
+        WHEN p_model ILIKE 'gpt%' OR p_model ~* '^o[0-9]' THEN 'openai'
+        WHEN p_model ILIKE 'grok%' THEN 'xai'
+        WHEN p_model ILIKE 'deepseek%' THEN 'deepseek'
+        ELSE 'ollama'
+    END
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Cached prompt tokens (Anthropic prompt caching, DeepSeek context cache).
+ALTER TABLE ch_agent_usage
+    ADD COLUMN IF NOT EXISTS cache_read_tokens INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS cache_write_tokens INT NOT NULL DEFAULT 0;
+
+-- ── Seed: list prices (previously hardcoded in analytics) ───────────────────
+
+INSERT INTO ch_model_pricing
+    (provider, model_pattern, effective_from, input_per_mtok, output_per_mtok,
+     cache_read_per_mtok, cache_write_per_mtok, notes)
+VALUES
+    ('anthropic', 'claude-*', '2024-01-01', 3, 15, 0.3, 3.75, 'Default Anthropic rate (sonnet)'),
+    ('anthropic', 'claude-*opus*', '2024-01-01', 15, 75, 1.5, 18.75, ''),
+    ('anthropic', 'claude-*sonnet*', '2024-01-01', 3, 15, 0.3, 3.75, ''),
+    ('anthropic', 'claude-*haiku*', '2024-01-01', 0.25, 1.25, 0.03, 0.3, ''),
+    ('google', 'gemini-*flash*', '2024-01-01', 0.3, 2.5, 0.075, NULL, ''),
+    ('google', 'gemini-*pro*', '2024-01-01', 1.25, 10, 0.31, NULL, ''),
+    ('deepseek', 'deepseek-chat*', '2024-01-01', 0.27, 1.1, 0.07, NULL, ''),
+    ('deepseek', 'deepseek-reasoner*', '2024-01-01', 0.55, 2.19, 0.14, NULL, ''),
+    ('ollama', '*', '2024-01-01', 0, 0, 0, 0, 'Local models are free')
+ON CONFLICT (provider, model_pattern, effective_from) DO NOTHING;
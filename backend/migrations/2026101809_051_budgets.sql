@@ -0,0 +1,35 @@
+-- Spending budgets, checked before every Anthropic call of the chat paths
+-- (WebSocket and NDJSON) and of delegated agents (call_agent).
+--
+-- scope:    'user'    — one signed-in user by email (scope_id) or, with
+--                       scope_id NULL, every user individually; usage without
+--                       a user is measured as 'anonymous'
+--           'session' — one chat session (scope_id) or, with scope_id NULL,
+--                       every session individually
+--           'agent'   — one delegated agent by name (scope_id) or every agent
+-- period:   'daily' / 'monthly' (calendar, UTC)
+-- unit:     'usd' (priced via ch_model_pricing) or 'tokens'
+-- Crossing soft_limit warns the client; crossing hard_limit stops execution.
+
+CREATE TABLE IF NOT EXISTS ch_budgets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL UNIQUE,
+    scope TEXT NOT NULL CHECK (scope IN ('user', 'session', 'agent')),
+    scope_id TEXT,
+    period TEXT NOT NULL CHECK (period IN ('daily', 'monthly')),
+    unit TEXT NOT NULL DEFAULT 'usd' CHECK (unit IN ('usd', 'tokens')),
+    soft_limit DOUBLE PRECISION CHECK (soft_limit >= 0),
+    hard_limit DOUBLE PRECISION CHECK (hard_limit >= 0),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (soft_limit IS NOT NULL OR hard_limit IS NOT NULL)
+);
+
+-- Session attribution for usage (session budgets). Delegated agent calls are
+-- recorded with agent_id set to the agent name.
+ALTER TABLE ch_agent_usage
+    ADD COLUMN IF NOT EXISTS session_id UUID REFERENCES ch_sessions(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_ch_agent_usage_session
+    ON ch_agent_usage (session_id, created_at) WHERE session_id IS NOT NULL;
@@ -0,0 +1,44 @@
+-- Per-session / per-execution analytics drill-down.
+--
+-- ch_agent_usage rows are linked to the WebSocket execution and, for
+-- delegated agents (agent_id = agent name), to the ch_a2a_tasks row.
+-- ch_execution_steps is the timeline of a run: one row per model call and per
+-- tool call, for the top-level loop and for every delegation inside it.
+-- Top-level WebSocket input tokens are estimated from request size (the
+-- stream reports output tokens only); delegated calls carry exact usage.
+
+ALTER TABLE ch_agent_usage
+    ADD COLUMN IF NOT EXISTS execution_id TEXT,
+    ADD COLUMN IF NOT EXISTS delegation_task_id UUID;
+
+CREATE INDEX IF NOT EXISTS idx_ch_agent_usage_execution
+    ON ch_agent_usage (execution_id) WHERE execution_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS ch_execution_steps (
+    id BIGSERIAL PRIMARY KEY,
+    -- NULL for delegations started from the NDJSON path.
+    execution_id TEXT,
+    session_id UUID REFERENCES ch_sessions(id) ON DELETE SET NULL,
+    -- Not a foreign key: the ch_a2a_tasks row is written asynchronously.
+    delegation_task_id UUID,
+    agent_id TEXT,
+    kind TEXT NOT NULL CHECK (kind IN ('model_call', 'tool_call')),
+    iteration INT NOT NULL DEFAULT 0,
+    model TEXT,
+    tool_name TEXT,
+    is_error BOOLEAN NOT NULL DEFAULT FALSE,
+    input_tokens INT NOT NULL DEFAULT 0,
+    output_tokens INT NOT NULL DEFAULT 0,
+    cache_read_tokens INT NOT NULL DEFAULT 0,
+    cache_write_tokens INT NOT NULL DEFAULT 0,
+    duration_ms INT NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ch_execution_steps_execution
+    ON ch_execution_steps (execution_id, started_at) WHERE execution_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_ch_execution_steps_session
+    ON ch_execution_steps (session_id, started_at) WHERE session_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_ch_execution_steps_delegation
+    ON ch_execution_steps (delegation_task_id) WHERE delegation_task_id IS NOT NULL;
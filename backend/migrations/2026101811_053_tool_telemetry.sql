@@ -0,0 +1,22 @@
+-- Tool execution telemetry.
+--
+-- Tool-call steps of ch_execution_steps record how the call ended, a
+-- normalised error class, the result size and whether the result was
+-- truncated before being sent back to the model. mcp_server is set for
+-- tools served by an MCP server. ch_tool_interactions (written by clients
+-- when saving messages) gains the error class and an optional duration.
+
+ALTER TABLE ch_execution_steps
+    ADD COLUMN IF NOT EXISTS outcome TEXT
+        CHECK (outcome IN ('ok', 'error', 'timeout', 'panic')),
+    ADD COLUMN IF NOT EXISTS error_class TEXT,
+    ADD COLUMN IF NOT EXISTS result_bytes INT,
+    ADD COLUMN IF NOT EXISTS truncated BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS mcp_server TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_ch_execution_steps_tool
+    ON ch_execution_steps (tool_name, started_at) WHERE kind = 'tool_call';
+
+ALTER TABLE ch_tool_interactions
+    ADD COLUMN IF NOT EXISTS duration_ms INT,
+    ADD COLUMN IF NOT EXISTS error_class TEXT;
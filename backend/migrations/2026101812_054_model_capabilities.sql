@@ -0,0 +1,38 @@
+-- Model capability metadata.
+--
+-- Maintained override table for model limits and features. Providers that
+-- report limits in their model listing (Gemini: input/output token limits,
+-- thinking) take precedence for those fields; everything else comes from
+-- the most specific matching pattern here (`*` is a wildcard, as in
+-- ch_model_pricing). pricing_ref names the ch_model_pricing pattern the
+-- model is billed under.
+
+CREATE TABLE IF NOT EXISTS ch_model_capabilities (
+    model_pattern TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    context_window INT NOT NULL CHECK (context_window > 0),
+    max_output_tokens INT NOT NULL CHECK (max_output_tokens > 0),
+    supports_vision BOOLEAN NOT NULL DEFAULT FALSE,
+    supports_tools BOOLEAN NOT NULL DEFAULT TRUE,
+    supports_thinking BOOLEAN NOT NULL DEFAULT FALSE,
+    pricing_ref TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO ch_model_capabilities
+    (model_pattern, provider, context_window, max_output_tokens, supports_vision,
+     supports_tools, supports_thinking, pricing_ref, notes)
+VALUES
+    ('claude-*', 'anthropic', 200000, 8192, TRUE, TRUE, FALSE, 'claude-*', 'Conservative Anthropic default'),
+    ('claude-opus-4*', 'anthropic', 200000, 32000, TRUE, TRUE, TRUE, 'claude-*opus*', ''),
+    ('claude-sonnet-4*', 'anthropic', 200000, 64000, TRUE, TRUE, TRUE, 'claude-*sonnet*', ''),
+    ('claude-haiku-4*', 'anthropic', 200000, 64000, TRUE, TRUE, TRUE, 'claude-*haiku*', ''),
+    ('claude-3-7-sonnet*', 'anthropic', 200000, 64000, TRUE, TRUE, TRUE, 'claude-*sonnet*', ''),
+    ('claude-3-5-haiku*', 'anthropic', 200000, 8192, FALSE, TRUE, FALSE, 'claude-*haiku*', ''),
+    ('gemini-*', 'google', 1048576, 8192, TRUE, TRUE, FALSE, NULL, 'Conservative Gemini default'),
+    ('gemini-2.5-*', 'google', 1048576, 65536, TRUE, TRUE, TRUE, NULL, ''),
+    ('gemini-3*', 'google', 1048576, 65536, TRUE, TRUE, TRUE, NULL, ''),
+    ('deepseek-chat*', 'deepseek', 128000, 8192, FALSE, TRUE, FALSE, 'deepseek-chat*', ''),
+    ('deepseek-reasoner*', 'deepseek', 128000, 64000, FALSE, TRUE, TRUE, 'deepseek-reasoner*', '')
+ON CONFLICT (model_pattern) DO NOTHING;
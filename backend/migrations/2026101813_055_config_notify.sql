@@ -0,0 +1,39 @@
+-- Cross-replica invalidation of the in-memory settings / pins snapshot.
+--
+-- Every write to the tables the backend caches (settings, model pins, the
+-- running A/B experiment and its arms, capability overrides) notifies
+-- channel `ch_config_changed` with the table name as payload. Each backend
+-- LISTENs on it and drops its snapshot, so edits made through another
+-- replica — or directly in SQL — are picked up without a restart.
+
+CREATE OR REPLACE FUNCTION ch_notify_config_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('ch_config_changed', TG_TABLE_NAME);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_ch_settings_notify ON ch_settings;
+CREATE TRIGGER trg_ch_settings_notify
+    AFTER INSERT OR UPDATE OR DELETE ON ch_settings
+    FOR EACH STATEMENT EXECUTE FUNCTION ch_notify_config_changed();
+
+DROP TRIGGER IF EXISTS trg_ch_model_pins_notify ON ch_model_pins;
+CREATE TRIGGER trg_ch_model_pins_notify
+    AFTER INSERT OR UPDATE OR DELETE ON ch_model_pins
+    FOR EACH STATEMENT EXECUTE FUNCTION ch_notify_config_changed();
+
+DROP TRIGGER IF EXISTS trg_ch_experiments_notify ON ch_experiments;
+CREATE TRIGGER trg_ch_experiments_notify
+    AFTER INSERT OR UPDATE OR DELETE ON ch_experiments
+    FOR EACH STATEMENT EXECUTE FUNCTION ch_notify_config_changed();
+
+DROP TRIGGER IF EXISTS trg_ch_experiment_arms_notify ON ch_experiment_arms;
+CREATE TRIGGER trg_ch_experiment_arms_notify
+    AFTER INSERT OR UPDATE OR DELETE ON ch_experiment_arms
+    FOR EACH STATEMENT EXECUTE FUNCTION ch_notify_config_changed();
+
+DROP TRIGGER IF EXISTS trg_ch_model_capabilities_notify ON ch_model_capabilities;
+CREATE TRIGGER trg_ch_model_capabilities_notify
+    AFTER INSERT OR UPDATE OR DELETE ON ch_model_capabilities
+    FOR EACH STATEMENT EXECUTE FUNCTION ch_notify_config_changed();
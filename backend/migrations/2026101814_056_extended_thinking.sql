@@ -0,0 +1,13 @@
+-- Extended thinking.
+--
+-- thinking_budgets: budget_tokens per tier and per agent, e.g.
+--   {"tiers": {"commander": 8000}, "agents": {"yennefer": 16000}}
+-- An agent's own budget wins over its tier's; 0 / absent disables thinking.
+-- store_thinking: keep the (summarised) thinking of assistant turns in
+-- ch_messages.thinking so it can be shown again with the session.
+
+ALTER TABLE ch_settings
+    ADD COLUMN IF NOT EXISTS thinking_budgets JSONB NOT NULL DEFAULT '{}'::jsonb,
+    ADD COLUMN IF NOT EXISTS store_thinking BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE ch_messages ADD COLUMN IF NOT EXISTS thinking TEXT;
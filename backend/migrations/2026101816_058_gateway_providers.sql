@@ -0,0 +1,37 @@
+-- Runtime configuration of AI gateway providers.
+--
+-- `kind = 'builtin'` rows override the factory defaults of a built-in
+-- provider (id = provider name): NULL columns keep the default, and deleting
+-- the row resets the provider. `kind = 'openai_compatible'` rows are generic
+-- OpenAI-compatible endpoints (vLLM, LM Studio, llama.cpp server) tried after
+-- the built-in providers, in `priority` order. Writes notify
+-- `ch_config_changed` so every replica reloads its provider set.
+
+CREATE TABLE IF NOT EXISTS ch_gateway_providers (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL CHECK (kind IN ('builtin', 'openai_compatible')),
+    name TEXT,
+    upstream_url TEXT,
+    extra_headers JSONB,
+    monthly_cost_cents INTEGER CHECK (monthly_cost_cents >= 0),
+    commander_model TEXT,
+    coordinator_model TEXT,
+    executor_model TEXT,
+    vault_service TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    priority INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (
+        kind = 'builtin'
+        OR (upstream_url IS NOT NULL
+            AND commander_model IS NOT NULL
+            AND coordinator_model IS NOT NULL
+            AND executor_model IS NOT NULL)
+    )
+);
+
+DROP TRIGGER IF EXISTS trg_ch_gateway_providers_notify ON ch_gateway_providers;
+CREATE TRIGGER trg_ch_gateway_providers_notify
+    AFTER INSERT OR UPDATE OR DELETE ON ch_gateway_providers
+    FOR EACH STATEMENT EXECUTE FUNCTION ch_notify_config_changed();
@@ -0,0 +1,33 @@
+-- AI gateway usage ledger and per-provider plan quota overrides.
+--
+-- One row per upstream attempt made by `/api/ai/{provider}/chat`,
+-- `/api/ai/{provider}/stream` and `/v1/chat/completions`, including failed
+-- attempts and fallbacks (`fallback_attempt > 0`). `status = 0` means no
+-- upstream response was received. The last 24 hours seed the in-memory
+-- quota windows at startup.
+
+CREATE TABLE IF NOT EXISTS ch_gateway_usage (
+    id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    endpoint TEXT NOT NULL,
+    caller TEXT,
+    status INTEGER NOT NULL,
+    input_tokens BIGINT,
+    output_tokens BIGINT,
+    latency_ms BIGINT NOT NULL,
+    fallback_attempt INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_ch_gateway_usage_created_at
+    ON ch_gateway_usage (created_at);
+CREATE INDEX IF NOT EXISTS idx_ch_gateway_usage_provider_created_at
+    ON ch_gateway_usage (provider, created_at);
+
+-- Plan quota of a built-in provider: NULL keeps the default, a window of 0
+-- disables quota tracking, and a limit of 0 leaves that dimension unlimited.
+ALTER TABLE ch_gateway_providers
+    ADD COLUMN IF NOT EXISTS quota_window_secs INTEGER CHECK (quota_window_secs >= 0),
+    ADD COLUMN IF NOT EXISTS quota_requests INTEGER CHECK (quota_requests >= 0),
+    ADD COLUMN IF NOT EXISTS quota_tokens BIGINT CHECK (quota_tokens >= 0);
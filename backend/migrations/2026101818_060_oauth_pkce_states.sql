@@ -0,0 +1,17 @@
+-- Pending OAuth PKCE states shared by all replicas (`OAUTH_STATE_STORE=postgres`).
+--
+-- `state_hash` is the SHA-256 (base64url) of the `state` parameter and
+-- `code_verifier_enc` the AES-256-GCM encrypted verifier. Rows are consumed
+-- with a single DELETE ... RETURNING in the callback, so a state can be used
+-- once; expired rows are purged by `OAuthFlowManager::cleanup_expired_states`.
+
+CREATE TABLE IF NOT EXISTS ch_oauth_pkce_states (
+    state_hash TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    code_verifier_enc TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ch_oauth_pkce_states_expires_at
+    ON ch_oauth_pkce_states (expires_at);
@@ -0,0 +1,18 @@
+-- Credential lockout after a Vault anomaly or `vault_panic` (`VaultGuard`).
+--
+-- At most one row: while it exists every outbound AI call is refused, also
+-- after a restart. Deleted by `POST /api/vault/lockout/clear`. Writes notify
+-- `ch_config_changed` so every replica picks up a lockout, or its clearing,
+-- as it happens.
+
+CREATE TABLE IF NOT EXISTS ch_vault_lockout (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    trigger TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    since TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+DROP TRIGGER IF EXISTS trg_ch_vault_lockout_notify ON ch_vault_lockout;
+CREATE TRIGGER trg_ch_vault_lockout_notify
+    AFTER INSERT OR UPDATE OR DELETE ON ch_vault_lockout
+    FOR EACH STATEMENT EXECUTE FUNCTION ch_notify_config_changed();
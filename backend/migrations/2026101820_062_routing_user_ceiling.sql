@@ -0,0 +1,13 @@
+-- Per-user auto-routing cost ceiling: usage rows and routing decisions carry
+-- the signed-in user (jaskier-auth email, NULL = anonymous), and the ceiling
+-- is checked against that user's own spend today.
+
+ALTER TABLE ch_agent_usage ADD COLUMN IF NOT EXISTS user_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_ch_agent_usage_user_day
+    ON ch_agent_usage (user_id, created_at);
+
+ALTER TABLE ch_routing_decisions ADD COLUMN IF NOT EXISTS user_id TEXT;
+
+-- Per-user overrides of routing_cost_ceiling_usd: {"<email>": <usd>}.
+ALTER TABLE ch_settings
+    ADD COLUMN IF NOT EXISTS routing_user_cost_ceilings JSONB NOT NULL DEFAULT '{}'::jsonb;
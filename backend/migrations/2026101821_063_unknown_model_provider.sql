@@ -0,0 +1,13 @@
+-- Models of an unrecognised provider map to NULL instead of 'ollama', so they
+-- match no catalogue price and show up as unpriced rather than free.
+
+CREATE OR REPLACE FUNCTION ch_model_provider(p_model TEXT) RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN p_model ILIKE 'claude%' THEN 'anthropic'
+        WHEN p_model ILIKE 'gemini%' THEN 'google'
+        WHEN p_model ILIKE 'gpt%' OR p_model ~* '^o[0-9]' THEN 'openai'
+        WHEN p_model ILIKE 'grok%' THEN 'xai'
+        WHEN p_model ILIKE 'deepseek%' THEN 'deepseek'
+        ELSE NULL
+    END
+$$ LANGUAGE sql IMMUTABLE;
@@ -2,14 +2,28 @@
 
 use serde_json::{Value, json};
 
-use super::types::GatewayChatRequest;
-use crate::ai_gateway::AiProvider;
+use super::openai_translate::{build_completion_payload, parse_completion};
+use super::types::{
+    ChatCompletionFunction, ChatCompletionFunctionCall, ChatCompletionMessage,
+    ChatCompletionRequest, ChatCompletionTool, ChatCompletionToolCall, GatewayChatRequest,
+    GatewayContent, GatewayContentBlock, GatewayImageSource,
+};
+use crate::ai_gateway::{AiProvider, ModelTier, ModelTiers};
 
 /// Resolve the upstream URL, replacing `{model}` placeholder if present.
 pub(crate) fn resolve_upstream_url(url_template: &str, model: &str) -> String {
     url_template.replace("{model}", model)
 }
 
+/// The provider's model for `tier` (used when falling back to another provider).
+pub(crate) fn tier_model(tiers: &ModelTiers, tier: ModelTier) -> String {
+    match tier {
+        ModelTier::Commander => tiers.commander.clone(),
+        ModelTier::Coordinator => tiers.coordinator.clone(),
+        ModelTier::Executor => tiers.executor.clone(),
+    }
+}
+
 /// Build a minimal test payload for verifying provider connectivity.
 pub(crate) fn build_test_payload(provider: &AiProvider, model: &str) -> Value {
     match provider {
@@ -45,111 +59,112 @@ pub(crate) fn build_test_payload(provider: &AiProvider, model: &str) -> Value {
     }
 }
 
-/// Build the full chat payload in the provider's native format.
-pub(crate) fn build_chat_payload(
-    provider: &AiProvider,
-    model: &str,
-    request: &GatewayChatRequest,
-) -> Value {
-    let temperature = request.temperature.unwrap_or(0.7);
-    let max_tokens = request.max_tokens.unwrap_or(4096);
+/// The gateway request as a Chat Completions request, so both share one
+/// translation into provider-native payloads (`openai_translate`).
+///
+/// `system` messages stay system messages (each provider puts them where it
+/// expects them), images become `image_url` parts (base64 as `data:` URLs),
+/// `tool_use` blocks become assistant `tool_calls` and `tool_result` blocks
+/// become `tool` messages ahead of the rest of their user turn.
+pub(crate) fn completion_request(request: &GatewayChatRequest) -> ChatCompletionRequest {
+    let mut messages = Vec::with_capacity(request.messages.len());
+    for m in &request.messages {
+        let blocks = match &m.content {
+            GatewayContent::Text(text) => {
+                messages.push(ChatCompletionMessage {
+                    role: m.role.clone(),
+                    content: Some(json!(text)),
+                    tool_calls: Vec::new(),
+                    tool_call_id: None,
+                });
+                continue;
+            }
+            GatewayContent::Blocks(blocks) => blocks,
+        };
 
-    match provider {
-        AiProvider::Anthropic => {
-            let messages: Vec<Value> = request
-                .messages
-                .iter()
-                .map(|m| json!({"role": m.role, "content": m.content}))
-                .collect();
-            json!({
-                "model": model,
-                "max_tokens": max_tokens,
-                "temperature": temperature,
-                "messages": messages,
-            })
-        }
-        AiProvider::OpenAI => {
-            let messages: Vec<Value> = request
-                .messages
-                .iter()
-                .map(|m| json!({"role": m.role, "content": m.content}))
-                .collect();
-            json!({
-                "model": model,
-                "max_tokens": max_tokens,
-                "temperature": temperature,
-                "messages": messages,
-            })
-        }
-        AiProvider::Google => {
-            // Gemini uses a different message format: contents[].parts[].text
-            let contents: Vec<Value> = request
-                .messages
-                .iter()
-                .map(|m| {
-                    let role = match m.role.as_str() {
-                        "assistant" => "model",
-                        other => other,
+        let mut parts = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block {
+                GatewayContentBlock::Text { text } => {
+                    parts.push(json!({ "type": "text", "text": text }));
+                }
+                GatewayContentBlock::Image { source } => {
+                    let url = match source {
+                        GatewayImageSource::Base64 { media_type, data } => {
+                            format!("data:{media_type};base64,{data}")
+                        }
+                        GatewayImageSource::Url { url } => url.clone(),
                     };
-                    json!({
-                        "role": role,
-                        "parts": [{"text": m.content}],
-                    })
-                })
-                .collect();
-            json!({
-                "contents": contents,
-                "generationConfig": {
-                    "maxOutputTokens": max_tokens,
-                    "temperature": temperature,
-                },
-            })
-        }
-        AiProvider::Xai => {
-            let messages: Vec<Value> = request
-                .messages
-                .iter()
-                .map(|m| json!({"role": m.role, "content": m.content}))
-                .collect();
-            json!({
-                "model": model,
-                "max_tokens": max_tokens,
-                "temperature": temperature,
-                "messages": messages,
-            })
+                    parts.push(json!({ "type": "image_url", "image_url": { "url": url } }));
+                }
+                GatewayContentBlock::ToolUse { id, name, input } => {
+                    tool_calls.push(ChatCompletionToolCall {
+                        id: id.clone(),
+                        kind: "function".to_string(),
+                        function: ChatCompletionFunctionCall {
+                            name: name.clone(),
+                            arguments: input.to_string(),
+                        },
+                    });
+                }
+                GatewayContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                } => messages.push(ChatCompletionMessage {
+                    role: "tool".to_string(),
+                    content: Some(json!(content)),
+                    tool_calls: Vec::new(),
+                    tool_call_id: Some(tool_use_id.clone()),
+                }),
+            }
         }
-        AiProvider::DeepSeek => {
-            let messages: Vec<Value> = request
-                .messages
-                .iter()
-                .map(|m| json!({"role": m.role, "content": m.content}))
-                .collect();
-            json!({
-                "model": model,
-                "max_tokens": max_tokens,
-                "temperature": temperature,
-                "messages": messages,
-            })
+
+        if !parts.is_empty() || !tool_calls.is_empty() {
+            messages.push(ChatCompletionMessage {
+                role: m.role.clone(),
+                content: (!parts.is_empty()).then(|| json!(parts)),
+                tool_calls,
+                tool_call_id: None,
+            });
         }
-        AiProvider::Ollama => {
-            let messages: Vec<Value> = request
-                .messages
-                .iter()
-                .map(|m| json!({"role": m.role, "content": m.content}))
-                .collect();
-            json!({
-                "model": model,
-                "messages": messages,
-                "options": {
-                    "temperature": temperature,
-                    "num_predict": max_tokens,
+    }
+
+    ChatCompletionRequest {
+        model: request.model.clone().unwrap_or_default(),
+        messages,
+        tools: request
+            .tools
+            .iter()
+            .map(|t| ChatCompletionTool {
+                kind: "function".to_string(),
+                function: ChatCompletionFunction {
+                    name: t.name.clone(),
+                    description: t.description.clone(),
+                    parameters: t.input_schema.clone(),
                 },
-                "stream": false,
             })
-        }
+            .collect(),
+        tool_choice: None,
+        temperature: Some(request.temperature.unwrap_or(0.7)),
+        top_p: None,
+        max_tokens: Some(request.max_tokens.unwrap_or(4096)),
+        max_completion_tokens: None,
+        stop: None,
+        stream: false,
+        stream_options: None,
     }
 }
 
+/// Build the full chat payload in the provider's native format.
+pub(crate) fn build_chat_payload(
+    provider: &AiProvider,
+    model: &str,
+    request: &GatewayChatRequest,
+) -> Value {
+    build_completion_payload(provider, model, &completion_request(request))
+}
+
 /// Extract a short preview from the upstream response (for test results).
 pub(crate) fn extract_response_preview(provider: &AiProvider, body: &Value) -> Option<String> {
     let text = extract_content_text(provider, body);
@@ -164,53 +179,29 @@ pub(crate) fn extract_response_preview(provider: &AiProvider, body: &Value) -> O
     }
 }
 
-/// Extract the main content text from a provider's response body.
+/// Extract the text of a provider's response body (all text blocks/parts).
 pub(crate) fn extract_content_text(provider: &AiProvider, body: &Value) -> String {
-    match provider {
-        AiProvider::Anthropic => {
-            // Anthropic: { content: [{ type: "text", text: "..." }] }
-            body.get("content")
-                .and_then(|c| c.as_array())
-                .and_then(|arr| arr.first())
-                .and_then(|block| block.get("text"))
-                .and_then(|t| t.as_str())
-                .unwrap_or("")
-                .to_string()
-        }
-        AiProvider::OpenAI | AiProvider::Xai | AiProvider::DeepSeek => {
-            // OpenAI-compatible: { choices: [{ message: { content: "..." } }] }
-            body.get("choices")
-                .and_then(|c| c.as_array())
-                .and_then(|arr| arr.first())
-                .and_then(|choice| choice.get("message"))
-                .and_then(|msg| msg.get("content"))
-                .and_then(|t| t.as_str())
-                .unwrap_or("")
-                .to_string()
-        }
-        AiProvider::Google => {
-            // Gemini: { candidates: [{ content: { parts: [{ text: "..." }] } }] }
-            body.get("candidates")
-                .and_then(|c| c.as_array())
-                .and_then(|arr| arr.first())
-                .and_then(|cand| cand.get("content"))
-                .and_then(|content| content.get("parts"))
-                .and_then(|parts| parts.as_array())
-                .and_then(|arr| arr.first())
-                .and_then(|part| part.get("text"))
-                .and_then(|t| t.as_str())
-                .unwrap_or("")
-                .to_string()
-        }
-        AiProvider::Ollama => {
-            // Ollama: { message: { content: "..." } }
-            body.get("message")
-                .and_then(|msg| msg.get("content"))
-                .and_then(|t| t.as_str())
-                .unwrap_or("")
-                .to_string()
-        }
-    }
+    parse_completion(provider, body).content
+}
+
+/// The response's content as gateway blocks: its text, then any tool calls.
+pub(crate) fn extract_content_blocks(
+    provider: &AiProvider,
+    body: &Value,
+) -> Vec<GatewayContentBlock> {
+    let completion = parse_completion(provider, body);
+    let text = (!completion.content.is_empty()).then(|| GatewayContentBlock::Text {
+        text: completion.content,
+    });
+    let tool_uses = completion
+        .tool_calls
+        .into_iter()
+        .map(|call| GatewayContentBlock::ToolUse {
+            id: call.id,
+            name: call.function.name,
+            input: serde_json::from_str(&call.function.arguments).unwrap_or_else(|_| json!({})),
+        });
+    text.into_iter().chain(tool_uses).collect()
 }
 
 /// Chunk text into segments of approximately `chunk_size` characters,
@@ -0,0 +1,563 @@
+// integrations.rs — Service integration handlers (GitHub, Vercel): list,
+// status, connect (web or device flow), callback, disconnect.
+//
+// Tokens come from `OAuthFlowManager` and are stored in Jaskier Vault under
+// `integrations/{github,vercel}_oauth` (NOT in PostgreSQL). A token that lacks
+// one of the configured scopes is rejected before it is stored.
+// `integration_token` is how the GitHub / Vercel tools read it back.
+
+use axum::extract::{Json, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::ai_gateway::{
+    HasAiGateway,
+    oauth_flows::{DevicePoll, OAuthProvider, OAuthTokens},
+    vault_bridge::{HasVaultBridge, VaultError},
+};
+
+use super::router::vault_error_response;
+use super::types::*;
+
+/// Vault namespace of integration tokens.
+pub const INTEGRATIONS_VAULT_NAMESPACE: &str = "integrations";
+
+/// Integrations connectable through these endpoints.
+pub const INTEGRATIONS: [OAuthProvider; 2] = [OAuthProvider::GitHub, OAuthProvider::Vercel];
+
+/// Vault service of an integration's token (one of `INTEGRATIONS`).
+fn vault_service(provider: OAuthProvider) -> &'static str {
+    match provider {
+        OAuthProvider::GitHub => "github_oauth",
+        _ => "vercel_oauth",
+    }
+}
+
+fn display_name(provider: OAuthProvider) -> &'static str {
+    match provider {
+        OAuthProvider::GitHub => "GitHub",
+        OAuthProvider::Vercel => "Vercel",
+        OAuthProvider::Anthropic => "Anthropic",
+        OAuthProvider::Google => "Google",
+    }
+}
+
+/// Env vars that configure an integration's OAuth client.
+fn config_env_vars(provider: OAuthProvider) -> &'static str {
+    match provider {
+        OAuthProvider::GitHub => "GITHUB_OAUTH_CLIENT_ID and GITHUB_OAUTH_CLIENT_SECRET",
+        _ => "VERCEL_OAUTH_CLIENT_ID, VERCEL_OAUTH_CLIENT_SECRET and VERCEL_INTEGRATION_SLUG",
+    }
+}
+
+/// Parse an integration from a URL path segment.
+pub(crate) fn parse_integration(name: &str) -> Result<OAuthProvider, (StatusCode, Json<Value>)> {
+    match name.to_ascii_lowercase().as_str() {
+        "github" => Ok(OAuthProvider::GitHub),
+        "vercel" => Ok(OAuthProvider::Vercel),
+        _ => Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "unknown_integration",
+                "message": format!("Unknown integration '{}'. Supported: github, vercel", name),
+            })),
+        )),
+    }
+}
+
+/// Scopes the integration's token must have (the configured ones).
+fn required_scopes<S: HasAiGateway>(state: &S, provider: OAuthProvider) -> Vec<String> {
+    state
+        .oauth_manager()
+        .provider_configs()
+        .get(&provider)
+        .map(|c| c.scopes.clone())
+        .unwrap_or_default()
+}
+
+/// The stored tokens, `None` when not connected (no credential, or the
+/// disconnect marker).
+async fn stored_tokens<S>(
+    state: &S,
+    provider: OAuthProvider,
+) -> Result<Option<(OAuthTokens, Value)>, VaultError>
+where
+    S: HasVaultBridge,
+{
+    match state
+        .vault_client()
+        .get(INTEGRATIONS_VAULT_NAMESPACE, vault_service(provider))
+        .await
+    {
+        Ok(data) if data.get("disconnected").and_then(Value::as_bool) == Some(true) => Ok(None),
+        Ok(data) => Ok(serde_json::from_value::<OAuthTokens>(data.clone())
+            .ok()
+            .map(|tokens| (tokens, data))),
+        Err(VaultError::NotFound) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Token lookup for tools
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Token of a connected integration.
+#[derive(Debug, Clone)]
+pub struct IntegrationToken {
+    pub access_token: String,
+    /// Vercel team the integration was installed for, if any.
+    pub team_id: Option<String>,
+}
+
+/// Read a connected integration's token. The error is a message for the
+/// model / user: not connected, missing scopes, or Vault unavailable.
+pub async fn integration_token<S>(
+    state: &S,
+    provider: OAuthProvider,
+) -> Result<IntegrationToken, String>
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let name = display_name(provider);
+    match stored_tokens(state, provider).await {
+        Ok(Some((tokens, data))) => {
+            let missing = tokens.missing_scopes(&required_scopes(state, provider));
+            if !missing.is_empty() {
+                return Err(format!(
+                    "{name} is connected without the required scope(s) {}. Reconnect it via POST /api/integrations/{provider}/connect.",
+                    missing.join(", "),
+                ));
+            }
+            Ok(IntegrationToken {
+                access_token: tokens.access_token,
+                team_id: data
+                    .get("team_id")
+                    .and_then(Value::as_str)
+                    .map(String::from),
+            })
+        }
+        Ok(None) => Err(format!(
+            "{name} is not connected. Connect it via POST /api/integrations/{provider}/connect{}.",
+            if provider == OAuthProvider::GitHub {
+                " (or /device for the device flow)"
+            } else {
+                ""
+            },
+        )),
+        Err(err) => Err(format!(
+            "Cannot read the {name} token from Jaskier Vault: {err}"
+        )),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  GET /api/integrations, GET /api/integrations/{integration}/status
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Connection state of one integration.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrationStatus {
+    pub integration: String,
+    /// An OAuth client is configured (env vars set).
+    pub configured: bool,
+    pub device_flow: bool,
+    pub connected: bool,
+    pub scope: Option<String>,
+    /// Configured scopes the stored token does not have.
+    pub missing_scopes: Vec<String>,
+    pub connected_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+async fn status_of<S>(state: &S, provider: OAuthProvider) -> IntegrationStatus
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let mut status = IntegrationStatus {
+        integration: provider.to_string(),
+        configured: state.oauth_manager().has_provider(provider),
+        device_flow: provider == OAuthProvider::GitHub,
+        connected: false,
+        scope: None,
+        missing_scopes: Vec::new(),
+        connected_at: None,
+        last_error: None,
+    };
+    match stored_tokens(state, provider).await {
+        Ok(Some((tokens, data))) => {
+            status.connected = true;
+            status.missing_scopes = tokens.missing_scopes(&required_scopes(state, provider));
+            status.scope = tokens.scope;
+            status.connected_at = data
+                .get("connected_at")
+                .and_then(Value::as_str)
+                .map(String::from);
+        }
+        Ok(None) => {}
+        Err(err) => status.last_error = Some(format!("vault: {}", err)),
+    }
+    status
+}
+
+/// Lists the service integrations with their connection state.
+pub(crate) async fn list_integrations<S>(State(state): State<S>) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    let mut integrations = Vec::with_capacity(INTEGRATIONS.len());
+    for provider in INTEGRATIONS {
+        integrations.push(status_of(&state, provider).await);
+    }
+    Json(json!({ "integrations": integrations }))
+}
+
+/// Returns the connection state of a single integration.
+pub(crate) async fn integration_status<S>(
+    State(state): State<S>,
+    Path(integration): Path<String>,
+) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    match parse_integration(&integration) {
+        Ok(provider) => Json(status_of(&state, provider).await).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  POST /api/integrations/{integration}/connect — web flow
+// ═══════════════════════════════════════════════════════════════════════════
+
+fn not_configured(provider: OAuthProvider) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": "integration_not_configured",
+            "message": format!(
+                "{} OAuth is not configured. Set {}.",
+                display_name(provider),
+                config_env_vars(provider),
+            ),
+        })),
+    )
+}
+
+/// Starts the web (authorization code + PKCE) flow; returns the URL to open.
+pub(crate) async fn connect_integration<S>(
+    State(state): State<S>,
+    Path(integration): Path<String>,
+) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    let provider = match parse_integration(&integration) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+    if !state.oauth_manager().has_provider(provider) {
+        return not_configured(provider).into_response();
+    }
+
+    match state.oauth_manager().initiate_login(provider).await {
+        Ok(login) => Json(json!({
+            "integration": provider.to_string(),
+            "status": "login_initiated",
+            "authorize_url": login.authorize_url,
+            "state": login.state,
+            "next_step": format!("POST /api/integrations/{}/callback with the authorization code", provider),
+        }))
+        .into_response(),
+        Err(e) => {
+            tracing::error!(integration = %provider, error = %e, "integration OAuth initiation failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "oauth_initiation_failed",
+                    "message": e.to_string(),
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  GET|POST /api/integrations/{integration}/callback
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Browser redirect from the provider (`?code=&state=`).
+pub(crate) async fn integration_redirect<S>(
+    State(state): State<S>,
+    Path(integration): Path<String>,
+    Query(params): Query<CallbackPayload>,
+) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    complete_callback(&state, &integration, params).await
+}
+
+/// Authorization code relayed by the frontend.
+pub(crate) async fn integration_callback<S>(
+    State(state): State<S>,
+    Path(integration): Path<String>,
+    Json(body): Json<CallbackPayload>,
+) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    complete_callback(&state, &integration, body).await
+}
+
+async fn complete_callback<S>(
+    state: &S,
+    integration: &str,
+    body: CallbackPayload,
+) -> axum::response::Response
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let provider = match parse_integration(integration) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+
+    let tokens = match state
+        .oauth_manager()
+        .handle_callback(&body.state, &body.code)
+        .await
+    {
+        Ok((flow_provider, tokens)) if flow_provider == provider => tokens,
+        Ok((flow_provider, _)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "state_mismatch",
+                    "message": format!("OAuth state belongs to {}, not {}", flow_provider, provider),
+                })),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            tracing::error!(integration = %provider, error = %e, "integration OAuth code exchange failed");
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "oauth_exchange_failed",
+                    "message": e.to_string(),
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    store_tokens(state, provider, tokens, "web").await
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  POST /api/integrations/{integration}/device[/poll] — device flow
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Starts a device authorization: show `user_code` and `verification_uri`,
+/// then poll `/device/poll` with `device_code` every `interval` seconds.
+pub(crate) async fn start_device_login<S>(
+    State(state): State<S>,
+    Path(integration): Path<String>,
+) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    let provider = match parse_integration(&integration) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+    if !state.oauth_manager().has_provider(provider) {
+        return not_configured(provider).into_response();
+    }
+
+    match state.oauth_manager().start_device_login(provider).await {
+        Ok(device) => Json(json!({
+            "integration": provider.to_string(),
+            "status": "device_login_initiated",
+            "device": device,
+            "next_step": format!("POST /api/integrations/{}/device/poll with device_code", provider),
+        }))
+        .into_response(),
+        Err(e) => {
+            tracing::error!(integration = %provider, error = %e, "device login initiation failed");
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "device_login_failed",
+                    "message": e.to_string(),
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Polls a device authorization once; stores the token when it completes.
+pub(crate) async fn poll_device_login<S>(
+    State(state): State<S>,
+    Path(integration): Path<String>,
+    Json(body): Json<DevicePollPayload>,
+) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    let provider = match parse_integration(&integration) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+
+    match state
+        .oauth_manager()
+        .poll_device_login(provider, &body.device_code)
+        .await
+    {
+        Ok(DevicePoll::Pending) => (
+            StatusCode::ACCEPTED,
+            Json(json!({ "integration": provider.to_string(), "status": "pending" })),
+        )
+            .into_response(),
+        Ok(DevicePoll::SlowDown { interval }) => (
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "integration": provider.to_string(),
+                "status": "slow_down",
+                "interval": interval,
+            })),
+        )
+            .into_response(),
+        Ok(DevicePoll::Complete(tokens)) => store_tokens(&state, provider, tokens, "device").await,
+        Err(e) => {
+            tracing::warn!(integration = %provider, error = %e, "device login failed");
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "device_login_failed",
+                    "message": e.to_string(),
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Validate the granted scopes and store the tokens in Vault.
+async fn store_tokens<S>(
+    state: &S,
+    provider: OAuthProvider,
+    tokens: OAuthTokens,
+    flow: &str,
+) -> axum::response::Response
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let missing = tokens.missing_scopes(&required_scopes(state, provider));
+    if !missing.is_empty() {
+        tracing::warn!(integration = %provider, ?missing, "integration token lacks required scopes — not stored");
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "insufficient_scope",
+                "message": format!("{} granted {} — missing {}", display_name(provider), tokens.scope.as_deref().unwrap_or("no scopes"), missing.join(", ")),
+                "missing_scopes": missing,
+            })),
+        )
+            .into_response();
+    }
+
+    let service = vault_service(provider);
+    let now = chrono::Utc::now();
+    let credential_data = json!({
+        "access_token": tokens.access_token,
+        "refresh_token": tokens.refresh_token,
+        "expires_in": tokens.expires_in,
+        "expires_at": tokens.expires_in.map(|secs| now.timestamp() + secs),
+        "token_type": tokens.token_type,
+        "scope": tokens.scope,
+        "team_id": tokens.extra.get("team_id"),
+        "flow": flow,
+        "connected_at": now.to_rfc3339(),
+    });
+
+    let vault = state.vault_client();
+    match vault
+        .set(INTEGRATIONS_VAULT_NAMESPACE, service, credential_data)
+        .await
+    {
+        Ok(()) => {
+            vault
+                .invalidate_cache(INTEGRATIONS_VAULT_NAMESPACE, service)
+                .await;
+            tracing::info!(integration = %provider, flow, "integration tokens stored in Vault");
+            Json(json!({
+                "integration": provider.to_string(),
+                "status": "connected",
+                "scope": tokens.scope,
+                "message": format!("{} tokens stored in Jaskier Vault.", display_name(provider)),
+            }))
+            .into_response()
+        }
+        Err(err) => {
+            tracing::error!(integration = %provider, error = %err, "failed to store integration tokens in Vault");
+            vault_error_response(&provider, err).into_response()
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  POST /api/integrations/{integration}/disconnect
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Disconnects an integration by overwriting its Vault credential with the
+/// disconnect marker.
+pub(crate) async fn disconnect_integration<S>(
+    State(state): State<S>,
+    Path(integration): Path<String>,
+) -> impl IntoResponse
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    let provider = match parse_integration(&integration) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+    let service = vault_service(provider);
+
+    let vault = state.vault_client();
+    match vault
+        .set(
+            INTEGRATIONS_VAULT_NAMESPACE,
+            service,
+            json!({
+                "disconnected": true,
+                "disconnected_at": chrono::Utc::now().to_rfc3339(),
+            }),
+        )
+        .await
+    {
+        Ok(()) => {
+            vault
+                .invalidate_cache(INTEGRATIONS_VAULT_NAMESPACE, service)
+                .await;
+            tracing::info!(integration = %provider, "integration disconnected — credentials overwritten in Vault");
+            Json(json!({
+                "integration": provider.to_string(),
+                "status": "disconnected",
+                "message": format!("Credentials for {} removed from Jaskier Vault.", display_name(provider)),
+            }))
+            .into_response()
+        }
+        Err(err) => {
+            tracing::error!(integration = %provider, error = %err, "failed to disconnect integration");
+            vault_error_response(&provider, err).into_response()
+        }
+    }
+}
@@ -4,14 +4,22 @@
 // - `types` — Request / Response types
 // - `router` — Sub-router builder + shared error helpers
 // - `providers` — Provider management handlers (list, status, connect, etc.)
+// - `integrations` — GitHub / Vercel integrations (web + device flow, tokens in Vault)
 // - `proxy` — Chat proxy handlers (non-streaming + SSE streaming)
 // - `helpers` — Private utility functions (payload builders, content extractors)
+// - `openai` — OpenAI-compatible facade (`/v1/models`, `/v1/chat/completions`)
+// - `openai_translate` — Chat Completions <-> provider-native translation
+// - `upstream` — Sending payloads to built-in and OpenAI-compatible providers
 
 pub(crate) mod helpers;
+pub mod integrations;
+pub mod openai;
+pub(crate) mod openai_translate;
 pub mod providers;
 pub mod proxy;
 pub mod router;
 mod types;
+pub(crate) mod upstream;
 
 // ── Public re-exports ────────────────────────────────────────────────────
 pub use router::ai_gateway_router;
@@ -85,11 +93,12 @@ mod tests {
             model: Some("gpt-4o".to_string()),
             messages: vec![GatewayChatMessage {
                 role: "user".to_string(),
-                content: "Hello".to_string(),
+                content: "Hello".into(),
             }],
             temperature: Some(0.5),
             max_tokens: Some(1024),
             stream: None,
+            tools: Vec::new(),
         };
         let payload = build_chat_payload(&AiProvider::OpenAI, "gpt-4o", &request);
         assert_eq!(payload["model"], "gpt-4o");
@@ -105,16 +114,17 @@ mod tests {
             messages: vec![
                 GatewayChatMessage {
                     role: "user".to_string(),
-                    content: "Hi".to_string(),
+                    content: "Hi".into(),
                 },
                 GatewayChatMessage {
                     role: "assistant".to_string(),
-                    content: "Hello!".to_string(),
+                    content: "Hello!".into(),
                 },
             ],
             temperature: None,
             max_tokens: None,
             stream: None,
+            tools: Vec::new(),
         };
         let payload = build_chat_payload(&AiProvider::Google, "gemini-2.5-pro", &request);
         // Google maps "assistant" -> "model"
@@ -122,6 +132,94 @@ mod tests {
         assert_eq!(payload["contents"][0]["role"], "user");
     }
 
+    #[test]
+    fn build_chat_payload_rich_content() {
+        let request: GatewayChatRequest = serde_json::from_value(json!({
+            "messages": [
+                { "role": "system", "content": "Be terse." },
+                { "role": "user", "content": [
+                    { "type": "text", "text": "What is this?" },
+                    { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "iVBOR" } },
+                ]},
+                { "role": "assistant", "content": [
+                    { "type": "tool_use", "id": "toolu_1", "name": "lookup", "input": { "q": "png" } },
+                ]},
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_1", "content": "a logo" },
+                ]},
+            ],
+            "tools": [{ "name": "lookup", "input_schema": { "type": "object" } }],
+        }))
+        .unwrap();
+
+        let anthropic = build_chat_payload(&AiProvider::Anthropic, "claude-sonnet-4-6", &request);
+        assert_eq!(anthropic["system"], "Be terse.");
+        let messages = anthropic["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["content"][1]["source"]["media_type"],
+            "image/png"
+        );
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"]["q"], "png");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "toolu_1");
+        assert_eq!(anthropic["tools"][0]["name"], "lookup");
+
+        let gemini = build_chat_payload(&AiProvider::Google, "gemini-2.5-pro", &request);
+        assert_eq!(gemini["systemInstruction"]["parts"][0]["text"], "Be terse.");
+        assert_eq!(
+            gemini["contents"][0]["parts"][1]["inlineData"]["data"],
+            "iVBOR"
+        );
+        assert_eq!(
+            gemini["contents"][2]["parts"][0]["functionResponse"]["name"],
+            "lookup"
+        );
+        assert!(
+            gemini["contents"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|c| c["role"] != "system")
+        );
+
+        let openai = build_chat_payload(&AiProvider::OpenAI, "gpt-4o", &request);
+        assert_eq!(openai["messages"][0]["role"], "system");
+        assert_eq!(
+            openai["messages"][1]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,iVBOR"
+        );
+        assert_eq!(openai["messages"][3]["role"], "tool");
+    }
+
+    #[test]
+    fn extract_content_blocks_with_tool_use() {
+        let body = json!({
+            "content": [
+                { "type": "text", "text": "Looking " },
+                { "type": "text", "text": "it up." },
+                { "type": "tool_use", "id": "toolu_2", "name": "lookup", "input": { "q": "x" } },
+            ],
+        });
+        assert_eq!(
+            extract_content_text(&AiProvider::Anthropic, &body),
+            "Looking it up."
+        );
+        assert_eq!(
+            extract_content_blocks(&AiProvider::Anthropic, &body),
+            vec![
+                GatewayContentBlock::Text {
+                    text: "Looking it up.".to_string()
+                },
+                GatewayContentBlock::ToolUse {
+                    id: "toolu_2".to_string(),
+                    name: "lookup".to_string(),
+                    input: json!({ "q": "x" }),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn extract_content_anthropic() {
         let body = json!({
@@ -202,4 +300,188 @@ mod tests {
         let reassembled: String = chunks.into_iter().collect();
         assert_eq!(reassembled, text);
     }
+
+    // ── OpenAI-compatible facade ────────────────────────────────────────────
+
+    fn completion_request(body: serde_json::Value) -> ChatCompletionRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn openai_request_to_anthropic_tools_and_results() {
+        let request = completion_request(json!({
+            "model": "claude-sonnet-4-6",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Weather in Oslo and Rome?"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "weather", "arguments": "{\"city\":\"Oslo\"}"}},
+                    {"id": "call_2", "type": "function",
+                     "function": {"name": "weather", "arguments": "{\"city\":\"Rome\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "4C"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "19C"}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "weather", "parameters": {"type": "object"}}}],
+            "tool_choice": "required",
+            "max_completion_tokens": 512
+        }));
+        let payload = super::openai_translate::build_completion_payload(
+            &AiProvider::Anthropic,
+            "claude-sonnet-4-6",
+            &request,
+        );
+        assert_eq!(payload["system"], "Be brief.");
+        assert_eq!(payload["max_tokens"], 512);
+        assert_eq!(payload["tool_choice"], json!({"type": "any"}));
+        assert_eq!(
+            payload["tools"][0]["input_schema"],
+            json!({"type": "object"})
+        );
+
+        let messages = payload["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["content"][1]["input"], json!({"city": "Rome"}));
+        // Both tool results share one user turn.
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][1]["tool_use_id"], "call_2");
+    }
+
+    #[test]
+    fn openai_request_to_gemini_resolves_function_names() {
+        let request = completion_request(json!({
+            "model": "gemini-2.5-pro",
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBOR"}}
+                ]},
+                {"role": "assistant", "tool_calls": [{"id": "call_9", "type": "function",
+                    "function": {"name": "lookup", "arguments": "{}"}}]},
+                {"role": "tool", "tool_call_id": "call_9", "content": "a cat"}
+            ]
+        }));
+        let payload = super::openai_translate::build_completion_payload(
+            &AiProvider::Google,
+            "gemini-2.5-pro",
+            &request,
+        );
+        let contents = payload["contents"].as_array().unwrap();
+        assert_eq!(
+            contents[0]["parts"][1]["inlineData"]["mimeType"],
+            "image/png"
+        );
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(
+            contents[2]["parts"][0]["functionResponse"]["name"],
+            "lookup"
+        );
+    }
+
+    #[test]
+    fn anthropic_tool_use_response_to_openai_completion() {
+        use super::openai_translate::{completion_chunks, completion_response, parse_completion};
+
+        let body = json!({
+            "content": [
+                {"type": "text", "text": "Checking."},
+                {"type": "tool_use", "id": "toolu_1", "name": "weather", "input": {"city": "Oslo"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 20, "output_tokens": 7}
+        });
+        let completion = parse_completion(&AiProvider::Anthropic, &body);
+        assert_eq!(completion.finish_reason, "tool_calls");
+
+        let response = completion_response("chatcmpl-1", 0, "claude-sonnet-4-6", &completion);
+        let message = &response["choices"][0]["message"];
+        assert_eq!(message["content"], "Checking.");
+        assert_eq!(message["tool_calls"][0]["id"], "toolu_1");
+        assert_eq!(
+            message["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Oslo\"}"
+        );
+        assert_eq!(response["usage"]["total_tokens"], 27);
+
+        let chunks = completion_chunks("chatcmpl-1", 0, "claude-sonnet-4-6", &completion, true);
+        let last = chunks.last().unwrap();
+        assert_eq!(last["usage"]["prompt_tokens"], 20);
+        assert_eq!(
+            chunks[chunks.len() - 2]["choices"][0]["finish_reason"],
+            "tool_calls"
+        );
+    }
+
+    #[test]
+    fn only_server_errors_and_rate_limits_fall_back() {
+        use super::openai::{falls_back, upstream_error};
+
+        assert!(falls_back(500));
+        assert!(falls_back(503));
+        assert!(falls_back(429));
+        assert!(!falls_back(400));
+        assert!(!falls_back(401));
+        assert!(!falls_back(404));
+
+        // OpenAI-shaped bodies pass through with the upstream status.
+        let body =
+            json!({"error": {"message": "bad tool schema", "type": "invalid_request_error"}});
+        let response = upstream_error("openai", 400, &body);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Ollama-style errors keep their status too.
+        let response = upstream_error("ollama", 404, &json!({"error": "model not found"}));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn openai_stream_lines_pass_through_and_report_usage() {
+        use super::openai_translate::ChunkTranslator;
+
+        let mut translator =
+            ChunkTranslator::new("chatcmpl-1".into(), 0, "gpt-4o", AiProvider::OpenAI, true);
+        assert!(translator.line(": keep-alive").is_empty());
+        assert!(translator.line("").is_empty());
+
+        let chunks = translator.line(
+            r#"data: {"id":"up-1","object":"chat.completion.chunk","choices":[{"index":0,"delta":{"content":"Hi"}}]}"#,
+        );
+        assert_eq!(chunks[0]["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(chunks[0]["id"], "up-1");
+
+        translator
+            .line(r#"data: {"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":2}}"#);
+        assert_eq!(translator.usage.map(|u| u.prompt_tokens), Some(9));
+        assert!(translator.line("data: [DONE]").is_empty());
+    }
+
+    #[test]
+    fn ollama_stream_lines_become_chunks() {
+        use super::openai_translate::ChunkTranslator;
+
+        let mut translator =
+            ChunkTranslator::new("chatcmpl-1".into(), 0, "llama3.2", AiProvider::Ollama, true);
+        let first =
+            translator.line(r#"{"message":{"role":"assistant","content":"Hel"},"done":false}"#);
+        assert_eq!(first[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(first[1]["choices"][0]["delta"]["content"], "Hel");
+
+        let call = translator.line(
+            r#"{"message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"weather","arguments":{"city":"Oslo"}}}]},"done":false}"#,
+        );
+        assert_eq!(call.len(), 1);
+        assert_eq!(call[0]["choices"][0]["delta"]["tool_calls"][0]["index"], 0);
+
+        let last = translator.line(
+            r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":12,"eval_count":4}"#,
+        );
+        assert_eq!(last[0]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(last[1]["usage"]["total_tokens"], 16);
+        assert_eq!(translator.usage.map(|u| u.completion_tokens), Some(4));
+
+        let error = translator.line(r#"{"error":"model not loaded"}"#);
+        assert_eq!(error[0]["error"]["message"], "model not loaded");
+    }
 }
@@ -0,0 +1,590 @@
+// openai.rs — OpenAI-compatible facade (`/v1/models`, `/v1/chat/completions`).
+//
+// Lets tooling that speaks the Chat Completions protocol (IDE plugins,
+// scripts, LangChain) use the gateway. Requests are routed with
+// `ModelRouter`, sent through the Vault Bouncer (or directly for
+// `AuthType::None`) and walk the same fallback chain as `proxy_chat`:
+// built-in providers first, then the OpenAI-compatible endpoints.
+// Only server errors, rate limits and connection failures fall back; other
+// upstream errors are returned to the caller with the upstream's body.
+// Errors use the OpenAI `{"error": {message, type, code}}` shape.
+//
+// Callers authenticate with jaskier-auth (route layer in `app_routes`);
+// usage is attributed to the signed-in user.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::str::FromStr;
+use std::time::Instant;
+
+use axum::extract::{FromRequestParts, Json, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+
+use crate::ai_gateway::model_router::{ModelRouter, ModelTier};
+use crate::ai_gateway::usage::UsageRecord;
+use crate::ai_gateway::vault_bridge::{HasVaultBridge, VaultClient};
+use crate::ai_gateway::{
+    AiGatewayState, AiProvider, AuthType, CompatibleProvider, GatewayProviders, HasAiGateway,
+    ProviderConfig,
+};
+use crate::extractor::OptionalAuth;
+
+use super::helpers::{resolve_upstream_url, tier_model};
+use super::openai_translate::{
+    ChunkTranslator, Completion, build_completion_payload, completion_chunks, completion_response,
+    parse_completion,
+};
+use super::router::vault_error_response;
+use super::types::ChatCompletionRequest;
+use super::upstream::{
+    UpstreamFailure, open_direct_stream, read_body, send_compatible, send_upstream,
+};
+
+// ── Errors ──────────────────────────────────────────────────────────────────
+
+/// An error in the OpenAI response shape.
+pub(crate) fn openai_error(status: StatusCode, kind: &str, code: &str, message: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "error": { "message": message, "type": kind, "param": null, "code": code },
+        })),
+    )
+        .into_response()
+}
+
+/// The OpenAI error `type` for an HTTP status.
+fn error_kind(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        401 => "authentication_error",
+        403 => "permission_error",
+        429 => "rate_limit_error",
+        400..=499 => "invalid_request_error",
+        _ => "api_error",
+    }
+}
+
+/// Whether an upstream status moves on to the next link of the fallback
+/// chain. Other errors are about the request itself, so they are returned.
+pub(super) fn falls_back(status: u16) -> bool {
+    status == 429 || status >= 500
+}
+
+/// An upstream error response relayed with the upstream's status. Bodies
+/// already in an `{"error": {..}}` shape pass through unchanged; anything
+/// else is wrapped with its message.
+pub(super) fn upstream_error(provider: &str, status: u16, body: &Value) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+    if body["error"].is_object() {
+        return (status, Json(body.clone())).into_response();
+    }
+    let message = body["error"]
+        .as_str()
+        .or_else(|| body["message"].as_str())
+        .or_else(|| body.as_str().filter(|text| !text.trim().is_empty()))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{} returned HTTP {}", provider, status.as_u16()));
+    openai_error(status, error_kind(status), "upstream_error", &message)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  GET /v1/models
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Tier models of every configured provider and OpenAI-compatible endpoint.
+pub(crate) async fn list_models<S>(State(state): State<S>) -> Json<Value>
+where
+    S: HasAiGateway + Clone + Send + Sync + 'static,
+{
+    let created = chrono::Utc::now().timestamp();
+    let providers = state.ai_gateway().providers();
+    let mut builtin: Vec<&ProviderConfig> = providers.builtin.values().collect();
+    builtin.sort_by_key(|c| c.provider.to_string());
+    let owners = builtin
+        .into_iter()
+        .map(|c| (c.provider.to_string(), &c.model_tiers))
+        .chain(
+            providers
+                .compatible
+                .iter()
+                .map(|p| (p.id.clone(), &p.model_tiers)),
+        );
+
+    let mut data = Vec::new();
+    for (owner, tiers) in owners {
+        let mut models = vec![&tiers.commander, &tiers.coordinator, &tiers.executor];
+        models.dedup();
+        data.extend(models.into_iter().map(|model| {
+            json!({
+                "id": model,
+                "object": "model",
+                "created": created,
+                "owned_by": owner,
+            })
+        }));
+    }
+
+    Json(json!({ "object": "list", "data": data }))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  POST /v1/chat/completions
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Where a requested model ID is served.
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// A built-in provider: first link of its fallback chain.
+    Builtin {
+        provider: AiProvider,
+        tier: ModelTier,
+        model: String,
+    },
+    /// A generic OpenAI-compatible endpoint, without fallback.
+    Compatible { id: String, model: String },
+}
+
+/// Resolve a requested model ID. A `provider/model` prefix pins a built-in
+/// provider or an OpenAI-compatible endpoint; a tier model of an endpoint
+/// routes there; anything else is detected by `ModelRouter`.
+fn resolve_target(providers: &GatewayProviders, model: &str) -> Result<Target, String> {
+    if let Some((prefix, name)) = model.split_once('/') {
+        if let Ok(provider) = AiProvider::from_str(prefix) {
+            return Ok(Target::Builtin {
+                provider,
+                tier: ModelRouter::detect_tier(name),
+                model: name.to_string(),
+            });
+        }
+        if providers.compatible(prefix).is_some() {
+            return Ok(Target::Compatible {
+                id: prefix.to_string(),
+                model: name.to_string(),
+            });
+        }
+    }
+    if let Some(endpoint) = providers.compatible.iter().find(|p| p.serves(model)) {
+        return Ok(Target::Compatible {
+            id: endpoint.id.clone(),
+            model: model.to_string(),
+        });
+    }
+    ModelRouter::new()
+        .resolve_model(model)
+        .map(|route| Target::Builtin {
+            provider: route.provider,
+            tier: route.tier,
+            model: route.upstream_model,
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// One link of the fallback chain.
+enum Link<'a> {
+    Builtin(AiProvider, &'a ProviderConfig),
+    Compatible(&'a CompatibleProvider),
+}
+
+impl Link<'_> {
+    fn name(&self) -> String {
+        match self {
+            Self::Builtin(provider, _) => provider.to_string(),
+            Self::Compatible(endpoint) => endpoint.id.clone(),
+        }
+    }
+
+    /// Wire format of the payload and response; generic endpoints speak OpenAI's.
+    fn format(&self) -> AiProvider {
+        match self {
+            Self::Builtin(provider, _) => *provider,
+            Self::Compatible(_) => AiProvider::OpenAI,
+        }
+    }
+
+    /// URL and headers of a link called directly (not through the Vault
+    /// Bouncer) in a format `ChunkTranslator` reads — the only links whose
+    /// streams can be proxied as they arrive.
+    fn direct(&self, model: &str) -> Option<(String, HashMap<String, String>)> {
+        if !ChunkTranslator::supports(&self.format()) {
+            return None;
+        }
+        match self {
+            Self::Builtin(_, config) if config.auth_type == AuthType::None => Some((
+                resolve_upstream_url(&config.upstream_url, model),
+                HashMap::new(),
+            )),
+            Self::Compatible(endpoint) if endpoint.vault_service.is_none() => Some((
+                endpoint.upstream_url.clone(),
+                endpoint.extra_headers.clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Whether direct calls may reach private addresses: keyless built-ins
+    /// are local by design, compatible endpoints only outside production.
+    fn allows_private(&self, gateway: &AiGatewayState) -> bool {
+        matches!(self, Self::Builtin(..)) || gateway.allow_private_upstreams
+    }
+
+    async fn send(
+        &self,
+        gateway: &AiGatewayState,
+        vault: &VaultClient,
+        model: &str,
+        body: &ChatCompletionRequest,
+    ) -> Result<(u16, Value), UpstreamFailure> {
+        let payload = build_completion_payload(&self.format(), model, body);
+        match self {
+            Self::Builtin(_, config) => {
+                let url = resolve_upstream_url(&config.upstream_url, model);
+                send_upstream(vault, config, &url, payload).await
+            }
+            Self::Compatible(endpoint) => {
+                send_compatible(vault, endpoint, payload, self.allows_private(gateway)).await
+            }
+        }
+    }
+}
+
+/// Links to try for `target`, each with the model to request. A built-in
+/// provider falls back along the gateway's ranked chain (same tier), then to
+/// the OpenAI-compatible endpoints.
+fn fallback_links<'a>(
+    providers: &'a GatewayProviders,
+    gateway: &AiGatewayState,
+    target: &Target,
+) -> Vec<(Link<'a>, String)> {
+    match target {
+        Target::Compatible { id, model } => providers
+            .compatible(id)
+            .map(|endpoint| (Link::Compatible(endpoint), model.clone()))
+            .into_iter()
+            .collect(),
+        Target::Builtin {
+            provider,
+            tier,
+            model,
+        } => {
+            let chain = gateway.fallback_chain(*provider, providers);
+            let builtin = chain.into_iter().filter_map(|p| {
+                let config = providers.get(&p)?;
+                let model = if p == *provider {
+                    model.clone()
+                } else {
+                    tier_model(&config.model_tiers, *tier)
+                };
+                Some((Link::Builtin(p, config), model))
+            });
+            let compatible = providers.compatible.iter().map(|endpoint| {
+                (
+                    Link::Compatible(endpoint),
+                    tier_model(&endpoint.model_tiers, *tier),
+                )
+            });
+            builtin.chain(compatible).collect()
+        }
+    }
+}
+
+/// OpenAI-compatible chat completions, streaming or not.
+///
+/// With `stream: true`, links called directly (`Link::direct`) are streamed
+/// and their chunks proxied as they arrive. Links behind the Vault Bouncer
+/// return whole bodies, so their finished completion is replayed as
+/// `chat.completion.chunk` SSE events terminated by `data: [DONE]`. Either
+/// way the fallback decision is made on the upstream status, before the
+/// first byte reaches the caller.
+pub(crate) async fn chat_completions<S>(
+    State(state): State<S>,
+    OptionalAuth { email }: OptionalAuth,
+    Json(body): Json<ChatCompletionRequest>,
+) -> Response
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+    OptionalAuth: FromRequestParts<S>,
+{
+    if body.messages.is_empty() {
+        return openai_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            "invalid_messages",
+            "messages must not be empty",
+        );
+    }
+
+    if let Err(lockout) = state.ai_gateway().vault_guard.check() {
+        return openai_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "api_error",
+            "vault_lockout",
+            &lockout.to_string(),
+        );
+    }
+
+    let providers = state.ai_gateway().providers();
+    let target = match resolve_target(&providers, &body.model) {
+        Ok(target) => target,
+        Err(e) => {
+            return openai_error(
+                StatusCode::NOT_FOUND,
+                "invalid_request_error",
+                "model_not_found",
+                &format!("The model `{}` does not exist: {}", body.model, e),
+            );
+        }
+    };
+
+    let vault = state.vault_client();
+    let gateway = state.ai_gateway();
+    let caller = email.map(|email| format!("user:{}", email));
+    let mut last_error = None;
+
+    let links = fallback_links(&providers, gateway, &target);
+    for (attempt, (link, model)) in links.into_iter().enumerate() {
+        let name = link.name();
+        let started = Instant::now();
+
+        let direct = if body.stream {
+            link.direct(&model)
+        } else {
+            None
+        };
+        let outcome = match direct {
+            Some((url, headers)) => {
+                let mut payload = build_completion_payload(&link.format(), &model, &body);
+                payload["stream"] = json!(true);
+                if link.format() != AiProvider::Ollama && include_usage(&body) {
+                    payload["stream_options"] = json!({ "include_usage": true });
+                }
+                let allow_private = link.allows_private(gateway);
+                match open_direct_stream(&url, &headers, payload, allow_private).await {
+                    Ok(resp) if resp.status().is_success() => {
+                        tracing::info!(
+                            caller = caller.as_deref().unwrap_or("anonymous"),
+                            provider = %name,
+                            model = %model,
+                            attempt = attempt + 1,
+                            latency_ms = started.elapsed().as_millis() as u64,
+                            "v1 chat_completions: streaming upstream",
+                        );
+                        let translator = ChunkTranslator::new(
+                            completion_id(),
+                            chrono::Utc::now().timestamp(),
+                            &model,
+                            link.format(),
+                            include_usage(&body),
+                        );
+                        let record = UsageRecord::new("v1", name.clone(), &model, attempt, started)
+                            .caller(caller.clone());
+                        return stream_reply(state.clone(), resp, translator, record);
+                    }
+                    Ok(resp) => {
+                        let status = resp.status().as_u16();
+                        Ok((status, read_body(resp).await))
+                    }
+                    Err(failure) => Err(failure),
+                }
+            }
+            None => link.send(gateway, vault, &model, &body).await,
+        };
+
+        let record =
+            UsageRecord::new("v1", name.clone(), &model, attempt, started).caller(caller.clone());
+        match &outcome {
+            Ok((status, upstream)) => {
+                gateway.record(record.response(*status, &link.format(), upstream))
+            }
+            Err(failure) => gateway.record(record.error(failure)),
+        }
+
+        match outcome {
+            Ok((status, upstream)) if (200..300).contains(&status) => {
+                let completion = parse_completion(&link.format(), &upstream);
+                tracing::info!(
+                    caller = caller.as_deref().unwrap_or("anonymous"),
+                    provider = %name,
+                    model = %model,
+                    attempt = attempt + 1,
+                    latency_ms = started.elapsed().as_millis() as u64,
+                    "v1 chat_completions: upstream success",
+                );
+                return completion_reply(&body, &model, &completion);
+            }
+            Ok((status, upstream)) => {
+                let response = upstream_error(&name, status, &upstream);
+                if !falls_back(status) {
+                    tracing::warn!(
+                        provider = %name,
+                        model = %model,
+                        status,
+                        "v1 chat_completions: upstream rejected the request",
+                    );
+                    return response;
+                }
+                tracing::warn!(
+                    provider = %name,
+                    model = %model,
+                    status,
+                    "v1 chat_completions: upstream error, trying fallback",
+                );
+                last_error = Some(response);
+            }
+            Err(failure) => {
+                tracing::error!(
+                    provider = %name,
+                    error = %failure,
+                    "v1 chat_completions: upstream call failed",
+                );
+                if let UpstreamFailure::Vault(err) = &failure {
+                    gateway.vault_guard.observe(err).await;
+                }
+                let anomaly = failure.is_anomaly();
+                let response = match failure {
+                    UpstreamFailure::Vault(err) => {
+                        let (status, Json(detail)) = vault_error_response(&name, err);
+                        openai_error(
+                            status,
+                            "api_error",
+                            detail["error"].as_str().unwrap_or("vault_error"),
+                            detail["message"].as_str().unwrap_or_default(),
+                        )
+                    }
+                    UpstreamFailure::Connection(message) => openai_error(
+                        StatusCode::BAD_GATEWAY,
+                        "api_error",
+                        "upstream_connection_failed",
+                        &message,
+                    ),
+                };
+                // Vault anomalies halt — no fallback. Other Vault failures
+                // (provider not connected, Vault unreachable) concern this
+                // link's credential, not the request, so the chain moves on.
+                if anomaly {
+                    return response;
+                }
+                last_error = Some(response);
+            }
+        }
+    }
+
+    last_error.unwrap_or_else(|| {
+        openai_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "api_error",
+            "all_providers_failed",
+            "All AI providers in the fallback chain failed.",
+        )
+    })
+}
+
+fn completion_id() -> String {
+    format!("chatcmpl-{}", uuid::Uuid::new_v4().simple())
+}
+
+fn include_usage(body: &ChatCompletionRequest) -> bool {
+    body.stream_options
+        .as_ref()
+        .is_some_and(|o| o.include_usage)
+}
+
+/// Proxy a streamed upstream response as SSE, translating it line by line.
+/// Usage is recorded once the upstream body ends; a read error mid-stream is
+/// sent as an `error` event and the stream ends without `[DONE]`.
+fn stream_reply<S>(
+    state: S,
+    resp: reqwest::Response,
+    mut translator: ChunkTranslator,
+    record: UsageRecord,
+) -> Response
+where
+    S: HasAiGateway + Send + Sync + 'static,
+{
+    let status = resp.status().as_u16();
+    let events = async_stream::stream! {
+        let mut bytes = resp.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut failure = None;
+        loop {
+            let next = bytes.next().await;
+            let end = !matches!(next, Some(Ok(_)));
+            match next {
+                Some(Ok(data)) => buffer.extend_from_slice(&data),
+                Some(Err(e)) => failure = Some(e.to_string()),
+                None => {}
+            }
+            // At the end, a last line without a trailing newline counts too.
+            while let Some(len) = buffer
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|at| at + 1)
+                .or_else(|| (end && !buffer.is_empty()).then_some(buffer.len()))
+            {
+                let line: Vec<u8> = buffer.drain(..len).collect();
+                for chunk in translator.line(&String::from_utf8_lossy(&line)) {
+                    yield Ok::<_, Infallible>(Event::default().data(chunk.to_string()));
+                }
+            }
+            if end {
+                break;
+            }
+        }
+
+        let record = record.streamed(status, translator.usage);
+        match failure {
+            Some(error) => {
+                tracing::warn!(
+                    provider = %record.provider,
+                    error = %error,
+                    "v1 chat_completions: upstream stream broke off",
+                );
+                state.ai_gateway().record(record.error(&error));
+                let event = json!({
+                    "error": {
+                        "message": error,
+                        "type": "api_error",
+                        "param": null,
+                        "code": "upstream_stream_failed",
+                    },
+                });
+                yield Ok(Event::default().data(event.to_string()));
+            }
+            None => {
+                state.ai_gateway().record(record);
+                yield Ok(Event::default().data("[DONE]"));
+            }
+        }
+    };
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Render a completion as JSON or as an SSE chunk stream.
+fn completion_reply(
+    body: &ChatCompletionRequest,
+    model: &str,
+    completion: &Completion,
+) -> Response {
+    let id = completion_id();
+    let created = chrono::Utc::now().timestamp();
+
+    if !body.stream {
+        return Json(completion_response(&id, created, model, completion)).into_response();
+    }
+
+    let events = completion_chunks(&id, created, model, completion, include_usage(body))
+        .into_iter()
+        .map(|chunk| Event::default().data(chunk.to_string()))
+        .chain(std::iter::once(Event::default().data("[DONE]")))
+        .map(Ok::<_, Infallible>);
+
+    Sse::new(futures_util::stream::iter(events))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
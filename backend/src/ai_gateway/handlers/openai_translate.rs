@@ -0,0 +1,884 @@
+// openai_translate.rs — OpenAI Chat Completions <-> provider-native formats.
+//
+// Requests arriving at `/v1/chat/completions` — and gateway chat requests,
+// via `helpers::completion_request` — are rewritten into each provider's own
+// payload (system prompt, content parts, tools, tool results),
+// and the provider's response is normalised back into a `Completion` that is
+// rendered as a `chat.completion` object or a sequence of
+// `chat.completion.chunk` objects. Streams opened directly against
+// OpenAI-format or Ollama upstreams are translated line by line by
+// `ChunkTranslator`.
+
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+
+use super::helpers::chunk_text;
+use super::types::{ChatCompletionMessage, ChatCompletionRequest, ChatCompletionToolCall};
+use crate::ai_gateway::AiProvider;
+
+/// Characters of text per streamed chunk.
+const STREAM_CHUNK_CHARS: usize = 64;
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Request translation
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A content part of a message, independent of provider.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Image(String),
+}
+
+/// Normalised `tool_choice`.
+#[derive(Debug, Clone, PartialEq)]
+enum ToolChoice {
+    Auto,
+    None,
+    Required,
+    Function(String),
+}
+
+fn tool_choice(request: &ChatCompletionRequest) -> Option<ToolChoice> {
+    match request.tool_choice.as_ref()? {
+        Value::String(s) => match s.as_str() {
+            "auto" => Some(ToolChoice::Auto),
+            "none" => Some(ToolChoice::None),
+            "required" => Some(ToolChoice::Required),
+            _ => None,
+        },
+        choice => choice
+            .pointer("/function/name")
+            .and_then(Value::as_str)
+            .map(|name| ToolChoice::Function(name.to_string())),
+    }
+}
+
+/// Content parts of a message; a plain string is one text part.
+fn content_parts(message: &ChatCompletionMessage) -> Vec<Part> {
+    match &message.content {
+        Some(Value::String(text)) => vec![Part::Text(text.clone())],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(Value::as_str)? {
+                "text" => Some(Part::Text(part.get("text")?.as_str()?.to_string())),
+                "image_url" => {
+                    let url = part.pointer("/image_url/url").or(part.get("image_url"))?;
+                    Some(Part::Image(url.as_str()?.to_string()))
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Text parts of a message joined with newlines.
+fn message_text(message: &ChatCompletionMessage) -> String {
+    content_parts(message)
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Text(text) => Some(text),
+            Part::Image(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `(media_type, base64 data)` of a `data:` URL.
+fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let (meta, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let media_type = meta.strip_suffix(";base64")?;
+    Some((media_type, data))
+}
+
+/// Tool call arguments are a JSON string in OpenAI, an object elsewhere.
+fn parse_arguments(arguments: &str) -> Value {
+    serde_json::from_str(arguments).unwrap_or_else(|_| json!({}))
+}
+
+fn system_text(request: &ChatCompletionRequest) -> Option<String> {
+    let system: Vec<String> = request
+        .messages
+        .iter()
+        .filter(|m| matches!(m.role.as_str(), "system" | "developer"))
+        .map(message_text)
+        .collect();
+    (!system.is_empty()).then(|| system.join("\n\n"))
+}
+
+/// Append `items` to the last message when it has the same role, otherwise
+/// start a new message. Anthropic and Gemini expect alternating roles, and
+/// consecutive tool results belong in one turn.
+fn push_merged(messages: &mut Vec<Value>, role: &str, key: &str, items: Vec<Value>) {
+    if items.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut()
+        && last["role"] == role
+        && let Some(existing) = last[key].as_array_mut()
+    {
+        existing.extend(items);
+        return;
+    }
+    messages.push(json!({ "role": role, key: items }));
+}
+
+/// Build the provider-native payload for an OpenAI Chat Completions request.
+/// The payload asks for a whole body; callers streaming directly flip `stream`.
+pub(crate) fn build_completion_payload(
+    provider: &AiProvider,
+    model: &str,
+    request: &ChatCompletionRequest,
+) -> Value {
+    match provider {
+        AiProvider::Anthropic => anthropic_payload(model, request),
+        AiProvider::Google => gemini_payload(request),
+        AiProvider::Ollama => ollama_payload(model, request),
+        AiProvider::OpenAI | AiProvider::Xai | AiProvider::DeepSeek => {
+            openai_payload(model, request)
+        }
+    }
+}
+
+fn anthropic_payload(model: &str, request: &ChatCompletionRequest) -> Value {
+    let mut messages = Vec::new();
+    for m in &request.messages {
+        match m.role.as_str() {
+            "system" | "developer" => {}
+            "assistant" => {
+                let text = message_text(m);
+                let mut blocks = Vec::new();
+                if !text.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": text }));
+                }
+                blocks.extend(m.tool_calls.iter().map(|call| {
+                    json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": parse_arguments(&call.function.arguments),
+                    })
+                }));
+                push_merged(&mut messages, "assistant", "content", blocks);
+            }
+            "tool" => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": m.tool_call_id.clone().unwrap_or_default(),
+                    "content": message_text(m),
+                });
+                push_merged(&mut messages, "user", "content", vec![block]);
+            }
+            _ => {
+                let blocks = content_parts(m)
+                    .into_iter()
+                    .map(|part| match part {
+                        Part::Text(text) => json!({ "type": "text", "text": text }),
+                        Part::Image(url) => match parse_data_url(&url) {
+                            Some((media_type, data)) => json!({
+                                "type": "image",
+                                "source": { "type": "base64", "media_type": media_type, "data": data },
+                            }),
+                            None => json!({
+                                "type": "image",
+                                "source": { "type": "url", "url": url },
+                            }),
+                        },
+                    })
+                    .collect();
+                push_merged(&mut messages, "user", "content", blocks);
+            }
+        }
+    }
+
+    let mut body = json!({
+        "model": model,
+        "max_tokens": request.max_output_tokens(),
+        "messages": messages,
+    });
+    if let Some(system) = system_text(request) {
+        body["system"] = json!(system);
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = request.top_p {
+        body["top_p"] = json!(top_p);
+    }
+    let stop = request.stop_sequences();
+    if !stop.is_empty() {
+        body["stop_sequences"] = json!(stop);
+    }
+    if !request.tools.is_empty() {
+        let tools: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|t| {
+                let schema = t
+                    .function
+                    .parameters
+                    .clone()
+                    .unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+                json!({
+                    "name": t.function.name,
+                    "description": t.function.description.clone().unwrap_or_default(),
+                    "input_schema": schema,
+                })
+            })
+            .collect();
+        body["tools"] = json!(tools);
+        if let Some(choice) = tool_choice(request) {
+            body["tool_choice"] = match choice {
+                ToolChoice::Auto => json!({ "type": "auto" }),
+                ToolChoice::None => json!({ "type": "none" }),
+                ToolChoice::Required => json!({ "type": "any" }),
+                ToolChoice::Function(name) => json!({ "type": "tool", "name": name }),
+            };
+        }
+    }
+    body
+}
+
+fn openai_payload(model: &str, request: &ChatCompletionRequest) -> Value {
+    let messages: Vec<Value> = request
+        .messages
+        .iter()
+        .map(|m| {
+            let mut message = serde_json::to_value(m).unwrap_or_default();
+            // Only OpenAI itself knows the `developer` role.
+            if m.role == "developer" {
+                message["role"] = json!("system");
+            }
+            message
+        })
+        .collect();
+
+    let mut body = json!({
+        "model": model,
+        "max_tokens": request.max_output_tokens(),
+        "messages": messages,
+        "stream": false,
+    });
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = request.top_p {
+        body["top_p"] = json!(top_p);
+    }
+    if let Some(stop) = &request.stop {
+        body["stop"] = stop.clone();
+    }
+    if !request.tools.is_empty() {
+        body["tools"] = json!(function_tools(request));
+        if let Some(choice) = &request.tool_choice {
+            body["tool_choice"] = choice.clone();
+        }
+    }
+    body
+}
+
+/// Tools in the OpenAI `{type: "function", function: {..}}` shape.
+fn function_tools(request: &ChatCompletionRequest) -> Vec<Value> {
+    request
+        .tools
+        .iter()
+        .map(|t| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": t.function.name,
+                    "description": t.function.description,
+                    "parameters": t.function.parameters,
+                },
+            })
+        })
+        .collect()
+}
+
+fn gemini_payload(request: &ChatCompletionRequest) -> Value {
+    // Gemini answers tool calls by function name, not call id.
+    let call_names: HashMap<&str, &str> = request
+        .messages
+        .iter()
+        .flat_map(|m| &m.tool_calls)
+        .map(|call| (call.id.as_str(), call.function.name.as_str()))
+        .collect();
+
+    let mut contents = Vec::new();
+    for m in &request.messages {
+        match m.role.as_str() {
+            "system" | "developer" => {}
+            "assistant" => {
+                let text = message_text(m);
+                let mut parts = Vec::new();
+                if !text.is_empty() {
+                    parts.push(json!({ "text": text }));
+                }
+                parts.extend(m.tool_calls.iter().map(|call| {
+                    json!({ "functionCall": {
+                        "name": call.function.name,
+                        "args": parse_arguments(&call.function.arguments),
+                    }})
+                }));
+                push_merged(&mut contents, "model", "parts", parts);
+            }
+            "tool" => {
+                let name = m
+                    .tool_call_id
+                    .as_deref()
+                    .and_then(|id| call_names.get(id))
+                    .copied()
+                    .unwrap_or_default();
+                let part = json!({ "functionResponse": {
+                    "name": name,
+                    "response": { "content": message_text(m) },
+                }});
+                push_merged(&mut contents, "user", "parts", vec![part]);
+            }
+            _ => {
+                let parts = content_parts(m)
+                    .into_iter()
+                    .map(|part| match part {
+                        Part::Text(text) => json!({ "text": text }),
+                        Part::Image(url) => match parse_data_url(&url) {
+                            Some((mime_type, data)) => {
+                                json!({ "inlineData": { "mimeType": mime_type, "data": data } })
+                            }
+                            // Remote files need a MIME type Gemini can't infer from a URL.
+                            None => json!({ "text": format!("[image: {url}]") }),
+                        },
+                    })
+                    .collect();
+                push_merged(&mut contents, "user", "parts", parts);
+            }
+        }
+    }
+
+    let mut generation_config = json!({ "maxOutputTokens": request.max_output_tokens() });
+    if let Some(temperature) = request.temperature {
+        generation_config["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = request.top_p {
+        generation_config["topP"] = json!(top_p);
+    }
+    let stop = request.stop_sequences();
+    if !stop.is_empty() {
+        generation_config["stopSequences"] = json!(stop);
+    }
+
+    let mut body = json!({
+        "contents": contents,
+        "generationConfig": generation_config,
+    });
+    if let Some(system) = system_text(request) {
+        body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+    }
+    if !request.tools.is_empty() {
+        let declarations: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|t| {
+                let mut declaration = json!({
+                    "name": t.function.name,
+                    "description": t.function.description.clone().unwrap_or_default(),
+                });
+                if let Some(parameters) = &t.function.parameters {
+                    declaration["parameters"] = gemini_schema(parameters);
+                }
+                declaration
+            })
+            .collect();
+        body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        if let Some(choice) = tool_choice(request) {
+            let config = match choice {
+                ToolChoice::Auto => json!({ "mode": "AUTO" }),
+                ToolChoice::None => json!({ "mode": "NONE" }),
+                ToolChoice::Required => json!({ "mode": "ANY" }),
+                ToolChoice::Function(name) => {
+                    json!({ "mode": "ANY", "allowedFunctionNames": [name] })
+                }
+            };
+            body["toolConfig"] = json!({ "functionCallingConfig": config });
+        }
+    }
+    body
+}
+
+/// Drop JSON Schema keywords Gemini's OpenAPI subset rejects.
+fn gemini_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "$schema" | "additionalProperties"))
+                .map(|(key, value)| (key.clone(), gemini_schema(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(gemini_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+fn ollama_payload(model: &str, request: &ChatCompletionRequest) -> Value {
+    let messages: Vec<Value> = request
+        .messages
+        .iter()
+        .map(|m| {
+            let role = match m.role.as_str() {
+                "developer" => "system",
+                other => other,
+            };
+            let mut message = json!({ "role": role, "content": message_text(m) });
+            let parts = content_parts(m);
+            let images: Vec<&str> = parts
+                .iter()
+                .filter_map(|part| match part {
+                    Part::Image(url) => parse_data_url(url).map(|(_, data)| data),
+                    Part::Text(_) => None,
+                })
+                .collect();
+            if !images.is_empty() {
+                message["images"] = json!(images);
+            }
+            if !m.tool_calls.is_empty() {
+                let calls: Vec<Value> = m
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({ "function": {
+                            "name": call.function.name,
+                            "arguments": parse_arguments(&call.function.arguments),
+                        }})
+                    })
+                    .collect();
+                message["tool_calls"] = json!(calls);
+            }
+            message
+        })
+        .collect();
+
+    let mut options = json!({ "num_predict": request.max_output_tokens() });
+    if let Some(temperature) = request.temperature {
+        options["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = request.top_p {
+        options["top_p"] = json!(top_p);
+    }
+    let stop = request.stop_sequences();
+    if !stop.is_empty() {
+        options["stop"] = json!(stop);
+    }
+
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+        "options": options,
+        "stream": false,
+    });
+    if !request.tools.is_empty() {
+        body["tools"] = json!(function_tools(request));
+    }
+    body
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Response translation
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Token usage reported by the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CompletionUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// A provider response reduced to what Chat Completions can express.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Completion {
+    pub content: String,
+    pub tool_calls: Vec<ChatCompletionToolCall>,
+    /// `stop`, `length`, `tool_calls` or `content_filter`.
+    pub finish_reason: &'static str,
+    pub usage: Option<CompletionUsage>,
+}
+
+fn new_call_id() -> String {
+    format!("call_{}", uuid::Uuid::new_v4().simple())
+}
+
+fn tool_call(id: String, name: &str, arguments: &Value) -> ChatCompletionToolCall {
+    ChatCompletionToolCall {
+        id,
+        kind: "function".to_string(),
+        function: super::types::ChatCompletionFunctionCall {
+            name: name.to_string(),
+            // Some providers already return the arguments JSON-encoded.
+            arguments: match arguments {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            },
+        },
+    }
+}
+
+fn usage(body: &Value, prompt: &str, completion: &str) -> Option<CompletionUsage> {
+    Some(CompletionUsage {
+        prompt_tokens: body.get(prompt)?.as_u64()?,
+        completion_tokens: body.get(completion).and_then(Value::as_u64).unwrap_or(0),
+    })
+}
+
+/// Normalise a provider response body.
+pub(crate) fn parse_completion(provider: &AiProvider, body: &Value) -> Completion {
+    match provider {
+        AiProvider::Anthropic => {
+            let blocks = body
+                .get("content")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let content = blocks
+                .iter()
+                .filter(|b| b["type"] == "text")
+                .filter_map(|b| b["text"].as_str())
+                .collect();
+            let tool_calls = blocks
+                .iter()
+                .filter(|b| b["type"] == "tool_use")
+                .map(|b| {
+                    let id = b["id"].as_str().map_or_else(new_call_id, str::to_string);
+                    tool_call(id, b["name"].as_str().unwrap_or_default(), &b["input"])
+                })
+                .collect();
+            let finish_reason = match body["stop_reason"].as_str() {
+                Some("max_tokens") => "length",
+                Some("tool_use") => "tool_calls",
+                Some("refusal") => "content_filter",
+                _ => "stop",
+            };
+            Completion {
+                content,
+                tool_calls,
+                finish_reason,
+                usage: body
+                    .get("usage")
+                    .and_then(|u| usage(u, "input_tokens", "output_tokens")),
+            }
+        }
+        AiProvider::OpenAI | AiProvider::Xai | AiProvider::DeepSeek => {
+            let choice = &body["choices"][0];
+            let message = &choice["message"];
+            let tool_calls = message["tool_calls"]
+                .as_array()
+                .map(|calls| {
+                    calls
+                        .iter()
+                        .filter_map(|call| {
+                            let id = call["id"].as_str().map_or_else(new_call_id, str::to_string);
+                            let function = call.get("function")?;
+                            Some(tool_call(
+                                id,
+                                function["name"].as_str()?,
+                                &function["arguments"],
+                            ))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let finish_reason = match choice["finish_reason"].as_str() {
+                Some("length") => "length",
+                Some("tool_calls" | "function_call") => "tool_calls",
+                Some("content_filter") => "content_filter",
+                _ => "stop",
+            };
+            Completion {
+                content: message["content"].as_str().unwrap_or_default().to_string(),
+                tool_calls,
+                finish_reason,
+                usage: body
+                    .get("usage")
+                    .and_then(|u| usage(u, "prompt_tokens", "completion_tokens")),
+            }
+        }
+        AiProvider::Google => {
+            let candidate = &body["candidates"][0];
+            let parts = candidate
+                .pointer("/content/parts")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let content = parts.iter().filter_map(|p| p["text"].as_str()).collect();
+            let tool_calls: Vec<ChatCompletionToolCall> = parts
+                .iter()
+                .filter_map(|p| p.get("functionCall"))
+                .map(|call| {
+                    tool_call(
+                        new_call_id(),
+                        call["name"].as_str().unwrap_or_default(),
+                        &call["args"],
+                    )
+                })
+                .collect();
+            let finish_reason = match candidate["finishReason"].as_str() {
+                _ if !tool_calls.is_empty() => "tool_calls",
+                Some("MAX_TOKENS") => "length",
+                Some("SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT") => {
+                    "content_filter"
+                }
+                _ => "stop",
+            };
+            Completion {
+                content,
+                tool_calls,
+                finish_reason,
+                usage: body
+                    .get("usageMetadata")
+                    .and_then(|u| usage(u, "promptTokenCount", "candidatesTokenCount")),
+            }
+        }
+        AiProvider::Ollama => {
+            let message = &body["message"];
+            let tool_calls: Vec<ChatCompletionToolCall> = message["tool_calls"]
+                .as_array()
+                .map(|calls| {
+                    calls
+                        .iter()
+                        .filter_map(|call| {
+                            let function = call.get("function")?;
+                            Some(tool_call(
+                                new_call_id(),
+                                function["name"].as_str()?,
+                                &function["arguments"],
+                            ))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let finish_reason = match body["done_reason"].as_str() {
+                _ if !tool_calls.is_empty() => "tool_calls",
+                Some("length") => "length",
+                _ => "stop",
+            };
+            Completion {
+                content: message["content"].as_str().unwrap_or_default().to_string(),
+                tool_calls,
+                finish_reason,
+                usage: usage(body, "prompt_eval_count", "eval_count"),
+            }
+        }
+    }
+}
+
+// ── Rendering ───────────────────────────────────────────────────────────────
+
+fn usage_json(usage: CompletionUsage) -> Value {
+    json!({
+        "prompt_tokens": usage.prompt_tokens,
+        "completion_tokens": usage.completion_tokens,
+        "total_tokens": usage.prompt_tokens + usage.completion_tokens,
+    })
+}
+
+/// A `chat.completion` object.
+pub(crate) fn completion_response(
+    id: &str,
+    created: i64,
+    model: &str,
+    completion: &Completion,
+) -> Value {
+    let mut message = json!({
+        "role": "assistant",
+        "content": (!completion.content.is_empty() || completion.tool_calls.is_empty())
+            .then_some(&completion.content),
+    });
+    if !completion.tool_calls.is_empty() {
+        message["tool_calls"] = json!(completion.tool_calls);
+    }
+    let mut response = json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": created,
+        "model": model,
+        "choices": [{ "index": 0, "message": message, "finish_reason": completion.finish_reason }],
+    });
+    if let Some(usage) = completion.usage {
+        response["usage"] = usage_json(usage);
+    }
+    response
+}
+
+fn chunk_object(
+    id: &str,
+    created: i64,
+    model: &str,
+    delta: Value,
+    finish_reason: Option<&str>,
+) -> Value {
+    json!({
+        "id": id,
+        "object": "chat.completion.chunk",
+        "created": created,
+        "model": model,
+        "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+    })
+}
+
+fn usage_chunk(id: &str, created: i64, model: &str, usage: Option<CompletionUsage>) -> Value {
+    json!({
+        "id": id,
+        "object": "chat.completion.chunk",
+        "created": created,
+        "model": model,
+        "choices": [],
+        "usage": usage.map(usage_json),
+    })
+}
+
+fn tool_call_delta(index: usize, call: &ChatCompletionToolCall) -> Value {
+    json!({ "tool_calls": [{
+        "index": index,
+        "id": call.id,
+        "type": "function",
+        "function": { "name": call.function.name, "arguments": call.function.arguments },
+    }]})
+}
+
+/// The `chat.completion.chunk` sequence for a finished completion: role,
+/// text deltas, one delta per tool call, the finish reason and — when
+/// requested — a final usage-only chunk.
+pub(crate) fn completion_chunks(
+    id: &str,
+    created: i64,
+    model: &str,
+    completion: &Completion,
+    include_usage: bool,
+) -> Vec<Value> {
+    let chunk = |delta: Value, finish_reason: Option<&str>| {
+        chunk_object(id, created, model, delta, finish_reason)
+    };
+
+    let mut chunks = vec![chunk(json!({ "role": "assistant", "content": "" }), None)];
+    chunks.extend(
+        chunk_text(&completion.content, STREAM_CHUNK_CHARS)
+            .into_iter()
+            .map(|text| chunk(json!({ "content": text }), None)),
+    );
+    chunks.extend(
+        completion
+            .tool_calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| chunk(tool_call_delta(index, call), None)),
+    );
+    chunks.push(chunk(json!({}), Some(completion.finish_reason)));
+
+    if include_usage {
+        chunks.push(usage_chunk(id, created, model, completion.usage));
+    }
+    chunks
+}
+
+/// Translates a streamed upstream body, one line at a time, into
+/// `chat.completion.chunk` objects. OpenAI-format streams are SSE whose
+/// `data:` payloads already are chunks and pass through unchanged; Ollama
+/// streams NDJSON messages, which are rewritten.
+pub(crate) struct ChunkTranslator {
+    id: String,
+    created: i64,
+    model: String,
+    format: AiProvider,
+    include_usage: bool,
+    started: bool,
+    tool_calls: usize,
+    /// Token usage reported by the upstream so far.
+    pub usage: Option<CompletionUsage>,
+}
+
+impl ChunkTranslator {
+    pub(crate) fn new(
+        id: String,
+        created: i64,
+        model: &str,
+        format: AiProvider,
+        include_usage: bool,
+    ) -> Self {
+        Self {
+            id,
+            created,
+            model: model.to_string(),
+            format,
+            include_usage,
+            started: false,
+            tool_calls: 0,
+            usage: None,
+        }
+    }
+
+    /// Whether `format` streams in a shape this translator reads.
+    pub(crate) fn supports(format: &AiProvider) -> bool {
+        matches!(
+            format,
+            AiProvider::OpenAI | AiProvider::Xai | AiProvider::DeepSeek | AiProvider::Ollama
+        )
+    }
+
+    /// Chunks for one line of the upstream body. Blank lines, SSE comments
+    /// and the upstream's own `[DONE]` yield none.
+    pub(crate) fn line(&mut self, line: &str) -> Vec<Value> {
+        let line = line.trim();
+        if self.format != AiProvider::Ollama {
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                return Vec::new();
+            };
+            let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+                return Vec::new();
+            };
+            if let Some(usage) = usage(&chunk["usage"], "prompt_tokens", "completion_tokens") {
+                self.usage = Some(usage);
+            }
+            return vec![chunk];
+        }
+
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            return Vec::new();
+        };
+        if let Some(error) = message["error"].as_str() {
+            return vec![json!({
+                "error": { "message": error, "type": "api_error", "code": "upstream_error" },
+            })];
+        }
+        let part = parse_completion(&AiProvider::Ollama, &message);
+        let chunk = |delta: Value, finish_reason: Option<&str>| {
+            chunk_object(&self.id, self.created, &self.model, delta, finish_reason)
+        };
+
+        let mut chunks = Vec::new();
+        if !self.started {
+            chunks.push(chunk(json!({ "role": "assistant", "content": "" }), None));
+        }
+        if !part.content.is_empty() {
+            chunks.push(chunk(json!({ "content": part.content }), None));
+        }
+        for (offset, call) in part.tool_calls.iter().enumerate() {
+            chunks.push(chunk(tool_call_delta(self.tool_calls + offset, call), None));
+        }
+        if message["done"].as_bool() == Some(true) {
+            // Tool calls may have arrived in earlier lines.
+            let finish_reason = match self.tool_calls + part.tool_calls.len() {
+                0 => part.finish_reason,
+                _ => "tool_calls",
+            };
+            chunks.push(chunk(json!({}), Some(finish_reason)));
+            if self.include_usage {
+                chunks.push(usage_chunk(&self.id, self.created, &self.model, part.usage));
+            }
+        }
+        self.started = true;
+        self.tool_calls += part.tool_calls.len();
+        if part.usage.is_some() {
+            self.usage = part.usage;
+        }
+        chunks
+    }
+}
@@ -8,12 +8,14 @@ use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use serde_json::json;
 
+use crate::ai_gateway::health::Outcome;
+use crate::ai_gateway::token_refresh::{self, RefreshStatus};
 use crate::ai_gateway::{
     AiProvider, AuthType, HasAiGateway, oauth_flows::OAuthProvider, vault_bridge::HasVaultBridge,
 };
 
 use super::helpers::{build_test_payload, extract_response_preview, resolve_upstream_url};
-use super::router::{parse_provider, vault_error_response};
+use super::router::{lockout_response, parse_provider, vault_error_response};
 use super::types::*;
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -23,18 +25,20 @@ use super::types::*;
 /// Lists all configured AI providers with their connection status.
 ///
 /// For each provider, queries Jaskier Vault to check if credentials exist
-/// and whether they're still valid. Returns an array of `GatewayProviderInfo`.
+/// and whether they're still valid, and adds the circuit breaker state and
+/// health score of the gateway's calls and the credential refresh schedule.
+/// Returns an array of `GatewayProviderInfo`.
 pub(crate) async fn list_providers<S>(State(state): State<S>) -> impl IntoResponse
 where
     S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
 {
-    let gateway = state.ai_gateway();
+    let configs = state.ai_gateway().providers();
     let vault = state.vault_client();
 
     let mut providers = Vec::with_capacity(AiProvider::ALL.len());
 
     for provider in AiProvider::ALL {
-        let config = match gateway.providers.get(&provider) {
+        let config = match configs.get(&provider) {
             Some(cfg) => cfg,
             None => continue,
         };
@@ -55,6 +59,8 @@ where
                 coordinator: config.model_tiers.coordinator.clone(),
                 executor: config.model_tiers.executor.clone(),
             },
+            health: state.ai_gateway().health.snapshot(provider),
+            token_refresh: state.ai_gateway().token_refresh.snapshot(provider),
         });
     }
 
@@ -89,8 +95,8 @@ where
         Err(e) => return e.into_response(),
     };
 
-    let gateway = state.ai_gateway();
-    let config = match gateway.providers.get(&provider_enum) {
+    let providers = state.ai_gateway().providers();
+    let config = match providers.get(&provider_enum) {
         Some(cfg) => cfg,
         None => {
             return (
@@ -120,6 +126,8 @@ where
             "coordinator": config.model_tiers.coordinator,
             "executor": config.model_tiers.executor,
         },
+        "health": state.ai_gateway().health.snapshot(provider_enum),
+        "token_refresh": state.ai_gateway().token_refresh.snapshot(provider_enum),
     }))
     .into_response()
 }
@@ -149,8 +157,8 @@ where
         Err(e) => return e.into_response(),
     };
 
-    let gateway = state.ai_gateway();
-    let config = match gateway.providers.get(&provider_enum) {
+    let providers = state.ai_gateway().providers();
+    let config = match providers.get(&provider_enum) {
         Some(cfg) => cfg,
         None => {
             return (
@@ -298,8 +306,8 @@ where
         Err(e) => return e.into_response(),
     };
 
-    let gateway = state.ai_gateway();
-    let config = match gateway.providers.get(&provider_enum) {
+    let providers = state.ai_gateway().providers();
+    let config = match providers.get(&provider_enum) {
         Some(cfg) => cfg,
         None => {
             return (
@@ -353,12 +361,14 @@ where
         }
     };
 
-    // Store exchanged tokens in Vault (NEVER in PostgreSQL)
+    // Store exchanged tokens in Vault (NEVER in PostgreSQL). `expires_at`
+    // drives the background refresh scheduler.
     let vault = state.vault_client();
     let credential_data = json!({
         "access_token": tokens.access_token,
         "refresh_token": tokens.refresh_token,
         "expires_in": tokens.expires_in,
+        "expires_at": tokens.expires_in.map(|secs| chrono::Utc::now().timestamp() + secs),
         "token_type": tokens.token_type,
         "scope": tokens.scope,
     });
@@ -372,6 +382,7 @@ where
         .await
     {
         Ok(()) => {
+            state.ai_gateway().token_refresh.reschedule(provider_enum);
             tracing::info!(provider = %provider_enum, "OAuth tokens exchanged and stored in Vault");
             Json(json!({
                 "provider": provider_enum.to_string(),
@@ -408,8 +419,8 @@ where
         Err(e) => return e.into_response(),
     };
 
-    let gateway = state.ai_gateway();
-    let config = match gateway.providers.get(&provider_enum) {
+    let providers = state.ai_gateway().providers();
+    let config = match providers.get(&provider_enum) {
         Some(cfg) => cfg,
         None => {
             return (
@@ -449,6 +460,7 @@ where
             vault
                 .invalidate_cache(&config.vault_namespace, &config.vault_service)
                 .await;
+            state.ai_gateway().token_refresh.reschedule(provider_enum);
             tracing::info!(provider = %provider_enum, "provider disconnected — credentials overwritten in Vault");
             Json(json!({
                 "provider": provider_enum.to_string(),
@@ -469,6 +481,10 @@ where
 // ═══════════════════════════════════════════════════════════════════════════
 
 /// Forces a token refresh for the specified provider.
+///
+/// OAuth tokens are refreshed right away by the token refresh scheduler
+/// (which otherwise refreshes them ahead of expiry); the response carries
+/// the resulting schedule.
 pub(crate) async fn refresh_provider<S>(
     State(state): State<S>,
     Path(provider): Path<String>,
@@ -481,8 +497,8 @@ where
         Err(e) => return e.into_response(),
     };
 
-    let gateway = state.ai_gateway();
-    let config = match gateway.providers.get(&provider_enum) {
+    let providers = state.ai_gateway().providers();
+    let config = match providers.get(&provider_enum) {
         Some(cfg) => cfg,
         None => {
             return (
@@ -497,28 +513,40 @@ where
 
     match config.auth_type {
         AuthType::OAuthPkce => {
-            let vault = state.vault_client();
-            let status = vault.get_provider_status(&provider).await;
+            let refresh = token_refresh::check(&state, provider_enum, true).await;
 
-            if status.is_connected {
-                Json(json!({
+            match refresh.as_ref().map(|r| r.status) {
+                Some(RefreshStatus::Scheduled) => Json(json!({
                     "provider": provider_enum.to_string(),
-                    "status": "valid",
+                    "status": if refresh.as_ref().and_then(|r| r.last_error.as_ref()).is_some() {
+                        "valid"
+                    } else {
+                        "refreshed"
+                    },
                     "auth_type": "oauth_pkce",
-                    "message": "OAuth credential is currently valid. If expired, reconnect via /connect.",
-                    "last_verified": status.last_verified,
-                    "expires_at": status.expires_at,
+                    "token_refresh": refresh,
                 }))
-                .into_response()
-            } else {
-                Json(json!({
+                .into_response(),
+                Some(RefreshStatus::Backoff) => (
+                    StatusCode::BAD_GATEWAY,
+                    Json(json!({
+                        "provider": provider_enum.to_string(),
+                        "status": "refresh_failed",
+                        "auth_type": "oauth_pkce",
+                        "message": "Token refresh failed; it will be retried in the background.",
+                        "token_refresh": refresh,
+                    })),
+                )
+                    .into_response(),
+                _ => Json(json!({
                     "provider": provider_enum.to_string(),
                     "status": "reconnect_required",
                     "auth_type": "oauth_pkce",
                     "message": "OAuth credential expired or not found. Reconnect via POST /api/ai/providers/{provider}/connect.",
                     "action": format!("POST /api/ai/providers/{}/connect", provider_enum),
+                    "token_refresh": refresh,
                 }))
-                .into_response()
+                .into_response(),
             }
         }
         AuthType::SessionToken | AuthType::CookieSession => {
@@ -576,9 +604,12 @@ where
         Ok(p) => p,
         Err(e) => return e.into_response(),
     };
+    if let Err(lockout) = state.ai_gateway().vault_guard.check() {
+        return lockout_response(&lockout).into_response();
+    }
 
-    let gateway = state.ai_gateway();
-    let config = match gateway.providers.get(&provider_enum) {
+    let providers = state.ai_gateway().providers();
+    let config = match providers.get(&provider_enum) {
         Some(cfg) => cfg,
         None => {
             return (
@@ -616,6 +647,21 @@ where
 
     let latency_ms = started.elapsed().as_millis() as u64;
 
+    // A manual test counts like a half-open probe.
+    let (outcome, error) = match &result {
+        Ok(resp) => (
+            Outcome::from_status(resp.status),
+            Some(format!("test: upstream returned HTTP {}", resp.status)),
+        ),
+        Err(err) => (Outcome::Failed, Some(format!("test: {}", err))),
+    };
+    state.ai_gateway().health.record(
+        provider_enum,
+        outcome,
+        latency_ms,
+        error.filter(|_| outcome != Outcome::Ok),
+    );
+
     match result {
         Ok(resp) => {
             let success = (200..300).contains(&(resp.status as usize));
@@ -653,7 +699,7 @@ where
             );
 
             // For Vault errors, return appropriate HTTP status
-            if err.is_anomaly() {
+            if state.ai_gateway().vault_guard.observe(&err).await {
                 return vault_error_response(&provider_enum, err).into_response();
             }
 
@@ -3,17 +3,46 @@
 use std::convert::Infallible;
 use std::time::Instant;
 
-use axum::extract::{Json, Path, State};
+use axum::extract::{FromRequestParts, Json, Path, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::response::sse::{Event, KeepAlive, Sse};
 use serde_json::{Value, json};
 
-use crate::ai_gateway::{AuthType, HasAiGateway, vault_bridge::HasVaultBridge};
-
-use super::helpers::{build_chat_payload, chunk_text, extract_content_text, resolve_upstream_url};
-use super::router::{parse_provider, vault_error_response};
-use super::types::GatewayChatRequest;
+use crate::ai_gateway::model_router::{ModelRouter, ModelTier};
+use crate::ai_gateway::usage::UsageRecord;
+use crate::ai_gateway::{AiProvider, AuthType, HasAiGateway, vault_bridge::HasVaultBridge};
+use crate::extractor::OptionalAuth;
+
+use super::helpers::{
+    build_chat_payload, chunk_text, extract_content_blocks, resolve_upstream_url, tier_model,
+};
+use super::router::{lockout_response, parse_provider, vault_error_response};
+use super::types::{GatewayChatRequest, GatewayContentBlock};
+use super::upstream::{UpstreamFailure, read_body, send_compatible};
+
+/// SSE events for a finished upstream response: the text as `token` chunks,
+/// then one `tool_use` event per tool call.
+fn content_events(provider: &AiProvider, body: &Value) -> Vec<Event> {
+    extract_content_blocks(provider, body)
+        .into_iter()
+        .flat_map(|block| match block {
+            GatewayContentBlock::Text { text } => chunk_text(&text, 20)
+                .into_iter()
+                .map(|chunk| {
+                    Event::default()
+                        .event("token")
+                        .data(json!({ "text": chunk }).to_string())
+                })
+                .collect(),
+            block => vec![
+                Event::default()
+                    .event("tool_use")
+                    .data(json!(block).to_string()),
+            ],
+        })
+        .collect()
+}
 
 // ═══════════════════════════════════════════════════════════════════════════
 //  POST /api/ai/{provider}/chat — proxied non-streaming chat
@@ -25,38 +54,43 @@ use super::types::GatewayChatRequest;
 /// Bouncer pattern. The backend NEVER sees raw credentials.
 ///
 /// On upstream failure, attempts fallback to an alternative provider via
-/// the model router (if configured).
+/// the model router (if configured), then to the OpenAI-compatible endpoints.
 pub(crate) async fn proxy_chat<S>(
     State(state): State<S>,
+    OptionalAuth { email }: OptionalAuth,
     Path(provider): Path<String>,
     Json(body): Json<GatewayChatRequest>,
 ) -> impl IntoResponse
 where
     S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+    OptionalAuth: FromRequestParts<S>,
 {
     let current_provider = match parse_provider(&provider) {
         Ok(p) => p,
         Err(e) => return e.into_response(),
     };
-
-    let router = crate::ai_gateway::model_router::ModelRouter::new();
-    let fallback_chain = router.fallback_chain(current_provider);
+    if let Err(lockout) = state.ai_gateway().vault_guard.check() {
+        return lockout_response(&lockout).into_response();
+    }
 
     let original_model = body.model.clone();
     let gateway = state.ai_gateway();
+    let providers = gateway.providers();
+    let fallback_chain = gateway.fallback_chain(current_provider, &providers);
     let vault = state.vault_client();
+    let caller = email.map(|email| format!("user:{}", email));
 
     let mut last_error_response = None;
     let mut last_latency;
 
     for (attempt, provider_enum) in fallback_chain.iter().enumerate() {
-        let config = match gateway.providers.get(provider_enum) {
+        let config = match providers.get(provider_enum) {
             Some(cfg) => cfg,
             None => continue,
         };
 
         // If fallback, we need to map to the new provider's model for the same tier
-        let model = if attempt == 0 {
+        let model = if *provider_enum == current_provider {
             original_model
                 .clone()
                 .unwrap_or_else(|| config.model_tiers.coordinator.clone())
@@ -103,35 +137,56 @@ where
                 Ok(resp) => {
                     let status = resp.status().as_u16();
                     last_latency = started.elapsed().as_millis() as u64;
-                    if let Ok(json_body) = resp.json::<Value>().await {
-                        if (200..300).contains(&(status as usize)) {
-                            return Json(json!({
+                    let response = read_body(resp).await;
+                    gateway.record(
+                        UsageRecord::new(
+                            "chat",
+                            provider_enum.to_string(),
+                            &model,
+                            attempt,
+                            started,
+                        )
+                        .caller(caller.clone())
+                        .response(status, provider_enum, &response),
+                    );
+                    if (200..300).contains(&(status as usize)) {
+                        return Json(json!({
+                            "provider": provider_enum.to_string(),
+                            "model": model,
+                            "latency_ms": last_latency,
+                            "content": extract_content_blocks(provider_enum, &response),
+                            "response": response,
+                            "fallback_attempts": attempt,
+                        }))
+                        .into_response();
+                    }
+                    last_error_response = Some(
+                        (
+                            StatusCode::BAD_GATEWAY,
+                            Json(json!({
+                                "error": "upstream_error",
                                 "provider": provider_enum.to_string(),
-                                "model": model,
+                                "upstream_status": status,
+                                "upstream_body": response,
                                 "latency_ms": last_latency,
-                                "response": json_body,
-                                "fallback_attempts": attempt,
-                            }))
-                            .into_response();
-                        } else {
-                            last_error_response = Some(
-                                (
-                                    StatusCode::BAD_GATEWAY,
-                                    Json(json!({
-                                        "error": "upstream_error",
-                                        "provider": provider_enum.to_string(),
-                                        "upstream_status": status,
-                                        "upstream_body": json_body,
-                                        "latency_ms": last_latency,
-                                    })),
-                                )
-                                    .into_response(),
-                            );
-                        }
-                    }
+                            })),
+                        )
+                            .into_response(),
+                    );
                 }
                 Err(e) => {
                     last_latency = started.elapsed().as_millis() as u64;
+                    gateway.record(
+                        UsageRecord::new(
+                            "chat",
+                            provider_enum.to_string(),
+                            &model,
+                            attempt,
+                            started,
+                        )
+                        .caller(caller.clone())
+                        .error(&e),
+                    );
                     last_error_response = Some(
                         (
                             StatusCode::BAD_GATEWAY,
@@ -162,6 +217,11 @@ where
         {
             Ok(resp) => {
                 last_latency = started.elapsed().as_millis() as u64;
+                gateway.record(
+                    UsageRecord::new("chat", provider_enum.to_string(), &model, attempt, started)
+                        .caller(caller.clone())
+                        .response(resp.status, provider_enum, &resp.body),
+                );
                 if (200..300).contains(&(resp.status as usize)) {
                     tracing::info!(
                         provider = %provider_enum,
@@ -173,6 +233,7 @@ where
                         "provider": provider_enum.to_string(),
                         "model": model,
                         "latency_ms": last_latency,
+                        "content": extract_content_blocks(provider_enum, &resp.body),
                         "response": resp.body,
                         "fallback_attempts": attempt,
                     }))
@@ -208,9 +269,14 @@ where
                     error = %err,
                     "proxy_chat: vault delegate failed",
                 );
+                gateway.record(
+                    UsageRecord::new("chat", provider_enum.to_string(), &model, attempt, started)
+                        .caller(caller.clone())
+                        .error(&err),
+                );
 
                 // For Vault anomalies we shouldn't fallback, we should halt
-                if err.is_anomaly() {
+                if gateway.vault_guard.observe(&err).await {
                     return vault_error_response(provider_enum, err).into_response();
                 }
 
@@ -220,6 +286,88 @@ where
         }
     }
 
+    // Then the OpenAI-compatible endpoints, in priority order.
+    let tier = original_model
+        .as_deref()
+        .map(ModelRouter::detect_tier)
+        .unwrap_or(ModelTier::Coordinator);
+    for (offset, endpoint) in providers.compatible.iter().enumerate() {
+        let attempt = fallback_chain.len() + offset;
+        let model = tier_model(&endpoint.model_tiers, tier);
+
+        tracing::info!(
+            provider = %endpoint.id,
+            model = %model,
+            attempt = attempt + 1,
+            "proxy_chat: routing request",
+        );
+
+        let upstream_body = build_chat_payload(&AiProvider::OpenAI, &model, &body);
+        let started = Instant::now();
+
+        let outcome = send_compatible(
+            vault,
+            endpoint,
+            upstream_body,
+            gateway.allow_private_upstreams,
+        )
+        .await;
+        let record = UsageRecord::new("chat", endpoint.id.clone(), &model, attempt, started)
+            .caller(caller.clone());
+        match outcome {
+            Ok((status, response)) => {
+                gateway.record(record.response(status, &AiProvider::OpenAI, &response));
+                let latency_ms = started.elapsed().as_millis() as u64;
+                if (200..300).contains(&status) {
+                    return Json(json!({
+                        "provider": endpoint.id,
+                        "model": model,
+                        "latency_ms": latency_ms,
+                        "content": extract_content_blocks(&AiProvider::OpenAI, &response),
+                        "response": response,
+                        "fallback_attempts": attempt,
+                    }))
+                    .into_response();
+                }
+                last_error_response = Some(
+                    (
+                        StatusCode::BAD_GATEWAY,
+                        Json(json!({
+                            "error": "upstream_error",
+                            "provider": endpoint.id,
+                            "upstream_status": status,
+                            "upstream_body": response,
+                            "latency_ms": latency_ms,
+                        })),
+                    )
+                        .into_response(),
+                );
+            }
+            Err(UpstreamFailure::Vault(err)) => {
+                gateway.record(record);
+                if gateway.vault_guard.observe(&err).await {
+                    return vault_error_response(&endpoint.id, err).into_response();
+                }
+                last_error_response = Some(vault_error_response(&endpoint.id, err).into_response());
+            }
+            Err(UpstreamFailure::Connection(message)) => {
+                gateway.record(record);
+                last_error_response = Some(
+                    (
+                        StatusCode::BAD_GATEWAY,
+                        Json(json!({
+                            "error": "upstream_connection_failed",
+                            "provider": endpoint.id,
+                            "message": message,
+                            "latency_ms": started.elapsed().as_millis() as u64,
+                        })),
+                    )
+                        .into_response(),
+                );
+            }
+        }
+    }
+
     // If we exhaust the fallback chain, return the last error
     last_error_response.unwrap_or_else(|| {
         (
@@ -244,34 +392,41 @@ where
 /// provider is generating the response.
 pub(crate) async fn proxy_stream<S>(
     State(state): State<S>,
+    OptionalAuth { email }: OptionalAuth,
     Path(provider): Path<String>,
     Json(body): Json<GatewayChatRequest>,
 ) -> impl IntoResponse
 where
     S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+    OptionalAuth: FromRequestParts<S>,
 {
     let current_provider = match parse_provider(&provider) {
         Ok(p) => p,
         Err(e) => return e.into_response(),
     };
-
-    let router = crate::ai_gateway::model_router::ModelRouter::new();
-    let fallback_chain = router.fallback_chain(current_provider);
+    if let Err(lockout) = state.ai_gateway().vault_guard.check() {
+        return lockout_response(&lockout).into_response();
+    }
 
     let original_model = body.model.clone();
-    let cloned_state = state.clone();
+    let providers = state.ai_gateway().providers();
+    let fallback_chain = state
+        .ai_gateway()
+        .fallback_chain(current_provider, &providers);
     let vault_client = state.vault_client().clone();
+    let caller = email.map(|email| format!("user:{}", email));
 
     let stream = async_stream::stream! {
+        let gateway = state.ai_gateway();
         let mut last_error_response = None;
 
-        for (attempt, provider_enum) in fallback_chain.into_iter().enumerate() {
-            let config = match cloned_state.ai_gateway().providers.get(&provider_enum) {
+        for (attempt, provider_enum) in fallback_chain.iter().copied().enumerate() {
+            let config = match providers.get(&provider_enum) {
                 Some(cfg) => cfg.clone(),
                 None => continue,
             };
 
-            let model = if attempt == 0 {
+            let model = if provider_enum == current_provider {
                 original_model.clone().unwrap_or_else(|| config.model_tiers.coordinator.clone())
             } else {
                 let tier = original_model.as_ref()
@@ -324,29 +479,30 @@ where
                     Ok(resp) => {
                         let status = resp.status().as_u16();
                         let latency_ms = started.elapsed().as_millis() as u64;
-                        if let Ok(json_body) = resp.json::<Value>().await {
-                            if (200..300).contains(&(status as usize)) {
-                                let content = extract_content_text(&provider_enum, &json_body);
-                                for chunk in chunk_text(&content, 20) {
-                                    yield Ok(Event::default()
-                                        .event("token")
-                                        .data(json!({ "text": chunk }).to_string()));
-                                }
-                                yield Ok(Event::default()
-                                    .event("stream_end")
-                                    .data(json!({
-                                        "provider": provider_enum.to_string(),
-                                        "model": model,
-                                        "latency_ms": latency_ms,
-                                        "finish_reason": "end_turn",
-                                    }).to_string()));
-                                return;
-                            } else {
-                                last_error_response = Some(format!("Upstream returned HTTP {} (direct)", status));
+                        let response = read_body(resp).await;
+                        gateway.record(
+                            UsageRecord::new("stream", provider_enum.to_string(), &model, attempt, started)
+                                .caller(caller.clone())
+                                .response(status, &provider_enum, &response),
+                        );
+                        if (200..300).contains(&(status as usize)) {
+                            for event in content_events(&provider_enum, &response) {
+                                yield Ok(event);
                             }
+                            yield Ok(Event::default()
+                                .event("stream_end")
+                                .data(json!({
+                                    "provider": provider_enum.to_string(),
+                                    "model": model,
+                                    "latency_ms": latency_ms,
+                                    "finish_reason": "end_turn",
+                                }).to_string()));
+                            return;
                         }
+                        last_error_response = Some(format!("Upstream returned HTTP {} (direct)", status));
                     }
                     Err(e) => {
+                        gateway.record(UsageRecord::new("stream", provider_enum.to_string(), &model, attempt, started).caller(caller.clone()).error(&e));
                         last_error_response = Some(e.to_string());
                     }
                 }
@@ -364,13 +520,14 @@ where
             match delegate_result {
                 Ok(resp) => {
                     let latency_ms = started.elapsed().as_millis() as u64;
+                    gateway.record(
+                        UsageRecord::new("stream", provider_enum.to_string(), &model, attempt, started)
+                            .caller(caller.clone())
+                            .response(resp.status, &provider_enum, &resp.body),
+                    );
                     if (200..300).contains(&(resp.status as usize)) {
-                        let content = extract_content_text(&provider_enum, &resp.body);
-
-                        for chunk in chunk_text(&content, 20) {
-                            yield Ok(Event::default()
-                                .event("token")
-                                .data(json!({ "text": chunk }).to_string()));
+                        for event in content_events(&provider_enum, &resp.body) {
+                            yield Ok(event);
                         }
 
                         yield Ok(Event::default()
@@ -392,8 +549,9 @@ where
                         error = %err,
                         "proxy_stream: vault delegate failed",
                     );
+                    gateway.record(UsageRecord::new("stream", provider_enum.to_string(), &model, attempt, started).caller(caller.clone()).error(&err));
 
-                    if err.is_anomaly() {
+                    if gateway.vault_guard.observe(&err).await {
                         let (error_type, message) = ("anomaly_detected", format!("ANOMALY: {}", err));
                         yield Ok(Event::default()
                             .event("error")
@@ -410,6 +568,70 @@ where
             }
         }
 
+        // Then the OpenAI-compatible endpoints, in priority order.
+        let tier = original_model
+            .as_deref()
+            .map(ModelRouter::detect_tier)
+            .unwrap_or(ModelTier::Coordinator);
+        for (offset, endpoint) in providers.compatible.iter().enumerate() {
+            let attempt = fallback_chain.len() + offset;
+            let model = tier_model(&endpoint.model_tiers, tier);
+
+            yield Ok(Event::default()
+                .event("fallback")
+                .data(json!({
+                    "provider": endpoint.id,
+                    "model": model,
+                    "attempt": attempt,
+                }).to_string()));
+
+            let upstream_body = build_chat_payload(&AiProvider::OpenAI, &model, &body);
+            let started = Instant::now();
+
+            let outcome = send_compatible(&vault_client, endpoint, upstream_body, gateway.allow_private_upstreams).await;
+            let record = UsageRecord::new("stream", endpoint.id.clone(), &model, attempt, started)
+                .caller(caller.clone());
+            match &outcome {
+                Ok((status, response)) => gateway.record(record.response(*status, &AiProvider::OpenAI, response)),
+                Err(failure) => gateway.record(record.error(failure)),
+            }
+            match outcome {
+                Ok((status, response)) if (200..300).contains(&status) => {
+                    for event in content_events(&AiProvider::OpenAI, &response) {
+                        yield Ok(event);
+                    }
+                    yield Ok(Event::default()
+                        .event("stream_end")
+                        .data(json!({
+                            "provider": endpoint.id,
+                            "model": model,
+                            "latency_ms": started.elapsed().as_millis() as u64,
+                            "finish_reason": "end_turn",
+                        }).to_string()));
+                    return;
+                }
+                Ok((status, _)) => {
+                    last_error_response = Some(format!("{} returned HTTP {}", endpoint.id, status));
+                }
+                Err(failure) => {
+                    if let UpstreamFailure::Vault(err) = &failure {
+                        gateway.vault_guard.observe(err).await;
+                    }
+                    if failure.is_anomaly() {
+                        yield Ok(Event::default()
+                            .event("error")
+                            .data(json!({
+                                "error": "anomaly_detected",
+                                "provider": endpoint.id,
+                                "message": format!("ANOMALY: {}", failure),
+                            }).to_string()));
+                        return;
+                    }
+                    last_error_response = Some(failure.to_string());
+                }
+            }
+        }
+
         // Exhausted fallback chain
         yield Ok(Event::default()
             .event("error")
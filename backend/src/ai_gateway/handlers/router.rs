@@ -1,7 +1,7 @@
 // router.rs — AI Gateway sub-router builder and shared error helpers.
 
 use axum::Router;
-use axum::extract::Json;
+use axum::extract::{FromRequestParts, Json};
 use axum::http::StatusCode;
 use axum::routing::{get, post};
 use serde_json::{Value, json};
@@ -9,8 +9,11 @@ use serde_json::{Value, json};
 use crate::ai_gateway::{
     AiProvider, HasAiGateway,
     vault_bridge::{HasVaultBridge, VaultError},
+    vault_guard::Lockout,
 };
+use crate::extractor::OptionalAuth;
 
+use super::integrations::*;
 use super::providers::*;
 use super::proxy::*;
 
@@ -29,10 +32,18 @@ use super::proxy::*;
 /// POST /api/ai/providers/{provider}/disconnect — revoke + delete
 /// POST /api/ai/providers/{provider}/refresh  — force token refresh
 /// POST /api/ai/providers/{provider}/test     — test connection
+/// GET  /api/integrations                     — GitHub / Vercel connection state
+/// GET  /api/integrations/{integration}/status
+/// POST /api/integrations/{integration}/connect     — web flow (authorize URL)
+/// GET|POST /api/integrations/{integration}/callback — code exchange, tokens to Vault
+/// POST /api/integrations/{integration}/device      — start device flow (GitHub)
+/// POST /api/integrations/{integration}/device/poll — poll device flow
+/// POST /api/integrations/{integration}/disconnect
 /// ```
 pub fn ai_gateway_router<S>() -> Router<S>
 where
     S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+    OptionalAuth: FromRequestParts<S>,
 {
     Router::new()
         // ── Chat proxy endpoints ────────────────────────────────────────
@@ -64,6 +75,32 @@ where
             "/api/ai/providers/{provider}/test",
             post(test_provider::<S>),
         )
+        // ── Service integrations (GitHub, Vercel) ───────────────────────
+        .route("/api/integrations", get(list_integrations::<S>))
+        .route(
+            "/api/integrations/{integration}/status",
+            get(integration_status::<S>),
+        )
+        .route(
+            "/api/integrations/{integration}/connect",
+            post(connect_integration::<S>),
+        )
+        .route(
+            "/api/integrations/{integration}/callback",
+            get(integration_redirect::<S>).post(integration_callback::<S>),
+        )
+        .route(
+            "/api/integrations/{integration}/device",
+            post(start_device_login::<S>),
+        )
+        .route(
+            "/api/integrations/{integration}/device/poll",
+            post(poll_device_login::<S>),
+        )
+        .route(
+            "/api/integrations/{integration}/disconnect",
+            post(disconnect_integration::<S>),
+        )
 }
 
 // ── Helper: parse provider from path ────────────────────────────────────────
@@ -84,9 +121,23 @@ pub(crate) fn parse_provider(provider: &str) -> Result<AiProvider, (StatusCode,
     })
 }
 
+/// 503 body of a call refused by the Vault lockout.
+pub(crate) fn lockout_response(lockout: &Lockout) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": "vault_lockout",
+            "trigger": lockout.trigger,
+            "since": lockout.since.to_rfc3339(),
+            "message": lockout.to_string(),
+            "action_required": "An operator must clear the lockout: POST /api/vault/lockout/clear",
+        })),
+    )
+}
+
 /// Map a `VaultError` to an HTTP status code + JSON error body.
 pub(crate) fn vault_error_response(
-    provider: &AiProvider,
+    provider: &(impl std::fmt::Display + ?Sized),
     err: VaultError,
 ) -> (StatusCode, Json<Value>) {
     match &err {
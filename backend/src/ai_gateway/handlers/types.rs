@@ -2,6 +2,9 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::ai_gateway::health::HealthSnapshot;
+use crate::ai_gateway::token_refresh::RefreshSnapshot;
+
 // ── Request / Response Types ────────────────────────────────────────────────
 
 /// OAuth / manual auth callback payload.
@@ -11,6 +14,12 @@ pub struct CallbackPayload {
     pub state: String,
 }
 
+/// Device flow poll payload.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DevicePollPayload {
+    pub device_code: String,
+}
+
 /// Unified chat request for any provider.
 #[derive(Debug, Clone, Deserialize)]
 pub struct GatewayChatRequest {
@@ -26,13 +35,76 @@ pub struct GatewayChatRequest {
     /// Whether to stream (only relevant for the non-stream endpoint as a hint;
     /// the /stream endpoint always streams).
     pub stream: Option<bool>,
+    /// Tools the model may call (answered with `tool_use` blocks).
+    #[serde(default)]
+    pub tools: Vec<GatewayTool>,
 }
 
-/// A single chat message (role + content).
+/// A single chat message. `role` is `system`, `user` or `assistant`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayChatMessage {
     pub role: String,
-    pub content: String,
+    pub content: GatewayContent,
+}
+
+/// Message content: a plain string or a list of typed blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GatewayContent {
+    Text(String),
+    Blocks(Vec<GatewayContentBlock>),
+}
+
+impl From<&str> for GatewayContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<String> for GatewayContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// A typed content block, modelled on the Anthropic Messages API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GatewayContentBlock {
+    Text {
+        text: String,
+    },
+    /// Only in `user` messages.
+    Image {
+        source: GatewayImageSource,
+    },
+    /// A tool call made by the model; only in `assistant` messages.
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// The result of a `tool_use`; only in `user` messages.
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GatewayImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+/// A tool the model may call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayTool {
+    pub name: String,
+    pub description: Option<String>,
+    /// JSON Schema of the tool input.
+    pub input_schema: Option<serde_json::Value>,
 }
 
 /// Provider info returned by the list/status endpoints.
@@ -47,6 +119,11 @@ pub struct GatewayProviderInfo {
     pub last_verified: Option<String>,
     pub last_error: Option<String>,
     pub model_tiers: ProviderModelTiers,
+    /// Circuit breaker and rolling health of the gateway's calls.
+    pub health: HealthSnapshot,
+    /// Credential expiry and background refresh; `None` until first checked
+    /// or for providers whose credential does not expire.
+    pub token_refresh: Option<RefreshSnapshot>,
 }
 
 /// Nested model tier info for the provider info response.
@@ -67,3 +144,102 @@ pub struct TestResult {
     pub response_preview: Option<String>,
     pub error: Option<String>,
 }
+
+// ── OpenAI-compatible facade (/v1/*) ────────────────────────────────────────
+
+/// `POST /v1/chat/completions` request body (OpenAI Chat Completions).
+/// Unsupported fields are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionRequest {
+    /// Model ID, optionally prefixed with a provider (`"deepseek/deepseek-chat"`).
+    pub model: String,
+    pub messages: Vec<ChatCompletionMessage>,
+    #[serde(default)]
+    pub tools: Vec<ChatCompletionTool>,
+    /// `"auto"`, `"none"`, `"required"` or `{"type":"function","function":{"name":..}}`.
+    pub tool_choice: Option<serde_json::Value>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u32>,
+    /// Newer name for `max_tokens`; takes precedence when both are set.
+    pub max_completion_tokens: Option<u32>,
+    /// A string or a list of strings.
+    pub stop: Option<serde_json::Value>,
+    #[serde(default)]
+    pub stream: bool,
+    pub stream_options: Option<StreamOptions>,
+}
+
+impl ChatCompletionRequest {
+    pub fn max_output_tokens(&self) -> u32 {
+        self.max_completion_tokens
+            .or(self.max_tokens)
+            .unwrap_or(4096)
+    }
+
+    pub fn stop_sequences(&self) -> Vec<String> {
+        match &self.stop {
+            Some(serde_json::Value::String(s)) => vec![s.clone()],
+            Some(serde_json::Value::Array(items)) => items
+                .iter()
+                .filter_map(|s| s.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// One message of a Chat Completions conversation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionMessage {
+    /// `system`, `developer`, `user`, `assistant` or `tool`.
+    pub role: String,
+    /// A string, a list of content parts (`text` / `image_url`) or null.
+    #[serde(default)]
+    pub content: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ChatCompletionToolCall>,
+    /// Set on `tool` messages: the call this message answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatCompletionToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub kind: String,
+    pub function: ChatCompletionFunctionCall,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatCompletionFunctionCall {
+    pub name: String,
+    /// JSON-encoded arguments.
+    pub arguments: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionTool {
+    #[serde(rename = "type", default = "function_type")]
+    pub kind: String,
+    pub function: ChatCompletionFunction,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionFunction {
+    pub name: String,
+    pub description: Option<String>,
+    /// JSON Schema of the arguments.
+    pub parameters: Option<serde_json::Value>,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
@@ -0,0 +1,292 @@
+// upstream.rs — Sending native payloads to providers.
+//
+// Built-in providers go through the Vault Bouncer (or directly for
+// `AuthType::None`); OpenAI-compatible endpoints go through the Vault only
+// when they have an API key stored there, under their own namespace
+// (`ai_compatible/<id>`). Direct calls never follow redirects, and calls to
+// compatible endpoints connect only to the address checked at send time, so
+// a DNS answer changed after validation cannot redirect them to a private
+// host. Streamed `/v1` requests to links
+// called directly are opened with `open_direct_stream` and proxied as they
+// arrive; the Bouncer only returns whole bodies.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::ai_gateway::vault_bridge::{VaultClient, VaultError};
+use crate::ai_gateway::{AuthType, CompatibleProvider, ProviderConfig};
+
+/// Prefix of the Vault namespace of an OpenAI-compatible endpoint's API key.
+const COMPATIBLE_VAULT_NAMESPACE_PREFIX: &str = "ai_compatible";
+/// Timeout of direct (non-Vault) upstream calls.
+const DIRECT_TIMEOUT: Duration = Duration::from_secs(120);
+/// Timeout of a direct streamed call, body included.
+const STREAM_TIMEOUT: Duration = Duration::from_secs(600);
+
+pub(crate) enum UpstreamFailure {
+    Vault(VaultError),
+    Connection(String),
+}
+
+impl UpstreamFailure {
+    /// Vault anomalies halt the request instead of falling back.
+    pub(crate) fn is_anomaly(&self) -> bool {
+        matches!(self, Self::Vault(err) if err.is_anomaly())
+    }
+}
+
+impl fmt::Display for UpstreamFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vault(err) => write!(f, "{}", err),
+            Self::Connection(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Vault namespace holding the API key of the compatible endpoint `id`,
+/// apart from the built-in providers' `ai_providers`.
+pub(crate) fn compatible_vault_namespace(id: &str) -> String {
+    format!("{}/{}", COMPATIBLE_VAULT_NAMESPACE_PREFIX, id)
+}
+
+/// Whether `ip` is reachable on the public internet — not private, loopback,
+/// link-local, shared (CGNAT), unspecified or a unique-local IPv6 address.
+pub(crate) fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public_ip(IpAddr::V4(v4)),
+            None => {
+                let first = v6.segments()[0];
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// A client for one direct call to `url`, without redirects. Unless
+/// `allow_private`, the host is resolved now, every address must be public,
+/// and the client is pinned to the checked address.
+async fn direct_client(url: &str, allow_private: bool) -> Result<reqwest::Client, UpstreamFailure> {
+    let builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+    let build = |builder: reqwest::ClientBuilder| {
+        builder
+            .build()
+            .map_err(|e| UpstreamFailure::Connection(e.to_string()))
+    };
+    if allow_private {
+        return build(builder);
+    }
+
+    let parsed = url::Url::parse(url)
+        .map_err(|e| UpstreamFailure::Connection(format!("invalid upstream URL: {}", e)))?;
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let (domain, addrs): (Option<&str>, Vec<SocketAddr>) = match parsed.host() {
+        Some(url::Host::Domain(domain)) => {
+            let addrs = tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| {
+                    UpstreamFailure::Connection(format!("cannot resolve {}: {}", domain, e))
+                })?
+                .collect();
+            (Some(domain), addrs)
+        }
+        Some(url::Host::Ipv4(ip)) => (None, vec![SocketAddr::new(IpAddr::V4(ip), port)]),
+        Some(url::Host::Ipv6(ip)) => (None, vec![SocketAddr::new(IpAddr::V6(ip), port)]),
+        None => {
+            return Err(UpstreamFailure::Connection(
+                "upstream URL has no host".to_string(),
+            ));
+        }
+    };
+
+    if let Some(blocked) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(UpstreamFailure::Connection(format!(
+            "upstream {} resolves to non-public address {}",
+            parsed.host_str().unwrap_or_default(),
+            blocked.ip()
+        )));
+    }
+    match (domain, addrs.first()) {
+        (Some(domain), Some(addr)) => build(builder.resolve(domain, *addr)),
+        (Some(domain), None) => Err(UpstreamFailure::Connection(format!(
+            "cannot resolve {}",
+            domain
+        ))),
+        (None, _) => build(builder),
+    }
+}
+
+async fn post_direct(
+    url: &str,
+    headers: &HashMap<String, String>,
+    payload: Value,
+    timeout: Duration,
+    allow_private: bool,
+) -> Result<reqwest::Response, UpstreamFailure> {
+    let mut request = direct_client(url, allow_private)
+        .await?
+        .post(url)
+        .json(&payload)
+        .timeout(timeout);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    request
+        .send()
+        .await
+        .map_err(|e| UpstreamFailure::Connection(e.to_string()))
+}
+
+/// The body of an upstream response: JSON when it parses, otherwise the raw
+/// text as a JSON string (so error pages still reach the caller).
+pub(crate) async fn read_body(resp: reqwest::Response) -> Value {
+    let text = resp.text().await.unwrap_or_default();
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+async fn send_direct(
+    url: &str,
+    headers: &HashMap<String, String>,
+    payload: Value,
+    allow_private: bool,
+) -> Result<(u16, Value), UpstreamFailure> {
+    let resp = post_direct(url, headers, payload, DIRECT_TIMEOUT, allow_private).await?;
+    let status = resp.status().as_u16();
+    Ok((status, read_body(resp).await))
+}
+
+/// Open a streamed call to a direct (non-Vault) upstream. Only the status has
+/// been received when this returns; the body is left to the caller.
+pub(crate) async fn open_direct_stream(
+    url: &str,
+    headers: &HashMap<String, String>,
+    payload: Value,
+    allow_private: bool,
+) -> Result<reqwest::Response, UpstreamFailure> {
+    post_direct(url, headers, payload, STREAM_TIMEOUT, allow_private).await
+}
+
+/// Send a native payload to a built-in provider; returns the HTTP status and
+/// JSON body.
+pub(crate) async fn send_upstream(
+    vault: &VaultClient,
+    config: &ProviderConfig,
+    url: &str,
+    payload: Value,
+) -> Result<(u16, Value), UpstreamFailure> {
+    // Keyless built-ins (Ollama) are local by design.
+    if config.auth_type == AuthType::None {
+        return send_direct(url, &HashMap::new(), payload, true).await;
+    }
+
+    let resp = vault
+        .delegate(
+            url,
+            "POST",
+            &config.vault_namespace,
+            &config.vault_service,
+            Some(payload),
+        )
+        .await
+        .map_err(UpstreamFailure::Vault)?;
+    Ok((resp.status, resp.body))
+}
+
+/// Send an OpenAI-format payload to a generic OpenAI-compatible endpoint.
+/// `allow_private` lets direct calls reach private addresses (development).
+pub(crate) async fn send_compatible(
+    vault: &VaultClient,
+    provider: &CompatibleProvider,
+    payload: Value,
+    allow_private: bool,
+) -> Result<(u16, Value), UpstreamFailure> {
+    let Some(service) = &provider.vault_service else {
+        return send_direct(
+            &provider.upstream_url,
+            &provider.extra_headers,
+            payload,
+            allow_private,
+        )
+        .await;
+    };
+
+    let resp = vault
+        .delegate(
+            &provider.upstream_url,
+            "POST",
+            &compatible_vault_namespace(&provider.id),
+            service,
+            Some(payload),
+        )
+        .await
+        .map_err(UpstreamFailure::Vault)?;
+    Ok((resp.status, resp.body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_pass() {
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.5",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn private_targets_are_refused_at_send_time() {
+        let refused = direct_client("http://127.0.0.1:8000/v1/chat/completions", false).await;
+        assert!(matches!(refused, Err(UpstreamFailure::Connection(_))));
+        let refused = direct_client("http://[::1]/v1/chat/completions", false).await;
+        assert!(matches!(refused, Err(UpstreamFailure::Connection(_))));
+        let refused = direct_client("http://localhost:8000/v1/chat/completions", false).await;
+        assert!(matches!(refused, Err(UpstreamFailure::Connection(_))));
+        assert!(
+            direct_client("http://127.0.0.1:8000/v1/chat/completions", true)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn compatible_keys_have_their_own_namespace() {
+        assert_eq!(
+            compatible_vault_namespace("local-vllm"),
+            "ai_compatible/local-vllm"
+        );
+    }
+}
@@ -0,0 +1,546 @@
+// health.rs — Per-provider circuit breakers and rolling health scores.
+//
+// Every upstream attempt of the gateway handlers (and every probe) is fed in
+// through `AiGatewayState::record`. A provider whose calls fail
+// `FAILURE_THRESHOLD` times in a row (no response, timeout or HTTP 5xx) has
+// its breaker opened: live traffic skips it instead of waiting for another
+// upstream timeout. When the open period ends the breaker goes half-open and
+// `spawn_prober` sends the provider the `test_provider` payload; success
+// closes the breaker, failure re-opens it for twice as long.
+//
+// The health score (success rate weighted by p95 latency over recent calls)
+// ranks the fallbacks of `ModelRouter`'s static chain. Breaker state and
+// transitions are exposed on `/api/ai/providers` and as Prometheus metrics.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::handlers::helpers::{build_test_payload, resolve_upstream_url};
+use super::handlers::upstream::send_upstream;
+use super::vault_bridge::HasVaultBridge;
+use super::{AiProvider, HasAiGateway};
+
+/// Consecutive failures that open a breaker.
+const FAILURE_THRESHOLD: u32 = 3;
+const BASE_OPEN: Duration = Duration::from_secs(30);
+const MAX_OPEN: Duration = Duration::from_secs(10 * 60);
+/// Calls kept for the health score.
+const SAMPLE_WINDOW: Duration = Duration::from_secs(15 * 60);
+const MAX_SAMPLES: usize = 100;
+/// p95 latency at which the score is halved.
+const SLOW_P95_MS: u64 = 60_000;
+/// Score of a provider without recent calls.
+const UNKNOWN_SCORE: f64 = 0.75;
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Breaker
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    /// Skipped by live traffic until the open period ends.
+    Open,
+    /// Waiting for a probe to close or re-open it.
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub const ALL: [Self; 3] = [Self::Closed, Self::Open, Self::HalfOpen];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half_open",
+        }
+    }
+
+    /// Gauge value: 0 closed, 1 half-open, 2 open.
+    fn gauge(self) -> u8 {
+        match self {
+            Self::Closed => 0,
+            Self::HalfOpen => 1,
+            Self::Open => 2,
+        }
+    }
+}
+
+/// Outcome of one call, as seen by the breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The provider answered (including client errors such as HTTP 400).
+    Ok,
+    /// No response, timeout or server error.
+    Failed,
+    /// Says nothing about availability (Vault errors, 401/403, 429).
+    Ignored,
+}
+
+impl Outcome {
+    /// Classify an upstream status; 0 means no response was received.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            0 | 408 | 500..=599 => Self::Failed,
+            401 | 403 | 429 => Self::Ignored,
+            _ => Self::Ok,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ProviderHealthState {
+    breaker: BreakerState,
+    open_until: Option<Instant>,
+    open_for: Duration,
+    consecutive_failures: u32,
+    /// `(at, ok, latency_ms)` of recent calls.
+    samples: VecDeque<(Instant, bool, u64)>,
+    last_error: Option<(DateTime<Utc>, String)>,
+    last_transition: Option<DateTime<Utc>>,
+    transitions: HashMap<BreakerState, u64>,
+}
+
+impl Default for ProviderHealthState {
+    fn default() -> Self {
+        Self {
+            breaker: BreakerState::Closed,
+            open_until: None,
+            open_for: BASE_OPEN,
+            consecutive_failures: 0,
+            samples: VecDeque::new(),
+            last_error: None,
+            last_transition: None,
+            transitions: HashMap::new(),
+        }
+    }
+}
+
+impl ProviderHealthState {
+    fn transition(&mut self, provider: AiProvider, to: BreakerState, now: Instant) {
+        if self.breaker == to {
+            return;
+        }
+        tracing::info!(
+            provider = %provider,
+            from = self.breaker.as_str(),
+            to = to.as_str(),
+            "health: circuit breaker transition",
+        );
+        self.breaker = to;
+        self.open_until = (to == BreakerState::Open).then(|| now + self.open_for);
+        self.last_transition = Some(Utc::now());
+        *self.transitions.entry(to).or_default() += 1;
+    }
+
+    fn record(
+        &mut self,
+        provider: AiProvider,
+        outcome: Outcome,
+        latency_ms: u64,
+        error: Option<String>,
+        now: Instant,
+    ) {
+        while self.samples.len() >= MAX_SAMPLES
+            || self
+                .samples
+                .front()
+                .is_some_and(|(at, ..)| now.duration_since(*at) > SAMPLE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        match outcome {
+            Outcome::Ok => {
+                self.samples.push_back((now, true, latency_ms));
+                self.consecutive_failures = 0;
+                self.open_for = BASE_OPEN;
+                self.transition(provider, BreakerState::Closed, now);
+            }
+            Outcome::Failed => {
+                self.samples.push_back((now, false, latency_ms));
+                self.consecutive_failures += 1;
+                if let Some(error) = error {
+                    self.last_error = Some((Utc::now(), error));
+                }
+                match self.breaker {
+                    BreakerState::Closed if self.consecutive_failures >= FAILURE_THRESHOLD => {
+                        self.transition(provider, BreakerState::Open, now);
+                    }
+                    BreakerState::HalfOpen => {
+                        self.open_for = (self.open_for * 2).min(MAX_OPEN);
+                        self.transition(provider, BreakerState::Open, now);
+                    }
+                    _ => {}
+                }
+            }
+            Outcome::Ignored => {
+                if let Some(error) = error {
+                    self.last_error = Some((Utc::now(), error));
+                }
+            }
+        }
+    }
+
+    /// Success rate weighted by p95 latency; `None` without recent calls.
+    fn score(&self, now: Instant) -> (Option<f64>, Option<f64>, Option<u64>) {
+        let recent: Vec<_> = self
+            .samples
+            .iter()
+            .filter(|(at, ..)| now.duration_since(*at) <= SAMPLE_WINDOW)
+            .collect();
+        if recent.is_empty() {
+            return (None, None, None);
+        }
+        let ok = recent.iter().filter(|(_, ok, _)| *ok).count();
+        let success_rate = ok as f64 / recent.len() as f64;
+
+        let mut latencies: Vec<u64> = recent
+            .iter()
+            .filter(|(_, ok, _)| *ok)
+            .map(|(.., ms)| *ms)
+            .collect();
+        latencies.sort_unstable();
+        let p95 = (!latencies.is_empty())
+            .then(|| latencies[(latencies.len() * 95).div_ceil(100).saturating_sub(1)]);
+
+        let slowness = p95.map_or(0.0, |ms| (ms as f64 / SLOW_P95_MS as f64).min(1.0));
+        let score = success_rate * (1.0 - 0.5 * slowness);
+        (Some(score), Some(success_rate), p95)
+    }
+}
+
+/// Health of one provider, as reported by `/api/ai/providers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSnapshot {
+    pub breaker: BreakerState,
+    /// Seconds until an open breaker goes half-open.
+    pub open_for_secs: Option<u64>,
+    pub consecutive_failures: u32,
+    /// 0..1; `None` without calls in the last 15 minutes.
+    pub score: Option<f64>,
+    pub success_rate: Option<f64>,
+    pub p95_latency_ms: Option<u64>,
+    pub calls: usize,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_transition_at: Option<DateTime<Utc>>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Registry
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Circuit breakers and health scores of the built-in providers.
+#[derive(Debug, Default)]
+pub struct ProviderHealth {
+    providers: Mutex<HashMap<AiProvider, ProviderHealthState>>,
+}
+
+impl ProviderHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the outcome of a call to `provider`.
+    pub fn record(
+        &self,
+        provider: AiProvider,
+        outcome: Outcome,
+        latency_ms: u64,
+        error: Option<String>,
+    ) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        providers.entry(provider).or_default().record(
+            provider,
+            outcome,
+            latency_ms,
+            error,
+            Instant::now(),
+        );
+    }
+
+    /// Whether live traffic may use `provider`.
+    pub fn is_available(&self, provider: AiProvider) -> bool {
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        providers
+            .get(&provider)
+            .is_none_or(|h| h.breaker == BreakerState::Closed)
+    }
+
+    pub fn snapshot(&self, provider: AiProvider) -> HealthSnapshot {
+        let now = Instant::now();
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let default = ProviderHealthState::default();
+        let health = providers.get(&provider).unwrap_or(&default);
+        let (score, success_rate, p95_latency_ms) = health.score(now);
+        HealthSnapshot {
+            breaker: health.breaker,
+            open_for_secs: health
+                .open_until
+                .map(|until| until.saturating_duration_since(now).as_secs()),
+            consecutive_failures: health.consecutive_failures,
+            score,
+            success_rate,
+            p95_latency_ms,
+            calls: health.samples.len(),
+            last_error: health.last_error.as_ref().map(|(_, e)| e.clone()),
+            last_error_at: health.last_error.as_ref().map(|(at, _)| *at),
+            last_transition_at: health.last_transition,
+        }
+    }
+
+    /// Rank a fallback chain: providers with an open (or half-open) breaker
+    /// are dropped, `primary` stays first when available, and the fallbacks
+    /// are ordered by health score (ties keep the static order).
+    pub fn rank(&self, primary: AiProvider, chain: Vec<AiProvider>) -> Vec<AiProvider> {
+        let now = Instant::now();
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let available = |p: &AiProvider| {
+            providers
+                .get(p)
+                .is_none_or(|h| h.breaker == BreakerState::Closed)
+        };
+        let score = |p: &AiProvider| {
+            providers
+                .get(p)
+                .and_then(|h| h.score(now).0)
+                .unwrap_or(UNKNOWN_SCORE)
+        };
+
+        let skipped: Vec<_> = chain.iter().filter(|p| !available(p)).copied().collect();
+        if !skipped.is_empty() {
+            tracing::info!(?skipped, "health: providers with an open circuit skipped");
+        }
+
+        let mut fallbacks: Vec<AiProvider> = chain
+            .into_iter()
+            .filter(|p| *p != primary && available(p))
+            .collect();
+        fallbacks.sort_by(|a, b| score(b).total_cmp(&score(a)));
+
+        available(&primary)
+            .then_some(primary)
+            .into_iter()
+            .chain(fallbacks)
+            .collect()
+    }
+
+    /// Move open breakers whose period ended to half-open and return them
+    /// for probing.
+    fn due_probes(&self) -> Vec<AiProvider> {
+        let now = Instant::now();
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let mut due = Vec::new();
+        for (provider, health) in providers.iter_mut() {
+            if health.breaker == BreakerState::Open
+                && health.open_until.is_some_and(|until| until <= now)
+            {
+                health.transition(*provider, BreakerState::HalfOpen, now);
+                due.push(*provider);
+            }
+        }
+        due
+    }
+
+    pub fn prometheus_output(&self) -> String {
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let mut names: Vec<_> = providers.keys().copied().collect();
+        names.sort_by_key(|p| p.to_string());
+
+        let mut state = String::from(
+            "# HELP claudehydra_gateway_breaker_state Provider circuit breaker (0 closed, 1 half-open, 2 open).\n\
+             # TYPE claudehydra_gateway_breaker_state gauge\n",
+        );
+        let mut transitions = String::from(
+            "# HELP claudehydra_gateway_breaker_transitions_total Circuit breaker transitions by target state.\n\
+             # TYPE claudehydra_gateway_breaker_transitions_total counter\n",
+        );
+        let mut score = String::from(
+            "# HELP claudehydra_gateway_provider_health_score Success rate weighted by p95 latency (0-1).\n\
+             # TYPE claudehydra_gateway_provider_health_score gauge\n",
+        );
+        for provider in names {
+            let health = &providers[&provider];
+            state.push_str(&format!(
+                "claudehydra_gateway_breaker_state{{provider=\"{}\"}} {}\n",
+                provider,
+                health.breaker.gauge()
+            ));
+            for to in BreakerState::ALL {
+                transitions.push_str(&format!(
+                    "claudehydra_gateway_breaker_transitions_total{{provider=\"{}\",to=\"{}\"}} {}\n",
+                    provider,
+                    to.as_str(),
+                    health.transitions.get(&to).copied().unwrap_or(0)
+                ));
+            }
+            if let (Some(value), ..) = health.score(now) {
+                score.push_str(&format!(
+                    "claudehydra_gateway_provider_health_score{{provider=\"{}\"}} {:.3}\n",
+                    provider, value
+                ));
+            }
+        }
+        state + &transitions + &score
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Half-open probes
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Probe half-open providers with the `test_provider` payload every 10 s.
+pub fn spawn_prober<S>(state: S) -> tokio::task::JoinHandle<()>
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(PROBE_INTERVAL).await;
+            if state.ai_gateway().vault_guard.check().is_err() {
+                continue;
+            }
+            for provider in state.ai_gateway().health.due_probes() {
+                probe(&state, provider).await;
+            }
+        }
+    })
+}
+
+async fn probe<S>(state: &S, provider: AiProvider)
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let health = &state.ai_gateway().health;
+    let Some(config) = state.provider_config(provider) else {
+        // Disabled meanwhile — forget the breaker.
+        health
+            .providers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&provider);
+        return;
+    };
+
+    let model = config.model_tiers.executor.clone();
+    let url = resolve_upstream_url(&config.upstream_url, &model);
+    let started = Instant::now();
+    let result = send_upstream(
+        state.vault_client(),
+        &config,
+        &url,
+        build_test_payload(&provider, &model),
+    )
+    .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let (outcome, error) = match result {
+        Ok((status, _)) => (
+            Outcome::from_status(status),
+            Some(format!("probe: upstream returned HTTP {}", status)),
+        ),
+        Err(failure) => (Outcome::Failed, Some(format!("probe: {}", failure))),
+    };
+    // A probe that says nothing about availability counts as a failure, so
+    // the breaker does not stay half-open forever.
+    let outcome = match outcome {
+        Outcome::Ignored => Outcome::Failed,
+        outcome => outcome,
+    };
+    tracing::info!(provider = %provider, ?outcome, latency_ms, "health: half-open probe");
+    health.record(
+        provider,
+        outcome,
+        latency_ms,
+        error.filter(|_| outcome == Outcome::Failed),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN: [AiProvider; 3] = [
+        AiProvider::Anthropic,
+        AiProvider::OpenAI,
+        AiProvider::Google,
+    ];
+
+    #[test]
+    fn breaker_opens_after_consecutive_failures_and_probe_closes_it() {
+        let health = ProviderHealth::new();
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            health.record(AiProvider::OpenAI, Outcome::Failed, 120_000, None);
+        }
+        assert!(health.is_available(AiProvider::OpenAI));
+
+        health.record(
+            AiProvider::OpenAI,
+            Outcome::Failed,
+            120_000,
+            Some("timeout".into()),
+        );
+        let snapshot = health.snapshot(AiProvider::OpenAI);
+        assert_eq!(snapshot.breaker, BreakerState::Open);
+        assert_eq!(snapshot.last_error.as_deref(), Some("timeout"));
+        assert_eq!(
+            health.rank(AiProvider::Anthropic, CHAIN.to_vec()),
+            [AiProvider::Anthropic, AiProvider::Google]
+        );
+
+        // Open period over: half-open, then a failed probe doubles it.
+        {
+            let mut providers = health.providers.lock().unwrap();
+            providers.get_mut(&AiProvider::OpenAI).unwrap().open_until = Some(Instant::now());
+        }
+        assert_eq!(health.due_probes(), [AiProvider::OpenAI]);
+        assert!(!health.is_available(AiProvider::OpenAI));
+        health.record(AiProvider::OpenAI, Outcome::Failed, 10, None);
+        assert_eq!(
+            health.snapshot(AiProvider::OpenAI).breaker,
+            BreakerState::Open
+        );
+        assert_eq!(
+            health.providers.lock().unwrap()[&AiProvider::OpenAI].open_for,
+            BASE_OPEN * 2
+        );
+
+        health.record(AiProvider::OpenAI, Outcome::Ok, 800, None);
+        assert!(health.is_available(AiProvider::OpenAI));
+        assert!(health.prometheus_output().contains(
+            "claudehydra_gateway_breaker_transitions_total{provider=\"openai\",to=\"open\"} 2"
+        ));
+    }
+
+    #[test]
+    fn fallbacks_ranked_by_health_score() {
+        let health = ProviderHealth::new();
+        // OpenAI answers but slowly and half the time; Google is fast.
+        health.record(AiProvider::OpenAI, Outcome::Ok, 50_000, None);
+        health.record(AiProvider::OpenAI, Outcome::Failed, 0, None);
+        health.record(AiProvider::Google, Outcome::Ok, 900, None);
+        // Ignored outcomes neither count as calls nor trip the breaker.
+        health.record(AiProvider::Anthropic, Outcome::Ignored, 0, None);
+
+        assert_eq!(
+            health.rank(AiProvider::Anthropic, CHAIN.to_vec()),
+            [
+                AiProvider::Anthropic,
+                AiProvider::Google,
+                AiProvider::OpenAI
+            ]
+        );
+        let snapshot = health.snapshot(AiProvider::OpenAI);
+        assert_eq!(snapshot.success_rate, Some(0.5));
+        assert_eq!(snapshot.p95_latency_ms, Some(50_000));
+        assert_eq!(health.snapshot(AiProvider::Anthropic).calls, 0);
+    }
+}
@@ -2,15 +2,22 @@
 // Strategia STRICT_PLAN_ONLY: zero API billing, wszystko przez subskrypcje konsumenckie
 
 pub mod handlers;
+pub mod health;
 pub mod model_router;
 pub mod oauth_flows;
 pub mod session_manager;
+pub mod token_refresh;
+pub mod usage;
 pub mod vault_bridge;
+pub mod vault_guard;
 pub mod vault_handlers;
 
 use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 
+use futures_util::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 
 // ── Re-exports from submodules ────────────────────────────────────────────────
@@ -87,6 +94,108 @@ pub struct ProviderConfig {
     pub monthly_cost_cents: u32,
     /// Default model tiers for this provider.
     pub model_tiers: ModelTiers,
+    /// Estimated plan rate limit; `None` for pay-per-use and local providers.
+    #[serde(default)]
+    pub quota: Option<PlanQuota>,
+}
+
+// ── PlanQuota ─────────────────────────────────────────────────────────────────
+
+/// Rolling-window usage limit of a subscription plan. Vendors don't publish
+/// exact numbers, so these are estimates — editable per provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanQuota {
+    /// Length of the rolling window in seconds.
+    pub window_secs: u64,
+    /// Successful requests allowed per window.
+    pub max_requests: Option<u32>,
+    /// Tokens (input + output) allowed per window.
+    pub max_tokens: Option<u64>,
+}
+
+// ── CompatibleProvider ────────────────────────────────────────────────────────
+
+/// A generic endpoint speaking the OpenAI Chat Completions protocol (vLLM,
+/// LM Studio, llama.cpp server, ...). Configured at runtime; tried after the
+/// built-in providers in the fallback chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompatibleProvider {
+    /// Unique slug (e.g. "local-vllm"); never a built-in provider name.
+    pub id: String,
+    /// Human-readable name.
+    pub name: String,
+    /// Full chat completions URL (e.g. "http://10.0.0.5:8000/v1/chat/completions").
+    pub upstream_url: String,
+    /// Extra headers sent on direct (keyless) calls.
+    pub extra_headers: HashMap<String, String>,
+    /// Models served for each tier.
+    pub model_tiers: ModelTiers,
+    /// Vault service holding the API key (namespace `ai_compatible/<id>`);
+    /// `None` for endpoints without authentication.
+    pub vault_service: Option<String>,
+    /// Position in the fallback chain (lower first).
+    pub priority: i32,
+}
+
+impl CompatibleProvider {
+    /// Whether `model` is one of this endpoint's tier models.
+    pub fn serves(&self, model: &str) -> bool {
+        let tiers = &self.model_tiers;
+        [&tiers.commander, &tiers.coordinator, &tiers.executor]
+            .iter()
+            .any(|m| m.as_str() == model)
+    }
+}
+
+// ── GatewayProviders ──────────────────────────────────────────────────────────
+
+/// The enabled provider set: built-in providers (factory defaults with runtime
+/// overrides applied) and generic OpenAI-compatible endpoints.
+#[derive(Debug, Clone, Default)]
+pub struct GatewayProviders {
+    /// Enabled built-in providers; a disabled provider is absent.
+    pub builtin: HashMap<AiProvider, ProviderConfig>,
+    /// Enabled OpenAI-compatible endpoints in fallback order.
+    pub compatible: Vec<CompatibleProvider>,
+}
+
+impl GatewayProviders {
+    /// Factory defaults only (no runtime configuration).
+    pub fn defaults() -> Self {
+        Self {
+            builtin: default_provider_configs(),
+            compatible: Vec::new(),
+        }
+    }
+
+    /// Config of an enabled built-in provider.
+    pub fn get(&self, provider: &AiProvider) -> Option<&ProviderConfig> {
+        self.builtin.get(provider)
+    }
+
+    /// An enabled OpenAI-compatible endpoint by id.
+    pub fn compatible(&self, id: &str) -> Option<&CompatibleProvider> {
+        self.compatible.iter().find(|p| p.id == id)
+    }
+}
+
+/// Live provider set, swapped wholesale when the runtime configuration changes.
+/// Readers take a cheap `Arc` snapshot, so a request sees one consistent set.
+#[derive(Debug, Default)]
+pub struct ProviderRegistry(RwLock<Arc<GatewayProviders>>);
+
+impl ProviderRegistry {
+    pub fn new(providers: GatewayProviders) -> Self {
+        Self(RwLock::new(Arc::new(providers)))
+    }
+
+    pub fn current(&self) -> Arc<GatewayProviders> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn replace(&self, providers: GatewayProviders) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(providers);
+    }
 }
 
 // ── AiGatewayState ────────────────────────────────────────────────────────────
@@ -94,12 +203,59 @@ pub struct ProviderConfig {
 /// Central gateway state holding all provider configurations and the Vault client.
 /// Stored in `AppState` and accessed via the `HasAiGateway` trait.
 pub struct AiGatewayState {
-    /// Provider configurations indexed by provider enum.
-    pub providers: HashMap<AiProvider, ProviderConfig>,
+    /// Provider configurations, replaced when the stored configuration changes.
+    pub providers: ProviderRegistry,
     /// Client for communicating with Jaskier Vault (The Sentinel).
     pub vault_client: vault_bridge::VaultClient,
     /// Unified OAuth PKCE flow manager for all providers.
     pub oauth_manager: oauth_flows::OAuthFlowManager,
+    /// Usage ledger and plan quota estimates.
+    pub usage: usage::UsageLedger,
+    /// Circuit breakers and health scores of the built-in providers.
+    pub health: health::ProviderHealth,
+    /// Expiry tracking and background refresh of provider credentials.
+    pub token_refresh: token_refresh::TokenRefresh,
+    /// Credential-source policy, Vault session cache and anomaly lockout.
+    pub vault_guard: vault_guard::VaultGuard,
+    /// Whether direct calls to OpenAI-compatible endpoints may reach private
+    /// and loopback addresses — outside production only, as for MCP URLs.
+    pub allow_private_upstreams: bool,
+}
+
+impl AiGatewayState {
+    /// Snapshot of the enabled providers.
+    pub fn providers(&self) -> Arc<GatewayProviders> {
+        self.providers.current()
+    }
+
+    /// `ModelRouter`'s fallback chain for `primary`: providers with an open
+    /// circuit skipped, fallbacks ranked by health, providers near their plan
+    /// limit last.
+    pub fn fallback_chain(
+        &self,
+        primary: AiProvider,
+        providers: &GatewayProviders,
+    ) -> Vec<AiProvider> {
+        let chain = ModelRouter::new().fallback_chain(primary);
+        self.usage
+            .order_chain(self.health.rank(primary, chain), providers)
+    }
+
+    /// Record an upstream attempt in the usage ledger and, for built-in
+    /// providers, in their health.
+    pub fn record(&self, record: usage::UsageRecord) {
+        if let Ok(provider) = AiProvider::from_str(&record.provider) {
+            let outcome = health::Outcome::from_status(record.status);
+            let error = match record.status {
+                0 => record.error.clone(),
+                200..=299 => None,
+                status => Some(format!("upstream returned HTTP {}", status)),
+            };
+            self.health
+                .record(provider, outcome, record.latency_ms, error);
+        }
+        self.usage.record(record);
+    }
 }
 
 // ── HasAiGateway trait ────────────────────────────────────────────────────────
@@ -110,15 +266,22 @@ pub trait HasAiGateway: Send + Sync + 'static {
     /// Returns a reference to the AI Gateway state.
     fn ai_gateway(&self) -> &AiGatewayState;
 
-    /// Convenience: look up a single provider's config.
-    fn provider_config(&self, provider: AiProvider) -> Option<&ProviderConfig> {
-        self.ai_gateway().providers.get(&provider)
+    /// Convenience: look up a single enabled provider's config.
+    fn provider_config(&self, provider: AiProvider) -> Option<ProviderConfig> {
+        self.ai_gateway().providers().get(&provider).cloned()
     }
 
     /// Access the unified OAuth PKCE flow manager.
     fn oauth_manager(&self) -> &oauth_flows::OAuthFlowManager {
         &self.ai_gateway().oauth_manager
     }
+
+    /// Called by the token refresh scheduler when a credential was refreshed,
+    /// failed to refresh, is about to expire or was disconnected. Override to
+    /// audit and notify; the default only relies on the scheduler's logs.
+    fn credential_event(&self, _event: token_refresh::RefreshEvent) -> BoxFuture<'_, ()> {
+        Box::pin(async {})
+    }
 }
 
 // ── Default provider configs ──────────────────────────────────────────────────
@@ -127,7 +290,8 @@ pub trait HasAiGateway: Send + Sync + 'static {
 /// Skarbiec Krasnali auth-rebuild-plan (STRICT_PLAN_ONLY strategy).
 ///
 /// These are the "factory defaults" — actual runtime state may differ
-/// once providers are connected/disconnected via the Settings UI.
+/// once providers are connected/disconnected via the Settings UI, and
+/// `ch_gateway_providers` rows override them (see `gateway_providers`).
 pub fn default_provider_configs() -> HashMap<AiProvider, ProviderConfig> {
     let mut configs = HashMap::with_capacity(6);
 
@@ -156,6 +320,12 @@ pub fn default_provider_configs() -> HashMap<AiProvider, ProviderConfig> {
                 coordinator: "claude-sonnet-4-6".to_string(),
                 executor: "claude-haiku-4-5-20251001".to_string(),
             },
+            // Max 5x: ~225 messages per 5 hours.
+            quota: Some(PlanQuota {
+                window_secs: 5 * 3600,
+                max_requests: Some(225),
+                max_tokens: None,
+            }),
         },
     );
 
@@ -178,6 +348,12 @@ pub fn default_provider_configs() -> HashMap<AiProvider, ProviderConfig> {
                 coordinator: "gpt-4o-mini".to_string(),
                 executor: "gpt-4o-mini".to_string(),
             },
+            // Plus: ~80 GPT-4o messages per 3 hours.
+            quota: Some(PlanQuota {
+                window_secs: 3 * 3600,
+                max_requests: Some(80),
+                max_tokens: None,
+            }),
         },
     );
 
@@ -202,6 +378,12 @@ pub fn default_provider_configs() -> HashMap<AiProvider, ProviderConfig> {
                 coordinator: "gemini-2.5-flash-preview-05-20".to_string(),
                 executor: "gemini-2.0-flash".to_string(),
             },
+            // Advanced: ~100 prompts per day on the Pro model.
+            quota: Some(PlanQuota {
+                window_secs: 24 * 3600,
+                max_requests: Some(100),
+                max_tokens: None,
+            }),
         },
     );
 
@@ -227,6 +409,12 @@ pub fn default_provider_configs() -> HashMap<AiProvider, ProviderConfig> {
                 coordinator: "grok-3-mini".to_string(),
                 executor: "grok-3-mini-fast".to_string(),
             },
+            // Premium+: ~100 Grok queries per 2 hours.
+            quota: Some(PlanQuota {
+                window_secs: 2 * 3600,
+                max_requests: Some(100),
+                max_tokens: None,
+            }),
         },
     );
 
@@ -249,6 +437,7 @@ pub fn default_provider_configs() -> HashMap<AiProvider, ProviderConfig> {
                 coordinator: "deepseek-chat".to_string(),
                 executor: "deepseek-chat".to_string(),
             },
+            quota: None,
         },
     );
 
@@ -271,6 +460,7 @@ pub fn default_provider_configs() -> HashMap<AiProvider, ProviderConfig> {
                 coordinator: "llama3.1:8b".to_string(),
                 executor: "llama3.1:8b".to_string(),
             },
+            quota: None,
         },
     );
 
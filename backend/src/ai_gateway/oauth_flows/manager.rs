@@ -5,9 +5,9 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use serde_json::Value;
-use tokio::sync::RwLock;
 
 use super::pkce::{parse_token_response, random_base64url, sha256_base64url};
+use super::store::{MemoryPkceStore, PkceStateStore};
 use super::types::*;
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -32,16 +32,29 @@ const GOOGLE_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform \
     https://www.googleapis.com/auth/userinfo.email \
     https://www.googleapis.com/auth/userinfo.profile";
 
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Provider constants — GitHub / Vercel (service integrations)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+const GITHUB_AUTHORIZE_URL: &str = "https://github.com/login/oauth/authorize";
+const GITHUB_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const GITHUB_DEVICE_CODE_URL: &str = "https://github.com/login/device/code";
+const GITHUB_SCOPE: &str = "repo read:user";
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+const VERCEL_TOKEN_URL: &str = "https://api.vercel.com/v2/oauth/access_token";
+
 // ═══════════════════════════════════════════════════════════════════════════════
 //  OAuthFlowManager
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Unified OAuth flow manager. Holds per-provider configs and ephemeral PKCE
-/// states. Thread-safe — designed to live inside `Arc<AppState>`.
+/// Unified OAuth flow manager. Holds per-provider configs; pending PKCE
+/// states live in a `PkceStateStore` (process memory unless replaced with
+/// `with_pkce_store`). Thread-safe — designed to live inside `Arc<AppState>`.
 #[derive(Debug, Clone)]
 pub struct OAuthFlowManager {
     /// Pending PKCE states keyed by the random `state` parameter.
-    pub(crate) pkce_states: Arc<RwLock<HashMap<String, PkceState>>>,
+    pkce_store: Arc<dyn PkceStateStore>,
     /// Static provider configurations (populated at construction time).
     provider_configs: HashMap<OAuthProvider, OAuthProviderConfig>,
     /// Shared HTTP client for token exchange / refresh requests.
@@ -64,18 +77,32 @@ impl OAuthFlowManager {
         // Anthropic — always available (hardcoded client_id, no client_secret).
         provider_configs.insert(OAuthProvider::Anthropic, Self::default_anthropic_config());
 
-        // Google — available only when env vars are set.
-        if let Some(cfg) = Self::default_google_config() {
-            provider_configs.insert(OAuthProvider::Google, cfg);
+        // Google, GitHub, Vercel — available only when env vars are set.
+        for cfg in [
+            Self::default_google_config(),
+            Self::default_github_config(),
+            Self::default_vercel_config(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            provider_configs.insert(cfg.provider, cfg);
         }
 
         Self {
-            pkce_states: Arc::new(RwLock::new(HashMap::new())),
+            pkce_store: Arc::new(MemoryPkceStore::default()),
             provider_configs,
             http_client,
         }
     }
 
+    /// Keep pending PKCE states in `store` (e.g. Postgres, shared by all
+    /// replicas) instead of process memory.
+    pub fn with_pkce_store(mut self, store: Arc<dyn PkceStateStore>) -> Self {
+        self.pkce_store = store;
+        self
+    }
+
     /// Register (or replace) a provider configuration at runtime.
     /// Used for GitHub / Vercel whose client_id/secret may come from Vault.
     pub fn register_provider(&mut self, config: OAuthProviderConfig) {
@@ -147,19 +174,18 @@ impl OAuthFlowManager {
             }
         }
 
-        // Store PKCE state (prune expired first).
-        {
-            let mut states = self.pkce_states.write().await;
-            states.retain(|_, s| s.created_at.elapsed() < PKCE_STATE_TTL);
-            states.insert(
-                state.clone(),
+        // Store PKCE state.
+        self.pkce_store
+            .insert(
+                &state,
                 PkceState {
                     code_verifier,
                     provider,
                     created_at: std::time::Instant::now(),
                 },
-            );
-        }
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to store OAuth state: {e}"))?;
 
         tracing::info!(provider = %provider, "OAuth login initiated");
 
@@ -183,13 +209,10 @@ impl OAuthFlowManager {
         code: &str,
     ) -> anyhow::Result<(OAuthProvider, OAuthTokens)> {
         // Consume PKCE state (validates + removes atomically).
-        let pkce = {
-            let mut states = self.pkce_states.write().await;
-            match states.remove(state) {
-                Some(s) if s.created_at.elapsed() < PKCE_STATE_TTL => s,
-                Some(_) => anyhow::bail!("OAuth state expired (older than 10 min)"),
-                None => anyhow::bail!("Invalid or already-consumed OAuth state"),
-            }
+        let pkce = match self.pkce_store.take(state).await? {
+            Some(s) if s.created_at.elapsed() < PKCE_STATE_TTL => s,
+            Some(_) => anyhow::bail!("OAuth state expired (older than 10 min)"),
+            None => anyhow::bail!("Invalid or already-consumed OAuth state"),
         };
 
         let provider = pkce.provider;
@@ -219,6 +242,96 @@ impl OAuthFlowManager {
         Ok((provider, tokens))
     }
 
+    // ── Device flow (GitHub) ───────────────────────────────────────────────
+
+    /// Start a device authorization (RFC 8628) — for machines without a
+    /// browser that can reach the redirect URI. Only GitHub supports it.
+    pub async fn start_device_login(
+        &self,
+        provider: OAuthProvider,
+    ) -> anyhow::Result<DeviceLoginResponse> {
+        let config = self
+            .provider_configs
+            .get(&provider)
+            .ok_or_else(|| anyhow::anyhow!("Provider {provider} is not configured"))?;
+        if provider != OAuthProvider::GitHub {
+            anyhow::bail!("Provider {provider} does not support the device flow");
+        }
+
+        let scope = config.scopes.join(" ");
+        let resp = self
+            .http_client
+            .post(GITHUB_DEVICE_CODE_URL)
+            .header("accept", "application/json")
+            .form(&[("client_id", config.client_id.as_str()), ("scope", &scope)])
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("{provider} device code request failed: {e}"))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let err = resp.text().await.unwrap_or_default();
+            anyhow::bail!("{provider} device code request rejected ({status}): {err}");
+        }
+
+        let raw: Value = resp
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("Invalid JSON from {provider} device endpoint: {e}"))?;
+        if let Some(error) = raw.get("error").and_then(Value::as_str) {
+            anyhow::bail!("{provider} device code request returned {error}");
+        }
+
+        tracing::info!(provider = %provider, "OAuth device login initiated");
+        serde_json::from_value(raw)
+            .map_err(|e| anyhow::anyhow!("Unexpected {provider} device code response: {e}"))
+    }
+
+    /// Poll the token endpoint once for a device authorization. Errors when
+    /// the user denied access or the device code expired.
+    pub async fn poll_device_login(
+        &self,
+        provider: OAuthProvider,
+        device_code: &str,
+    ) -> anyhow::Result<DevicePoll> {
+        let config = self
+            .provider_configs
+            .get(&provider)
+            .ok_or_else(|| anyhow::anyhow!("Provider {provider} is not configured"))?;
+
+        let resp = self
+            .http_client
+            .post(&config.token_url)
+            .header("accept", "application/json")
+            .form(&[
+                ("client_id", config.client_id.as_str()),
+                ("device_code", device_code),
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+            ])
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("{provider} device token request failed: {e}"))?;
+
+        let raw: Value = resp
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("Invalid JSON from {provider} token endpoint: {e}"))?;
+
+        match raw.get("error").and_then(Value::as_str) {
+            Some("authorization_pending") => Ok(DevicePoll::Pending),
+            Some("slow_down") => Ok(DevicePoll::SlowDown {
+                interval: raw.get("interval").and_then(Value::as_i64).unwrap_or(10),
+            }),
+            _ => {
+                let tokens = parse_token_response(raw, &config.scopes)?;
+                tracing::info!(provider = %provider, "OAuth device login completed");
+                Ok(DevicePoll::Complete(tokens))
+            }
+        }
+    }
+
     // ── Token refresh ──────────────────────────────────────────────────────
 
     /// Refresh an OAuth access token using the stored refresh_token.
@@ -227,7 +340,7 @@ impl OAuthFlowManager {
         &self,
         provider: OAuthProvider,
         refresh_token: &str,
-    ) -> anyhow::Result<OAuthTokens> {
+    ) -> Result<OAuthTokens, RefreshError> {
         let config = self
             .provider_configs
             .get(&provider)
@@ -253,18 +366,19 @@ impl OAuthFlowManager {
     /// called periodically (e.g. from a background timer) or inline before
     /// inserting new states.
     pub async fn cleanup_expired_states(&self) {
-        let mut states = self.pkce_states.write().await;
-        let before = states.len();
-        states.retain(|_, s| s.created_at.elapsed() < PKCE_STATE_TTL);
-        let removed = before - states.len();
-        if removed > 0 {
-            tracing::debug!(removed, "Cleaned up expired PKCE states");
+        match self.pkce_store.cleanup_expired().await {
+            Ok(0) => {}
+            Ok(removed) => tracing::debug!(removed, "Cleaned up expired PKCE states"),
+            Err(e) => tracing::warn!("Failed to clean up expired PKCE states: {e}"),
         }
     }
 
     /// Returns the number of pending PKCE states (for diagnostics).
     pub async fn pending_states_count(&self) -> usize {
-        self.pkce_states.read().await.len()
+        self.pkce_store.pending().await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to count pending PKCE states: {e}");
+            0
+        })
     }
 
     // ═══════════════════════════════════════════════════════════════════════
@@ -291,6 +405,54 @@ impl OAuthFlowManager {
         }
     }
 
+    /// Build the default GitHub OAuth App config from env vars.
+    /// Returns `None` if `GITHUB_OAUTH_CLIENT_ID` or `GITHUB_OAUTH_CLIENT_SECRET`
+    /// are not set. The device flow must be enabled in the app's settings.
+    pub fn default_github_config() -> Option<OAuthProviderConfig> {
+        let client_id = std::env::var("GITHUB_OAUTH_CLIENT_ID").ok()?;
+        let client_secret = std::env::var("GITHUB_OAUTH_CLIENT_SECRET").ok()?;
+        if client_id.is_empty() || client_secret.is_empty() {
+            return None;
+        }
+
+        Some(OAuthProviderConfig {
+            provider: OAuthProvider::GitHub,
+            authorize_url: GITHUB_AUTHORIZE_URL.to_string(),
+            token_url: GITHUB_TOKEN_URL.to_string(),
+            redirect_uri: integration_redirect_uri("GITHUB", "github"),
+            client_id,
+            client_secret: Some(client_secret),
+            scopes: GITHUB_SCOPE.split_whitespace().map(String::from).collect(),
+            pkce_method: PkceMethod::S256,
+            extra_params: HashMap::new(),
+        })
+    }
+
+    /// Build the default Vercel integration config from env vars.
+    /// Returns `None` unless `VERCEL_OAUTH_CLIENT_ID`, `VERCEL_OAUTH_CLIENT_SECRET`
+    /// and `VERCEL_INTEGRATION_SLUG` are set. Scopes are part of the
+    /// integration's settings, so none are requested.
+    pub fn default_vercel_config() -> Option<OAuthProviderConfig> {
+        let client_id = std::env::var("VERCEL_OAUTH_CLIENT_ID").ok()?;
+        let client_secret = std::env::var("VERCEL_OAUTH_CLIENT_SECRET").ok()?;
+        let slug = std::env::var("VERCEL_INTEGRATION_SLUG").ok()?;
+        if client_id.is_empty() || client_secret.is_empty() || slug.is_empty() {
+            return None;
+        }
+
+        Some(OAuthProviderConfig {
+            provider: OAuthProvider::Vercel,
+            authorize_url: format!("https://vercel.com/integrations/{slug}/new"),
+            token_url: VERCEL_TOKEN_URL.to_string(),
+            redirect_uri: integration_redirect_uri("VERCEL", "vercel"),
+            client_id,
+            client_secret: Some(client_secret),
+            scopes: Vec::new(),
+            pkce_method: PkceMethod::S256,
+            extra_params: HashMap::new(),
+        })
+    }
+
     /// Build the default Google OAuth config from env vars.
     /// Returns `None` if `GOOGLE_OAUTH_CLIENT_ID` or `GOOGLE_OAUTH_CLIENT_SECRET`
     /// are not set.
@@ -392,6 +554,7 @@ impl OAuthFlowManager {
         let resp = self
             .http_client
             .post(&config.token_url)
+            .header("accept", "application/json")
             .form(&form)
             .timeout(Duration::from_secs(30))
             .send()
@@ -421,7 +584,7 @@ impl OAuthFlowManager {
         &self,
         config: &OAuthProviderConfig,
         refresh_token: &str,
-    ) -> anyhow::Result<OAuthTokens> {
+    ) -> Result<OAuthTokens, RefreshError> {
         let body = serde_json::json!({
             "grant_type": "refresh_token",
             "client_id": config.client_id,
@@ -439,9 +602,11 @@ impl OAuthFlowManager {
             .map_err(|e| anyhow::anyhow!("Anthropic token refresh request failed: {e}"))?;
 
         if !resp.status().is_success() {
-            let status = resp.status();
-            let err = resp.text().await.unwrap_or_default();
-            anyhow::bail!("Anthropic token refresh rejected ({status}): {err}");
+            return Err(RefreshError::Rejected {
+                provider: config.provider,
+                status: resp.status().as_u16(),
+                body: resp.text().await.unwrap_or_default(),
+            });
         }
 
         let raw: Value = resp
@@ -449,7 +614,7 @@ impl OAuthFlowManager {
             .await
             .map_err(|e| anyhow::anyhow!("Invalid JSON from Anthropic refresh endpoint: {e}"))?;
 
-        parse_token_response(raw, &config.scopes)
+        Ok(parse_token_response(raw, &config.scopes)?)
     }
 
     /// Standard token refresh — form-encoded body with client_secret.
@@ -457,7 +622,7 @@ impl OAuthFlowManager {
         &self,
         config: &OAuthProviderConfig,
         refresh_token: &str,
-    ) -> anyhow::Result<OAuthTokens> {
+    ) -> Result<OAuthTokens, RefreshError> {
         let mut form: Vec<(&str, &str)> = vec![
             ("grant_type", "refresh_token"),
             ("client_id", &config.client_id),
@@ -473,6 +638,7 @@ impl OAuthFlowManager {
         let resp = self
             .http_client
             .post(&config.token_url)
+            .header("accept", "application/json")
             .form(&form)
             .timeout(Duration::from_secs(30))
             .send()
@@ -482,12 +648,11 @@ impl OAuthFlowManager {
             })?;
 
         if !resp.status().is_success() {
-            let status = resp.status();
-            let err = resp.text().await.unwrap_or_default();
-            anyhow::bail!(
-                "{} token refresh rejected ({status}): {err}",
-                config.provider
-            );
+            return Err(RefreshError::Rejected {
+                provider: config.provider,
+                status: resp.status().as_u16(),
+                body: resp.text().await.unwrap_or_default(),
+            });
         }
 
         let raw: Value = resp.json().await.map_err(|e| {
@@ -497,6 +662,18 @@ impl OAuthFlowManager {
             )
         })?;
 
-        parse_token_response(raw, &config.scopes)
+        Ok(parse_token_response(raw, &config.scopes)?)
     }
 }
+
+/// `{env_prefix}_OAUTH_REDIRECT_URI`, or the backend's own integration
+/// callback endpoint.
+fn integration_redirect_uri(env_prefix: &str, integration: &str) -> String {
+    std::env::var(format!("{env_prefix}_OAUTH_REDIRECT_URI"))
+        .ok()
+        .filter(|uri| !uri.is_empty())
+        .unwrap_or_else(|| {
+            let port = std::env::var("PORT").unwrap_or_else(|_| "8082".to_string());
+            format!("http://localhost:{port}/api/integrations/{integration}/callback")
+        })
+}
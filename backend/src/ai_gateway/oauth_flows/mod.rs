@@ -2,15 +2,18 @@
 //
 // Split into focused submodules:
 // - `types` — OAuthProvider enum, PkceMethod, OAuthTokens, configs
-// - `manager` — OAuthFlowManager (login, callback, refresh, cleanup)
+// - `manager` — OAuthFlowManager (login, callback, device flow, refresh, cleanup)
 // - `pkce` — PKCE utilities (random_base64url, sha256_base64url, parse_token_response)
+// - `store` — pending PKCE state storage (in memory or Postgres)
 
 pub mod manager;
 pub(crate) mod pkce;
+pub mod store;
 mod types;
 
 // ── Public re-exports ────────────────────────────────────────────────────
 pub use manager::OAuthFlowManager;
+pub use store::{MemoryPkceStore, PgPkceStore, PkceStateStore};
 pub use types::*;
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -20,12 +23,14 @@ pub use types::*;
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::sync::Arc;
     use std::time::{Duration, Instant};
 
     use serde_json::Value;
 
     use super::manager::OAuthFlowManager;
     use super::pkce::*;
+    use super::store::MemoryPkceStore;
     use super::types::*;
 
     // ── OAuthProvider Display + Serialize ──────────────────────────────────────
@@ -112,6 +117,70 @@ mod tests {
         assert!(tokens.extra.is_empty());
     }
 
+    #[test]
+    fn oauth_tokens_missing_scopes() {
+        let tokens = OAuthTokens {
+            access_token: "gho_xxx".into(),
+            refresh_token: None,
+            expires_in: None,
+            scope: Some("repo,user".into()),
+            token_type: "bearer".into(),
+            extra: HashMap::new(),
+        };
+        // `read:user` is covered by the broader `user` scope.
+        let required = vec![
+            "repo".to_string(),
+            "read:user".to_string(),
+            "workflow".to_string(),
+        ];
+        assert_eq!(
+            tokens.missing_scopes(&required),
+            vec!["workflow".to_string()]
+        );
+    }
+
+    #[test]
+    fn oauth_tokens_missing_scopes_follow_github_implications() {
+        let granted = |scope: &str| OAuthTokens {
+            access_token: "gho_xxx".into(),
+            refresh_token: None,
+            expires_in: None,
+            scope: Some(scope.into()),
+            token_type: "bearer".into(),
+            extra: HashMap::new(),
+        };
+        let required =
+            |scopes: &[&str]| -> Vec<String> { scopes.iter().map(|s| s.to_string()).collect() };
+
+        // `repo:status` is part of `repo` — not of some `status` scope.
+        assert!(
+            granted("repo")
+                .missing_scopes(&required(&["repo:status"]))
+                .is_empty()
+        );
+        assert_eq!(
+            granted("status").missing_scopes(&required(&["repo:status"])),
+            vec!["repo:status".to_string()]
+        );
+        // `repo:status` alone does not grant the whole `repo`.
+        assert_eq!(
+            granted("repo:status").missing_scopes(&required(&["repo"])),
+            vec!["repo".to_string()]
+        );
+
+        // `admin:org` grants `write:org` and, through it, `read:org` …
+        assert!(
+            granted("admin:org")
+                .missing_scopes(&required(&["write:org", "read:org"]))
+                .is_empty()
+        );
+        // … but is not covered by a granted `org` (no such scope) or `read:org`.
+        assert_eq!(
+            granted("org,read:org").missing_scopes(&required(&["admin:org"])),
+            vec!["admin:org".to_string()]
+        );
+    }
+
     // ── LoginResponse ──────────────────────────────────────────────────────
 
     #[test]
@@ -340,13 +409,14 @@ mod tests {
 
     #[tokio::test]
     async fn initiate_login_stores_pkce_state() {
-        let mgr = OAuthFlowManager::new(reqwest::Client::new());
+        let store = Arc::new(MemoryPkceStore::default());
+        let mgr = OAuthFlowManager::new(reqwest::Client::new()).with_pkce_store(store.clone());
         assert_eq!(mgr.pending_states_count().await, 0);
 
         let resp = mgr.initiate_login(OAuthProvider::Anthropic).await.unwrap();
         assert_eq!(mgr.pending_states_count().await, 1);
 
-        let states = mgr.pkce_states.read().await;
+        let states = store.states.read().await;
         let pkce = states.get(&resp.state).unwrap();
         assert_eq!(pkce.provider, OAuthProvider::Anthropic);
         assert!(!pkce.code_verifier.is_empty());
@@ -382,11 +452,12 @@ mod tests {
 
     #[tokio::test]
     async fn cleanup_expired_states_removes_old_entries() {
-        let mgr = OAuthFlowManager::new(reqwest::Client::new());
+        let store = Arc::new(MemoryPkceStore::default());
+        let mgr = OAuthFlowManager::new(reqwest::Client::new()).with_pkce_store(store.clone());
 
         // Insert an already-expired state manually.
         {
-            let mut states = mgr.pkce_states.write().await;
+            let mut states = store.states.write().await;
             states.insert(
                 "old-state".to_string(),
                 PkceState {
@@ -409,7 +480,7 @@ mod tests {
         mgr.cleanup_expired_states().await;
         assert_eq!(mgr.pending_states_count().await, 1);
 
-        let states = mgr.pkce_states.read().await;
+        let states = store.states.read().await;
         assert!(states.contains_key("fresh-state"));
         assert!(!states.contains_key("old-state"));
     }
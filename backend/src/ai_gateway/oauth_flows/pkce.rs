@@ -46,6 +46,15 @@ pub(crate) fn parse_token_response(
         .as_object()
         .ok_or_else(|| anyhow::anyhow!("Token response is not a JSON object"))?;
 
+    // GitHub reports errors with HTTP 200 and an `error` field.
+    if let Some(error) = obj.get("error").and_then(|v| v.as_str()) {
+        let description = obj
+            .get("error_description")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        anyhow::bail!("Token endpoint returned {error}: {description}");
+    }
+
     let access_token = obj
         .get("access_token")
         .and_then(|v| v.as_str())
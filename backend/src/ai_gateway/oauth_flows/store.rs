@@ -0,0 +1,197 @@
+// store.rs — Pluggable storage of pending PKCE states.
+//
+// `initiate_login` and `handle_callback` may run on different replicas, or
+// the process may restart between them, so the state → verifier mapping can
+// live outside the process. Two implementations:
+// - `MemoryPkceStore` — process memory (single replica, tests)
+// - `PgPkceStore` — `ch_oauth_pkce_states`; the verifier is encrypted with
+//   AES-256-GCM (`OAUTH_ENCRYPTION_KEY` / `AUTH_SECRET`), the state is stored
+//   hashed, and consumption is a single `DELETE ... RETURNING`.
+//
+// `from_env` picks one from `OAUTH_STATE_STORE` (`postgres`, the default, or
+// `memory`).
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+
+use super::pkce::sha256_base64url;
+use super::types::{OAuthProvider, PKCE_STATE_TTL, PkceState};
+
+/// Storage of pending PKCE states, keyed by the `state` parameter.
+pub trait PkceStateStore: fmt::Debug + Send + Sync {
+    /// Store the state of a login that was just initiated.
+    fn insert<'a>(&'a self, state: &'a str, pkce: PkceState) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Remove and return a state. A state can be taken once; expired states
+    /// are returned too, so the caller can tell "expired" from "unknown".
+    fn take<'a>(&'a self, state: &'a str) -> BoxFuture<'a, anyhow::Result<Option<PkceState>>>;
+
+    /// Remove states older than `PKCE_STATE_TTL`; returns how many.
+    fn cleanup_expired(&self) -> BoxFuture<'_, anyhow::Result<u64>>;
+
+    /// Number of pending states (for diagnostics).
+    fn pending(&self) -> BoxFuture<'_, anyhow::Result<usize>>;
+}
+
+/// The store selected by `OAUTH_STATE_STORE`. Postgres needs an encryption
+/// key; without one the in-memory store is used.
+pub fn from_env(db: &PgPool) -> Arc<dyn PkceStateStore> {
+    let kind = std::env::var("OAUTH_STATE_STORE").unwrap_or_else(|_| "postgres".to_string());
+    match kind.trim().to_ascii_lowercase().as_str() {
+        "memory" => Arc::new(MemoryPkceStore::default()),
+        "postgres" | "" if jaskier_net_sec::oauth::is_encryption_configured() => {
+            Arc::new(PgPkceStore::new(db.clone()))
+        }
+        "postgres" | "" => {
+            tracing::warn!(
+                "OAUTH_STATE_STORE=postgres needs OAUTH_ENCRYPTION_KEY or AUTH_SECRET — \
+                 keeping PKCE states in memory"
+            );
+            Arc::new(MemoryPkceStore::default())
+        }
+        other => {
+            tracing::warn!(
+                "Unknown OAUTH_STATE_STORE '{}' — keeping PKCE states in memory",
+                other
+            );
+            Arc::new(MemoryPkceStore::default())
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  In memory
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// PKCE states in process memory.
+#[derive(Debug, Default)]
+pub struct MemoryPkceStore {
+    pub(crate) states: RwLock<HashMap<String, PkceState>>,
+}
+
+impl PkceStateStore for MemoryPkceStore {
+    fn insert<'a>(&'a self, state: &'a str, pkce: PkceState) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut states = self.states.write().await;
+            states.retain(|_, s| s.created_at.elapsed() < PKCE_STATE_TTL);
+            states.insert(state.to_string(), pkce);
+            Ok(())
+        })
+    }
+
+    fn take<'a>(&'a self, state: &'a str) -> BoxFuture<'a, anyhow::Result<Option<PkceState>>> {
+        Box::pin(async move { Ok(self.states.write().await.remove(state)) })
+    }
+
+    fn cleanup_expired(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(async move {
+            let mut states = self.states.write().await;
+            let before = states.len();
+            states.retain(|_, s| s.created_at.elapsed() < PKCE_STATE_TTL);
+            Ok((before - states.len()) as u64)
+        })
+    }
+
+    fn pending(&self) -> BoxFuture<'_, anyhow::Result<usize>> {
+        Box::pin(async move { Ok(self.states.read().await.len()) })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Postgres
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// PKCE states in `ch_oauth_pkce_states`, shared by all replicas.
+#[derive(Debug, Clone)]
+pub struct PgPkceStore {
+    db: PgPool,
+}
+
+impl PgPkceStore {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+impl PkceStateStore for PgPkceStore {
+    fn insert<'a>(&'a self, state: &'a str, pkce: PkceState) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let verifier = jaskier_net_sec::oauth::encrypt_token(&pkce.code_verifier)
+                .map_err(|e| anyhow::anyhow!("Failed to encrypt PKCE verifier: {e}"))?;
+            sqlx::query(
+                "INSERT INTO ch_oauth_pkce_states \
+                 (state_hash, provider, code_verifier_enc, expires_at) \
+                 VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))",
+            )
+            .bind(sha256_base64url(state))
+            .bind(pkce.provider.to_string())
+            .bind(verifier)
+            .bind(PKCE_STATE_TTL.as_secs_f64())
+            .execute(&self.db)
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn take<'a>(&'a self, state: &'a str) -> BoxFuture<'a, anyhow::Result<Option<PkceState>>> {
+        Box::pin(async move {
+            let row: Option<(String, String, f64)> = sqlx::query_as(
+                "DELETE FROM ch_oauth_pkce_states WHERE state_hash = $1 \
+                 RETURNING provider, code_verifier_enc, \
+                           EXTRACT(EPOCH FROM NOW() - created_at)::FLOAT8",
+            )
+            .bind(sha256_base64url(state))
+            .fetch_optional(&self.db)
+            .await?;
+            let Some((provider, verifier, age_secs)) = row else {
+                return Ok(None);
+            };
+
+            let provider = [
+                OAuthProvider::Anthropic,
+                OAuthProvider::Google,
+                OAuthProvider::GitHub,
+                OAuthProvider::Vercel,
+            ]
+            .into_iter()
+            .find(|p| p.to_string() == provider)
+            .ok_or_else(|| anyhow::anyhow!("Unknown provider '{provider}' in stored PKCE state"))?;
+            let code_verifier = jaskier_net_sec::oauth::decrypt_token(&verifier)
+                .map_err(|e| anyhow::anyhow!("Failed to decrypt PKCE verifier: {e}"))?;
+            let now = Instant::now();
+            Ok(Some(PkceState {
+                code_verifier,
+                provider,
+                created_at: now
+                    .checked_sub(Duration::from_secs_f64(age_secs.max(0.0)))
+                    .unwrap_or(now),
+            }))
+        })
+    }
+
+    fn cleanup_expired(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(async move {
+            let result = sqlx::query("DELETE FROM ch_oauth_pkce_states WHERE expires_at <= NOW()")
+                .execute(&self.db)
+                .await?;
+            Ok(result.rows_affected())
+        })
+    }
+
+    fn pending(&self) -> BoxFuture<'_, anyhow::Result<usize>> {
+        Box::pin(async move {
+            let count: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM ch_oauth_pkce_states WHERE expires_at > NOW()",
+            )
+            .fetch_one(&self.db)
+            .await?;
+            Ok(usize::try_from(count).unwrap_or_default())
+        })
+    }
+}
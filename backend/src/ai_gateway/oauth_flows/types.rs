@@ -92,6 +92,152 @@ pub(crate) fn default_token_type() -> String {
     "Bearer".to_string()
 }
 
+/// GitHub scopes and the narrower scopes each one grants
+/// (<https://docs.github.com/apps/oauth-apps/building-oauth-apps/scopes-for-oauth-apps>).
+/// Implication is transitive: `admin:org` grants `write:org`, which grants
+/// `read:org`.
+const GITHUB_SCOPE_IMPLIES: &[(&str, &[&str])] = &[
+    (
+        "repo",
+        &[
+            "repo:status",
+            "repo_deployment",
+            "public_repo",
+            "repo:invite",
+            "security_events",
+        ],
+    ),
+    ("admin:repo_hook", &["write:repo_hook"]),
+    ("write:repo_hook", &["read:repo_hook"]),
+    ("admin:org", &["write:org"]),
+    ("write:org", &["read:org"]),
+    ("admin:public_key", &["write:public_key"]),
+    ("write:public_key", &["read:public_key"]),
+    ("admin:gpg_key", &["write:gpg_key"]),
+    ("write:gpg_key", &["read:gpg_key"]),
+    ("admin:ssh_signing_key", &["write:ssh_signing_key"]),
+    ("write:ssh_signing_key", &["read:ssh_signing_key"]),
+    ("user", &["read:user", "user:email", "user:follow"]),
+    ("project", &["read:project"]),
+    ("write:packages", &["read:packages"]),
+    ("codespace", &["codespace:secrets"]),
+    ("audit_log", &["read:audit_log"]),
+    (
+        "admin:enterprise",
+        &[
+            "manage_runners:enterprise",
+            "manage_billing:enterprise",
+            "read:enterprise",
+        ],
+    ),
+    ("manage_billing:enterprise", &["read:enterprise"]),
+];
+
+/// Whether the granted scope `granted` is, or implies, `required`.
+fn scope_covers(granted: &str, required: &str) -> bool {
+    granted == required
+        || GITHUB_SCOPE_IMPLIES
+            .iter()
+            .find(|(scope, _)| *scope == granted)
+            .is_some_and(|(_, implied)| implied.iter().any(|s| scope_covers(s, required)))
+}
+
+impl OAuthTokens {
+    /// Scopes of `required` that were not granted. GitHub separates granted
+    /// scopes with commas, everyone else with spaces; a narrower GitHub scope
+    /// is covered by a granted scope that implies it (`GITHUB_SCOPE_IMPLIES`).
+    /// Other providers' scopes never appear in the table, so only exact
+    /// matches count for them.
+    pub fn missing_scopes(&self, required: &[String]) -> Vec<String> {
+        let granted: Vec<&str> = self
+            .scope
+            .as_deref()
+            .unwrap_or_default()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .collect();
+        required
+            .iter()
+            .filter(|s| !granted.iter().any(|g| scope_covers(g, s)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Device authorization (RFC 8628) started by `start_device_login`: the user
+/// enters `user_code` at `verification_uri` while the app polls with
+/// `device_code`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceLoginResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Seconds until `device_code` expires.
+    pub expires_in: i64,
+    /// Minimum seconds between polls.
+    pub interval: i64,
+}
+
+/// Result of one `poll_device_login`.
+#[derive(Debug, Clone)]
+pub enum DevicePoll {
+    /// The user has not authorized yet.
+    Pending,
+    /// Polling too fast — wait `interval` seconds between polls.
+    SlowDown {
+        interval: i64,
+    },
+    Complete(OAuthTokens),
+}
+
+/// Why `OAuthFlowManager::refresh_token` failed.
+#[derive(Debug)]
+pub enum RefreshError {
+    /// The token endpoint answered with a non-success HTTP status.
+    Rejected {
+        provider: OAuthProvider,
+        status: u16,
+        body: String,
+    },
+    /// No usable answer: provider not configured, request failed, or the
+    /// response could not be parsed.
+    Other(anyhow::Error),
+}
+
+impl RefreshError {
+    /// Whether retrying with the same refresh token cannot succeed: the token
+    /// endpoint refused it with a 4xx other than 408 (timeout) or 429 (rate
+    /// limit).
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, Self::Rejected { status, .. }
+            if (400..500).contains(status) && *status != 408 && *status != 429)
+    }
+}
+
+impl std::fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rejected {
+                provider,
+                status,
+                body,
+            } => write!(
+                f,
+                "{provider} token refresh rejected (HTTP {status}): {body}"
+            ),
+            Self::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for RefreshError {}
+
+impl From<anyhow::Error> for RefreshError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::Other(err)
+    }
+}
+
 /// Response returned to the frontend after initiating a login flow.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginResponse {
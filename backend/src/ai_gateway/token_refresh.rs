@@ -0,0 +1,720 @@
+// token_refresh.rs — Background refresh of Vault-stored provider credentials.
+//
+// Tracks when the credential of every OAuth-connected provider expires (the
+// `expires_at` stored with the tokens, derived from `OAuthTokens.expires_in`,
+// or the `exp` claim of a session JWT) and refreshes OAuth tokens
+// `REFRESH_AHEAD_SECS` before that, with random jitter so replicas sharing a Vault
+// do not all refresh at the same moment. Failed refreshes back off
+// exponentially. A permanent failure — the token endpoint rejects the refresh
+// token, none is stored, or the token expired while refreshes kept failing —
+// marks the provider disconnected in Vault together with the error. Before a
+// failed refresh disconnects, the credential is re-read from Vault: when
+// another replica refreshed it (or the user reconnected) meanwhile, the
+// stored refresh token differs and the new credential is kept.
+//
+// Session tokens and cookies can only be renewed by logging in again through
+// the browser proxy, so for them the scheduler warns ahead of expiry and
+// disconnects them once expired.
+//
+// Refreshes, failures and disconnects are reported through
+// `HasAiGateway::credential_event` (audit log and notifications in the app);
+// the per-provider schedule is exposed on `/api/ai/providers`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use super::oauth_flows::{OAuthProvider, OAuthTokens, RefreshError};
+use super::session_manager::extract_jwt_expiry;
+use super::vault_bridge::{HasVaultBridge, VaultError};
+use super::{AiProvider, AuthType, HasAiGateway, ProviderConfig};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How long before expiry a token is refreshed (or a session warned about).
+const REFRESH_AHEAD_SECS: i64 = 10 * 60;
+/// Upper bound of the random delay added to every scheduled refresh.
+const MAX_JITTER_SECS: i64 = 120;
+const BACKOFF_BASE_SECS: i64 = 30;
+const BACKOFF_MAX_SECS: i64 = 15 * 60;
+/// Failed refreshes of an already expired token before it is given up on.
+const MAX_FAILURES_AFTER_EXPIRY: u32 = 5;
+/// Re-read interval for credentials without a known expiry (or none at all).
+const IDLE_RECHECK_SECS: i64 = 30 * 60;
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Schedule
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshStatus {
+    /// No credential in Vault (or it was disconnected).
+    NotConnected,
+    /// Valid; the next check is scheduled ahead of expiry.
+    Scheduled,
+    /// The last refresh failed; retrying with backoff.
+    Backoff,
+    /// A session credential close to expiry (needs a browser re-login).
+    Expiring,
+    /// Given up on — the provider was marked disconnected.
+    Disconnected,
+}
+
+#[derive(Debug)]
+struct RefreshEntry {
+    status: RefreshStatus,
+    expires_at: Option<DateTime<Utc>>,
+    next_check: DateTime<Utc>,
+    failures: u32,
+    last_refreshed: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+impl Default for RefreshEntry {
+    fn default() -> Self {
+        Self {
+            status: RefreshStatus::NotConnected,
+            expires_at: None,
+            next_check: Utc::now(),
+            failures: 0,
+            last_refreshed: None,
+            last_error: None,
+        }
+    }
+}
+
+/// Credential schedule of one provider, as reported by `/api/ai/providers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshSnapshot {
+    pub status: RefreshStatus,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub next_check: DateTime<Utc>,
+    pub failures: u32,
+    pub last_refreshed: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl From<&RefreshEntry> for RefreshSnapshot {
+    fn from(entry: &RefreshEntry) -> Self {
+        Self {
+            status: entry.status,
+            expires_at: entry.expires_at,
+            next_check: entry.next_check,
+            failures: entry.failures,
+            last_refreshed: entry.last_refreshed,
+            last_error: entry.last_error.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshEventKind {
+    Refreshed,
+    /// A refresh failed and will be retried.
+    Failed,
+    /// A session credential expires soon.
+    Expiring,
+    Disconnected,
+}
+
+impl RefreshEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Refreshed => "refreshed",
+            Self::Failed => "failed",
+            Self::Expiring => "expiring",
+            Self::Disconnected => "disconnected",
+        }
+    }
+}
+
+/// A change of a provider's credential, passed to
+/// `HasAiGateway::credential_event`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshEvent {
+    pub provider: AiProvider,
+    pub kind: RefreshEventKind,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+/// Refresh schedule of the built-in providers.
+#[derive(Debug, Default)]
+pub struct TokenRefresh {
+    providers: Mutex<HashMap<AiProvider, RefreshEntry>>,
+}
+
+impl TokenRefresh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self, provider: AiProvider) -> Option<RefreshSnapshot> {
+        self.lock().get(&provider).map(RefreshSnapshot::from)
+    }
+
+    /// Check `provider` on the next tick (e.g. right after it was connected).
+    pub fn reschedule(&self, provider: AiProvider) {
+        let mut providers = self.lock();
+        let entry = providers.entry(provider).or_default();
+        entry.next_check = Utc::now();
+        entry.failures = 0;
+    }
+
+    /// Providers of `enabled` whose next check is due; untracked ones are due.
+    fn due(&self, enabled: impl Iterator<Item = AiProvider>) -> Vec<AiProvider> {
+        let now = Utc::now();
+        let providers = self.lock();
+        enabled
+            .filter(|p| providers.get(p).is_none_or(|e| e.next_check <= now))
+            .collect()
+    }
+
+    fn update<R>(&self, provider: AiProvider, f: impl FnOnce(&mut RefreshEntry) -> R) -> R {
+        f(self.lock().entry(provider).or_default())
+    }
+
+    fn forget(&self, provider: AiProvider) {
+        self.lock().remove(&provider);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<AiProvider, RefreshEntry>> {
+        self.providers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// When to refresh a token expiring at `expires_at`.
+fn refresh_at(expires_at: DateTime<Utc>) -> DateTime<Utc> {
+    expires_at - chrono::Duration::seconds(REFRESH_AHEAD_SECS + jitter_secs())
+}
+
+fn jitter_secs() -> i64 {
+    (rand::random::<u64>() % (MAX_JITTER_SECS as u64 + 1)) as i64
+}
+
+/// Delay before retry number `failures` (1-based), without jitter.
+fn backoff_secs(failures: u32) -> i64 {
+    BACKOFF_BASE_SECS
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(BACKOFF_MAX_SECS)
+}
+
+fn idle_recheck() -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(IDLE_RECHECK_SECS)
+}
+
+/// Expiry stored with a credential: `expires_at` as Unix seconds, Unix
+/// milliseconds (the pre-Vault `ch_*` tables) or an RFC 3339 string.
+fn stored_expiry(data: &Value) -> Option<DateTime<Utc>> {
+    match data.get("expires_at")? {
+        Value::Number(n) => {
+            let ts = n.as_i64()?;
+            if ts > 100_000_000_000 {
+                DateTime::from_timestamp_millis(ts)
+            } else {
+                DateTime::from_timestamp(ts, 0)
+            }
+        }
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|t| t.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+/// Whether `current` (re-read from Vault) still holds `refresh_token`, i.e.
+/// nobody replaced the credential since the refresh that failed.
+fn still_stored(current: &Value, refresh_token: &str) -> bool {
+    current.get("disconnected").and_then(Value::as_bool) != Some(true)
+        && current.get("refresh_token").and_then(Value::as_str) == Some(refresh_token)
+}
+
+/// The stored credential with the refreshed tokens; a refresh token the
+/// endpoint did not rotate is kept.
+fn merge_tokens(data: &Value, tokens: &OAuthTokens, now: DateTime<Utc>) -> Value {
+    let mut merged = match data {
+        Value::Object(map) => map.clone(),
+        _ => serde_json::Map::new(),
+    };
+    merged.insert("access_token".into(), json!(tokens.access_token));
+    if let Some(refresh_token) = &tokens.refresh_token {
+        merged.insert("refresh_token".into(), json!(refresh_token));
+    }
+    merged.insert("token_type".into(), json!(tokens.token_type));
+    merged.insert("expires_in".into(), json!(tokens.expires_in));
+    merged.insert(
+        "expires_at".into(),
+        json!(tokens.expires_in.map(|secs| now.timestamp() + secs)),
+    );
+    if let Some(scope) = &tokens.scope {
+        merged.insert("scope".into(), json!(scope));
+    }
+    merged.insert("refreshed_at".into(), json!(now.to_rfc3339()));
+    Value::Object(merged)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Scheduler
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Spawn the scheduler: every `CHECK_INTERVAL` the due providers are checked.
+pub fn spawn_scheduler<S>(state: S) -> tokio::task::JoinHandle<()>
+where
+    S: HasAiGateway + HasVaultBridge + Clone + Send + Sync + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            let providers = state.ai_gateway().providers();
+            let due = state
+                .ai_gateway()
+                .token_refresh
+                .due(providers.builtin.keys().copied());
+            for provider in due {
+                check(&state, provider, false).await;
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    })
+}
+
+/// Check one provider's credential and refresh it if it is due — or right
+/// away with `force` (the `/refresh` endpoint). Returns the new schedule,
+/// `None` for providers without an expiring credential.
+pub async fn check<S>(state: &S, provider: AiProvider, force: bool) -> Option<RefreshSnapshot>
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let refresh = &state.ai_gateway().token_refresh;
+    if state.ai_gateway().vault_guard.check().is_err() {
+        // Credentials are not touched during a Vault lockout.
+        return refresh.snapshot(provider);
+    }
+    let Some(config) = state.provider_config(provider) else {
+        refresh.forget(provider);
+        return None;
+    };
+    let oauth_provider = match (config.auth_type, provider) {
+        (AuthType::OAuthPkce, AiProvider::Anthropic) => Some(OAuthProvider::Anthropic),
+        (AuthType::OAuthPkce, AiProvider::Google) => Some(OAuthProvider::Google),
+        (AuthType::SessionToken | AuthType::CookieSession, _) => None,
+        _ => {
+            refresh.forget(provider);
+            return None;
+        }
+    };
+
+    let vault = state.vault_client();
+    let data = match vault
+        .get(&config.vault_namespace, &config.vault_service)
+        .await
+    {
+        Ok(data) if data.get("disconnected").and_then(Value::as_bool) == Some(true) => {
+            not_connected(state, provider);
+            return refresh.snapshot(provider);
+        }
+        Ok(data) => data,
+        Err(VaultError::NotFound) => {
+            not_connected(state, provider);
+            return refresh.snapshot(provider);
+        }
+        Err(err) => {
+            // Vault unreachable — says nothing about the credential itself.
+            refresh.update(provider, |e| {
+                e.status = RefreshStatus::Backoff;
+                e.next_check = Utc::now() + chrono::Duration::seconds(BACKOFF_BASE_SECS);
+                e.last_error = Some(format!("vault: {err}"));
+            });
+            return refresh.snapshot(provider);
+        }
+    };
+
+    match oauth_provider {
+        Some(oauth_provider) => {
+            check_oauth(state, provider, oauth_provider, &config, &data, force).await
+        }
+        None => check_session(state, provider, &config, &data).await,
+    }
+    refresh.snapshot(provider)
+}
+
+fn not_connected<S: HasAiGateway>(state: &S, provider: AiProvider) {
+    state.ai_gateway().token_refresh.update(provider, |e| {
+        if e.status != RefreshStatus::Disconnected {
+            e.status = RefreshStatus::NotConnected;
+        }
+        e.expires_at = None;
+        e.failures = 0;
+        e.next_check = idle_recheck();
+    });
+}
+
+async fn check_oauth<S>(
+    state: &S,
+    provider: AiProvider,
+    oauth_provider: OAuthProvider,
+    config: &ProviderConfig,
+    data: &Value,
+    force: bool,
+) where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let refresh = &state.ai_gateway().token_refresh;
+    let now = Utc::now();
+    let expires_at = stored_expiry(data);
+
+    if !force {
+        match expires_at.map(|at| (at, refresh_at(at))) {
+            Some((at, due)) if due > now => {
+                refresh.update(provider, |e| {
+                    e.status = RefreshStatus::Scheduled;
+                    e.expires_at = Some(at);
+                    e.next_check = due.min(idle_recheck());
+                });
+                return;
+            }
+            Some(_) => {}
+            // Stored before `expires_at` was recorded — refresh once to learn it.
+            None if data.get("expires_in").is_some_and(|v| !v.is_null()) => {}
+            None => {
+                refresh.update(provider, |e| {
+                    e.status = RefreshStatus::Scheduled;
+                    e.expires_at = None;
+                    e.next_check = idle_recheck();
+                });
+                return;
+            }
+        }
+    }
+
+    let Some(refresh_token) = data
+        .get("refresh_token")
+        .and_then(Value::as_str)
+        .filter(|t| !t.is_empty())
+    else {
+        let error = "No refresh token stored — reconnect the provider".to_string();
+        if expires_at.is_some_and(|at| at <= now) {
+            disconnect(state, provider, config, error).await;
+        } else {
+            // Usable until it expires; then it is disconnected.
+            refresh.update(provider, |e| {
+                e.status = RefreshStatus::Scheduled;
+                e.expires_at = expires_at;
+                e.last_error = Some(error);
+                e.next_check = expires_at.unwrap_or_else(idle_recheck);
+            });
+        }
+        return;
+    };
+
+    tracing::info!(provider = %provider, expires_at = ?expires_at, "token refresh: refreshing OAuth token");
+    let result = match state
+        .oauth_manager()
+        .refresh_token(oauth_provider, refresh_token)
+        .await
+    {
+        Ok(tokens) => {
+            let merged = merge_tokens(data, &tokens, now);
+            let vault = state.vault_client();
+            match vault
+                .set(&config.vault_namespace, &config.vault_service, merged)
+                .await
+            {
+                Ok(()) => {
+                    vault
+                        .invalidate_cache(&config.vault_namespace, &config.vault_service)
+                        .await;
+                    Ok(tokens
+                        .expires_in
+                        .map(|secs| now + chrono::Duration::seconds(secs)))
+                }
+                Err(err) => Err(RefreshError::Other(anyhow::anyhow!(
+                    "Failed to store refreshed tokens in Vault: {err}"
+                ))),
+            }
+        }
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(new_expiry) => {
+            refresh.update(provider, |e| {
+                e.status = RefreshStatus::Scheduled;
+                e.expires_at = new_expiry;
+                e.failures = 0;
+                e.last_refreshed = Some(now);
+                e.last_error = None;
+                e.next_check = new_expiry
+                    .map(refresh_at)
+                    .unwrap_or_else(idle_recheck)
+                    .min(idle_recheck());
+            });
+            tracing::info!(provider = %provider, expires_at = ?new_expiry, "token refresh: OAuth token refreshed");
+            state
+                .credential_event(RefreshEvent {
+                    provider,
+                    kind: RefreshEventKind::Refreshed,
+                    expires_at: new_expiry,
+                    error: None,
+                })
+                .await;
+        }
+        Err(err) if err.is_permanent() => {
+            disconnect_if_unchanged(state, provider, config, refresh_token, err.to_string()).await;
+        }
+        Err(err) => {
+            let error = err.to_string();
+            let failures = refresh.update(provider, |e| {
+                e.failures += 1;
+                e.failures
+            });
+            if expires_at.is_some_and(|at| at <= now) && failures >= MAX_FAILURES_AFTER_EXPIRY {
+                disconnect_if_unchanged(state, provider, config, refresh_token, error).await;
+                return;
+            }
+            refresh.update(provider, |e| {
+                e.status = RefreshStatus::Backoff;
+                e.expires_at = expires_at;
+                e.last_error = Some(error.clone());
+                e.next_check =
+                    now + chrono::Duration::seconds(backoff_secs(failures) + jitter_secs());
+            });
+            tracing::warn!(provider = %provider, failures, error = %error, "token refresh: failed, backing off");
+            state
+                .credential_event(RefreshEvent {
+                    provider,
+                    kind: RefreshEventKind::Failed,
+                    expires_at,
+                    error: Some(error),
+                })
+                .await;
+        }
+    }
+}
+
+async fn check_session<S>(state: &S, provider: AiProvider, config: &ProviderConfig, data: &Value)
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let refresh = &state.ai_gateway().token_refresh;
+    let now = Utc::now();
+    let expires_at = stored_expiry(data).or_else(|| {
+        data.get("session_token")
+            .and_then(Value::as_str)
+            .and_then(extract_jwt_expiry)
+            .and_then(|exp| DateTime::from_timestamp(exp, 0))
+    });
+    let Some(expires_at) = expires_at else {
+        refresh.update(provider, |e| {
+            e.status = RefreshStatus::Scheduled;
+            e.expires_at = None;
+            e.next_check = idle_recheck();
+        });
+        return;
+    };
+
+    if expires_at <= now {
+        disconnect(
+            state,
+            provider,
+            config,
+            "Session expired — log in again via the browser proxy".into(),
+        )
+        .await;
+        return;
+    }
+
+    let warn_at = expires_at - chrono::Duration::seconds(REFRESH_AHEAD_SECS);
+    if warn_at > now {
+        refresh.update(provider, |e| {
+            e.status = RefreshStatus::Scheduled;
+            e.expires_at = Some(expires_at);
+            e.next_check = warn_at.min(idle_recheck());
+        });
+        return;
+    }
+
+    let first_warning = refresh.update(provider, |e| {
+        let first = e.status != RefreshStatus::Expiring;
+        e.status = RefreshStatus::Expiring;
+        e.expires_at = Some(expires_at);
+        e.next_check = expires_at;
+        first
+    });
+    if first_warning {
+        tracing::warn!(provider = %provider, %expires_at, "token refresh: session expires soon");
+        state
+            .credential_event(RefreshEvent {
+                provider,
+                kind: RefreshEventKind::Expiring,
+                expires_at: Some(expires_at),
+                error: None,
+            })
+            .await;
+    }
+}
+
+/// Disconnect after the refresh of `refresh_token` failed for good — unless
+/// Vault, read fresh, no longer holds that token. A replaced credential is
+/// checked again on the next tick; a Vault error postpones the decision.
+async fn disconnect_if_unchanged<S>(
+    state: &S,
+    provider: AiProvider,
+    config: &ProviderConfig,
+    refresh_token: &str,
+    error: String,
+) where
+    S: HasAiGateway + HasVaultBridge,
+{
+    let refresh = &state.ai_gateway().token_refresh;
+    let vault = state.vault_client();
+    vault
+        .invalidate_cache(&config.vault_namespace, &config.vault_service)
+        .await;
+    match vault
+        .get(&config.vault_namespace, &config.vault_service)
+        .await
+    {
+        Ok(current) if still_stored(&current, refresh_token) => {
+            disconnect(state, provider, config, error).await;
+        }
+        Ok(_) | Err(VaultError::NotFound) => {
+            tracing::info!(provider = %provider, "token refresh: credential replaced meanwhile — not disconnecting");
+            refresh.update(provider, |e| {
+                e.failures = 0;
+                e.last_error = None;
+                e.next_check = Utc::now();
+            });
+        }
+        Err(err) => {
+            refresh.update(provider, |e| {
+                e.status = RefreshStatus::Backoff;
+                e.next_check = Utc::now() + chrono::Duration::seconds(BACKOFF_BASE_SECS);
+                e.last_error = Some(format!("{error} (vault: {err})"));
+            });
+        }
+    }
+}
+
+/// Mark the provider disconnected in Vault (the same marker as the
+/// `/disconnect` endpoint, plus the error).
+async fn disconnect<S>(state: &S, provider: AiProvider, config: &ProviderConfig, error: String)
+where
+    S: HasAiGateway + HasVaultBridge,
+{
+    tracing::error!(provider = %provider, error = %error, "token refresh: giving up — marking provider disconnected");
+    let vault = state.vault_client();
+    let marker = json!({
+        "disconnected": true,
+        "disconnected_at": Utc::now().to_rfc3339(),
+        "last_error": error,
+    });
+    match vault
+        .set(&config.vault_namespace, &config.vault_service, marker)
+        .await
+    {
+        Ok(()) => {
+            vault
+                .invalidate_cache(&config.vault_namespace, &config.vault_service)
+                .await;
+        }
+        Err(err) => {
+            tracing::warn!(provider = %provider, error = %err, "token refresh: failed to store disconnect marker");
+        }
+    }
+
+    let expires_at = state.ai_gateway().token_refresh.update(provider, |e| {
+        e.status = RefreshStatus::Disconnected;
+        e.failures = 0;
+        e.last_error = Some(error.clone());
+        e.next_check = idle_recheck();
+        e.expires_at
+    });
+    state
+        .credential_event(RefreshEvent {
+            provider,
+            kind: RefreshEventKind::Disconnected,
+            expires_at,
+            error: Some(error),
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_expiry_accepts_seconds_millis_and_rfc3339() {
+        let at = DateTime::from_timestamp(1_800_000_000, 0).unwrap();
+        assert_eq!(
+            stored_expiry(&json!({ "expires_at": 1_800_000_000 })),
+            Some(at)
+        );
+        assert_eq!(
+            stored_expiry(&json!({ "expires_at": 1_800_000_000_000_i64 })),
+            Some(at)
+        );
+        assert_eq!(
+            stored_expiry(&json!({ "expires_at": at.to_rfc3339() })),
+            Some(at)
+        );
+        assert_eq!(stored_expiry(&json!({ "expires_in": 3600 })), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap_and_permanent_errors_are_4xx() {
+        assert_eq!(backoff_secs(1), BACKOFF_BASE_SECS);
+        assert_eq!(backoff_secs(2), 2 * BACKOFF_BASE_SECS);
+        assert_eq!(backoff_secs(40), BACKOFF_MAX_SECS);
+
+        let rejected = |status| RefreshError::Rejected {
+            provider: OAuthProvider::Google,
+            status,
+            body: "{}".into(),
+        };
+        assert!(rejected(400).is_permanent());
+        assert!(rejected(401).is_permanent());
+        assert!(!rejected(408).is_permanent());
+        assert!(!rejected(429).is_permanent());
+        assert!(!rejected(503).is_permanent());
+        // A detail that merely mentions a 4xx does not make a failure permanent.
+        let other = RefreshError::Other(anyhow::anyhow!(
+            "proxy error: upstream rejected (401) — error sending request"
+        ));
+        assert!(!other.is_permanent());
+    }
+
+    #[test]
+    fn failed_refresh_token_is_matched_against_a_fresh_read() {
+        let stored = json!({ "access_token": "a", "refresh_token": "rt-1" });
+        assert!(still_stored(&stored, "rt-1"));
+        // Another replica rotated the token in the meantime.
+        assert!(!still_stored(&stored, "rt-0"));
+        let disconnected = json!({ "disconnected": true, "refresh_token": "rt-1" });
+        assert!(!still_stored(&disconnected, "rt-1"));
+    }
+
+    #[test]
+    fn merge_tokens_keeps_unrotated_refresh_token() {
+        let now = Utc::now();
+        let stored = json!({ "access_token": "old", "refresh_token": "rt-1", "user_email": "a@b" });
+        let tokens = OAuthTokens {
+            access_token: "new".into(),
+            refresh_token: None,
+            expires_in: Some(3600),
+            scope: None,
+            token_type: "Bearer".into(),
+            extra: HashMap::new(),
+        };
+        let merged = merge_tokens(&stored, &tokens, now);
+        assert_eq!(merged["access_token"], "new");
+        assert_eq!(merged["refresh_token"], "rt-1");
+        assert_eq!(merged["user_email"], "a@b");
+        assert_eq!(merged["expires_at"], now.timestamp() + 3600);
+    }
+}
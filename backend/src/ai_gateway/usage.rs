@@ -0,0 +1,432 @@
+// usage.rs — Gateway usage ledger and plan quota estimation.
+//
+// Every upstream attempt made by `proxy_chat`, `proxy_stream` and
+// `/v1/chat/completions` is recorded: written to `ch_gateway_usage` (when a
+// database is attached) and kept in an in-memory rolling window per provider.
+// The windows estimate how much of each subscription plan's quota
+// (`ProviderConfig::quota`) is used; an HTTP 429 puts the provider in a
+// cooldown that doubles on repeated 429s. `order_chain` moves providers that
+// are near their limit or cooling down to the end of `ModelRouter`'s
+// fallback chain, so they are only tried when everything else failed.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use super::handlers::openai_translate::{CompletionUsage, parse_completion};
+use super::{AiProvider, GatewayProviders, PlanQuota};
+
+/// Utilisation from which a provider is tried last.
+pub const NEAR_LIMIT: f64 = 0.9;
+/// Longest window kept in memory (and seeded from the ledger at startup).
+const MAX_WINDOW: Duration = Duration::from_secs(24 * 3600);
+const BASE_COOLDOWN: Duration = Duration::from_secs(60);
+const MAX_COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Records
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// One upstream attempt.
+#[derive(Debug, Clone)]
+pub struct UsageRecord {
+    /// Built-in provider name or OpenAI-compatible endpoint id.
+    pub provider: String,
+    pub model: String,
+    /// `chat`, `stream` or `v1`.
+    pub endpoint: &'static str,
+    /// Who made the call (`user:<email>` for signed-in callers).
+    pub caller: Option<String>,
+    /// Upstream HTTP status; 0 when no response was received.
+    pub status: u16,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    pub latency_ms: u64,
+    /// 0 for the first provider tried.
+    pub fallback_attempt: usize,
+    /// Failure detail for the provider's health; not stored in the ledger.
+    pub error: Option<String>,
+}
+
+impl UsageRecord {
+    /// An attempt that got no upstream response (yet).
+    pub fn new(
+        endpoint: &'static str,
+        provider: impl Into<String>,
+        model: &str,
+        fallback_attempt: usize,
+        started: Instant,
+    ) -> Self {
+        Self {
+            provider: provider.into(),
+            model: model.to_string(),
+            endpoint,
+            caller: None,
+            status: 0,
+            input_tokens: None,
+            output_tokens: None,
+            latency_ms: started.elapsed().as_millis() as u64,
+            fallback_attempt,
+            error: None,
+        }
+    }
+
+    pub fn caller(mut self, caller: Option<String>) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    pub fn error(mut self, error: impl ToString) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    /// Attach the upstream status and, for successes, the token usage reported
+    /// in the body (`format` is the wire format of the body).
+    pub fn response(mut self, status: u16, format: &AiProvider, body: &Value) -> Self {
+        self.status = status;
+        if (200..300).contains(&status)
+            && let Some(usage) = parse_completion(format, body).usage
+        {
+            self.input_tokens = Some(usage.prompt_tokens);
+            self.output_tokens = Some(usage.completion_tokens);
+        }
+        self
+    }
+
+    /// Attach the status of a streamed response and the token usage the
+    /// stream reported, if any.
+    pub(crate) fn streamed(mut self, status: u16, usage: Option<CompletionUsage>) -> Self {
+        self.status = status;
+        if let Some(usage) = usage {
+            self.input_tokens = Some(usage.prompt_tokens);
+            self.output_tokens = Some(usage.completion_tokens);
+        }
+        self
+    }
+
+    fn tokens(&self) -> u64 {
+        self.input_tokens.unwrap_or(0) + self.output_tokens.unwrap_or(0)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Quota estimation
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Default)]
+struct ProviderWindow {
+    /// `(at, tokens)` of successful calls within `MAX_WINDOW`.
+    calls: VecDeque<(Instant, u64)>,
+    cooldown_until: Option<Instant>,
+    consecutive_429: u32,
+}
+
+impl ProviderWindow {
+    fn prune(&mut self, now: Instant) {
+        while let Some((at, _)) = self.calls.front() {
+            if now.duration_since(*at) <= MAX_WINDOW {
+                break;
+            }
+            self.calls.pop_front();
+        }
+    }
+
+    fn record(&mut self, at: Instant, status: u16, tokens: u64) {
+        match status {
+            200..=299 => {
+                self.calls.push_back((at, tokens));
+                self.consecutive_429 = 0;
+            }
+            429 => {
+                let cooldown = BASE_COOLDOWN
+                    .saturating_mul(2u32.saturating_pow(self.consecutive_429))
+                    .min(MAX_COOLDOWN);
+                self.cooldown_until = Some(at + cooldown);
+                self.consecutive_429 += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Estimated quota state of one provider.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaEstimate {
+    pub provider: String,
+    pub quota: Option<PlanQuota>,
+    /// Successful requests within the quota window (last 24 h without a quota).
+    pub requests: u64,
+    pub tokens: u64,
+    /// Highest used fraction of the request and token limits.
+    pub utilization: Option<f64>,
+    /// Seconds left of a 429 cooldown.
+    pub cooldown_secs: Option<u64>,
+    /// Tried only after all other providers.
+    pub deprioritized: bool,
+}
+
+fn estimate(
+    provider: &str,
+    window: Option<&ProviderWindow>,
+    quota: Option<PlanQuota>,
+    now: Instant,
+) -> QuotaEstimate {
+    let span = quota
+        .map(|q| Duration::from_secs(q.window_secs).min(MAX_WINDOW))
+        .unwrap_or(MAX_WINDOW);
+    let (requests, tokens) = window
+        .map(|w| {
+            w.calls
+                .iter()
+                .filter(|(at, _)| now.duration_since(*at) <= span)
+                .fold((0, 0), |(n, t), (_, tokens)| (n + 1, t + tokens))
+        })
+        .unwrap_or_default();
+
+    let utilization = quota.filter(|q| q.window_secs > 0).and_then(|q| {
+        let by_requests = q
+            .max_requests
+            .filter(|max| *max > 0)
+            .map(|max| requests as f64 / f64::from(max));
+        let by_tokens = q
+            .max_tokens
+            .filter(|max| *max > 0)
+            .map(|max| tokens as f64 / max as f64);
+        by_requests.into_iter().chain(by_tokens).reduce(f64::max)
+    });
+    let cooldown_secs = window
+        .and_then(|w| w.cooldown_until)
+        .filter(|until| *until > now)
+        .map(|until| until.duration_since(now).as_secs().max(1));
+
+    QuotaEstimate {
+        provider: provider.to_string(),
+        quota,
+        requests,
+        tokens,
+        utilization,
+        cooldown_secs,
+        deprioritized: cooldown_secs.is_some() || utilization.is_some_and(|u| u >= NEAR_LIMIT),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Ledger
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Usage ledger of the gateway. Without a database only the in-memory
+/// windows are kept (tests).
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    db: Option<PgPool>,
+    windows: Mutex<HashMap<String, ProviderWindow>>,
+}
+
+impl UsageLedger {
+    pub fn new(db: Option<PgPool>) -> Self {
+        Self {
+            db,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fill the in-memory windows from the last 24 h of the ledger, so a
+    /// restart does not forget how much of each plan is used.
+    pub async fn seed(&self) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let rows: Vec<(String, i32, i64, f64)> = match sqlx::query_as(
+            "SELECT provider, status, \
+             COALESCE(input_tokens, 0) + COALESCE(output_tokens, 0), \
+             EXTRACT(EPOCH FROM NOW() - created_at)::FLOAT8 \
+             FROM ch_gateway_usage \
+             WHERE created_at > NOW() - INTERVAL '24 hours' AND (status BETWEEN 200 AND 299 OR status = 429) \
+             ORDER BY created_at",
+        )
+        .fetch_all(db)
+        .await
+        {
+            Ok(rows) => rows,
+            Err(e) => {
+                tracing::warn!("usage: failed to seed quota windows: {}", e);
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        for (provider, status, tokens, age_secs) in rows {
+            let at = now
+                .checked_sub(Duration::from_secs_f64(age_secs.max(0.0)))
+                .unwrap_or(now);
+            windows.entry(provider).or_default().record(
+                at,
+                u16::try_from(status).unwrap_or_default(),
+                u64::try_from(tokens).unwrap_or_default(),
+            );
+        }
+    }
+
+    /// Record an upstream attempt: update the window and append to the ledger.
+    pub fn record(&self, record: UsageRecord) {
+        let now = Instant::now();
+        {
+            let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+            let window = windows.entry(record.provider.clone()).or_default();
+            window.prune(now);
+            window.record(now, record.status, record.tokens());
+        }
+
+        let Some(db) = self.db.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            let result = sqlx::query(
+                "INSERT INTO ch_gateway_usage \
+                 (provider, model, endpoint, caller, status, input_tokens, output_tokens, \
+                  latency_ms, fallback_attempt) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            )
+            .bind(&record.provider)
+            .bind(&record.model)
+            .bind(record.endpoint)
+            .bind(&record.caller)
+            .bind(i32::from(record.status))
+            .bind(record.input_tokens.map(|t| t as i64))
+            .bind(record.output_tokens.map(|t| t as i64))
+            .bind(record.latency_ms as i64)
+            .bind(record.fallback_attempt as i32)
+            .execute(&db)
+            .await;
+            if let Err(e) = result {
+                tracing::warn!("usage: failed to write ledger entry: {}", e);
+            }
+        });
+    }
+
+    /// Quota estimate of one provider.
+    pub fn estimate(&self, provider: &str, quota: Option<PlanQuota>) -> QuotaEstimate {
+        let windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        estimate(provider, windows.get(provider), quota, Instant::now())
+    }
+
+    /// Estimates of every enabled provider: built-ins (by name), then the
+    /// OpenAI-compatible endpoints.
+    pub fn estimates(&self, providers: &GatewayProviders) -> Vec<QuotaEstimate> {
+        let mut builtin: Vec<_> = providers.builtin.values().collect();
+        builtin.sort_by_key(|c| c.provider.to_string());
+        builtin
+            .into_iter()
+            .map(|c| self.estimate(&c.provider.to_string(), c.quota))
+            .chain(
+                providers
+                    .compatible
+                    .iter()
+                    .map(|p| self.estimate(&p.id, None)),
+            )
+            .collect()
+    }
+
+    /// Move providers near their plan limit or in a 429 cooldown to the end
+    /// of a fallback chain, keeping the relative order otherwise.
+    pub fn order_chain(
+        &self,
+        chain: Vec<AiProvider>,
+        providers: &GatewayProviders,
+    ) -> Vec<AiProvider> {
+        let (ready, deprioritized): (Vec<_>, Vec<_>) = chain.into_iter().partition(|p| {
+            let quota = providers.get(p).and_then(|c| c.quota);
+            !self.estimate(&p.to_string(), quota).deprioritized
+        });
+        if !deprioritized.is_empty() {
+            tracing::info!(
+                skipped = ?deprioritized,
+                "usage: providers near their plan limit moved to the end of the fallback chain",
+            );
+        }
+        ready.into_iter().chain(deprioritized).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUOTA: PlanQuota = PlanQuota {
+        window_secs: 3600,
+        max_requests: Some(10),
+        max_tokens: Some(1000),
+    };
+
+    #[test]
+    fn utilization_counts_the_quota_window_only() {
+        let earlier = Instant::now();
+        let now = earlier + Duration::from_secs(7200);
+        let mut window = ProviderWindow::default();
+        window.record(earlier, 200, 500);
+        for _ in 0..8 {
+            window.record(now, 200, 10);
+        }
+        window.record(now, 500, 0);
+
+        let before = estimate("openai", Some(&window), Some(QUOTA), now);
+        assert_eq!(before.requests, 8);
+        assert_eq!(before.tokens, 80);
+        assert_eq!(before.utilization, Some(0.8));
+        assert!(!before.deprioritized);
+
+        window.record(now, 200, 900);
+        let after = estimate("openai", Some(&window), Some(QUOTA), now);
+        assert_eq!(after.utilization, Some(0.98));
+        assert!(after.deprioritized);
+    }
+
+    #[test]
+    fn repeated_429_doubles_the_cooldown() {
+        let now = Instant::now();
+        let mut window = ProviderWindow::default();
+        window.record(now, 429, 0);
+        assert_eq!(
+            estimate("xai", Some(&window), None, now).cooldown_secs,
+            Some(60)
+        );
+        window.record(now, 429, 0);
+        let limited = estimate("xai", Some(&window), None, now);
+        assert_eq!(limited.cooldown_secs, Some(120));
+        assert!(limited.deprioritized);
+
+        window.record(now, 200, 0);
+        assert_eq!(window.consecutive_429, 0);
+    }
+
+    #[test]
+    fn order_chain_moves_limited_providers_last() {
+        let ledger = UsageLedger::new(None);
+        let providers = GatewayProviders::defaults();
+        let started = Instant::now();
+        ledger.record(UsageRecord {
+            status: 429,
+            ..UsageRecord::new("chat", "anthropic", "claude-sonnet-4-6", 0, started)
+        });
+
+        let chain = vec![
+            AiProvider::Anthropic,
+            AiProvider::OpenAI,
+            AiProvider::Google,
+        ];
+        assert_eq!(
+            ledger.order_chain(chain, &providers),
+            vec![
+                AiProvider::OpenAI,
+                AiProvider::Google,
+                AiProvider::Anthropic
+            ]
+        );
+    }
+}
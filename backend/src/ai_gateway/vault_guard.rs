@@ -0,0 +1,625 @@
+// vault_guard.rs — Credential-source policy, encrypted Vault session cache and
+// the anomaly lockout.
+//
+// - Policy (`CREDENTIAL_POLICY`): what credential resolution may use when
+//   Vault cannot answer. `vault_only` fails the call, `vault_with_cache` keeps
+//   using a recently confirmed Vault session, `env_allowed` (the default, and
+//   the previous behaviour) additionally falls back to runtime / env API keys.
+// - Cache: Vault lookups that confirmed a delegated session, sealed with
+//   AES-256-GCM (`OAUTH_ENCRYPTION_KEY` / `AUTH_SECRET`) and kept for
+//   `VAULT_CACHE_TTL_SECS` (default 300). Only read while Vault is
+//   unreachable; without an encryption key nothing is cached.
+// - Lockout: set by a Vault anomaly or `vault_panic`. Every outbound AI call
+//   is refused until an operator clears it (`POST /api/vault/lockout/clear`);
+//   it is persisted in `ch_vault_lockout`, so a restart does not lift it.
+//   Writes to that table notify `ch_config_changed`, and the config cache
+//   listener reloads the lockout on every replica (`reload`). Local writes
+//   are serialised, and each is awaited, so a lock cannot land after the
+//   clear that followed it.
+// - Calls authenticated with a runtime or env API key rather than a Vault
+//   session (embeddings, OCR, model listings, fallback providers) pass
+//   `check_api_key`, which applies both the lockout and the policy.
+//
+// `GET /api/vault/status` reports all three plus the source each credential
+// was last resolved from.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use super::vault_bridge::VaultError;
+
+/// Vault address used when `VAULT_URL` is not set.
+pub const DEFAULT_VAULT_URL: &str = "http://localhost:5190";
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+/// Upper bound of `VAULT_CACHE_TTL_SECS` — the cache bridges outages, it is
+/// not a second credential store.
+const MAX_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// The Vault address (`VAULT_URL`, default `http://localhost:5190`).
+pub fn vault_url() -> String {
+    std::env::var("VAULT_URL")
+        .ok()
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_VAULT_URL.to_string())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Policy and sources
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialPolicy {
+    /// Only live Vault answers.
+    VaultOnly,
+    /// Live Vault, then a cached Vault session while Vault is unreachable.
+    VaultWithCache,
+    /// Vault, cached Vault session, then runtime / env API keys.
+    EnvAllowed,
+}
+
+impl CredentialPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "vault_only" => Some(Self::VaultOnly),
+            "vault_with_cache" => Some(Self::VaultWithCache),
+            "env_allowed" => Some(Self::EnvAllowed),
+            _ => None,
+        }
+    }
+
+    /// `CREDENTIAL_POLICY`, `env_allowed` when unset or unknown.
+    pub fn from_env() -> Self {
+        match std::env::var("CREDENTIAL_POLICY") {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                tracing::warn!("Unknown CREDENTIAL_POLICY '{}' — using env_allowed", value);
+                Self::EnvAllowed
+            }),
+            Err(_) => Self::EnvAllowed,
+        }
+    }
+
+    pub fn allows_cache(self) -> bool {
+        self != Self::VaultOnly
+    }
+
+    pub fn allows_env(self) -> bool {
+        self == Self::EnvAllowed
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::VaultOnly => "vault_only",
+            Self::VaultWithCache => "vault_with_cache",
+            Self::EnvAllowed => "env_allowed",
+        }
+    }
+}
+
+impl fmt::Display for CredentialPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where a credential was resolved from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialSource {
+    Vault,
+    VaultCache,
+    RuntimeKey,
+    EnvKey,
+    /// Nothing the policy allows was available.
+    Unavailable,
+    /// Refused by the lockout.
+    Locked,
+}
+
+/// Last resolution of one credential.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceRecord {
+    pub source: CredentialSource,
+    pub at: DateTime<Utc>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Lockout
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockoutTrigger {
+    Anomaly,
+    VaultPanic,
+}
+
+impl LockoutTrigger {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Anomaly => "anomaly",
+            Self::VaultPanic => "vault_panic",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "vault_panic" => Self::VaultPanic,
+            _ => Self::Anomaly,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Lockout {
+    pub trigger: LockoutTrigger,
+    pub reason: String,
+    pub since: DateTime<Utc>,
+}
+
+impl fmt::Display for Lockout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "credential lockout ({}) since {}: {}",
+            self.trigger.as_str(),
+            self.since.to_rfc3339(),
+            self.reason
+        )
+    }
+}
+
+/// The lockout row, if any.
+async fn load_lockout(db: &PgPool) -> Result<Option<Lockout>, sqlx::Error> {
+    let row: Option<(String, String, DateTime<Utc>)> =
+        sqlx::query_as("SELECT trigger, reason, since FROM ch_vault_lockout WHERE id")
+            .fetch_optional(db)
+            .await?;
+    Ok(row.map(|(trigger, reason, since)| Lockout {
+        trigger: LockoutTrigger::parse(&trigger),
+        reason,
+        since,
+    }))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+//  Guard
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct Sealed {
+    data: String,
+    stored_at: Instant,
+}
+
+/// Cached Vault session, as reported by `/api/vault/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedEntry {
+    pub credential: String,
+    pub age_secs: u64,
+}
+
+/// Snapshot for `/api/vault/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultGuardStatus {
+    pub policy: CredentialPolicy,
+    pub cache_enabled: bool,
+    pub cache_ttl_secs: u64,
+    pub cached: Vec<CachedEntry>,
+    pub lockout: Option<Lockout>,
+    pub sources: HashMap<String, SourceRecord>,
+}
+
+#[derive(Debug)]
+pub struct VaultGuard {
+    policy: CredentialPolicy,
+    cache_ttl: Duration,
+    db: Option<PgPool>,
+    cache: Mutex<HashMap<String, Sealed>>,
+    lockout: Mutex<Option<Lockout>>,
+    /// Held across each lockout write (and reload) with its database round trip.
+    lockout_writes: tokio::sync::Mutex<()>,
+    sources: Mutex<HashMap<String, SourceRecord>>,
+}
+
+impl VaultGuard {
+    pub fn new(policy: CredentialPolicy, cache_ttl: Duration, db: Option<PgPool>) -> Self {
+        Self {
+            policy,
+            cache_ttl: cache_ttl.min(MAX_CACHE_TTL),
+            db,
+            cache: Mutex::new(HashMap::new()),
+            lockout: Mutex::new(None),
+            lockout_writes: tokio::sync::Mutex::new(()),
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Policy from `CREDENTIAL_POLICY`, cache TTL from `VAULT_CACHE_TTL_SECS`.
+    pub fn from_env(db: Option<PgPool>) -> Self {
+        let cache_ttl = std::env::var("VAULT_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CACHE_TTL);
+        let guard = Self::new(CredentialPolicy::from_env(), cache_ttl, db);
+        if guard.policy == CredentialPolicy::VaultWithCache && !guard.cache_enabled() {
+            tracing::warn!(
+                "CREDENTIAL_POLICY=vault_with_cache needs OAUTH_ENCRYPTION_KEY or AUTH_SECRET — \
+                 Vault sessions will not be cached"
+            );
+        }
+        guard
+    }
+
+    /// Take over the persisted lockout — at startup, and whenever
+    /// `ch_vault_lockout` changed (possibly on another replica). Keeps the
+    /// current state when the database cannot be read.
+    pub async fn reload(&self) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let _writes = self.lockout_writes.lock().await;
+        let persisted = match load_lockout(db).await {
+            Ok(persisted) => persisted,
+            Err(e) => {
+                tracing::warn!("vault_guard: failed to load the persisted lockout: {}", e);
+                return;
+            }
+        };
+        let previous = {
+            let mut current = self.lockout.lock().unwrap_or_else(|e| e.into_inner());
+            std::mem::replace(&mut *current, persisted.clone())
+        };
+        match (previous, persisted) {
+            (None, Some(lockout)) => {
+                self.cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+                tracing::error!("vault_guard: {} — outbound AI calls halted", lockout);
+            }
+            (Some(_), None) => tracing::warn!("vault_guard: lockout cleared"),
+            _ => {}
+        }
+    }
+
+    pub fn policy(&self) -> CredentialPolicy {
+        self.policy
+    }
+
+    pub fn cache_enabled(&self) -> bool {
+        self.policy.allows_cache()
+            && !self.cache_ttl.is_zero()
+            && jaskier_net_sec::oauth::is_encryption_configured()
+    }
+
+    // ── Lockout ─────────────────────────────────────────────────────────
+
+    /// `Err` while a lockout is active — no outbound AI call may be made.
+    pub fn check(&self) -> Result<(), Lockout> {
+        match &*self.lockout.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(lockout) => Err(lockout.clone()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn lockout(&self) -> Option<Lockout> {
+        self.lockout
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Start a lockout, drop the cache and persist it. An active lockout —
+    /// also one another replica persisted first — keeps its original trigger;
+    /// returns whether this call started one. It waits for a `clear` in
+    /// progress, and returns once the lockout is stored.
+    pub async fn lock(&self, trigger: LockoutTrigger, reason: impl Into<String>) -> bool {
+        let _writes = self.lockout_writes.lock().await;
+        let lockout = {
+            let mut current = self.lockout.lock().unwrap_or_else(|e| e.into_inner());
+            if current.is_some() {
+                return false;
+            }
+            let lockout = Lockout {
+                trigger,
+                reason: reason.into(),
+                since: Utc::now(),
+            };
+            *current = Some(lockout.clone());
+            lockout
+        };
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        tracing::error!("vault_guard: {} — outbound AI calls halted", lockout);
+
+        let Some(db) = &self.db else {
+            return true;
+        };
+        let inserted = sqlx::query(
+            "INSERT INTO ch_vault_lockout (trigger, reason, since) VALUES ($1, $2, $3) \
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(lockout.trigger.as_str())
+        .bind(&lockout.reason)
+        .bind(lockout.since)
+        .execute(db)
+        .await;
+        match inserted {
+            Ok(result) if result.rows_affected() == 1 => true,
+            // Another replica locked first: adopt its lockout.
+            Ok(_) => {
+                if let Ok(Some(persisted)) = load_lockout(db).await {
+                    *self.lockout.lock().unwrap_or_else(|e| e.into_inner()) = Some(persisted);
+                }
+                false
+            }
+            Err(e) => {
+                // Still locked here; other replicas and restarts will not know.
+                tracing::error!("vault_guard: failed to persist the lockout: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Lock on a Vault anomaly; returns whether `err` was one.
+    pub async fn observe(&self, err: &VaultError) -> bool {
+        if let VaultError::AnomalyDetected(msg) = err {
+            self.lock(LockoutTrigger::Anomaly, msg.clone()).await;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Lift the lockout (operator action); returns the one that was active.
+    /// Other replicas lift theirs when the deletion's notification arrives.
+    pub async fn clear(&self) -> anyhow::Result<Option<Lockout>> {
+        let _writes = self.lockout_writes.lock().await;
+        if let Some(db) = &self.db {
+            sqlx::query("DELETE FROM ch_vault_lockout")
+                .execute(db)
+                .await?;
+        }
+        Ok(self
+            .lockout
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take())
+    }
+
+    /// Gate a call authenticated with a runtime or env API key instead of a
+    /// Vault session: refused during a lockout, and unless the policy is
+    /// `env_allowed`. Records `source` (or the refusal) for `credential`.
+    pub fn check_api_key(&self, credential: &str, source: CredentialSource) -> Result<(), String> {
+        if let Err(lockout) = self.check() {
+            self.record_source(credential, CredentialSource::Locked);
+            return Err(lockout.to_string());
+        }
+        if !self.policy.allows_env() {
+            self.record_source(credential, CredentialSource::Unavailable);
+            return Err(format!(
+                "CREDENTIAL_POLICY={} does not allow {} from an API key",
+                self.policy, credential
+            ));
+        }
+        self.record_source(credential, source);
+        Ok(())
+    }
+
+    // ── Session cache ───────────────────────────────────────────────────
+
+    fn cache_key(namespace: &str, service: &str) -> String {
+        format!("{}/{}", namespace, service)
+    }
+
+    /// Remember a Vault answer confirming a delegated session.
+    pub fn cache_put(&self, namespace: &str, service: &str, data: &Value) {
+        if !self.cache_enabled() || self.check().is_err() {
+            return;
+        }
+        match jaskier_net_sec::oauth::encrypt_token(&data.to_string()) {
+            Ok(sealed) => {
+                self.cache.lock().unwrap_or_else(|e| e.into_inner()).insert(
+                    Self::cache_key(namespace, service),
+                    Sealed {
+                        data: sealed,
+                        stored_at: Instant::now(),
+                    },
+                );
+            }
+            Err(e) => tracing::warn!("vault_guard: failed to seal a Vault session: {}", e),
+        }
+    }
+
+    /// The cached answer, while it is younger than the TTL.
+    pub fn cache_get(&self, namespace: &str, service: &str) -> Option<Value> {
+        if !self.cache_enabled() || self.check().is_err() {
+            return None;
+        }
+        let sealed = {
+            let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            cache.retain(|_, s| s.stored_at.elapsed() < self.cache_ttl);
+            cache
+                .get(&Self::cache_key(namespace, service))?
+                .data
+                .clone()
+        };
+        jaskier_net_sec::oauth::decrypt_token(&sealed)
+            .ok()
+            .and_then(|plain| serde_json::from_str(&plain).ok())
+    }
+
+    /// Forget a session Vault reported as gone.
+    pub fn cache_forget(&self, namespace: &str, service: &str) {
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&Self::cache_key(namespace, service));
+    }
+
+    // ── Sources ─────────────────────────────────────────────────────────
+
+    pub fn record_source(&self, credential: &str, source: CredentialSource) {
+        self.sources
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                credential.to_string(),
+                SourceRecord {
+                    source,
+                    at: Utc::now(),
+                },
+            );
+    }
+
+    pub fn status(&self) -> VaultGuardStatus {
+        let cached = {
+            let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            cache.retain(|_, s| s.stored_at.elapsed() < self.cache_ttl);
+            cache
+                .iter()
+                .map(|(credential, s)| CachedEntry {
+                    credential: credential.clone(),
+                    age_secs: s.stored_at.elapsed().as_secs(),
+                })
+                .collect()
+        };
+        VaultGuardStatus {
+            policy: self.policy,
+            cache_enabled: self.cache_enabled(),
+            cache_ttl_secs: self.cache_ttl.as_secs(),
+            cached,
+            lockout: self.lockout(),
+            sources: self
+                .sources
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_parse() {
+        assert_eq!(
+            CredentialPolicy::parse("vault-only"),
+            Some(CredentialPolicy::VaultOnly)
+        );
+        assert_eq!(
+            CredentialPolicy::parse(" Vault_With_Cache "),
+            Some(CredentialPolicy::VaultWithCache)
+        );
+        assert_eq!(
+            CredentialPolicy::parse("env_allowed"),
+            Some(CredentialPolicy::EnvAllowed)
+        );
+        assert_eq!(CredentialPolicy::parse("anything"), None);
+        assert!(!CredentialPolicy::VaultOnly.allows_cache());
+        assert!(!CredentialPolicy::VaultWithCache.allows_env());
+    }
+
+    #[tokio::test]
+    async fn lockout_keeps_first_trigger_until_cleared() {
+        let guard = VaultGuard::new(CredentialPolicy::VaultOnly, DEFAULT_CACHE_TTL, None);
+        assert!(guard.check().is_ok());
+
+        assert!(
+            guard
+                .observe(&VaultError::AnomalyDetected("ticket replay".into()))
+                .await
+        );
+        assert!(!guard.lock(LockoutTrigger::VaultPanic, "operator").await);
+        let lockout = guard.check().unwrap_err();
+        assert_eq!(lockout.trigger, LockoutTrigger::Anomaly);
+        assert_eq!(lockout.reason, "ticket replay");
+
+        assert!(!guard.observe(&VaultError::NotFound).await);
+        let cleared = guard.clear().await.unwrap();
+        assert_eq!(cleared.map(|l| l.trigger), Some(LockoutTrigger::Anomaly));
+        assert!(guard.check().is_ok());
+    }
+
+    /// Skipped unless DATABASE_URL points at a disposable database.
+    #[tokio::test]
+    async fn replicas_share_the_persisted_lockout() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        sqlx::query("DELETE FROM ch_vault_lockout")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let replica = |db: &PgPool| {
+            VaultGuard::new(
+                CredentialPolicy::VaultOnly,
+                DEFAULT_CACHE_TTL,
+                Some(db.clone()),
+            )
+        };
+        let (a, b) = (replica(&pool), replica(&pool));
+
+        // The write is done once `lock` returns, so `clear` cannot precede it.
+        assert!(a.lock(LockoutTrigger::Anomaly, "ticket replay").await);
+        b.reload().await;
+        assert_eq!(b.check().unwrap_err().reason, "ticket replay");
+        b.clear().await.unwrap();
+        a.reload().await;
+        assert!(a.check().is_ok());
+        // A replica locking second adopts the lockout persisted first.
+        assert!(b.lock(LockoutTrigger::VaultPanic, "operator").await);
+        assert!(!a.lock(LockoutTrigger::Anomaly, "late").await);
+        assert_eq!(a.check().unwrap_err().trigger, LockoutTrigger::VaultPanic);
+
+        a.clear().await.unwrap();
+        b.reload().await;
+        assert!(b.check().is_ok());
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ch_vault_lockout")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 0);
+    }
+
+    #[tokio::test]
+    async fn api_keys_need_env_allowed_and_no_lockout() {
+        let guard = VaultGuard::new(CredentialPolicy::EnvAllowed, DEFAULT_CACHE_TTL, None);
+        assert!(
+            guard
+                .check_api_key("google", CredentialSource::EnvKey)
+                .is_ok()
+        );
+        guard.lock(LockoutTrigger::VaultPanic, "operator").await;
+        assert!(
+            guard
+                .check_api_key("google", CredentialSource::EnvKey)
+                .is_err()
+        );
+        assert_eq!(
+            guard.status().sources["google"].source,
+            CredentialSource::Locked
+        );
+
+        let strict = VaultGuard::new(CredentialPolicy::VaultWithCache, DEFAULT_CACHE_TTL, None);
+        assert!(
+            strict
+                .check_api_key("google", CredentialSource::EnvKey)
+                .is_err()
+        );
+        assert_eq!(
+            strict.status().sources["google"].source,
+            CredentialSource::Unavailable
+        );
+    }
+}
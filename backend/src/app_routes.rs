@@ -19,10 +19,12 @@
 //! - `ch_vault_public_routes`    — Vault health/audit (public)
 //! - `ch_vault_protected_routes` — Vault panic/rotate (auth)
 //! - `ch_auto_qa_routes`     — Grafana webhook endpoint (public)
+//! - `ch_openai_compat_routes` — OpenAI-compatible `/v1/*` facade (auth required)
 
 use axum::Router;
-use axum::routing::{delete, get, patch, post};
+use axum::routing::{delete, get, patch, post, put};
 
+use crate::ai_gateway::handlers::openai;
 use crate::auth;
 use crate::browser_proxy;
 use crate::handlers;
@@ -199,6 +201,35 @@ pub(crate) fn ch_app_protected_routes() -> Router<AppState> {
         .route("/api/claude/models", get(handlers::claude_models))
         // Session search (literal path, NOT in shared session_routes)
         .route("/api/sessions/search", get(handlers::search_sessions))
+        // Hybrid semantic + keyword search, related sessions, embedding backfill
+        .route("/api/sessions/search/hybrid", get(handlers::hybrid_search))
+        .route(
+            "/api/sessions/{id}/related",
+            get(handlers::related_sessions),
+        )
+        .route(
+            "/api/sessions/embeddings/backfill",
+            post(handlers::backfill_embeddings),
+        )
+        .route(
+            "/api/sessions/embeddings/status",
+            get(handlers::embeddings_status),
+        )
+        // Session branching — forks, edited turns, branch checkout
+        .route("/api/sessions/{id}/fork", post(handlers::fork_session))
+        .route(
+            "/api/sessions/{id}/messages/{message_id}/edit",
+            post(handlers::edit_message),
+        )
+        .route(
+            "/api/sessions/{id}/checkout",
+            post(handlers::checkout_branch),
+        )
+        .route("/api/sessions/{id}/branches", get(handlers::list_branches))
+        // Session export / import
+        .route("/api/sessions/export", get(handlers::export_sessions))
+        .route("/api/sessions/{id}/export", get(handlers::export_session))
+        .route("/api/sessions/import", post(handlers::import_sessions))
         // Session tags (NOT in shared session_routes)
         .route(
             "/api/sessions/{id}/tags",
@@ -223,7 +254,58 @@ pub(crate) fn ch_app_protected_routes() -> Router<AppState> {
             "/api/analytics/top-tools",
             get(handlers::analytics_top_tools),
         )
+        .route(
+            "/api/analytics/tool-latency",
+            get(handlers::analytics_tool_latency),
+        )
         .route("/api/analytics/cost", get(handlers::analytics_cost))
+        .route("/api/analytics/budgets", get(handlers::budget_status))
+        .route(
+            "/api/analytics/executions/{execution_id}",
+            get(handlers::analytics_execution),
+        )
+        .route(
+            "/api/analytics/sessions/{id}",
+            get(handlers::analytics_session),
+        )
+        // Spending budgets
+        .route(
+            "/api/budgets",
+            get(handlers::list_budgets).post(handlers::create_budget),
+        )
+        .route(
+            "/api/budgets/{id}",
+            put(handlers::update_budget).delete(handlers::delete_budget),
+        )
+        // Pricing catalogue
+        .route(
+            "/api/pricing",
+            get(handlers::list_pricing).post(handlers::create_price),
+        )
+        .route("/api/pricing/{id}", delete(handlers::delete_price))
+        // AI gateway provider configuration
+        .route(
+            "/api/ai/provider-configs",
+            get(handlers::list_provider_configs).post(handlers::create_provider_config),
+        )
+        .route(
+            "/api/ai/provider-configs/{id}",
+            put(handlers::update_provider_config).delete(handlers::delete_provider_config),
+        )
+        .route("/api/ai/usage", get(handlers::gateway_usage))
+        // A/B experiments
+        .route(
+            "/api/experiments",
+            get(handlers::list_experiments).post(handlers::create_experiment),
+        )
+        .route(
+            "/api/experiments/{id}",
+            patch(handlers::update_experiment).delete(handlers::delete_experiment),
+        )
+        .route(
+            "/api/experiments/{id}/results",
+            get(handlers::experiment_results),
+        )
 }
 
 /// Prometheus metrics endpoint (public, no auth).
@@ -242,18 +324,37 @@ pub(crate) fn ch_profiling_routes() -> Router<AppState> {
     )
 }
 
-/// Vault proxy public endpoints (health + audit — no auth required).
+/// Vault proxy public endpoints (health + audit + status — no auth required).
 pub(crate) fn ch_vault_public_routes() -> Router<AppState> {
     Router::new()
         .route("/api/vault/health", get(vault_proxy::vault_health))
         .route("/api/vault/audit", get(vault_proxy::vault_audit))
+        .route("/api/vault/status", get(vault_proxy::vault_status))
 }
 
-/// Vault proxy protected endpoints (panic + rotate — auth required).
+/// Vault proxy protected endpoints (panic + rotate + lockout clear — auth required).
 pub(crate) fn ch_vault_protected_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/api/vault/panic", post(vault_proxy::vault_panic))
         .route("/api/vault/rotate", post(vault_proxy::vault_rotate))
+        .route(
+            "/api/vault/lockout/clear",
+            post(vault_proxy::vault_lockout_clear),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            state,
+            auth::jaskier_auth_require_auth::<AppState>,
+        ))
+}
+
+/// OpenAI-compatible facade over the AI gateway (auth applied via `route_layer`).
+pub(crate) fn ch_openai_compat_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/v1/models", get(openai::list_models::<AppState>))
+        .route(
+            "/v1/chat/completions",
+            post(openai::chat_completions::<AppState>),
+        )
         .route_layer(axum::middleware::from_fn_with_state(
             state,
             auth::jaskier_auth_require_auth::<AppState>,
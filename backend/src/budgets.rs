@@ -0,0 +1,682 @@
+// ClaudeHydra v4 -- Spending budgets
+//
+// Budgets cap spend (USD priced via the pricing catalogue, or raw tokens) per
+// user, session or delegated agent over the current day or month. They are
+// evaluated before every Anthropic call of the WebSocket chat paths, of the
+// NDJSON chat stream and of `execute_agent_call`: a crossed soft limit
+// produces a `WsServerMessage::BudgetWarning` (WebSocket only), a crossed
+// hard limit stops the execution with the `BUDGET_EXCEEDED` error code. Usage
+// without a signed-in user is measured as the user `anonymous`. Each
+// execution loads its budgets and
+// their spend once (`RunBudgets`) and adds its own and its delegations' usage
+// in memory. Checks fail open — a database error never blocks chat.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::handlers::pricing::{PRICE_AT_USE_JOIN, USAGE_COST_EXPR};
+use crate::handlers::streaming::helpers::TOP_LEVEL_AGENT_ID;
+use crate::state::AppState;
+
+/// Error code sent to clients when a hard limit stops an execution.
+pub const BUDGET_EXCEEDED: &str = "BUDGET_EXCEEDED";
+
+/// User budget subject of usage recorded without a signed-in user (emails
+/// always contain `@`, so it cannot name a real user).
+pub(crate) const ANONYMOUS_USER: &str = "anonymous";
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Types
+// ═══════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct BudgetRow {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub scope: String,
+    pub scope_id: Option<String>,
+    pub period: String,
+    pub unit: String,
+    pub soft_limit: Option<f64>,
+    pub hard_limit: Option<f64>,
+}
+
+pub(crate) const BUDGET_COLUMNS: &str =
+    "id, name, scope, scope_id, period, unit, soft_limit, hard_limit";
+
+/// Whom the spend of a request is attributed to.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BudgetSubject {
+    /// Signed-in user's email, `None` for anonymous requests.
+    pub user: Option<String>,
+    pub session_id: Option<uuid::Uuid>,
+    /// Delegated agent name (`call_agent`), `None` for top-level chat.
+    pub agent: Option<String>,
+}
+
+/// Token usage of the running execution's model — not in `ch_agent_usage`
+/// yet (WebSocket executions record their usage on completion), or recorded
+/// after the run's budgets were loaded.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PendingUsage {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+}
+
+impl PendingUsage {
+    fn tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_write_tokens
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLevel {
+    Soft,
+    Hard,
+}
+
+/// A budget whose soft or hard limit has been reached.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetBreach {
+    pub budget_id: uuid::Uuid,
+    pub name: String,
+    pub scope: String,
+    /// User, session id or agent name the spend was measured for.
+    pub subject: Option<String>,
+    pub period: String,
+    pub unit: String,
+    pub level: BudgetLevel,
+    pub spent: f64,
+    pub limit: f64,
+}
+
+impl BudgetBreach {
+    pub fn message(&self) -> String {
+        let amount = |v: f64| match self.unit.as_str() {
+            "usd" => format!("${:.2}", v),
+            _ => format!("{} tokens", v.round() as i64),
+        };
+        let what = match self.level {
+            BudgetLevel::Soft => "soft limit reached",
+            BudgetLevel::Hard => "hard limit exceeded",
+        };
+        format!(
+            "Budget '{}' ({} {}): {} — {} of {}",
+            self.name,
+            self.period,
+            self.scope,
+            what,
+            amount(self.spent),
+            amount(self.limit)
+        )
+    }
+}
+
+/// Outcome of a pre-call budget check.
+#[derive(Debug, Default)]
+pub(crate) struct BudgetCheck {
+    pub warnings: Vec<BudgetBreach>,
+    pub exceeded: Option<BudgetBreach>,
+}
+
+/// Spend of one subject in the current period of a budget.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct Spend {
+    pub subject: Option<String>,
+    pub usd: f64,
+    pub tokens: i64,
+}
+
+/// Breach counters for Prometheus.
+#[derive(Debug, Default)]
+pub struct BudgetMetrics {
+    soft_breaches: AtomicU64,
+    hard_breaches: AtomicU64,
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Evaluation
+// ═══════════════════════════════════════════════════════════════════════
+
+impl BudgetRow {
+    fn measure(&self, spend: &Spend) -> f64 {
+        self.amount(spend.usd, spend.tokens)
+    }
+
+    /// Usage in the budget's unit.
+    fn amount(&self, usd: f64, tokens: i64) -> f64 {
+        match self.unit.as_str() {
+            "tokens" => tokens as f64,
+            _ => usd,
+        }
+    }
+
+    /// The crossed limit with the highest level, if any.
+    fn breach(&self, spent: f64, subject: Option<String>) -> Option<BudgetBreach> {
+        let (level, limit) = match (self.hard_limit, self.soft_limit) {
+            (Some(hard), _) if spent >= hard => (BudgetLevel::Hard, hard),
+            (_, Some(soft)) if spent >= soft => (BudgetLevel::Soft, soft),
+            _ => return None,
+        };
+        Some(BudgetBreach {
+            budget_id: self.id,
+            name: self.name.clone(),
+            scope: self.scope.clone(),
+            subject,
+            period: self.period.clone(),
+            unit: self.unit.clone(),
+            level,
+            spent,
+            limit,
+        })
+    }
+}
+
+/// Spend in the budget's current period. With `subject` the spend of that
+/// user / session / agent; without it the top spender. `None` when nothing
+/// was spent.
+pub(crate) async fn spend(
+    db: &sqlx::PgPool,
+    budget: &BudgetRow,
+    subject: Option<&str>,
+) -> Result<Option<Spend>, sqlx::Error> {
+    let key = match budget.scope.as_str() {
+        "session" => "u.session_id::text".to_string(),
+        // Top-level usage is not an agent's for agent budgets.
+        "agent" => format!("lower(NULLIF(u.agent_id, '{TOP_LEVEL_AGENT_ID}'))"),
+        _ => format!("COALESCE(u.user_id, '{ANONYMOUS_USER}')"),
+    };
+    let trunc = if budget.period == "monthly" {
+        "month"
+    } else {
+        "day"
+    };
+    let order = if budget.unit == "tokens" {
+        "tokens"
+    } else {
+        "usd"
+    };
+
+    sqlx::query_as::<_, Spend>(&format!(
+        "SELECT {key} AS subject, \
+             COALESCE(SUM({USAGE_COST_EXPR}) FILTER (WHERE price.currency = 'USD'), 0)::float8 AS usd, \
+             COALESCE(SUM(COALESCE(u.total_tokens, 0) + u.cache_read_tokens \
+                 + u.cache_write_tokens), 0)::int8 AS tokens \
+         FROM ch_agent_usage u {PRICE_AT_USE_JOIN}\
+         WHERE u.created_at >= date_trunc('{trunc}', NOW()) AND {key} IS NOT NULL \
+           AND ($1::text IS NULL OR {key} = $1) \
+         GROUP BY 1 ORDER BY {order} DESC LIMIT 1"
+    ))
+    .bind(subject)
+    .fetch_optional(db)
+    .await
+}
+
+/// USD rates (per million tokens) of a run's model when the run started;
+/// zero when the model has no USD catalogue price.
+#[derive(Debug, Clone, Copy, Default, sqlx::FromRow)]
+struct UsdRates {
+    input_rate: f64,
+    output_rate: f64,
+    cache_read_rate: f64,
+    cache_write_rate: f64,
+}
+
+impl UsdRates {
+    async fn load(db: &sqlx::PgPool, model: &str) -> Self {
+        sqlx::query_as::<_, UsdRates>(&format!(
+            "SELECT price.input_rate, price.output_rate, \
+                 price.cache_read_rate, price.cache_write_rate \
+             FROM (SELECT $1::text AS model, NOW() AS created_at) u {PRICE_AT_USE_JOIN}\
+             WHERE price.currency = 'USD'"
+        ))
+        .bind(model)
+        .fetch_optional(db)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("budgets: failed to load the price of {}: {}", model, e);
+            None
+        })
+        .unwrap_or_default()
+    }
+
+    fn cost(&self, usage: &PendingUsage) -> f64 {
+        (usage.input_tokens as f64 * self.input_rate
+            + usage.output_tokens as f64 * self.output_rate
+            + usage.cache_read_tokens as f64 * self.cache_read_rate
+            + usage.cache_write_tokens as f64 * self.cache_write_rate)
+            / 1_000_000.0
+    }
+}
+
+/// A budget that applies to a run.
+#[derive(Debug, Clone)]
+struct TrackedBudget {
+    row: BudgetRow,
+    /// User, session id or agent name the spend is measured for.
+    subject: Option<String>,
+    /// Spend in the budget's unit: the period's usage when the run started
+    /// plus what the run and its delegations recorded since.
+    spent: f64,
+}
+
+/// Budgets of one execution with their spend, loaded once when it starts.
+/// Usage is then added in memory — the checks before every model call no
+/// longer query the database. Clones share the same spend.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunBudgets {
+    budgets: Arc<Mutex<Vec<TrackedBudget>>>,
+    rates: UsdRates,
+    /// Delegated agent the run's usage is attributed to (lower-cased).
+    agent: Option<String>,
+    /// Budgets of the calling execution, which this run's usage counts against too.
+    parent: Option<Box<RunBudgets>>,
+}
+
+impl RunBudgets {
+    /// Load every enabled budget that applies to `subject`, with its spend in
+    /// the current period. Fails open: on a database error the run has no
+    /// budgets (or fewer).
+    pub(crate) async fn load(
+        state: &AppState,
+        subject: &BudgetSubject,
+        model: &str,
+        parent: Option<&RunBudgets>,
+    ) -> Self {
+        let user = subject
+            .user
+            .clone()
+            .unwrap_or_else(|| ANONYMOUS_USER.to_string());
+        let session = subject.session_id.map(|s| s.to_string());
+        let agent = subject.agent.as_deref().map(str::to_lowercase);
+
+        let rows: Vec<BudgetRow> = sqlx::query_as(&format!(
+            "SELECT {BUDGET_COLUMNS} FROM ch_budgets \
+             WHERE enabled AND ((scope = 'user' AND (scope_id IS NULL OR scope_id = $3)) \
+                OR (scope = 'session' AND $1::text IS NOT NULL AND (scope_id IS NULL OR scope_id = $1)) \
+                OR (scope = 'agent' AND $2::text IS NOT NULL \
+                    AND (scope_id IS NULL OR lower(scope_id) = $2)))"
+        ))
+        .bind(&session)
+        .bind(&agent)
+        .bind(&user)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("budgets: failed to load budgets: {}", e);
+            Vec::new()
+        });
+
+        let mut budgets = Vec::with_capacity(rows.len());
+        for row in rows {
+            let subject = match row.scope.as_str() {
+                "session" => session.clone(),
+                "agent" => agent.clone(),
+                _ => Some(user.clone()),
+            };
+            match spend(&state.db, &row, subject.as_deref()).await {
+                Ok(s) => budgets.push(TrackedBudget {
+                    spent: s.map_or(0.0, |s| row.measure(&s)),
+                    row,
+                    subject,
+                }),
+                Err(e) => tracing::warn!("budgets: spend query for '{}' failed: {}", row.name, e),
+            }
+        }
+        let rates = if budgets.iter().any(|b| b.row.unit == "usd") {
+            UsdRates::load(&state.db, model).await
+        } else {
+            UsdRates::default()
+        };
+        Self::new(budgets, rates, agent, parent)
+    }
+
+    fn new(
+        budgets: Vec<TrackedBudget>,
+        rates: UsdRates,
+        agent: Option<String>,
+        parent: Option<&RunBudgets>,
+    ) -> Self {
+        Self {
+            budgets: Arc::new(Mutex::new(budgets)),
+            rates,
+            agent,
+            parent: parent.cloned().map(Box::new),
+        }
+    }
+
+    fn tracked(&self) -> std::sync::MutexGuard<'_, Vec<TrackedBudget>> {
+        self.budgets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Usage the run recorded in `ch_agent_usage` after its budgets were
+    /// loaded; it also counts against the calling execution's budgets.
+    pub(crate) fn add_recorded(&self, usage: PendingUsage) {
+        self.add_spend(
+            self.rates.cost(&usage),
+            usage.tokens(),
+            self.agent.as_deref(),
+        );
+    }
+
+    fn add_spend(&self, usd: f64, tokens: i64, agent: Option<&str>) {
+        for budget in self.tracked().iter_mut() {
+            // Agent budgets only count that agent's usage.
+            if budget.row.scope == "agent" && budget.subject.as_deref() != agent {
+                continue;
+            }
+            budget.spent += budget.row.amount(usd, tokens);
+        }
+        if let Some(parent) = &self.parent {
+            parent.add_spend(usd, tokens, agent);
+        }
+    }
+
+    /// Evaluate the budgets, counting `pending` usage of this run that is
+    /// not recorded yet.
+    pub(crate) fn check(
+        &self,
+        metrics: &BudgetMetrics,
+        pending: Option<PendingUsage>,
+    ) -> BudgetCheck {
+        let (pending_usd, pending_tokens) =
+            pending.map_or((0.0, 0), |p| (self.rates.cost(&p), p.tokens()));
+
+        let mut result = BudgetCheck::default();
+        for budget in self.tracked().iter() {
+            let spent = budget.spent + budget.row.amount(pending_usd, pending_tokens);
+            match budget.row.breach(spent, budget.subject.clone()) {
+                Some(b) if b.level == BudgetLevel::Hard => {
+                    if result.exceeded.is_none() {
+                        result.exceeded = Some(b);
+                    }
+                }
+                Some(b) => result.warnings.push(b),
+                None => {}
+            }
+        }
+
+        if let Some(ref b) = result.exceeded {
+            metrics.hard_breaches.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("budgets: {}", b.message());
+        }
+        if !result.warnings.is_empty() {
+            metrics
+                .soft_breaches
+                .fetch_add(result.warnings.len() as u64, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Prometheus
+// ═══════════════════════════════════════════════════════════════════════
+
+pub(crate) fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Breach counters plus current spend and limits of every enabled budget
+/// (top spender for budgets without a `scope_id`).
+pub(crate) async fn prometheus_output(state: &AppState) -> String {
+    let metrics = &state.budget_metrics;
+    let mut out = format!(
+        "# HELP claudehydra_budget_breaches_total Budget checks that hit a limit.\n\
+         # TYPE claudehydra_budget_breaches_total counter\n\
+         claudehydra_budget_breaches_total{{level=\"soft\"}} {}\n\
+         claudehydra_budget_breaches_total{{level=\"hard\"}} {}\n",
+        metrics.soft_breaches.load(Ordering::Relaxed),
+        metrics.hard_breaches.load(Ordering::Relaxed),
+    );
+
+    let budgets: Vec<BudgetRow> = sqlx::query_as(&format!(
+        "SELECT {BUDGET_COLUMNS} FROM ch_budgets WHERE enabled ORDER BY name"
+    ))
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+    if budgets.is_empty() {
+        return out;
+    }
+
+    out.push_str(
+        "# HELP claudehydra_budget_spent Spend in the current budget period.\n\
+         # TYPE claudehydra_budget_spent gauge\n",
+    );
+    let mut limits = String::from(
+        "# HELP claudehydra_budget_limit Configured budget limits.\n\
+         # TYPE claudehydra_budget_limit gauge\n",
+    );
+    for budget in &budgets {
+        let subject = budget
+            .scope_id
+            .as_deref()
+            .map(|s| match budget.scope.as_str() {
+                "agent" => s.to_lowercase(),
+                _ => s.to_string(),
+            });
+        let spent = spend(&state.db, budget, subject.as_deref())
+            .await
+            .ok()
+            .flatten()
+            .map_or(0.0, |s| budget.measure(&s));
+        let labels = format!(
+            "budget=\"{}\",scope=\"{}\",period=\"{}\",unit=\"{}\"",
+            escape_label(&budget.name),
+            budget.scope,
+            budget.period,
+            budget.unit
+        );
+        out.push_str(&format!("claudehydra_budget_spent{{{labels}}} {spent}\n"));
+        for (level, limit) in [("soft", budget.soft_limit), ("hard", budget.hard_limit)] {
+            if let Some(limit) = limit {
+                limits.push_str(&format!(
+                    "claudehydra_budget_limit{{{labels},level=\"{level}\"}} {limit}\n"
+                ));
+            }
+        }
+    }
+    out.push_str(&limits);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(soft: Option<f64>, hard: Option<f64>) -> BudgetRow {
+        BudgetRow {
+            id: uuid::Uuid::nil(),
+            name: "daily".to_string(),
+            scope: "user".to_string(),
+            scope_id: None,
+            period: "daily".to_string(),
+            unit: "usd".to_string(),
+            soft_limit: soft,
+            hard_limit: hard,
+        }
+    }
+
+    #[test]
+    fn breach_reports_highest_crossed_level() {
+        let b = budget(Some(5.0), Some(10.0));
+        assert!(b.breach(4.99, None).is_none());
+        assert_eq!(b.breach(5.0, None).unwrap().level, BudgetLevel::Soft);
+        let hard = b.breach(12.0, None).unwrap();
+        assert_eq!(hard.level, BudgetLevel::Hard);
+        assert_eq!(hard.limit, 10.0);
+        assert!(hard.message().contains("$12.00 of $10.00"));
+
+        let soft_only = budget(Some(1.0), None);
+        assert_eq!(
+            soft_only.breach(100.0, None).unwrap().level,
+            BudgetLevel::Soft
+        );
+    }
+
+    fn tracked(scope: &str, subject: Option<&str>, unit: &str, hard: f64) -> TrackedBudget {
+        TrackedBudget {
+            row: BudgetRow {
+                scope: scope.to_string(),
+                unit: unit.to_string(),
+                ..budget(None, Some(hard))
+            },
+            subject: subject.map(str::to_string),
+            spent: 0.0,
+        }
+    }
+
+    const SONNET: UsdRates = UsdRates {
+        input_rate: 3.0,
+        output_rate: 15.0,
+        cache_read_rate: 0.3,
+        cache_write_rate: 3.75,
+    };
+
+    fn output(tokens: i64) -> PendingUsage {
+        PendingUsage {
+            output_tokens: tokens,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pending_usage_warns_then_blocks() {
+        let mut daily = tracked("user", None, "usd", 2.0);
+        daily.row.soft_limit = Some(1.0);
+        daily.spent = 0.5;
+        let budgets = RunBudgets::new(vec![daily], SONNET, None, None);
+        let metrics = BudgetMetrics::default();
+
+        // $0.50 spent + $0.30 pending: below both limits.
+        let check = budgets.check(&metrics, Some(output(20_000)));
+        assert!(check.warnings.is_empty());
+        assert!(check.exceeded.is_none());
+
+        // + $0.75: soft limit reached — warn, the call goes ahead.
+        let check = budgets.check(&metrics, Some(output(50_000)));
+        assert_eq!(check.warnings.len(), 1);
+        assert_eq!(check.warnings[0].spent, 1.25);
+        assert!(check.exceeded.is_none());
+
+        // + $1.50: hard limit reached — the call is blocked.
+        let check = budgets.check(&metrics, Some(output(100_000)));
+        let exceeded = check.exceeded.unwrap();
+        assert_eq!(exceeded.level, BudgetLevel::Hard);
+        assert_eq!(exceeded.spent, 2.0);
+        assert!(check.warnings.is_empty());
+
+        assert_eq!(metrics.soft_breaches.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.hard_breaches.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn recorded_usage_accumulates_without_reloading() {
+        let budgets = RunBudgets::new(
+            vec![tracked("user", None, "tokens", 3_000.0)],
+            SONNET,
+            None,
+            None,
+        );
+        let metrics = BudgetMetrics::default();
+        let usage = PendingUsage {
+            input_tokens: 500,
+            output_tokens: 200,
+            cache_read_tokens: 250,
+            cache_write_tokens: 50,
+        };
+
+        budgets.add_recorded(usage);
+        budgets.add_recorded(usage);
+        assert!(budgets.check(&metrics, None).exceeded.is_none());
+        budgets.clone().add_recorded(usage);
+        assert_eq!(
+            budgets.check(&metrics, None).exceeded.unwrap().spent,
+            3_000.0
+        );
+    }
+
+    #[test]
+    fn delegation_usage_counts_against_the_caller() {
+        // A delegation to "Triss" (with its own agent budget) calls "Yennefer".
+        let top_level = RunBudgets::new(
+            vec![tracked("session", Some("s-1"), "usd", 1.0)],
+            SONNET,
+            None,
+            None,
+        );
+        let triss = RunBudgets::new(
+            vec![tracked("agent", Some("triss"), "tokens", 1_000.0)],
+            SONNET,
+            Some("triss".to_string()),
+            Some(&top_level),
+        );
+        let yennefer = RunBudgets::new(
+            vec![tracked("agent", Some("yennefer"), "tokens", 1_000.0)],
+            SONNET,
+            Some("yennefer".to_string()),
+            Some(&triss),
+        );
+        let metrics = BudgetMetrics::default();
+
+        yennefer.add_recorded(output(70_000));
+        // Yennefer's own budget is spent; Triss' agent budget is not hers.
+        assert!(yennefer.check(&metrics, None).exceeded.is_some());
+        assert!(triss.check(&metrics, None).exceeded.is_none());
+        // The session budget of the top-level run sees the $1.05.
+        let exceeded = top_level.check(&metrics, None).exceeded.unwrap();
+        assert_eq!(exceeded.subject.as_deref(), Some("s-1"));
+        assert!((exceeded.spent - 1.05).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn user_budgets_measure_each_user() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let ciri = format!("{}@example.com", uuid::Uuid::new_v4());
+        let geralt = format!("{}@example.com", uuid::Uuid::new_v4());
+        for (user, tokens) in [(&ciri, 1_000), (&geralt, 10)] {
+            sqlx::query(
+                "INSERT INTO ch_agent_usage (model, input_tokens, output_tokens, total_tokens, \
+                 latency_ms, success, tier, user_id) \
+                 VALUES ('claude-sonnet-4-6', $2, 0, $2, 10, TRUE, 'coordinator', $1)",
+            )
+            .bind(user)
+            .bind(tokens)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let daily = BudgetRow {
+            unit: "tokens".to_string(),
+            ..budget(None, Some(100.0))
+        };
+        let spent = |user: &str| {
+            let (pool, daily, user) = (pool.clone(), daily.clone(), user.to_string());
+            async move { spend(&pool, &daily, Some(&user)).await.unwrap() }
+        };
+        let ciri_spend = spent(&ciri).await.unwrap();
+        assert_eq!(ciri_spend.subject.as_deref(), Some(ciri.as_str()));
+        assert_eq!(ciri_spend.tokens, 1_000);
+        assert_eq!(spent(&geralt).await.unwrap().tokens, 10);
+        let yennefer = format!("{}@example.com", uuid::Uuid::new_v4());
+        assert!(spent(&yennefer).await.is_none());
+
+        sqlx::query("DELETE FROM ch_agent_usage WHERE user_id = ANY($1)")
+            .bind(vec![ciri, geralt])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
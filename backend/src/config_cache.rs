@@ -0,0 +1,398 @@
+// ClaudeHydra v4 -- Settings and model pin snapshot cache
+//
+// The chat hot path (context resolution, model selection, delegated agents,
+// tier routing, sandbox policy) reads `ch_settings`, `ch_model_pins` and the
+// running A/B experiment from one in-memory snapshot instead of querying the
+// database on every call. The snapshot is dropped when this process writes
+// those tables (`pin_model`, `unpin_model`, `update_settings`, experiment
+// handlers) and when anybody else does: table triggers `pg_notify` channel
+// `ch_config_changed` and every replica LISTENs on it. A TTL bounds staleness
+// should a notification be lost while the listener reconnects. The same
+// notifications rebuild the AI gateway provider set (`ch_gateway_providers`)
+// and reload the Vault lockout (`ch_vault_lockout`).
+//
+// Resolved tier models are memoised as well, keyed by the model-list and
+// capability refresh times, so `get_model_id` no longer re-runs selection.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+
+use crate::handlers::experiments::RunningExperiment;
+use crate::model_registry::ResolvedModels;
+use crate::state::AppState;
+
+/// Postgres channel the table triggers notify (payload = table name).
+pub const CHANNEL: &str = "ch_config_changed";
+/// Upper bound on snapshot age when notifications are missed.
+const SNAPSHOT_TTL: Duration = Duration::from_secs(300);
+const LISTENER_RETRY: Duration = Duration::from_secs(5);
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Snapshot
+// ═══════════════════════════════════════════════════════════════════════
+
+/// `ch_settings` fields read on the hot path (NULLs already defaulted).
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SettingsSnapshot {
+    pub language: String,
+    pub working_directory: String,
+    pub temperature: f64,
+    pub max_tokens: i32,
+    pub max_iterations: i32,
+    pub custom_instructions: String,
+    pub routing_cost_ceiling_usd: Option<f64>,
+    /// Per-user overrides of `routing_cost_ceiling_usd` (lower-cased email → USD).
+    pub routing_user_cost_ceilings: sqlx::types::Json<HashMap<String, f64>>,
+    pub use_docker_sandbox: bool,
+    pub agent_max_call_depth: i32,
+    pub agent_max_iterations: i32,
+    pub thinking_budgets: sqlx::types::Json<crate::models::ThinkingBudgets>,
+    pub store_thinking: bool,
+}
+
+impl Default for SettingsSnapshot {
+    fn default() -> Self {
+        Self {
+            language: "en".to_string(),
+            working_directory: String::new(),
+            temperature: 0.7,
+            max_tokens: 4096,
+            max_iterations: 10,
+            custom_instructions: String::new(),
+            routing_cost_ceiling_usd: None,
+            routing_user_cost_ceilings: Default::default(),
+            use_docker_sandbox: false,
+            agent_max_call_depth: 3,
+            agent_max_iterations: 8,
+            thinking_budgets: Default::default(),
+            store_thinking: false,
+        }
+    }
+}
+
+/// Point-in-time copy of the cached configuration tables.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConfigSnapshot {
+    pub settings: SettingsSnapshot,
+    /// `use_case` → pinned model id.
+    pub pins: HashMap<String, String>,
+    pub experiment: Option<RunningExperiment>,
+}
+
+async fn load_snapshot(db: &PgPool) -> Result<ConfigSnapshot, sqlx::Error> {
+    let settings = sqlx::query_as::<_, SettingsSnapshot>(
+        "SELECT COALESCE(language, 'en') AS language, \
+         COALESCE(working_directory, '') AS working_directory, \
+         COALESCE(temperature, 0.7) AS temperature, \
+         COALESCE(max_tokens, 4096) AS max_tokens, \
+         COALESCE(max_iterations, 10) AS max_iterations, \
+         COALESCE(custom_instructions, '') AS custom_instructions, \
+         routing_cost_ceiling_usd, \
+         COALESCE(routing_user_cost_ceilings, '{}'::jsonb) AS routing_user_cost_ceilings, \
+         COALESCE(use_docker_sandbox, FALSE) AS use_docker_sandbox, \
+         COALESCE(agent_max_call_depth, 3) AS agent_max_call_depth, \
+         COALESCE(agent_max_iterations, 8) AS agent_max_iterations, \
+         COALESCE(thinking_budgets, '{}'::jsonb) AS thinking_budgets, \
+         COALESCE(store_thinking, FALSE) AS store_thinking \
+         FROM ch_settings WHERE id = 1",
+    )
+    .fetch_optional(db)
+    .await?
+    .unwrap_or_default();
+
+    let pins: Vec<(String, String)> =
+        sqlx::query_as("SELECT use_case, model_id FROM ch_model_pins")
+            .fetch_all(db)
+            .await?;
+
+    let experiment = crate::handlers::experiments::load_running(db).await?;
+
+    Ok(ConfigSnapshot {
+        settings,
+        pins: pins.into_iter().collect(),
+        experiment,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Cache
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Model-list and capability refresh times the memoised selection was made from.
+pub(crate) type ResolvedKey = (Option<Instant>, Option<Instant>);
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigCache {
+    snapshot: RwLock<Option<(Arc<ConfigSnapshot>, Instant)>>,
+    /// Bumped by `invalidate`; a load that started under an older generation
+    /// may have read the tables before the write and is not cached.
+    generation: AtomicU64,
+    resolved: std::sync::Mutex<Option<(ResolvedKey, ResolvedModels)>>,
+    snapshot_counters: Counters,
+    resolved_counters: Counters,
+    invalidations: AtomicU64,
+    load_errors: AtomicU64,
+    listening: AtomicBool,
+}
+
+impl ConfigCache {
+    /// Current snapshot, loading it on first use or after invalidation.
+    /// A failed reload serves the previous snapshot (or defaults) uncached.
+    pub(crate) async fn get(&self, db: &PgPool) -> Arc<ConfigSnapshot> {
+        self.get_or_load(load_snapshot(db)).await
+    }
+
+    async fn get_or_load(
+        &self,
+        load: impl Future<Output = Result<ConfigSnapshot, sqlx::Error>>,
+    ) -> Arc<ConfigSnapshot> {
+        if let Some((snapshot, _)) = self
+            .snapshot
+            .read()
+            .await
+            .as_ref()
+            .filter(|(_, loaded_at)| loaded_at.elapsed() < SNAPSHOT_TTL)
+        {
+            self.snapshot_counters.hits.fetch_add(1, Ordering::Relaxed);
+            return snapshot.clone();
+        }
+
+        self.snapshot_counters
+            .misses
+            .fetch_add(1, Ordering::Relaxed);
+        let generation = self.generation.load(Ordering::SeqCst);
+        match load.await {
+            Ok(snapshot) => {
+                let snapshot = Arc::new(snapshot);
+                let mut cached = self.snapshot.write().await;
+                if self.generation.load(Ordering::SeqCst) == generation {
+                    *cached = Some((snapshot.clone(), Instant::now()));
+                }
+                snapshot
+            }
+            Err(e) => {
+                self.load_errors.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("config_cache: failed to load snapshot: {}", e);
+                self.snapshot
+                    .read()
+                    .await
+                    .as_ref()
+                    .map(|(snapshot, _)| snapshot.clone())
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    /// Drop the snapshot and the memoised tier selection.
+    pub async fn invalidate(&self) {
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut cached = self.snapshot.write().await;
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            *cached = None;
+        }
+        *self.resolved.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    pub(crate) fn resolved(&self, key: ResolvedKey) -> Option<ResolvedModels> {
+        let resolved = self.resolved.lock().unwrap_or_else(|e| e.into_inner());
+        match resolved.as_ref() {
+            Some((cached_key, models)) if *cached_key == key => {
+                self.resolved_counters.hits.fetch_add(1, Ordering::Relaxed);
+                Some(models.clone())
+            }
+            _ => {
+                self.resolved_counters
+                    .misses
+                    .fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn store_resolved(&self, key: ResolvedKey, models: ResolvedModels) {
+        *self.resolved.lock().unwrap_or_else(|e| e.into_inner()) = Some((key, models));
+    }
+
+    pub fn prometheus_output(&self) -> String {
+        let load = |a: &AtomicU64| a.load(Ordering::Relaxed);
+        format!(
+            "# HELP claudehydra_config_cache_requests_total Settings/pins snapshot lookups.\n\
+             # TYPE claudehydra_config_cache_requests_total counter\n\
+             claudehydra_config_cache_requests_total{{cache=\"snapshot\",result=\"hit\"}} {}\n\
+             claudehydra_config_cache_requests_total{{cache=\"snapshot\",result=\"miss\"}} {}\n\
+             claudehydra_config_cache_requests_total{{cache=\"resolved_models\",result=\"hit\"}} {}\n\
+             claudehydra_config_cache_requests_total{{cache=\"resolved_models\",result=\"miss\"}} {}\n\
+             # HELP claudehydra_config_cache_invalidations_total Snapshot invalidations (local writes and notifications).\n\
+             # TYPE claudehydra_config_cache_invalidations_total counter\n\
+             claudehydra_config_cache_invalidations_total {}\n\
+             # HELP claudehydra_config_cache_load_errors_total Failed snapshot reloads.\n\
+             # TYPE claudehydra_config_cache_load_errors_total counter\n\
+             claudehydra_config_cache_load_errors_total {}\n\
+             # HELP claudehydra_config_cache_listener_up Whether LISTEN {} is connected.\n\
+             # TYPE claudehydra_config_cache_listener_up gauge\n\
+             claudehydra_config_cache_listener_up {}\n",
+            load(&self.snapshot_counters.hits),
+            load(&self.snapshot_counters.misses),
+            load(&self.resolved_counters.hits),
+            load(&self.resolved_counters.misses),
+            load(&self.invalidations),
+            load(&self.load_errors),
+            CHANNEL,
+            u8::from(self.listening.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+/// Current configuration snapshot.
+pub(crate) async fn snapshot(state: &AppState) -> Arc<ConfigSnapshot> {
+    state.config_cache.get(&state.db).await
+}
+
+/// Invalidate after a local write; other replicas follow via the table triggers.
+pub async fn invalidate(state: &AppState) {
+    state.config_cache.invalidate().await;
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  LISTEN / NOTIFY
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Listen on `ch_config_changed` for the lifetime of the process.
+///
+/// Anything received while disconnected is lost, so the snapshot is also
+/// dropped, and the Vault lockout reloaded, on every (re)connect.
+pub fn spawn_listener(state: AppState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let mut listener = match sqlx::postgres::PgListener::connect_with(&state.db).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::warn!("config_cache: listener connect failed: {}", e);
+                    tokio::time::sleep(LISTENER_RETRY).await;
+                    continue;
+                }
+            };
+            if let Err(e) = listener.listen(CHANNEL).await {
+                tracing::warn!("config_cache: LISTEN {} failed: {}", CHANNEL, e);
+                tokio::time::sleep(LISTENER_RETRY).await;
+                continue;
+            }
+            state.config_cache.listening.store(true, Ordering::Relaxed);
+            state.config_cache.invalidate().await;
+            crate::gateway_providers::reload(&state).await;
+            state.ai_gateway.vault_guard.reload().await;
+            tracing::info!("config_cache: listening on {}", CHANNEL);
+
+            loop {
+                match listener.try_recv().await {
+                    Ok(Some(notification)) => {
+                        tracing::debug!(
+                            "config_cache: {} changed — dropping snapshot",
+                            notification.payload()
+                        );
+                        match notification.payload() {
+                            "ch_model_capabilities" => {
+                                state.model_capabilities.write().await.expire();
+                            }
+                            "ch_gateway_providers" => {
+                                crate::gateway_providers::reload(&state).await;
+                            }
+                            "ch_vault_lockout" => {
+                                state.ai_gateway.vault_guard.reload().await;
+                            }
+                            _ => {}
+                        }
+                        state.config_cache.invalidate().await;
+                    }
+                    // Connection lost; the next try_recv reconnects and re-listens.
+                    Ok(None) => {
+                        tracing::warn!("config_cache: listener connection lost — reconnecting");
+                        state.config_cache.invalidate().await;
+                        crate::gateway_providers::reload(&state).await;
+                        state.ai_gateway.vault_guard.reload().await;
+                    }
+                    Err(e) => {
+                        tracing::warn!("config_cache: listener error: {}", e);
+                        break;
+                    }
+                }
+            }
+            state.config_cache.listening.store(false, Ordering::Relaxed);
+            tokio::time::sleep(LISTENER_RETRY).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolved_models_memo_is_keyed_by_refresh_times() {
+        let cache = ConfigCache::default();
+        let now = Some(Instant::now());
+        let models = ResolvedModels {
+            commander: None,
+            coordinator: None,
+            executor: None,
+            flash: None,
+        };
+        assert!(cache.resolved((now, None)).is_none());
+        cache.store_resolved((now, None), models);
+        assert!(cache.resolved((now, None)).is_some());
+        assert!(cache.resolved((now, now)).is_none());
+
+        let out = cache.prometheus_output();
+        assert!(out.contains("cache=\"resolved_models\",result=\"hit\"} 1\n"));
+        assert!(out.contains("cache=\"resolved_models\",result=\"miss\"} 2\n"));
+    }
+
+    fn pinned(model: &str) -> ConfigSnapshot {
+        ConfigSnapshot {
+            pins: HashMap::from([("coordinator".to_string(), model.to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn load_started_before_invalidate_is_not_cached() {
+        let cache = ConfigCache::default();
+        let (release, loaded) = tokio::sync::oneshot::channel::<()>();
+
+        // The load reads the old pin, then a pin write invalidates the
+        // cache before the load finishes.
+        let (stale, ()) = tokio::join!(
+            cache.get_or_load(async {
+                loaded.await.unwrap();
+                Ok(pinned("claude-sonnet-4-6"))
+            }),
+            async {
+                cache.invalidate().await;
+                release.send(()).unwrap();
+            }
+        );
+        assert_eq!(stale.pins["coordinator"], "claude-sonnet-4-6");
+        assert!(cache.snapshot.read().await.is_none());
+
+        let fresh = cache
+            .get_or_load(async { Ok(pinned("claude-opus-4-6")) })
+            .await;
+        assert_eq!(fresh.pins["coordinator"], "claude-opus-4-6");
+        let cached = cache
+            .get_or_load(async { Ok(pinned("claude-haiku-4-5")) })
+            .await;
+        assert_eq!(cached.pins["coordinator"], "claude-opus-4-6");
+    }
+}
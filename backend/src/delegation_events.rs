@@ -0,0 +1,250 @@
+// ClaudeHydra v4 -- Agent delegation lifecycle events
+//
+// `execute_agent_call` publishes one `DelegationEvent` per lifecycle transition
+// on `AppState::a2a_task_tx`. A bounded in-memory history lets the SSE endpoint
+// (`GET /api/agents/delegations/stream`) replay the latest events to clients
+// that connect mid-delegation.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::state::AppState;
+
+/// Events kept for replay on connect.
+pub const HISTORY_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationEventKind {
+    Created,
+    Started,
+    Iteration,
+    ToolCall,
+    Completed,
+    Failed,
+    TimedOut,
+    Cancelled,
+}
+
+impl DelegationEventKind {
+    /// Terminal states close a delegation; the value matches `ch_a2a_tasks.status`.
+    pub fn terminal_status(self) -> Option<&'static str> {
+        match self {
+            Self::Completed => Some("completed"),
+            Self::Failed => Some("failed"),
+            Self::TimedOut => Some("timed_out"),
+            Self::Cancelled => Some("cancelled"),
+            _ => None,
+        }
+    }
+}
+
+/// One delegation lifecycle transition.
+#[derive(Debug, Clone, Serialize)]
+pub struct DelegationEvent {
+    /// Monotonic sequence number (per process) — lets clients de-duplicate replays.
+    pub seq: u64,
+    #[serde(rename = "type")]
+    pub kind: DelegationEventKind,
+    /// `ch_a2a_tasks.id` of the delegation.
+    pub task_id: uuid::Uuid,
+    /// Delegation that spawned this one (nested `call_agent`), if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_task_id: Option<uuid::Uuid>,
+    /// Top-level chat execution the delegation tree belongs to, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_id: Option<String>,
+    pub agent: String,
+    pub agent_tier: String,
+    pub model: String,
+    pub depth: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
+    /// Tool duration for `tool_call`, total delegation duration for terminal events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Task preview (`created`), result preview or error message (terminal events).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub timestamp: String,
+}
+
+impl DelegationEvent {
+    /// Whether the event matches the SSE filters (`agent`, `execution_id`).
+    pub fn matches(&self, agent: Option<&str>, execution_id: Option<&str>) -> bool {
+        let agent_ok = agent.is_none_or(|a| self.agent.eq_ignore_ascii_case(a));
+        let execution_ok = execution_id.is_none_or(|id| {
+            self.execution_id.as_deref() == Some(id)
+                || self.task_id.to_string() == id
+                || self.parent_task_id.is_some_and(|p| p.to_string() == id)
+        });
+        agent_ok && execution_ok
+    }
+}
+
+/// Bounded replay buffer plus sequence counter.
+#[derive(Debug, Default)]
+pub struct DelegationHistory {
+    inner: Mutex<HistoryInner>,
+}
+
+#[derive(Debug, Default)]
+struct HistoryInner {
+    seq: u64,
+    recent: VecDeque<DelegationEvent>,
+}
+
+impl DelegationHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Latest `limit` events matching the filters, oldest first.
+    pub fn replay(
+        &self,
+        limit: usize,
+        agent: Option<&str>,
+        execution_id: Option<&str>,
+    ) -> Vec<DelegationEvent> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<DelegationEvent> = inner
+            .recent
+            .iter()
+            .rev()
+            .filter(|e| e.matches(agent, execution_id))
+            .take(limit)
+            .cloned()
+            .collect();
+        out.reverse();
+        out
+    }
+
+    /// Assign the next `seq`, keep the event for replay and hand it to
+    /// `broadcast` — all under one lock, so subscribers see strictly
+    /// increasing `seq` (the SSE stream drops anything not newer than the
+    /// last event it sent).
+    fn record(&self, mut event: DelegationEvent, broadcast: impl FnOnce(DelegationEvent)) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.seq += 1;
+        event.seq = inner.seq;
+        if inner.recent.len() >= HISTORY_CAPACITY {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(event.clone());
+        broadcast(event);
+    }
+}
+
+/// Record an event and broadcast it to live subscribers.
+pub fn publish(state: &AppState, event: DelegationEvent) {
+    tracing::debug!(
+        "delegation {:?}: task={} agent={} depth={}",
+        event.kind,
+        event.task_id,
+        event.agent,
+        event.depth
+    );
+    state.delegation_history.record(event, |event| {
+        // No subscribers is the normal case — ignore the send error.
+        let _ = state.a2a_task_tx.send(event);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        kind: DelegationEventKind,
+        agent: &str,
+        execution_id: Option<&str>,
+    ) -> DelegationEvent {
+        DelegationEvent {
+            seq: 0,
+            kind,
+            task_id: uuid::Uuid::new_v4(),
+            parent_task_id: None,
+            execution_id: execution_id.map(str::to_string),
+            agent: agent.to_string(),
+            agent_tier: "Coordinator".to_string(),
+            model: "claude-sonnet-4-6".to_string(),
+            depth: 1,
+            iteration: None,
+            tool_name: None,
+            is_error: None,
+            duration_ms: None,
+            message: None,
+            timestamp: String::new(),
+        }
+    }
+
+    #[test]
+    fn replay_is_bounded_filtered_and_ordered() {
+        let history = DelegationHistory::new();
+        for i in 0..(HISTORY_CAPACITY + 10) {
+            let agent = if i % 2 == 0 { "Yennefer" } else { "Triss" };
+            history.record(
+                event(DelegationEventKind::Iteration, agent, Some("exec-1")),
+                drop,
+            );
+        }
+        history.record(
+            event(DelegationEventKind::Completed, "Yennefer", Some("exec-2")),
+            drop,
+        );
+
+        let all = history.replay(usize::MAX, None, None);
+        assert_eq!(all.len(), HISTORY_CAPACITY);
+        assert!(all.windows(2).all(|w| w[0].seq < w[1].seq));
+
+        let yen = history.replay(3, Some("yennefer"), None);
+        assert_eq!(yen.len(), 3);
+        assert_eq!(yen[2].kind, DelegationEventKind::Completed);
+
+        let exec2 = history.replay(50, None, Some("exec-2"));
+        assert_eq!(exec2.len(), 1);
+    }
+
+    #[test]
+    fn concurrent_publishers_broadcast_in_seq_order() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 500;
+        let history = DelegationHistory::new();
+        let (tx, mut rx) = tokio::sync::broadcast::channel(THREADS * PER_THREAD);
+
+        std::thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    for _ in 0..PER_THREAD {
+                        history.record(event(DelegationEventKind::ToolCall, "Ciri", None), |e| {
+                            let _ = tx.send(e);
+                        });
+                    }
+                });
+            }
+        });
+
+        let mut last_seq = 0;
+        let mut received = 0;
+        while let Ok(ev) = rx.try_recv() {
+            assert!(ev.seq > last_seq, "seq {} after {}", ev.seq, last_seq);
+            last_seq = ev.seq;
+            received += 1;
+        }
+        assert_eq!(received, THREADS * PER_THREAD);
+        assert_eq!(last_seq, (THREADS * PER_THREAD) as u64);
+    }
+
+    #[test]
+    fn events_serialize_type_in_snake_case() {
+        let v = serde_json::to_value(event(DelegationEventKind::TimedOut, "Geralt", None)).unwrap();
+        assert_eq!(v["type"], "timed_out");
+        assert!(v.get("execution_id").is_none());
+    }
+}
@@ -6,7 +6,11 @@
 //!
 //! This wraps the shared [`jaskier_auth::validate_token`] function so
 //! handler signatures can simply include `RequireAuth` to enforce
-//! user authentication.
+//! user authentication. [`OptionalAuth`] is the non-rejecting variant for
+//! routes that also serve anonymous callers but attribute work (spend,
+//! routing decisions) to the signed-in user.
+
+use std::convert::Infallible;
 
 use axum::extract::FromRequestParts;
 use axum::http::StatusCode;
@@ -69,6 +73,32 @@ impl FromRequestParts<AppState> for RequireAuth {
     }
 }
 
+/// Axum extractor that never rejects: `email` is set only when a valid
+/// jaskier-auth JWT is present (lower-cased, so it can key per-user data).
+pub struct OptionalAuth {
+    pub email: Option<String>,
+}
+
+impl FromRequestParts<AppState> for OptionalAuth {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let email = state
+            .base
+            .auth_secret
+            .as_deref()
+            .zip(extract_token(parts))
+            .and_then(|(secret, token)| {
+                jaskier_auth::validate_token(&token, secret.as_bytes()).ok()
+            })
+            .map(|user| user.email.to_lowercase());
+        Ok(OptionalAuth { email })
+    }
+}
+
 /// Extract a JWT token from the request (header, cookie, or query parameter).
 fn extract_token(parts: &Parts) -> Option<String> {
     // 1. Try Authorization: Bearer header
@@ -0,0 +1,364 @@
+// ClaudeHydra v4 -- Gateway provider configuration
+//
+// `ai_gateway::default_provider_configs()` holds the factory defaults of the
+// built-in providers. Rows in `ch_gateway_providers` override them (model
+// tiers, extra headers, plan, cost and quota, enabled flag, and the upstream
+// URL of keyless providers — the Vault Bouncer sends a Vault-authenticated
+// provider's credential to its URL, so that stays the default) and add
+// generic OpenAI-compatible endpoints — vLLM, LM Studio, llama.cpp server —
+// that join the fallback chain after the built-in providers. The merged set
+// lives in `AiGatewayState.providers`; it is rebuilt after every write through
+// `handlers::gateway_providers` and on `ch_config_changed` notifications, so
+// edits made through another replica apply without a restart.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::ai_gateway::{
+    AiProvider, AuthType, CompatibleProvider, GatewayProviders, ModelTiers, PlanQuota,
+    ProviderConfig, default_provider_configs,
+};
+use crate::state::AppState;
+
+/// `kind` of a row overriding a built-in provider.
+pub const KIND_BUILTIN: &str = "builtin";
+/// `kind` of a generic OpenAI-compatible endpoint.
+pub const KIND_OPENAI_COMPATIBLE: &str = "openai_compatible";
+
+pub(crate) const PROVIDER_COLUMNS: &str = "id, kind, name, upstream_url, extra_headers, \
+     monthly_cost_cents, commander_model, coordinator_model, executor_model, vault_service, \
+     enabled, priority, quota_window_secs, quota_requests, quota_tokens, updated_at";
+
+/// Quota window of a provider without a default quota when only limits are set.
+const DEFAULT_QUOTA_WINDOW_SECS: u64 = 24 * 3600;
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Rows
+// ═══════════════════════════════════════════════════════════════════════
+
+/// A `ch_gateway_providers` row. For built-in overrides NULL keeps the default.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct ProviderRow {
+    pub id: String,
+    pub kind: String,
+    pub name: Option<String>,
+    pub upstream_url: Option<String>,
+    pub extra_headers: Option<sqlx::types::Json<HashMap<String, String>>>,
+    pub monthly_cost_cents: Option<i32>,
+    pub commander_model: Option<String>,
+    pub coordinator_model: Option<String>,
+    pub executor_model: Option<String>,
+    pub vault_service: Option<String>,
+    pub enabled: bool,
+    pub priority: i32,
+    /// 0 disables quota tracking for the provider.
+    pub quota_window_secs: Option<i32>,
+    /// 0 leaves requests unlimited.
+    pub quota_requests: Option<i32>,
+    /// 0 leaves tokens unlimited.
+    pub quota_tokens: Option<i64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ProviderRow {
+    pub(crate) fn is_builtin(&self) -> bool {
+        self.kind == KIND_BUILTIN
+    }
+
+    /// Apply this override to a built-in provider's config.
+    pub(crate) fn apply_to(&self, config: &mut ProviderConfig) {
+        if let Some(name) = &self.name {
+            config.plan_name = name.clone();
+        }
+        if let Some(url) = &self.upstream_url {
+            if config.auth_type == AuthType::None {
+                config.upstream_url = url.clone();
+            } else if *url != config.upstream_url {
+                tracing::warn!(
+                    "gateway_providers: ignoring upstream_url override of Vault-authenticated provider '{}'",
+                    self.id
+                );
+            }
+        }
+        if let Some(headers) = &self.extra_headers {
+            config.extra_headers = headers.0.clone();
+        }
+        if let Some(cents) = self.monthly_cost_cents {
+            config.monthly_cost_cents = u32::try_from(cents).unwrap_or_default();
+        }
+        let tiers = &mut config.model_tiers;
+        for (slot, model) in [
+            (&mut tiers.commander, &self.commander_model),
+            (&mut tiers.coordinator, &self.coordinator_model),
+            (&mut tiers.executor, &self.executor_model),
+        ] {
+            if let Some(model) = model {
+                *slot = model.clone();
+            }
+        }
+        config.quota = self.quota(config.quota);
+    }
+
+    /// The plan quota after applying the row's quota columns to `default`.
+    fn quota(&self, default: Option<PlanQuota>) -> Option<PlanQuota> {
+        if self.quota_window_secs == Some(0) {
+            return None;
+        }
+        if self.quota_window_secs.is_none()
+            && self.quota_requests.is_none()
+            && self.quota_tokens.is_none()
+        {
+            return default;
+        }
+        let mut quota = default.unwrap_or(PlanQuota {
+            window_secs: DEFAULT_QUOTA_WINDOW_SECS,
+            max_requests: None,
+            max_tokens: None,
+        });
+        if let Some(secs) = self.quota_window_secs {
+            quota.window_secs = u64::try_from(secs).unwrap_or(DEFAULT_QUOTA_WINDOW_SECS);
+        }
+        if let Some(requests) = self.quota_requests {
+            quota.max_requests = u32::try_from(requests).ok().filter(|&n| n > 0);
+        }
+        if let Some(tokens) = self.quota_tokens {
+            quota.max_tokens = u64::try_from(tokens).ok().filter(|&n| n > 0);
+        }
+        Some(quota)
+    }
+
+    /// The OpenAI-compatible endpoint described by this row.
+    pub(crate) fn to_compatible(&self) -> Option<CompatibleProvider> {
+        Some(CompatibleProvider {
+            id: self.id.clone(),
+            name: self.name.clone().unwrap_or_else(|| self.id.clone()),
+            upstream_url: self.upstream_url.clone()?,
+            extra_headers: self
+                .extra_headers
+                .as_ref()
+                .map(|h| h.0.clone())
+                .unwrap_or_default(),
+            model_tiers: ModelTiers {
+                commander: self.commander_model.clone()?,
+                coordinator: self.coordinator_model.clone()?,
+                executor: self.executor_model.clone()?,
+            },
+            vault_service: self.vault_service.clone(),
+            priority: self.priority,
+        })
+    }
+}
+
+/// Built-in provider configs with overrides applied, including disabled ones.
+pub(crate) fn builtin_configs(rows: &[ProviderRow]) -> HashMap<AiProvider, ProviderConfig> {
+    let mut configs = default_provider_configs();
+    for row in rows.iter().filter(|r| r.is_builtin()) {
+        match AiProvider::from_str(&row.id) {
+            Ok(provider) => {
+                if let Some(config) = configs.get_mut(&provider) {
+                    row.apply_to(config);
+                }
+            }
+            Err(_) => tracing::warn!("gateway_providers: unknown built-in provider '{}'", row.id),
+        }
+    }
+    configs
+}
+
+/// The enabled provider set described by `rows`.
+pub(crate) fn build(rows: &[ProviderRow]) -> GatewayProviders {
+    let mut builtin = builtin_configs(rows);
+    for row in rows.iter().filter(|r| r.is_builtin() && !r.enabled) {
+        if let Ok(provider) = AiProvider::from_str(&row.id) {
+            builtin.remove(&provider);
+        }
+    }
+
+    let mut compatible: Vec<CompatibleProvider> = rows
+        .iter()
+        .filter(|r| !r.is_builtin() && r.enabled)
+        .filter_map(ProviderRow::to_compatible)
+        .collect();
+    compatible.sort_by(|a, b| a.priority.cmp(&b.priority).then_with(|| a.id.cmp(&b.id)));
+
+    GatewayProviders {
+        builtin,
+        compatible,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Loading
+// ═══════════════════════════════════════════════════════════════════════
+
+pub(crate) async fn fetch_rows(db: &PgPool) -> Result<Vec<ProviderRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {PROVIDER_COLUMNS} FROM ch_gateway_providers ORDER BY kind, priority, id"
+    ))
+    .fetch_all(db)
+    .await
+}
+
+/// Provider set at startup; factory defaults when the table can't be read.
+pub async fn load(db: &PgPool) -> GatewayProviders {
+    match fetch_rows(db).await {
+        Ok(rows) => build(&rows),
+        Err(e) => {
+            tracing::warn!(
+                "gateway_providers: failed to load configuration, using defaults: {}",
+                e
+            );
+            GatewayProviders::defaults()
+        }
+    }
+}
+
+/// Rebuild the live provider set from the database. A failed read keeps the
+/// current set.
+pub async fn reload(state: &AppState) {
+    match fetch_rows(&state.db).await {
+        Ok(rows) => state.ai_gateway.providers.replace(build(&rows)),
+        Err(e) => tracing::warn!("gateway_providers: reload failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: &str, kind: &str) -> ProviderRow {
+        ProviderRow {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            name: None,
+            upstream_url: None,
+            extra_headers: None,
+            monthly_cost_cents: None,
+            commander_model: None,
+            coordinator_model: None,
+            executor_model: None,
+            vault_service: None,
+            enabled: true,
+            priority: 0,
+            quota_window_secs: None,
+            quota_requests: None,
+            quota_tokens: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn compatible(id: &str, priority: i32) -> ProviderRow {
+        ProviderRow {
+            upstream_url: Some("http://10.0.0.5:8000/v1/chat/completions".to_string()),
+            commander_model: Some("qwen2.5-72b".to_string()),
+            coordinator_model: Some("qwen2.5-32b".to_string()),
+            executor_model: Some("qwen2.5-7b".to_string()),
+            priority,
+            ..row(id, KIND_OPENAI_COMPATIBLE)
+        }
+    }
+
+    #[test]
+    fn overrides_apply_on_top_of_defaults() {
+        let defaults = default_provider_configs();
+        let anthropic = ProviderRow {
+            coordinator_model: Some("claude-sonnet-5".to_string()),
+            monthly_cost_cents: Some(20000),
+            ..row("anthropic", KIND_BUILTIN)
+        };
+        let deepseek = ProviderRow {
+            enabled: false,
+            ..row("deepseek", KIND_BUILTIN)
+        };
+
+        let providers = build(&[anthropic, deepseek]);
+        let config = providers.get(&AiProvider::Anthropic).unwrap();
+        assert_eq!(config.model_tiers.coordinator, "claude-sonnet-5");
+        assert_eq!(config.monthly_cost_cents, 20000);
+        assert_eq!(
+            config.model_tiers.commander,
+            defaults[&AiProvider::Anthropic].model_tiers.commander
+        );
+        assert_eq!(
+            config.upstream_url,
+            defaults[&AiProvider::Anthropic].upstream_url
+        );
+        assert!(providers.get(&AiProvider::DeepSeek).is_none());
+        assert_eq!(providers.builtin.len(), defaults.len() - 1);
+    }
+
+    #[test]
+    fn only_keyless_builtins_take_an_upstream_url() {
+        let defaults = default_provider_configs();
+        let anthropic = ProviderRow {
+            upstream_url: Some("https://attacker.example/v1/messages".to_string()),
+            ..row("anthropic", KIND_BUILTIN)
+        };
+        let ollama = ProviderRow {
+            upstream_url: Some("http://10.0.0.9:11434/api/chat".to_string()),
+            ..row("ollama", KIND_BUILTIN)
+        };
+
+        let providers = build(&[anthropic, ollama]);
+        assert_eq!(
+            providers.get(&AiProvider::Anthropic).unwrap().upstream_url,
+            defaults[&AiProvider::Anthropic].upstream_url
+        );
+        assert_eq!(
+            providers.get(&AiProvider::Ollama).unwrap().upstream_url,
+            "http://10.0.0.9:11434/api/chat"
+        );
+    }
+
+    #[test]
+    fn quota_overrides_merge_with_defaults() {
+        let anthropic = ProviderRow {
+            quota_requests: Some(900),
+            ..row("anthropic", KIND_BUILTIN)
+        };
+        let openai = ProviderRow {
+            quota_window_secs: Some(0),
+            ..row("openai", KIND_BUILTIN)
+        };
+        let deepseek = ProviderRow {
+            quota_tokens: Some(2_000_000),
+            ..row("deepseek", KIND_BUILTIN)
+        };
+
+        let providers = build(&[anthropic, openai, deepseek]);
+        let quota = providers
+            .get(&AiProvider::Anthropic)
+            .unwrap()
+            .quota
+            .unwrap();
+        assert_eq!(quota.max_requests, Some(900));
+        assert_eq!(quota.window_secs, 5 * 3600);
+        assert!(providers.get(&AiProvider::OpenAI).unwrap().quota.is_none());
+        let quota = providers.get(&AiProvider::DeepSeek).unwrap().quota.unwrap();
+        assert_eq!(quota.window_secs, DEFAULT_QUOTA_WINDOW_SECS);
+        assert_eq!(quota.max_tokens, Some(2_000_000));
+        assert_eq!(quota.max_requests, None);
+    }
+
+    #[test]
+    fn compatible_endpoints_sorted_and_disabled_skipped() {
+        let disabled = ProviderRow {
+            enabled: false,
+            ..compatible("lm-studio", 0)
+        };
+        let providers = build(&[
+            compatible("llama-cpp", 20),
+            compatible("vllm", 10),
+            disabled,
+        ]);
+
+        let ids: Vec<&str> = providers.compatible.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["vllm", "llama-cpp"]);
+        assert_eq!(providers.compatible[0].name, "vllm");
+        assert!(providers.compatible[0].serves("qwen2.5-32b"));
+        assert!(providers.compatible("lm-studio").is_none());
+    }
+}
@@ -1,10 +1,11 @@
 //! Agent listing, refresh, CRUD management, and delegation monitoring endpoints.
 
 use axum::Json;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::sse::{Event, KeepAlive, Sse};
 use futures_util::stream::Stream;
+use serde::Deserialize;
 use serde_json::{Value, json};
 use std::convert::Infallible;
 
@@ -439,20 +440,67 @@ pub async fn list_delegations(
 //  GET /api/agents/delegations/stream — A2A real-time SSE stream
 // ═══════════════════════════════════════════════════════════════════════
 
+/// Query parameters for the delegation SSE stream.
+#[derive(Debug, Deserialize)]
+pub struct DelegationStreamParams {
+    /// Only events of this agent (case-insensitive name).
+    pub agent: Option<String>,
+    /// Only events of this chat execution or delegation task id.
+    pub execution_id: Option<String>,
+    /// Number of recent events replayed on connect (default 50, max 200).
+    pub replay: Option<usize>,
+}
+
 #[utoipa::path(
     get,
     path = "/api/agents/delegations/stream",
     tag = "agents",
-    responses((status = 200, description = "SSE stream of agent-to-agent delegations"))
+    params(
+        ("agent" = Option<String>, Query, description = "Filter by agent name"),
+        ("execution_id" = Option<String>, Query, description = "Filter by execution or task id"),
+        ("replay" = Option<usize>, Query, description = "Recent events to replay (default 50)"),
+    ),
+    responses((status = 200, description = "SSE stream of delegation lifecycle events"))
 )]
 pub async fn delegations_stream(
     State(state): State<AppState>,
+    Query(params): Query<DelegationStreamParams>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // Subscribe before snapshotting history so nothing falls in between;
+    // live events already covered by the replay are skipped by `seq`.
     let mut rx = state.a2a_task_tx.subscribe();
+    let replay = state.delegation_history.replay(
+        params
+            .replay
+            .unwrap_or(50)
+            .min(crate::delegation_events::HISTORY_CAPACITY),
+        params.agent.as_deref(),
+        params.execution_id.as_deref(),
+    );
+    let mut last_seq = replay.last().map(|e| e.seq).unwrap_or(0);
 
     let stream = async_stream::stream! {
-        while let Ok(msg) = rx.recv().await {
-            if let Ok(event) = Event::default().json_data(msg) {
+        for ev in replay {
+            if let Ok(event) = Event::default().id(ev.seq.to_string()).json_data(&ev) {
+                yield Ok(event);
+            }
+        }
+        loop {
+            let ev = match rx.recv().await {
+                Ok(ev) => ev,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("delegations_stream: client lagged, {} events skipped", skipped);
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            if ev.seq <= last_seq
+                || !ev.matches(params.agent.as_deref(), params.execution_id.as_deref())
+            {
+                continue;
+            }
+            last_seq = ev.seq;
+            if let Ok(event) = Event::default().id(ev.seq.to_string()).json_data(&ev) {
                 yield Ok(event);
             }
         }
@@ -1,15 +1,23 @@
 //! Analytics aggregation endpoints for the Agent Performance Dashboard.
 //!
 //! Provides token usage, latency, success rate, top tools, and cost estimates
-//! from `ch_agent_usage` and `ch_tool_interactions` tables.
+//! from `ch_agent_usage` and `ch_tool_interactions` tables. Costs use the
+//! versioned pricing catalogue (see [`super::pricing`]).
+//!
+//! Drill-downs of a single session or execution (iterations, tool timings,
+//! delegations, cost and a timeline) and per-tool latency percentiles and
+//! failure modes are built from `ch_execution_steps`.
 
 use axum::Json;
-use axum::extract::{Query, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
+use super::pricing::{PRICE_AT_USE_JOIN, USAGE_COST_EXPR};
+use crate::ai_gateway::vault_bridge::HasVaultBridge;
+use crate::ai_gateway::{AiProvider, HasAiGateway};
 use crate::state::AppState;
 
 // ── Query params ────────────────────────────────────────────────────────
@@ -92,22 +100,96 @@ pub struct TopToolsResponse {
     pub limit: i32,
 }
 
+#[derive(Debug, Serialize)]
+pub struct FailureMode {
+    pub error_class: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolLatencyStat {
+    pub tool_name: String,
+    pub mcp_server: Option<String>,
+    pub calls: i64,
+    pub errors: i64,
+    pub timeouts: i64,
+    pub panics: i64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: i64,
+    pub avg_result_bytes: f64,
+    /// Calls whose result was truncated before being sent to the model.
+    pub truncated: i64,
+    pub failure_modes: Vec<FailureMode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct McpServerLatency {
+    pub server: String,
+    pub calls: i64,
+    pub errors: i64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolLatencyResponse {
+    pub data: Vec<ToolLatencyStat>,
+    pub mcp_servers: Vec<McpServerLatency>,
+    pub days: i32,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CostBreakdown {
     pub model: String,
     pub tier: String,
+    pub provider: String,
     pub input_tokens: i64,
     pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    /// Metered cost at the catalogue price valid when each request ran.
+    /// The `_usd` names are kept for the dashboard; amounts are in `currency`.
     pub input_cost_usd: f64,
     pub output_cost_usd: f64,
+    pub cache_cost_usd: f64,
     pub total_cost_usd: f64,
+    /// Currency of the matched price; absent for unpriced usage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// `metered` (pay per token) or `subscription` (flat plan, amortised).
+    pub billing: &'static str,
+    /// What the usage actually cost: metered cost, or the model's share of
+    /// the amortised subscription fee.
+    pub effective_cost_usd: f64,
+    /// Requests with no matching catalogue price (not included in any cost).
+    pub unpriced_requests: i64,
+}
+
+/// A flat-fee provider plan spread over the tokens it served in the window.
+#[derive(Debug, Serialize)]
+pub struct SubscriptionCost {
+    pub provider: String,
+    pub plan_name: String,
+    pub monthly_cost_usd: f64,
+    /// Monthly fee pro-rated to the query window (`days / 30`).
+    pub window_cost_usd: f64,
+    pub tokens: i64,
+    pub effective_per_mtok_usd: f64,
 }
 
 #[derive(Debug, Serialize)]
 pub struct CostResponse {
     pub data: Vec<CostBreakdown>,
+    pub subscriptions: Vec<SubscriptionCost>,
+    /// Metered (list price) cost of all USD-priced usage.
     pub total_cost_usd: f64,
     pub projected_monthly_usd: f64,
+    /// Cost after replacing subscription-covered usage by the amortised fee.
+    pub effective_total_usd: f64,
+    /// Requests with no catalogue price (unknown provider or model), which
+    /// the totals above leave out.
+    pub unpriced_requests: i64,
     pub days: i32,
 }
 
@@ -146,14 +228,54 @@ struct ToolRow {
     tool_name: Option<String>,
     usage_count: Option<i64>,
     error_count: Option<i64>,
+    avg_duration_ms: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ToolLatencyRow {
+    tool_name: Option<String>,
+    mcp_server: Option<String>,
+    calls: Option<i64>,
+    errors: Option<i64>,
+    timeouts: Option<i64>,
+    panics: Option<i64>,
+    p50_ms: Option<f64>,
+    p95_ms: Option<f64>,
+    max_ms: Option<i64>,
+    avg_result_bytes: Option<f64>,
+    truncated: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FailureModeRow {
+    tool_name: Option<String>,
+    error_class: Option<String>,
+    count: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ServerLatencyRow {
+    server: Option<String>,
+    calls: Option<i64>,
+    errors: Option<i64>,
+    p50_ms: Option<f64>,
+    p95_ms: Option<f64>,
 }
 
 #[derive(sqlx::FromRow)]
 struct CostRow {
     model: Option<String>,
     tier: Option<String>,
+    provider: Option<String>,
     input_tokens: Option<i64>,
     output_tokens: Option<i64>,
+    cache_read_tokens: Option<i64>,
+    cache_write_tokens: Option<i64>,
+    input_cost: Option<f64>,
+    output_cost: Option<f64>,
+    cache_cost: Option<f64>,
+    currency: Option<String>,
+    unpriced_requests: Option<i64>,
 }
 
 // ── Helpers ─────────────────────────────────────────────────────────────
@@ -176,14 +298,8 @@ fn model_tier(model: &str) -> &'static str {
     }
 }
 
-/// Per-million-token pricing: (input, output).
-fn tier_pricing(tier: &str) -> (f64, f64) {
-    match tier {
-        "opus" => (15.0, 75.0),
-        "sonnet" => (3.0, 15.0),
-        "haiku" => (0.25, 1.25),
-        _ => (3.0, 15.0),
-    }
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
 }
 
 // ── Handlers ────────────────────────────────────────────────────────────
@@ -359,7 +475,8 @@ pub async fn analytics_top_tools(
         SELECT
             tool_name,
             COUNT(*) AS usage_count,
-            COUNT(*) FILTER (WHERE is_error = TRUE) AS error_count
+            COUNT(*) FILTER (WHERE is_error = TRUE) AS error_count,
+            AVG(duration_ms)::float8 AS avg_duration_ms
         FROM ch_tool_interactions
         WHERE executed_at >= NOW() - make_interval(days => $1)
         GROUP BY tool_name
@@ -385,37 +502,179 @@ pub async fn analytics_top_tools(
             tool_name: r.tool_name.unwrap_or_default(),
             usage_count: r.usage_count.unwrap_or(0),
             error_count: r.error_count.unwrap_or(0),
-            avg_duration_ms: None, // ch_tool_interactions doesn't have duration_ms
+            avg_duration_ms: r.avg_duration_ms,
         })
         .collect();
 
     Ok(Json(TopToolsResponse { data, days, limit }))
 }
 
-/// `GET /api/analytics/cost?days=30` — estimated cost based on token usage + model pricing
-pub async fn analytics_cost(
+/// `GET /api/analytics/tool-latency?days=7` — per-tool p50/p95 latency, timeouts,
+/// panics, result sizes and failure modes of backend tool executions
+pub async fn analytics_tool_latency(
     State(state): State<AppState>,
     Query(q): Query<TimeRangeQuery>,
-) -> Result<Json<CostResponse>, (StatusCode, Json<Value>)> {
+) -> Result<Json<ToolLatencyResponse>, (StatusCode, Json<Value>)> {
     let days = clamp_days(q.days);
+    let failed = |e: sqlx::Error| {
+        tracing::error!("analytics/tool-latency query failed: {e}");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Failed to fetch tool latency" })),
+        )
+    };
 
-    let rows = sqlx::query_as::<_, CostRow>(
+    let rows = sqlx::query_as::<_, ToolLatencyRow>(
         r#"
         SELECT
-            model,
-            tier,
-            COALESCE(SUM(input_tokens), 0) AS input_tokens,
-            COALESCE(SUM(output_tokens), 0) AS output_tokens
-        FROM ch_agent_usage
-        WHERE created_at >= NOW() - make_interval(days => $1)
-        GROUP BY model, tier
-        ORDER BY model ASC
+            tool_name,
+            MAX(mcp_server) AS mcp_server,
+            COUNT(*) AS calls,
+            COUNT(*) FILTER (WHERE is_error) AS errors,
+            COUNT(*) FILTER (WHERE outcome = 'timeout') AS timeouts,
+            COUNT(*) FILTER (WHERE outcome = 'panic') AS panics,
+            percentile_cont(0.5) WITHIN GROUP (ORDER BY duration_ms)::float8 AS p50_ms,
+            percentile_cont(0.95) WITHIN GROUP (ORDER BY duration_ms)::float8 AS p95_ms,
+            MAX(duration_ms)::int8 AS max_ms,
+            AVG(result_bytes)::float8 AS avg_result_bytes,
+            COUNT(*) FILTER (WHERE truncated) AS truncated
+        FROM ch_execution_steps
+        WHERE kind = 'tool_call' AND started_at >= NOW() - make_interval(days => $1)
+        GROUP BY tool_name
+        ORDER BY calls DESC
         "#,
     )
     .bind(days)
     .fetch_all(&state.db)
     .await
-    .map_err(|e| {
+    .map_err(failed)?;
+
+    let failures = sqlx::query_as::<_, FailureModeRow>(
+        r#"
+        SELECT tool_name, COALESCE(error_class, 'other') AS error_class, COUNT(*) AS count
+        FROM ch_execution_steps
+        WHERE kind = 'tool_call' AND is_error
+          AND started_at >= NOW() - make_interval(days => $1)
+        GROUP BY 1, 2
+        ORDER BY count DESC
+        "#,
+    )
+    .bind(days)
+    .fetch_all(&state.db)
+    .await
+    .map_err(failed)?;
+
+    let servers = sqlx::query_as::<_, ServerLatencyRow>(
+        r#"
+        SELECT
+            mcp_server AS server,
+            COUNT(*) AS calls,
+            COUNT(*) FILTER (WHERE is_error) AS errors,
+            percentile_cont(0.5) WITHIN GROUP (ORDER BY duration_ms)::float8 AS p50_ms,
+            percentile_cont(0.95) WITHIN GROUP (ORDER BY duration_ms)::float8 AS p95_ms
+        FROM ch_execution_steps
+        WHERE kind = 'tool_call' AND mcp_server IS NOT NULL
+          AND started_at >= NOW() - make_interval(days => $1)
+        GROUP BY mcp_server
+        ORDER BY calls DESC
+        "#,
+    )
+    .bind(days)
+    .fetch_all(&state.db)
+    .await
+    .map_err(failed)?;
+
+    let data = rows
+        .into_iter()
+        .map(|r| {
+            let tool_name = r.tool_name.unwrap_or_default();
+            let failure_modes = failures
+                .iter()
+                .filter(|f| f.tool_name.as_deref() == Some(tool_name.as_str()))
+                .map(|f| FailureMode {
+                    error_class: f.error_class.clone().unwrap_or_default(),
+                    count: f.count.unwrap_or(0),
+                })
+                .collect();
+            ToolLatencyStat {
+                tool_name,
+                mcp_server: r.mcp_server,
+                calls: r.calls.unwrap_or(0),
+                errors: r.errors.unwrap_or(0),
+                timeouts: r.timeouts.unwrap_or(0),
+                panics: r.panics.unwrap_or(0),
+                p50_ms: r.p50_ms.unwrap_or(0.0),
+                p95_ms: r.p95_ms.unwrap_or(0.0),
+                max_ms: r.max_ms.unwrap_or(0),
+                avg_result_bytes: r.avg_result_bytes.unwrap_or(0.0).round(),
+                truncated: r.truncated.unwrap_or(0),
+                failure_modes,
+            }
+        })
+        .collect();
+
+    let mcp_servers = servers
+        .into_iter()
+        .map(|s| McpServerLatency {
+            server: s.server.unwrap_or_default(),
+            calls: s.calls.unwrap_or(0),
+            errors: s.errors.unwrap_or(0),
+            p50_ms: s.p50_ms.unwrap_or(0.0),
+            p95_ms: s.p95_ms.unwrap_or(0.0),
+        })
+        .collect();
+
+    Ok(Json(ToolLatencyResponse {
+        data,
+        mcp_servers,
+        days,
+    }))
+}
+
+/// Usage of the last `days` per model, tier and price currency, priced at
+/// time of use. A model whose price changed currency yields one row per
+/// currency; usage without a catalogue price has `currency` NULL.
+async fn cost_rows(db: &sqlx::PgPool, days: i32) -> Result<Vec<CostRow>, sqlx::Error> {
+    sqlx::query_as::<_, CostRow>(&format!(
+        r#"
+        SELECT
+            u.model,
+            u.tier,
+            ch_model_provider(u.model) AS provider,
+            COALESCE(SUM(u.input_tokens), 0)::int8 AS input_tokens,
+            COALESCE(SUM(u.output_tokens), 0)::int8 AS output_tokens,
+            COALESCE(SUM(u.cache_read_tokens), 0)::int8 AS cache_read_tokens,
+            COALESCE(SUM(u.cache_write_tokens), 0)::int8 AS cache_write_tokens,
+            COALESCE(SUM(COALESCE(u.input_tokens, 0) * price.input_rate), 0)::float8
+                / 1000000.0 AS input_cost,
+            COALESCE(SUM(COALESCE(u.output_tokens, 0) * price.output_rate), 0)::float8
+                / 1000000.0 AS output_cost,
+            COALESCE(SUM(u.cache_read_tokens * price.cache_read_rate
+                + u.cache_write_tokens * price.cache_write_rate), 0)::float8
+                / 1000000.0 AS cache_cost,
+            price.currency,
+            COUNT(*) FILTER (WHERE price.input_rate IS NULL) AS unpriced_requests
+        FROM ch_agent_usage u
+        {PRICE_AT_USE_JOIN}
+        WHERE u.created_at >= NOW() - make_interval(days => $1)
+        GROUP BY u.model, u.tier, price.currency
+        ORDER BY u.model ASC, price.currency ASC
+        "#
+    ))
+    .bind(days)
+    .fetch_all(db)
+    .await
+}
+
+/// `GET /api/analytics/cost?days=30` — cost from token usage priced at time of use,
+/// with subscription-plan providers amortised over the tokens they served
+pub async fn analytics_cost(
+    State(state): State<AppState>,
+    Query(q): Query<TimeRangeQuery>,
+) -> Result<Json<CostResponse>, (StatusCode, Json<Value>)> {
+    let days = clamp_days(q.days);
+
+    let rows = cost_rows(&state.db, days).await.map_err(|e| {
         tracing::error!("analytics/cost query failed: {e}");
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -423,34 +682,95 @@ pub async fn analytics_cost(
         )
     })?;
 
+    let row_tokens = |r: &CostRow| {
+        r.input_tokens.unwrap_or(0)
+            + r.output_tokens.unwrap_or(0)
+            + r.cache_read_tokens.unwrap_or(0)
+            + r.cache_write_tokens.unwrap_or(0)
+    };
+
+    // Flat-fee plans that are connected and served traffic in the window.
+    let mut subscriptions = Vec::new();
+    for provider in AiProvider::ALL {
+        let Some(config) = state.provider_config(provider) else {
+            continue;
+        };
+        if config.monthly_cost_cents == 0 {
+            continue;
+        }
+        let name = provider.to_string();
+        let tokens: i64 = rows
+            .iter()
+            .filter(|r| r.provider.as_deref() == Some(name.as_str()))
+            .map(row_tokens)
+            .sum();
+        if tokens == 0
+            || !state
+                .vault_client()
+                .get_provider_status(&name)
+                .await
+                .is_connected
+        {
+            continue;
+        }
+        let monthly = config.monthly_cost_cents as f64 / 100.0;
+        let window_cost = monthly * days as f64 / 30.0;
+        subscriptions.push(SubscriptionCost {
+            provider: name,
+            plan_name: config.plan_name.clone(),
+            monthly_cost_usd: monthly,
+            window_cost_usd: round_cents(window_cost),
+            tokens,
+            effective_per_mtok_usd: (window_cost / tokens as f64 * 1_000_000.0 * 10_000.0).round()
+                / 10_000.0,
+        });
+    }
+
     let data: Vec<CostBreakdown> = rows
-        .into_iter()
+        .iter()
         .map(|r| {
-            let model = r.model.unwrap_or_default();
+            let model = r.model.clone().unwrap_or_default();
             let tier = r
                 .tier
                 .clone()
                 .unwrap_or_else(|| model_tier(&model).to_string());
-            let input_tokens = r.input_tokens.unwrap_or(0);
-            let output_tokens = r.output_tokens.unwrap_or(0);
+            let provider = r.provider.clone().unwrap_or_else(|| "unknown".to_string());
+            let input_cost = r.input_cost.unwrap_or(0.0);
+            let output_cost = r.output_cost.unwrap_or(0.0);
+            let cache_cost = r.cache_cost.unwrap_or(0.0);
+            let metered = input_cost + output_cost + cache_cost;
 
-            let (input_price, output_price) = tier_pricing(&tier);
-            let input_cost = (input_tokens as f64 / 1_000_000.0) * input_price;
-            let output_cost = (output_tokens as f64 / 1_000_000.0) * output_price;
+            let subscription = subscriptions.iter().find(|s| s.provider == provider);
+            let (billing, effective) = match subscription {
+                Some(s) => (
+                    "subscription",
+                    s.window_cost_usd * row_tokens(r) as f64 / s.tokens as f64,
+                ),
+                None => ("metered", metered),
+            };
 
             CostBreakdown {
                 model,
                 tier,
-                input_tokens,
-                output_tokens,
-                input_cost_usd: (input_cost * 100.0).round() / 100.0,
-                output_cost_usd: (output_cost * 100.0).round() / 100.0,
-                total_cost_usd: ((input_cost + output_cost) * 100.0).round() / 100.0,
+                provider,
+                input_tokens: r.input_tokens.unwrap_or(0),
+                output_tokens: r.output_tokens.unwrap_or(0),
+                cache_read_tokens: r.cache_read_tokens.unwrap_or(0),
+                cache_write_tokens: r.cache_write_tokens.unwrap_or(0),
+                input_cost_usd: round_cents(input_cost),
+                output_cost_usd: round_cents(output_cost),
+                cache_cost_usd: round_cents(cache_cost),
+                total_cost_usd: round_cents(metered),
+                currency: r.currency.clone(),
+                billing,
+                effective_cost_usd: round_cents(effective),
+                unpriced_requests: r.unpriced_requests.unwrap_or(0),
             }
         })
         .collect();
 
-    let total_cost: f64 = data.iter().map(|d| d.total_cost_usd).sum();
+    let (total_cost, effective_total) = usd_totals(&data);
+    let unpriced_requests = data.iter().map(|d| d.unpriced_requests).sum();
     let projected_monthly = if days > 0 {
         (total_cost / days as f64) * 30.0
     } else {
@@ -459,8 +779,701 @@ pub async fn analytics_cost(
 
     Ok(Json(CostResponse {
         data,
-        total_cost_usd: (total_cost * 100.0).round() / 100.0,
-        projected_monthly_usd: (projected_monthly * 100.0).round() / 100.0,
+        subscriptions,
+        total_cost_usd: round_cents(total_cost),
+        projected_monthly_usd: round_cents(projected_monthly),
+        effective_total_usd: round_cents(effective_total),
+        unpriced_requests,
         days,
     }))
 }
+
+/// Metered and effective totals of the USD rows — other currencies are
+/// reported per row only, unpriced rows carry no cost.
+fn usd_totals(data: &[CostBreakdown]) -> (f64, f64) {
+    data.iter()
+        .filter(|d| d.currency.as_deref() == Some("USD"))
+        .fold((0.0, 0.0), |(metered, effective), d| {
+            (metered + d.total_cost_usd, effective + d.effective_cost_usd)
+        })
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Session / execution drill-down
+// ═══════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Default, Serialize)]
+pub struct StepTotals {
+    pub model_calls: i64,
+    pub tool_calls: i64,
+    pub tool_errors: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub cost_usd: f64,
+    /// Model calls without a catalogue price (not included in `cost_usd`).
+    pub unpriced_calls: i64,
+}
+
+impl StepTotals {
+    fn add(&mut self, step: &TimelineStep) {
+        if step.kind == "tool_call" {
+            self.tool_calls += 1;
+            if step.is_error {
+                self.tool_errors += 1;
+            }
+            return;
+        }
+        self.model_calls += 1;
+        self.input_tokens += step.input_tokens as i64;
+        self.output_tokens += step.output_tokens as i64;
+        self.cache_read_tokens += step.cache_read_tokens as i64;
+        self.cache_write_tokens += step.cache_write_tokens as i64;
+        match step.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_calls += 1,
+        }
+    }
+}
+
+/// One model or tool call, ordered by start time.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TimelineStep {
+    pub id: i64,
+    pub kind: String,
+    pub iteration: i32,
+    /// Agent name for delegated steps, `None` for the top-level loop.
+    pub agent_id: Option<String>,
+    pub delegation_task_id: Option<uuid::Uuid>,
+    pub model: Option<String>,
+    pub tool_name: Option<String>,
+    pub is_error: bool,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cache_read_tokens: i32,
+    pub cache_write_tokens: i32,
+    pub duration_ms: i32,
+    pub started_at: DateTime<Utc>,
+    /// Tool calls only: ok / error / timeout / panic and the error class.
+    pub outcome: Option<String>,
+    pub error_class: Option<String>,
+    pub result_bytes: Option<i32>,
+    pub truncated: bool,
+    pub mcp_server: Option<String>,
+    /// Milliseconds since the first step of the execution.
+    #[sqlx(default)]
+    pub offset_ms: i64,
+    pub cost_usd: Option<f64>,
+}
+
+/// A top-level loop iteration: its model call plus the tools it requested.
+#[derive(Debug, Serialize)]
+pub struct IterationSummary {
+    pub iteration: i32,
+    pub model: Option<String>,
+    pub model_ms: i64,
+    pub tool_ms: i64,
+    #[serde(flatten)]
+    pub totals: StepTotals,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolTiming {
+    pub tool_name: String,
+    pub calls: i64,
+    pub errors: i64,
+    pub total_ms: i64,
+    pub avg_ms: f64,
+    pub max_ms: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DelegationSummary {
+    pub task_id: uuid::Uuid,
+    pub parent_task_id: Option<uuid::Uuid>,
+    pub agent: String,
+    pub model: String,
+    pub status: String,
+    pub call_depth: i32,
+    pub iterations_used: i32,
+    pub duration_ms: Option<i32>,
+    pub is_error: bool,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub totals: StepTotals,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExecutionDetail {
+    pub execution_id: String,
+    pub session_id: Option<uuid::Uuid>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: i64,
+    /// Whole execution, delegations included.
+    pub totals: StepTotals,
+    pub iterations: Vec<IterationSummary>,
+    pub tools: Vec<ToolTiming>,
+    pub delegations: Vec<DelegationSummary>,
+    pub timeline: Vec<TimelineStep>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SessionExecution {
+    pub execution_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub models: Vec<String>,
+    pub iterations: i32,
+    pub model_calls: i64,
+    pub tool_calls: i64,
+    pub tool_errors: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub cost_usd: f64,
+    pub unpriced_calls: i64,
+    pub delegations: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionDetail {
+    pub session_id: uuid::Uuid,
+    pub title: String,
+    pub executions: Vec<SessionExecution>,
+    pub totals: StepTotals,
+}
+
+#[derive(sqlx::FromRow)]
+struct DelegationRow {
+    id: uuid::Uuid,
+    parent_task_id: Option<uuid::Uuid>,
+    agent_name: String,
+    model_used: String,
+    status: String,
+    call_depth: i32,
+    iterations_used: i32,
+    duration_ms: Option<i32>,
+    is_error: bool,
+    created_at: DateTime<Utc>,
+}
+
+fn query_failed(what: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    tracing::error!("analytics/{what} query failed: {e}");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("Failed to fetch {what}") })),
+    )
+}
+
+/// Totals of a timeline, its top-level iterations and per-tool timings
+/// (slowest tool first).
+fn summarize_steps(
+    timeline: &[TimelineStep],
+) -> (StepTotals, Vec<IterationSummary>, Vec<ToolTiming>) {
+    let mut totals = StepTotals::default();
+    let mut iterations: Vec<IterationSummary> = Vec::new();
+    let mut tools: Vec<ToolTiming> = Vec::new();
+    for step in timeline {
+        totals.add(step);
+
+        if let Some(tool_name) = &step.tool_name {
+            let timing = match tools.iter_mut().position(|t| &t.tool_name == tool_name) {
+                Some(i) => &mut tools[i],
+                None => {
+                    tools.push(ToolTiming {
+                        tool_name: tool_name.clone(),
+                        calls: 0,
+                        errors: 0,
+                        total_ms: 0,
+                        avg_ms: 0.0,
+                        max_ms: 0,
+                    });
+                    tools.last_mut().expect("just pushed")
+                }
+            };
+            timing.calls += 1;
+            timing.errors += step.is_error as i64;
+            timing.total_ms += step.duration_ms as i64;
+            timing.max_ms = timing.max_ms.max(step.duration_ms as i64);
+        }
+
+        // Delegated steps are reported per delegation below.
+        if step.delegation_task_id.is_some() {
+            continue;
+        }
+        let summary = match iterations
+            .iter_mut()
+            .position(|i| i.iteration == step.iteration)
+        {
+            Some(i) => &mut iterations[i],
+            None => {
+                iterations.push(IterationSummary {
+                    iteration: step.iteration,
+                    model: None,
+                    model_ms: 0,
+                    tool_ms: 0,
+                    totals: StepTotals::default(),
+                });
+                iterations.last_mut().expect("just pushed")
+            }
+        };
+        summary.totals.add(step);
+        if step.kind == "model_call" {
+            summary.model = step.model.clone();
+            summary.model_ms += step.duration_ms as i64;
+        } else {
+            summary.tool_ms += step.duration_ms as i64;
+        }
+    }
+    for timing in &mut tools {
+        timing.avg_ms = timing.total_ms as f64 / timing.calls as f64;
+    }
+    tools.sort_by(|a, b| b.total_ms.cmp(&a.total_ms));
+    iterations.sort_by_key(|i| i.iteration);
+    (totals, iterations, tools)
+}
+
+/// `GET /api/analytics/executions/{execution_id}` — iterations, tool timings,
+/// delegations, cost and the step timeline of one execution
+pub async fn analytics_execution(
+    State(state): State<AppState>,
+    Path(execution_id): Path<String>,
+) -> Result<Json<ExecutionDetail>, (StatusCode, Json<Value>)> {
+    let mut timeline = sqlx::query_as::<_, TimelineStep>(&format!(
+        r#"
+        SELECT u.id, u.kind, u.iteration, u.agent_id, u.delegation_task_id, u.model,
+            u.tool_name, u.is_error, u.input_tokens, u.output_tokens, u.cache_read_tokens,
+            u.cache_write_tokens, u.duration_ms, u.started_at, u.outcome, u.error_class,
+            u.result_bytes, u.truncated, u.mcp_server,
+            {USAGE_COST_EXPR}::float8 AS cost_usd
+        FROM ch_execution_steps u
+        {PRICE_AT_USE_JOIN}
+        WHERE u.execution_id = $1
+        ORDER BY u.started_at ASC, u.id ASC
+        "#
+    ))
+    .bind(&execution_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| query_failed("execution", e))?;
+
+    if timeline.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Execution not found" })),
+        ));
+    }
+
+    let session_id: Option<uuid::Uuid> = sqlx::query_scalar(
+        "SELECT session_id FROM ch_execution_steps \
+         WHERE execution_id = $1 AND session_id IS NOT NULL LIMIT 1",
+    )
+    .bind(&execution_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| query_failed("execution", e))?;
+
+    let started_at = timeline.first().map(|s| s.started_at);
+    let finished_at = timeline
+        .iter()
+        .map(|s| s.started_at + chrono::Duration::milliseconds(s.duration_ms as i64))
+        .max();
+    if let Some(start) = started_at {
+        for step in &mut timeline {
+            step.offset_ms = (step.started_at - start).num_milliseconds();
+        }
+    }
+
+    let (mut totals, mut iterations, tools) = summarize_steps(&timeline);
+
+    let delegation_rows = sqlx::query_as::<_, DelegationRow>(
+        "SELECT id, parent_task_id, agent_name, model_used, status, call_depth, \
+            iterations_used, duration_ms, is_error, created_at \
+         FROM ch_a2a_tasks WHERE execution_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(&execution_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| query_failed("execution", e))?;
+
+    let delegations = delegation_rows
+        .into_iter()
+        .map(|d| {
+            let mut totals = StepTotals::default();
+            for step in timeline
+                .iter()
+                .filter(|s| s.delegation_task_id == Some(d.id))
+            {
+                totals.add(step);
+            }
+            totals.cost_usd = round_cents(totals.cost_usd);
+            DelegationSummary {
+                task_id: d.id,
+                parent_task_id: d.parent_task_id,
+                agent: d.agent_name,
+                model: d.model_used,
+                status: d.status,
+                call_depth: d.call_depth,
+                iterations_used: d.iterations_used,
+                duration_ms: d.duration_ms,
+                is_error: d.is_error,
+                created_at: d.created_at,
+                totals,
+            }
+        })
+        .collect();
+
+    totals.cost_usd = round_cents(totals.cost_usd);
+    for summary in &mut iterations {
+        summary.totals.cost_usd = round_cents(summary.totals.cost_usd);
+    }
+
+    Ok(Json(ExecutionDetail {
+        execution_id,
+        session_id,
+        started_at,
+        finished_at,
+        duration_ms: match (started_at, finished_at) {
+            (Some(start), Some(end)) => (end - start).num_milliseconds(),
+            _ => 0,
+        },
+        totals,
+        iterations,
+        tools,
+        delegations,
+        timeline,
+    }))
+}
+
+/// `GET /api/analytics/sessions/{id}` — per-execution usage, tool calls,
+/// delegations and cost of one chat session
+pub async fn analytics_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<uuid::Uuid>,
+) -> Result<Json<SessionDetail>, (StatusCode, Json<Value>)> {
+    let title: Option<String> = sqlx::query_scalar("SELECT title FROM ch_sessions WHERE id = $1")
+        .bind(session_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| query_failed("session", e))?;
+    let Some(title) = title else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Session not found" })),
+        ));
+    };
+
+    let executions = sqlx::query_as::<_, SessionExecution>(&format!(
+        r#"
+        SELECT
+            u.execution_id,
+            MIN(u.started_at) AS started_at,
+            MAX(u.started_at + make_interval(secs => u.duration_ms / 1000.0)) AS finished_at,
+            COALESCE(array_agg(DISTINCT u.model) FILTER (WHERE u.model IS NOT NULL),
+                '{{}}') AS models,
+            COALESCE(MAX(u.iteration) FILTER (WHERE u.delegation_task_id IS NULL), 0)
+                AS iterations,
+            COUNT(*) FILTER (WHERE u.kind = 'model_call') AS model_calls,
+            COUNT(*) FILTER (WHERE u.kind = 'tool_call') AS tool_calls,
+            COUNT(*) FILTER (WHERE u.kind = 'tool_call' AND u.is_error) AS tool_errors,
+            COALESCE(SUM(u.input_tokens), 0)::int8 AS input_tokens,
+            COALESCE(SUM(u.output_tokens), 0)::int8 AS output_tokens,
+            COALESCE(SUM(u.cache_read_tokens), 0)::int8 AS cache_read_tokens,
+            COALESCE(SUM(u.cache_write_tokens), 0)::int8 AS cache_write_tokens,
+            COALESCE(SUM({USAGE_COST_EXPR}), 0)::float8 AS cost_usd,
+            COUNT(*) FILTER (WHERE u.kind = 'model_call' AND price.input_rate IS NULL)
+                AS unpriced_calls,
+            COUNT(DISTINCT u.delegation_task_id) AS delegations
+        FROM ch_execution_steps u
+        {PRICE_AT_USE_JOIN}
+        WHERE u.session_id = $1 AND u.execution_id IS NOT NULL
+        GROUP BY u.execution_id
+        ORDER BY started_at ASC
+        "#
+    ))
+    .bind(session_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| query_failed("session", e))?;
+
+    let mut totals = StepTotals::default();
+    for e in &executions {
+        totals.model_calls += e.model_calls;
+        totals.tool_calls += e.tool_calls;
+        totals.tool_errors += e.tool_errors;
+        totals.input_tokens += e.input_tokens;
+        totals.output_tokens += e.output_tokens;
+        totals.cache_read_tokens += e.cache_read_tokens;
+        totals.cache_write_tokens += e.cache_write_tokens;
+        totals.cost_usd += e.cost_usd;
+        totals.unpriced_calls += e.unpriced_calls;
+    }
+    totals.cost_usd = round_cents(totals.cost_usd);
+
+    let executions = executions
+        .into_iter()
+        .map(|e| SessionExecution {
+            cost_usd: round_cents(e.cost_usd),
+            ..e
+        })
+        .collect();
+
+    Ok(Json(SessionDetail {
+        session_id,
+        title,
+        executions,
+        totals,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breakdown(currency: Option<&str>, cost: f64, unpriced: i64) -> CostBreakdown {
+        CostBreakdown {
+            model: "m".to_string(),
+            tier: "coordinator".to_string(),
+            provider: "anthropic".to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+            input_cost_usd: cost,
+            output_cost_usd: 0.0,
+            cache_cost_usd: 0.0,
+            total_cost_usd: cost,
+            currency: currency.map(str::to_string),
+            billing: "metered",
+            effective_cost_usd: cost / 2.0,
+            unpriced_requests: unpriced,
+        }
+    }
+
+    #[test]
+    fn totals_add_up_usd_rows_only() {
+        let data = [
+            breakdown(Some("USD"), 2.0, 0),
+            breakdown(Some("EUR"), 5.0, 0),
+            breakdown(None, 0.0, 3),
+            breakdown(Some("USD"), 1.0, 0),
+        ];
+        assert_eq!(usd_totals(&data), (3.0, 1.5));
+        assert_eq!(usd_totals(&[]), (0.0, 0.0));
+    }
+
+    fn model_step(
+        iteration: i32,
+        input: i32,
+        output: i32,
+        ms: i32,
+        cost: Option<f64>,
+    ) -> TimelineStep {
+        TimelineStep {
+            id: 0,
+            kind: "model_call".to_string(),
+            iteration,
+            agent_id: None,
+            delegation_task_id: None,
+            model: Some("claude-sonnet-4-6".to_string()),
+            tool_name: None,
+            is_error: false,
+            input_tokens: input,
+            output_tokens: output,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+            duration_ms: ms,
+            started_at: Utc::now(),
+            outcome: None,
+            error_class: None,
+            result_bytes: None,
+            truncated: false,
+            mcp_server: None,
+            offset_ms: 0,
+            cost_usd: cost,
+        }
+    }
+
+    fn tool_step(iteration: i32, tool: &str, ms: i32, is_error: bool) -> TimelineStep {
+        TimelineStep {
+            kind: "tool_call".to_string(),
+            model: None,
+            tool_name: Some(tool.to_string()),
+            is_error,
+            ..model_step(iteration, 0, 0, ms, None)
+        }
+    }
+
+    fn delegated(step: TimelineStep, task_id: uuid::Uuid) -> TimelineStep {
+        TimelineStep {
+            agent_id: Some("yennefer".to_string()),
+            delegation_task_id: Some(task_id),
+            ..step
+        }
+    }
+
+    #[test]
+    fn steps_roll_up_into_iterations_tools_and_totals() {
+        let task = uuid::Uuid::new_v4();
+        let timeline = [
+            model_step(1, 1_000, 200, 800, Some(0.25)),
+            tool_step(1, "read_file", 30, false),
+            tool_step(1, "call_agent", 900, false),
+            delegated(model_step(1, 500, 100, 600, Some(0.5)), task),
+            delegated(tool_step(1, "read_file", 50, true), task),
+            model_step(2, 1_500, 300, 700, None),
+        ];
+        let (totals, iterations, tools) = summarize_steps(&timeline);
+
+        // The execution totals include the delegation.
+        assert_eq!(totals.model_calls, 3);
+        assert_eq!(totals.tool_calls, 3);
+        assert_eq!(totals.tool_errors, 1);
+        assert_eq!(totals.input_tokens, 3_000);
+        assert_eq!(totals.output_tokens, 600);
+        assert_eq!(totals.cost_usd, 0.75);
+        assert_eq!(totals.unpriced_calls, 1);
+
+        // Iterations are top-level only.
+        assert_eq!(iterations.len(), 2);
+        let first = &iterations[0];
+        assert_eq!(first.iteration, 1);
+        assert_eq!((first.model_ms, first.tool_ms), (800, 930));
+        assert_eq!(first.totals.model_calls, 1);
+        assert_eq!(first.totals.tool_calls, 2);
+        assert_eq!(first.totals.tool_errors, 0);
+        assert_eq!(first.totals.cost_usd, 0.25);
+        let second = &iterations[1];
+        assert_eq!(second.model.as_deref(), Some("claude-sonnet-4-6"));
+        assert_eq!(second.totals.input_tokens, 1_500);
+        assert_eq!(second.totals.unpriced_calls, 1);
+        assert_eq!(second.totals.cost_usd, 0.0);
+
+        // Tool timings span the delegation, slowest first.
+        let names: Vec<_> = tools.iter().map(|t| t.tool_name.as_str()).collect();
+        assert_eq!(names, ["call_agent", "read_file"]);
+        let read_file = &tools[1];
+        assert_eq!((read_file.calls, read_file.errors), (2, 1));
+        assert_eq!((read_file.total_ms, read_file.max_ms), (80, 50));
+        assert_eq!(read_file.avg_ms, 40.0);
+    }
+
+    #[test]
+    fn iterations_are_ordered_by_number() {
+        let timeline = [
+            model_step(2, 10, 1, 5, Some(0.0)),
+            model_step(1, 10, 1, 5, Some(0.0)),
+        ];
+        let (_, iterations, tools) = summarize_steps(&timeline);
+        let numbers: Vec<_> = iterations.iter().map(|i| i.iteration).collect();
+        assert_eq!(numbers, [1, 2]);
+        assert!(tools.is_empty());
+    }
+
+    async fn test_db() -> Option<sqlx::PgPool> {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        Some(pool)
+    }
+
+    #[tokio::test]
+    async fn unknown_models_have_no_provider() {
+        let Some(db) = test_db().await else {
+            return;
+        };
+        for (model, provider) in [
+            ("claude-sonnet-4-6", Some("anthropic")),
+            ("gemini-2.5-flash", Some("google")),
+            ("gpt-4o", Some("openai")),
+            ("o3-mini", Some("openai")),
+            ("grok-3", Some("xai")),
+            ("deepseek-chat", Some("deepseek")),
+            ("llama3.1:8b", None),
+            ("mistral-large", None),
+            ("", None),
+        ] {
+            let mapped: Option<String> = sqlx::query_scalar("SELECT ch_model_provider($1)")
+                .bind(model)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+            assert_eq!(mapped.as_deref(), provider, "{model}");
+        }
+    }
+
+    #[tokio::test]
+    async fn cost_rows_split_currencies_and_keep_unknown_models_unpriced() {
+        let Some(db) = test_db().await else {
+            return;
+        };
+        let tag = uuid::Uuid::new_v4().simple().to_string();
+        let priced = format!("claude-cost-{tag}");
+        let unknown = format!("mystery-{tag}");
+
+        // USD until yesterday, EUR since.
+        for (days_ago, rate, currency) in [(3, 2.0, "USD"), (1, 4.0, "EUR")] {
+            sqlx::query(
+                "INSERT INTO ch_model_pricing \
+                 (provider, model_pattern, effective_from, input_per_mtok, output_per_mtok, currency) \
+                 VALUES ('anthropic', $1, NOW() - make_interval(days => $2), $3, 0, $4)",
+            )
+            .bind(&priced)
+            .bind(days_ago)
+            .bind(rate)
+            .bind(currency)
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+        for (model, hours_ago) in [(&priced, 48), (&priced, 47), (&priced, 2), (&unknown, 2)] {
+            sqlx::query(
+                "INSERT INTO ch_agent_usage \
+                 (model, input_tokens, output_tokens, total_tokens, latency_ms, success, tier, \
+                  created_at) \
+                 VALUES ($1, 1000000, 0, 1000000, 10, TRUE, 'coordinator', \
+                  NOW() - make_interval(hours => $2))",
+            )
+            .bind(model)
+            .bind(hours_ago)
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+
+        let rows = cost_rows(&db, 7).await.unwrap();
+        let priced_rows: Vec<&CostRow> = rows
+            .iter()
+            .filter(|r| r.model.as_deref() == Some(priced.as_str()))
+            .collect();
+        assert_eq!(priced_rows.len(), 2);
+        assert_eq!(priced_rows[0].currency.as_deref(), Some("EUR"));
+        assert_eq!(priced_rows[0].input_cost, Some(4.0));
+        assert_eq!(priced_rows[1].currency.as_deref(), Some("USD"));
+        assert_eq!(priced_rows[1].input_tokens, Some(2_000_000));
+        assert_eq!(priced_rows[1].input_cost, Some(4.0));
+
+        let unknown_row = rows
+            .iter()
+            .find(|r| r.model.as_deref() == Some(unknown.as_str()))
+            .unwrap();
+        assert_eq!(unknown_row.provider, None);
+        assert_eq!(unknown_row.currency, None);
+        assert_eq!(unknown_row.unpriced_requests, Some(1));
+        assert_eq!(unknown_row.input_cost, Some(0.0));
+
+        sqlx::query("DELETE FROM ch_agent_usage WHERE model = ANY($1)")
+            .bind(vec![priced.clone(), unknown])
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM ch_model_pricing WHERE model_pattern = $1")
+            .bind(&priced)
+            .execute(&db)
+            .await
+            .unwrap();
+    }
+}
@@ -3,7 +3,8 @@
 //! Provides the shared infrastructure used by both streaming and non-streaming
 //! chat handlers to communicate with the Anthropic Messages API:
 //!
-//! - [`get_anthropic_credential`] — 3-tier resolution: Vault → runtime keys → env var
+//! - [`get_anthropic_credential`] — Vault → cached Vault session → runtime keys → env var,
+//!   limited by `CREDENTIAL_POLICY` and refused during a Vault lockout
 //! - [`build_anthropic_request`] — builds a `reqwest::RequestBuilder` with auth headers
 //! - [`send_to_anthropic_once`] — single attempt with Vault Bouncer delegation
 //! - [`send_to_anthropic`]      — circuit-breaker + one retry on 429/5xx
@@ -14,24 +15,39 @@ use axum::http::StatusCode;
 use serde_json::{Value, json};
 
 use crate::ai_gateway::vault_bridge::HasVaultBridge;
+use crate::ai_gateway::vault_guard::{CredentialSource, LockoutTrigger};
 use crate::state::AppState;
 
 use super::is_retryable_status;
 
+/// Vault location of the Anthropic session.
+const ANTHROPIC_VAULT_NAMESPACE: &str = "ai_providers";
+const ANTHROPIC_VAULT_SERVICE: &str = "anthropic_max";
+
 /// Get the Anthropic credential with resolution strategy:
 /// 1. First try: Jaskier Vault (`ai_providers/anthropic_max`)
-/// 2. Fallback: Runtime API keys (hot-loaded from DB)
-/// 3. Last resort: `ANTHROPIC_API_KEY` env var
+/// 2. Vault unreachable: the cached Vault session (`vault_with_cache`, `env_allowed`)
+/// 3. Fallback: Runtime API keys (hot-loaded from DB) — `env_allowed` only
+/// 4. Last resort: `ANTHROPIC_API_KEY` env var — `env_allowed` only
 ///
 /// B13: Removed old DB OAuth path (`get_valid_anthropic_access_token`).
 /// Credentials now come from Vault or environment variables.
 ///
+/// Returns `None` during a Vault lockout; a Vault anomaly starts one.
 /// Returns `(token_or_key, is_oauth)`.
 pub(crate) async fn get_anthropic_credential(state: &AppState) -> Option<(String, bool)> {
+    let guard = &state.ai_gateway.vault_guard;
+    if let Err(lockout) = guard.check() {
+        tracing::error!("Anthropic credential refused — {}", lockout);
+        guard.record_source("anthropic", CredentialSource::Locked);
+        return None;
+    }
+    let policy = guard.policy();
+
     // 1. Try Vault first (ai_providers/anthropic_max)
     match state
         .vault_client()
-        .get("ai_providers", "anthropic_max")
+        .get(ANTHROPIC_VAULT_NAMESPACE, ANTHROPIC_VAULT_SERVICE)
         .await
     {
         Ok(cred) if cred.is_connected => {
@@ -39,6 +55,12 @@ pub(crate) async fn get_anthropic_credential(state: &AppState) -> Option<(String
                 "Using Vault credential for Anthropic (plan: {:?})",
                 cred.plan_tier
             );
+            guard.cache_put(
+                ANTHROPIC_VAULT_NAMESPACE,
+                ANTHROPIC_VAULT_SERVICE,
+                &json!({ "is_connected": true, "plan_tier": format!("{:?}", cred.plan_tier) }),
+            );
+            guard.record_source("anthropic", CredentialSource::Vault);
             return Some(("__vault_managed__".to_string(), true));
         }
         Ok(cred) => {
@@ -46,39 +68,68 @@ pub(crate) async fn get_anthropic_credential(state: &AppState) -> Option<(String
                 "Vault has Anthropic credential but not connected (is_connected={}), falling back",
                 cred.is_connected
             );
+            guard.cache_forget(ANTHROPIC_VAULT_NAMESPACE, ANTHROPIC_VAULT_SERVICE);
         }
         Err(crate::ai_gateway::vault_bridge::VaultError::NotFound) => {
             tracing::debug!("Vault has no Anthropic credential, falling back to env var");
+            guard.cache_forget(ANTHROPIC_VAULT_NAMESPACE, ANTHROPIC_VAULT_SERVICE);
         }
         Err(crate::ai_gateway::vault_bridge::VaultError::AnomalyDetected(msg)) => {
             tracing::error!(
                 "ANOMALY DETECTED from Vault during credential resolution: {}",
                 msg
             );
+            guard.lock(LockoutTrigger::Anomaly, msg).await;
+            guard.record_source("anthropic", CredentialSource::Locked);
             return None;
         }
         Err(e) => {
-            tracing::debug!("Vault unavailable ({}), falling back to env var", e);
+            // 2. Vault unreachable — keep the recently confirmed Vault session
+            if policy.allows_cache()
+                && guard
+                    .cache_get(ANTHROPIC_VAULT_NAMESPACE, ANTHROPIC_VAULT_SERVICE)
+                    .is_some()
+            {
+                tracing::warn!(
+                    "Vault unavailable ({}), using the cached Vault session for Anthropic",
+                    e
+                );
+                guard.record_source("anthropic", CredentialSource::VaultCache);
+                return Some(("__vault_managed__".to_string(), true));
+            }
+            tracing::warn!("Vault unavailable ({}) and no cached Vault session", e);
         }
     }
 
-    // 2. Try runtime state (hot-loaded API key)
+    if !policy.allows_env() {
+        tracing::warn!(
+            "No Vault credential for Anthropic — CREDENTIAL_POLICY={} forbids API key fallback",
+            policy
+        );
+        guard.record_source("anthropic", CredentialSource::Unavailable);
+        return None;
+    }
+
+    // 3. Try runtime state (hot-loaded API key)
     {
         let rt = state.runtime.read().await;
         if let Some(key) = rt.api_keys.get("ANTHROPIC_API_KEY")
             && !key.is_empty()
         {
             tracing::info!("Falling back to runtime API key for Anthropic");
+            guard.record_source("anthropic", CredentialSource::RuntimeKey);
             return Some((key.clone(), false));
         }
     }
-    // 3. Last resort: env var
+    // 4. Last resort: env var
     let key = std::env::var("ANTHROPIC_API_KEY").unwrap_or_default();
     if !key.is_empty() {
         tracing::info!("Falling back to ANTHROPIC_API_KEY env var for Anthropic");
+        guard.record_source("anthropic", CredentialSource::EnvKey);
         return Some((key, false));
     }
 
+    guard.record_source("anthropic", CredentialSource::Unavailable);
     None
 }
 
@@ -122,6 +173,9 @@ pub(crate) async fn send_to_anthropic_once(
     timeout_secs: u64,
 ) -> Result<reqwest::Response, (StatusCode, Json<Value>)> {
     let (credential, is_oauth) = get_anthropic_credential(state).await.ok_or_else(|| {
+        if let Err(lockout) = state.ai_gateway.vault_guard.check() {
+            return lockout_error(&lockout);
+        }
         (
             StatusCode::UNAUTHORIZED,
             Json(json!({ "error": "No Anthropic API key configured" })),
@@ -218,6 +272,11 @@ async fn send_to_anthropic_via_vault(
         }
         Err(crate::ai_gateway::vault_bridge::VaultError::AnomalyDetected(msg)) => {
             tracing::error!("ANOMALY DETECTED during Vault delegate: {}", msg);
+            state
+                .ai_gateway
+                .vault_guard
+                .lock(LockoutTrigger::Anomaly, msg)
+                .await;
             Err((
                 StatusCode::SERVICE_UNAVAILABLE,
                 Json(json!({ "error": "Security anomaly detected — operations halted" })),
@@ -253,8 +312,12 @@ async fn send_to_anthropic_via_vault(
     }
 }
 
-/// Get Anthropic API key only (skip OAuth/Vault). Used as fallback credential.
+/// Get Anthropic API key only (skip OAuth/Vault). Used as fallback credential,
+/// so `None` unless `CREDENTIAL_POLICY` is `env_allowed`.
 pub(crate) async fn get_anthropic_api_key_only(state: &AppState) -> Option<(String, bool)> {
+    if !state.ai_gateway.vault_guard.policy().allows_env() {
+        return None;
+    }
     {
         let rt = state.runtime.read().await;
         if let Some(key) = rt.api_keys.get("ANTHROPIC_API_KEY")
@@ -269,15 +332,34 @@ pub(crate) async fn get_anthropic_api_key_only(state: &AppState) -> Option<(Stri
         .map(|k| (k, false))
 }
 
+/// 503 body of a call refused by the Vault lockout.
+pub(crate) fn lockout_error(
+    lockout: &crate::ai_gateway::vault_guard::Lockout,
+) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": "vault_lockout",
+            "message": lockout.to_string(),
+            "action_required": "An operator must clear the lockout: POST /api/vault/lockout/clear",
+        })),
+    )
+}
+
 /// Send to Anthropic with circuit breaker + one retry on 429/5xx.
 ///
-/// Gates on the circuit breaker before any request. On transient failure
-/// (429 or 5xx), waits 2 s and retries once before returning the result.
+/// Gates on the Vault lockout and the circuit breaker before any request. On
+/// transient failure (429 or 5xx), waits 2 s and retries once before
+/// returning the result.
 pub(crate) async fn send_to_anthropic(
     state: &AppState,
     body: &Value,
     timeout_secs: u64,
 ) -> Result<reqwest::Response, (StatusCode, Json<Value>)> {
+    if let Err(lockout) = state.ai_gateway.vault_guard.check() {
+        return Err(lockout_error(&lockout));
+    }
+
     // Circuit breaker gate
     if let Err(msg) = state.circuit_breaker.check().await {
         return Err((
@@ -0,0 +1,815 @@
+//! Session branching — fork a session at any message, edit a user turn into a
+//! sibling branch, and switch between branches.
+//!
+//! Messages form a tree per session (`ch_messages.parent_message_id`). The
+//! branch that is displayed and sent to the model ends at
+//! `ch_sessions.active_leaf_message_id`; plain inserts append to it (DB
+//! trigger), so the existing message writers need no branch awareness.
+//!
+//! Endpoints:
+//! - `POST /api/sessions/{id}/fork`                       — copy a branch into a new session
+//! - `POST /api/sessions/{id}/messages/{message_id}/edit` — edited user turn as a sibling branch
+//! - `POST /api/sessions/{id}/checkout`                   — switch the active branch
+//! - `GET  /api/sessions/{id}/branches`                   — branch heads and forks of a session
+//!
+//! Edit-and-resend over WebSocket uses `Execute { edit_message_id }`, which
+//! calls [`branch_for_edit`] before loading history and stores the prompt as
+//! the new sibling; if the run stores nothing, the previous branch is checked
+//! out again. A client that stored the edit over REST first and then sends
+//! `Execute` with the same prompt gets that turn answered — the WS run never
+//! stores a second copy of an unanswered head turn ([`pending_user_turn`]).
+
+use std::collections::HashMap;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::models::*;
+use crate::state::AppState;
+
+use super::MAX_MESSAGE_LENGTH;
+
+/// Recursive CTE `path(id, parent_message_id, depth)` — the branch ending at
+/// message `$2` of session `$1`, depth 0 being the head.
+pub(crate) const BRANCH_PATH_CTE: &str = "WITH RECURSIVE path AS (\
+        SELECT id, parent_message_id, 0 AS depth FROM ch_messages \
+        WHERE id = $2 AND session_id = $1 \
+        UNION ALL \
+        SELECT m.id, m.parent_message_id, p.depth + 1 FROM ch_messages m \
+        JOIN path p ON m.id = p.parent_message_id \
+        WHERE m.session_id = $1\
+    ) ";
+
+// ── Request types ───────────────────────────────────────────────────────────
+
+/// Request body for `POST /api/sessions/{id}/fork`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ForkSessionRequest {
+    /// Last message (inclusive) copied into the fork.
+    pub message_id: String,
+    /// Title of the new session (default: "<parent title> (fork)").
+    pub title: Option<String>,
+}
+
+/// Request body for `POST /api/sessions/{id}/messages/{message_id}/edit`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct EditMessageRequest {
+    pub content: String,
+}
+
+/// Request body for `POST /api/sessions/{id}/checkout`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CheckoutBranchRequest {
+    /// Any message of the branch — the newest leaf below it becomes the head.
+    pub message_id: String,
+}
+
+/// A branch head (message without children) in a session.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BranchHead {
+    pub message_id: String,
+    pub role: String,
+    pub preview: String,
+    pub timestamp: String,
+    pub active: bool,
+}
+
+// ── Shared helpers ──────────────────────────────────────────────────────────
+
+/// Head of the branch currently checked out in a session.
+pub(crate) async fn active_head(
+    db: &sqlx::PgPool,
+    session_id: uuid::Uuid,
+) -> Result<Option<uuid::Uuid>, sqlx::Error> {
+    sqlx::query_scalar::<_, Option<uuid::Uuid>>(
+        "SELECT active_leaf_message_id FROM ch_sessions WHERE id = $1",
+    )
+    .bind(session_id)
+    .fetch_optional(db)
+    .await
+    .map(Option::flatten)
+}
+
+/// A user turn being edited: [`branch_for_edit`] checked out the turn's
+/// parent; `previous_head` is the branch that was active before.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EditCheckout {
+    pub(crate) session_id: uuid::Uuid,
+    pub(crate) parent: Option<uuid::Uuid>,
+    pub(crate) previous_head: Option<uuid::Uuid>,
+}
+
+impl EditCheckout {
+    /// Check the previous branch out again if nothing was stored under the
+    /// edited turn's parent (the run failed, timed out or was cancelled).
+    /// Returns whether the checkout was rolled back.
+    pub(crate) async fn restore_if_unused(&self, db: &sqlx::PgPool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "SELECT ch_checkout_message(id, $3) FROM ch_sessions \
+             WHERE id = $1 AND active_leaf_message_id IS NOT DISTINCT FROM $2",
+        )
+        .bind(self.session_id)
+        .bind(self.parent)
+        .bind(self.previous_head)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Prepare an edit of a user turn: check out the turn's parent so the next
+/// message stored in the session becomes a sibling of the edited one.
+///
+/// Returns `Ok(None)` when `message_id` is not a user message of the session.
+pub(crate) async fn branch_for_edit(
+    conn: &mut sqlx::PgConnection,
+    session_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+) -> Result<Option<EditCheckout>, sqlx::Error> {
+    let row: Option<(Option<uuid::Uuid>, Option<uuid::Uuid>)> = sqlx::query_as(
+        "SELECT m.parent_message_id, s.active_leaf_message_id \
+         FROM ch_messages m JOIN ch_sessions s ON s.id = m.session_id \
+         WHERE m.id = $1 AND m.session_id = $2 AND m.role = 'user'",
+    )
+    .bind(message_id)
+    .bind(session_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some((parent, previous_head)) = row else {
+        return Ok(None);
+    };
+    sqlx::query("SELECT ch_checkout_message($1, $2)")
+        .bind(session_id)
+        .bind(parent)
+        .execute(&mut *conn)
+        .await?;
+    Ok(Some(EditCheckout {
+        session_id,
+        parent,
+        previous_head,
+    }))
+}
+
+/// Store an edited user turn as a sibling of `message_id` and check it out,
+/// in one transaction. Returns `Ok(None)` when `message_id` is not a user
+/// message of the session.
+pub(crate) async fn store_edit(
+    db: &sqlx::PgPool,
+    session_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+    content: &str,
+) -> Result<Option<MessageRow>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    if branch_for_edit(&mut tx, session_id, message_id)
+        .await?
+        .is_none()
+    {
+        return Ok(None);
+    }
+    let row = sqlx::query_as::<_, MessageRow>(
+        "INSERT INTO ch_messages (session_id, role, content) \
+         VALUES ($1, 'user', $2) \
+         RETURNING id, session_id, role, content, model, agent, created_at, parent_message_id",
+    )
+    .bind(session_id)
+    .bind(content)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query("UPDATE ch_sessions SET updated_at = NOW() WHERE id = $1")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(Some(row))
+}
+
+/// The head of the active branch when it is a user turn with exactly this
+/// content and no reply yet — e.g. an edit stored over REST that the next WS
+/// run answers instead of storing the prompt again.
+pub(crate) async fn pending_user_turn(
+    db: &sqlx::PgPool,
+    session_id: uuid::Uuid,
+    content: &str,
+) -> Result<Option<uuid::Uuid>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT m.id FROM ch_sessions s \
+         JOIN ch_messages m ON m.id = s.active_leaf_message_id \
+         WHERE s.id = $1 AND m.role = 'user' AND m.content = $2",
+    )
+    .bind(session_id)
+    .bind(content)
+    .fetch_optional(db)
+    .await
+}
+
+/// Newest leaf below `message_id` — following the newest child at every
+/// level, so picking an older sibling shows that branch's latest continuation.
+pub(crate) async fn newest_leaf_below(
+    db: &sqlx::PgPool,
+    session_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+) -> Result<Option<uuid::Uuid>, sqlx::Error> {
+    sqlx::query_scalar(
+        "WITH RECURSIVE down AS (\
+            SELECT id, 0 AS depth FROM ch_messages WHERE id = $1 AND session_id = $2 \
+            UNION ALL \
+            SELECT c.id, d.depth + 1 FROM down d \
+            JOIN LATERAL (\
+                SELECT id FROM ch_messages WHERE parent_message_id = d.id \
+                ORDER BY created_at DESC, id DESC LIMIT 1\
+            ) c ON TRUE\
+        ) SELECT id FROM down ORDER BY depth DESC LIMIT 1",
+    )
+    .bind(message_id)
+    .bind(session_id)
+    .fetch_optional(db)
+    .await
+}
+
+/// Alternative versions of the given messages (same parent), for messages
+/// that have at least one sibling. Values are ordered oldest first.
+pub(crate) async fn load_siblings(
+    db: &sqlx::PgPool,
+    session_id: uuid::Uuid,
+    message_ids: &[uuid::Uuid],
+) -> HashMap<uuid::Uuid, Vec<String>> {
+    if message_ids.is_empty() {
+        return HashMap::new();
+    }
+    sqlx::query_as::<_, (uuid::Uuid, Vec<uuid::Uuid>)>(
+        "SELECT m.id, ARRAY_AGG(s.id ORDER BY s.created_at, s.id) \
+         FROM ch_messages m \
+         JOIN ch_messages s ON s.session_id = m.session_id \
+             AND s.parent_message_id IS NOT DISTINCT FROM m.parent_message_id \
+         WHERE m.session_id = $1 AND m.id = ANY($2) \
+         GROUP BY m.id \
+         HAVING COUNT(*) > 1",
+    )
+    .bind(session_id)
+    .bind(message_ids)
+    .fetch_all(db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(id, siblings)| (id, siblings.iter().map(|s| s.to_string()).collect()))
+    .collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Fork session
+// ═══════════════════════════════════════════════════════════════════════
+
+#[utoipa::path(post, path = "/api/sessions/{id}/fork", tag = "sessions",
+    params(("id" = String, Path, description = "Session UUID")),
+    request_body = ForkSessionRequest,
+    responses(
+        (status = 201, description = "Forked session created"),
+        (status = 404, description = "Session or message not found"),
+    ))]
+pub async fn fork_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ForkSessionRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let session_id: uuid::Uuid = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let message_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let parent = sqlx::query_as::<_, SessionRow>(
+        "SELECT id, title, created_at, updated_at, working_directory FROM ch_sessions WHERE id = $1",
+    )
+    .bind(session_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to get session for fork: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Root first, so every copy is appended to the previous one.
+    let path = sqlx::query_as::<_, MessageRow>(&format!(
+        "{BRANCH_PATH_CTE}\
+         SELECT m.id, m.session_id, m.role, m.content, m.model, m.agent, m.created_at, \
+             m.parent_message_id \
+         FROM path p JOIN ch_messages m ON m.id = p.id \
+         ORDER BY p.depth DESC"
+    ))
+    .bind(session_id)
+    .bind(message_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load branch for fork: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if path.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let title = req
+        .title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| format!("{} (fork)", parent.title));
+
+    let db_err = |e: sqlx::Error| {
+        tracing::error!("Failed to fork session: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+
+    let new_id: uuid::Uuid = sqlx::query_scalar(
+        "INSERT INTO ch_sessions (title, working_directory, parent_session_id, forked_from_message_id) \
+         VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(&title)
+    .bind(&parent.working_directory)
+    .bind(session_id)
+    .bind(message_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_err)?;
+
+    for msg in &path {
+        let copy_id: uuid::Uuid = sqlx::query_scalar(
+            "INSERT INTO ch_messages (session_id, role, content, model, agent, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+        )
+        .bind(new_id)
+        .bind(&msg.role)
+        .bind(&msg.content)
+        .bind(&msg.model)
+        .bind(&msg.agent)
+        .bind(msg.created_at)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_err)?;
+
+        sqlx::query(
+            "INSERT INTO ch_tool_interactions \
+             (message_id, tool_use_id, tool_name, tool_input, result, is_error, executed_at, \
+              duration_ms, error_class) \
+             SELECT $1, tool_use_id, tool_name, tool_input, result, is_error, executed_at, \
+                 duration_ms, error_class \
+             FROM ch_tool_interactions WHERE message_id = $2",
+        )
+        .bind(copy_id)
+        .bind(msg.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    }
+
+    sqlx::query(
+        "INSERT INTO ch_session_tags (session_id, tag) \
+         SELECT $1, tag FROM ch_session_tags WHERE session_id = $2 ON CONFLICT DO NOTHING",
+    )
+    .bind(new_id)
+    .bind(session_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+
+    tx.commit().await.map_err(db_err)?;
+
+    crate::audit::log_audit(
+        &state.db,
+        "fork_session",
+        json!({
+            "session_id": session_id,
+            "fork_id": new_id,
+            "message_id": message_id,
+            "messages": path.len(),
+        }),
+        None,
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "id": new_id.to_string(),
+            "title": title,
+            "parent_session_id": session_id.to_string(),
+            "forked_from_message_id": message_id.to_string(),
+            "message_count": path.len(),
+        })),
+    ))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Edit a user turn (sibling branch)
+// ═══════════════════════════════════════════════════════════════════════
+
+#[utoipa::path(post, path = "/api/sessions/{id}/messages/{message_id}/edit", tag = "sessions",
+    params(
+        ("id" = String, Path, description = "Session UUID"),
+        ("message_id" = String, Path, description = "User message to edit"),
+    ),
+    request_body = EditMessageRequest,
+    responses(
+        (status = 201, description = "Edited message stored on a new branch"),
+        (status = 404, description = "Session or user message not found"),
+    ))]
+pub async fn edit_message(
+    State(state): State<AppState>,
+    Path((id, message_id)): Path<(String, String)>,
+    Json(req): Json<EditMessageRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    if req.content.trim().is_empty() || req.content.len() > MAX_MESSAGE_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let session_id: uuid::Uuid = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let message_id: uuid::Uuid = message_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let row = store_edit(&state.db, session_id, message_id, &req.content)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to store edited message: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    crate::session_index::spawn_index_messages(
+        &state,
+        vec![(row.id, session_id, row.role.clone(), row.content.clone())],
+    );
+
+    let mut siblings = load_siblings(&state.db, session_id, &[row.id]).await;
+    let entry = HistoryEntry {
+        id: row.id.to_string(),
+        role: row.role,
+        content: row.content,
+        model: row.model,
+        agent: row.agent,
+        timestamp: row.created_at.to_rfc3339(),
+        tool_interactions: None,
+        thinking: row.thinking,
+        parent_id: row.parent_message_id.map(|p| p.to_string()),
+        sibling_ids: siblings.remove(&row.id),
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::to_value(entry).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?),
+    ))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Checkout / list branches
+// ═══════════════════════════════════════════════════════════════════════
+
+#[utoipa::path(post, path = "/api/sessions/{id}/checkout", tag = "sessions",
+    params(("id" = String, Path, description = "Session UUID")),
+    request_body = CheckoutBranchRequest,
+    responses(
+        (status = 200, description = "Active branch switched"),
+        (status = 404, description = "Message not found in session"),
+    ))]
+pub async fn checkout_branch(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<CheckoutBranchRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let session_id: uuid::Uuid = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let message_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let head = newest_leaf_below(&state.db, session_id, message_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to resolve branch head: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let head = head.ok_or(StatusCode::NOT_FOUND)?;
+
+    sqlx::query("SELECT ch_checkout_message($1, $2)")
+        .bind(session_id)
+        .bind(head)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to checkout branch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "session_id": id,
+        "active_leaf_message_id": head.to_string(),
+    })))
+}
+
+#[utoipa::path(get, path = "/api/sessions/{id}/branches", tag = "sessions",
+    params(("id" = String, Path, description = "Session UUID")),
+    responses((status = 200, description = "Branch heads and forks of the session")))]
+pub async fn list_branches(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    let session_id: uuid::Uuid = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let session = sqlx::query_as::<_, SessionRow>(
+        "SELECT id, title, created_at, updated_at, working_directory, \
+             parent_session_id, forked_from_message_id, active_leaf_message_id \
+         FROM ch_sessions WHERE id = $1",
+    )
+    .bind(session_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to get session: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let heads: Vec<(
+        uuid::Uuid,
+        String,
+        String,
+        chrono::DateTime<chrono::Utc>,
+        bool,
+    )> = sqlx::query_as(
+        "SELECT m.id, m.role, LEFT(m.content, 200), m.created_at, m.on_active_path \
+             FROM ch_messages m \
+             WHERE m.session_id = $1 \
+               AND NOT EXISTS (SELECT 1 FROM ch_messages c WHERE c.parent_message_id = m.id) \
+             ORDER BY m.created_at DESC",
+    )
+    .bind(session_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list branch heads: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let forks: Vec<(
+        uuid::Uuid,
+        String,
+        Option<uuid::Uuid>,
+        chrono::DateTime<chrono::Utc>,
+    )> = sqlx::query_as(
+        "SELECT id, title, forked_from_message_id, created_at FROM ch_sessions \
+             WHERE parent_session_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(session_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list forks: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let branches: Vec<BranchHead> = heads
+        .into_iter()
+        .map(
+            |(message_id, role, preview, created_at, active)| BranchHead {
+                message_id: message_id.to_string(),
+                role,
+                preview,
+                timestamp: created_at.to_rfc3339(),
+                active,
+            },
+        )
+        .collect();
+
+    Ok(Json(json!({
+        "session_id": id,
+        "active_leaf_message_id": session.active_leaf_message_id.map(|m| m.to_string()),
+        "parent_session_id": session.parent_session_id.map(|s| s.to_string()),
+        "forked_from_message_id": session.forked_from_message_id.map(|m| m.to_string()),
+        "branches": branches,
+        "forks": forks.into_iter().map(|(fid, title, from, created_at)| json!({
+            "id": fid.to_string(),
+            "title": title,
+            "forked_from_message_id": from.map(|m| m.to_string()),
+            "created_at": created_at.to_rfc3339(),
+        })).collect::<Vec<_>>(),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Skipped (None) unless DATABASE_URL points at a disposable database.
+    async fn test_db() -> Option<sqlx::PgPool> {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        Some(pool)
+    }
+
+    /// Session with a linear `user, assistant, user, assistant` history.
+    async fn linear_session(db: &sqlx::PgPool) -> (uuid::Uuid, Vec<uuid::Uuid>) {
+        let session_id: uuid::Uuid =
+            sqlx::query_scalar("INSERT INTO ch_sessions (title) VALUES ('branching') RETURNING id")
+                .fetch_one(db)
+                .await
+                .unwrap();
+        let mut ids = Vec::new();
+        for (role, content) in [
+            ("user", "first question"),
+            ("assistant", "first answer"),
+            ("user", "second question"),
+            ("assistant", "second answer"),
+        ] {
+            ids.push(append(db, session_id, role, content).await);
+        }
+        (session_id, ids)
+    }
+
+    async fn append(
+        db: &sqlx::PgPool,
+        session_id: uuid::Uuid,
+        role: &str,
+        content: &str,
+    ) -> uuid::Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO ch_messages (session_id, role, content) VALUES ($1, $2, $3) RETURNING id",
+        )
+        .bind(session_id)
+        .bind(role)
+        .bind(content)
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn on_active_path(db: &sqlx::PgPool, message_id: uuid::Uuid) -> bool {
+        sqlx::query_scalar("SELECT on_active_path FROM ch_messages WHERE id = $1")
+            .bind(message_id)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    async fn delete_session(db: &sqlx::PgPool, session_id: uuid::Uuid) {
+        sqlx::query("DELETE FROM ch_sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn edit_creates_active_sibling_branch() {
+        let Some(db) = test_db().await else {
+            return;
+        };
+        let (session_id, ids) = linear_session(&db).await;
+
+        let edited = store_edit(&db, session_id, ids[2], "second question, rephrased")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(edited.parent_message_id, Some(ids[1]));
+        assert_eq!(active_head(&db, session_id).await.unwrap(), Some(edited.id));
+        assert!(!on_active_path(&db, ids[2]).await);
+        assert!(!on_active_path(&db, ids[3]).await);
+        assert!(on_active_path(&db, ids[1]).await);
+
+        // Both versions list each other, oldest first; single turns list nothing.
+        let siblings = load_siblings(&db, session_id, &[ids[1], ids[2], edited.id]).await;
+        let expected = vec![ids[2].to_string(), edited.id.to_string()];
+        assert_eq!(siblings.get(&ids[2]), Some(&expected));
+        assert_eq!(siblings.get(&edited.id), Some(&expected));
+        assert!(!siblings.contains_key(&ids[1]));
+
+        // Only user turns can be edited.
+        assert!(
+            store_edit(&db, session_id, ids[3], "not a user turn")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        delete_session(&db, session_id).await;
+    }
+
+    #[tokio::test]
+    async fn checkout_follows_the_newest_leaf() {
+        let Some(db) = test_db().await else {
+            return;
+        };
+        let (session_id, ids) = linear_session(&db).await;
+        let edited = store_edit(&db, session_id, ids[2], "another take")
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Picking the original turn shows its continuation.
+        let head = newest_leaf_below(&db, session_id, ids[2])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(head, ids[3]);
+        sqlx::query("SELECT ch_checkout_message($1, $2)")
+            .bind(session_id)
+            .bind(head)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(active_head(&db, session_id).await.unwrap(), Some(ids[3]));
+        assert!(on_active_path(&db, ids[2]).await);
+        assert!(!on_active_path(&db, edited.id).await);
+
+        // From a shared ancestor, the newest branch wins.
+        assert_eq!(
+            newest_leaf_below(&db, session_id, ids[0]).await.unwrap(),
+            Some(edited.id)
+        );
+        // Messages of other sessions are not found.
+        assert_eq!(
+            newest_leaf_below(&db, uuid::Uuid::new_v4(), ids[0])
+                .await
+                .unwrap(),
+            None
+        );
+        delete_session(&db, session_id).await;
+    }
+
+    #[tokio::test]
+    async fn unused_edit_checkout_is_rolled_back() {
+        let Some(db) = test_db().await else {
+            return;
+        };
+        let (session_id, ids) = linear_session(&db).await;
+
+        let mut conn = db.acquire().await.unwrap();
+        let checkout = branch_for_edit(&mut conn, session_id, ids[2])
+            .await
+            .unwrap()
+            .unwrap();
+        drop(conn);
+        assert_eq!(active_head(&db, session_id).await.unwrap(), Some(ids[1]));
+
+        // The run stored nothing: the original branch comes back.
+        assert!(checkout.restore_if_unused(&db).await.unwrap());
+        assert_eq!(active_head(&db, session_id).await.unwrap(), Some(ids[3]));
+        assert!(on_active_path(&db, ids[2]).await);
+
+        // The run stored the new turn: the new branch stays.
+        let mut conn = db.acquire().await.unwrap();
+        let checkout = branch_for_edit(&mut conn, session_id, ids[2])
+            .await
+            .unwrap()
+            .unwrap();
+        drop(conn);
+        let new_turn = append(&db, session_id, "user", "edited over WebSocket").await;
+        assert!(!checkout.restore_if_unused(&db).await.unwrap());
+        assert_eq!(active_head(&db, session_id).await.unwrap(), Some(new_turn));
+        delete_session(&db, session_id).await;
+    }
+
+    #[tokio::test]
+    async fn stored_edit_is_a_pending_turn_until_answered() {
+        let Some(db) = test_db().await else {
+            return;
+        };
+        let (session_id, ids) = linear_session(&db).await;
+        assert_eq!(
+            pending_user_turn(&db, session_id, "second answer")
+                .await
+                .unwrap(),
+            None
+        );
+
+        let edited = store_edit(&db, session_id, ids[2], "resend me")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pending_user_turn(&db, session_id, "resend me")
+                .await
+                .unwrap(),
+            Some(edited.id)
+        );
+        assert_eq!(
+            pending_user_turn(&db, session_id, "something else")
+                .await
+                .unwrap(),
+            None
+        );
+
+        append(&db, session_id, "assistant", "answer to the edit").await;
+        assert_eq!(
+            pending_user_turn(&db, session_id, "resend me")
+                .await
+                .unwrap(),
+            None
+        );
+        delete_session(&db, session_id).await;
+    }
+}
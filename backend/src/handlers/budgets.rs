@@ -0,0 +1,363 @@
+//! Spending budgets — definitions and current spend versus limits.
+//!
+//! Enforcement lives in [`crate::budgets`]; this module manages the
+//! `ch_budgets` rows and reports their state.
+//!
+//! Endpoints:
+//! - `GET    /api/budgets`           — list budgets
+//! - `POST   /api/budgets`           — create a budget
+//! - `PUT    /api/budgets/{id}`      — replace a budget definition
+//! - `DELETE /api/budgets/{id}`      — delete a budget
+//! - `GET    /api/analytics/budgets` — current spend vs limits of enabled budgets
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::budgets::{BUDGET_COLUMNS, BudgetRow};
+use crate::state::AppState;
+
+// ── Request / response types ────────────────────────────────────────────────
+
+/// Request body for `POST /api/budgets` and `PUT /api/budgets/{id}`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BudgetRequest {
+    pub name: String,
+    /// `user`, `session` or `agent`.
+    pub scope: String,
+    /// User email / session id / agent name; omit to apply to every user,
+    /// session or agent individually.
+    pub scope_id: Option<String>,
+    /// `daily` or `monthly`.
+    pub period: String,
+    /// `usd` (default) or `tokens`.
+    pub unit: Option<String>,
+    /// Crossing it sends a `budget_warning` to the client.
+    pub soft_limit: Option<f64>,
+    /// Crossing it stops execution (`BUDGET_EXCEEDED`).
+    pub hard_limit: Option<f64>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema, sqlx::FromRow)]
+pub struct Budget {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub scope: String,
+    pub scope_id: Option<String>,
+    pub period: String,
+    pub unit: String,
+    pub soft_limit: Option<f64>,
+    pub hard_limit: Option<f64>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Current spend of an enabled budget.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BudgetStatus {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub scope: String,
+    pub period: String,
+    pub unit: String,
+    /// User / session / agent measured — the top spender when the budget
+    /// applies to every user, session or agent.
+    pub subject: Option<String>,
+    pub spent: f64,
+    pub soft_limit: Option<f64>,
+    pub hard_limit: Option<f64>,
+    /// Spend as a share of the hard limit (or the soft limit without one).
+    pub used_ratio: Option<f64>,
+    /// `ok`, `warning` or `exceeded`.
+    pub status: &'static str,
+}
+
+const SELECT_BUDGET: &str = "SELECT id, name, scope, scope_id, period, unit, soft_limit, hard_limit, \
+     enabled, created_at, updated_at FROM ch_budgets";
+
+// ── Helpers ─────────────────────────────────────────────────────────────────
+
+fn bad_request(message: &str) -> (StatusCode, Json<Value>) {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message })))
+}
+
+fn db_error(action: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    // The only unique constraint is the budget name.
+    if e.as_database_error()
+        .is_some_and(|db_err| db_err.code().as_deref() == Some("23505"))
+    {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": "A budget with this name already exists" })),
+        );
+    }
+    tracing::error!("Failed to {} budget: {}", action, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("Failed to {} budget", action) })),
+    )
+}
+
+/// Validated and normalised copy of a request.
+fn validate(req: &BudgetRequest) -> Result<BudgetRequest, (StatusCode, Json<Value>)> {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(bad_request("name must be 1-100 characters"));
+    }
+    if !matches!(req.scope.as_str(), "user" | "session" | "agent") {
+        return Err(bad_request("scope must be user, session or agent"));
+    }
+    let scope_id = req
+        .scope_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    // Session ids are stored in canonical form to match `session_id::text`.
+    let scope_id = match (req.scope.as_str(), scope_id) {
+        ("user", Some(email)) if !email.contains('@') => {
+            return Err(bad_request("scope_id must be a user's email"));
+        }
+        ("session", Some(id)) => match id.parse::<uuid::Uuid>() {
+            Ok(id) => Some(id.to_string()),
+            Err(_) => return Err(bad_request("scope_id must be a session id")),
+        },
+        (_, scope_id) => scope_id,
+    };
+    if !matches!(req.period.as_str(), "daily" | "monthly") {
+        return Err(bad_request("period must be daily or monthly"));
+    }
+    let unit = req.unit.clone().unwrap_or_else(|| "usd".to_string());
+    if !matches!(unit.as_str(), "usd" | "tokens") {
+        return Err(bad_request("unit must be usd or tokens"));
+    }
+    let limits = [req.soft_limit, req.hard_limit];
+    if limits.iter().all(Option::is_none) {
+        return Err(bad_request("set soft_limit, hard_limit or both"));
+    }
+    if limits.iter().flatten().any(|l| !l.is_finite() || *l < 0.0) {
+        return Err(bad_request("limits must be non-negative numbers"));
+    }
+    if let (Some(soft), Some(hard)) = (req.soft_limit, req.hard_limit)
+        && soft > hard
+    {
+        return Err(bad_request("soft_limit must not exceed hard_limit"));
+    }
+
+    Ok(BudgetRequest {
+        name: name.to_string(),
+        scope: req.scope.clone(),
+        scope_id,
+        period: req.period.clone(),
+        unit: Some(unit),
+        soft_limit: req.soft_limit,
+        hard_limit: req.hard_limit,
+        enabled: req.enabled,
+    })
+}
+
+// ── Handlers ────────────────────────────────────────────────────────────────
+
+/// GET /api/budgets
+#[utoipa::path(get, path = "/api/budgets", tag = "budgets",
+    responses((status = 200, description = "Budgets", body = Vec<Budget>)))]
+pub async fn list_budgets(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let budgets: Vec<Budget> = sqlx::query_as(&format!("{SELECT_BUDGET} ORDER BY name"))
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| db_error("list", e))?;
+
+    Ok(Json(json!({ "budgets": budgets })))
+}
+
+/// POST /api/budgets
+#[utoipa::path(post, path = "/api/budgets", tag = "budgets",
+    request_body = BudgetRequest,
+    responses(
+        (status = 201, description = "Budget created", body = Budget),
+        (status = 400, description = "Invalid budget"),
+        (status = 409, description = "Name already taken"),
+    ))]
+pub async fn create_budget(
+    State(state): State<AppState>,
+    Json(req): Json<BudgetRequest>,
+) -> Result<(StatusCode, Json<Budget>), (StatusCode, Json<Value>)> {
+    let req = validate(&req)?;
+
+    let budget: Budget = sqlx::query_as(
+        "INSERT INTO ch_budgets \
+         (name, scope, scope_id, period, unit, soft_limit, hard_limit, enabled) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+         RETURNING id, name, scope, scope_id, period, unit, soft_limit, hard_limit, \
+                   enabled, created_at, updated_at",
+    )
+    .bind(&req.name)
+    .bind(&req.scope)
+    .bind(&req.scope_id)
+    .bind(&req.period)
+    .bind(&req.unit)
+    .bind(req.soft_limit)
+    .bind(req.hard_limit)
+    .bind(req.enabled.unwrap_or(true))
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| db_error("create", e))?;
+
+    crate::audit::log_audit(
+        &state.db,
+        "create_budget",
+        json!({ "id": budget.id, "name": budget.name, "scope": budget.scope }),
+        None,
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(budget)))
+}
+
+/// PUT /api/budgets/{id}
+#[utoipa::path(put, path = "/api/budgets/{id}", tag = "budgets",
+    params(("id" = String, Path, description = "Budget id")),
+    request_body = BudgetRequest,
+    responses(
+        (status = 200, description = "Budget updated", body = Budget),
+        (status = 400, description = "Invalid budget"),
+        (status = 404, description = "Budget not found"),
+    ))]
+pub async fn update_budget(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+    Json(req): Json<BudgetRequest>,
+) -> Result<Json<Budget>, (StatusCode, Json<Value>)> {
+    let req = validate(&req)?;
+
+    let budget: Option<Budget> = sqlx::query_as(
+        "UPDATE ch_budgets SET name = $2, scope = $3, scope_id = $4, period = $5, unit = $6, \
+             soft_limit = $7, hard_limit = $8, enabled = $9, updated_at = NOW() \
+         WHERE id = $1 \
+         RETURNING id, name, scope, scope_id, period, unit, soft_limit, hard_limit, \
+                   enabled, created_at, updated_at",
+    )
+    .bind(id)
+    .bind(&req.name)
+    .bind(&req.scope)
+    .bind(&req.scope_id)
+    .bind(&req.period)
+    .bind(&req.unit)
+    .bind(req.soft_limit)
+    .bind(req.hard_limit)
+    .bind(req.enabled.unwrap_or(true))
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("update", e))?;
+
+    let budget = budget.ok_or((
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": "Budget not found" })),
+    ))?;
+
+    crate::audit::log_audit(
+        &state.db,
+        "update_budget",
+        json!({ "id": budget.id, "name": budget.name }),
+        None,
+    )
+    .await;
+
+    Ok(Json(budget))
+}
+
+/// DELETE /api/budgets/{id}
+#[utoipa::path(delete, path = "/api/budgets/{id}", tag = "budgets",
+    params(("id" = String, Path, description = "Budget id")),
+    responses(
+        (status = 200, description = "Budget deleted"),
+        (status = 404, description = "Budget not found"),
+    ))]
+pub async fn delete_budget(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let deleted = sqlx::query("DELETE FROM ch_budgets WHERE id = $1")
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| db_error("delete", e))?;
+    if deleted.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Budget not found" })),
+        ));
+    }
+
+    crate::audit::log_audit(&state.db, "delete_budget", json!({ "id": id }), None).await;
+
+    Ok(Json(json!({ "status": "deleted", "id": id })))
+}
+
+/// GET /api/analytics/budgets
+#[utoipa::path(get, path = "/api/analytics/budgets", tag = "budgets",
+    responses((status = 200, description = "Current spend vs limits", body = Vec<BudgetStatus>)))]
+pub async fn budget_status(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let budgets: Vec<BudgetRow> = sqlx::query_as(&format!(
+        "SELECT {BUDGET_COLUMNS} FROM ch_budgets WHERE enabled ORDER BY name"
+    ))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| db_error("load", e))?;
+
+    let mut statuses = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        let subject = budget
+            .scope_id
+            .as_deref()
+            .map(|s| match budget.scope.as_str() {
+                "agent" => s.to_lowercase(),
+                _ => s.to_string(),
+            });
+        let spend = crate::budgets::spend(&state.db, &budget, subject.as_deref())
+            .await
+            .map_err(|e| db_error("measure", e))?;
+        let (spent, subject) = match spend {
+            Some(s) if budget.unit == "tokens" => (s.tokens as f64, s.subject),
+            Some(s) => ((s.usd * 10_000.0).round() / 10_000.0, s.subject),
+            None => (0.0, subject),
+        };
+
+        let status = match (budget.soft_limit, budget.hard_limit) {
+            (_, Some(hard)) if spent >= hard => "exceeded",
+            (Some(soft), _) if spent >= soft => "warning",
+            _ => "ok",
+        };
+        let used_ratio = budget
+            .hard_limit
+            .or(budget.soft_limit)
+            .filter(|l| *l > 0.0)
+            .map(|l| spent / l);
+
+        statuses.push(BudgetStatus {
+            id: budget.id,
+            name: budget.name,
+            scope: budget.scope,
+            period: budget.period,
+            unit: budget.unit,
+            subject,
+            spent,
+            soft_limit: budget.soft_limit,
+            hard_limit: budget.hard_limit,
+            used_ratio,
+            status,
+        });
+    }
+
+    Ok(Json(json!({ "budgets": statuses })))
+}
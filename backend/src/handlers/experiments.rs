@@ -0,0 +1,902 @@
+//! A/B experiments — named experiments whose arms vary model, temperature and
+//! system prompt variant, with sticky per-session assignment.
+//!
+//! While an experiment is `running`, `resolve_chat_context` assigns every
+//! request to an arm ([`assign_arm`]) and logs an exposure ([`log_exposure`]).
+//! Sessions keep their arm (`ch_experiment_assignments`); requests without a
+//! session are assigned at random by arm weight. Completed WebSocket
+//! executions link their `ch_agent_usage` row to the exposure and record tool
+//! success ([`complete_exposure`]); ratings join through the stored reply.
+//!
+//! Endpoints:
+//! - `GET    /api/experiments`              — list experiments with arms
+//! - `POST   /api/experiments`              — create an experiment
+//! - `PATCH  /api/experiments/{id}`         — start / stop an experiment
+//! - `DELETE /api/experiments/{id}`         — delete an experiment and its data
+//! - `GET    /api/experiments/{id}/results` — per-arm metrics with 95% confidence intervals
+
+use std::collections::HashSet;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use jaskier_core::sessions::HasSessionsState;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+
+/// z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+// ── Request / response types ────────────────────────────────────────────────
+
+/// One arm of a new experiment. Unset fields keep the regular behaviour.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ExperimentArmInput {
+    pub name: String,
+    /// Model id used instead of the routed / requested model.
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    /// Extra instructions appended to the system prompt.
+    pub system_prompt_variant: Option<String>,
+    /// Relative share of traffic (default 1).
+    pub weight: Option<i32>,
+}
+
+/// Request body for `POST /api/experiments`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CreateExperimentRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub arms: Vec<ExperimentArmInput>,
+    /// Start immediately (default: created as `draft`).
+    #[serde(default)]
+    pub start: bool,
+}
+
+/// Request body for `PATCH /api/experiments/{id}`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct UpdateExperimentRequest {
+    /// `draft`, `running` or `stopped`.
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExperimentArm {
+    pub id: String,
+    pub name: String,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub system_prompt_variant: Option<String>,
+    pub weight: i32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Experiment {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub status: String,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub stopped_at: Option<String>,
+    pub arms: Vec<ExperimentArm>,
+}
+
+/// Mean of a metric with a normal-approximation 95% confidence interval.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MeanEstimate {
+    pub n: i64,
+    pub mean: Option<f64>,
+    pub ci_low: Option<f64>,
+    pub ci_high: Option<f64>,
+}
+
+/// Proportion with a Wilson score 95% confidence interval.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RateEstimate {
+    pub n: i64,
+    pub successes: i64,
+    pub rate: Option<f64>,
+    pub ci_low: Option<f64>,
+    pub ci_high: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ArmResults {
+    pub arm: ExperimentArm,
+    pub exposures: i64,
+    pub sessions: i64,
+    /// Exposures that ran to completion (not failed / cancelled).
+    pub completion_rate: RateEstimate,
+    pub latency_ms: MeanEstimate,
+    pub total_tokens: MeanEstimate,
+    /// Tool calls that did not return an error.
+    pub tool_success_rate: RateEstimate,
+    pub rating: MeanEstimate,
+}
+
+// ── DB rows ─────────────────────────────────────────────────────────────────
+
+#[derive(sqlx::FromRow)]
+struct ExperimentRow {
+    id: uuid::Uuid,
+    name: String,
+    description: String,
+    status: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    started_at: Option<chrono::DateTime<chrono::Utc>>,
+    stopped_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct ArmRow {
+    id: uuid::Uuid,
+    experiment_id: uuid::Uuid,
+    name: String,
+    model: Option<String>,
+    temperature: Option<f64>,
+    system_prompt_variant: Option<String>,
+    weight: i32,
+}
+
+impl From<ArmRow> for ExperimentArm {
+    fn from(row: ArmRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            name: row.name,
+            model: row.model,
+            temperature: row.temperature,
+            system_prompt_variant: row.system_prompt_variant,
+            weight: row.weight,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ArmStatsRow {
+    #[sqlx(flatten)]
+    arm: ArmRow,
+    exposures: i64,
+    sessions: i64,
+    completed: i64,
+    tool_calls: i64,
+    tool_errors: i64,
+    latency_n: i64,
+    latency_mean: Option<f64>,
+    latency_sd: Option<f64>,
+    tokens_n: i64,
+    tokens_mean: Option<f64>,
+    tokens_sd: Option<f64>,
+    rating_n: i64,
+    rating_mean: Option<f64>,
+    rating_sd: Option<f64>,
+}
+
+const ARM_COLUMNS: &str =
+    "a.id, a.experiment_id, a.name, a.model, a.temperature, a.system_prompt_variant, a.weight";
+
+// ── Assignment & exposure logging ───────────────────────────────────────────
+
+/// Arm of the running experiment a request was assigned to.
+#[derive(Debug, Clone)]
+pub(crate) struct ArmAssignment {
+    pub experiment_id: uuid::Uuid,
+    pub experiment: String,
+    pub arm_id: uuid::Uuid,
+    pub arm: String,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub system_prompt_variant: Option<String>,
+}
+
+/// Logged exposure carried through an execution in `ChatContext`.
+#[derive(Debug, Clone)]
+pub(crate) struct ExperimentExposure {
+    pub exposure_id: i64,
+    pub experiment: String,
+    pub arm: String,
+}
+
+/// Stable bucket for a session (FNV-1a over experiment + session id), so the
+/// first assignment does not depend on process or hasher state.
+fn sticky_bucket(experiment_id: uuid::Uuid, session_id: uuid::Uuid) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in experiment_id.as_bytes().iter().chain(session_id.as_bytes()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Weighted pick: `bucket` is reduced modulo the total weight.
+fn pick_arm(arms: &[ArmRow], bucket: u64) -> Option<&ArmRow> {
+    let total: u64 = arms.iter().map(|a| a.weight.max(1) as u64).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut point = bucket % total;
+    arms.iter().find(|a| {
+        let w = a.weight.max(1) as u64;
+        if point < w {
+            true
+        } else {
+            point -= w;
+            false
+        }
+    })
+}
+
+/// The running experiment with its arms (cached in `crate::config_cache`).
+#[derive(Debug, Clone)]
+pub(crate) struct RunningExperiment {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub arms: Vec<ArmRow>,
+}
+
+pub(crate) async fn load_running(
+    db: &sqlx::PgPool,
+) -> Result<Option<RunningExperiment>, sqlx::Error> {
+    let running: Option<(uuid::Uuid, String)> =
+        sqlx::query_as("SELECT id, name FROM ch_experiments WHERE status = 'running' LIMIT 1")
+            .fetch_optional(db)
+            .await?;
+    let Some((id, name)) = running else {
+        return Ok(None);
+    };
+
+    let arms: Vec<ArmRow> = sqlx::query_as(&format!(
+        "SELECT {ARM_COLUMNS} FROM ch_experiment_arms a WHERE a.experiment_id = $1 ORDER BY a.name"
+    ))
+    .bind(id)
+    .fetch_all(db)
+    .await?;
+    Ok(Some(RunningExperiment { id, name, arms }))
+}
+
+/// Assign a request to an arm of the running experiment, if any.
+///
+/// Sessions are sticky: the first assignment is stored and reused.
+pub(crate) async fn assign_arm(
+    state: &AppState,
+    session_id: Option<uuid::Uuid>,
+) -> Option<ArmAssignment> {
+    let snapshot = crate::config_cache::snapshot(state).await;
+    let running = snapshot.experiment.as_ref()?;
+    let (experiment_id, experiment, arms) = (running.id, running.name.clone(), &running.arms);
+
+    let arm = match session_id {
+        Some(sid) => {
+            let candidate = pick_arm(arms, sticky_bucket(experiment_id, sid))?;
+            // On conflict the no-op update returns the arm stored earlier.
+            let stored: Result<uuid::Uuid, _> = sqlx::query_scalar(
+                "INSERT INTO ch_experiment_assignments (experiment_id, session_id, arm_id) \
+                 VALUES ($1, $2, $3) \
+                 ON CONFLICT (experiment_id, session_id) \
+                 DO UPDATE SET experiment_id = EXCLUDED.experiment_id \
+                 RETURNING arm_id",
+            )
+            .bind(experiment_id)
+            .bind(sid)
+            .bind(candidate.id)
+            .fetch_one(&state.db)
+            .await;
+            match stored {
+                Ok(arm_id) => arms.iter().find(|a| a.id == arm_id).unwrap_or(candidate),
+                Err(e) => {
+                    tracing::warn!("Experiment assignment not stored for {}: {}", sid, e);
+                    candidate
+                }
+            }
+        }
+        None => pick_arm(arms, rand::random::<u64>())?,
+    };
+
+    Some(ArmAssignment {
+        experiment_id,
+        experiment,
+        arm_id: arm.id,
+        arm: arm.name.clone(),
+        model: arm.model.clone(),
+        temperature: arm.temperature,
+        system_prompt_variant: arm.system_prompt_variant.clone(),
+    })
+}
+
+/// Record that a request was served by `assignment` with the final `model`.
+pub(crate) async fn log_exposure(
+    state: &AppState,
+    assignment: &ArmAssignment,
+    session_id: Option<uuid::Uuid>,
+    model: &str,
+) -> Option<ExperimentExposure> {
+    let exposure_id: i64 = sqlx::query_scalar(
+        "INSERT INTO ch_experiment_exposures (experiment_id, arm_id, session_id, model) \
+         VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(assignment.experiment_id)
+    .bind(assignment.arm_id)
+    .bind(session_id)
+    .bind(model)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| tracing::error!("Failed to log experiment exposure: {}", e))
+    .ok()?;
+
+    tracing::info!(
+        experiment = %assignment.experiment,
+        arm = %assignment.arm,
+        exposure_id,
+        "experiment exposure"
+    );
+    Some(ExperimentExposure {
+        exposure_id,
+        experiment: assignment.experiment.clone(),
+        arm: assignment.arm.clone(),
+    })
+}
+
+/// Mark an exposure as completed and attach its outcome.
+pub(crate) async fn complete_exposure(
+    state: &AppState,
+    exposure_id: i64,
+    execution_id: &str,
+    message_id: Option<uuid::Uuid>,
+    tool_calls: u32,
+    tool_errors: u32,
+) {
+    if let Err(e) = sqlx::query(
+        "UPDATE ch_experiment_exposures \
+         SET execution_id = $2, message_id = $3, tool_calls = $4, tool_errors = $5, \
+             completed_at = NOW() \
+         WHERE id = $1",
+    )
+    .bind(exposure_id)
+    .bind(execution_id)
+    .bind(message_id)
+    .bind(tool_calls as i32)
+    .bind(tool_errors as i32)
+    .execute(&state.db)
+    .await
+    {
+        tracing::error!(
+            "Failed to complete experiment exposure {}: {}",
+            exposure_id,
+            e
+        );
+    }
+}
+
+// ── Statistics ──────────────────────────────────────────────────────────────
+
+fn mean_estimate(n: i64, mean: Option<f64>, sd: Option<f64>) -> MeanEstimate {
+    let half = match (mean, sd) {
+        (Some(_), Some(sd)) if n >= 2 => Some(Z_95 * sd / (n as f64).sqrt()),
+        _ => None,
+    };
+    MeanEstimate {
+        n,
+        mean,
+        ci_low: mean.zip(half).map(|(m, h)| m - h),
+        ci_high: mean.zip(half).map(|(m, h)| m + h),
+    }
+}
+
+fn wilson_estimate(successes: i64, n: i64) -> RateEstimate {
+    if n <= 0 {
+        return RateEstimate {
+            n: 0,
+            successes: 0,
+            rate: None,
+            ci_low: None,
+            ci_high: None,
+        };
+    }
+    let nf = n as f64;
+    let p = successes as f64 / nf;
+    let z2 = Z_95 * Z_95;
+    let denom = 1.0 + z2 / nf;
+    let center = (p + z2 / (2.0 * nf)) / denom;
+    let half = Z_95 * (p * (1.0 - p) / nf + z2 / (4.0 * nf * nf)).sqrt() / denom;
+    RateEstimate {
+        n,
+        successes,
+        rate: Some(p),
+        ci_low: Some((center - half).max(0.0)),
+        ci_high: Some((center + half).min(1.0)),
+    }
+}
+
+// ── Handlers ────────────────────────────────────────────────────────────────
+
+fn internal_error(context: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    tracing::error!("{}: {}", context, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": context })),
+    )
+}
+
+fn bad_request(message: &str) -> (StatusCode, Json<Value>) {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message })))
+}
+
+fn already_running() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::CONFLICT,
+        Json(json!({ "error": "Another experiment is already running — stop it first" })),
+    )
+}
+
+/// Index that allows only one running experiment (migration 048).
+const SINGLE_RUNNING_INDEX: &str = "idx_ch_experiments_single_running";
+
+/// The constraint named by a unique violation (`""` if Postgres gave none),
+/// or `None` for any other error.
+fn unique_violation(e: &sqlx::Error) -> Option<&str> {
+    let db_err = e.as_database_error()?;
+    (db_err.code().as_deref() == Some("23505")).then(|| db_err.constraint().unwrap_or(""))
+}
+
+async fn load_experiments(
+    db: &sqlx::PgPool,
+    id: Option<uuid::Uuid>,
+) -> Result<Vec<Experiment>, sqlx::Error> {
+    let rows: Vec<ExperimentRow> = sqlx::query_as(
+        "SELECT id, name, description, status, created_at, started_at, stopped_at \
+         FROM ch_experiments WHERE $1::uuid IS NULL OR id = $1 ORDER BY created_at DESC",
+    )
+    .bind(id)
+    .fetch_all(db)
+    .await?;
+
+    let ids: Vec<uuid::Uuid> = rows.iter().map(|r| r.id).collect();
+    let arms: Vec<ArmRow> = sqlx::query_as(&format!(
+        "SELECT {ARM_COLUMNS} FROM ch_experiment_arms a \
+         WHERE a.experiment_id = ANY($1) ORDER BY a.name"
+    ))
+    .bind(&ids)
+    .fetch_all(db)
+    .await?;
+
+    let mut experiments: Vec<Experiment> = rows
+        .into_iter()
+        .map(|r| Experiment {
+            id: r.id.to_string(),
+            name: r.name,
+            description: r.description,
+            status: r.status,
+            created_at: r.created_at.to_rfc3339(),
+            started_at: r.started_at.map(|t| t.to_rfc3339()),
+            stopped_at: r.stopped_at.map(|t| t.to_rfc3339()),
+            arms: Vec::new(),
+        })
+        .collect();
+    for arm in arms {
+        let experiment_id = arm.experiment_id.to_string();
+        if let Some(exp) = experiments.iter_mut().find(|e| e.id == experiment_id) {
+            exp.arms.push(arm.into());
+        }
+    }
+    Ok(experiments)
+}
+
+/// GET /api/experiments
+#[utoipa::path(get, path = "/api/experiments", tag = "experiments",
+    responses((status = 200, description = "Experiments with their arms", body = Vec<Experiment>)))]
+pub async fn list_experiments(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let experiments = load_experiments(&state.db, None)
+        .await
+        .map_err(|e| internal_error("Failed to list experiments", e))?;
+    Ok(Json(json!({ "experiments": experiments })))
+}
+
+/// POST /api/experiments
+#[utoipa::path(post, path = "/api/experiments", tag = "experiments",
+    request_body = CreateExperimentRequest,
+    responses(
+        (status = 201, description = "Experiment created", body = Experiment),
+        (status = 400, description = "Invalid experiment definition"),
+        (status = 409, description = "Name taken or another experiment already running"),
+    ))]
+pub async fn create_experiment(
+    State(state): State<AppState>,
+    Json(req): Json<CreateExperimentRequest>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Value>)> {
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return Err(bad_request("name must not be empty"));
+    }
+    if req.arms.len() < 2 {
+        return Err(bad_request("an experiment needs at least two arms"));
+    }
+    let mut arm_names = HashSet::new();
+    for arm in &req.arms {
+        let arm_name = arm.name.trim();
+        if arm_name.is_empty() || !arm_names.insert(arm_name.to_string()) {
+            return Err(bad_request("arm names must be non-empty and unique"));
+        }
+        if arm.temperature.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            return Err(bad_request("arm temperature must be between 0 and 1"));
+        }
+        if arm.weight.is_some_and(|w| w <= 0) {
+            return Err(bad_request("arm weight must be positive"));
+        }
+    }
+
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| internal_error("Failed to create experiment", e))?;
+
+    let status = if req.start { "running" } else { "draft" };
+    let experiment_id: uuid::Uuid = sqlx::query_scalar(
+        "INSERT INTO ch_experiments (name, description, status, started_at) \
+         VALUES ($1, $2, $3, CASE WHEN $3 = 'running' THEN NOW() END) RETURNING id",
+    )
+    .bind(&name)
+    .bind(req.description.as_deref().unwrap_or(""))
+    .bind(status)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match unique_violation(&e) {
+        Some(SINGLE_RUNNING_INDEX) => already_running(),
+        Some(_) => (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": format!("Experiment '{}' already exists", name) })),
+        ),
+        None => internal_error("Failed to create experiment", e),
+    })?;
+
+    for arm in &req.arms {
+        sqlx::query(
+            "INSERT INTO ch_experiment_arms \
+             (experiment_id, name, model, temperature, system_prompt_variant, weight) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(experiment_id)
+        .bind(arm.name.trim())
+        .bind(
+            arm.model
+                .as_deref()
+                .map(str::trim)
+                .filter(|m| !m.is_empty()),
+        )
+        .bind(arm.temperature)
+        .bind(
+            arm.system_prompt_variant
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty()),
+        )
+        .bind(arm.weight.unwrap_or(1))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| internal_error("Failed to create experiment", e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| internal_error("Failed to create experiment", e))?;
+
+    crate::config_cache::invalidate(&state).await;
+    crate::audit::log_audit(
+        &state.db,
+        "create_experiment",
+        json!({ "experiment_id": experiment_id, "name": name, "status": status }),
+        None,
+    )
+    .await;
+
+    let experiment = load_experiments(&state.db, Some(experiment_id))
+        .await
+        .map_err(|e| internal_error("Failed to load experiment", e))?;
+    Ok((StatusCode::CREATED, Json(json!(experiment.first()))))
+}
+
+/// PATCH /api/experiments/{id}
+#[utoipa::path(patch, path = "/api/experiments/{id}", tag = "experiments",
+    params(("id" = String, Path, description = "Experiment UUID")),
+    request_body = UpdateExperimentRequest,
+    responses(
+        (status = 200, description = "Experiment status updated", body = Experiment),
+        (status = 404, description = "Experiment not found"),
+        (status = 409, description = "Another experiment is already running"),
+    ))]
+pub async fn update_experiment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateExperimentRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let experiment_id: uuid::Uuid = id
+        .parse()
+        .map_err(|_| bad_request("invalid experiment id"))?;
+    if !["draft", "running", "stopped"].contains(&req.status.as_str()) {
+        return Err(bad_request(
+            "status must be one of: draft, running, stopped",
+        ));
+    }
+
+    let updated = sqlx::query(
+        "UPDATE ch_experiments SET status = $2, \
+             started_at = CASE WHEN $2 = 'running' THEN COALESCE(started_at, NOW()) ELSE started_at END, \
+             stopped_at = CASE WHEN $2 = 'stopped' THEN NOW() WHEN $2 = 'running' THEN NULL \
+                 ELSE stopped_at END \
+         WHERE id = $1",
+    )
+    .bind(experiment_id)
+    .bind(&req.status)
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        if unique_violation(&e) == Some(SINGLE_RUNNING_INDEX) {
+            already_running()
+        } else {
+            internal_error("Failed to update experiment", e)
+        }
+    })?;
+    if updated.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Experiment not found" })),
+        ));
+    }
+
+    crate::config_cache::invalidate(&state).await;
+    crate::audit::log_audit(
+        &state.db,
+        "update_experiment",
+        json!({ "experiment_id": experiment_id, "status": req.status }),
+        None,
+    )
+    .await;
+
+    let experiment = load_experiments(&state.db, Some(experiment_id))
+        .await
+        .map_err(|e| internal_error("Failed to load experiment", e))?;
+    Ok(Json(json!(experiment.first())))
+}
+
+/// DELETE /api/experiments/{id}
+#[utoipa::path(delete, path = "/api/experiments/{id}", tag = "experiments",
+    params(("id" = String, Path, description = "Experiment UUID")),
+    responses(
+        (status = 200, description = "Experiment deleted"),
+        (status = 404, description = "Experiment not found"),
+    ))]
+pub async fn delete_experiment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let experiment_id: uuid::Uuid = id
+        .parse()
+        .map_err(|_| bad_request("invalid experiment id"))?;
+
+    let deleted = sqlx::query("DELETE FROM ch_experiments WHERE id = $1")
+        .bind(experiment_id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| internal_error("Failed to delete experiment", e))?;
+    if deleted.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Experiment not found" })),
+        ));
+    }
+
+    crate::config_cache::invalidate(&state).await;
+    crate::audit::log_audit(
+        &state.db,
+        "delete_experiment",
+        json!({ "experiment_id": experiment_id }),
+        None,
+    )
+    .await;
+
+    Ok(Json(json!({ "status": "deleted", "id": id })))
+}
+
+/// GET /api/experiments/{id}/results
+#[utoipa::path(get, path = "/api/experiments/{id}/results", tag = "experiments",
+    params(("id" = String, Path, description = "Experiment UUID")),
+    responses(
+        (status = 200, description = "Per-arm metrics with 95% confidence intervals"),
+        (status = 404, description = "Experiment not found"),
+    ))]
+pub async fn experiment_results(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let experiment_id: uuid::Uuid = id
+        .parse()
+        .map_err(|_| bad_request("invalid experiment id"))?;
+
+    let experiment = load_experiments(&state.db, Some(experiment_id))
+        .await
+        .map_err(|e| internal_error("Failed to load experiment", e))?
+        .into_iter()
+        .next()
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Experiment not found" })),
+        ))?;
+
+    // Aggregate each source separately so the joins do not multiply rows.
+    let rows: Vec<ArmStatsRow> = sqlx::query_as(&format!(
+        "WITH x AS (SELECT * FROM ch_experiment_exposures WHERE experiment_id = $1), \
+         e AS ( \
+             SELECT arm_id, COUNT(*) AS exposures, COUNT(DISTINCT session_id) AS sessions, \
+                 COUNT(completed_at) AS completed, \
+                 SUM(tool_calls)::int8 AS tool_calls, SUM(tool_errors)::int8 AS tool_errors \
+             FROM x GROUP BY arm_id), \
+         u AS ( \
+             SELECT x.arm_id, \
+                 COUNT(u.latency_ms) AS latency_n, \
+                 AVG(u.latency_ms)::float8 AS latency_mean, \
+                 STDDEV_SAMP(u.latency_ms)::float8 AS latency_sd, \
+                 COUNT(u.total_tokens) AS tokens_n, \
+                 AVG(u.total_tokens)::float8 AS tokens_mean, \
+                 STDDEV_SAMP(u.total_tokens)::float8 AS tokens_sd \
+             FROM x JOIN ch_agent_usage u ON u.exposure_id = x.id GROUP BY x.arm_id), \
+         r AS ( \
+             SELECT x.arm_id, \
+                 COUNT(r.rating) AS rating_n, \
+                 AVG(r.rating)::float8 AS rating_mean, \
+                 STDDEV_SAMP(r.rating)::float8 AS rating_sd \
+             FROM x JOIN {ratings} r ON r.message_id = x.message_id GROUP BY x.arm_id) \
+         SELECT {ARM_COLUMNS}, \
+             COALESCE(e.exposures, 0) AS exposures, COALESCE(e.sessions, 0) AS sessions, \
+             COALESCE(e.completed, 0) AS completed, \
+             COALESCE(e.tool_calls, 0) AS tool_calls, COALESCE(e.tool_errors, 0) AS tool_errors, \
+             COALESCE(u.latency_n, 0) AS latency_n, u.latency_mean, u.latency_sd, \
+             COALESCE(u.tokens_n, 0) AS tokens_n, u.tokens_mean, u.tokens_sd, \
+             COALESCE(r.rating_n, 0) AS rating_n, r.rating_mean, r.rating_sd \
+         FROM ch_experiment_arms a \
+         LEFT JOIN e ON e.arm_id = a.id \
+         LEFT JOIN u ON u.arm_id = a.id \
+         LEFT JOIN r ON r.arm_id = a.id \
+         WHERE a.experiment_id = $1 \
+         ORDER BY a.name",
+        ratings = state.ratings_table(),
+    ))
+    .bind(experiment_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| internal_error("Failed to compute experiment results", e))?;
+
+    let arms: Vec<ArmResults> = rows
+        .into_iter()
+        .map(|r| ArmResults {
+            completion_rate: wilson_estimate(r.completed, r.exposures),
+            latency_ms: mean_estimate(r.latency_n, r.latency_mean, r.latency_sd),
+            total_tokens: mean_estimate(r.tokens_n, r.tokens_mean, r.tokens_sd),
+            tool_success_rate: wilson_estimate(r.tool_calls - r.tool_errors, r.tool_calls),
+            rating: mean_estimate(r.rating_n, r.rating_mean, r.rating_sd),
+            exposures: r.exposures,
+            sessions: r.sessions,
+            arm: r.arm.into(),
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "experiment": experiment,
+        "confidence": 0.95,
+        "arms": arms,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arm(name: &str, weight: i32) -> ArmRow {
+        ArmRow {
+            id: uuid::Uuid::new_v4(),
+            experiment_id: uuid::Uuid::nil(),
+            name: name.to_string(),
+            model: None,
+            temperature: None,
+            system_prompt_variant: None,
+            weight,
+        }
+    }
+
+    #[test]
+    fn weighted_pick_follows_weights_and_is_sticky() {
+        let arms = vec![arm("control", 3), arm("variant", 1)];
+        let picks: Vec<&str> = (0..4)
+            .map(|b| pick_arm(&arms, b).unwrap().name.as_str())
+            .collect();
+        assert_eq!(picks, ["control", "control", "control", "variant"]);
+
+        let exp = uuid::Uuid::new_v4();
+        let session = uuid::Uuid::new_v4();
+        assert_eq!(sticky_bucket(exp, session), sticky_bucket(exp, session));
+        assert!(pick_arm(&[], 7).is_none());
+    }
+
+    #[test]
+    fn confidence_intervals() {
+        let rate = wilson_estimate(0, 10);
+        assert_eq!(rate.rate, Some(0.0));
+        assert!(rate.ci_low.unwrap() < 1e-9);
+        assert!(rate.ci_high.unwrap() > 0.2 && rate.ci_high.unwrap() < 0.35);
+        assert!(wilson_estimate(0, 0).rate.is_none());
+
+        let mean = mean_estimate(100, Some(500.0), Some(100.0));
+        assert!((mean.ci_low.unwrap() - 480.4).abs() < 1e-9);
+        assert!((mean.ci_high.unwrap() - 519.6).abs() < 1e-9);
+        assert!(mean_estimate(1, Some(5.0), None).ci_low.is_none());
+    }
+
+    /// Skipped unless DATABASE_URL points at a disposable database.
+    #[tokio::test]
+    async fn unique_violations_name_their_constraint() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let insert = "INSERT INTO ch_experiments (name, status) VALUES ($1, $2)";
+        let name = format!("unique-{}", uuid::Uuid::new_v4());
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("UPDATE ch_experiments SET status = 'stopped' WHERE status = 'running'")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query(insert)
+            .bind(&name)
+            .bind("running")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+        sqlx::query("SAVEPOINT conflict")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        let second_running = sqlx::query(insert)
+            .bind(format!("{name}-2"))
+            .bind("running")
+            .execute(&mut *tx)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            unique_violation(&second_running),
+            Some(SINGLE_RUNNING_INDEX)
+        );
+        sqlx::query("ROLLBACK TO SAVEPOINT conflict")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+        sqlx::query("SAVEPOINT conflict")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        let same_name = sqlx::query(insert)
+            .bind(&name)
+            .bind("draft")
+            .execute(&mut *tx)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            unique_violation(&same_name),
+            Some("ch_experiments_name_key")
+        );
+        sqlx::query("ROLLBACK TO SAVEPOINT conflict")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+        tx.rollback().await.unwrap();
+        assert_eq!(unique_violation(&sqlx::Error::RowNotFound), None);
+    }
+}
@@ -0,0 +1,677 @@
+//! Gateway provider configuration — built-in overrides and OpenAI-compatible
+//! endpoints.
+//!
+//! Merging and reloading live in [`crate::gateway_providers`]; this module
+//! manages the `ch_gateway_providers` rows. Upstream URLs go through the same
+//! SSRF validation as MCP server URLs (and are re-checked when called). The
+//! upstream URL of a Vault-authenticated built-in provider cannot be changed,
+//! and moving a compatible endpoint with a Vault credential to another URL
+//! requires re-binding its `vault_service` in the same request.
+//!
+//! Endpoints:
+//! - `GET    /api/ai/provider-configs`      — built-in and compatible providers
+//! - `POST   /api/ai/provider-configs`      — add an OpenAI-compatible endpoint
+//! - `PUT    /api/ai/provider-configs/{id}` — edit a provider (omitted fields unchanged)
+//! - `DELETE /api/ai/provider-configs/{id}` — remove an endpoint / reset a built-in
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::ai_gateway::{
+    AiProvider, AuthType, PlanQuota, ProviderConfig, default_provider_configs,
+};
+use crate::gateway_providers::{
+    KIND_BUILTIN, KIND_OPENAI_COMPATIBLE, ProviderRow, builtin_configs, fetch_rows,
+};
+use crate::state::AppState;
+
+/// Header names whose values are masked in responses.
+const SECRET_HEADER_HINTS: &[&str] = &["authorization", "cookie", "key", "token", "secret"];
+
+// ── Request / response types ────────────────────────────────────────────────
+
+/// Request body for `POST /api/ai/provider-configs` and
+/// `PUT /api/ai/provider-configs/{id}`. On PUT omitted fields are unchanged.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct ProviderConfigRequest {
+    /// Slug of a new OpenAI-compatible endpoint (POST only), e.g. `local-vllm`.
+    pub id: Option<String>,
+    /// Display name (plan name for built-in providers).
+    pub name: Option<String>,
+    /// Upstream URL; for compatible endpoints the full chat completions URL.
+    pub upstream_url: Option<String>,
+    /// Replaces the whole header map.
+    pub extra_headers: Option<HashMap<String, String>>,
+    pub monthly_cost_cents: Option<i32>,
+    pub commander_model: Option<String>,
+    /// Required on POST; `commander_model` and `executor_model` default to it.
+    pub coordinator_model: Option<String>,
+    pub executor_model: Option<String>,
+    /// Vault service (namespace `ai_compatible/<id>`) holding the endpoint's
+    /// API key; compatible endpoints only, never a built-in provider's
+    /// service. An empty string removes it.
+    pub vault_service: Option<String>,
+    pub enabled: Option<bool>,
+    /// Fallback position among compatible endpoints (lower first).
+    pub priority: Option<i32>,
+    /// Plan quota window of a built-in provider; 0 disables quota tracking.
+    pub quota_window_secs: Option<i32>,
+    /// Successful requests per quota window; 0 means unlimited.
+    pub quota_requests: Option<i32>,
+    /// Tokens per quota window; 0 means unlimited.
+    pub quota_tokens: Option<i64>,
+}
+
+/// Effective configuration of one provider.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProviderConfigView {
+    pub id: String,
+    /// `builtin` or `openai_compatible`.
+    pub kind: String,
+    pub name: String,
+    pub upstream_url: String,
+    /// Values of credential-like headers are masked.
+    pub extra_headers: HashMap<String, String>,
+    pub monthly_cost_cents: u32,
+    pub commander_model: String,
+    pub coordinator_model: String,
+    pub executor_model: String,
+    pub vault_service: Option<String>,
+    pub enabled: bool,
+    pub priority: i32,
+    /// Estimated plan quota (`window_secs`, `max_requests`, `max_tokens`).
+    #[schema(value_type = Option<Object>)]
+    pub quota: Option<PlanQuota>,
+    /// Whether a built-in provider differs from its factory defaults.
+    pub customized: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+// ── Helpers ─────────────────────────────────────────────────────────────────
+
+fn bad_request(message: &str) -> (StatusCode, Json<Value>) {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message })))
+}
+
+fn not_found(id: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("Provider '{}' not found", id) })),
+    )
+}
+
+fn db_error(action: &str, e: sqlx::Error) -> (StatusCode, Json<Value>) {
+    tracing::error!("Failed to {} gateway provider: {}", action, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("Failed to {} gateway provider", action) })),
+    )
+}
+
+fn redact_headers(headers: &HashMap<String, String>) -> HashMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let lower = name.to_ascii_lowercase();
+            if SECRET_HEADER_HINTS.iter().any(|hint| lower.contains(hint)) {
+                (name.clone(), "***".to_string())
+            } else {
+                (name.clone(), value.clone())
+            }
+        })
+        .collect()
+}
+
+fn builtin_view(config: &ProviderConfig, row: Option<&ProviderRow>) -> ProviderConfigView {
+    ProviderConfigView {
+        id: config.provider.to_string(),
+        kind: KIND_BUILTIN.to_string(),
+        name: config.plan_name.clone(),
+        upstream_url: config.upstream_url.clone(),
+        extra_headers: redact_headers(&config.extra_headers),
+        monthly_cost_cents: config.monthly_cost_cents,
+        commander_model: config.model_tiers.commander.clone(),
+        coordinator_model: config.model_tiers.coordinator.clone(),
+        executor_model: config.model_tiers.executor.clone(),
+        vault_service: Some(config.vault_service.clone()),
+        enabled: row.is_none_or(|r| r.enabled),
+        priority: 0,
+        quota: config.quota,
+        customized: row.is_some(),
+        updated_at: row.map(|r| r.updated_at),
+    }
+}
+
+fn compatible_view(row: &ProviderRow) -> Option<ProviderConfigView> {
+    let provider = row.to_compatible()?;
+    Some(ProviderConfigView {
+        id: provider.id,
+        kind: KIND_OPENAI_COMPATIBLE.to_string(),
+        name: provider.name,
+        upstream_url: provider.upstream_url,
+        extra_headers: redact_headers(&provider.extra_headers),
+        monthly_cost_cents: row
+            .monthly_cost_cents
+            .and_then(|c| u32::try_from(c).ok())
+            .unwrap_or_default(),
+        commander_model: provider.model_tiers.commander,
+        coordinator_model: provider.model_tiers.coordinator,
+        executor_model: provider.model_tiers.executor,
+        vault_service: provider.vault_service,
+        enabled: row.enabled,
+        priority: provider.priority,
+        quota: None,
+        customized: true,
+        updated_at: Some(row.updated_at),
+    })
+}
+
+/// Built-in providers (in `AiProvider::ALL` order), then compatible endpoints.
+fn views(rows: &[ProviderRow]) -> Vec<ProviderConfigView> {
+    let configs = builtin_configs(rows);
+    let builtin = AiProvider::ALL.iter().filter_map(|provider| {
+        let row = rows
+            .iter()
+            .find(|r| r.is_builtin() && AiProvider::from_str(&r.id).ok() == Some(*provider));
+        configs
+            .get(provider)
+            .map(|config| builtin_view(config, row))
+    });
+    let compatible = rows
+        .iter()
+        .filter(|r| !r.is_builtin())
+        .filter_map(compatible_view);
+    builtin.chain(compatible).collect()
+}
+
+/// Reload the live provider set and return the view of `id`.
+async fn reload_view(
+    state: &AppState,
+    id: &str,
+) -> Result<Json<ProviderConfigView>, (StatusCode, Json<Value>)> {
+    let rows = fetch_rows(&state.db)
+        .await
+        .map_err(|e| db_error("load", e))?;
+    state
+        .ai_gateway
+        .providers
+        .replace(crate::gateway_providers::build(&rows));
+    views(&rows)
+        .into_iter()
+        .find(|v| v.id == id)
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+/// Valid id of a new compatible endpoint: a lowercase slug that does not
+/// name (or alias) a built-in provider.
+fn validate_id(id: &str) -> Result<(), String> {
+    let slug = !id.is_empty()
+        && id.len() <= 64
+        && id.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !slug {
+        return Err("id must be a lowercase slug of 1-64 characters (a-z, 0-9, '-', '_')".into());
+    }
+    if AiProvider::from_str(id).is_ok() {
+        return Err(format!("'{}' is a built-in provider", id));
+    }
+    Ok(())
+}
+
+/// A compatible endpoint's Vault service: a slug that is neither a built-in
+/// provider nor the Vault service of one, so an endpoint can never be bound
+/// to a built-in credential. Empty (removal) is valid.
+fn validate_vault_service(service: &str) -> Result<(), String> {
+    if service.is_empty() {
+        return Ok(());
+    }
+    let slug = service.len() <= 64
+        && service
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !slug {
+        return Err("vault_service must be 1-64 characters (a-z, 0-9, '-', '_')".into());
+    }
+    let builtin = AiProvider::from_str(service).is_ok()
+        || default_provider_configs()
+            .values()
+            .any(|c| c.vault_service.eq_ignore_ascii_case(service));
+    if builtin {
+        return Err(format!(
+            "vault_service '{}' belongs to a built-in provider",
+            service
+        ));
+    }
+    Ok(())
+}
+
+/// Scheme check plus the MCP SSRF rules (private/loopback targets are
+/// rejected in production).
+fn validate_url(url: &str, is_prod: bool) -> Result<(), String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err("upstream_url must be an http(s) URL".into());
+    }
+    crate::mcp::config::validate_mcp_url(url, is_prod)
+        .map_err(|msg| format!("upstream_url rejected: {}", msg))
+}
+
+/// Field checks shared by create and update. `check_ssrf` is false for
+/// built-in providers that are local by design (`AuthType::None`).
+fn validate_fields(
+    req: &ProviderConfigRequest,
+    is_prod: bool,
+    check_ssrf: bool,
+) -> Result<(), String> {
+    if let Some(url) = &req.upstream_url {
+        if check_ssrf {
+            validate_url(url, is_prod)?;
+        } else if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err("upstream_url must be an http(s) URL".into());
+        }
+    }
+    if let Some(service) = &req.vault_service {
+        validate_vault_service(service)?;
+    }
+    if let Some(headers) = &req.extra_headers {
+        for (name, value) in headers {
+            if HeaderName::from_str(name).is_err() || HeaderValue::from_str(value).is_err() {
+                return Err(format!("invalid header '{}'", name));
+            }
+        }
+    }
+    for (field, model) in [
+        ("commander_model", &req.commander_model),
+        ("coordinator_model", &req.coordinator_model),
+        ("executor_model", &req.executor_model),
+    ] {
+        if model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+            return Err(format!("{} must not be empty", field));
+        }
+    }
+    if req.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err("name must not be empty".into());
+    }
+    if req.monthly_cost_cents.is_some_and(|c| c < 0) {
+        return Err("monthly_cost_cents must not be negative".into());
+    }
+    if req.quota_window_secs.is_some_and(|s| s < 0)
+        || req.quota_requests.is_some_and(|n| n < 0)
+        || req.quota_tokens.is_some_and(|n| n < 0)
+    {
+        return Err("quota limits must not be negative".into());
+    }
+    Ok(())
+}
+
+/// Whether `req` moves an endpoint holding a Vault credential to another URL
+/// without re-binding the credential: the key must not silently follow the
+/// endpoint to a new host.
+fn requires_rebinding(
+    req: &ProviderConfigRequest,
+    current_url: Option<&str>,
+    current_service: Option<&str>,
+) -> bool {
+    current_service.is_some()
+        && req.vault_service.is_none()
+        && req
+            .upstream_url
+            .as_deref()
+            .is_some_and(|url| Some(url) != current_url)
+}
+
+fn has_quota(req: &ProviderConfigRequest) -> bool {
+    req.quota_window_secs.is_some() || req.quota_requests.is_some() || req.quota_tokens.is_some()
+}
+
+// ── Handlers ────────────────────────────────────────────────────────────────
+
+/// GET /api/ai/provider-configs
+#[utoipa::path(get, path = "/api/ai/provider-configs", tag = "providers",
+    responses((status = 200, description = "Provider configurations", body = Vec<ProviderConfigView>)))]
+pub async fn list_provider_configs(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let rows = fetch_rows(&state.db)
+        .await
+        .map_err(|e| db_error("list", e))?;
+    Ok(Json(json!({ "providers": views(&rows) })))
+}
+
+/// POST /api/ai/provider-configs
+#[utoipa::path(post, path = "/api/ai/provider-configs", tag = "providers",
+    request_body = ProviderConfigRequest,
+    responses(
+        (status = 201, description = "Endpoint added", body = ProviderConfigView),
+        (status = 400, description = "Invalid request"),
+        (status = 409, description = "Id already in use"),
+    ))]
+pub async fn create_provider_config(
+    State(state): State<AppState>,
+    Json(req): Json<ProviderConfigRequest>,
+) -> Result<(StatusCode, Json<ProviderConfigView>), (StatusCode, Json<Value>)> {
+    let id = req.id.as_deref().map(str::trim).unwrap_or_default();
+    validate_id(id).map_err(|e| bad_request(&e))?;
+    let Some(url) = req.upstream_url.as_deref() else {
+        return Err(bad_request("upstream_url is required"));
+    };
+    let Some(coordinator) = req.coordinator_model.as_deref() else {
+        return Err(bad_request("coordinator_model is required"));
+    };
+    if has_quota(&req) {
+        return Err(bad_request("quotas can only be set on built-in providers"));
+    }
+    validate_fields(&req, state.auth_secret.is_some(), true).map_err(|e| bad_request(&e))?;
+
+    let inserted: Option<String> = sqlx::query_scalar(
+        "INSERT INTO ch_gateway_providers \
+         (id, kind, name, upstream_url, extra_headers, monthly_cost_cents, commander_model, \
+          coordinator_model, executor_model, vault_service, enabled, priority) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NULLIF($10, ''), $11, $12) \
+         ON CONFLICT (id) DO NOTHING RETURNING id",
+    )
+    .bind(id)
+    .bind(KIND_OPENAI_COMPATIBLE)
+    .bind(req.name.as_deref().map(str::trim))
+    .bind(url)
+    .bind(req.extra_headers.as_ref().map(sqlx::types::Json))
+    .bind(req.monthly_cost_cents)
+    .bind(req.commander_model.as_deref().unwrap_or(coordinator))
+    .bind(coordinator)
+    .bind(req.executor_model.as_deref().unwrap_or(coordinator))
+    .bind(&req.vault_service)
+    .bind(req.enabled.unwrap_or(true))
+    .bind(req.priority.unwrap_or(0))
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| db_error("create", e))?;
+
+    if inserted.is_none() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({ "error": format!("Provider '{}' already exists", id) })),
+        ));
+    }
+
+    crate::audit::log_audit(
+        &state.db,
+        "create_gateway_provider",
+        json!({ "id": id, "upstream_url": url }),
+        None,
+    )
+    .await;
+
+    let view = reload_view(&state, id).await?;
+    Ok((StatusCode::CREATED, view))
+}
+
+/// PUT /api/ai/provider-configs/{id}
+#[utoipa::path(put, path = "/api/ai/provider-configs/{id}", tag = "providers",
+    params(("id" = String, Path, description = "Built-in provider name or endpoint id")),
+    request_body = ProviderConfigRequest,
+    responses(
+        (status = 200, description = "Provider updated", body = ProviderConfigView),
+        (status = 400, description = "Invalid request"),
+        (status = 404, description = "Provider not found"),
+    ))]
+pub async fn update_provider_config(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ProviderConfigRequest>,
+) -> Result<Json<ProviderConfigView>, (StatusCode, Json<Value>)> {
+    if req.id.as_deref().is_some_and(|new_id| new_id != id) {
+        return Err(bad_request("id cannot be changed"));
+    }
+    let is_prod = state.auth_secret.is_some();
+
+    let id = if let Ok(provider) = AiProvider::from_str(&id) {
+        if req.vault_service.is_some() || req.priority.is_some() {
+            return Err(bad_request(
+                "vault_service and priority cannot be set on built-in providers",
+            ));
+        }
+        // Auth type and factory URL are fixed, so they come from the defaults
+        // (a disabled provider is missing from the live set).
+        let Some(default) = default_provider_configs().remove(&provider) else {
+            return Err(not_found(&id));
+        };
+        let local = default.auth_type == AuthType::None;
+        if !local
+            && req
+                .upstream_url
+                .as_ref()
+                .is_some_and(|url| *url != default.upstream_url)
+        {
+            return Err(bad_request(
+                "upstream_url of a Vault-authenticated built-in provider cannot be changed",
+            ));
+        }
+        validate_fields(&req, is_prod, !local).map_err(|e| bad_request(&e))?;
+
+        let id = provider.to_string();
+        sqlx::query(
+            "INSERT INTO ch_gateway_providers \
+             (id, kind, name, upstream_url, extra_headers, monthly_cost_cents, commander_model, \
+              coordinator_model, executor_model, enabled, quota_window_secs, quota_requests, \
+              quota_tokens) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, COALESCE($10, TRUE), $11, $12, $13) \
+             ON CONFLICT (id) DO UPDATE SET \
+               name = COALESCE($3, ch_gateway_providers.name), \
+               upstream_url = COALESCE($4, ch_gateway_providers.upstream_url), \
+               extra_headers = COALESCE($5, ch_gateway_providers.extra_headers), \
+               monthly_cost_cents = COALESCE($6, ch_gateway_providers.monthly_cost_cents), \
+               commander_model = COALESCE($7, ch_gateway_providers.commander_model), \
+               coordinator_model = COALESCE($8, ch_gateway_providers.coordinator_model), \
+               executor_model = COALESCE($9, ch_gateway_providers.executor_model), \
+               enabled = COALESCE($10, ch_gateway_providers.enabled), \
+               quota_window_secs = COALESCE($11, ch_gateway_providers.quota_window_secs), \
+               quota_requests = COALESCE($12, ch_gateway_providers.quota_requests), \
+               quota_tokens = COALESCE($13, ch_gateway_providers.quota_tokens), \
+               updated_at = NOW()",
+        )
+        .bind(&id)
+        .bind(KIND_BUILTIN)
+        .bind(req.name.as_deref().map(str::trim))
+        .bind(&req.upstream_url)
+        .bind(req.extra_headers.as_ref().map(sqlx::types::Json))
+        .bind(req.monthly_cost_cents)
+        .bind(&req.commander_model)
+        .bind(&req.coordinator_model)
+        .bind(&req.executor_model)
+        .bind(req.enabled)
+        .bind(req.quota_window_secs)
+        .bind(req.quota_requests)
+        .bind(req.quota_tokens)
+        .execute(&state.db)
+        .await
+        .map_err(|e| db_error("update", e))?;
+        id
+    } else {
+        if has_quota(&req) {
+            return Err(bad_request("quotas can only be set on built-in providers"));
+        }
+        validate_fields(&req, is_prod, true).map_err(|e| bad_request(&e))?;
+
+        let current: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT upstream_url, vault_service FROM ch_gateway_providers \
+             WHERE id = $1 AND kind = $2",
+        )
+        .bind(&id)
+        .bind(KIND_OPENAI_COMPATIBLE)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| db_error("load", e))?;
+        let Some((current_url, current_service)) = current else {
+            return Err(not_found(&id));
+        };
+        if requires_rebinding(&req, current_url.as_deref(), current_service.as_deref()) {
+            return Err(bad_request(
+                "upstream_url of an endpoint with a Vault credential can only change \
+                 together with vault_service (re-bind the credential, or send \"\" to remove it)",
+            ));
+        }
+
+        let updated = sqlx::query(
+            "UPDATE ch_gateway_providers SET \
+               name = COALESCE($3, name), \
+               upstream_url = COALESCE($4, upstream_url), \
+               extra_headers = COALESCE($5, extra_headers), \
+               monthly_cost_cents = COALESCE($6, monthly_cost_cents), \
+               commander_model = COALESCE($7, commander_model), \
+               coordinator_model = COALESCE($8, coordinator_model), \
+               executor_model = COALESCE($9, executor_model), \
+               vault_service = CASE WHEN $10::TEXT IS NULL THEN vault_service \
+                                    ELSE NULLIF($10, '') END, \
+               enabled = COALESCE($11, enabled), \
+               priority = COALESCE($12, priority), \
+               updated_at = NOW() \
+             WHERE id = $1 AND kind = $2",
+        )
+        .bind(&id)
+        .bind(KIND_OPENAI_COMPATIBLE)
+        .bind(req.name.as_deref().map(str::trim))
+        .bind(&req.upstream_url)
+        .bind(req.extra_headers.as_ref().map(sqlx::types::Json))
+        .bind(req.monthly_cost_cents)
+        .bind(&req.commander_model)
+        .bind(&req.coordinator_model)
+        .bind(&req.executor_model)
+        .bind(&req.vault_service)
+        .bind(req.enabled)
+        .bind(req.priority)
+        .execute(&state.db)
+        .await
+        .map_err(|e| db_error("update", e))?;
+        if updated.rows_affected() == 0 {
+            return Err(not_found(&id));
+        }
+        id
+    };
+
+    crate::audit::log_audit(
+        &state.db,
+        "update_gateway_provider",
+        json!({
+            "id": id,
+            "upstream_url": req.upstream_url,
+            "enabled": req.enabled,
+            "headers_changed": req.extra_headers.is_some(),
+            "quota_changed": has_quota(&req),
+        }),
+        None,
+    )
+    .await;
+
+    reload_view(&state, &id).await
+}
+
+/// DELETE /api/ai/provider-configs/{id}
+#[utoipa::path(delete, path = "/api/ai/provider-configs/{id}", tag = "providers",
+    params(("id" = String, Path, description = "Built-in provider name or endpoint id")),
+    responses(
+        (status = 200, description = "Endpoint removed or built-in provider reset"),
+        (status = 404, description = "Nothing stored for this id"),
+    ))]
+pub async fn delete_provider_config(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let id = AiProvider::from_str(&id)
+        .map(|p| p.to_string())
+        .unwrap_or(id);
+
+    let kind: Option<String> =
+        sqlx::query_scalar("DELETE FROM ch_gateway_providers WHERE id = $1 RETURNING kind")
+            .bind(&id)
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| db_error("delete", e))?;
+    let kind = kind.ok_or_else(|| not_found(&id))?;
+
+    crate::audit::log_audit(
+        &state.db,
+        "delete_gateway_provider",
+        json!({ "id": id, "kind": kind }),
+        None,
+    )
+    .await;
+    crate::gateway_providers::reload(&state).await;
+
+    Ok(Json(json!({
+        "deleted": kind == KIND_OPENAI_COMPATIBLE,
+        "reset": kind == KIND_BUILTIN,
+        "id": id,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatible_ids_are_slugs_distinct_from_builtins() {
+        assert!(validate_id("local-vllm").is_ok());
+        assert!(validate_id("lm_studio2").is_ok());
+        assert!(validate_id("").is_err());
+        assert!(validate_id("Local VLLM").is_err());
+        assert!(validate_id("-vllm").is_err());
+        assert!(validate_id("openai").is_err());
+        assert!(validate_id("gemini").is_err());
+    }
+
+    #[test]
+    fn builtin_vault_services_are_rejected() {
+        assert!(validate_vault_service("").is_ok());
+        assert!(validate_vault_service("vllm_key").is_ok());
+        assert!(validate_vault_service("anthropic_max").is_err());
+        assert!(validate_vault_service("ANTHROPIC_MAX").is_err());
+        assert!(validate_vault_service("openai").is_err());
+        assert!(validate_vault_service("../anthropic_max").is_err());
+    }
+
+    #[test]
+    fn moving_a_vault_endpoint_requires_rebinding() {
+        let url = "https://llm.internal.example/v1/chat/completions";
+        let moved = ProviderConfigRequest {
+            upstream_url: Some("https://elsewhere.example/v1/chat/completions".into()),
+            ..Default::default()
+        };
+        assert!(requires_rebinding(&moved, Some(url), Some("vllm_key")));
+        assert!(!requires_rebinding(&moved, Some(url), None));
+
+        let rebound = ProviderConfigRequest {
+            vault_service: Some("vllm_key".into()),
+            ..moved.clone()
+        };
+        assert!(!requires_rebinding(&rebound, Some(url), Some("vllm_key")));
+
+        let unchanged = ProviderConfigRequest {
+            upstream_url: Some(url.into()),
+            ..Default::default()
+        };
+        assert!(!requires_rebinding(&unchanged, Some(url), Some("vllm_key")));
+    }
+
+    #[test]
+    fn credential_headers_are_masked() {
+        let headers = HashMap::from([
+            ("Authorization".to_string(), "Bearer sk-1".to_string()),
+            ("x-api-key".to_string(), "k".to_string()),
+            ("anthropic-version".to_string(), "2023-06-01".to_string()),
+        ]);
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted["Authorization"], "***");
+        assert_eq!(redacted["x-api-key"], "***");
+        assert_eq!(redacted["anthropic-version"], "2023-06-01");
+    }
+}
@@ -0,0 +1,110 @@
+//! AI gateway usage — the per-call ledger aggregated by provider and model,
+//! plus the live plan quota estimates.
+//!
+//! Calls are recorded by [`crate::ai_gateway::usage::UsageLedger`] into
+//! `ch_gateway_usage`; quota estimates come from its in-memory windows and
+//! decide which providers `ModelRouter`'s fallback chain tries last.
+//!
+//! Endpoints:
+//! - `GET /api/ai/usage?days=7&provider=anthropic` — usage summary and quotas
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+
+// ── Query / response types ──────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct GatewayUsageQuery {
+    /// Number of days to look back (1-90, default 7).
+    pub days: Option<i32>,
+    /// Only this provider (built-in name or endpoint id).
+    pub provider: Option<String>,
+}
+
+/// Usage of one model through one provider.
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct GatewayUsageStat {
+    pub provider: String,
+    pub model: String,
+    pub calls: i64,
+    pub succeeded: i64,
+    /// Upstream HTTP 429 responses.
+    pub rate_limited: i64,
+    /// Other errors, including calls without an upstream response.
+    pub failed: i64,
+    /// Calls made as a fallback for another provider.
+    pub fallbacks: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub avg_latency_ms: f64,
+    pub p95_latency_ms: f64,
+}
+
+// ── Handler ─────────────────────────────────────────────────────────────────
+
+/// GET /api/ai/usage
+#[utoipa::path(get, path = "/api/ai/usage", tag = "providers",
+    params(
+        ("days" = Option<i32>, Query, description = "Days to look back (1-90, default 7)"),
+        ("provider" = Option<String>, Query, description = "Only this provider"),
+    ),
+    responses((status = 200, description = "Gateway usage per provider and model, and plan quota estimates")))]
+pub async fn gateway_usage(
+    State(state): State<AppState>,
+    Query(q): Query<GatewayUsageQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let days = q.days.unwrap_or(7).clamp(1, 90);
+
+    let usage = sqlx::query_as::<_, GatewayUsageStat>(
+        r#"
+        SELECT
+            provider,
+            model,
+            COUNT(*) AS calls,
+            COUNT(*) FILTER (WHERE status BETWEEN 200 AND 299) AS succeeded,
+            COUNT(*) FILTER (WHERE status = 429) AS rate_limited,
+            COUNT(*) FILTER (WHERE status NOT BETWEEN 200 AND 299 AND status <> 429) AS failed,
+            COUNT(*) FILTER (WHERE fallback_attempt > 0) AS fallbacks,
+            COALESCE(SUM(input_tokens), 0)::int8 AS input_tokens,
+            COALESCE(SUM(output_tokens), 0)::int8 AS output_tokens,
+            AVG(latency_ms)::float8 AS avg_latency_ms,
+            percentile_cont(0.95) WITHIN GROUP (ORDER BY latency_ms)::float8 AS p95_latency_ms
+        FROM ch_gateway_usage
+        WHERE created_at >= NOW() - make_interval(days => $1)
+          AND ($2::TEXT IS NULL OR provider = $2)
+        GROUP BY provider, model
+        ORDER BY calls DESC
+        "#,
+    )
+    .bind(days)
+    .bind(&q.provider)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("gateway usage query failed: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Failed to fetch gateway usage" })),
+        )
+    })?;
+
+    let quotas: Vec<_> = state
+        .ai_gateway
+        .usage
+        .estimates(&state.ai_gateway.providers())
+        .into_iter()
+        .filter(|e| q.provider.as_deref().is_none_or(|p| e.provider == p))
+        .collect();
+
+    Ok(Json(json!({
+        "days": days,
+        "usage": usage,
+        "quotas": quotas,
+    })))
+}
@@ -7,20 +7,36 @@
 //! - `chat` — non-streaming Claude chat endpoints
 //! - `health` — health, readiness, system stats, auth mode, admin
 //! - `sessions` — session CRUD, messages, AI title generation
+//! - `branches` — session forks, edit-as-sibling-branch, branch checkout
+//! - `session_export` — Markdown / JSON / JSONL export and JSON import of sessions
 //! - `settings` — application settings endpoints
 //! - `agents` — agent listing and refresh
 //! - `files` — file listing and native folder browser
 //! - `prompt_history` — bash-like prompt recall
 //! - `analytics` — agent performance dashboard aggregation endpoints
+//! - `budgets` — spending budget definitions and current spend vs limits
+//! - `gateway_providers` — AI gateway provider overrides and OpenAI-compatible endpoints
+//! - `gateway_usage` — AI gateway call ledger summary and plan quota estimates
+//! - `experiments` — A/B experiments: arms, sticky assignment, exposures, results
+//! - `pricing` — versioned model pricing catalogue used for cost analytics
+//! - `semantic_search` — hybrid (vector + keyword) session search, related sessions
 
 pub mod agents;
 pub mod analytics;
 pub mod anthropic_client;
+pub mod branches;
+pub mod budgets;
 pub mod chat;
+pub mod experiments;
 pub mod files;
+pub mod gateway_providers;
+pub mod gateway_usage;
 pub mod health;
+pub mod pricing;
 pub mod prompt;
 pub mod prompt_history;
+pub mod semantic_search;
+pub mod session_export;
 pub mod sessions;
 pub mod settings;
 pub mod streaming;
@@ -29,14 +45,22 @@ pub mod tags;
 // Re-export everything (including utoipa __path_* types needed by OpenApi derive)
 pub use agents::*;
 pub use analytics::*;
+pub use branches::*;
+pub use budgets::*;
 // Re-export send_to_anthropic within the crate so sub-modules (chat, streaming)
 // can continue to use `super::send_to_anthropic` without path changes.
 pub(crate) use anthropic_client::send_to_anthropic;
 pub use chat::*;
+pub use experiments::*;
 pub use files::*;
+pub use gateway_providers::*;
+pub use gateway_usage::*;
 pub use health::*;
+pub use pricing::*;
 pub use prompt::warm_prompt_cache;
 pub use prompt_history::*;
+pub use semantic_search::*;
+pub use session_export::*;
 pub use sessions::*;
 pub use settings::*;
 pub use streaming::*;
@@ -0,0 +1,258 @@
+//! Model pricing catalogue — versioned per-million-token rates used by cost
+//! analytics and the routing cost ceiling.
+//!
+//! Prices are keyed by provider + model pattern (`*` wildcard) with an
+//! effective-from date; usage is always priced with the rate that was valid
+//! when it was recorded ([`PRICE_AT_USE_JOIN`]). Changing a price means adding
+//! a new version, so historical costs stay stable.
+//!
+//! Endpoints:
+//! - `GET    /api/pricing`      — catalogue (all versions, `active` marks current rates)
+//! - `POST   /api/pricing`      — add a price version
+//! - `DELETE /api/pricing/{id}` — remove a mistaken entry
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::ai_gateway::AiProvider;
+use crate::state::AppState;
+
+/// `LEFT JOIN LATERAL` exposing `price.{input,output,cache_read,cache_write}_rate`
+/// and `price.currency` for usage row `u`, valid at `u.created_at`. The most
+/// specific matching pattern wins, then the newest version.
+pub(crate) const PRICE_AT_USE_JOIN: &str = "LEFT JOIN LATERAL ( \
+        SELECT p.input_per_mtok::float8 AS input_rate, \
+            p.output_per_mtok::float8 AS output_rate, \
+            COALESCE(p.cache_read_per_mtok, p.input_per_mtok)::float8 AS cache_read_rate, \
+            COALESCE(p.cache_write_per_mtok, p.input_per_mtok)::float8 AS cache_write_rate, \
+            p.currency \
+        FROM ch_model_pricing p \
+        WHERE p.provider = ch_model_provider(u.model) \
+          AND u.model LIKE replace(p.model_pattern, '*', '%') \
+          AND p.effective_from <= COALESCE(u.created_at, NOW()) \
+        ORDER BY length(replace(p.model_pattern, '*', '')) DESC, p.effective_from DESC \
+        LIMIT 1 \
+    ) price ON TRUE ";
+
+/// Cost of usage row `u` under [`PRICE_AT_USE_JOIN`] (NULL when unpriced).
+pub(crate) const USAGE_COST_EXPR: &str = "((COALESCE(u.input_tokens, 0) * price.input_rate \
+    + COALESCE(u.output_tokens, 0) * price.output_rate \
+    + u.cache_read_tokens * price.cache_read_rate \
+    + u.cache_write_tokens * price.cache_write_rate) / 1000000.0)";
+
+// ── Types ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, ToSchema, sqlx::FromRow)]
+pub struct ModelPrice {
+    pub id: i64,
+    pub provider: String,
+    pub model_pattern: String,
+    pub effective_from: DateTime<Utc>,
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    pub cache_read_per_mtok: Option<f64>,
+    pub cache_write_per_mtok: Option<f64>,
+    pub currency: String,
+    pub notes: String,
+    /// Newest version of its pattern that is already in effect.
+    pub active: bool,
+}
+
+/// Request body for `POST /api/pricing`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CreatePriceRequest {
+    pub provider: String,
+    /// Model id pattern, `*` matches any characters (e.g. `claude-*sonnet*`).
+    pub model_pattern: String,
+    /// Defaults to now; a future date schedules a price change.
+    pub effective_from: Option<DateTime<Utc>>,
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    pub cache_read_per_mtok: Option<f64>,
+    pub cache_write_per_mtok: Option<f64>,
+    /// ISO 4217 code (default `USD`).
+    pub currency: Option<String>,
+    pub notes: Option<String>,
+}
+
+// ── Shared helpers ──────────────────────────────────────────────────────────
+
+/// Spend (USD) of one user since midnight UTC, priced at time of use.
+/// `None` is the anonymous caller (usage recorded without a user).
+pub(crate) async fn spend_today_usd(db: &sqlx::PgPool, user_id: Option<&str>) -> f64 {
+    sqlx::query_scalar::<_, f64>(&format!(
+        "SELECT COALESCE(SUM({USAGE_COST_EXPR}) FILTER (WHERE price.currency = 'USD'), 0)::float8 \
+         FROM ch_agent_usage u {PRICE_AT_USE_JOIN}\
+         WHERE u.created_at >= date_trunc('day', NOW()) \
+           AND u.user_id IS NOT DISTINCT FROM $1"
+    ))
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .unwrap_or_else(|e| {
+        tracing::warn!("Failed to compute today's spend: {}", e);
+        0.0
+    })
+}
+
+fn bad_request(message: &str) -> (StatusCode, Json<Value>) {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message })))
+}
+
+// ── Handlers ────────────────────────────────────────────────────────────────
+
+/// GET /api/pricing
+#[utoipa::path(get, path = "/api/pricing", tag = "pricing",
+    responses((status = 200, description = "Pricing catalogue", body = Vec<ModelPrice>)))]
+pub async fn list_pricing(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let prices: Vec<ModelPrice> = sqlx::query_as(
+        "SELECT id, provider, model_pattern, effective_from, \
+             input_per_mtok::float8 AS input_per_mtok, output_per_mtok::float8 AS output_per_mtok, \
+             cache_read_per_mtok::float8 AS cache_read_per_mtok, \
+             cache_write_per_mtok::float8 AS cache_write_per_mtok, \
+             currency, notes, \
+             (effective_from <= NOW() AND NOT EXISTS ( \
+                 SELECT 1 FROM ch_model_pricing n \
+                 WHERE n.provider = p.provider AND n.model_pattern = p.model_pattern \
+                   AND n.effective_from > p.effective_from AND n.effective_from <= NOW() \
+             )) AS active \
+         FROM ch_model_pricing p \
+         ORDER BY provider, model_pattern, effective_from DESC",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list pricing: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Failed to list pricing" })),
+        )
+    })?;
+
+    Ok(Json(json!({ "prices": prices })))
+}
+
+/// POST /api/pricing
+#[utoipa::path(post, path = "/api/pricing", tag = "pricing",
+    request_body = CreatePriceRequest,
+    responses(
+        (status = 201, description = "Price version added"),
+        (status = 400, description = "Invalid price"),
+        (status = 409, description = "A version for this pattern and date already exists"),
+    ))]
+pub async fn create_price(
+    State(state): State<AppState>,
+    Json(req): Json<CreatePriceRequest>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Value>)> {
+    let provider = req.provider.trim().to_lowercase();
+    if !AiProvider::ALL.iter().any(|p| p.to_string() == provider) {
+        return Err(bad_request("unknown provider"));
+    }
+    let pattern = req.model_pattern.trim();
+    if pattern.is_empty() {
+        return Err(bad_request("model_pattern must not be empty"));
+    }
+    let rates = [
+        Some(req.input_per_mtok),
+        Some(req.output_per_mtok),
+        req.cache_read_per_mtok,
+        req.cache_write_per_mtok,
+    ];
+    if rates.iter().flatten().any(|r| !r.is_finite() || *r < 0.0) {
+        return Err(bad_request("rates must be non-negative numbers"));
+    }
+    let currency = req
+        .currency
+        .as_deref()
+        .map(|c| c.trim().to_uppercase())
+        .unwrap_or_else(|| "USD".to_string());
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(bad_request("currency must be a 3-letter ISO 4217 code"));
+    }
+
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO ch_model_pricing \
+         (provider, model_pattern, effective_from, input_per_mtok, output_per_mtok, \
+          cache_read_per_mtok, cache_write_per_mtok, currency, notes) \
+         VALUES ($1, $2, COALESCE($3, NOW()), $4, $5, $6, $7, $8, $9) RETURNING id",
+    )
+    .bind(&provider)
+    .bind(pattern)
+    .bind(req.effective_from)
+    .bind(req.input_per_mtok)
+    .bind(req.output_per_mtok)
+    .bind(req.cache_read_per_mtok)
+    .bind(req.cache_write_per_mtok)
+    .bind(&currency)
+    .bind(req.notes.as_deref().unwrap_or(""))
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        // The only unique constraint is (provider, model_pattern, effective_from).
+        if e.as_database_error()
+            .is_some_and(|db_err| db_err.code().as_deref() == Some("23505"))
+        {
+            (
+                StatusCode::CONFLICT,
+                Json(json!({ "error": "A price for this pattern and date already exists" })),
+            )
+        } else {
+            tracing::error!("Failed to add price: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Failed to add price" })),
+            )
+        }
+    })?;
+
+    crate::audit::log_audit(
+        &state.db,
+        "create_price",
+        json!({ "id": id, "provider": provider, "model_pattern": pattern, "currency": currency }),
+        None,
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(json!({ "id": id }))))
+}
+
+/// DELETE /api/pricing/{id}
+#[utoipa::path(delete, path = "/api/pricing/{id}", tag = "pricing",
+    params(("id" = i64, Path, description = "Price version id")),
+    responses(
+        (status = 200, description = "Price version deleted"),
+        (status = 404, description = "Price version not found"),
+    ))]
+pub async fn delete_price(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let deleted = sqlx::query("DELETE FROM ch_model_pricing WHERE id = $1")
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete price: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Failed to delete price" })),
+            )
+        })?;
+    if deleted.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Price version not found" })),
+        ));
+    }
+
+    crate::audit::log_audit(&state.db, "delete_price", json!({ "id": id }), None).await;
+
+    Ok(Json(json!({ "status": "deleted", "id": id })))
+}
@@ -1,18 +1,32 @@
 //! System prompt construction, chat context resolution, and auto-tier routing.
 //!
 //! - `build_system_prompt` — server-side system prompt (single source of truth)
-//! - `resolve_chat_context` — model selection, session WD, generation params
+//! - `resolve_chat_context` — model selection, session WD, generation params,
+//!   A/B experiment arm overrides (see `experiments`)
 //! - `warm_prompt_cache` — pre-warm system prompt cache at startup
-//! - `tier_token_budget` — per-model max_tokens budget
-//! - `classify_complexity` — auto-tier routing (re-exported from model_registry)
+//! - `tier_token_budget` — per-model max_tokens budget for delegated calls
+//!
+//! Generation limits come from the model's capability metadata
+//! (`crate::model_registry::model_capabilities`); a request that needs vision
+//! is refused when the selected model has none.
+//!
+//! Auto-tier routing for requests without an explicit model lives in `crate::tier_routing`.
 
+use crate::model_registry::ModelCapabilities;
 use crate::state::AppState;
+use crate::tier_routing::{ChatTransport, RoutingDecision};
 
 // ═══════════════════════════════════════════════════════════════════════
 //  Token budget per model tier
 // ═══════════════════════════════════════════════════════════════════════
 
-pub(crate) fn tier_token_budget(model: &str) -> u32 {
+/// Tier default capped by the model's real output limit.
+pub(crate) async fn tier_token_budget(state: &AppState, model: &str) -> u32 {
+    let caps = crate::model_registry::model_capabilities(state, model).await;
+    tier_default_budget(model).min(caps.max_output_tokens)
+}
+
+fn tier_default_budget(model: &str) -> u32 {
     let lower = model.to_lowercase();
     if lower.contains("opus") {
         8192
@@ -38,7 +52,31 @@ pub(crate) struct ChatContext {
     pub max_iterations: i32,
     pub working_directory: String,
     pub session_id: Option<uuid::Uuid>,
+    /// Signed-in user the request's spend is attributed to (None = anonymous).
+    pub user_id: Option<String>,
     pub system_prompt: String,
+    /// Auto-tier routing decision (None when the model was requested or set by an experiment arm).
+    pub routing: Option<RoutingDecision>,
+    /// Exposure logged when a running experiment assigned this request to an arm.
+    pub experiment: Option<super::experiments::ExperimentExposure>,
+    pub capabilities: ModelCapabilities,
+}
+
+/// Error code sent when the selected model cannot serve the request.
+pub const MODEL_CAPABILITY: &str = "MODEL_CAPABILITY";
+
+/// The selected model lacks a capability the request needs.
+#[derive(Debug)]
+pub(crate) struct CapabilityMismatch {
+    pub model: String,
+    pub message: String,
+}
+
+/// Whether the request carries image input (inline `data:image/` URLs).
+fn needs_vision(req: &crate::models::ChatRequest) -> bool {
+    req.messages
+        .iter()
+        .any(|m| m.content.contains("data:image/"))
 }
 
 // ═══════════════════════════════════════════════════════════════════════
@@ -127,125 +165,84 @@ fn build_system_prompt(
 // ═══════════════════════════════════════════════════════════════════════
 
 /// Resolves model, max_tokens, session WD (session → global fallback).
+///
+/// Model precedence: experiment arm → requested model → auto-tier routing.
+/// `max_tokens` is capped by the model's real output limit; a request with
+/// image input fails when the model has no vision.
 pub(crate) async fn resolve_chat_context(
     state: &AppState,
     req: &crate::models::ChatRequest,
-) -> ChatContext {
-    let model = if let Some(ref m) = req.model {
-        m.clone()
-    } else {
-        let prompt_text: String = req
-            .messages
-            .iter()
-            .rev()
-            .take(1)
-            .map(|m| m.content.as_str())
-            .collect();
-        let complexity = crate::model_registry::classify_complexity(&prompt_text);
-        match complexity {
-            "simple" => crate::model_registry::get_model_id(state, "coordinator").await,
-            "complex" => crate::model_registry::get_model_id(state, "commander").await,
-            _ => crate::model_registry::get_model_id(state, "commander").await,
-        }
-    };
+    transport: ChatTransport,
+    user_id: Option<&str>,
+) -> Result<ChatContext, CapabilityMismatch> {
+    let session_uuid = req
+        .session_id
+        .as_deref()
+        .and_then(|s| uuid::Uuid::parse_str(s).ok());
 
-    // A/B testing: read ab_model_b + ab_split from settings
-    let model = {
-        let ab_row: Option<(Option<String>, Option<f64>)> =
-            sqlx::query_as("SELECT ab_model_b, ab_split::float8 FROM ch_settings WHERE id = 1")
-                .fetch_optional(&state.db)
-                .await
-                .ok()
-                .flatten();
-        if let Some((Some(model_b), Some(split))) = ab_row {
-            if !model_b.is_empty() && rand::random::<f64>() < split {
-                tracing::info!(
-                    "A/B test: using model_b={} (split={:.0}%)",
-                    model_b,
-                    split * 100.0
-                );
-                model_b
-            } else {
-                model
-            }
-        } else {
+    // A/B experiments: sticky per-session arm of the running experiment
+    let arm = super::experiments::assign_arm(state, session_uuid).await;
+
+    let mut routing = None;
+    let model = match arm
+        .as_ref()
+        .and_then(|a| a.model.clone())
+        .or_else(|| req.model.clone())
+    {
+        Some(model) => model,
+        None => {
+            let decision =
+                crate::tier_routing::route(state, req, session_uuid, user_id, transport).await;
+            let model = decision.model.clone();
+            routing = Some(decision);
             model
         }
     };
 
-    let session_uuid = req
-        .session_id
-        .as_deref()
-        .and_then(|s| uuid::Uuid::parse_str(s).ok());
+    let capabilities = crate::model_registry::model_capabilities(state, &model).await;
+    if needs_vision(req) && !capabilities.vision {
+        return Err(CapabilityMismatch {
+            message: format!(
+                "Model {} does not accept image input — pick or pin a vision-capable model",
+                model
+            ),
+            model,
+        });
+    }
 
-    // Single query: fetch session WD, global WD, language, generation params, and custom instructions
-    let (
-        working_directory,
-        language,
-        db_temperature,
-        db_max_tokens,
-        db_max_iterations,
-        custom_instructions,
-    ) = if let Some(ref sid) = session_uuid {
-        let row: Option<(String, String, String, f64, i32, i32, String)> = sqlx::query_as(
-            "SELECT COALESCE(s.working_directory, '') AS session_wd, \
-             COALESCE(g.working_directory, '') AS global_wd, \
-             COALESCE(g.language, 'en') AS language, \
-             COALESCE(g.temperature, 0.7) AS temperature, \
-             COALESCE(g.max_tokens, 4096) AS max_tokens, \
-             COALESCE(g.max_iterations, 10) AS max_iterations, \
-             COALESCE(g.custom_instructions, '') AS custom_instructions \
-             FROM ch_sessions s \
-             CROSS JOIN ch_settings g \
-             WHERE s.id = $1 AND g.id = 1",
+    // Generation params, language and custom instructions come from the cached
+    // settings snapshot; only the session's own working directory hits the DB.
+    let config = crate::config_cache::snapshot(state).await;
+    let settings = &config.settings;
+    let session_wd: String = match session_uuid {
+        Some(sid) => sqlx::query_scalar(
+            "SELECT COALESCE(working_directory, '') FROM ch_sessions WHERE id = $1",
         )
         .bind(sid)
         .fetch_optional(&state.db)
         .await
         .ok()
-        .flatten();
-        match row {
-            Some((session_wd, global_wd, lang, temp, mtok, miter, ci)) => {
-                let wd = if !session_wd.is_empty() {
-                    session_wd
-                } else {
-                    global_wd
-                };
-                (wd, lang, temp, mtok, miter, ci)
-            }
-            None => (
-                String::new(),
-                "en".to_string(),
-                0.7,
-                4096,
-                10,
-                String::new(),
-            ),
-        }
+        .flatten()
+        .unwrap_or_default(),
+        None => String::new(),
+    };
+    let working_directory = if !session_wd.is_empty() {
+        session_wd
     } else {
-        let row: Option<(String, String, f64, i32, i32, String)> = sqlx::query_as(
-            "SELECT COALESCE(working_directory, ''), COALESCE(language, 'en'), \
-             COALESCE(temperature, 0.7), COALESCE(max_tokens, 4096), COALESCE(max_iterations, 10), \
-             COALESCE(custom_instructions, '') \
-             FROM ch_settings WHERE id = 1",
-        )
-        .fetch_optional(&state.db)
-        .await
-        .ok()
-        .flatten();
-        row.unwrap_or((
-            "".to_string(),
-            "en".to_string(),
-            0.7,
-            4096,
-            10,
-            String::new(),
-        ))
+        settings.working_directory.clone()
     };
+    let language = &settings.language;
+    let custom_instructions = &settings.custom_instructions;
 
-    let budget = tier_token_budget(&model);
-    let max_tokens = req.max_tokens.unwrap_or(db_max_tokens as u32).min(budget);
-    let temperature = req.temperature.unwrap_or(db_temperature);
+    let max_tokens = req
+        .max_tokens
+        .unwrap_or(settings.max_tokens as u32)
+        .min(capabilities.max_output_tokens);
+    let temperature = arm
+        .as_ref()
+        .and_then(|a| a.temperature)
+        .or(req.temperature)
+        .unwrap_or(settings.temperature);
 
     // Use cached system prompt if available (cache key includes custom_instructions hash)
     let ci_hash = {
@@ -260,7 +257,7 @@ pub(crate) async fn resolve_chat_context(
         cache.get(&cache_key).cloned()
     }
     .unwrap_or_else(|| {
-        let prompt = build_system_prompt(&working_directory, &language, &custom_instructions);
+        let prompt = build_system_prompt(&working_directory, language, custom_instructions);
         let prompt_clone = prompt.clone();
         let state_clone = state.prompt_cache.clone();
         let key_clone = cache_key;
@@ -269,16 +266,32 @@ pub(crate) async fn resolve_chat_context(
         });
         prompt
     });
+    let system_prompt = match arm
+        .as_ref()
+        .and_then(|a| a.system_prompt_variant.as_deref())
+    {
+        Some(variant) => format!("{}\n\n{}", system_prompt, variant),
+        None => system_prompt,
+    };
+
+    let experiment = match &arm {
+        Some(arm) => super::experiments::log_exposure(state, arm, session_uuid, &model).await,
+        None => None,
+    };
 
-    ChatContext {
+    Ok(ChatContext {
         model,
         max_tokens,
         temperature,
-        max_iterations: db_max_iterations,
+        max_iterations: settings.max_iterations,
         working_directory,
         session_id: session_uuid,
+        user_id: user_id.map(str::to_string),
         system_prompt,
-    }
+        routing,
+        experiment,
+        capabilities,
+    })
 }
 
 // ═══════════════════════════════════════════════════════════════════════
@@ -287,15 +300,12 @@ pub(crate) async fn resolve_chat_context(
 
 /// Pre-warm system prompt cache for common language variants.
 pub async fn warm_prompt_cache(state: &AppState) {
-    // Fetch custom_instructions from DB for accurate cache warming
-    let custom_instructions: String = sqlx::query_scalar(
-        "SELECT COALESCE(custom_instructions, '') FROM ch_settings WHERE id = 1",
-    )
-    .fetch_optional(&state.db)
-    .await
-    .ok()
-    .flatten()
-    .unwrap_or_default();
+    // custom_instructions from the settings snapshot for accurate cache warming
+    let custom_instructions = crate::config_cache::snapshot(state)
+        .await
+        .settings
+        .custom_instructions
+        .clone();
 
     let ci_hash = {
         use std::hash::{Hash, Hasher};
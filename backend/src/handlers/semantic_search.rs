@@ -0,0 +1,489 @@
+//! Hybrid (semantic + keyword) session search and related sessions.
+//!
+//! Endpoints:
+//! - `GET  /api/sessions/search/hybrid`          — vector similarity fused with FTS rank
+//! - `GET  /api/sessions/{id}/related`           — sessions semantically close to a session
+//! - `POST /api/sessions/embeddings/backfill`    — embed all not-yet-indexed messages
+//! - `GET  /api/sessions/embeddings/status`      — indexing progress
+//!
+//! Vector and keyword result lists are merged with Reciprocal Rank Fusion
+//! (RRF), weighted by `alpha` (1.0 = vector only, 0.0 = keyword only). When
+//! embeddings are not configured the hybrid endpoint degrades to keyword search.
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+
+use super::tags::{SearchRow, keyword_search, load_session_tags};
+
+/// RRF damping constant — the usual value from the original RRF paper.
+const RRF_K: f64 = 60.0;
+
+// ── Request / Response types ────────────────────────────────────────────────
+
+/// Query parameters for hybrid search.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HybridSearchParams {
+    pub q: String,
+    /// Comma-separated list of tags to filter by (optional).
+    pub tags: Option<String>,
+    /// Maximum number of results (default 20, max 100).
+    pub limit: Option<i64>,
+    /// Offset for pagination (default 0).
+    pub offset: Option<i64>,
+    /// Keyword query syntax — same as `/api/sessions/search` (`web`, `prefix`, `boolean`).
+    pub mode: Option<String>,
+    /// Weight of the vector ranking in [0, 1] (default 0.5).
+    pub alpha: Option<f64>,
+}
+
+/// A fused hybrid search hit.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HybridSearchResult {
+    pub session_id: String,
+    pub session_title: String,
+    pub message_id: String,
+    pub message_preview: Option<String>,
+    pub message_role: Option<String>,
+    pub message_timestamp: Option<String>,
+    pub tags: Vec<String>,
+    /// Fused RRF score.
+    pub score: f64,
+    /// Cosine similarity from Qdrant (if the message was a vector hit).
+    pub vector_score: Option<f32>,
+    /// `ts_rank` from the keyword index (if the message was a keyword hit).
+    pub keyword_rank: Option<f32>,
+}
+
+/// Query parameters for related sessions.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RelatedParams {
+    /// Maximum number of related sessions (default 5, max 20).
+    pub limit: Option<usize>,
+}
+
+/// A session related to the requested one.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RelatedSession {
+    pub session_id: String,
+    pub title: String,
+    pub updated_at: String,
+    pub tags: Vec<String>,
+    /// Best similarity between any of its messages and the source session.
+    pub score: f32,
+    /// Number of its messages among the nearest neighbours.
+    pub matches: usize,
+}
+
+/// Accumulator for one message across both rankings.
+#[derive(Default)]
+struct Fused {
+    row: Option<SearchRow>,
+    vector_rank: Option<usize>,
+    vector_score: Option<f32>,
+    keyword_rank: Option<usize>,
+    keyword_score: Option<f32>,
+}
+
+fn rrf(rank: Option<usize>, weight: f64) -> f64 {
+    rank.map_or(0.0, |r| weight / (RRF_K + r as f64 + 1.0))
+}
+
+// ── GET /api/sessions/search/hybrid ─────────────────────────────────────────
+
+#[utoipa::path(get, path = "/api/sessions/search/hybrid", tag = "tags",
+    params(
+        ("q" = String, Query, description = "Search query"),
+        ("tags" = Option<String>, Query, description = "Comma-separated tag filter"),
+        ("limit" = Option<i64>, Query, description = "Max results (default 20)"),
+        ("offset" = Option<i64>, Query, description = "Pagination offset"),
+        ("mode" = Option<String>, Query, description = "Keyword syntax: web (default), prefix, boolean"),
+        ("alpha" = Option<f64>, Query, description = "Vector weight 0..1 (default 0.5)"),
+    ),
+    responses((status = 200, description = "Fused search results", body = Vec<HybridSearchResult>)))]
+pub async fn hybrid_search(
+    State(state): State<AppState>,
+    Query(params): Query<HybridSearchParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let query_text = params.q.trim();
+    if query_text.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let alpha = params.alpha.unwrap_or(0.5).clamp(0.0, 1.0);
+    // Each ranking contributes a deeper candidate list than the final page.
+    let candidates = ((limit + offset) * 3).clamp(50, 300);
+
+    let tag_filter: Vec<String> = params
+        .tags
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let mut fused: HashMap<uuid::Uuid, Fused> = HashMap::new();
+
+    // ── Keyword ranking (messages + tool interactions) ──
+    let keyword_rows = keyword_search(
+        &state.db,
+        query_text,
+        params.mode.as_deref(),
+        &tag_filter,
+        (true, true),
+        false,
+        candidates,
+        0,
+    )
+    .await
+    .map_err(|e| {
+        if let sqlx::Error::Database(ref db_err) = e
+            && db_err.code().as_deref() == Some("42601")
+        {
+            return StatusCode::BAD_REQUEST;
+        }
+        tracing::error!("Hybrid search keyword query failed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .unwrap_or_default();
+
+    let mut keyword_rank = 0usize;
+    for row in keyword_rows {
+        let Some(message_id) = row.message_id else {
+            continue;
+        };
+        let entry = fused.entry(message_id).or_default();
+        if entry.keyword_rank.is_none() {
+            entry.keyword_rank = Some(keyword_rank);
+            entry.keyword_score = row.rank;
+            entry.row = Some(row);
+            keyword_rank += 1;
+        }
+    }
+
+    // ── Vector ranking (Qdrant) ──
+    let mut semantic = state.session_index.is_enabled() && alpha > 0.0;
+    if semantic {
+        match state
+            .session_index
+            .search(
+                &state.ai_gateway.vault_guard,
+                query_text,
+                candidates as usize,
+            )
+            .await
+        {
+            Ok(hits) => {
+                let ids: Vec<uuid::Uuid> = hits.iter().map(|h| h.message_id).collect();
+                // Resolve rows for vector hits + apply the tag filter (deleted
+                // sessions drop out here as well).
+                let rows = sqlx::query_as::<_, SearchRow>(
+                    "SELECT s.id AS session_id, s.title AS session_title, m.id AS message_id, \
+                        LEFT(m.content, 200) AS message_preview, m.role AS message_role, \
+                        m.created_at AS message_timestamp, NULL::REAL AS rank, \
+                        'message'::TEXT AS source, NULL::TEXT AS tool_name \
+                     FROM ch_messages m \
+                     JOIN ch_sessions s ON s.id = m.session_id \
+                     WHERE m.id = ANY($1) AND (cardinality($2::TEXT[]) = 0 OR EXISTS (\
+                        SELECT 1 FROM ch_session_tags t WHERE t.session_id = s.id AND t.tag = ANY($2)))",
+                )
+                .bind(&ids)
+                .bind(&tag_filter)
+                .fetch_all(&state.db)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Hybrid search vector row lookup failed: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                let mut rows_by_id: HashMap<uuid::Uuid, SearchRow> = rows
+                    .into_iter()
+                    .filter_map(|r| r.message_id.map(|id| (id, r)))
+                    .collect();
+
+                let mut vector_rank = 0usize;
+                for hit in hits {
+                    let Some(row) = rows_by_id.remove(&hit.message_id) else {
+                        continue;
+                    };
+                    let entry = fused.entry(hit.message_id).or_default();
+                    entry.vector_rank = Some(vector_rank);
+                    entry.vector_score = Some(hit.score);
+                    // Prefer the keyword row — it carries a highlighted snippet.
+                    if entry.row.is_none() {
+                        entry.row = Some(row);
+                    }
+                    vector_rank += 1;
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Hybrid search: vector search unavailable: {}", e);
+                semantic = false;
+            }
+        }
+    }
+
+    let mut ranked: Vec<(f64, Fused)> = fused
+        .into_values()
+        .filter(|f| f.row.is_some())
+        .map(|f| {
+            let score = rrf(f.vector_rank, alpha) + rrf(f.keyword_rank, 1.0 - alpha);
+            (score, f)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    // Only the fused candidate lists are ranked, so there is no exact total.
+    let has_more = ranked.len() > (offset + limit) as usize;
+    let page: Vec<(f64, Fused)> = ranked
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect();
+
+    let session_ids: Vec<uuid::Uuid> = page
+        .iter()
+        .filter_map(|(_, f)| f.row.as_ref().map(|r| r.session_id))
+        .collect::<std::collections::HashSet<_>>()
+        .into_iter()
+        .collect();
+    let tags_map = load_session_tags(&state.db, &session_ids).await;
+
+    let results: Vec<HybridSearchResult> = page
+        .into_iter()
+        .filter_map(|(score, f)| {
+            let row = f.row?;
+            Some(HybridSearchResult {
+                session_id: row.session_id.to_string(),
+                session_title: row.session_title,
+                message_id: row.message_id?.to_string(),
+                message_preview: row.message_preview,
+                message_role: row.message_role,
+                message_timestamp: row.message_timestamp.map(|t| t.to_rfc3339()),
+                tags: tags_map.get(&row.session_id).cloned().unwrap_or_default(),
+                score,
+                vector_score: f.vector_score,
+                keyword_rank: f.keyword_score,
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "results": results,
+        "count": results.len(),
+        "has_more": has_more,
+        "query": query_text,
+        "tags": tag_filter,
+        "semantic": semantic,
+        "alpha": alpha,
+    })))
+}
+
+// ── GET /api/sessions/{id}/related ──────────────────────────────────────────
+
+#[utoipa::path(get, path = "/api/sessions/{id}/related", tag = "tags",
+    params(
+        ("id" = String, Path, description = "Session UUID"),
+        ("limit" = Option<usize>, Query, description = "Max related sessions (default 5)"),
+    ),
+    responses(
+        (status = 200, description = "Related sessions", body = Vec<RelatedSession>),
+        (status = 404, description = "Session not found"),
+        (status = 503, description = "Embeddings not configured"),
+    ))]
+pub async fn related_sessions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<RelatedParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let session_id: uuid::Uuid = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let limit = params.limit.unwrap_or(5).clamp(1, 20);
+
+    if !state.session_index.is_enabled() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    sqlx::query("SELECT 1 FROM ch_sessions WHERE id = $1")
+        .bind(session_id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check session: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Seed with the most recent messages of the session that have a vector.
+    let seed_ids: Vec<uuid::Uuid> = sqlx::query_scalar(&format!(
+        "SELECT id FROM ch_messages WHERE session_id = $1 AND {} \
+         ORDER BY created_at DESC LIMIT 20",
+        crate::session_index::HAS_VECTOR_SQL
+    ))
+    .bind(session_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load seed messages: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if seed_ids.is_empty() {
+        return Ok(Json(json!({
+            "session_id": id,
+            "related": [],
+            "reason": "not_indexed",
+        })));
+    }
+
+    // Related sessions are a side panel — an unreachable vector store yields
+    // an empty list rather than an error.
+    let hits = match state
+        .session_index
+        .recommend(&seed_ids, session_id, 100)
+        .await
+    {
+        Ok(hits) => hits,
+        Err(e) => {
+            tracing::warn!("Related sessions: vector search failed: {}", e);
+            return Ok(Json(json!({
+                "session_id": id,
+                "related": [],
+                "reason": "vector_unavailable",
+            })));
+        }
+    };
+
+    // Aggregate message hits per session: best score + number of matches.
+    let mut per_session: HashMap<uuid::Uuid, (f32, usize)> = HashMap::new();
+    for hit in hits {
+        let entry = per_session.entry(hit.session_id).or_insert((0.0, 0));
+        entry.0 = entry.0.max(hit.score);
+        entry.1 += 1;
+    }
+
+    let candidate_ids: Vec<uuid::Uuid> = per_session.keys().copied().collect();
+    let sessions: Vec<(uuid::Uuid, String, chrono::DateTime<chrono::Utc>)> =
+        if candidate_ids.is_empty() {
+            Vec::new()
+        } else {
+            sqlx::query_as("SELECT id, title, updated_at FROM ch_sessions WHERE id = ANY($1)")
+                .bind(&candidate_ids)
+                .fetch_all(&state.db)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to load related sessions: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?
+        };
+
+    let mut related: Vec<RelatedSession> = sessions
+        .into_iter()
+        .filter_map(|(sid, title, updated_at)| {
+            let (score, matches) = per_session.get(&sid).copied()?;
+            Some(RelatedSession {
+                session_id: sid.to_string(),
+                title,
+                updated_at: updated_at.to_rfc3339(),
+                tags: Vec::new(),
+                score,
+                matches,
+            })
+        })
+        .collect();
+    related.sort_by(|a, b| b.score.total_cmp(&a.score));
+    related.truncate(limit);
+
+    let related_ids: Vec<uuid::Uuid> = related
+        .iter()
+        .filter_map(|r| r.session_id.parse().ok())
+        .collect();
+    let tags_map = load_session_tags(&state.db, &related_ids).await;
+    for r in &mut related {
+        if let Ok(sid) = r.session_id.parse::<uuid::Uuid>() {
+            r.tags = tags_map.get(&sid).cloned().unwrap_or_default();
+        }
+    }
+
+    Ok(Json(json!({
+        "session_id": id,
+        "related": related,
+    })))
+}
+
+// ── POST /api/sessions/embeddings/backfill ──────────────────────────────────
+
+#[utoipa::path(post, path = "/api/sessions/embeddings/backfill", tag = "tags",
+    responses(
+        (status = 202, description = "Backfill started"),
+        (status = 503, description = "Embeddings not configured"),
+    ))]
+pub async fn backfill_embeddings(
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    if !state.session_index.is_enabled() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    if state.session_index.backfill.running.load(Ordering::SeqCst) {
+        return Ok((StatusCode::OK, Json(json!({ "status": "already_running" }))));
+    }
+
+    let job_state = state.clone();
+    tokio::spawn(async move {
+        crate::session_index::run_backfill(&job_state).await;
+    });
+
+    crate::audit::log_audit(&state.db, "backfill_embeddings", json!({}), None).await;
+
+    Ok((StatusCode::ACCEPTED, Json(json!({ "status": "started" }))))
+}
+
+// ── GET /api/sessions/embeddings/status ─────────────────────────────────────
+
+#[utoipa::path(get, path = "/api/sessions/embeddings/status", tag = "tags",
+    responses((status = 200, description = "Embedding index status")))]
+pub async fn embeddings_status(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let pending: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM ch_messages WHERE embedded_at IS NULL")
+            .fetch_one(&state.db)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to count pending embeddings: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+    let progress = &state.session_index.backfill;
+    Ok(Json(json!({
+        "enabled": state.session_index.is_enabled(),
+        "running": progress.running.load(Ordering::Relaxed),
+        "indexed": progress.indexed.load(Ordering::Relaxed),
+        "failed": progress.failed.load(Ordering::Relaxed),
+        "pending": pending,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rrf_weights_rankings_by_alpha() {
+        assert_eq!(rrf(None, 1.0), 0.0);
+        // Top of a ranking beats the second place of the same ranking.
+        assert!(rrf(Some(0), 0.5) > rrf(Some(1), 0.5));
+        // A hit in both rankings outranks a hit in only one of them.
+        let both = rrf(Some(3), 0.5) + rrf(Some(3), 0.5);
+        let vector_only = rrf(Some(0), 0.5) + rrf(None, 0.5);
+        assert!(both > vector_only);
+        // alpha = 0 ignores the vector ranking entirely.
+        assert_eq!(rrf(Some(0), 0.0), 0.0);
+    }
+}
@@ -0,0 +1,973 @@
+//! Session export / import.
+//!
+//! Endpoints:
+//! - `GET  /api/sessions/{id}/export` — export one session
+//! - `GET  /api/sessions/export`      — export sessions matching a tag filter, oldest
+//!   first, in pages of `MAX_SESSIONS`; a truncated page carries the
+//!   `x-export-next-cursor` header to pass back as `?cursor=`
+//! - `POST /api/sessions/import`      — restore a `json` export under new ids
+//!
+//! Formats (`?format=`):
+//! - `markdown` — readable transcript of the active branch, tool calls collapsed
+//! - `json`     — full-fidelity document: every branch, tool interactions, tags,
+//!   working directory and per-message model metadata (the only importable format)
+//! - `jsonl`    — one Anthropic Messages request body per session (active branch),
+//!   with `tool_use` / `tool_result` blocks — for evals and fine-tuning
+
+use std::collections::{HashMap, HashSet};
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderName, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::ToSchema;
+
+use crate::models::*;
+use crate::state::AppState;
+
+use super::MAX_MESSAGE_LENGTH;
+
+/// Identifies the JSON export document.
+pub const EXPORT_FORMAT: &str = "claudehydra.sessions";
+pub const EXPORT_VERSION: u32 = 1;
+/// Upper bound on sessions per export page / import.
+const MAX_SESSIONS: usize = 500;
+/// Set on a bulk export page when more sessions follow.
+const NEXT_CURSOR_HEADER: &str = "x-export-next-cursor";
+/// Longest tag accepted (bytes, the limit of `add_session_tags`).
+const MAX_TAG_LENGTH: usize = 50;
+/// Upper bound on messages per imported session.
+const MAX_IMPORT_MESSAGES: usize = 10_000;
+const IMPORT_ROLES: &[&str] = &["user", "assistant", "system"];
+
+// ── Request / Response types ────────────────────────────────────────────────
+
+/// Query parameters for the export endpoints.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportParams {
+    /// `markdown` (default), `json` or `jsonl`.
+    pub format: Option<String>,
+    /// Comma-separated tag filter (bulk export only; empty = all sessions).
+    pub tags: Option<String>,
+    /// Continue a bulk export after the page that returned this cursor.
+    pub cursor: Option<String>,
+}
+
+/// Full-fidelity export document (`format=json`), also the import payload.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SessionExportDocument {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub sessions: Vec<ExportedSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportedSession {
+    pub id: String,
+    pub title: String,
+    pub created_at: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub working_directory: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub parent_session_id: Option<String>,
+    /// Head of the branch that was checked out.
+    #[serde(default)]
+    pub active_leaf_message_id: Option<String>,
+    /// Distinct models used in the session.
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// All messages of all branches, parents before children.
+    pub messages: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportedMessage {
+    pub id: String,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub agent: Option<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub tool_interactions: Vec<ExportedToolInteraction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportedToolInteraction {
+    pub tool_use_id: String,
+    pub tool_name: String,
+    pub tool_input: Value,
+    #[serde(default)]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default)]
+    pub executed_at: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Markdown,
+    Json,
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn parse(s: Option<&str>) -> Option<Self> {
+        match s.unwrap_or("markdown") {
+            "markdown" | "md" => Some(Self::Markdown),
+            "json" => Some(Self::Json),
+            "jsonl" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Loading
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Load a session with every message of every branch, in parent-first order.
+async fn load_exported_session(
+    db: &sqlx::PgPool,
+    session_id: uuid::Uuid,
+) -> Result<Option<ExportedSession>, sqlx::Error> {
+    Ok(load_exported_sessions(db, &[session_id]).await?.pop())
+}
+
+/// Load several sessions in `session_ids` order (missing ids are skipped),
+/// with one query per table rather than per session.
+async fn load_exported_sessions(
+    db: &sqlx::PgPool,
+    session_ids: &[uuid::Uuid],
+) -> Result<Vec<ExportedSession>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, SessionRow>(
+        "SELECT id, title, created_at, updated_at, working_directory, \
+             parent_session_id, forked_from_message_id, active_leaf_message_id \
+         FROM ch_sessions WHERE id = ANY($1)",
+    )
+    .bind(session_ids)
+    .fetch_all(db)
+    .await?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let tag_rows: Vec<(uuid::Uuid, String)> = sqlx::query_as(
+        "SELECT session_id, tag FROM ch_session_tags WHERE session_id = ANY($1) \
+         ORDER BY tag ASC",
+    )
+    .bind(session_ids)
+    .fetch_all(db)
+    .await?;
+    let mut tags: HashMap<uuid::Uuid, Vec<String>> = HashMap::new();
+    for (session_id, tag) in tag_rows {
+        tags.entry(session_id).or_default().push(tag);
+    }
+
+    // Parents are always older than their children, so creation order is topological.
+    let message_rows = sqlx::query_as::<_, MessageRow>(
+        "SELECT id, session_id, role, content, model, agent, created_at, parent_message_id \
+         FROM ch_messages WHERE session_id = ANY($1) ORDER BY created_at ASC, id ASC",
+    )
+    .bind(session_ids)
+    .fetch_all(db)
+    .await?;
+
+    let message_ids: Vec<uuid::Uuid> = message_rows.iter().map(|m| m.id).collect();
+    let ti_rows = sqlx::query_as::<_, ToolInteractionRow>(
+        "SELECT id, message_id, tool_use_id, tool_name, tool_input, result, is_error, executed_at \
+         FROM ch_tool_interactions WHERE message_id = ANY($1) ORDER BY executed_at ASC",
+    )
+    .bind(&message_ids)
+    .fetch_all(db)
+    .await?;
+
+    let mut ti_map: HashMap<uuid::Uuid, Vec<ExportedToolInteraction>> = HashMap::new();
+    for ti in ti_rows {
+        ti_map
+            .entry(ti.message_id)
+            .or_default()
+            .push(ExportedToolInteraction {
+                tool_use_id: ti.tool_use_id,
+                tool_name: ti.tool_name,
+                tool_input: ti.tool_input,
+                result: ti.result,
+                is_error: ti.is_error,
+                executed_at: Some(ti.executed_at.to_rfc3339()),
+            });
+    }
+
+    let mut messages: HashMap<uuid::Uuid, Vec<ExportedMessage>> = HashMap::new();
+    let mut models: HashMap<uuid::Uuid, Vec<String>> = HashMap::new();
+    for m in message_rows {
+        if let Some(ref model) = m.model {
+            let session_models = models.entry(m.session_id).or_default();
+            if !session_models.contains(model) {
+                session_models.push(model.clone());
+            }
+        }
+        messages
+            .entry(m.session_id)
+            .or_default()
+            .push(ExportedMessage {
+                id: m.id.to_string(),
+                parent_id: m.parent_message_id.map(|p| p.to_string()),
+                role: m.role,
+                content: m.content,
+                model: m.model,
+                agent: m.agent,
+                created_at: m.created_at.to_rfc3339(),
+                tool_interactions: ti_map.remove(&m.id).unwrap_or_default(),
+            });
+    }
+
+    let mut by_id: HashMap<uuid::Uuid, SessionRow> = rows.into_iter().map(|r| (r.id, r)).collect();
+    Ok(session_ids
+        .iter()
+        .filter_map(|id| by_id.remove(id))
+        .map(|row| ExportedSession {
+            id: row.id.to_string(),
+            title: row.title,
+            created_at: row.created_at.to_rfc3339(),
+            updated_at: row.updated_at.to_rfc3339(),
+            working_directory: row.working_directory,
+            tags: tags.remove(&row.id).unwrap_or_default(),
+            parent_session_id: row.parent_session_id.map(|s| s.to_string()),
+            active_leaf_message_id: row.active_leaf_message_id.map(|m| m.to_string()),
+            models: models.remove(&row.id).unwrap_or_default(),
+            messages: messages.remove(&row.id).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Messages of the checked-out branch, root first (falls back to the newest
+/// message when no head is recorded).
+fn active_branch(session: &ExportedSession) -> Vec<&ExportedMessage> {
+    let by_id: HashMap<&str, &ExportedMessage> = session
+        .messages
+        .iter()
+        .map(|m| (m.id.as_str(), m))
+        .collect();
+    let mut cursor = session
+        .active_leaf_message_id
+        .as_deref()
+        .and_then(|id| by_id.get(id).copied())
+        .or_else(|| session.messages.last());
+
+    let mut branch = Vec::new();
+    let mut seen = HashSet::new();
+    while let Some(msg) = cursor {
+        if !seen.insert(msg.id.as_str()) {
+            break;
+        }
+        branch.push(msg);
+        cursor = msg.parent_id.as_deref().and_then(|p| by_id.get(p).copied());
+    }
+    branch.reverse();
+    branch
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Renderers
+// ═══════════════════════════════════════════════════════════════════════
+
+fn fenced(text: &str, lang: &str) -> String {
+    // Use a fence longer than any backtick run inside the text.
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{lang}\n{text}\n{fence}")
+}
+
+fn render_markdown(sessions: &[ExportedSession]) -> String {
+    let mut out = String::new();
+    for (i, session) in sessions.iter().enumerate() {
+        if i > 0 {
+            out.push_str("\n---\n\n");
+        }
+        out.push_str(&format!("# {}\n\n", session.title));
+        out.push_str(&format!("- **Session:** `{}`\n", session.id));
+        out.push_str(&format!("- **Created:** {}\n", session.created_at));
+        if !session.working_directory.is_empty() {
+            out.push_str(&format!(
+                "- **Working directory:** `{}`\n",
+                session.working_directory
+            ));
+        }
+        if !session.models.is_empty() {
+            out.push_str(&format!("- **Models:** {}\n", session.models.join(", ")));
+        }
+        if !session.tags.is_empty() {
+            out.push_str(&format!("- **Tags:** {}\n", session.tags.join(", ")));
+        }
+        out.push('\n');
+
+        for msg in active_branch(session) {
+            let who = match msg.role.as_str() {
+                "user" => "User".to_string(),
+                "assistant" => match (&msg.agent, &msg.model) {
+                    (Some(agent), Some(model)) => format!("Assistant — {agent} ({model})"),
+                    (None, Some(model)) => format!("Assistant ({model})"),
+                    (Some(agent), None) => format!("Assistant — {agent}"),
+                    (None, None) => "Assistant".to_string(),
+                },
+                other => other.to_string(),
+            };
+            out.push_str(&format!("## {who}\n\n_{}_\n\n", msg.created_at));
+
+            for ti in &msg.tool_interactions {
+                let status = if ti.is_error { " ❌" } else { "" };
+                out.push_str(&format!(
+                    "<details>\n<summary>🔧 {}{status}</summary>\n\n",
+                    ti.tool_name
+                ));
+                let input = serde_json::to_string_pretty(&ti.tool_input).unwrap_or_default();
+                out.push_str(&fenced(&input, "json"));
+                out.push_str("\n\n");
+                if let Some(ref result) = ti.result {
+                    out.push_str(&fenced(result, ""));
+                    out.push_str("\n\n");
+                }
+                out.push_str("</details>\n\n");
+            }
+
+            out.push_str(msg.content.trim_end());
+            out.push_str("\n\n");
+        }
+    }
+    out
+}
+
+/// Append content blocks to the conversation, merging with the previous turn
+/// when the role repeats (the Messages API requires alternating roles).
+fn push_blocks(messages: &mut Vec<Value>, role: &str, blocks: Vec<Value>) {
+    if blocks.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut()
+        && last["role"] == role
+        && let Some(content) = last["content"].as_array_mut()
+    {
+        content.extend(blocks);
+        return;
+    }
+    messages.push(json!({ "role": role, "content": blocks }));
+}
+
+/// One Anthropic Messages request body for the active branch of a session.
+fn render_anthropic_transcript(session: &ExportedSession) -> Value {
+    let mut system: Vec<String> = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+
+    for msg in active_branch(session) {
+        match msg.role.as_str() {
+            "system" => system.push(msg.content.clone()),
+            "assistant" => {
+                if !msg.tool_interactions.is_empty() {
+                    let uses = msg
+                        .tool_interactions
+                        .iter()
+                        .map(|ti| {
+                            json!({
+                                "type": "tool_use",
+                                "id": ti.tool_use_id,
+                                "name": ti.tool_name,
+                                "input": ti.tool_input,
+                            })
+                        })
+                        .collect();
+                    push_blocks(&mut messages, "assistant", uses);
+                    let results = msg
+                        .tool_interactions
+                        .iter()
+                        .map(|ti| {
+                            json!({
+                                "type": "tool_result",
+                                "tool_use_id": ti.tool_use_id,
+                                "content": ti.result.clone().unwrap_or_default(),
+                                "is_error": ti.is_error,
+                            })
+                        })
+                        .collect();
+                    push_blocks(&mut messages, "user", results);
+                }
+                if !msg.content.trim().is_empty() {
+                    push_blocks(
+                        &mut messages,
+                        "assistant",
+                        vec![json!({ "type": "text", "text": msg.content })],
+                    );
+                }
+            }
+            _ => {
+                if !msg.content.trim().is_empty() {
+                    push_blocks(
+                        &mut messages,
+                        "user",
+                        vec![json!({ "type": "text", "text": msg.content })],
+                    );
+                }
+            }
+        }
+    }
+
+    let mut body = json!({
+        "model": session.models.last(),
+        "messages": messages,
+        "metadata": {
+            "session_id": session.id,
+            "title": session.title,
+            "tags": session.tags,
+        },
+    });
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+    body
+}
+
+/// Bulk export cursor: creation time (µs) and id of the last exported session.
+fn encode_cursor(created_at: chrono::DateTime<chrono::Utc>, id: uuid::Uuid) -> String {
+    format!("{}.{}", created_at.timestamp_micros(), id)
+}
+
+fn decode_cursor(cursor: &str) -> Option<(chrono::DateTime<chrono::Utc>, uuid::Uuid)> {
+    let (micros, id) = cursor.split_once('.')?;
+    Some((
+        chrono::DateTime::from_timestamp_micros(micros.parse().ok()?)?,
+        id.parse().ok()?,
+    ))
+}
+
+fn export_response(sessions: Vec<ExportedSession>, format: ExportFormat, stem: &str) -> Response {
+    let (content_type, ext, body) = match format {
+        ExportFormat::Markdown => (
+            "text/markdown; charset=utf-8",
+            "md",
+            render_markdown(&sessions),
+        ),
+        ExportFormat::Jsonl => (
+            "application/x-ndjson",
+            "jsonl",
+            sessions
+                .iter()
+                .map(|s| render_anthropic_transcript(s).to_string() + "\n")
+                .collect(),
+        ),
+        ExportFormat::Json => {
+            let doc = SessionExportDocument {
+                format: EXPORT_FORMAT.to_string(),
+                version: EXPORT_VERSION,
+                exported_at: chrono::Utc::now().to_rfc3339(),
+                sessions,
+            };
+            (
+                "application/json",
+                "json",
+                serde_json::to_string_pretty(&doc).unwrap_or_default(),
+            )
+        }
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{stem}.{ext}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Export handlers
+// ═══════════════════════════════════════════════════════════════════════
+
+#[utoipa::path(get, path = "/api/sessions/{id}/export", tag = "sessions",
+    params(
+        ("id" = String, Path, description = "Session UUID"),
+        ("format" = Option<String>, Query, description = "markdown (default), json, jsonl"),
+    ),
+    responses(
+        (status = 200, description = "Exported session file"),
+        (status = 400, description = "Unknown format"),
+        (status = 404, description = "Session not found"),
+    ))]
+pub async fn export_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, StatusCode> {
+    let session_id: uuid::Uuid = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let format = ExportFormat::parse(params.format.as_deref()).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let session = load_exported_session(&state.db, session_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load session for export: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(export_response(
+        vec![session],
+        format,
+        &format!("session-{session_id}"),
+    ))
+}
+
+#[utoipa::path(get, path = "/api/sessions/export", tag = "sessions",
+    params(
+        ("format" = Option<String>, Query, description = "markdown (default), json, jsonl"),
+        ("tags" = Option<String>, Query, description = "Comma-separated tag filter (any match)"),
+        ("cursor" = Option<String>, Query, description = "x-export-next-cursor of the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Exported sessions file (x-export-next-cursor set when truncated)"),
+        (status = 400, description = "Unknown format or invalid cursor"),
+    ))]
+pub async fn export_sessions(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, StatusCode> {
+    let format = ExportFormat::parse(params.format.as_deref()).ok_or(StatusCode::BAD_REQUEST)?;
+    let tag_filter: Vec<String> = params
+        .tags
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let after = match params.cursor.as_deref() {
+        Some(cursor) => Some(decode_cursor(cursor).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    // One row past the page tells whether more sessions follow.
+    let mut page: Vec<(uuid::Uuid, chrono::DateTime<chrono::Utc>)> = sqlx::query_as(
+        "SELECT s.id, s.created_at FROM ch_sessions s \
+         WHERE (cardinality($1::TEXT[]) = 0 OR EXISTS (\
+             SELECT 1 FROM ch_session_tags t WHERE t.session_id = s.id AND t.tag = ANY($1))) \
+           AND ($2::TIMESTAMPTZ IS NULL OR (s.created_at, s.id) > ($2, $3::UUID)) \
+         ORDER BY s.created_at ASC, s.id ASC LIMIT $4",
+    )
+    .bind(&tag_filter)
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(MAX_SESSIONS as i64 + 1)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list sessions for export: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let next_cursor = (page.len() > MAX_SESSIONS).then(|| {
+        page.truncate(MAX_SESSIONS);
+        let (id, created_at) = page[MAX_SESSIONS - 1];
+        encode_cursor(created_at, id)
+    });
+
+    let session_ids: Vec<uuid::Uuid> = page.iter().map(|(id, _)| *id).collect();
+    let sessions = load_exported_sessions(&state.db, &session_ids)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load sessions for export: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    crate::audit::log_audit(
+        &state.db,
+        "export_sessions",
+        json!({
+            "count": sessions.len(),
+            "tags": tag_filter,
+            "truncated": next_cursor.is_some(),
+        }),
+        None,
+    )
+    .await;
+
+    let mut response = export_response(sessions, format, "sessions");
+    if let Some(cursor) = next_cursor
+        && let Ok(value) = HeaderValue::from_str(&cursor)
+    {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(NEXT_CURSOR_HEADER), value);
+    }
+    Ok(response)
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Import
+// ═══════════════════════════════════════════════════════════════════════
+
+fn parse_timestamp(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Utc))
+}
+
+/// Validate an import document. Returns every problem found, so the client
+/// can fix the file in one go.
+fn validate_import(doc: &SessionExportDocument) -> Vec<String> {
+    let mut errors = Vec::new();
+    if doc.format != EXPORT_FORMAT {
+        errors.push(format!("format must be \"{EXPORT_FORMAT}\""));
+    }
+    if doc.version == 0 || doc.version > EXPORT_VERSION {
+        errors.push(format!("unsupported version {}", doc.version));
+    }
+    if doc.sessions.is_empty() {
+        errors.push("no sessions".to_string());
+    }
+    if doc.sessions.len() > MAX_SESSIONS {
+        errors.push(format!("too many sessions (max {MAX_SESSIONS})"));
+    }
+
+    for (si, session) in doc.sessions.iter().enumerate() {
+        let at = format!("sessions[{si}]");
+        if session.title.trim().is_empty() {
+            errors.push(format!("{at}.title is empty"));
+        }
+        if session.messages.len() > MAX_IMPORT_MESSAGES {
+            errors.push(format!(
+                "{at} has too many messages (max {MAX_IMPORT_MESSAGES})"
+            ));
+        }
+        for (ti, tag) in session.tags.iter().enumerate() {
+            if tag.trim().to_lowercase().len() > MAX_TAG_LENGTH {
+                errors.push(format!(
+                    "{at}.tags[{ti}] \"{tag}\" exceeds {MAX_TAG_LENGTH} bytes"
+                ));
+            }
+        }
+        let mut seen: HashSet<&str> = HashSet::new();
+        for (mi, msg) in session.messages.iter().enumerate() {
+            let at = format!("{at}.messages[{mi}]");
+            if !seen.insert(msg.id.as_str()) {
+                errors.push(format!("{at}.id is duplicated"));
+            }
+            if let Some(ref parent) = msg.parent_id
+                && !seen.contains(parent.as_str())
+            {
+                errors.push(format!(
+                    "{at}.parent_id does not reference an earlier message"
+                ));
+            }
+            if !IMPORT_ROLES.contains(&msg.role.as_str()) {
+                errors.push(format!("{at}.role \"{}\" is not allowed", msg.role));
+            }
+            if msg.content.len() > MAX_MESSAGE_LENGTH {
+                errors.push(format!("{at}.content exceeds {MAX_MESSAGE_LENGTH} bytes"));
+            }
+            if parse_timestamp(&msg.created_at).is_none() {
+                errors.push(format!("{at}.created_at is not RFC 3339"));
+            }
+            for (ti_idx, ti) in msg.tool_interactions.iter().enumerate() {
+                if ti.tool_use_id.is_empty() || ti.tool_name.is_empty() {
+                    errors.push(format!(
+                        "{at}.tool_interactions[{ti_idx}] needs tool_use_id and tool_name"
+                    ));
+                }
+            }
+        }
+        if let Some(ref leaf) = session.active_leaf_message_id
+            && !seen.contains(leaf.as_str())
+        {
+            errors.push(format!(
+                "{at}.active_leaf_message_id is not one of its messages"
+            ));
+        }
+    }
+    errors
+}
+
+#[utoipa::path(post, path = "/api/sessions/import", tag = "sessions",
+    request_body = SessionExportDocument,
+    responses(
+        (status = 201, description = "Sessions restored under new ids"),
+        (status = 400, description = "Invalid import document"),
+    ))]
+pub async fn import_sessions(
+    State(state): State<AppState>,
+    Json(doc): Json<SessionExportDocument>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Value>)> {
+    let errors = validate_import(&doc);
+    if !errors.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "invalid import document", "details": errors })),
+        ));
+    }
+
+    let db_err = |e: sqlx::Error| {
+        tracing::error!("Session import failed: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "import failed" })),
+        )
+    };
+
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+    let mut imported = Vec::with_capacity(doc.sessions.len());
+    let mut message_count = 0usize;
+
+    for session in &doc.sessions {
+        let created_at = parse_timestamp(&session.created_at).unwrap_or_else(chrono::Utc::now);
+        let updated_at = parse_timestamp(&session.updated_at).unwrap_or(created_at);
+
+        let new_sid: uuid::Uuid = sqlx::query_scalar(
+            "INSERT INTO ch_sessions (title, working_directory, created_at, updated_at) \
+             VALUES ($1, $2, $3, $4) RETURNING id",
+        )
+        .bind(session.title.trim())
+        .bind(&session.working_directory)
+        .bind(created_at)
+        .bind(updated_at)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_err)?;
+
+        let mut id_map: HashMap<&str, uuid::Uuid> = HashMap::new();
+        for msg in &session.messages {
+            let parent = msg
+                .parent_id
+                .as_deref()
+                .and_then(|p| id_map.get(p).copied());
+            // Roots must not be auto-attached to the current head.
+            if parent.is_none() {
+                sqlx::query("SELECT ch_checkout_message($1, NULL)")
+                    .bind(new_sid)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_err)?;
+            }
+            let new_mid: uuid::Uuid = sqlx::query_scalar(
+                "INSERT INTO ch_messages \
+                 (session_id, role, content, model, agent, created_at, parent_message_id) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+            )
+            .bind(new_sid)
+            .bind(&msg.role)
+            .bind(&msg.content)
+            .bind(&msg.model)
+            .bind(&msg.agent)
+            .bind(parse_timestamp(&msg.created_at).unwrap_or(created_at))
+            .bind(parent)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_err)?;
+            id_map.insert(msg.id.as_str(), new_mid);
+
+            for ti in &msg.tool_interactions {
+                sqlx::query(
+                    "INSERT INTO ch_tool_interactions \
+                     (message_id, tool_use_id, tool_name, tool_input, result, is_error, executed_at, \
+                      error_class) \
+                     VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, NOW()), $8)",
+                )
+                .bind(new_mid)
+                .bind(&ti.tool_use_id)
+                .bind(&ti.tool_name)
+                .bind(&ti.tool_input)
+                .bind(&ti.result)
+                .bind(ti.is_error)
+                .bind(ti.executed_at.as_deref().and_then(parse_timestamp))
+                .bind(ti.is_error.then(|| {
+                    crate::tool_telemetry::classify_error(
+                        &ti.tool_name,
+                        ti.result.as_deref().unwrap_or(""),
+                    )
+                }))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+            }
+        }
+        message_count += session.messages.len();
+
+        let head = session
+            .active_leaf_message_id
+            .as_deref()
+            .and_then(|id| id_map.get(id).copied())
+            .or_else(|| {
+                session
+                    .messages
+                    .last()
+                    .and_then(|m| id_map.get(m.id.as_str()).copied())
+            });
+        if head.is_some() {
+            sqlx::query("SELECT ch_checkout_message($1, $2)")
+                .bind(new_sid)
+                .bind(head)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+        }
+
+        for tag in &session.tags {
+            let tag = tag.trim().to_lowercase();
+            if tag.is_empty() {
+                continue;
+            }
+            sqlx::query(
+                "INSERT INTO ch_session_tags (session_id, tag) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            )
+            .bind(new_sid)
+            .bind(&tag)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        }
+
+        imported.push(json!({ "original_id": session.id, "id": new_sid.to_string() }));
+    }
+
+    tx.commit().await.map_err(db_err)?;
+
+    crate::audit::log_audit(
+        &state.db,
+        "import_sessions",
+        json!({ "sessions": imported.len(), "messages": message_count }),
+        None,
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "imported": imported,
+            "sessions": imported.len(),
+            "messages": message_count,
+        })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(id: &str, parent: Option<&str>, role: &str, content: &str) -> ExportedMessage {
+        ExportedMessage {
+            id: id.to_string(),
+            parent_id: parent.map(str::to_string),
+            role: role.to_string(),
+            content: content.to_string(),
+            model: None,
+            agent: None,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            tool_interactions: Vec::new(),
+        }
+    }
+
+    fn session(messages: Vec<ExportedMessage>, leaf: Option<&str>) -> ExportedSession {
+        ExportedSession {
+            id: "s1".to_string(),
+            title: "Test".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+            working_directory: String::new(),
+            tags: Vec::new(),
+            parent_session_id: None,
+            active_leaf_message_id: leaf.map(str::to_string),
+            models: vec!["claude-sonnet-4-6".to_string()],
+            messages,
+        }
+    }
+
+    #[test]
+    fn active_branch_follows_head_not_newest_sibling() {
+        let s = session(
+            vec![
+                msg("a", None, "user", "q"),
+                msg("b", Some("a"), "assistant", "first"),
+                msg("c", Some("a"), "assistant", "second"),
+            ],
+            Some("b"),
+        );
+        let ids: Vec<&str> = active_branch(&s).iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn transcript_pairs_tool_use_with_tool_result() {
+        let mut answer = msg("b", Some("a"), "assistant", "done");
+        answer.tool_interactions.push(ExportedToolInteraction {
+            tool_use_id: "tu_1".to_string(),
+            tool_name: "read_file".to_string(),
+            tool_input: json!({ "path": "x" }),
+            result: Some("ok".to_string()),
+            is_error: false,
+            executed_at: None,
+        });
+        let s = session(vec![msg("a", None, "user", "read x"), answer], None);
+        let body = render_anthropic_transcript(&s);
+        let roles: Vec<&str> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["user", "assistant", "user", "assistant"]);
+        assert_eq!(body["messages"][1]["content"][0]["type"], "tool_use");
+        assert_eq!(body["messages"][2]["content"][0]["tool_use_id"], "tu_1");
+    }
+
+    #[test]
+    fn validate_rejects_dangling_parent_and_bad_role() {
+        let doc = SessionExportDocument {
+            format: EXPORT_FORMAT.to_string(),
+            version: EXPORT_VERSION,
+            exported_at: String::new(),
+            sessions: vec![session(vec![msg("a", Some("zz"), "tool", "x")], None)],
+        };
+        let errors = validate_import(&doc);
+        assert!(errors.iter().any(|e| e.contains("parent_id")));
+        assert!(errors.iter().any(|e| e.contains("role")));
+    }
+
+    #[test]
+    fn validate_names_overlong_tags() {
+        let long_tag = "x".repeat(MAX_TAG_LENGTH + 1);
+        let mut tagged = session(vec![msg("a", None, "user", "hi")], None);
+        tagged.tags = vec!["ok".to_string(), long_tag.clone()];
+        let doc = SessionExportDocument {
+            format: EXPORT_FORMAT.to_string(),
+            version: EXPORT_VERSION,
+            exported_at: String::new(),
+            sessions: vec![tagged],
+        };
+        let errors = validate_import(&doc);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("sessions[0].tags[1]"));
+        assert!(errors[0].contains(&long_tag));
+    }
+
+    #[test]
+    fn export_cursor_round_trips() {
+        let created_at = chrono::DateTime::from_timestamp_micros(1_760_000_000_123_456).unwrap();
+        let id = uuid::Uuid::new_v4();
+        assert_eq!(
+            decode_cursor(&encode_cursor(created_at, id)),
+            Some((created_at, id))
+        );
+        assert_eq!(decode_cursor("not-a-cursor"), None);
+        assert_eq!(decode_cursor("12.not-a-uuid"), None);
+    }
+}
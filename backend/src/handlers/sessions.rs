@@ -4,6 +4,7 @@
 //! ClaudeHydra keeps local overrides for `get_session` and `add_session_message`
 //! because they include `ch_tool_interactions` joins and inserts — a feature
 //! specific to Claude's tool-use protocol that other Hydras don't have.
+//! `get_session` returns a single branch of the message tree (see `branches`).
 
 use axum::Json;
 use axum::extract::{Path, Query, State};
@@ -14,6 +15,7 @@ use crate::models::*;
 use crate::state::AppState;
 
 use super::MAX_MESSAGE_LENGTH;
+use super::branches::{BRANCH_PATH_CTE, load_siblings};
 
 // ── Re-export shared session types ───────────────────────────────────────────
 // These are used by lib.rs OpenAPI derive and route registration.
@@ -30,23 +32,42 @@ pub use jaskier_core::sessions::{
 
 // ═══════════════════════════════════════════════════════════════════════
 //  Get session (with paginated messages + tool interactions)
-//  LOCAL OVERRIDE — shared version lacks tool_interactions join
+//  LOCAL OVERRIDE — shared version lacks tool_interactions join and branches
 // ═══════════════════════════════════════════════════════════════════════
 
+/// Query parameters for `GET /api/sessions/{id}`.
+#[derive(Debug, serde::Deserialize)]
+pub struct GetSessionParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Show the branch ending at this message instead of the active one
+    /// (read-only — use `/checkout` to switch).
+    pub head: Option<String>,
+}
+
 #[utoipa::path(get, path = "/api/sessions/{id}", tag = "sessions",
-    params(("id" = String, Path, description = "Session UUID")),
-    responses((status = 200, description = "Session with messages")))]
+    params(
+        ("id" = String, Path, description = "Session UUID"),
+        ("head" = Option<String>, Query, description = "Branch head message (default: active branch)"),
+    ),
+    responses((status = 200, description = "Session with messages of one branch")))]
 pub async fn get_session(
     State(state): State<AppState>,
     Path(id): Path<String>,
-    Query(params): Query<PaginationParams>,
+    Query(params): Query<GetSessionParams>,
 ) -> Result<Json<Value>, StatusCode> {
     let session_id: uuid::Uuid = id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
     let msg_limit = params.limit.unwrap_or(200).clamp(1, 500);
     let msg_offset = params.offset.unwrap_or(0).max(0);
+    let requested_head: Option<uuid::Uuid> = match params.head.as_deref() {
+        Some(h) => Some(h.parse().map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
 
     let session_row = sqlx::query_as::<_, SessionRow>(
-        "SELECT id, title, created_at, updated_at, working_directory FROM ch_sessions WHERE id = $1",
+        "SELECT id, title, created_at, updated_at, working_directory, \
+             parent_session_id, forked_from_message_id, active_leaf_message_id \
+         FROM ch_sessions WHERE id = $1",
     )
     .bind(session_id)
     .fetch_optional(&state.db)
@@ -57,9 +78,13 @@ pub async fn get_session(
     })?
     .ok_or(StatusCode::NOT_FOUND)?;
 
+    let head = requested_head.or(session_row.active_leaf_message_id);
+
+    // Only the messages on the selected branch — root to head.
     let total_messages: i64 =
-        sqlx::query_scalar("SELECT COUNT(*) FROM ch_messages WHERE session_id = $1")
+        sqlx::query_scalar(&format!("{BRANCH_PATH_CTE}SELECT COUNT(*) FROM path"))
             .bind(session_id)
+            .bind(head)
             .fetch_one(&state.db)
             .await
             .map_err(|e| {
@@ -67,14 +92,23 @@ pub async fn get_session(
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
 
-    let message_rows = sqlx::query_as::<_, MessageRow>(
-        "SELECT * FROM (\
-            SELECT id, session_id, role, content, model, agent, created_at \
-            FROM ch_messages WHERE session_id = $1 \
-            ORDER BY created_at DESC LIMIT $2 OFFSET $3\
-        ) sub ORDER BY created_at ASC",
-    )
+    if requested_head.is_some() && total_messages == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let message_rows = sqlx::query_as::<_, MessageRow>(&format!(
+        "{BRANCH_PATH_CTE}\
+         SELECT id, session_id, role, content, model, agent, created_at, parent_message_id, \
+            thinking \
+         FROM (\
+            SELECT m.id, m.session_id, m.role, m.content, m.model, m.agent, m.created_at, \
+                m.parent_message_id, m.thinking, p.depth \
+            FROM path p JOIN ch_messages m ON m.id = p.id \
+            ORDER BY p.depth ASC LIMIT $3 OFFSET $4\
+         ) sub ORDER BY depth DESC"
+    ))
     .bind(session_id)
+    .bind(head)
     .bind(msg_limit)
     .bind(msg_offset)
     .fetch_all(&state.db)
@@ -90,7 +124,7 @@ pub async fn get_session(
     } else {
         sqlx::query_as::<_, ToolInteractionRow>(
             "SELECT ti.id, ti.message_id, ti.tool_use_id, ti.tool_name, \
-             ti.tool_input, ti.result, ti.is_error, ti.executed_at \
+             ti.tool_input, ti.result, ti.is_error, ti.executed_at, ti.duration_ms \
              FROM ch_tool_interactions ti \
              WHERE ti.message_id = ANY($1) \
              ORDER BY ti.executed_at ASC",
@@ -113,9 +147,12 @@ pub async fn get_session(
                 tool_input: ti.tool_input,
                 result: ti.result,
                 is_error: ti.is_error,
+                duration_ms: ti.duration_ms.map(i64::from),
             });
     }
 
+    let mut siblings = load_siblings(&state.db, session_id, &message_ids).await;
+
     let messages: Vec<HistoryEntry> = message_rows
         .into_iter()
         .map(|m| {
@@ -128,6 +165,9 @@ pub async fn get_session(
                 agent: m.agent,
                 timestamp: m.created_at.to_rfc3339(),
                 tool_interactions: interactions,
+                thinking: m.thinking,
+                parent_id: m.parent_message_id.map(|p| p.to_string()),
+                sibling_ids: siblings.remove(&m.id),
             }
         })
         .collect();
@@ -137,6 +177,10 @@ pub async fn get_session(
         "title": session_row.title,
         "created_at": session_row.created_at.to_rfc3339(),
         "working_directory": session_row.working_directory,
+        "parent_session_id": session_row.parent_session_id.map(|s| s.to_string()),
+        "forked_from_message_id": session_row.forked_from_message_id.map(|m| m.to_string()),
+        "active_leaf_message_id": session_row.active_leaf_message_id.map(|m| m.to_string()),
+        "head": head.map(|h| h.to_string()),
         "messages": serde_json::to_value(&messages).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
         "pagination": {
             "total": total_messages,
@@ -187,7 +231,7 @@ pub async fn add_session_message(
     let row = sqlx::query_as::<_, MessageRow>(
         "INSERT INTO ch_messages (session_id, role, content, model, agent) \
          VALUES ($1, $2, $3, $4, $5) \
-         RETURNING id, session_id, role, content, model, agent, created_at",
+         RETURNING id, session_id, role, content, model, agent, created_at, parent_message_id",
     )
     .bind(session_id)
     .bind(&req.role)
@@ -205,8 +249,9 @@ pub async fn add_session_message(
         for ti in interactions {
             sqlx::query(
                 "INSERT INTO ch_tool_interactions \
-                 (message_id, tool_use_id, tool_name, tool_input, result, is_error) \
-                 VALUES ($1, $2, $3, $4, $5, $6)",
+                 (message_id, tool_use_id, tool_name, tool_input, result, is_error, duration_ms, \
+                  error_class) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
             )
             .bind(row.id)
             .bind(&ti.tool_use_id)
@@ -214,6 +259,13 @@ pub async fn add_session_message(
             .bind(&ti.tool_input)
             .bind(&ti.result)
             .bind(ti.is_error)
+            .bind(ti.duration_ms.map(|d| d.clamp(0, i32::MAX as i64) as i32))
+            .bind(ti.is_error.then(|| {
+                crate::tool_telemetry::classify_error(
+                    &ti.tool_name,
+                    ti.result.as_deref().unwrap_or(""),
+                )
+            }))
             .execute(&state.db)
             .await
             .map_err(|e| {
@@ -229,6 +281,11 @@ pub async fn add_session_message(
         .await
         .ok();
 
+    crate::session_index::spawn_index_messages(
+        &state,
+        vec![(row.id, session_id, row.role.clone(), row.content.clone())],
+    );
+
     let entry = HistoryEntry {
         id: row.id.to_string(),
         role: row.role,
@@ -237,6 +294,9 @@ pub async fn add_session_message(
         agent: row.agent,
         timestamp: row.created_at.to_rfc3339(),
         tool_interactions: req.tool_interactions,
+        thinking: row.thinking,
+        parent_id: row.parent_message_id.map(|p| p.to_string()),
+        sibling_ids: None,
     };
 
     Ok((
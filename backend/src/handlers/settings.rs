@@ -1,5 +1,7 @@
 //! Application settings endpoints (DB-backed).
 
+use std::collections::HashMap;
+
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -24,7 +26,11 @@ pub async fn get_settings(State(state): State<AppState>) -> Result<Json<Value>,
          COALESCE(auto_updater, TRUE) AS auto_updater, \
          COALESCE(telemetry, FALSE) AS telemetry, \
          COALESCE(compaction_threshold, 25) AS compaction_threshold, \
-         COALESCE(compaction_keep, 15) AS compaction_keep \
+         COALESCE(compaction_keep, 15) AS compaction_keep, \
+         routing_cost_ceiling_usd, \
+         COALESCE(routing_user_cost_ceilings, '{}'::jsonb) AS routing_user_cost_ceilings, \
+         COALESCE(thinking_budgets, '{}'::jsonb) AS thinking_budgets, \
+         COALESCE(store_thinking, FALSE) AS store_thinking \
          FROM ch_settings WHERE id = 1",
     )
     .fetch_one(&state.db)
@@ -49,6 +55,10 @@ pub async fn get_settings(State(state): State<AppState>) -> Result<Json<Value>,
         telemetry: row.telemetry,
         compaction_threshold: row.compaction_threshold,
         compaction_keep: row.compaction_keep,
+        routing_cost_ceiling_usd: row.routing_cost_ceiling_usd,
+        routing_user_cost_ceilings: row.routing_user_cost_ceilings.0,
+        thinking_budgets: row.thinking_budgets.0,
+        store_thinking: row.store_thinking,
     };
 
     Ok(Json(
@@ -80,6 +90,9 @@ pub async fn update_settings(
          temperature = $8, max_tokens = $9, custom_instructions = $10, \
          auto_updater = $11, telemetry = $12, \
          compaction_threshold = $13, compaction_keep = $14, \
+         routing_cost_ceiling_usd = $15, \
+         thinking_budgets = $16, store_thinking = $17, \
+         routing_user_cost_ceilings = $18, \
          updated_at = NOW() WHERE id = 1",
     )
     .bind(&new_settings.theme)
@@ -96,6 +109,12 @@ pub async fn update_settings(
     .bind(new_settings.telemetry)
     .bind(new_settings.compaction_threshold.clamp(10, 100))
     .bind(new_settings.compaction_keep.clamp(5, 50))
+    .bind(new_settings.routing_cost_ceiling_usd.filter(|c| *c >= 0.0))
+    .bind(sqlx::types::Json(&new_settings.thinking_budgets))
+    .bind(new_settings.store_thinking)
+    .bind(sqlx::types::Json(user_cost_ceilings(
+        &new_settings.routing_user_cost_ceilings,
+    )))
     .execute(&state.db)
     .await
     .map_err(|e| {
@@ -103,6 +122,7 @@ pub async fn update_settings(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    crate::config_cache::invalidate(&state).await;
     crate::audit::log_audit(
         &state.db,
         "update_settings",
@@ -116,6 +136,16 @@ pub async fn update_settings(
     ))
 }
 
+/// Per-user ceilings keyed by lower-cased email (as `OptionalAuth` reports
+/// it); negative or non-finite limits are dropped.
+fn user_cost_ceilings(ceilings: &HashMap<String, f64>) -> HashMap<String, f64> {
+    ceilings
+        .iter()
+        .filter(|(email, usd)| !email.trim().is_empty() && usd.is_finite() && **usd >= 0.0)
+        .map(|(email, usd)| (email.trim().to_lowercase(), *usd))
+        .collect()
+}
+
 // ═══════════════════════════════════════════════════════════════════════
 //  POST /api/settings/api-key
 // ═══════════════════════════════════════════════════════════════════════
@@ -2,6 +2,18 @@
 //!
 //! Runs a non-streaming Claude conversation with the target agent's identity
 //! and tier model. Supports nested delegation up to configurable depth.
+//!
+//! Spending budgets are checked before every call and each call's usage is
+//! recorded in `ch_agent_usage` under the agent's name and parent session.
+//! Model and tool calls are also written to the execution timeline
+//! (`ch_execution_steps`) under the delegation's task id.
+//!
+//! Every lifecycle transition (created, started, iteration, tool_call and the
+//! terminal completed / failed / timed_out / cancelled) is published as a
+//! [`DelegationEvent`](crate::delegation_events::DelegationEvent) for the
+//! delegations SSE stream.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 use serde_json::{Value, json};
 
@@ -9,49 +21,207 @@ use jaskier_core::handlers::anthropic_streaming::{
     sanitize_api_error, trim_conversation, truncate_for_context_with_limit as truncate_tool_output,
 };
 
+use crate::budgets::{BUDGET_EXCEEDED, BudgetSubject, PendingUsage, RunBudgets};
+use crate::delegation_events::{self, DelegationEvent, DelegationEventKind};
 use crate::state::AppState;
+use crate::tool_telemetry::{ToolExecution, ToolOutcome};
 
+use super::helpers::{StepContext, StepTokens, record_model_step, record_tool_step};
+use super::thinking::{ThinkingConfig, resolve_thinking, thinking_blocks};
 use super::{TOOL_TIMEOUT_SECS, send_to_anthropic, truncate_for_context_with_limit};
 
+/// Wall-clock limit for a single delegation (including nested ones).
+pub(crate) const DELEGATION_TIMEOUT_SECS: u64 = 120;
+
+/// Where a delegation sits in the call tree.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DelegationParent {
+    /// Call depth of the caller (0 = top-level chat).
+    pub depth: u32,
+    /// Top-level chat execution the delegation belongs to, if known.
+    pub execution_id: Option<String>,
+    /// Delegation that issued this `call_agent` (nested calls only).
+    pub task_id: Option<uuid::Uuid>,
+    /// Chat session the call tree runs in (usage attribution, budgets).
+    pub session_id: Option<uuid::Uuid>,
+    /// Signed-in user of the top-level chat (usage attribution).
+    pub user_id: Option<String>,
+    /// Budgets of the caller, which the delegation's usage counts against too.
+    pub budgets: Option<RunBudgets>,
+}
+
+/// Optional fields of a delegation event.
+#[derive(Default)]
+struct EventDetail {
+    iteration: Option<u32>,
+    tool_name: Option<String>,
+    is_error: Option<bool>,
+    duration_ms: Option<u64>,
+    message: Option<String>,
+}
+
+/// Publishes lifecycle events for one delegation and closes its DB row.
+///
+/// If the delegation future is dropped before a terminal event (the parent
+/// execution was cancelled or timed out), `Drop` reports it as cancelled.
+struct DelegationTracker {
+    state: AppState,
+    task_id: uuid::Uuid,
+    parent_task_id: Option<uuid::Uuid>,
+    execution_id: Option<String>,
+    session_id: Option<uuid::Uuid>,
+    user_id: Option<String>,
+    parent_budgets: Option<RunBudgets>,
+    agent: String,
+    agent_tier: String,
+    model: String,
+    depth: u32,
+    started: std::time::Instant,
+    iterations: AtomicU32,
+    finished: AtomicBool,
+}
+
+impl DelegationTracker {
+    fn step_context(&self) -> StepContext {
+        StepContext {
+            execution_id: self.execution_id.clone(),
+            session_id: self.session_id,
+            delegation_task_id: Some(self.task_id),
+            agent_id: Some(self.agent.clone()),
+        }
+    }
+
+    fn emit(&self, kind: DelegationEventKind, detail: EventDetail) {
+        delegation_events::publish(
+            &self.state,
+            DelegationEvent {
+                seq: 0,
+                kind,
+                task_id: self.task_id,
+                parent_task_id: self.parent_task_id,
+                execution_id: self.execution_id.clone(),
+                agent: self.agent.clone(),
+                agent_tier: self.agent_tier.clone(),
+                model: self.model.clone(),
+                depth: self.depth,
+                iteration: detail.iteration,
+                tool_name: detail.tool_name,
+                is_error: detail.is_error,
+                duration_ms: detail.duration_ms,
+                message: detail.message,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            },
+        );
+    }
+
+    fn iteration(&self, iteration: u32) {
+        self.iterations.store(iteration, Ordering::Relaxed);
+        self.emit(
+            DelegationEventKind::Iteration,
+            EventDetail {
+                iteration: Some(iteration),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Emit the terminal event and update `ch_a2a_tasks` (fire-and-forget).
+    fn finish(&self, kind: DelegationEventKind, message: String) {
+        if self.finished.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let status = kind.terminal_status().unwrap_or("failed");
+        let is_error = kind != DelegationEventKind::Completed;
+        let elapsed = self.started.elapsed().as_millis() as u64;
+        self.emit(
+            kind,
+            EventDetail {
+                iteration: Some(self.iterations.load(Ordering::Relaxed)),
+                is_error: Some(is_error),
+                duration_ms: Some(elapsed),
+                message: Some(message.chars().take(500).collect()),
+                ..Default::default()
+            },
+        );
+
+        // Update task status in DB (clamped to i32::MAX to prevent overflow)
+        let duration_ms = elapsed.min(i32::MAX as u64) as i32;
+        let preview: String = message.chars().take(500).collect();
+        let iterations = self.iterations.load(Ordering::Relaxed) as i32;
+        let db = self.state.db.clone();
+        let task_id = self.task_id;
+        tokio::spawn(async move {
+            let _ = sqlx::query(
+                "UPDATE ch_a2a_tasks SET status = $1, result_preview = $2, duration_ms = $3, \
+                 is_error = $4, iterations_used = $5, completed_at = NOW() WHERE id = $6",
+            )
+            .bind(status)
+            .bind(&preview)
+            .bind(duration_ms)
+            .bind(is_error)
+            .bind(iterations)
+            .bind(task_id)
+            .execute(&db)
+            .await;
+        });
+    }
+}
+
+impl Drop for DelegationTracker {
+    fn drop(&mut self) {
+        if !self.finished.load(Ordering::SeqCst) {
+            self.finish(
+                DelegationEventKind::Cancelled,
+                "Delegation cancelled before completion".to_string(),
+            );
+        }
+    }
+}
+
 /// Execute a `call_agent` tool call — runs a non-streaming Claude conversation
 /// with the target agent's identity and tier model. Supports nested delegation.
+/// A delegation that runs out of time is reported as `ToolOutcome::Timeout`.
 pub(crate) async fn execute_agent_call(
     state: &AppState,
     input: &Value,
     working_directory: &str,
-    call_depth: u32,
-) -> (String, bool) {
-    // Read configurable limits from DB (with fallback defaults)
-    let (max_call_depth, agent_max_iterations) = {
-        let row: Option<(i32, i32)> = sqlx::query_as(
-            "SELECT COALESCE(agent_max_call_depth, 3), COALESCE(agent_max_iterations, 8) \
-             FROM ch_settings WHERE id = 1",
-        )
-        .fetch_optional(&state.db)
-        .await
-        .ok()
-        .flatten();
-        row.unwrap_or((3, 8))
-    };
+    parent: &DelegationParent,
+) -> (String, ToolOutcome) {
+    // Configurable limits from the cached settings snapshot
+    let config = crate::config_cache::snapshot(state).await;
+    let (max_call_depth, agent_max_iterations) = (
+        config.settings.agent_max_call_depth,
+        config.settings.agent_max_iterations,
+    );
 
-    let depth = call_depth + 1;
+    let depth = parent.depth + 1;
     if depth > max_call_depth as u32 {
         return (
             format!(
                 "Agent call depth limit ({}) reached — cannot delegate further",
                 max_call_depth
             ),
-            true,
+            ToolOutcome::Error,
         );
     }
 
     let agent_name = match input.get("agent_name").and_then(|v| v.as_str()) {
         Some(n) => n.to_lowercase(),
-        None => return ("Missing required argument: agent_name".to_string(), true),
+        None => {
+            return (
+                "Missing required argument: agent_name".to_string(),
+                ToolOutcome::Error,
+            );
+        }
     };
     let task = match input.get("task").and_then(|v| v.as_str()) {
         Some(t) => t,
-        None => return ("Missing required argument: task".to_string(), true),
+        None => {
+            return (
+                "Missing required argument: task".to_string(),
+                ToolOutcome::Error,
+            );
+        }
     };
 
     // Find agent by name (case-insensitive)
@@ -72,7 +242,7 @@ pub(crate) async fn execute_agent_call(
                         agent_name,
                         available.join(", ")
                     ),
-                    true,
+                    ToolOutcome::Error,
                 );
             }
         }
@@ -80,7 +250,15 @@ pub(crate) async fn execute_agent_call(
 
     // Get the model for the agent's tier
     let model = crate::model_registry::get_model_id(state, &agent_tier.to_lowercase()).await;
-    let max_tokens = crate::handlers::prompt::tier_token_budget(&model);
+    let max_tokens = crate::handlers::prompt::tier_token_budget(state, &model).await;
+    let thinking = resolve_thinking(
+        state,
+        &crate::model_registry::model_capabilities(state, &model).await,
+        &agent_tier,
+        Some(&agent_name),
+        max_tokens,
+    )
+    .await;
 
     tracing::info!(
         "call_agent: delegating to {} ({}, {}, depth={}) — model={}",
@@ -91,20 +269,46 @@ pub(crate) async fn execute_agent_call(
         model
     );
 
-    let task_start = std::time::Instant::now();
+    // Register the delegation and announce it
+    let task_id = uuid::Uuid::new_v4();
+    let tracker = DelegationTracker {
+        state: state.clone(),
+        task_id,
+        parent_task_id: parent.task_id,
+        execution_id: parent.execution_id.clone(),
+        session_id: parent.session_id,
+        user_id: parent.user_id.clone(),
+        parent_budgets: parent.budgets.clone(),
+        agent: agent_display_name.clone(),
+        agent_tier: agent_tier.clone(),
+        model: model.clone(),
+        depth,
+        started: std::time::Instant::now(),
+        iterations: AtomicU32::new(0),
+        finished: AtomicBool::new(false),
+    };
+    tracker.emit(
+        DelegationEventKind::Created,
+        EventDetail {
+            message: Some(task.chars().take(200).collect()),
+            ..Default::default()
+        },
+    );
 
     // Log delegation to DB (fire-and-forget)
-    let task_id = uuid::Uuid::new_v4();
     {
         let db = state.db.clone();
         let name = agent_name.clone();
         let tier = agent_tier.clone();
         let model_clone = model.clone();
         let task_clone = task.to_string();
+        let parent_task_id = parent.task_id;
+        let execution_id = parent.execution_id.clone();
         tokio::spawn(async move {
             let _ = sqlx::query(
-                "INSERT INTO ch_a2a_tasks (id, agent_name, agent_tier, task_prompt, model_used, call_depth, status) \
-                 VALUES ($1, $2, $3, $4, $5, $6, 'working')"
+                "INSERT INTO ch_a2a_tasks (id, agent_name, agent_tier, task_prompt, model_used, \
+                 call_depth, status, parent_task_id, execution_id) \
+                 VALUES ($1, $2, $3, $4, $5, $6, 'working', $7, $8)",
             )
             .bind(task_id)
             .bind(&name)
@@ -112,22 +316,19 @@ pub(crate) async fn execute_agent_call(
             .bind(&task_clone)
             .bind(&model_clone)
             .bind(depth as i32)
+            .bind(parent_task_id)
+            .bind(&execution_id)
             .execute(&db)
             .await;
         });
     }
 
     // Build agent-specific system prompt
-    let lang = {
-        let row: Option<(String,)> =
-            sqlx::query_as("SELECT COALESCE(language, 'en') FROM ch_settings WHERE id = 1")
-                .fetch_optional(&state.db)
-                .await
-                .ok()
-                .flatten();
-        row.map(|(l,)| l).unwrap_or_else(|| "en".to_string())
+    let lang_name = if config.settings.language == "pl" {
+        "Polish"
+    } else {
+        "English"
     };
-    let lang_name = if lang == "pl" { "Polish" } else { "English" };
 
     let system_prompt = format!(
         "## Identity\n\
@@ -162,10 +363,76 @@ pub(crate) async fn execute_agent_call(
         },
     );
 
+    tracker.emit(DelegationEventKind::Started, EventDetail::default());
+
+    let outcome = tokio::time::timeout(
+        std::time::Duration::from_secs(DELEGATION_TIMEOUT_SECS),
+        run_delegation(
+            state,
+            &tracker,
+            &system_prompt,
+            task,
+            working_directory,
+            max_tokens,
+            thinking,
+            agent_max_iterations,
+        ),
+    )
+    .await;
+
+    match outcome {
+        Ok(Ok(text)) if text.is_empty() => {
+            // Nothing to hand back counts as a failed delegation everywhere:
+            // the event stream, telemetry and routing feedback.
+            let msg = format!("Agent {} produced no text output", agent_display_name);
+            tracker.finish(DelegationEventKind::Failed, msg.clone());
+            (msg, ToolOutcome::Error)
+        }
+        Ok(Ok(text)) => {
+            tracker.finish(DelegationEventKind::Completed, text.clone());
+            (
+                format!(
+                    "**[Agent {} ({})]:**\n\n{}",
+                    agent_display_name, agent_role, text
+                ),
+                ToolOutcome::Ok,
+            )
+        }
+        Ok(Err(err)) => {
+            tracker.finish(DelegationEventKind::Failed, err.clone());
+            (err, ToolOutcome::Error)
+        }
+        Err(_) => {
+            let msg = format!(
+                "Agent delegation timed out after {}s",
+                DELEGATION_TIMEOUT_SECS
+            );
+            tracker.finish(DelegationEventKind::TimedOut, msg.clone());
+            (msg, ToolOutcome::Timeout)
+        }
+    }
+}
+
+/// The delegated agent's tool loop. Returns the collected text, or the
+/// tool-result error message on provider failures.
+async fn run_delegation(
+    state: &AppState,
+    tracker: &DelegationTracker,
+    system_prompt: &str,
+    task: &str,
+    working_directory: &str,
+    max_tokens: u32,
+    thinking: Option<ThinkingConfig>,
+    agent_max_iterations: i32,
+) -> Result<String, String> {
+    let agent_display_name = &tracker.agent;
+    let model = &tracker.model;
+    let depth = tracker.depth;
+
     // Build tool definitions (including MCP)
     let tool_defs: Vec<Value> = state
         .tool_executor
-        .tool_definitions_with_mcp(state, Some(&model))
+        .tool_definitions_with_mcp(state, Some(model))
         .await
         .into_iter()
         .map(|td| {
@@ -180,16 +447,45 @@ pub(crate) async fn execute_agent_call(
     let mut conversation: Vec<Value> = vec![json!({ "role": "user", "content": task })];
 
     let mut collected_text = String::new();
+    let budgets = RunBudgets::load(
+        state,
+        &BudgetSubject {
+            user: tracker.user_id.clone(),
+            session_id: tracker.session_id,
+            agent: Some(agent_display_name.clone()),
+        },
+        model,
+        tracker.parent_budgets.as_ref(),
+    )
+    .await;
+    let step_ctx = tracker.step_context();
+
+    for iter in 0..agent_max_iterations.max(0) as usize {
+        tracker.iteration(iter as u32 + 1);
 
-    for iter in 0..agent_max_iterations as usize {
-        let body = json!({
-            "model": &model,
+        // Soft limits are reported by the parent execution's own check.
+        if let Some(breach) = budgets.check(&state.budget_metrics, None).exceeded {
+            return Err(format!(
+                "[{} stopped — {}: {}]",
+                agent_display_name,
+                BUDGET_EXCEEDED,
+                breach.message()
+            ));
+        }
+
+        let mut body = json!({
+            "model": model,
             "max_tokens": max_tokens,
-            "system": &system_prompt,
+            "system": system_prompt,
             "messages": &conversation,
             "tools": &tool_defs,
         });
+        if let Some(thinking) = thinking {
+            thinking.apply(&mut body);
+        }
 
+        let call_started_at = chrono::Utc::now();
+        let call_start = std::time::Instant::now();
         let resp = match send_to_anthropic(state, &body, 120).await {
             Ok(r) => r,
             Err((_, axum::Json(err_val))) => {
@@ -202,10 +498,10 @@ pub(crate) async fn execute_agent_call(
                     agent_display_name,
                     raw_msg
                 );
-                return (
-                    format!("[{} error: AI provider request failed]", agent_display_name),
-                    true,
-                );
+                return Err(format!(
+                    "[{} error: AI provider request failed]",
+                    agent_display_name
+                ));
             }
         };
 
@@ -219,7 +515,7 @@ pub(crate) async fn execute_agent_call(
                 &truncate_for_context_with_limit(&err, 500)
             );
             let safe_err = sanitize_api_error(&err);
-            return (format!("[{} {}]", agent_display_name, safe_err), true);
+            return Err(format!("[{} {}]", agent_display_name, safe_err));
         }
 
         let resp_json: Value = match resp.json().await {
@@ -230,15 +526,28 @@ pub(crate) async fn execute_agent_call(
                     agent_display_name,
                     e
                 );
-                return (
-                    format!(
-                        "[{} error: failed to parse AI response]",
-                        agent_display_name
-                    ),
-                    true,
-                );
+                return Err(format!(
+                    "[{} error: failed to parse AI response]",
+                    agent_display_name
+                ));
             }
         };
+        let recorded = record_delegation_usage(
+            state,
+            tracker,
+            &step_ctx,
+            iter as u32 + 1,
+            &resp_json,
+            call_started_at,
+            call_start.elapsed().as_millis(),
+        )
+        .await;
+        budgets.add_recorded(PendingUsage {
+            input_tokens: recorded.input.into(),
+            output_tokens: recorded.output.into(),
+            cache_read_tokens: recorded.cache_read.into(),
+            cache_write_tokens: recorded.cache_write.into(),
+        });
 
         let stop_reason = resp_json
             .get("stop_reason")
@@ -248,6 +557,7 @@ pub(crate) async fn execute_agent_call(
 
         let mut text_parts = Vec::new();
         let mut tool_uses: Vec<Value> = Vec::new();
+        let thinking_blocks = thinking_blocks(content.map_or(&[][..], Vec::as_slice));
 
         if let Some(blocks) = content {
             for block in blocks {
@@ -268,8 +578,8 @@ pub(crate) async fn execute_agent_call(
         }
 
         if stop_reason == "tool_use" && !tool_uses.is_empty() {
-            // Build assistant message
-            let mut assistant_blocks: Vec<Value> = Vec::new();
+            // Build assistant message (signed thinking blocks first, unmodified)
+            let mut assistant_blocks: Vec<Value> = thinking_blocks;
             for t in &text_parts {
                 assistant_blocks.push(json!({ "type": "text", "text": t }));
             }
@@ -283,20 +593,36 @@ pub(crate) async fn execute_agent_call(
                 let tool_id = tu.get("id").and_then(|i| i.as_str()).unwrap_or("");
                 let empty = json!({});
                 let tool_input = tu.get("input").unwrap_or(&empty);
+                let tool_started_at = chrono::Utc::now();
+                let tool_start = std::time::Instant::now();
 
-                let (result, is_error) = if tool_name == "call_agent" {
+                let (result, outcome) = if tool_name == "call_agent" {
                     // Recursive delegation
                     Box::pin(execute_agent_call(
                         state,
                         tool_input,
                         working_directory,
-                        depth,
+                        &DelegationParent {
+                            depth,
+                            execution_id: tracker.execution_id.clone(),
+                            task_id: Some(tracker.task_id),
+                            session_id: tracker.session_id,
+                            user_id: tracker.user_id.clone(),
+                            budgets: Some(budgets.clone()),
+                        },
                     ))
                     .await
                 } else {
                     let executor = state
                         .tool_executor
-                        .with_working_directory(working_directory);
+                        .with_working_directory(working_directory)
+                        .with_run_context(
+                            tracker.session_id.map(|s| s.to_string()),
+                            tracker
+                                .execution_id
+                                .clone()
+                                .or_else(|| Some(tracker.task_id.to_string())),
+                        );
                     let timeout = std::time::Duration::from_secs(TOOL_TIMEOUT_SECS);
                     match tokio::time::timeout(
                         timeout,
@@ -304,12 +630,41 @@ pub(crate) async fn execute_agent_call(
                     )
                     .await
                     {
-                        Ok(res) => res,
-                        Err(_) => (format!("Tool '{}' timed out", tool_name), true),
+                        Ok((result, is_error)) => (result, ToolOutcome::from_error(is_error)),
+                        Err(_) => (
+                            format!("Tool '{}' timed out", tool_name),
+                            ToolOutcome::Timeout,
+                        ),
                     }
                 };
+                let is_error = outcome.is_error();
 
+                let tool_duration_ms = tool_start.elapsed().as_millis();
                 let truncated = truncate_tool_output(&result, 15000);
+                record_tool_step(
+                    state,
+                    &step_ctx,
+                    iter as u32 + 1,
+                    &ToolExecution {
+                        tool_name,
+                        outcome,
+                        result: &result,
+                        truncated: *truncated != *result,
+                        started_at: tool_started_at,
+                        duration_ms: tool_duration_ms,
+                    },
+                );
+                tracker.emit(
+                    DelegationEventKind::ToolCall,
+                    EventDetail {
+                        iteration: Some(iter as u32 + 1),
+                        tool_name: Some(tool_name.to_string()),
+                        is_error: Some(is_error),
+                        duration_ms: Some(tool_duration_ms as u64),
+                        ..Default::default()
+                    },
+                );
+
                 tool_results.push(json!({
                     "type": "tool_result",
                     "tool_use_id": tool_id,
@@ -337,42 +692,71 @@ pub(crate) async fn execute_agent_call(
         break;
     }
 
-    // Update task status in DB (clamped to i32::MAX to prevent overflow)
-    let duration_ms = task_start.elapsed().as_millis().min(i32::MAX as u128) as i32;
-    let is_error = collected_text.is_empty();
-    let preview: String = collected_text.chars().take(500).collect();
-    {
-        let db = state.db.clone();
-        tokio::spawn(async move {
-            let _ = sqlx::query(
-                "UPDATE ch_a2a_tasks SET status = $1, result_preview = $2, duration_ms = $3, \
-                 is_error = $4, completed_at = NOW() WHERE id = $5",
-            )
-            .bind(if is_error { "failed" } else { "completed" })
-            .bind(&preview)
-            .bind(duration_ms)
-            .bind(is_error)
-            .bind(task_id)
-            .execute(&db)
-            .await;
-        });
-    }
-
-    if collected_text.is_empty() {
-        return (
-            format!(
-                "[{} completed the task but produced no text output]",
-                agent_display_name
-            ),
-            false,
-        );
-    }
+    Ok(collected_text)
+}
 
-    (
-        format!(
-            "**[Agent {} ({})]:**\n\n{}",
-            agent_display_name, agent_role, collected_text
-        ),
+/// Record the token usage reported by one delegated Anthropic call, in
+/// `ch_agent_usage` and as a timeline step. Returns the recorded tokens.
+async fn record_delegation_usage(
+    state: &AppState,
+    tracker: &DelegationTracker,
+    step_ctx: &StepContext,
+    iteration: u32,
+    resp_json: &Value,
+    started_at: chrono::DateTime<chrono::Utc>,
+    latency_ms: u128,
+) -> StepTokens {
+    let usage = resp_json.get("usage");
+    let tokens = |key: &str| {
+        usage
+            .and_then(|u| u.get(key))
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0)
+            .min(i32::MAX as i64) as i32
+    };
+    let input_tokens = tokens("input_tokens");
+    let output_tokens = tokens("output_tokens");
+    let step_tokens = StepTokens {
+        input: input_tokens,
+        output: output_tokens,
+        cache_read: tokens("cache_read_input_tokens"),
+        cache_write: tokens("cache_creation_input_tokens"),
+    };
+    record_model_step(
+        state,
+        step_ctx,
+        iteration,
+        &tracker.model,
+        step_tokens,
         false,
+        started_at,
+        latency_ms,
+    );
+
+    if let Err(e) = sqlx::query(
+        "INSERT INTO ch_agent_usage \
+         (agent_id, model, input_tokens, output_tokens, total_tokens, latency_ms, success, tier, \
+          cache_read_tokens, cache_write_tokens, session_id, execution_id, delegation_task_id, \
+          user_id) \
+         VALUES ($1, $2, $3, $4, $5, $6, TRUE, $7, $8, $9, $10, $11, $12, $13)",
     )
+    .bind(&tracker.agent)
+    .bind(&tracker.model)
+    .bind(input_tokens)
+    .bind(output_tokens)
+    .bind(input_tokens.saturating_add(output_tokens))
+    .bind(latency_ms.min(i32::MAX as u128) as i32)
+    .bind(tracker.agent_tier.to_lowercase())
+    .bind(step_tokens.cache_read)
+    .bind(step_tokens.cache_write)
+    .bind(tracker.session_id)
+    .bind(&tracker.execution_id)
+    .bind(tracker.task_id)
+    .bind(&tracker.user_id)
+    .execute(&state.db)
+    .await
+    {
+        tracing::error!("Failed to record delegation usage: {}", e);
+    }
+    step_tokens
 }
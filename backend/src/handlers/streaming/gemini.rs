@@ -8,6 +8,7 @@ use serde_json::{Value, json};
 
 use jaskier_core::handlers::anthropic_streaming::{build_ndjson_response, sanitize_api_error};
 
+use crate::ai_gateway::vault_guard::CredentialSource;
 use crate::models::*;
 use crate::state::AppState;
 
@@ -18,6 +19,10 @@ pub(crate) async fn google_chat_stream(
     req: ChatRequest,
     ctx: ChatContext,
 ) -> Result<Response, (StatusCode, Json<Value>)> {
+    if let Err(lockout) = state.ai_gateway.vault_guard.check() {
+        return Err(crate::handlers::anthropic_client::lockout_error(&lockout));
+    }
+
     let credential = jaskier_net_sec::oauth::google::get_google_credential(&state).await;
     let (api_key, is_oauth) = match credential {
         Some(c) => c,
@@ -28,6 +33,14 @@ pub(crate) async fn google_chat_stream(
             ));
         }
     };
+    if !is_oauth
+        && let Err(e) = state
+            .ai_gateway
+            .vault_guard
+            .check_api_key("google", CredentialSource::EnvKey)
+    {
+        return Err((StatusCode::UNAUTHORIZED, Json(json!({ "error": e }))));
+    }
 
     let model = &ctx.model;
     let url = format!(
@@ -3,8 +3,10 @@
 
 use serde_json::{Value, json};
 
+use crate::handlers::branches::{BRANCH_PATH_CTE, active_head};
 use crate::models::*;
 use crate::state::AppState;
+use crate::tool_telemetry::{ToolExecution, ToolOutcome};
 
 // ═══════════════════════════════════════════════════════════════════════
 //  Post-task MCP notification (fire-and-forget)
@@ -34,10 +36,18 @@ pub(crate) async fn send_task_complete_notification(state: &AppState, model: &st
 // ═══════════════════════════════════════════════════════════════════════
 
 pub(crate) async fn load_session_history(db: &sqlx::PgPool, sid: &uuid::Uuid) -> Vec<Value> {
-    let mut messages: Vec<Value> = sqlx::query_as::<_, (String, String)>(
-        "SELECT role, content FROM ch_messages WHERE session_id = $1 ORDER BY created_at DESC LIMIT 20",
-    )
+    // Only the active branch — sibling branches (edits, regenerations) are not context.
+    let head = match active_head(db, *sid).await {
+        Ok(Some(head)) => head,
+        _ => return Vec::new(),
+    };
+    let mut messages: Vec<Value> = sqlx::query_as::<_, (String, String)>(&format!(
+        "{BRANCH_PATH_CTE}\
+         SELECT m.role, m.content FROM path p JOIN ch_messages m ON m.id = p.id \
+         ORDER BY p.depth ASC LIMIT 20"
+    ))
     .bind(sid)
+    .bind(head)
     .fetch_all(db)
     .await
     .unwrap_or_default()
@@ -156,31 +166,376 @@ pub(crate) fn detect_view_hints(prompt: &str) -> Vec<String> {
 // ═══════════════════════════════════════════════════════════════════════
 
 /// Store user prompt + assistant response to DB for a WebSocket session.
+///
+/// Returns the id of the stored assistant message, if any.
 pub(crate) async fn store_ws_messages(
     state: &AppState,
     session_id: &uuid::Uuid,
     user_prompt: &str,
     assistant_text: &str,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        "INSERT INTO ch_messages (id, session_id, role, content, created_at) VALUES ($1, $2, 'user', $3, NOW())",
-    )
-    .bind(uuid::Uuid::new_v4())
-    .bind(session_id)
-    .bind(user_prompt)
-    .execute(&state.db)
-    .await?;
+    thinking: Option<String>,
+) -> Result<Option<uuid::Uuid>, sqlx::Error> {
+    // The prompt may already be stored as the unanswered head (an edit stored
+    // over REST) — answer it instead of storing a second copy.
+    let mut stored = Vec::new();
+    if crate::handlers::branches::pending_user_turn(&state.db, *session_id, user_prompt)
+        .await?
+        .is_none()
+    {
+        let user_message_id = uuid::Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO ch_messages (id, session_id, role, content, created_at) VALUES ($1, $2, 'user', $3, NOW())",
+        )
+        .bind(user_message_id)
+        .bind(session_id)
+        .bind(user_prompt)
+        .execute(&state.db)
+        .await?;
+        stored.push((
+            user_message_id,
+            *session_id,
+            "user".to_string(),
+            user_prompt.to_string(),
+        ));
+    }
+
+    let mut assistant_id = None;
 
     if !assistant_text.is_empty() {
+        // Thinking summaries are kept only when enabled in settings.
+        let store_thinking = crate::config_cache::snapshot(state)
+            .await
+            .settings
+            .store_thinking;
+        let assistant_message_id = uuid::Uuid::new_v4();
         sqlx::query(
-            "INSERT INTO ch_messages (id, session_id, role, content, created_at) VALUES ($1, $2, 'assistant', $3, NOW())",
+            "INSERT INTO ch_messages (id, session_id, role, content, thinking, created_at) \
+             VALUES ($1, $2, 'assistant', $3, $4, NOW())",
         )
-        .bind(uuid::Uuid::new_v4())
+        .bind(assistant_message_id)
         .bind(session_id)
         .bind(assistant_text)
+        .bind(thinking.filter(|_| store_thinking))
         .execute(&state.db)
         .await?;
+        stored.push((
+            assistant_message_id,
+            *session_id,
+            "assistant".to_string(),
+            assistant_text.to_string(),
+        ));
+        assistant_id = Some(assistant_message_id);
+    }
+
+    crate::session_index::spawn_index_messages(state, stored);
+
+    Ok(assistant_id)
+}
+
+/// Usage tier label stored in `ch_agent_usage.tier`.
+pub(crate) fn usage_tier(model: &str) -> &'static str {
+    if model.contains("opus") {
+        "commander"
+    } else if model.contains("sonnet") {
+        "coordinator"
+    } else if model.contains("haiku") {
+        "executor"
+    } else if model.contains("flash") {
+        "flash"
+    } else {
+        "coordinator"
+    }
+}
+
+/// `ch_agent_usage.agent_id` of top-level executions; delegated agents
+/// record their own name.
+pub(crate) const TOP_LEVEL_AGENT_ID: &str = "orchestrator";
+
+/// Rows an execution reports its outcome to.
+#[derive(Debug, Default)]
+pub(crate) struct ExecutionTracking {
+    pub experiment: Option<crate::handlers::experiments::ExperimentExposure>,
+    /// `ch_routing_decisions.id` of an auto-routed execution.
+    pub routing_decision_id: Option<i64>,
+    /// Signed-in user the usage is attributed to (None = anonymous).
+    pub user_id: Option<String>,
+}
+
+/// Request context of an NDJSON chat stream (`AppState::ndjson_request`).
+#[derive(Debug)]
+pub(crate) struct NdjsonRequest {
+    pub session_id: Option<uuid::Uuid>,
+    /// Signed-in user the usage is attributed to (None = anonymous).
+    pub user_id: Option<String>,
+    /// Checked before every Anthropic call of the stream.
+    pub budgets: crate::budgets::RunBudgets,
+}
+
+/// Outcome of a completed WebSocket execution.
+pub(crate) struct WsExecutionOutcome<'a> {
+    pub execution_id: &'a str,
+    pub session_id: Option<uuid::Uuid>,
+    pub model: &'a str,
+    pub prompt_len: usize,
+    /// Input tokens sent over all API calls (0 = unknown, estimated from the prompt).
+    pub input_tokens: u32,
+    pub output_chars: usize,
+    /// Output tokens reported by the API (0 = unknown, estimated from chars).
+    pub output_tokens: u32,
+    pub latency_ms: u128,
+    pub tool_calls: u32,
+    pub tool_errors: u32,
+    pub assistant_message_id: Option<uuid::Uuid>,
+}
+
+impl WsExecutionOutcome<'_> {
+    fn token_counts(&self) -> (i32, i32) {
+        let input_tokens = if self.input_tokens > 0 {
+            self.input_tokens as i32
+        } else {
+            (self.prompt_len / 4) as i32
+        };
+        let output_tokens = if self.output_tokens > 0 {
+            self.output_tokens as i32
+        } else {
+            (self.output_chars / 4) as i32
+        };
+        (input_tokens, output_tokens)
+    }
+}
+
+/// Insert the `ch_agent_usage` row of a WebSocket execution.
+async fn insert_ws_usage(
+    state: &AppState,
+    tracking: &ExecutionTracking,
+    outcome: &WsExecutionOutcome<'_>,
+    success: bool,
+    exposure_id: Option<i64>,
+) {
+    let (input_tokens, output_tokens) = outcome.token_counts();
+    if let Err(e) = sqlx::query(
+        "INSERT INTO ch_agent_usage \
+         (agent_id, model, input_tokens, output_tokens, total_tokens, latency_ms, success, tier, \
+          exposure_id, session_id, execution_id, user_id) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+    )
+    .bind(TOP_LEVEL_AGENT_ID)
+    .bind(outcome.model)
+    .bind(input_tokens)
+    .bind(output_tokens)
+    .bind(input_tokens + output_tokens)
+    .bind(outcome.latency_ms.min(i32::MAX as u128) as i32)
+    .bind(success)
+    .bind(usage_tier(outcome.model))
+    .bind(exposure_id)
+    .bind(outcome.session_id)
+    .bind(outcome.execution_id)
+    .bind(&tracking.user_id)
+    .execute(&state.db)
+    .await
+    {
+        tracing::error!("Failed to record WS usage: {}", e);
     }
+}
 
-    Ok(())
+/// Record the usage of an execution stopped before completion (budget hard
+/// limit), so the tokens it already consumed count against later checks.
+pub(crate) async fn record_ws_partial_usage(
+    state: &AppState,
+    tracking: &ExecutionTracking,
+    outcome: WsExecutionOutcome<'_>,
+) {
+    insert_ws_usage(state, tracking, &outcome, false, None).await;
+}
+
+/// Record token usage of a completed WebSocket execution, link it to the
+/// experiment exposure (if any) and report the routing outcome.
+pub(crate) async fn record_ws_outcome(
+    state: &AppState,
+    tracking: &ExecutionTracking,
+    outcome: WsExecutionOutcome<'_>,
+) {
+    let exposure_id = tracking.experiment.as_ref().map(|e| e.exposure_id);
+    insert_ws_usage(state, tracking, &outcome, true, exposure_id).await;
+
+    if let Some(exposure_id) = exposure_id {
+        crate::handlers::experiments::complete_exposure(
+            state,
+            exposure_id,
+            outcome.execution_id,
+            outcome.assistant_message_id,
+            outcome.tool_calls,
+            outcome.tool_errors,
+        )
+        .await;
+    }
+
+    if let Some(decision_id) = tracking.routing_decision_id {
+        crate::tier_routing::complete_decision(
+            state,
+            decision_id,
+            outcome.execution_id,
+            outcome.latency_ms,
+            outcome.tool_calls,
+            outcome.tool_errors,
+        )
+        .await;
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Execution timeline (ch_execution_steps, fire-and-forget)
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Where a step sits in the call tree.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StepContext {
+    pub execution_id: Option<String>,
+    pub session_id: Option<uuid::Uuid>,
+    /// Set for steps of a delegated agent (`call_agent`).
+    pub delegation_task_id: Option<uuid::Uuid>,
+    pub agent_id: Option<String>,
+}
+
+/// Token counts of one model call.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct StepTokens {
+    pub input: i32,
+    pub output: i32,
+    pub cache_read: i32,
+    pub cache_write: i32,
+}
+
+/// Telemetry of a tool-call step.
+struct ToolStepTelemetry {
+    outcome: ToolOutcome,
+    error_class: Option<&'static str>,
+    result_bytes: i32,
+    truncated: bool,
+}
+
+/// One `ch_execution_steps` row.
+struct StepRow {
+    kind: &'static str,
+    iteration: u32,
+    model: Option<String>,
+    tool_name: Option<String>,
+    is_error: bool,
+    tokens: StepTokens,
+    started_at: chrono::DateTime<chrono::Utc>,
+    duration_ms: u128,
+    tool: Option<ToolStepTelemetry>,
+}
+
+fn insert_step(state: &AppState, ctx: &StepContext, row: StepRow) {
+    let state = state.clone();
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        let duration_ms = row.duration_ms.min(i32::MAX as u128) as i32;
+        let mut mcp_server = None;
+        if let (Some(tool_name), Some(tool)) = (&row.tool_name, &row.tool) {
+            mcp_server = crate::tool_telemetry::mcp_server_of(&state, tool_name).await;
+            state.tool_metrics.observe(
+                tool_name,
+                mcp_server.as_deref(),
+                tool.outcome,
+                tool.error_class,
+                duration_ms as u64,
+            );
+        }
+
+        if let Err(e) = sqlx::query(
+            "INSERT INTO ch_execution_steps \
+             (execution_id, session_id, delegation_task_id, agent_id, kind, iteration, model, \
+              tool_name, is_error, input_tokens, output_tokens, cache_read_tokens, \
+              cache_write_tokens, duration_ms, started_at, outcome, error_class, result_bytes, \
+              truncated, mcp_server) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, \
+                     $18, $19, $20)",
+        )
+        .bind(&ctx.execution_id)
+        .bind(ctx.session_id)
+        .bind(ctx.delegation_task_id)
+        .bind(&ctx.agent_id)
+        .bind(row.kind)
+        .bind(row.iteration as i32)
+        .bind(&row.model)
+        .bind(&row.tool_name)
+        .bind(row.is_error)
+        .bind(row.tokens.input)
+        .bind(row.tokens.output)
+        .bind(row.tokens.cache_read)
+        .bind(row.tokens.cache_write)
+        .bind(duration_ms)
+        .bind(row.started_at)
+        .bind(row.tool.as_ref().map(|t| t.outcome.as_str()))
+        .bind(row.tool.as_ref().and_then(|t| t.error_class))
+        .bind(row.tool.as_ref().map(|t| t.result_bytes))
+        .bind(row.tool.as_ref().is_some_and(|t| t.truncated))
+        .bind(&mcp_server)
+        .execute(&state.db)
+        .await
+        {
+            tracing::warn!("Failed to record execution step: {}", e);
+        }
+    });
+}
+
+/// Record one model call of an execution timeline.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_model_step(
+    state: &AppState,
+    ctx: &StepContext,
+    iteration: u32,
+    model: &str,
+    tokens: StepTokens,
+    is_error: bool,
+    started_at: chrono::DateTime<chrono::Utc>,
+    duration_ms: u128,
+) {
+    insert_step(
+        state,
+        ctx,
+        StepRow {
+            kind: "model_call",
+            iteration,
+            model: Some(model.to_string()),
+            tool_name: None,
+            is_error,
+            tokens,
+            started_at,
+            duration_ms,
+            tool: None,
+        },
+    );
+}
+
+/// Record one tool call of an execution timeline and its telemetry
+/// (see [`crate::tool_telemetry`]).
+pub(crate) fn record_tool_step(
+    state: &AppState,
+    ctx: &StepContext,
+    iteration: u32,
+    execution: &ToolExecution<'_>,
+) {
+    insert_step(
+        state,
+        ctx,
+        StepRow {
+            kind: "tool_call",
+            iteration,
+            model: None,
+            tool_name: Some(execution.tool_name.to_string()),
+            is_error: execution.outcome.is_error(),
+            tokens: StepTokens::default(),
+            started_at: execution.started_at,
+            duration_ms: execution.duration_ms,
+            tool: Some(ToolStepTelemetry {
+                outcome: execution.outcome,
+                error_class: execution.error_class(),
+                result_bytes: execution.result.len().min(i32::MAX as usize) as i32,
+                truncated: execution.truncated,
+            }),
+        },
+    );
 }
@@ -7,6 +7,7 @@
 //! - `gemini` — Gemini hybrid streaming (Google API SSE -> NDJSON)
 //! - `websocket` — WebSocket streaming with rich protocol
 //! - `agent_call` — Agent-to-Agent delegation (call_agent tool)
+//! - `thinking` — extended thinking budgets and thinking-block bookkeeping
 //!
 //! BE-CH-003: NDJSON streaming uses `jaskier_core::handlers::anthropic_streaming`
 //! shared handler with `HasAnthropicStreamingState` trait. WebSocket + A2A delegation
@@ -15,6 +16,7 @@
 pub mod agent_call;
 mod gemini;
 pub mod helpers;
+pub(crate) mod thinking;
 mod trait_impl;
 pub mod websocket;
 
@@ -27,17 +29,22 @@ use jaskier_core::handlers::anthropic_streaming::{
     self, AnthropicChatContext, dynamic_max_iterations,
 };
 
+use crate::budgets::{BUDGET_EXCEEDED, BudgetSubject, RunBudgets};
+use crate::extractor::OptionalAuth;
 use crate::models::*;
 use crate::state::AppState;
 
 // Re-export so sub-modules (agent_call, trait_impl, websocket/execute*) can use
 // `super::send_to_anthropic` as before. The implementation now lives in `anthropic_client`.
 pub(crate) use super::anthropic_client::send_to_anthropic;
-use super::prompt::resolve_chat_context;
+use super::prompt::{CapabilityMismatch, ChatContext, MODEL_CAPABILITY, resolve_chat_context};
 use super::{
     TOOL_TIMEOUT_SECS, is_retryable_status, sanitize_json_strings, truncate_for_context_with_limit,
 };
-use helpers::{detect_view_hints, filter_client_system_prompt, load_session_history};
+use crate::tier_routing::ChatTransport;
+use helpers::{
+    NdjsonRequest, detect_view_hints, filter_client_system_prompt, load_session_history,
+};
 
 // ── Public re-exports ────────────────────────────────────────────────────
 
@@ -65,21 +72,31 @@ pub async fn prefetch_hints(Json(body): Json<Value>) -> Json<Value> {
     request_body = ChatRequest,
     responses((status = 200, description = "Streaming NDJSON response")))]
 pub async fn claude_chat_stream(
-    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::State(mut state): axum::extract::State<AppState>,
+    auth: OptionalAuth,
     Json(req): Json<ChatRequest>,
 ) -> Result<Response, (StatusCode, Json<Value>)> {
     // Gate: if tools_enabled, route to agentic handler
     if req.tools_enabled.unwrap_or(false) {
-        return claude_chat_stream_with_tools(state, req).await;
+        return claude_chat_stream_with_tools(state, req, auth.email).await;
     }
 
-    let ctx = resolve_chat_context(&state, &req).await;
+    let ctx = resolve_chat_context(
+        &state,
+        &req,
+        ChatTransport::NdjsonText,
+        auth.email.as_deref(),
+    )
+    .await
+    .map_err(capability_error)?;
     tracing::info!(
         session_id = ?ctx.session_id,
         wd = %ctx.working_directory,
         model = %ctx.model,
+        context_window = ctx.capabilities.context_window,
         "chat stream (no-tools)"
     );
+    attach_ndjson_request(&mut state, &ctx).await?;
 
     // Hybrid routing: Gemini models -> Google API
     if ctx.model.starts_with("gemini-") {
@@ -104,16 +121,61 @@ pub async fn claude_chat_stream(
         .await
 }
 
+/// Put the request's session, user and budgets on its state clone, where the
+/// shared handler's trait calls find them. A hard budget that is already
+/// spent rejects the request (402, `BUDGET_EXCEEDED`).
+async fn attach_ndjson_request(
+    state: &mut AppState,
+    ctx: &ChatContext,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    let budgets = RunBudgets::load(
+        state,
+        &BudgetSubject {
+            user: ctx.user_id.clone(),
+            session_id: ctx.session_id,
+            agent: None,
+        },
+        &ctx.model,
+        None,
+    )
+    .await;
+    if let Some(breach) = budgets.check(&state.budget_metrics, None).exceeded {
+        return Err((
+            StatusCode::PAYMENT_REQUIRED,
+            Json(json!({ "error": breach.message(), "code": BUDGET_EXCEEDED })),
+        ));
+    }
+    state.ndjson_request = Some(std::sync::Arc::new(NdjsonRequest {
+        session_id: ctx.session_id,
+        user_id: ctx.user_id.clone(),
+        budgets,
+    }));
+    Ok(())
+}
+
+/// 422 for a request the selected model cannot serve (e.g. images without vision).
+fn capability_error(mismatch: CapabilityMismatch) -> (StatusCode, Json<Value>) {
+    tracing::warn!(model = %mismatch.model, "chat stream: {}", mismatch.message);
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "error": mismatch.message, "code": MODEL_CAPABILITY })),
+    )
+}
+
 // ═══════════════════════════════════════════════════════════════════════
 //  Claude Streaming with Tools (agentic tool_use loop)
 //  BE-CH-003: Delegates to shared anthropic_streaming handler
 // ═══════════════════════════════════════════════════════════════════════
 
 async fn claude_chat_stream_with_tools(
-    state: AppState,
+    mut state: AppState,
     req: ChatRequest,
+    user_id: Option<String>,
 ) -> Result<Response, (StatusCode, Json<Value>)> {
-    let ctx = resolve_chat_context(&state, &req).await;
+    let ctx = resolve_chat_context(&state, &req, ChatTransport::NdjsonTools, user_id.as_deref())
+        .await
+        .map_err(capability_error)?;
+    attach_ndjson_request(&mut state, &ctx).await?;
 
     // Dynamic iteration cap based on prompt complexity
     let prompt_len = req.messages.last().map(|m| m.content.len()).unwrap_or(0);
@@ -131,6 +193,13 @@ async fn claude_chat_stream_with_tools(
         filter_client_system_prompt(&req.messages)
     };
 
+    // The shared handler's `execute_tool` gets no ids, so tool runs of this
+    // loop take the session / execution from the executor they are given.
+    state.tool_executor = std::sync::Arc::new(state.tool_executor.with_run_context(
+        ctx.session_id.map(|s| s.to_string()),
+        Some(uuid::Uuid::new_v4().to_string()),
+    ));
+
     let shared_ctx = AnthropicChatContext {
         model: ctx.model,
         max_tokens: ctx.max_tokens,
@@ -0,0 +1,240 @@
+//! Extended thinking for CH's own Anthropic loops (WebSocket tool loop,
+//! WebSocket no-tools path, `call_agent` delegation).
+//!
+//! - `resolve_thinking` — budget from settings (agent → tier) for models whose
+//!   capabilities include extended thinking
+//! - `ThinkingConfig::apply` — adds the `thinking` block to a request body
+//! - `ThinkingCollector` — rebuilds thinking blocks (with signatures) from the
+//!   SSE events the shared `AnthropicSseParser` does not surface
+//! - `thinking_blocks` — the same for non-streaming responses (delegation)
+//!
+//! The API requires the thinking blocks of an assistant turn that ended in
+//! `tool_use` to be sent back unmodified, ahead of the `tool_use` blocks, while
+//! the loop continues with tool results.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Value, json};
+
+use crate::model_registry::ModelCapabilities;
+use crate::state::AppState;
+
+/// Smallest `budget_tokens` the API accepts.
+const MIN_BUDGET_TOKENS: u32 = 1024;
+/// Answer tokens kept free when the budget has to be squeezed into `max_tokens`.
+const MIN_ANSWER_TOKENS: u32 = 1024;
+/// Stored thinking summaries are capped at this many characters.
+const MAX_STORED_CHARS: usize = 20_000;
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Request configuration
+// ═══════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ThinkingConfig {
+    pub budget_tokens: u32,
+    /// `max_tokens` of the request (thinking counts against it).
+    pub max_tokens: u32,
+}
+
+impl ThinkingConfig {
+    /// Fit `budget` on top of the answer's `max_tokens` within the model's
+    /// output limit. `None` when thinking is off or does not fit.
+    pub fn plan(budget: u32, max_tokens: u32, max_output_tokens: u32) -> Option<Self> {
+        if budget < MIN_BUDGET_TOKENS {
+            return None;
+        }
+        let total = max_tokens.saturating_add(budget).min(max_output_tokens);
+        let budget = budget.min(total.saturating_sub(MIN_ANSWER_TOKENS));
+        (budget >= MIN_BUDGET_TOKENS).then_some(Self {
+            budget_tokens: budget,
+            max_tokens: total,
+        })
+    }
+
+    /// Enable thinking on `body`. Temperature is dropped: thinking only runs
+    /// with the default.
+    pub fn apply(self, body: &mut Value) {
+        body["max_tokens"] = json!(self.max_tokens);
+        body["thinking"] = json!({ "type": "enabled", "budget_tokens": self.budget_tokens });
+        if let Some(obj) = body.as_object_mut() {
+            obj.remove("temperature");
+        }
+    }
+}
+
+/// Thinking for a call to a model with `caps`, budgeted for `tier` / `agent`
+/// in settings.
+pub(crate) async fn resolve_thinking(
+    state: &AppState,
+    caps: &ModelCapabilities,
+    tier: &str,
+    agent: Option<&str>,
+    max_tokens: u32,
+) -> Option<ThinkingConfig> {
+    if !caps.extended_thinking {
+        return None;
+    }
+    let config = crate::config_cache::snapshot(state).await;
+    let budget = config.settings.thinking_budgets.budget_for(tier, agent);
+    ThinkingConfig::plan(budget, max_tokens, caps.max_output_tokens)
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Response handling
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Collects thinking blocks from raw Anthropic SSE events.
+#[derive(Debug, Default)]
+pub(crate) struct ThinkingCollector {
+    /// Blocks of the current response by content index.
+    blocks: BTreeMap<u64, Value>,
+    /// Thinking text of every response seen (for storage).
+    text: String,
+}
+
+impl ThinkingCollector {
+    /// Track one SSE event; returns a thinking delta to forward to the client.
+    pub fn observe(&mut self, event: &Value) -> Option<String> {
+        let index = event.get("index").and_then(Value::as_u64);
+        match event.get("type").and_then(Value::as_str)? {
+            "content_block_start" => {
+                let block = event.get("content_block")?;
+                match block.get("type").and_then(Value::as_str)? {
+                    "thinking" => {
+                        self.blocks.insert(
+                            index?,
+                            json!({ "type": "thinking", "thinking": "", "signature": "" }),
+                        );
+                    }
+                    "redacted_thinking" => {
+                        self.blocks.insert(index?, block.clone());
+                    }
+                    _ => {}
+                }
+                None
+            }
+            "content_block_delta" => {
+                let delta = event.get("delta")?;
+                let block = self.blocks.get_mut(&index?)?;
+                match delta.get("type").and_then(Value::as_str)? {
+                    "thinking_delta" => {
+                        let text = delta.get("thinking").and_then(Value::as_str)?;
+                        if let Some(Value::String(thinking)) = block.get_mut("thinking") {
+                            thinking.push_str(text);
+                        }
+                        self.text.push_str(text);
+                        Some(text.to_string())
+                    }
+                    "signature_delta" => {
+                        block["signature"] = delta.get("signature").cloned().unwrap_or_default();
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Thinking blocks of the current response in order; resets for the next call.
+    pub fn take_blocks(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.blocks).into_values().collect()
+    }
+
+    pub fn text_len(&self) -> usize {
+        self.text.len()
+    }
+
+    /// Thinking text to store with the assistant message.
+    pub fn summary(&self) -> Option<String> {
+        let text = self.text.trim();
+        (!text.is_empty()).then(|| text.chars().take(MAX_STORED_CHARS).collect())
+    }
+}
+
+fn is_thinking_block(block: &Value) -> bool {
+    matches!(
+        block.get("type").and_then(Value::as_str),
+        Some("thinking" | "redacted_thinking")
+    )
+}
+
+/// Thinking blocks of a non-streaming response's `content`, in order.
+pub(crate) fn thinking_blocks(content: &[Value]) -> Vec<Value> {
+    content
+        .iter()
+        .filter(|b| is_thinking_block(b))
+        .cloned()
+        .collect()
+}
+
+/// Remove thinking blocks from assistant turns (for requests sent without thinking).
+pub(crate) fn strip_thinking(conversation: &mut [Value]) {
+    for message in conversation {
+        if let Some(blocks) = message.get_mut("content").and_then(Value::as_array_mut) {
+            blocks.retain(|b| !is_thinking_block(b));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_fits_budget_within_output_limit() {
+        assert_eq!(ThinkingConfig::plan(0, 4096, 64_000), None);
+        assert_eq!(ThinkingConfig::plan(512, 4096, 64_000), None);
+        assert_eq!(
+            ThinkingConfig::plan(8000, 4096, 64_000),
+            Some(ThinkingConfig {
+                budget_tokens: 8000,
+                max_tokens: 12_096,
+            })
+        );
+        // Squeezed: the answer keeps MIN_ANSWER_TOKENS of an 8192 limit.
+        assert_eq!(
+            ThinkingConfig::plan(16_000, 4096, 8192),
+            Some(ThinkingConfig {
+                budget_tokens: 7168,
+                max_tokens: 8192,
+            })
+        );
+        assert_eq!(ThinkingConfig::plan(4000, 1024, 2048), None);
+    }
+
+    #[test]
+    fn collector_rebuilds_signed_blocks() {
+        let mut collector = ThinkingCollector::default();
+        let events = [
+            json!({"type": "content_block_start", "index": 0,
+                   "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_delta", "index": 0,
+                   "delta": {"type": "thinking_delta", "thinking": "Check the "}}),
+            json!({"type": "content_block_delta", "index": 0,
+                   "delta": {"type": "thinking_delta", "thinking": "file first."}}),
+            json!({"type": "content_block_delta", "index": 0,
+                   "delta": {"type": "signature_delta", "signature": "sig=="}}),
+            json!({"type": "content_block_start", "index": 1,
+                   "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 1,
+                   "delta": {"type": "text_delta", "text": "Reading it."}}),
+        ];
+        let deltas: Vec<String> = events.iter().filter_map(|e| collector.observe(e)).collect();
+        assert_eq!(deltas, ["Check the ", "file first."]);
+
+        let blocks = collector.take_blocks();
+        assert_eq!(
+            blocks,
+            [
+                json!({"type": "thinking", "thinking": "Check the file first.", "signature": "sig=="})
+            ]
+        );
+        assert!(collector.take_blocks().is_empty());
+        assert_eq!(
+            collector.summary().as_deref(),
+            Some("Check the file first.")
+        );
+    }
+}
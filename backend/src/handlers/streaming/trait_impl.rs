@@ -10,9 +10,11 @@ use jaskier_core::handlers::anthropic_streaming::{
     AnthropicChatContext, AnthropicToolDef, HasAnthropicStreamingState,
 };
 
+use crate::ai_gateway::vault_guard::CredentialSource;
+use crate::budgets::PendingUsage;
 use crate::state::AppState;
 
-use super::agent_call::execute_agent_call;
+use super::agent_call::{DelegationParent, execute_agent_call};
 use super::helpers::{load_session_history, send_task_complete_notification};
 use super::{TOOL_TIMEOUT_SECS, is_retryable_status, sanitize_json_strings, send_to_anthropic};
 
@@ -38,6 +40,7 @@ impl HasAnthropicStreamingState for AppState {
         let state = self.clone();
         let body = body.clone();
         async move {
+            check_ndjson_budgets(&state)?;
             send_to_anthropic(&state, &body, timeout_secs)
                 .await
                 .map_err(|(status, axum::Json(err_val))| {
@@ -112,20 +115,18 @@ impl HasAnthropicStreamingState for AppState {
                         "A2A delegation limit reached — semaphore closed".to_string(),
                         true,
                     ),
+                    // Timeout is enforced inside (reported as a `timed_out` event).
                     Ok(_permit) => {
-                        match tokio::time::timeout(
-                            std::time::Duration::from_secs(120),
-                            execute_agent_call(&state, &input, &wd, 0),
-                        )
-                        .await
-                        {
-                            Ok(res) => res,
-                            Err(_) => ("Agent delegation timed out after 120s".to_string(), true),
-                        }
+                        let (result, outcome) =
+                            execute_agent_call(&state, &input, &wd, &DelegationParent::default())
+                                .await;
+                        (result, outcome.is_error())
                     }
                 }
             } else {
                 let timeout = std::time::Duration::from_secs(TOOL_TIMEOUT_SECS);
+                // Keeps the session / execution ids `claude_chat_stream_with_tools`
+                // put on the executor (`with_run_context`).
                 let executor = state.tool_executor.with_working_directory(&wd);
                 match tokio::time::timeout(
                     timeout,
@@ -185,34 +186,49 @@ impl HasAnthropicStreamingState for AppState {
         let msgs = messages.to_vec();
 
         async move {
+            check_ndjson_budgets(&state)?;
             let api_keys = state.base.api_keys.read().await;
 
-            let (target_model, base_url, api_key) = if let Some(key) = api_keys.get("deepseek") {
-                (
-                    "deepseek-chat",
-                    "https://api.deepseek.com/chat/completions",
-                    key.to_string(),
-                )
-            } else if let Some(key) = api_keys.get("grok") {
-                (
-                    "grok-2-1212",
-                    "https://api.x.ai/v1/chat/completions",
-                    key.to_string(),
-                )
-            } else if let Ok(key) = std::env::var("DEEPSEEK_API_KEY") {
-                (
-                    "deepseek-chat",
-                    "https://api.deepseek.com/chat/completions",
-                    key,
-                )
-            } else if let Ok(key) = std::env::var("XAI_API_KEY") {
-                ("grok-2-1212", "https://api.x.ai/v1/chat/completions", key)
-            } else {
-                return Err((
-                    StatusCode::NOT_IMPLEMENTED,
-                    "No fallback API keys found (deepseek/grok)".to_string(),
-                ));
-            };
+            let (target_model, base_url, api_key, source) =
+                if let Some(key) = api_keys.get("deepseek") {
+                    (
+                        "deepseek-chat",
+                        "https://api.deepseek.com/chat/completions",
+                        key.to_string(),
+                        CredentialSource::RuntimeKey,
+                    )
+                } else if let Some(key) = api_keys.get("grok") {
+                    (
+                        "grok-2-1212",
+                        "https://api.x.ai/v1/chat/completions",
+                        key.to_string(),
+                        CredentialSource::RuntimeKey,
+                    )
+                } else if let Ok(key) = std::env::var("DEEPSEEK_API_KEY") {
+                    (
+                        "deepseek-chat",
+                        "https://api.deepseek.com/chat/completions",
+                        key,
+                        CredentialSource::EnvKey,
+                    )
+                } else if let Ok(key) = std::env::var("XAI_API_KEY") {
+                    (
+                        "grok-2-1212",
+                        "https://api.x.ai/v1/chat/completions",
+                        key,
+                        CredentialSource::EnvKey,
+                    )
+                } else {
+                    return Err((
+                        StatusCode::NOT_IMPLEMENTED,
+                        "No fallback API keys found (deepseek/grok)".to_string(),
+                    ));
+                };
+            state
+                .ai_gateway
+                .vault_guard
+                .check_api_key("fallback_provider", source)
+                .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
 
             let mut openai_messages = Vec::new();
             if !system_prompt.is_empty() {
@@ -275,34 +291,37 @@ impl HasAnthropicStreamingState for AppState {
         let state = self.clone();
         let model = model.to_string();
         async move {
-            // Token usage tracking — fire-and-forget
+            // Token usage tracking — fire-and-forget, attributed to the
+            // request's session and user.
             let latency = latency_ms.min(i32::MAX as u128) as i32;
             let input_est = (prompt_len / 4) as i32;
             let output_est = (output_chars / 4) as i32;
-            let tier = if model.contains("opus") {
-                "commander"
-            } else if model.contains("sonnet") {
-                "coordinator"
-            } else if model.contains("haiku") {
-                "executor"
-            } else if model.contains("flash") {
-                "flash"
-            } else {
-                "coordinator"
-            };
+            let tier = super::helpers::usage_tier(&model);
+            let request = state.ndjson_request.clone();
+            if let Some(request) = &request {
+                request.budgets.add_recorded(PendingUsage {
+                    input_tokens: i64::from(input_est),
+                    output_tokens: i64::from(output_est),
+                    ..Default::default()
+                });
+            }
             let m = model.clone();
             let db_clone = db.clone();
             tokio::spawn(async move {
                 let _ = sqlx::query(
-                    "INSERT INTO ch_agent_usage (agent_id, model, input_tokens, output_tokens, total_tokens, latency_ms, success, tier) \
-                     VALUES (NULL, $1, $2, $3, $4, $5, TRUE, $6)",
+                    "INSERT INTO ch_agent_usage (agent_id, model, input_tokens, output_tokens, total_tokens, latency_ms, success, tier, \
+                     session_id, user_id) \
+                     VALUES ($1, $2, $3, $4, $5, $6, TRUE, $7, $8, $9)",
                 )
+                .bind(super::helpers::TOP_LEVEL_AGENT_ID)
                 .bind(&m)
                 .bind(input_est)
                 .bind(output_est)
                 .bind(input_est + output_est)
                 .bind(latency)
                 .bind(tier)
+                .bind(request.as_ref().and_then(|r| r.session_id))
+                .bind(request.as_ref().and_then(|r| r.user_id.clone()))
                 .execute(&db_clone)
                 .await;
             });
@@ -349,3 +368,14 @@ impl HasAnthropicStreamingState for AppState {
         100
     }
 }
+
+/// Refuse the NDJSON stream's next model call once a hard budget is spent.
+fn check_ndjson_budgets(state: &AppState) -> Result<(), (StatusCode, String)> {
+    let Some(request) = &state.ndjson_request else {
+        return Ok(());
+    };
+    match request.budgets.check(&state.budget_metrics, None).exceeded {
+        Some(breach) => Err((StatusCode::PAYMENT_REQUIRED, breach.message())),
+        None => Ok(()),
+    }
+}
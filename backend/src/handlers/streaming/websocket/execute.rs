@@ -31,19 +31,25 @@ use jaskier_core::handlers::anthropic_streaming::{
     truncate_for_context_with_limit as truncate_tool_output,
 };
 
+use crate::budgets::{BudgetSubject, PendingUsage, RunBudgets};
 use crate::models::*;
 use crate::state::AppState;
+use crate::tool_telemetry::{ToolExecution, ToolOutcome};
 
-use crate::handlers::prompt::resolve_chat_context;
-use crate::handlers::streaming::agent_call::execute_agent_call;
+use crate::handlers::prompt::{MODEL_CAPABILITY, resolve_chat_context};
+use crate::handlers::streaming::agent_call::{DelegationParent, execute_agent_call};
 use crate::handlers::streaming::helpers::{
-    detect_view_hints, load_session_history, store_ws_messages,
+    ExecutionTracking, StepContext, StepTokens, WsExecutionOutcome, detect_view_hints,
+    load_session_history, record_model_step, record_tool_step, record_ws_outcome,
+    record_ws_partial_usage, store_ws_messages, usage_tier,
 };
+use crate::handlers::streaming::thinking::{ThinkingCollector, ThinkingConfig, resolve_thinking};
 use crate::handlers::streaming::{
     TOOL_TIMEOUT_SECS, sanitize_json_strings, send_to_anthropic, truncate_for_context_with_limit,
 };
+use crate::tier_routing::ChatTransport;
 
-use super::ws_send;
+use super::{ws_check_budget, ws_send};
 
 /// Core WebSocket streaming execution with rich protocol.
 ///
@@ -56,6 +62,8 @@ pub(crate) async fn execute_streaming_ws(
     model_override: Option<String>,
     tools_enabled: bool,
     session_id: Option<String>,
+    edit_message_id: Option<String>,
+    user_id: Option<&str>,
     cancel: CancellationToken,
 ) {
     let execution_start = std::time::Instant::now();
@@ -77,12 +85,41 @@ pub(crate) async fn execute_streaming_ws(
         session_id: session_id.clone(),
     };
 
-    let ctx = resolve_chat_context(state, &chat_req).await;
+    let ctx = match resolve_chat_context(state, &chat_req, ChatTransport::WebSocket, user_id).await
+    {
+        Ok(ctx) => ctx,
+        Err(mismatch) => {
+            tracing::warn!(model = %mismatch.model, "ws: {}", mismatch.message);
+            ws_send(
+                sender,
+                &WsServerMessage::Error {
+                    message: mismatch.message,
+                    code: Some(MODEL_CAPABILITY.to_string()),
+                },
+            )
+            .await;
+            return;
+        }
+    };
     let model = ctx.model;
     let max_tokens = ctx.max_tokens;
     let effective_temperature = ctx.temperature;
     let wd = ctx.working_directory;
     let system_prompt = ctx.system_prompt;
+    let routing = ctx.routing.as_ref().map(|r| r.info());
+    let thinking = resolve_thinking(
+        state,
+        &ctx.capabilities,
+        usage_tier(&model),
+        None,
+        max_tokens,
+    )
+    .await;
+    let tracking = ExecutionTracking {
+        routing_decision_id: ctx.routing.as_ref().and_then(|r| r.decision_id),
+        experiment: ctx.experiment,
+        user_id: ctx.user_id,
+    };
 
     // Dynamic iteration cap
     let prompt_len = prompt.len();
@@ -96,6 +133,7 @@ pub(crate) async fn execute_streaming_ws(
             id: execution_id.clone(),
             model: model.clone(),
             files_loaded: vec![],
+            routing,
         },
     )
     .await;
@@ -106,10 +144,30 @@ pub(crate) async fn execute_streaming_ws(
         ws_send(sender, &WsServerMessage::ViewHint { views: view_hints }).await;
     }
 
+    // A prompt that is already the unanswered head of the session (an edit
+    // stored over REST) is answered as is. Otherwise edit-and-resend checks
+    // out the edited turn's parent so history ends before it and the stored
+    // prompt becomes a sibling branch.
+    let mut prompt_stored = false;
+    let mut edit_checkout = None;
+    if let Some(sid) = ctx.session_id.as_ref() {
+        prompt_stored = matches!(
+            crate::handlers::branches::pending_user_turn(&state.db, *sid, &prompt).await,
+            Ok(Some(_))
+        );
+        if let Some(edit_id) = edit_message_id.as_deref()
+            && !prompt_stored
+        {
+            edit_checkout = branch_ws_edit(state, *sid, edit_id).await;
+        }
+    }
+
     // Build initial messages — prefer DB history when session_id present
     let initial_messages: Vec<Value> = if let Some(ref sid) = ctx.session_id {
         let mut history = load_session_history(&state.db, sid).await;
-        history.push(json!({ "role": "user", "content": &prompt }));
+        if !prompt_stored {
+            history.push(json!({ "role": "user", "content": &prompt }));
+        }
         history
     } else {
         vec![json!({ "role": "user", "content": &prompt })]
@@ -127,10 +185,14 @@ pub(crate) async fn execute_streaming_ws(
             &initial_messages,
             &prompt,
             &ctx.session_id,
+            &execution_id,
+            &tracking,
+            thinking,
             execution_start,
             &cancel,
         )
         .await;
+        restore_unused_edit(state, edit_checkout).await;
         return;
     }
 
@@ -145,12 +207,68 @@ pub(crate) async fn execute_streaming_ws(
         initial_messages,
         &prompt,
         &ctx.session_id,
+        &execution_id,
+        &tracking,
         &wd,
         max_tool_iterations,
+        thinking,
         execution_start,
         &cancel,
     )
     .await;
+    restore_unused_edit(state, edit_checkout).await;
+}
+
+/// Check out the parent of the edited user turn for edit-and-resend.
+async fn branch_ws_edit(
+    state: &AppState,
+    session_id: uuid::Uuid,
+    edit_id: &str,
+) -> Option<crate::handlers::branches::EditCheckout> {
+    let Ok(edit_id) = edit_id.parse::<uuid::Uuid>() else {
+        tracing::warn!("Invalid edit_message_id: {}", edit_id);
+        return None;
+    };
+    let result = match state.db.acquire().await {
+        Ok(mut conn) => {
+            crate::handlers::branches::branch_for_edit(&mut conn, session_id, edit_id).await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(Some(checkout)) => Some(checkout),
+        Ok(None) => {
+            tracing::warn!(
+                "edit_message_id {} is not a user turn of session {}",
+                edit_id,
+                session_id
+            );
+            None
+        }
+        Err(e) => {
+            tracing::error!("Failed to branch session for edit: {}", e);
+            None
+        }
+    }
+}
+
+/// After an edit-and-resend run: if it stored nothing (error, timeout,
+/// cancel), check the branch that was active before the edit out again.
+async fn restore_unused_edit(
+    state: &AppState,
+    checkout: Option<crate::handlers::branches::EditCheckout>,
+) {
+    let Some(checkout) = checkout else {
+        return;
+    };
+    match checkout.restore_if_unused(&state.db).await {
+        Ok(true) => tracing::info!(
+            "Edit run for session {} stored nothing — previous branch restored",
+            checkout.session_id
+        ),
+        Ok(false) => {}
+        Err(e) => tracing::error!("Failed to restore branch after edit run: {}", e),
+    }
 }
 
 /// Tools-enabled path: agentic tool_use loop.
@@ -158,7 +276,9 @@ pub(crate) async fn execute_streaming_ws(
 /// Runs the Anthropic tool-use loop: each iteration calls the Anthropic API,
 /// parses SSE events via `AnthropicSseParser`, executes tool calls in parallel,
 /// and feeds results back until the model stops requesting tools or the
-/// iteration/timeout limit is reached.
+/// iteration/timeout limit is reached. With extended thinking, thinking deltas
+/// are streamed as `Thinking` and each `tool_use` turn carries its signed
+/// thinking blocks back to the API.
 #[allow(clippy::too_many_arguments)]
 async fn execute_with_tools(
     sender: &mut SplitSink<WebSocket, WsMessage>,
@@ -170,8 +290,11 @@ async fn execute_with_tools(
     initial_messages: Vec<Value>,
     prompt: &str,
     session_id: &Option<uuid::Uuid>,
+    execution_id: &str,
+    tracking: &ExecutionTracking,
     wd: &str,
     max_tool_iterations: usize,
+    thinking: Option<ThinkingConfig>,
     execution_start: std::time::Instant,
     cancel: &CancellationToken,
 ) {
@@ -194,7 +317,30 @@ async fn execute_with_tools(
     let mut has_written_file = false;
     let mut agent_text_len: usize = 0;
     let mut full_text = String::new();
+    let mut output_tokens: u32 = 0;
+    // Estimated from request sizes (the stream only reports output tokens).
+    let mut input_tokens: u32 = 0;
+    let mut tool_calls: u32 = 0;
+    let mut tool_errors: u32 = 0;
     let execution_timeout = std::time::Duration::from_secs(300);
+    let budgets = RunBudgets::load(
+        state,
+        &BudgetSubject {
+            user: tracking.user_id.clone(),
+            session_id: *session_id,
+            agent: None,
+        },
+        model,
+        None,
+    )
+    .await;
+    let mut budget_warned = std::collections::HashSet::new();
+    let mut thinking_collector = ThinkingCollector::default();
+    let step_ctx = StepContext {
+        execution_id: Some(execution_id.to_string()),
+        session_id: *session_id,
+        ..Default::default()
+    };
 
     loop {
         iteration += 1;
@@ -258,11 +404,64 @@ async fn execute_with_tools(
             "stream": true,
             "temperature": effective_temperature,
         });
+        if let Some(thinking) = thinking {
+            thinking.apply(&mut body);
+        }
         sanitize_json_strings(&mut body);
 
+        // Budgets: this execution's usage is only recorded on completion, so
+        // what it consumed so far is passed as pending.
+        let pending = PendingUsage {
+            input_tokens: input_tokens as i64,
+            output_tokens: output_tokens as i64,
+            ..Default::default()
+        };
+        if !ws_check_budget(sender, state, &budgets, Some(pending), &mut budget_warned).await {
+            if input_tokens > 0 {
+                record_ws_partial_usage(
+                    state,
+                    tracking,
+                    WsExecutionOutcome {
+                        execution_id,
+                        session_id: *session_id,
+                        model,
+                        prompt_len: prompt.len(),
+                        input_tokens,
+                        output_chars: full_text.len(),
+                        output_tokens,
+                        latency_ms: execution_start.elapsed().as_millis(),
+                        tool_calls,
+                        tool_errors,
+                        assistant_message_id: None,
+                    },
+                )
+                .await;
+            }
+            break;
+        }
+        let call_input_tokens = (body.to_string().len() / 4) as u32;
+        input_tokens += call_input_tokens;
+        let call_tokens = |output: u32| StepTokens {
+            input: call_input_tokens as i32,
+            output: output as i32,
+            ..Default::default()
+        };
+        let call_started_at = chrono::Utc::now();
+        let call_start = std::time::Instant::now();
+
         let resp = match send_to_anthropic(state, &body, 300).await {
             Ok(r) => r,
             Err((_, Json(err_val))) => {
+                record_model_step(
+                    state,
+                    &step_ctx,
+                    iteration,
+                    model,
+                    call_tokens(0),
+                    true,
+                    call_started_at,
+                    call_start.elapsed().as_millis(),
+                );
                 let raw_msg = err_val
                     .get("error")
                     .and_then(|e| e.as_str())
@@ -285,6 +484,16 @@ async fn execute_with_tools(
         };
 
         if !resp.status().is_success() {
+            record_model_step(
+                state,
+                &step_ctx,
+                iteration,
+                model,
+                call_tokens(0),
+                true,
+                call_started_at,
+                call_start.elapsed().as_millis(),
+            );
             let status = resp.status();
             let err_text = resp.text().await.unwrap_or_default();
             tracing::error!(
@@ -310,7 +519,7 @@ async fn execute_with_tools(
         let mut text_content = String::new();
         let mut tool_uses: Vec<Value> = Vec::new();
         let mut stop_reason = String::new();
-        let mut _total_tokens: u32 = 0;
+        let mut call_output_tokens: u32 = 0;
 
         let mut byte_stream = resp.bytes_stream();
         let mut raw_buf: Vec<u8> = Vec::new();
@@ -328,6 +537,11 @@ async fn execute_with_tools(
 
             let sse_events = parse_sse_lines(&mut raw_buf);
             for sse_json in sse_events {
+                // Thinking blocks are not surfaced by the shared parser.
+                if let Some(delta) = thinking_collector.observe(&sse_json) {
+                    ws_send(sender, &WsServerMessage::Thinking { content: delta }).await;
+                    continue;
+                }
                 let parsed = parser.parse_event(&sse_json);
                 for ev in parsed {
                     match ev {
@@ -358,7 +572,8 @@ async fn execute_with_tools(
                             stop_reason = sr;
                         }
                         AnthropicSseEvent::TokenUsage(tokens) => {
-                            _total_tokens = tokens;
+                            output_tokens += tokens;
+                            call_output_tokens += tokens;
                         }
                         AnthropicSseEvent::MessageStop => {}
                     }
@@ -366,6 +581,18 @@ async fn execute_with_tools(
             }
         }
 
+        let thinking_blocks = thinking_collector.take_blocks();
+        record_model_step(
+            state,
+            &step_ctx,
+            iteration,
+            model,
+            call_tokens(call_output_tokens),
+            cancel.is_cancelled(),
+            call_started_at,
+            call_start.elapsed().as_millis(),
+        );
+
         if cancel.is_cancelled() {
             ws_send(
                 sender,
@@ -380,7 +607,8 @@ async fn execute_with_tools(
 
         // Tool execution
         if stop_reason == "tool_use" && !tool_uses.is_empty() {
-            let mut assistant_blocks: Vec<Value> = Vec::new();
+            // Signed thinking blocks go back first, unmodified.
+            let mut assistant_blocks: Vec<Value> = thinking_blocks;
             if !text_content.is_empty() {
                 assistant_blocks.push(json!({ "type": "text", "text": &text_content }));
             }
@@ -393,7 +621,9 @@ async fn execute_with_tools(
 
             // Execute tools in parallel via tokio::spawn
             let mut handles = Vec::new();
-            let mut pending_tool_ids: Vec<String> = Vec::new();
+            let mut pending_tools: Vec<(String, String)> = Vec::new();
+            let batch_started_at = chrono::Utc::now();
+            let batch_start = std::time::Instant::now();
             for tu in &tool_uses {
                 let tool_name = tu
                     .get("name")
@@ -405,33 +635,46 @@ async fn execute_with_tools(
                     .and_then(|i| i.as_str())
                     .unwrap_or("")
                     .to_string();
-                pending_tool_ids.push(tool_id.clone());
+                pending_tools.push((tool_name.clone(), tool_id.clone()));
                 let tool_input = tu.get("input").unwrap_or(&json!({})).clone();
-                let executor = state.tool_executor.with_working_directory(wd);
+                let executor = state
+                    .tool_executor
+                    .with_working_directory(wd)
+                    .with_run_context(
+                        session_id.map(|s| s.to_string()),
+                        Some(execution_id.to_string()),
+                    );
                 let state_ref = state.clone();
                 let wd_ref = wd.to_string();
+                let delegation_parent = DelegationParent {
+                    depth: 0,
+                    execution_id: Some(execution_id.to_string()),
+                    task_id: None,
+                    session_id: *session_id,
+                    user_id: tracking.user_id.clone(),
+                    budgets: Some(budgets.clone()),
+                };
 
                 let semaphore = state.a2a_semaphore.clone();
                 let handle = tokio::spawn(async move {
-                    let (result, is_error) = if tool_name == "call_agent" {
+                    let started_at = chrono::Utc::now();
+                    let started = std::time::Instant::now();
+                    let (result, outcome) = if tool_name == "call_agent" {
                         // Acquire A2A concurrency permit
                         match semaphore.acquire_owned().await {
                             Err(_) => (
                                 "A2A delegation limit reached — semaphore closed".to_string(),
-                                true,
+                                ToolOutcome::Error,
                             ),
+                            // Timeout is enforced inside (reported as a `timed_out` event).
                             Ok(_permit) => {
-                                match tokio::time::timeout(
-                                    std::time::Duration::from_secs(120),
-                                    execute_agent_call(&state_ref, &tool_input, &wd_ref, 0),
+                                execute_agent_call(
+                                    &state_ref,
+                                    &tool_input,
+                                    &wd_ref,
+                                    &delegation_parent,
                                 )
                                 .await
-                                {
-                                    Ok(res) => res,
-                                    Err(_) => {
-                                        ("Agent delegation timed out after 120s".to_string(), true)
-                                    }
-                                }
                             }
                         }
                     } else {
@@ -442,17 +685,18 @@ async fn execute_with_tools(
                         )
                         .await
                         {
-                            Ok(res) => res,
+                            Ok((result, is_error)) => (result, ToolOutcome::from_error(is_error)),
                             Err(_) => (
                                 format!(
                                     "Tool '{}' timed out after {}s",
                                     tool_name, TOOL_TIMEOUT_SECS
                                 ),
-                                true,
+                                ToolOutcome::Timeout,
                             ),
                         }
                     };
-                    (tool_name, tool_id, result, is_error)
+                    let duration_ms = started.elapsed().as_millis();
+                    (tool_name, tool_id, result, outcome, started_at, duration_ms)
                 });
                 handles.push(handle);
             }
@@ -470,8 +714,13 @@ async fn execute_with_tools(
                 };
 
                 match result {
-                    Ok((tool_name, tool_id, result, is_error)) => {
+                    Ok((tool_name, tool_id, result, outcome, started_at, duration_ms)) => {
+                        let is_error = outcome.is_error();
                         tools_completed += 1;
+                        tool_calls += 1;
+                        if is_error {
+                            tool_errors += 1;
+                        }
                         if !is_error && (tool_name == "write_file" || tool_name == "edit_file") {
                             has_written_file = true;
                         }
@@ -500,6 +749,19 @@ async fn execute_with_tools(
 
                         let truncated =
                             truncate_tool_output(&result, tool_result_context_limit(iteration));
+                        record_tool_step(
+                            state,
+                            &step_ctx,
+                            iteration,
+                            &ToolExecution {
+                                tool_name: &tool_name,
+                                outcome,
+                                result: &result,
+                                truncated: *truncated != *result,
+                                started_at,
+                                duration_ms,
+                            },
+                        );
                         tool_results.push(json!({
                             "type": "tool_result",
                             "tool_use_id": &tool_id,
@@ -509,10 +771,26 @@ async fn execute_with_tools(
                     }
                     Err(e) => {
                         tracing::error!("Tool task panicked: {}", e);
+                        let (tool_name, tool_id) = &pending_tools[handle_idx];
+                        record_tool_step(
+                            state,
+                            &step_ctx,
+                            iteration,
+                            &ToolExecution {
+                                tool_name,
+                                outcome: ToolOutcome::Panic,
+                                result: "",
+                                truncated: false,
+                                started_at: batch_started_at,
+                                duration_ms: batch_start.elapsed().as_millis(),
+                            },
+                        );
                         tools_completed += 1;
+                        tool_calls += 1;
+                        tool_errors += 1;
                         tool_results.push(json!({
                             "type": "tool_result",
-                            "tool_use_id": &pending_tool_ids[handle_idx],
+                            "tool_use_id": tool_id,
                             "content": "Tool execution panicked — internal error",
                             "is_error": true,
                         }));
@@ -548,14 +826,41 @@ async fn execute_with_tools(
                 &tool_defs,
                 wd,
                 iteration,
+                session_id,
+                execution_id,
             )
             .await;
         }
 
         // Store messages if session present
-        if let Some(sid) = session_id {
-            let _ = store_ws_messages(state, sid, prompt, &full_text).await;
-        }
+        let assistant_message_id = match session_id {
+            Some(sid) => {
+                store_ws_messages(state, sid, prompt, &full_text, thinking_collector.summary())
+                    .await
+                    .ok()
+                    .flatten()
+            }
+            None => None,
+        };
+
+        record_ws_outcome(
+            state,
+            tracking,
+            WsExecutionOutcome {
+                execution_id,
+                session_id: *session_id,
+                model,
+                prompt_len: prompt.len(),
+                input_tokens,
+                output_chars: full_text.len(),
+                output_tokens,
+                latency_ms: execution_start.elapsed().as_millis(),
+                tool_calls,
+                tool_errors,
+                assistant_message_id,
+            },
+        )
+        .await;
 
         // Complete
         ws_send(
@@ -12,6 +12,7 @@ use crate::models::*;
 use crate::state::AppState;
 
 use super::ws_send;
+use crate::handlers::streaming::thinking::strip_thinking;
 use crate::handlers::streaming::{TOOL_TIMEOUT_SECS, sanitize_json_strings, send_to_anthropic};
 
 /// Auto-fix phase — detects when agent described changes but never wrote files.
@@ -31,6 +32,8 @@ pub(crate) async fn execute_auto_fix(
     tool_defs: &[Value],
     wd: &str,
     iteration: u32,
+    session_id: &Option<uuid::Uuid>,
+    execution_id: &str,
 ) {
     // Check if assistant text mentions fix/edit keywords
     let full_text: String = conversation
@@ -78,7 +81,9 @@ pub(crate) async fn execute_auto_fix(
         return;
     }
 
+    // Sent without thinking, so the loop's thinking blocks are dropped.
     let mut fix_conversation = conversation.to_vec();
+    strip_thinking(&mut fix_conversation);
     fix_conversation.push(json!({
         "role": "user",
         "content": "[SYSTEM: You described changes but never applied them. Use edit_file or write_file NOW to apply the changes you described. Do not explain — just make the edits.]"
@@ -105,7 +110,13 @@ pub(crate) async fn execute_auto_fix(
                 let fix_tool_name = block.get("name").and_then(|n| n.as_str()).unwrap_or("");
                 let empty_input = json!({});
                 let fix_tool_input = block.get("input").unwrap_or(&empty_input);
-                let executor = state.tool_executor.with_working_directory(wd);
+                let executor = state
+                    .tool_executor
+                    .with_working_directory(wd)
+                    .with_run_context(
+                        session_id.map(|s| s.to_string()),
+                        Some(execution_id.to_string()),
+                    );
                 let timeout = std::time::Duration::from_secs(TOOL_TIMEOUT_SECS);
                 let (result, is_error) = match tokio::time::timeout(
                     timeout,
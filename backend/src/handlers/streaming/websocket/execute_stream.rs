@@ -1,8 +1,9 @@
 //! Non-tools streaming path for WebSocket execution.
 //!
 //! Handles the simple case where `tools_enabled = false`: sends a single
-//! Anthropic streaming request, forwards SSE tokens to the WebSocket client,
-//! and supports model fallback on 429/5xx responses.
+//! Anthropic streaming request, forwards SSE tokens (and extended-thinking
+//! deltas) to the WebSocket client, and supports model fallback on 429/5xx
+//! responses.
 
 use axum::Json;
 use axum::extract::ws::{Message as WsMessage, WebSocket};
@@ -13,15 +14,20 @@ use tokio_util::sync::CancellationToken;
 
 use jaskier_core::handlers::anthropic_streaming::{parse_sse_lines, sanitize_api_error};
 
+use crate::budgets::{BudgetSubject, RunBudgets};
 use crate::models::*;
 use crate::state::AppState;
 
-use crate::handlers::streaming::helpers::store_ws_messages;
+use crate::handlers::streaming::helpers::{
+    ExecutionTracking, StepContext, StepTokens, WsExecutionOutcome, record_model_step,
+    record_ws_outcome, store_ws_messages,
+};
+use crate::handlers::streaming::thinking::{ThinkingCollector, ThinkingConfig};
 use crate::handlers::streaming::{
     is_retryable_status, sanitize_json_strings, send_to_anthropic, truncate_for_context_with_limit,
 };
 
-use super::ws_send;
+use super::{ws_check_budget, ws_send};
 
 /// Non-tools path: simple streaming without tool loop.
 ///
@@ -39,6 +45,9 @@ pub(crate) async fn execute_no_tools(
     initial_messages: &[Value],
     prompt: &str,
     session_id: &Option<uuid::Uuid>,
+    execution_id: &str,
+    tracking: &ExecutionTracking,
+    thinking: Option<ThinkingConfig>,
     execution_start: std::time::Instant,
     cancel: &CancellationToken,
 ) {
@@ -52,8 +61,35 @@ pub(crate) async fn execute_no_tools(
     if effective_temperature > 0.0 {
         body["temperature"] = json!(effective_temperature);
     }
+    if let Some(thinking) = thinking {
+        thinking.apply(&mut body);
+    }
     sanitize_json_strings(&mut body);
 
+    let budgets = RunBudgets::load(
+        state,
+        &BudgetSubject {
+            user: tracking.user_id.clone(),
+            session_id: *session_id,
+            agent: None,
+        },
+        model,
+        None,
+    )
+    .await;
+    if !ws_check_budget(sender, state, &budgets, None, &mut Default::default()).await {
+        return;
+    }
+
+    let step_ctx = StepContext {
+        execution_id: Some(execution_id.to_string()),
+        session_id: *session_id,
+        ..Default::default()
+    };
+    let input_tokens = (body.to_string().len() / 4) as u32;
+    let call_started_at = chrono::Utc::now();
+    let call_start = std::time::Instant::now();
+
     let resp = match send_to_anthropic(state, &body, 300).await {
         Ok(r) => r,
         Err((_, Json(err_val))) => {
@@ -75,6 +111,7 @@ pub(crate) async fn execute_no_tools(
     };
 
     // Fallback chain: if rate-limited or 5xx, try cheaper models
+    let mut served_model = model.to_string();
     let resp = if !resp.status().is_success() && is_retryable_status(resp.status().as_u16()) {
         let original_status = resp.status();
         let fallback_models = ["claude-sonnet-4-6", "claude-haiku-4-5-20251001"];
@@ -108,6 +145,7 @@ pub(crate) async fn execute_no_tools(
                 )
                 .await;
                 fallback_resp = Some(fb);
+                served_model = fb_model.to_string();
                 break;
             }
         }
@@ -117,6 +155,19 @@ pub(crate) async fn execute_no_tools(
     };
 
     if !resp.status().is_success() {
+        record_model_step(
+            state,
+            &step_ctx,
+            1,
+            &served_model,
+            StepTokens {
+                input: input_tokens as i32,
+                ..Default::default()
+            },
+            true,
+            call_started_at,
+            call_start.elapsed().as_millis(),
+        );
         let status = resp.status();
         let err_text = resp.text().await.unwrap_or_default();
         tracing::error!(
@@ -140,6 +191,7 @@ pub(crate) async fn execute_no_tools(
     let mut byte_stream = resp.bytes_stream();
     let mut raw_buf: Vec<u8> = Vec::new();
     let mut full_text = String::new();
+    let mut thinking_collector = ThinkingCollector::default();
 
     while let Some(chunk_result) = byte_stream.next().await {
         if cancel.is_cancelled() {
@@ -161,6 +213,10 @@ pub(crate) async fn execute_no_tools(
 
         let events = parse_sse_lines(&mut raw_buf);
         for event in events {
+            if let Some(delta) = thinking_collector.observe(&event) {
+                ws_send(sender, &WsServerMessage::Thinking { content: delta }).await;
+                continue;
+            }
             let event_type = event.get("type").and_then(|t| t.as_str()).unwrap_or("");
             if event_type == "content_block_delta" {
                 let text = event
@@ -182,10 +238,50 @@ pub(crate) async fn execute_no_tools(
         }
     }
 
+    record_model_step(
+        state,
+        &step_ctx,
+        1,
+        &served_model,
+        StepTokens {
+            input: input_tokens as i32,
+            output: ((full_text.len() + thinking_collector.text_len()) / 4) as i32,
+            ..Default::default()
+        },
+        false,
+        call_started_at,
+        call_start.elapsed().as_millis(),
+    );
+
     // Store message to DB if session present
-    if let Some(sid) = session_id {
-        let _ = store_ws_messages(state, sid, prompt, &full_text).await;
-    }
+    let assistant_message_id = match session_id {
+        Some(sid) => {
+            store_ws_messages(state, sid, prompt, &full_text, thinking_collector.summary())
+                .await
+                .ok()
+                .flatten()
+        }
+        None => None,
+    };
+
+    record_ws_outcome(
+        state,
+        tracking,
+        WsExecutionOutcome {
+            execution_id,
+            session_id: *session_id,
+            model: &served_model,
+            prompt_len: prompt.len(),
+            input_tokens,
+            output_chars: full_text.len(),
+            output_tokens: 0,
+            latency_ms: execution_start.elapsed().as_millis(),
+            tool_calls: 0,
+            tool_errors: 0,
+            assistant_message_id,
+        },
+    )
+    .await;
 
     ws_send(
         sender,
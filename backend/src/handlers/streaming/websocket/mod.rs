@@ -5,7 +5,7 @@
 //! - `execute` — core streaming execution (no-tools + tools-enabled paths)
 //!
 //! Message types: Start/Token/Iteration/ToolCall/ToolResult/ToolProgress/
-//! ViewHint/Fallback/Heartbeat/Complete/Error.
+//! ViewHint/Fallback/BudgetWarning/Heartbeat/Complete/Error.
 //!
 //! Remains CH-specific because:
 //! - CH uses its own WsClientMessage/WsServerMessage types
@@ -17,7 +17,7 @@ mod execute;
 pub(crate) mod execute_batch;
 pub(crate) mod execute_stream;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use axum::extract::State;
 use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
@@ -28,6 +28,8 @@ use tokio_util::sync::CancellationToken;
 
 use jaskier_core::auth::validate_ws_token;
 
+use crate::budgets::{BUDGET_EXCEEDED, PendingUsage, RunBudgets};
+use crate::extractor::OptionalAuth;
 use crate::models::*;
 use crate::state::AppState;
 
@@ -45,11 +47,61 @@ pub(crate) async fn ws_send(sender: &mut SplitSink<WebSocket, WsMessage>, msg: &
     }
 }
 
+/// Check spending budgets before an Anthropic call. Sends a `BudgetWarning`
+/// for every soft limit not yet reported in this execution (`warned`), and an
+/// `Error` with code `BUDGET_EXCEEDED` when a hard limit is hit.
+///
+/// Returns `false` if the call must not be made.
+pub(crate) async fn ws_check_budget(
+    sender: &mut SplitSink<WebSocket, WsMessage>,
+    state: &AppState,
+    budgets: &RunBudgets,
+    pending: Option<PendingUsage>,
+    warned: &mut HashSet<uuid::Uuid>,
+) -> bool {
+    let check = budgets.check(&state.budget_metrics, pending);
+
+    for breach in check.warnings {
+        if warned.insert(breach.budget_id) {
+            ws_send(
+                sender,
+                &WsServerMessage::BudgetWarning {
+                    message: breach.message(),
+                    budget: breach.name,
+                    scope: breach.scope,
+                    period: breach.period,
+                    unit: breach.unit,
+                    spent: breach.spent,
+                    limit: breach.limit,
+                },
+            )
+            .await;
+        }
+    }
+
+    match check.exceeded {
+        Some(breach) => {
+            ws_send(
+                sender,
+                &WsServerMessage::Error {
+                    message: breach.message(),
+                    code: Some(BUDGET_EXCEEDED.to_string()),
+                },
+            )
+            .await;
+            false
+        }
+        None => true,
+    }
+}
+
 /// WebSocket upgrade handler for `/ws/chat`.
 /// Auth via `?token=<secret>` query parameter (WS doesn't support custom headers).
+/// The jaskier-auth cookie, when present, attributes the runs to its user.
 pub async fn ws_chat(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    auth: OptionalAuth,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
     // Build query string from params for validate_ws_token
@@ -63,11 +115,11 @@ pub async fn ws_chat(
         return (StatusCode::UNAUTHORIZED, "Invalid or missing auth token").into_response();
     }
 
-    ws.on_upgrade(|socket| handle_ws(socket, state))
+    ws.on_upgrade(|socket| handle_ws(socket, state, auth.email))
 }
 
 /// Main WebSocket message loop.
-async fn handle_ws(socket: WebSocket, state: AppState) {
+async fn handle_ws(socket: WebSocket, state: AppState, user_id: Option<String>) {
     let (mut sender, mut receiver) = futures_util::StreamExt::split(socket);
     let cancel = CancellationToken::new();
 
@@ -114,6 +166,7 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
                         model,
                         tools_enabled,
                         session_id,
+                        edit_message_id,
                     } => {
                         let child_cancel = cancel.child_token();
                         execute::execute_streaming_ws(
@@ -123,6 +176,8 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
                             model,
                             tools_enabled.unwrap_or(false),
                             session_id,
+                            edit_message_id,
+                            user_id.as_deref(),
                             child_cancel,
                         )
                         .await;
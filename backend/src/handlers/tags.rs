@@ -5,6 +5,11 @@
 //! - `POST /api/sessions/{id}/tags`          — add tag(s) to a session
 //! - `DELETE /api/sessions/{id}/tags/{tag}`  — remove a tag from a session
 //! - `GET  /api/sessions/search`             — full-text search + tag filter
+//!
+//! Search is language-aware: each message carries a detected `search_language`
+//! (`pl`, `en` or `simple`) and is indexed with the matching unaccented text
+//! search configuration (`ch_polish`, `ch_english`, `ch_simple`). Tool
+//! interactions (name, input, result) are indexed with `ch_simple`.
 
 use axum::Json;
 use axum::extract::{Path, Query, State};
@@ -41,6 +46,16 @@ pub struct SearchParams {
     pub limit: Option<i64>,
     /// Offset for pagination (default 0).
     pub offset: Option<i64>,
+    /// Query syntax: `web` (default — quoted phrases, `or`, `-term`),
+    /// `prefix` (every term matched as a prefix) or `boolean` (`&`/`AND`,
+    /// `|`/`OR`, `!`/`NOT`, `<->`, parentheses, `term*` or `term:*`; other
+    /// punctuation separates terms).
+    pub mode: Option<String>,
+    /// What to search: `all` (default), `messages` or `tools`.
+    pub scope: Option<String>,
+    /// Which branches to search: `all` (default) or `active` (only the
+    /// branch currently checked out in each session).
+    pub branch: Option<String>,
 }
 
 /// A search result item — a session with matched context.
@@ -54,18 +69,289 @@ pub struct SearchResult {
     pub message_timestamp: Option<String>,
     pub tags: Vec<String>,
     pub rank: Option<f32>,
+    /// `message` or `tool` — which index produced the hit.
+    pub source: Option<String>,
+    /// Tool name for `tool` hits.
+    pub tool_name: Option<String>,
+    /// Whether the matched message is on the session's active branch.
+    pub on_active_branch: Option<bool>,
 }
 
 /// Row type for the search query.
 #[derive(Debug, Clone, sqlx::FromRow)]
-struct SearchRow {
-    session_id: uuid::Uuid,
-    session_title: String,
-    message_id: Option<uuid::Uuid>,
-    message_preview: Option<String>,
-    message_role: Option<String>,
-    message_timestamp: Option<chrono::DateTime<chrono::Utc>>,
-    rank: Option<f32>,
+pub(crate) struct SearchRow {
+    pub(crate) session_id: uuid::Uuid,
+    pub(crate) session_title: String,
+    pub(crate) message_id: Option<uuid::Uuid>,
+    pub(crate) message_preview: Option<String>,
+    pub(crate) message_role: Option<String>,
+    pub(crate) message_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub(crate) rank: Option<f32>,
+    pub(crate) source: Option<String>,
+    pub(crate) tool_name: Option<String>,
+    #[sqlx(default)]
+    pub(crate) on_active_branch: Option<bool>,
+}
+
+/// `ts_headline` options — highlighted fragments instead of a raw prefix.
+const HEADLINE_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15, MaxFragments=2, FragmentDelimiter=\" … \"";
+
+/// Tokens of a `boolean` mode query, as emitted into `to_tsquery` syntax.
+#[derive(Debug, Clone, PartialEq)]
+enum BoolToken {
+    Term(String),
+    And,
+    Or,
+    /// `<->` — followed by.
+    FollowedBy,
+    Not,
+    Open,
+    Close,
+}
+
+impl BoolToken {
+    fn is_operand_end(&self) -> bool {
+        matches!(self, Self::Term(_) | Self::Close)
+    }
+}
+
+/// Quote one search term for `to_tsquery`; `prefix` adds `:*`.
+fn quote_term(term: &str, prefix: bool) -> String {
+    format!(
+        "'{}'{}",
+        term.replace('\'', "''"),
+        if prefix { ":*" } else { "" }
+    )
+}
+
+/// Drop operators and open parentheses that no operand follows.
+fn trim_dangling(out: &mut Vec<BoolToken>, depth: &mut usize) {
+    while let Some(last) = out.last() {
+        if last.is_operand_end() {
+            break;
+        }
+        if *last == BoolToken::Open {
+            *depth -= 1;
+        }
+        out.pop();
+    }
+}
+
+/// Rebuild a `boolean` mode query as valid `to_tsquery` input.
+///
+/// Terms are quoted, `AND`/`OR`/`NOT` map to `&`/`|`/`!`, adjacent terms are
+/// joined with `&`, a trailing `*` or `:*` makes a term a prefix match, and
+/// unbalanced parentheses or dangling operators are dropped, so user input
+/// never reaches PostgreSQL as a syntax error.
+fn boolean_tsquery(q: &str) -> Option<String> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let chars: Vec<char> = q.chars().collect();
+    let mut out: Vec<BoolToken> = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        let token = match c {
+            '&' => BoolToken::And,
+            '|' => BoolToken::Or,
+            '<' if chars[i..].starts_with(&['-', '>']) => {
+                i += 2;
+                BoolToken::FollowedBy
+            }
+            '!' => BoolToken::Not,
+            '(' => BoolToken::Open,
+            ')' => BoolToken::Close,
+            c if is_word(c) => {
+                let start = i - 1;
+                while i < chars.len() && is_word(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let prefix = if chars[i..].starts_with(&['*']) {
+                    i += 1;
+                    true
+                } else if chars[i..].starts_with(&[':', '*']) {
+                    i += 2;
+                    true
+                } else {
+                    false
+                };
+                match word.as_str() {
+                    "AND" => BoolToken::And,
+                    "OR" => BoolToken::Or,
+                    "NOT" => BoolToken::Not,
+                    _ => BoolToken::Term(quote_term(&word, prefix)),
+                }
+            }
+            // Whitespace and other punctuation separate terms.
+            _ => continue,
+        };
+
+        let after_operand = out.last().is_some_and(BoolToken::is_operand_end);
+        match token {
+            BoolToken::Term(_) | BoolToken::Not | BoolToken::Open => {
+                if after_operand {
+                    out.push(BoolToken::And);
+                }
+                if token == BoolToken::Open {
+                    depth += 1;
+                }
+                out.push(token);
+            }
+            BoolToken::And | BoolToken::Or | BoolToken::FollowedBy => {
+                if after_operand {
+                    out.push(token);
+                }
+            }
+            BoolToken::Close => {
+                let open_before = depth;
+                trim_dangling(&mut out, &mut depth);
+                // An emptied group consumed its own parenthesis.
+                if depth == open_before && depth > 0 && !out.is_empty() {
+                    out.push(BoolToken::Close);
+                    depth -= 1;
+                }
+            }
+        }
+    }
+    trim_dangling(&mut out, &mut depth);
+    if !out.iter().any(|t| matches!(t, BoolToken::Term(_))) {
+        return None;
+    }
+    out.extend(std::iter::repeat_n(BoolToken::Close, depth));
+
+    let rendered: Vec<&str> = out
+        .iter()
+        .map(|t| match t {
+            BoolToken::Term(term) => term.as_str(),
+            BoolToken::And => "&",
+            BoolToken::Or => "|",
+            BoolToken::FollowedBy => "<->",
+            BoolToken::Not => "!",
+            BoolToken::Open => "(",
+            BoolToken::Close => ")",
+        })
+        .collect();
+    Some(rendered.join(" "))
+}
+
+/// Map the `mode` query param to a tsquery constructor and its input text.
+///
+/// Returns `None` when the query has no searchable terms (e.g. prefix mode
+/// with punctuation only).
+fn build_tsquery(q: &str, mode: Option<&str>) -> Option<(&'static str, String)> {
+    match mode.unwrap_or("web") {
+        "prefix" => {
+            let terms: Vec<String> = q
+                .split_whitespace()
+                .map(|t| {
+                    t.chars()
+                        .filter(|c| c.is_alphanumeric() || *c == '_')
+                        .collect::<String>()
+                })
+                .filter(|t| !t.is_empty())
+                .map(|t| quote_term(&t, true))
+                .collect();
+            if terms.is_empty() {
+                None
+            } else {
+                Some(("to_tsquery", terms.join(" & ")))
+            }
+        }
+        "boolean" => boolean_tsquery(q).map(|text| ("to_tsquery", text)),
+        _ if q.trim().is_empty() => None,
+        _ => Some(("websearch_to_tsquery", q.to_string())),
+    }
+}
+
+/// Language-aware keyword search over messages and/or tool interactions.
+///
+/// `scope` is `(messages, tools)`; `active_only` drops hits from branches that
+/// are not checked out. Returns `Ok(None)` when the query has no searchable
+/// terms. Shared with the hybrid search endpoint.
+pub(crate) async fn keyword_search(
+    db: &sqlx::PgPool,
+    query_text: &str,
+    mode: Option<&str>,
+    tag_filter: &[String],
+    scope: (bool, bool),
+    active_only: bool,
+    limit: i64,
+    offset: i64,
+) -> Result<Option<Vec<SearchRow>>, sqlx::Error> {
+    let Some((tsquery_fn, tsquery_text)) = build_tsquery(query_text, mode) else {
+        return Ok(None);
+    };
+
+    // One tsquery per text search configuration; each message is matched
+    // with the configuration it was indexed with so the GIN index stays usable.
+    // Hits are ranked and paged first; headlines (which re-parse the whole
+    // text) are only computed for the rows of the returned page.
+    let sql = format!(
+        "WITH q AS (\
+            SELECT {tsquery_fn}('ch_polish', $1) AS pl, \
+                   {tsquery_fn}('ch_english', $1) AS en, \
+                   {tsquery_fn}('ch_simple', $1) AS sim\
+        ), hits AS (\
+            SELECT m.session_id, m.id AS message_id, NULL::UUID AS tool_interaction_id, \
+                'message'::TEXT AS source, NULL::TEXT AS tool_name, m.role AS message_role, \
+                m.created_at AS message_timestamp, m.on_active_path, \
+                ts_rank(m.search_vector, \
+                    CASE m.search_language WHEN 'pl' THEN q.pl WHEN 'en' THEN q.en ELSE q.sim END) AS rank \
+            FROM ch_messages m, q \
+            WHERE $6 AND (NOT $8 OR m.on_active_path) AND (\
+                (m.search_language = 'pl' AND m.search_vector @@ q.pl) \
+                OR (m.search_language = 'en' AND m.search_vector @@ q.en) \
+                OR (COALESCE(m.search_language, 'simple') NOT IN ('pl', 'en') AND m.search_vector @@ q.sim)) \
+            UNION ALL \
+            SELECT msg.session_id, ti.message_id, ti.id AS tool_interaction_id, \
+                'tool'::TEXT AS source, ti.tool_name, msg.role AS message_role, \
+                ti.executed_at AS message_timestamp, msg.on_active_path, \
+                ts_rank(ti.search_vector, q.sim) AS rank \
+            FROM ch_tool_interactions ti \
+            JOIN ch_messages msg ON msg.id = ti.message_id, q \
+            WHERE $7 AND (NOT $8 OR msg.on_active_path) AND ti.search_vector @@ q.sim\
+        ), page AS (\
+            SELECT s.id AS session_id, s.title AS session_title, h.message_id, \
+                h.tool_interaction_id, h.message_role, h.message_timestamp, h.rank, \
+                h.source, h.tool_name, h.on_active_path \
+            FROM hits h \
+            JOIN ch_sessions s ON s.id = h.session_id \
+            WHERE cardinality($2::TEXT[]) = 0 OR EXISTS (\
+                SELECT 1 FROM ch_session_tags t WHERE t.session_id = s.id AND t.tag = ANY($2)) \
+            ORDER BY h.rank DESC, h.message_timestamp DESC \
+            LIMIT $3 OFFSET $4\
+        ) \
+        SELECT p.session_id, p.session_title, p.message_id, \
+            CASE p.source \
+                WHEN 'message' THEN ts_headline(ch_search_config(m.search_language), m.content, \
+                    CASE m.search_language WHEN 'pl' THEN q.pl WHEN 'en' THEN q.en ELSE q.sim END, $5) \
+                ELSE ts_headline('ch_simple', \
+                    COALESCE(ti.tool_name, '') || ' ' || COALESCE(ti.tool_input::TEXT, '') || E'\\n' || LEFT(COALESCE(ti.result, ''), 100000), \
+                    q.sim, $5) \
+            END AS message_preview, \
+            p.message_role, p.message_timestamp, p.rank, \
+            p.source, p.tool_name, p.on_active_path AS on_active_branch \
+        FROM page p \
+        CROSS JOIN q \
+        LEFT JOIN ch_messages m ON p.source = 'message' AND m.id = p.message_id \
+        LEFT JOIN ch_tool_interactions ti ON p.source = 'tool' AND ti.id = p.tool_interaction_id \
+        ORDER BY p.rank DESC, p.message_timestamp DESC"
+    );
+
+    sqlx::query_as::<_, SearchRow>(&sql)
+        .bind(&tsquery_text)
+        .bind(tag_filter)
+        .bind(limit)
+        .bind(offset)
+        .bind(HEADLINE_OPTIONS)
+        .bind(scope.0)
+        .bind(scope.1)
+        .bind(active_only)
+        .fetch_all(db)
+        .await
+        .map(Some)
 }
 
 // ── GET /api/sessions/{id}/tags ─────────────────────────────────────────────
@@ -219,8 +505,14 @@ pub async fn delete_session_tag(
         ("tags" = Option<String>, Query, description = "Comma-separated tag filter"),
         ("limit" = Option<i64>, Query, description = "Max results (default 50)"),
         ("offset" = Option<i64>, Query, description = "Pagination offset"),
+        ("mode" = Option<String>, Query, description = "Query syntax: web (default), prefix, boolean"),
+        ("scope" = Option<String>, Query, description = "Search scope: all (default), messages, tools"),
+        ("branch" = Option<String>, Query, description = "Branches to search: all (default), active"),
     ),
-    responses((status = 200, description = "Search results")))]
+    responses(
+        (status = 200, description = "Search results"),
+        (status = 400, description = "Malformed boolean query"),
+    ))]
 pub async fn search_sessions(
     State(state): State<AppState>,
     Query(params): Query<SearchParams>,
@@ -240,56 +532,36 @@ pub async fn search_sessions(
     let has_query = params.q.as_ref().is_some_and(|q| !q.trim().is_empty());
     let has_tags = !tag_filter.is_empty();
 
+    let (search_messages, search_tools) = match params.scope.as_deref().unwrap_or("all") {
+        "messages" => (true, false),
+        "tools" => (false, true),
+        _ => (true, true),
+    };
+
     // Build dynamic query based on presence of q and tags
     let results = if has_query {
         let query_text = params.q.as_deref().unwrap_or("").trim();
-
-        // Convert user query to tsquery — use plainto_tsquery for robustness
-        if has_tags {
-            // Full-text search + tag filter
-            sqlx::query_as::<_, SearchRow>(
-                "SELECT DISTINCT ON (s.id, m.id) \
-                    s.id AS session_id, s.title AS session_title, \
-                    m.id AS message_id, \
-                    LEFT(m.content, 200) AS message_preview, \
-                    m.role AS message_role, \
-                    m.created_at AS message_timestamp, \
-                    ts_rank(m.search_vector, plainto_tsquery('english', $1)) AS rank \
-                FROM ch_messages m \
-                JOIN ch_sessions s ON s.id = m.session_id \
-                JOIN ch_session_tags t ON t.session_id = s.id \
-                WHERE m.search_vector @@ plainto_tsquery('english', $1) \
-                    AND t.tag = ANY($2) \
-                ORDER BY s.id, m.id, rank DESC \
-                LIMIT $3 OFFSET $4",
-            )
-            .bind(query_text)
-            .bind(&tag_filter)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
-        } else {
-            // Full-text search only (no tag filter)
-            sqlx::query_as::<_, SearchRow>(
-                "SELECT \
-                    s.id AS session_id, s.title AS session_title, \
-                    m.id AS message_id, \
-                    LEFT(m.content, 200) AS message_preview, \
-                    m.role AS message_role, \
-                    m.created_at AS message_timestamp, \
-                    ts_rank(m.search_vector, plainto_tsquery('english', $1)) AS rank \
-                FROM ch_messages m \
-                JOIN ch_sessions s ON s.id = m.session_id \
-                WHERE m.search_vector @@ plainto_tsquery('english', $1) \
-                ORDER BY rank DESC \
-                LIMIT $2 OFFSET $3",
-            )
-            .bind(query_text)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
+        match keyword_search(
+            &state.db,
+            query_text,
+            params.mode.as_deref(),
+            &tag_filter,
+            (search_messages, search_tools),
+            params.branch.as_deref() == Some("active"),
+            limit,
+            offset,
+        )
+        .await
+        {
+            Ok(None) => {
+                return Ok(Json(json!({
+                    "results": [],
+                    "total": 0,
+                    "query": params.q,
+                    "tags": tag_filter,
+                })));
+            }
+            result => result.map(Option::unwrap_or_default),
         }
     } else if has_tags {
         // Tag filter only (no full-text search) — return sessions matching tags
@@ -300,7 +572,9 @@ pub async fn search_sessions(
                 NULL::TEXT AS message_preview, \
                 NULL::TEXT AS message_role, \
                 NULL::TIMESTAMPTZ AS message_timestamp, \
-                NULL::REAL AS rank \
+                NULL::REAL AS rank, \
+                NULL::TEXT AS source, \
+                NULL::TEXT AS tool_name \
             FROM ch_sessions s \
             JOIN ch_session_tags t ON t.session_id = s.id \
             WHERE t.tag = ANY($1) \
@@ -322,6 +596,14 @@ pub async fn search_sessions(
         })));
     }
     .map_err(|e| {
+        // `boolean_tsquery` normalizes user input; this stays as a backstop
+        // should a tsquery syntax error still reach PostgreSQL.
+        if let sqlx::Error::Database(ref db_err) = e
+            && db_err.code().as_deref() == Some("42601")
+        {
+            tracing::warn!("Search query syntax error: {}", e);
+            return StatusCode::BAD_REQUEST;
+        }
         tracing::error!("Search query failed: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
@@ -334,24 +616,7 @@ pub async fn search_sessions(
         .into_iter()
         .collect();
 
-    let session_tags: Vec<(uuid::Uuid, String)> = if session_ids.is_empty() {
-        Vec::new()
-    } else {
-        sqlx::query_as::<_, (uuid::Uuid, String)>(
-            "SELECT session_id, tag FROM ch_session_tags WHERE session_id = ANY($1) ORDER BY tag ASC",
-        )
-        .bind(&session_ids)
-        .fetch_all(&state.db)
-        .await
-        .unwrap_or_default()
-    };
-
-    // Group tags by session_id
-    let mut tags_map: std::collections::HashMap<uuid::Uuid, Vec<String>> =
-        std::collections::HashMap::new();
-    for (sid, tag) in session_tags {
-        tags_map.entry(sid).or_default().push(tag);
-    }
+    let tags_map = load_session_tags(&state.db, &session_ids).await;
 
     let search_results: Vec<SearchResult> = results
         .into_iter()
@@ -364,6 +629,9 @@ pub async fn search_sessions(
             message_timestamp: r.message_timestamp.map(|t| t.to_rfc3339()),
             tags: tags_map.get(&r.session_id).cloned().unwrap_or_default(),
             rank: r.rank,
+            source: r.source,
+            tool_name: r.tool_name,
+            on_active_branch: r.on_active_branch,
         })
         .collect();
 
@@ -377,6 +645,31 @@ pub async fn search_sessions(
     })))
 }
 
+/// Tags for the given sessions, grouped by session id.
+pub(crate) async fn load_session_tags(
+    db: &sqlx::PgPool,
+    session_ids: &[uuid::Uuid],
+) -> std::collections::HashMap<uuid::Uuid, Vec<String>> {
+    let session_tags: Vec<(uuid::Uuid, String)> = if session_ids.is_empty() {
+        Vec::new()
+    } else {
+        sqlx::query_as::<_, (uuid::Uuid, String)>(
+            "SELECT session_id, tag FROM ch_session_tags WHERE session_id = ANY($1) ORDER BY tag ASC",
+        )
+        .bind(session_ids)
+        .fetch_all(db)
+        .await
+        .unwrap_or_default()
+    };
+
+    let mut tags_map: std::collections::HashMap<uuid::Uuid, Vec<String>> =
+        std::collections::HashMap::new();
+    for (sid, tag) in session_tags {
+        tags_map.entry(sid).or_default().push(tag);
+    }
+    tags_map
+}
+
 // ── GET /api/tags — list all unique tags with counts ────────────────────────
 
 #[utoipa::path(get, path = "/api/tags", tag = "tags",
@@ -401,3 +694,67 @@ pub async fn list_all_tags(State(state): State<AppState>) -> Result<Json<Value>,
         "tags": tag_list,
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_input_has_no_query() {
+        for mode in [None, Some("prefix"), Some("boolean")] {
+            assert_eq!(build_tsquery("", mode), None);
+            assert_eq!(build_tsquery("   ", mode), None);
+        }
+        assert_eq!(build_tsquery("?!", Some("prefix")), None);
+        assert_eq!(build_tsquery("( & ) !", Some("boolean")), None);
+    }
+
+    #[test]
+    fn terms_are_quoted() {
+        assert_eq!(
+            build_tsquery("zażółć foo_bar", Some("prefix")),
+            Some(("to_tsquery", "'zażółć':* & 'foo_bar':*".to_string()))
+        );
+        assert_eq!(quote_term("it's", false), "'it''s'");
+        // Quotes and tsquery syntax in boolean input split terms instead of
+        // reaching PostgreSQL.
+        assert_eq!(
+            boolean_tsquery("it's <-> x:* y<z"),
+            Some("'it' & 's' <-> 'x':* & 'y' & 'z'".to_string())
+        );
+    }
+
+    #[test]
+    fn boolean_operators_are_normalized() {
+        assert_eq!(
+            boolean_tsquery("rust AND (tokio OR axum) NOT python"),
+            Some("'rust' & ( 'tokio' | 'axum' ) & ! 'python'".to_string())
+        );
+        assert_eq!(
+            boolean_tsquery("rust async*"),
+            Some("'rust' & 'async':*".to_string())
+        );
+        assert_eq!(boolean_tsquery("a | | b &"), Some("'a' | 'b'".to_string()));
+    }
+
+    #[test]
+    fn unbalanced_parentheses_are_repaired() {
+        assert_eq!(
+            boolean_tsquery("((a | b"),
+            Some("( ( 'a' | 'b' ) )".to_string())
+        );
+        assert_eq!(boolean_tsquery("a)) b"), Some("'a' & 'b'".to_string()));
+        assert_eq!(
+            boolean_tsquery("(a & ) | b ("),
+            Some("( 'a' ) | 'b'".to_string())
+        );
+    }
+
+    #[test]
+    fn web_mode_passes_text_through() {
+        assert_eq!(
+            build_tsquery("\"exact phrase\" -skip", None),
+            Some(("websearch_to_tsquery", "\"exact phrase\" -skip".to_string()))
+        );
+    }
+}
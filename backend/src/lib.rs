@@ -17,8 +17,12 @@ pub mod audit;
 pub mod auth;
 pub mod auto_qa;
 pub mod browser_proxy;
+pub mod budgets;
 pub mod collab;
+pub mod config_cache;
+pub mod delegation_events;
 pub mod extractor;
+pub mod gateway_providers;
 pub mod handlers;
 pub mod mcp;
 pub mod memory_pruning;
@@ -28,10 +32,13 @@ pub mod ocr;
 pub mod rate_limits;
 pub mod sandbox;
 pub mod semantic_cache;
+pub mod session_index;
 pub mod state;
 pub mod state_agent_helpers;
 pub mod swarm;
 pub mod system_monitor;
+pub mod tier_routing;
+pub mod tool_telemetry;
 pub mod tools;
 pub mod vault_proxy;
 pub mod watchdog;
@@ -81,12 +88,45 @@ use state::AppState;
         // Sessions (local overrides with utoipa annotations)
         handlers::get_session,
         handlers::add_session_message,
+        handlers::fork_session,
+        handlers::edit_message,
+        handlers::checkout_branch,
+        handlers::list_branches,
+        handlers::export_session,
+        handlers::export_sessions,
+        handlers::import_sessions,
         // Tags & search
         handlers::get_session_tags,
         handlers::add_session_tags,
         handlers::delete_session_tag,
         handlers::search_sessions,
         handlers::list_all_tags,
+        handlers::hybrid_search,
+        handlers::related_sessions,
+        handlers::backfill_embeddings,
+        handlers::embeddings_status,
+        // A/B experiments
+        handlers::list_experiments,
+        handlers::create_experiment,
+        handlers::update_experiment,
+        handlers::delete_experiment,
+        handlers::experiment_results,
+        // Pricing catalogue
+        handlers::list_pricing,
+        handlers::create_price,
+        handlers::delete_price,
+        // Spending budgets
+        handlers::list_budgets,
+        handlers::create_budget,
+        handlers::update_budget,
+        handlers::delete_budget,
+        handlers::budget_status,
+        // Gateway provider configuration
+        handlers::list_provider_configs,
+        handlers::create_provider_config,
+        handlers::update_provider_config,
+        handlers::delete_provider_config,
+        handlers::gateway_usage,
         // Model registry
         model_registry::list_models,
         model_registry::refresh_models,
@@ -114,6 +154,7 @@ use state::AppState;
         models::ClaudeModelInfo,
         // Settings
         models::AppSettings,
+        models::ThinkingBudgets,
         models::ApiKeyRequest,
         // Sessions
         models::Session,
@@ -123,15 +164,46 @@ use state::AppState;
         models::CreateSessionRequest,
         models::UpdateSessionRequest,
         models::AddMessageRequest,
+        handlers::branches::ForkSessionRequest,
+        handlers::branches::EditMessageRequest,
+        handlers::branches::CheckoutBranchRequest,
+        handlers::branches::BranchHead,
+        handlers::session_export::SessionExportDocument,
+        handlers::session_export::ExportedSession,
+        handlers::session_export::ExportedMessage,
+        handlers::session_export::ExportedToolInteraction,
         // Model registry
         model_registry::ModelInfo,
         model_registry::ResolvedModels,
+        model_registry::ModelCapabilities,
         model_registry::PinModelRequest,
         // Prompt history
         models::AddPromptRequest,
         // Tags
         handlers::tags::AddTagsRequest,
         handlers::tags::SearchResult,
+        handlers::semantic_search::HybridSearchResult,
+        handlers::semantic_search::RelatedSession,
+        // A/B experiments
+        handlers::experiments::Experiment,
+        handlers::experiments::ExperimentArm,
+        handlers::experiments::ExperimentArmInput,
+        handlers::experiments::CreateExperimentRequest,
+        handlers::experiments::UpdateExperimentRequest,
+        handlers::experiments::ArmResults,
+        handlers::experiments::MeanEstimate,
+        handlers::experiments::RateEstimate,
+        // Pricing catalogue
+        handlers::pricing::ModelPrice,
+        handlers::pricing::CreatePriceRequest,
+        // Spending budgets
+        handlers::budgets::Budget,
+        handlers::budgets::BudgetRequest,
+        handlers::budgets::BudgetStatus,
+        // Gateway provider configuration
+        handlers::gateway_providers::ProviderConfigRequest,
+        handlers::gateway_providers::ProviderConfigView,
+        handlers::gateway_usage::GatewayUsageStat,
     )),
     tags(
         (name = "health", description = "Health & readiness endpoints"),
@@ -143,6 +215,10 @@ use state::AppState;
         (name = "models", description = "Dynamic model registry & pinning"),
         (name = "system", description = "System monitoring"),
         (name = "tags", description = "Session tagging & full-text search"),
+        (name = "experiments", description = "A/B experiments on model, temperature & prompt"),
+        (name = "pricing", description = "Versioned model pricing catalogue"),
+        (name = "budgets", description = "Spending budgets & limits"),
+        (name = "providers", description = "AI gateway provider configuration"),
     )
 )]
 pub struct ApiDoc;
@@ -226,6 +302,7 @@ pub fn create_router(state: AppState) -> Router {
         .merge(app_routes::ch_vault_protected_routes(state.clone()))
         .merge(app_routes::ch_anthropic_provider_auth_routes())
         .merge(app_routes::ch_auto_qa_routes())
+        .merge(app_routes::ch_openai_compat_routes(state.clone()))
         .merge(app_routes::ch_profiling_routes())
         .merge(jaskier_swarm::swarm_router::<AppState>())
         .merge(jaskier_collab::collab_router::<AppState>())
@@ -259,6 +336,7 @@ pub fn create_test_router(state: AppState) -> Router {
         .merge(app_routes::ch_vault_protected_routes(state.clone()))
         .merge(app_routes::ch_anthropic_provider_auth_routes())
         .merge(app_routes::ch_auto_qa_routes())
+        .merge(app_routes::ch_openai_compat_routes(state.clone()))
         .merge(app_routes::ch_profiling_routes())
         .merge(jaskier_collab::collab_router::<AppState>())
         .merge(semantic_cache::handlers::semantic_cache_router::<AppState>())
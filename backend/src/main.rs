@@ -245,9 +245,29 @@ async fn main() -> anyhow::Result<()> {
         startup_state.mark_ready();
     });
 
+    // ── Spawn settings/pins cache invalidation listener (LISTEN ch_config_changed) ──
+    let _config_listener = claudehydra_backend::config_cache::spawn_listener(state.clone());
+
     // ── Spawn background watchdog ──
     let _watchdog = watchdog::spawn(state.clone());
 
+    // ── Spawn AI gateway half-open probes (circuit breakers, every 10s) ──
+    let _gateway_prober = claudehydra_backend::ai_gateway::health::spawn_prober(state.clone());
+
+    // ── Spawn provider token refresh scheduler (checks due credentials every 60s) ──
+    let _token_refresh =
+        claudehydra_backend::ai_gateway::token_refresh::spawn_scheduler(state.clone());
+
+    // ── Spawn OAuth PKCE state cleanup (expired logins, every 5 minutes) ──
+    let oauth_manager = state.ai_gateway.oauth_manager.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(300));
+        loop {
+            interval.tick().await;
+            oauth_manager.cleanup_expired_states().await;
+        }
+    });
+
     // ── Spawn MCP client startup (connect to enabled MCP servers) ──
     let mcp_state = state.clone();
     tokio::spawn(async move {
@@ -266,6 +286,9 @@ async fn main() -> anyhow::Result<()> {
     // ── Spawn Semantic Cache TTL cleanup loop (every 5 minutes) ──
     claudehydra_backend::semantic_cache::spawn_ttl_cleanup_loop(state.semantic_cache.clone());
 
+    // ── Spawn session embedding sweep (backfills un-indexed messages every 10 min) ──
+    let _session_index = claudehydra_backend::session_index::spawn_backfill_loop(state.clone());
+
     // ── Spawn Memory Pruning watchdog (configurable interval, default 1h) ──
     claudehydra_backend::memory_pruning::spawn_pruning_watchdog(state.clone());
 
@@ -8,8 +8,14 @@
 // ClaudeHydra keeps its own `ResolvedModels` and `resolve_models` since its
 // use cases (commander/coordinator/executor/flash) differ from the Gemini-focused
 // shared defaults (chat/thinking/image/flash).
+//
+// Capability metadata (context window, max output, vision, tool use, extended
+// thinking, pricing reference) is kept next to the shared model cache in
+// `CapabilityCache`: limits reported by provider listings (Gemini) win, the
+// `ch_model_capabilities` override table fills in the rest.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use axum::Json;
 use axum::extract::{Path, State};
@@ -19,6 +25,7 @@ use serde::Serialize;
 use serde_json::{Value, json};
 use utoipa::ToSchema;
 
+use crate::ai_gateway::vault_guard::CredentialSource;
 use crate::state::AppState;
 
 // ── Re-export shared types from jaskier-core ──────────────────────────────────
@@ -50,8 +57,17 @@ pub async fn resolve_models(state: &AppState) -> ResolvedModels {
         }
     }
 
+    ensure_capabilities(state).await;
+    let capabilities = state.model_capabilities.read().await;
     let cache = state.model_cache().read().await;
-    let anthropic = cache.models.get("anthropic").cloned().unwrap_or_default();
+    let key = (cache.fetched_at, capabilities.loaded_at);
+    if let Some(resolved) = state.config_cache.resolved(key) {
+        return resolved;
+    }
+
+    // Tier models drive the tool-use loop.
+    let mut anthropic = cache.models.get("anthropic").cloned().unwrap_or_default();
+    anthropic.retain(|m| capabilities.resolve(&m.id).tool_use);
 
     // Commander: latest opus (prefer non-dated, fallback to dated)
     let commander = select_best(&anthropic, &["opus"], &["20"])
@@ -75,33 +91,43 @@ pub async fn resolve_models(state: &AppState) -> ResolvedModels {
         ],
     );
 
-    ResolvedModels {
+    let resolved = ResolvedModels {
         commander,
         coordinator,
         executor,
         flash,
-    }
+    };
+    state.config_cache.store_resolved(key, resolved.clone());
+    resolved
 }
 
 /// Get the model ID for a given tier/use case.
 /// Priority: 1) DB pin  2) dynamic auto-selection  3) hardcoded fallback.
+/// Pins and the auto-selection come from `crate::config_cache`.
 pub async fn get_model_id(state: &AppState, use_case: &str) -> String {
-    // 1) Check for a pinned model in DB
-    let pinned: Option<String> =
-        sqlx::query_scalar("SELECT model_id FROM ch_model_pins WHERE use_case = $1")
-            .bind(use_case)
-            .fetch_optional(&state.db)
-            .await
-            .ok()
-            .flatten();
+    // 1) Check for a pinned model (cached snapshot of ch_model_pins)
+    let pinned: Option<String> = crate::config_cache::snapshot(state)
+        .await
+        .pins
+        .get(use_case)
+        .cloned();
 
     if let Some(ref pin) = pinned {
-        tracing::info!(
-            "model_registry: use_case={} → model={} (pinned)",
-            use_case,
-            pin
+        if pin_usable(use_case, &model_capabilities(state, pin).await) {
+            tracing::info!(
+                "model_registry: use_case={} → model={} (pinned)",
+                use_case,
+                pin
+            );
+            return pin.clone();
+        }
+        // Pins are checked when set; this one lost tool use since (capability
+        // override changed). GET /api/models lists it under `ignored_pins`.
+        tracing::warn!(
+            "model_registry: ignoring pin {} for use_case={} — model has no tool use",
+            pin,
+            use_case
         );
-        return pin.clone();
     }
 
     // 2) Dynamic auto-selection
@@ -131,22 +157,295 @@ pub async fn get_model_id(state: &AppState, use_case: &str) -> String {
     id.to_string()
 }
 
+/// Whether a pinned model can serve `use_case`: every tier but flash runs the
+/// tool loop, so it needs tool use.
+fn pin_usable(use_case: &str, capabilities: &ModelCapabilities) -> bool {
+    use_case.eq_ignore_ascii_case("flash") || capabilities.tool_use
+}
+
 /// Map a tier name to the current best model ID (used by agent init).
 pub async fn model_for_tier(state: &AppState, tier: &str) -> String {
     get_model_id(state, tier).await
 }
 
+// ── ClaudeHydra-specific: capability metadata ─────────────────────────────────
+
+/// How long loaded overrides and provider-reported limits stay fresh.
+const CAPABILITIES_TTL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Limits and features of a model.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ModelCapabilities {
+    pub model: String,
+    pub context_window: u32,
+    pub max_output_tokens: u32,
+    pub vision: bool,
+    pub tool_use: bool,
+    pub extended_thinking: bool,
+    /// `ch_model_pricing.model_pattern` the model is billed under.
+    pub pricing_ref: Option<String>,
+    /// `provider` (limits reported by the provider), `override` or `default`.
+    pub source: &'static str,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct CapabilityOverride {
+    model_pattern: String,
+    context_window: i32,
+    max_output_tokens: i32,
+    supports_vision: bool,
+    supports_tools: bool,
+    supports_thinking: bool,
+    pricing_ref: Option<String>,
+}
+
+/// Limits a provider reports in its model listing.
+#[derive(Debug, Clone, Default)]
+struct ReportedLimits {
+    context_window: Option<u32>,
+    max_output_tokens: Option<u32>,
+    extended_thinking: Option<bool>,
+}
+
+/// Override rows plus provider-reported limits, refreshed hourly.
+#[derive(Debug, Default)]
+pub struct CapabilityCache {
+    overrides: Vec<CapabilityOverride>,
+    reported: HashMap<String, ReportedLimits>,
+    loaded_at: Option<std::time::Instant>,
+    /// The override table changed since the last load.
+    expired: bool,
+    /// Held by the one caller reloading the cache.
+    refresh_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// `*`-wildcard match (same semantics as the `LIKE` patterns of `ch_model_pricing`).
+fn pattern_matches(pattern: &str, model: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = model.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+impl CapabilityCache {
+    fn is_stale(&self) -> bool {
+        self.expired
+            || self
+                .loaded_at
+                .is_none_or(|t| t.elapsed() > CAPABILITIES_TTL)
+    }
+
+    /// Reload on next use (override table changed); the current entries are
+    /// served until the reload finishes.
+    pub fn expire(&mut self) {
+        self.expired = true;
+    }
+
+    /// Capabilities of `model`: the most specific override (or conservative
+    /// defaults) with provider-reported limits on top.
+    pub fn resolve(&self, model: &str) -> ModelCapabilities {
+        let specific = |o: &&CapabilityOverride| o.model_pattern.replace('*', "").len();
+        let matched = self
+            .overrides
+            .iter()
+            .filter(|o| pattern_matches(&o.model_pattern, model))
+            .max_by_key(specific);
+
+        let mut caps = match matched {
+            Some(o) => ModelCapabilities {
+                model: model.to_string(),
+                context_window: o.context_window.max(1) as u32,
+                max_output_tokens: o.max_output_tokens.max(1) as u32,
+                vision: o.supports_vision,
+                tool_use: o.supports_tools,
+                extended_thinking: o.supports_thinking,
+                pricing_ref: o.pricing_ref.clone(),
+                source: "override",
+            },
+            None => ModelCapabilities {
+                model: model.to_string(),
+                context_window: 200_000,
+                max_output_tokens: 4096,
+                vision: false,
+                tool_use: true,
+                extended_thinking: false,
+                pricing_ref: None,
+                source: "default",
+            },
+        };
+
+        if let Some(reported) = self.reported.get(model) {
+            if let Some(context_window) = reported.context_window {
+                caps.context_window = context_window;
+                caps.source = "provider";
+            }
+            if let Some(max_output_tokens) = reported.max_output_tokens {
+                caps.max_output_tokens = max_output_tokens;
+                caps.source = "provider";
+            }
+            if let Some(thinking) = reported.extended_thinking {
+                caps.extended_thinking = thinking;
+            }
+        }
+        caps
+    }
+}
+
+/// Limits reported by the Gemini model listing (`inputTokenLimit`,
+/// `outputTokenLimit`, `thinking`). Anthropic's listing carries no limits.
+/// The listing is fetched with an API key, so it passes the Vault guard.
+async fn fetch_google_limits(state: &AppState) -> Result<HashMap<String, ReportedLimits>, String> {
+    let runtime_key = state.api_keys.read().await.get("GOOGLE_API_KEY").cloned();
+    let (key, source) = match runtime_key {
+        Some(key) => (key, CredentialSource::RuntimeKey),
+        None => match std::env::var("GOOGLE_API_KEY") {
+            Ok(key) => (key, CredentialSource::EnvKey),
+            Err(_) => return Ok(HashMap::new()),
+        },
+    };
+    state
+        .ai_gateway
+        .vault_guard
+        .check_api_key("google_models", source)
+        .map_err(|e| format!("google capabilities: {}", e))?;
+
+    let resp = state
+        .http_client
+        .get("https://generativelanguage.googleapis.com/v1beta/models?pageSize=1000")
+        .header("x-goog-api-key", key)
+        .timeout(std::time::Duration::from_secs(15))
+        .send()
+        .await
+        .map_err(|e| format!("google capabilities: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("google capabilities: HTTP {}", resp.status()));
+    }
+    let body: Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("google capabilities: {}", e))?;
+
+    let limit = |m: &Value, key: &str| {
+        m.get(key)
+            .and_then(Value::as_u64)
+            .map(|v| v.min(u32::MAX as u64) as u32)
+    };
+    Ok(body
+        .get("models")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|m| {
+            let id = m.get("name")?.as_str()?.trim_start_matches("models/");
+            Some((
+                id.to_string(),
+                ReportedLimits {
+                    context_window: limit(m, "inputTokenLimit"),
+                    max_output_tokens: limit(m, "outputTokenLimit"),
+                    extended_thinking: m.get("thinking").and_then(Value::as_bool),
+                },
+            ))
+        })
+        .collect())
+}
+
+/// Reload the override table and provider-reported limits. Returns fetch errors.
+pub async fn refresh_capabilities(state: &AppState) -> Vec<String> {
+    // Cleared before reading, so a change notified mid-reload expires it again.
+    state.model_capabilities.write().await.expired = false;
+    let mut errors = Vec::new();
+    let overrides = match sqlx::query_as::<_, CapabilityOverride>(
+        "SELECT model_pattern, context_window, max_output_tokens, supports_vision, \
+         supports_tools, supports_thinking, pricing_ref FROM ch_model_capabilities",
+    )
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(rows) => Some(rows),
+        Err(e) => {
+            errors.push(format!("capability overrides: {}", e));
+            None
+        }
+    };
+    let reported = match fetch_google_limits(state).await {
+        Ok(limits) => Some(limits),
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+
+    let mut cache = state.model_capabilities.write().await;
+    if let Some(overrides) = overrides {
+        cache.overrides = overrides;
+    }
+    if let Some(reported) = reported {
+        cache.reported = reported;
+    }
+    cache.loaded_at = Some(std::time::Instant::now());
+    errors
+}
+
+/// Reload stale capabilities without holding up the chat path: a loaded cache
+/// keeps being served while one background task reloads it. Only the very
+/// first load is waited for, by a single fetching caller.
+async fn ensure_capabilities(state: &AppState) {
+    let (loaded, refresh_lock) = {
+        let cache = state.model_capabilities.read().await;
+        if !cache.is_stale() {
+            return;
+        }
+        (cache.loaded_at.is_some(), cache.refresh_lock.clone())
+    };
+
+    if loaded {
+        let Ok(guard) = refresh_lock.try_lock_owned() else {
+            return;
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let _guard = guard;
+            if state.model_capabilities.read().await.is_stale() {
+                log_capability_errors(refresh_capabilities(&state).await);
+            }
+        });
+    } else {
+        let _guard = refresh_lock.lock().await;
+        if state.model_capabilities.read().await.loaded_at.is_none() {
+            log_capability_errors(refresh_capabilities(state).await);
+        }
+    }
+}
+
+fn log_capability_errors(errors: Vec<String>) {
+    for err in errors {
+        tracing::warn!("model_registry: {}", err);
+    }
+}
+
+/// Capabilities of a model (overrides loaded on first use).
+pub async fn model_capabilities(state: &AppState, model: &str) -> ModelCapabilities {
+    ensure_capabilities(state).await;
+    state.model_capabilities.read().await.resolve(model)
+}
+
 // ── HTTP handlers ────────────────────────────────────────────────────────────
 
-/// Read all pins from DB as a HashMap.
+/// All pins as a HashMap (from the config snapshot).
 async fn get_pins_map(state: &AppState) -> HashMap<String, String> {
-    let rows: Vec<(String, String)> =
-        sqlx::query_as("SELECT use_case, model_id FROM ch_model_pins")
-            .fetch_all(&state.db)
-            .await
-            .unwrap_or_default();
-
-    rows.into_iter().collect()
+    crate::config_cache::snapshot(state).await.pins.clone()
 }
 
 // ── Startup sync ─────────────────────────────────────────────────────────────
@@ -166,6 +465,9 @@ pub async fn startup_sync(state: &AppState) {
     for err in &startup_errors {
         tracing::warn!("model_registry: startup fetch error: {}", err);
     }
+    for err in refresh_capabilities(state).await {
+        tracing::warn!("model_registry: startup capability error: {}", err);
+    }
 
     let resolved = resolve_models(state).await;
 
@@ -224,17 +526,36 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
 
     let resolved = resolve_models(&state).await;
     let pins = get_pins_map(&state).await;
+    let capabilities = state.model_capabilities.read().await;
     let cache = state.model_cache().read().await;
 
     let total: usize = cache.models.values().map(std::vec::Vec::len).sum();
+    let model_capabilities: HashMap<&str, ModelCapabilities> = cache
+        .models
+        .values()
+        .flatten()
+        .map(|m| (m.id.as_str(), capabilities.resolve(&m.id)))
+        .collect();
     let stale = cache.is_stale();
     let fetched_ago = cache.fetched_at.map(|t| t.elapsed().as_secs());
+    // Pins `get_model_id` skips in favour of the auto-selection.
+    let ignored_pins: HashMap<&str, String> = pins
+        .iter()
+        .filter(|(use_case, model)| !pin_usable(use_case, &capabilities.resolve(model)))
+        .map(|(use_case, model)| {
+            (
+                use_case.as_str(),
+                format!("{model} does not support tool use"),
+            )
+        })
+        .collect();
 
     let body = Json(json!({
         "total_models": total,
         "cache_stale": stale,
         "cache_age_seconds": fetched_ago,
         "pins": pins,
+        "ignored_pins": ignored_pins,
         "selected": {
             "commander": resolved.commander,
             "coordinator": resolved.coordinator,
@@ -244,7 +565,8 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
         "providers": {
             "anthropic": cache.models.get("anthropic").cloned().unwrap_or_default(),
             "google": cache.models.get("google").cloned().unwrap_or_default(),
-        }
+        },
+        "capabilities": model_capabilities,
     }));
 
     // #6 — Cache static model list for 60 seconds
@@ -256,7 +578,8 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
     responses((status = 200, description = "Refreshed model cache", body = Value))
 )]
 pub async fn refresh_models(State(state): State<AppState>) -> Json<Value> {
-    let (models, errors) = refresh_cache(&state).await;
+    let (models, mut errors) = refresh_cache(&state).await;
+    errors.extend(refresh_capabilities(&state).await);
     let resolved = resolve_models(&state).await;
     let pins = get_pins_map(&state).await;
 
@@ -300,6 +623,18 @@ pub async fn pin_model(
         );
     }
 
+    if !pin_usable(
+        &normalized,
+        &model_capabilities(&state, &body.model_id).await,
+    ) {
+        return Json(json!({
+            "error": format!(
+                "Model '{}' does not support tool use and cannot serve use_case '{}'",
+                body.model_id, normalized
+            )
+        }));
+    }
+
     let result = sqlx::query(
         "INSERT INTO ch_model_pins (use_case, model_id) \
          VALUES ($1, $2) \
@@ -317,6 +652,7 @@ pub async fn pin_model(
                 normalized,
                 body.model_id
             );
+            crate::config_cache::invalidate(&state).await;
             // #40 Audit log
             crate::audit::log_audit(
                 &state.db,
@@ -345,7 +681,10 @@ pub async fn pin_model(
     responses((status = 200, description = "Model unpinned", body = Value))
 )]
 pub async fn unpin_model(state: State<AppState>, use_case: Path<String>) -> Json<Value> {
-    jaskier_core::model_registry::unpin_model(state, use_case).await
+    let app_state = state.0.clone();
+    let response = jaskier_core::model_registry::unpin_model(state, use_case).await;
+    crate::config_cache::invalidate(&app_state).await;
+    response
 }
 
 /// GET /api/models/pins — List all active pins (delegates to jaskier-core)
@@ -389,4 +728,92 @@ mod tests {
         let cache = ModelCache::new();
         assert!(cache.models.is_empty());
     }
+
+    // ── Capabilities ─────────────────────────────────────────────────────
+
+    fn capability_override(pattern: &str, max_output: i32, vision: bool) -> CapabilityOverride {
+        CapabilityOverride {
+            model_pattern: pattern.to_string(),
+            context_window: 200_000,
+            max_output_tokens: max_output,
+            supports_vision: vision,
+            supports_tools: true,
+            supports_thinking: false,
+            pricing_ref: None,
+        }
+    }
+
+    #[test]
+    fn pattern_matches_wildcards() {
+        assert!(pattern_matches("claude-*", "claude-sonnet-4-6"));
+        assert!(pattern_matches("gemini-*flash*", "gemini-2.5-flash-lite"));
+        assert!(pattern_matches("claude-opus-4-6", "claude-opus-4-6"));
+        assert!(!pattern_matches(
+            "claude-opus-4-6",
+            "claude-opus-4-6-20260101"
+        ));
+        assert!(!pattern_matches("claude-*haiku*", "claude-sonnet-4-6"));
+    }
+
+    #[test]
+    fn capabilities_prefer_specific_override_and_reported_limits() {
+        let mut cache = CapabilityCache {
+            overrides: vec![
+                capability_override("claude-*", 8192, true),
+                capability_override("claude-3-5-haiku*", 8192, false),
+                capability_override("gemini-*", 8192, true),
+            ],
+            ..Default::default()
+        };
+        assert!(!cache.resolve("claude-3-5-haiku-20241022").vision);
+        assert!(cache.resolve("claude-sonnet-4-6").vision);
+        assert_eq!(cache.resolve("unknown-model").source, "default");
+
+        cache.reported.insert(
+            "gemini-2.5-flash".to_string(),
+            ReportedLimits {
+                context_window: Some(1_048_576),
+                max_output_tokens: Some(65_536),
+                extended_thinking: Some(true),
+            },
+        );
+        let flash = cache.resolve("gemini-2.5-flash");
+        assert_eq!(flash.max_output_tokens, 65_536);
+        assert!(flash.extended_thinking);
+        assert_eq!(flash.source, "provider");
+    }
+
+    #[test]
+    fn pins_without_tool_use_only_serve_flash() {
+        let cache = CapabilityCache {
+            overrides: vec![CapabilityOverride {
+                supports_tools: false,
+                ..capability_override("gemini-*-image*", 8192, true)
+            }],
+            ..Default::default()
+        };
+        let image_model = cache.resolve("gemini-2.5-flash-image");
+        assert!(!pin_usable("coordinator", &image_model));
+        assert!(pin_usable("flash", &image_model));
+        assert!(pin_usable("Commander", &cache.resolve("claude-opus-4-6")));
+    }
+
+    #[test]
+    fn expired_capabilities_stay_served_until_reloaded() {
+        let mut cache = CapabilityCache {
+            overrides: vec![capability_override("claude-*", 8192, true)],
+            loaded_at: Some(std::time::Instant::now()),
+            ..Default::default()
+        };
+        assert!(!cache.is_stale());
+
+        cache.expire();
+        assert!(cache.is_stale());
+        assert!(cache.loaded_at.is_some());
+        assert!(cache.resolve("claude-sonnet-4-6").vision);
+
+        let first = cache.refresh_lock.clone().try_lock_owned();
+        assert!(first.is_ok());
+        assert!(cache.refresh_lock.clone().try_lock_owned().is_err());
+    }
 }
@@ -121,6 +121,42 @@ pub struct AppSettings {
     /// Message compaction keep — keep this many recent messages after compaction (default 15)
     #[serde(default = "default_compaction_keep")]
     pub compaction_keep: i32,
+    /// Daily spend (USD) per user above which auto-tier routing caps the tier (None = no ceiling)
+    #[serde(default)]
+    pub routing_cost_ceiling_usd: Option<f64>,
+    /// Per-user overrides of `routing_cost_ceiling_usd` (user email → USD)
+    #[serde(default)]
+    pub routing_user_cost_ceilings: std::collections::HashMap<String, f64>,
+    /// Extended-thinking budgets per tier / agent (empty = thinking off)
+    #[serde(default)]
+    pub thinking_budgets: ThinkingBudgets,
+    /// Store thinking summaries with assistant messages
+    #[serde(default)]
+    pub store_thinking: bool,
+}
+
+/// Extended-thinking `budget_tokens` per tier and per agent.
+///
+/// An agent's own budget wins over its tier's; 0 or absent disables thinking.
+/// Budgets below the API minimum (1024) are treated as off.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ThinkingBudgets {
+    /// Keyed by tier: `commander`, `coordinator`, `executor`.
+    #[serde(default)]
+    pub tiers: std::collections::HashMap<String, u32>,
+    /// Keyed by lower-case agent name.
+    #[serde(default)]
+    pub agents: std::collections::HashMap<String, u32>,
+}
+
+impl ThinkingBudgets {
+    pub fn budget_for(&self, tier: &str, agent: Option<&str>) -> u32 {
+        agent
+            .and_then(|a| self.agents.get(&a.to_lowercase()))
+            .or_else(|| self.tiers.get(&tier.to_lowercase()))
+            .copied()
+            .unwrap_or(0)
+    }
 }
 
 fn default_true() -> bool {
@@ -169,6 +205,16 @@ pub struct HistoryEntry {
     pub timestamp: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_interactions: Option<Vec<ToolInteractionInfo>>,
+    /// Thinking summary of an assistant turn (when `store_thinking` is on).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    /// Previous message on this branch (absent for the first turn).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// Alternative versions of this turn (including this one), oldest first.
+    /// Only present when the turn has been edited/regenerated into branches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sibling_ids: Option<Vec<String>>,
 }
 
 // ── Session ─────────────────────────────────────────────────────────────
@@ -292,4 +338,7 @@ pub struct ToolInteractionInfo {
     pub tool_input: Value,
     pub result: Option<String>,
     pub is_error: bool,
+    /// Wall time of the tool call, when the client measured it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
 }
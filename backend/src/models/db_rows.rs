@@ -7,6 +7,8 @@ use chrono::{DateTime, Utc};
 use serde_json::Value;
 use uuid::Uuid;
 
+use super::chat_models::ThinkingBudgets;
+
 /// DB row for application settings (ch_settings table).
 #[derive(sqlx::FromRow)]
 pub struct SettingsRow {
@@ -42,6 +44,17 @@ pub struct SettingsRow {
     /// Message compaction keep — keep this many recent messages after compaction (default 15)
     #[sqlx(default)]
     pub compaction_keep: i32,
+    /// Daily per-user spend ceiling for auto-tier routing (USD)
+    #[sqlx(default)]
+    pub routing_cost_ceiling_usd: Option<f64>,
+    /// Per-user ceiling overrides (email → USD, as JSON)
+    #[sqlx(default)]
+    pub routing_user_cost_ceilings: sqlx::types::Json<std::collections::HashMap<String, f64>>,
+    /// Extended-thinking budgets (`ThinkingBudgets` as JSON)
+    #[sqlx(default)]
+    pub thinking_budgets: sqlx::types::Json<ThinkingBudgets>,
+    #[sqlx(default)]
+    pub store_thinking: bool,
 }
 
 /// DB row for a chat session (ch_sessions table).
@@ -53,6 +66,15 @@ pub struct SessionRow {
     pub updated_at: DateTime<Utc>,
     #[sqlx(default)]
     pub working_directory: String,
+    /// Session this one was forked from (branching).
+    #[sqlx(default)]
+    pub parent_session_id: Option<Uuid>,
+    /// Message in the parent session at which the fork was made.
+    #[sqlx(default)]
+    pub forked_from_message_id: Option<Uuid>,
+    /// Head of the branch currently checked out.
+    #[sqlx(default)]
+    pub active_leaf_message_id: Option<Uuid>,
 }
 
 /// Lightweight session row for listing (includes message count, no body).
@@ -76,6 +98,12 @@ pub struct MessageRow {
     pub model: Option<String>,
     pub agent: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Previous turn on the same branch (NULL for a branch root).
+    #[sqlx(default)]
+    pub parent_message_id: Option<Uuid>,
+    /// Thinking summary of an assistant turn.
+    #[sqlx(default)]
+    pub thinking: Option<String>,
 }
 
 /// DB row for prompt history (ch_prompt_history table).
@@ -97,6 +125,8 @@ pub struct ToolInteractionRow {
     pub result: Option<String>,
     pub is_error: bool,
     pub executed_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub duration_ms: Option<i32>,
 }
 
 /// DB row for agent configuration (ch_agents_config table).
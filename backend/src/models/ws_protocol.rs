@@ -19,6 +19,10 @@ pub enum WsClientMessage {
         tools_enabled: Option<bool>,
         #[serde(default)]
         session_id: Option<String>,
+        /// Edit-and-resend: user message of `session_id` that `prompt` replaces.
+        /// The new turn is stored as a sibling branch of it.
+        #[serde(default)]
+        edit_message_id: Option<String>,
     },
     /// Cancel the currently running execution.
     Cancel,
@@ -36,9 +40,14 @@ pub enum WsServerMessage {
         model: String,
         #[serde(skip_serializing_if = "Vec::is_empty", default)]
         files_loaded: Vec<String>,
+        /// Auto-tier routing decision (absent when the model was chosen explicitly).
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        routing: Option<RoutingInfo>,
     },
     /// A streamed text token.
     Token { content: String },
+    /// A streamed extended-thinking delta (shown apart from the answer).
+    Thinking { content: String },
     /// Execution completed successfully.
     Complete { duration_ms: u64 },
     /// A tool call has been initiated.
@@ -81,4 +90,25 @@ pub enum WsServerMessage {
     /// Predictive UI hint — suggests views the user might navigate to next.
     /// Frontend uses these to prefetch lazy-loaded chunks and query data.
     ViewHint { views: Vec<String> },
+    /// A spending budget reached its soft limit; execution continues.
+    /// Hard limits end the execution with an `Error` (code `BUDGET_EXCEEDED`).
+    BudgetWarning {
+        budget: String,
+        scope: String,
+        period: String,
+        /// `usd` or `tokens`.
+        unit: String,
+        spent: f64,
+        limit: f64,
+        message: String,
+    },
+}
+
+/// Why auto-tier routing picked the model of an execution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingInfo {
+    /// `flash`, `executor`, `coordinator` or `commander`.
+    pub tier: String,
+    pub score: i32,
+    pub reasons: Vec<String>,
 }
@@ -5,6 +5,9 @@
 //   - Primary: Claude Vision API (Anthropic)
 //   - Fallback: Gemini Vision API (Google)
 //
+// Both providers are called with credentials outside the Vault Bouncer, so
+// every call passes the Vault guard first (lockout and credential policy).
+//
 // All handler logic, types, page splitting, and SSE events live in jaskier-tools.
 // This file only contains:
 //   1. HasOcrProvider impl for AppState (dual-provider extraction)
@@ -20,6 +23,7 @@
 
 use serde_json::{Value, json};
 
+use crate::ai_gateway::vault_guard::CredentialSource;
 use crate::state::AppState;
 
 // ── Re-export shared types from jaskier-tools ────────────────────────────────
@@ -67,18 +71,29 @@ impl HasOcrProvider for AppState {
         };
 
         if let Some(key) = api_key {
-            match ocr_with_claude(&self.http_client, &key, data_b64, mime_type, prompt).await {
-                Ok((text, confidence)) => return Ok((text, "claude".to_string(), confidence)),
-                Err(e) => {
-                    tracing::warn!("Claude OCR failed, trying Gemini fallback: {e}");
+            let allowed = self
+                .ai_gateway
+                .vault_guard
+                .check_api_key("anthropic_ocr", CredentialSource::RuntimeKey);
+            match allowed {
+                Ok(()) => {
+                    match ocr_with_claude(&self.http_client, &key, data_b64, mime_type, prompt)
+                        .await
+                    {
+                        Ok((text, confidence)) => {
+                            return Ok((text, "claude".to_string(), confidence));
+                        }
+                        Err(e) => {
+                            tracing::warn!("Claude OCR failed, trying Gemini fallback: {e}");
+                        }
+                    }
                 }
+                Err(e) => tracing::warn!("Claude OCR skipped, trying Gemini fallback: {e}"),
             }
         }
 
         // Fallback: Gemini Vision API via Google OAuth
-        if let Some((credential, is_oauth)) =
-            jaskier_net_sec::oauth::google::get_google_credential(self).await
-        {
+        if let Some((credential, is_oauth)) = google_credential(self).await? {
             let (text, confidence) = ocr_with_gemini(
                 &self.http_client,
                 &credential,
@@ -347,14 +362,24 @@ async fn ocr_with_gemini(
 
 // ── Structured data extraction ──────────────────────────────────────────────
 
+/// The Google credential for Gemini calls, if any: `Err` during a Vault
+/// lockout, and for an API key unless `CREDENTIAL_POLICY` is `env_allowed`.
+async fn google_credential(state: &AppState) -> Result<Option<(String, bool)>, String> {
+    let guard = &state.ai_gateway.vault_guard;
+    guard.check().map_err(|lockout| lockout.to_string())?;
+    let credential = jaskier_net_sec::oauth::google::get_google_credential(state).await;
+    if let Some((_, false)) = &credential {
+        guard.check_api_key("google_ocr", CredentialSource::EnvKey)?;
+    }
+    Ok(credential)
+}
+
 /// Extract structured data from OCR text. Uses Gemini (text-only, simpler) since
 /// this is a second-pass analysis that doesn't need vision capabilities.
 async fn extract_structured_data(state: &AppState, ocr_text: &str) -> Result<Value, String> {
-    let (credential, is_oauth) = jaskier_net_sec::oauth::google::get_google_credential(state)
-        .await
-        .ok_or_else(|| {
-            "No Google API credential configured for structured extraction".to_string()
-        })?;
+    let (credential, is_oauth) = google_credential(state).await?.ok_or_else(|| {
+        "No Google API credential configured for structured extraction".to_string()
+    })?;
 
     let url = format!("{GEMINI_API_BASE}/{GEMINI_OCR_MODEL}:generateContent");
 
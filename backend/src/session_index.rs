@@ -0,0 +1,658 @@
+// ClaudeHydra v4 -- Session message embeddings (hybrid search)
+//
+// Messages are embedded with the semantic cache's `EmbeddingClient` (Gemini)
+// and stored in a dedicated Qdrant collection (`ch_session_messages`).
+// Indexing is incremental: new messages are embedded fire-and-forget when they
+// are stored, and a background sweep picks up anything that was missed
+// (`ch_messages.embedded_at IS NULL`), which also serves as the backfill job.
+// Embedding calls use the `GOOGLE_API_KEY` env key, so they pass the Vault
+// guard (`check_api_key`) first; during a lockout messages stay pending.
+// Deleted messages (and those of deleted sessions) are queued by a trigger in
+// `ch_message_embedding_deletions`; the sweep removes their points.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tokio::sync::OnceCell;
+
+use crate::ai_gateway::vault_guard::{CredentialSource, VaultGuard};
+use crate::semantic_cache::embeddings::EmbeddingClient;
+use crate::state::AppState;
+
+const COLLECTION: &str = "ch_session_messages";
+const DEFAULT_QDRANT_URL: &str = "http://localhost:6333";
+/// Messages shorter than this (once trimmed) carry too little meaning to embed.
+const MIN_EMBED_CHARS: usize = 20;
+/// Whitespace trimmed before measuring a message: what `btrim` strips in
+/// `HAS_VECTOR_SQL`, so both sides agree on which messages have a point.
+const EMBED_TRIM: &[char] = &[' ', '\t', '\r', '\n'];
+/// `ch_messages` predicate for messages that have a Qdrant point: indexed and
+/// not skipped as too short (`MIN_EMBED_CHARS`).
+pub(crate) const HAS_VECTOR_SQL: &str =
+    "embedded_at IS NOT NULL AND char_length(btrim(content, E' \\t\\r\\n')) >= 20";
+/// Embedding input cap (chars) — long tool dumps are truncated.
+const MAX_EMBED_CHARS: usize = 8_000;
+const BACKFILL_BATCH: i64 = 64;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+// ═══════════════════════════════════════════════════════════════════════
+//  State
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Hit returned by a vector search.
+#[derive(Debug, Clone)]
+pub struct VectorHit {
+    pub message_id: uuid::Uuid,
+    pub session_id: uuid::Uuid,
+    pub score: f32,
+}
+
+/// Progress counters for the backfill job (exposed via the status endpoint).
+#[derive(Debug, Default)]
+pub struct BackfillProgress {
+    pub running: AtomicBool,
+    pub indexed: AtomicU64,
+    pub failed: AtomicU64,
+}
+
+pub struct SessionIndexState {
+    embeddings: Option<EmbeddingClient>,
+    http: reqwest::Client,
+    qdrant_url: String,
+    /// Set once the Qdrant collection exists (created lazily with the first vector's size).
+    collection_ready: OnceCell<()>,
+    pub backfill: BackfillProgress,
+}
+
+impl SessionIndexState {
+    pub fn new(http: reqwest::Client, google_api_key: Option<String>) -> Self {
+        let embeddings = google_api_key
+            .filter(|k| !k.is_empty())
+            .map(|key| EmbeddingClient::new(http.clone(), key));
+        if embeddings.is_none() {
+            tracing::info!(
+                "session_index: GOOGLE_API_KEY not set — semantic session search disabled"
+            );
+        }
+        Self {
+            embeddings,
+            http,
+            qdrant_url: std::env::var("QDRANT_URL")
+                .unwrap_or_else(|_| DEFAULT_QDRANT_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            collection_ready: OnceCell::new(),
+            backfill: BackfillProgress::default(),
+        }
+    }
+
+    /// Disabled index for tests — every operation is a no-op.
+    pub fn new_test() -> Self {
+        Self {
+            embeddings: None,
+            http: reqwest::Client::new(),
+            qdrant_url: "http://localhost:19999".to_string(),
+            collection_ready: OnceCell::new(),
+            backfill: BackfillProgress::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.embeddings.is_some()
+    }
+
+    async fn embed(&self, guard: &VaultGuard, text: &str) -> Result<Vec<f32>, String> {
+        let client = self
+            .embeddings
+            .as_ref()
+            .ok_or_else(|| "embeddings not configured".to_string())?;
+        guard.check_api_key("google_embeddings", CredentialSource::EnvKey)?;
+        let input: String = text.chars().take(MAX_EMBED_CHARS).collect();
+        client.embed(&input).await.map_err(|e| e.to_string())
+    }
+
+    async fn ensure_collection(&self, dim: usize) -> Result<(), String> {
+        self.collection_ready
+            .get_or_try_init(|| async {
+                let url = format!("{}/collections/{COLLECTION}", self.qdrant_url);
+                let exists = self
+                    .http
+                    .get(&url)
+                    .send()
+                    .await
+                    .map(|r| r.status().is_success())
+                    .unwrap_or(false);
+                if !exists {
+                    let resp = self
+                        .http
+                        .put(&url)
+                        .json(&json!({ "vectors": { "size": dim, "distance": "Cosine" } }))
+                        .send()
+                        .await
+                        .map_err(|e| format!("qdrant create collection: {e}"))?;
+                    if !resp.status().is_success() && resp.status().as_u16() != 409 {
+                        return Err(format!("qdrant create collection: HTTP {}", resp.status()));
+                    }
+                    tracing::info!(
+                        "session_index: created Qdrant collection {COLLECTION} (dim={dim})"
+                    );
+                }
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+    }
+
+    async fn qdrant_post(&self, path: &str, body: Value) -> Result<Value, String> {
+        let resp = self
+            .http
+            .post(format!(
+                "{}/collections/{COLLECTION}/{path}",
+                self.qdrant_url
+            ))
+            .json(&body)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| format!("qdrant {path}: {e}"))?;
+        if !resp.status().is_success() {
+            return Err(format!("qdrant {path}: HTTP {}", resp.status()));
+        }
+        resp.json::<Value>()
+            .await
+            .map_err(|e| format!("qdrant {path}: {e}"))
+    }
+
+    /// Embed a single message and upsert it into Qdrant.
+    pub async fn index_message(
+        &self,
+        guard: &VaultGuard,
+        message_id: uuid::Uuid,
+        session_id: uuid::Uuid,
+        role: &str,
+        content: &str,
+    ) -> Result<(), String> {
+        let vector = self.embed(guard, content).await?;
+        self.ensure_collection(vector.len()).await?;
+
+        let resp = self
+            .http
+            .put(format!(
+                "{}/collections/{COLLECTION}/points?wait=false",
+                self.qdrant_url
+            ))
+            .json(&json!({
+                "points": [{
+                    "id": message_id.to_string(),
+                    "vector": vector,
+                    "payload": {
+                        "session_id": session_id.to_string(),
+                        "role": role,
+                    },
+                }]
+            }))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| format!("qdrant upsert: {e}"))?;
+        if !resp.status().is_success() {
+            return Err(format!("qdrant upsert: HTTP {}", resp.status()));
+        }
+        Ok(())
+    }
+
+    /// Remove the points of deleted messages. A missing collection has
+    /// nothing to remove.
+    pub async fn delete_points(&self, message_ids: &[uuid::Uuid]) -> Result<(), String> {
+        let resp = self
+            .http
+            .post(format!(
+                "{}/collections/{COLLECTION}/points/delete?wait=true",
+                self.qdrant_url
+            ))
+            .json(&json!({
+                "points": message_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>(),
+            }))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| format!("qdrant delete: {e}"))?;
+        if !resp.status().is_success() && resp.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(format!("qdrant delete: HTTP {}", resp.status()));
+        }
+        Ok(())
+    }
+
+    /// Nearest messages to a free-text query.
+    pub async fn search(
+        &self,
+        guard: &VaultGuard,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<VectorHit>, String> {
+        let vector = self.embed(guard, query).await?;
+        self.ensure_collection(vector.len()).await?;
+        let body = self
+            .qdrant_post(
+                "points/search",
+                json!({ "vector": vector, "limit": limit, "with_payload": true }),
+            )
+            .await?;
+        Ok(parse_hits(&body))
+    }
+
+    /// Messages from other sessions that are close to the given messages.
+    pub async fn recommend(
+        &self,
+        positive_ids: &[uuid::Uuid],
+        exclude_session: uuid::Uuid,
+        limit: usize,
+    ) -> Result<Vec<VectorHit>, String> {
+        if positive_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let body = self
+            .qdrant_post(
+                "points/recommend",
+                json!({
+                    "positive": positive_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>(),
+                    "limit": limit,
+                    "with_payload": true,
+                    "filter": {
+                        "must_not": [{
+                            "key": "session_id",
+                            "match": { "value": exclude_session.to_string() },
+                        }]
+                    },
+                }),
+            )
+            .await?;
+        Ok(parse_hits(&body))
+    }
+}
+
+fn parse_hits(body: &Value) -> Vec<VectorHit> {
+    body.get("result")
+        .and_then(|r| r.as_array())
+        .map(|points| {
+            points
+                .iter()
+                .filter_map(|p| {
+                    let message_id = p.get("id")?.as_str()?.parse().ok()?;
+                    let session_id = p
+                        .get("payload")?
+                        .get("session_id")?
+                        .as_str()?
+                        .parse()
+                        .ok()?;
+                    let score = p.get("score")?.as_f64()? as f32;
+                    Some(VectorHit {
+                        message_id,
+                        session_id,
+                        score,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Incremental indexing + backfill
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Embed freshly stored messages in the background and mark them as indexed.
+pub fn spawn_index_messages(
+    state: &AppState,
+    messages: Vec<(uuid::Uuid, uuid::Uuid, String, String)>,
+) {
+    if !state.session_index.is_enabled() {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        for (message_id, session_id, role, content) in messages {
+            index_and_mark(&state, message_id, session_id, &role, &content).await;
+        }
+    });
+}
+
+async fn index_and_mark(
+    state: &AppState,
+    message_id: uuid::Uuid,
+    session_id: uuid::Uuid,
+    role: &str,
+    content: &str,
+) -> bool {
+    if !is_embeddable(content) {
+        // Too short to be useful — mark as handled so the sweep skips it.
+        let _ = sqlx::query("UPDATE ch_messages SET embedded_at = NOW() WHERE id = $1")
+            .bind(message_id)
+            .execute(&state.db)
+            .await;
+        return true;
+    }
+    match state
+        .session_index
+        .index_message(
+            &state.ai_gateway.vault_guard,
+            message_id,
+            session_id,
+            role,
+            content,
+        )
+        .await
+    {
+        Ok(()) => {
+            let _ = sqlx::query("UPDATE ch_messages SET embedded_at = NOW() WHERE id = $1")
+                .bind(message_id)
+                .execute(&state.db)
+                .await;
+            true
+        }
+        Err(e) => {
+            tracing::debug!(
+                "session_index: failed to index message {}: {}",
+                message_id,
+                e
+            );
+            false
+        }
+    }
+}
+
+fn is_embeddable(content: &str) -> bool {
+    content.trim_matches(EMBED_TRIM).chars().count() >= MIN_EMBED_CHARS
+}
+
+/// Remove the Qdrant points queued by deletes, oldest first. Rows are only
+/// dropped once Qdrant confirmed, so failures are retried by the next sweep.
+pub async fn purge_deleted(state: &AppState) -> u64 {
+    let mut purged = 0u64;
+    loop {
+        let ids: Vec<uuid::Uuid> = match sqlx::query_scalar(
+            "SELECT message_id FROM ch_message_embedding_deletions \
+             ORDER BY deleted_at ASC LIMIT $1",
+        )
+        .bind(BACKFILL_BATCH)
+        .fetch_all(&state.db)
+        .await
+        {
+            Ok(ids) => ids,
+            Err(e) => {
+                tracing::error!("session_index: deletion queue query failed: {}", e);
+                break;
+            }
+        };
+        if ids.is_empty() {
+            break;
+        }
+        if let Err(e) = state.session_index.delete_points(&ids).await {
+            tracing::warn!("session_index: failed to delete points: {}", e);
+            break;
+        }
+        if let Err(e) =
+            sqlx::query("DELETE FROM ch_message_embedding_deletions WHERE message_id = ANY($1)")
+                .bind(&ids)
+                .execute(&state.db)
+                .await
+        {
+            tracing::error!("session_index: failed to clear deletion queue: {}", e);
+            break;
+        }
+        purged += ids.len() as u64;
+    }
+    purged
+}
+
+/// A message that has not been embedded yet.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct PendingMessage {
+    pub(crate) id: uuid::Uuid,
+    pub(crate) session_id: uuid::Uuid,
+    pub(crate) role: String,
+    pub(crate) content: String,
+    pub(crate) created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Next page of not-yet-embedded messages after the `(created_at, id)` cursor,
+/// oldest first. The id breaks ties, so messages that share a timestamp (rows
+/// from one insert) are not skipped at a page boundary.
+pub(crate) async fn pending_messages(
+    db: &sqlx::PgPool,
+    after: Option<(chrono::DateTime<chrono::Utc>, uuid::Uuid)>,
+    limit: i64,
+) -> Result<Vec<PendingMessage>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, session_id, role, content, created_at FROM ch_messages \
+         WHERE embedded_at IS NULL \
+           AND ($1::TIMESTAMPTZ IS NULL OR (created_at, id) > ($1, $2::UUID)) \
+         ORDER BY created_at ASC, id ASC LIMIT $3",
+    )
+    .bind(after.map(|(created_at, _)| created_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit)
+    .fetch_all(db)
+    .await
+}
+
+/// Embed every message that has not been indexed yet, oldest first.
+///
+/// Returns `(indexed, failed)`. Only one backfill runs at a time; a second
+/// call while one is running returns immediately with `(0, 0)`.
+pub async fn run_backfill(state: &AppState) -> (u64, u64) {
+    let index = &state.session_index;
+    if !index.is_enabled()
+        || index
+            .backfill
+            .running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+    {
+        return (0, 0);
+    }
+
+    let (mut indexed, mut failed) = (0u64, 0u64);
+    let mut cursor = None;
+    loop {
+        let batch = match pending_messages(&state.db, cursor, BACKFILL_BATCH).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                tracing::error!("session_index: backfill query failed: {}", e);
+                break;
+            }
+        };
+
+        if batch.is_empty() {
+            break;
+        }
+        for message in batch {
+            cursor = Some((message.created_at, message.id));
+            if index_and_mark(
+                state,
+                message.id,
+                message.session_id,
+                &message.role,
+                &message.content,
+            )
+            .await
+            {
+                indexed += 1;
+                index.backfill.indexed.fetch_add(1, Ordering::Relaxed);
+            } else {
+                failed += 1;
+                index.backfill.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    index.backfill.running.store(false, Ordering::SeqCst);
+    if indexed + failed > 0 {
+        tracing::info!(
+            "session_index: backfill finished — {} indexed, {} failed",
+            indexed,
+            failed
+        );
+    }
+    (indexed, failed)
+}
+
+/// Periodic sweep that embeds messages stored by paths without an indexing hook
+/// (e.g. shared session handlers), retries earlier failures and removes the
+/// points of deleted messages.
+pub fn spawn_backfill_loop(state: AppState) -> Option<tokio::task::JoinHandle<()>> {
+    if !state.session_index.is_enabled() {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        loop {
+            purge_deleted(&state).await;
+            run_backfill(&state).await;
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hits_skips_malformed_points() {
+        let message_id = uuid::Uuid::new_v4();
+        let session_id = uuid::Uuid::new_v4();
+        let body = json!({ "result": [
+            {
+                "id": message_id.to_string(),
+                "score": 0.87,
+                "payload": { "session_id": session_id.to_string(), "role": "user" },
+            },
+            { "id": 42, "score": 0.5, "payload": { "session_id": session_id.to_string() } },
+            { "id": uuid::Uuid::new_v4().to_string(), "score": 0.4, "payload": {} },
+        ]});
+        let hits = parse_hits(&body);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, message_id);
+        assert_eq!(hits[0].session_id, session_id);
+        assert!((hits[0].score - 0.87).abs() < 1e-6);
+        assert!(parse_hits(&json!({ "status": "error" })).is_empty());
+    }
+
+    /// Skipped unless DATABASE_URL points at a disposable database.
+    #[tokio::test]
+    async fn backfill_pages_keep_messages_sharing_a_timestamp() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let session_id: uuid::Uuid = sqlx::query_scalar(
+            "INSERT INTO ch_sessions (title) VALUES ('backfill paging') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO ch_messages (session_id, role, content, created_at) \
+             SELECT $1, 'user', 'message number ' || g, '2000-01-01T00:00:00Z' \
+             FROM generate_series(1, 5) g",
+        )
+        .bind(session_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mut cursor = None;
+        let mut seen = Vec::new();
+        loop {
+            let page = pending_messages(&pool, cursor, 2).await.unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            cursor = Some((last.created_at, last.id));
+            seen.extend(
+                page.iter()
+                    .filter(|m| m.session_id == session_id)
+                    .map(|m| m.id),
+            );
+        }
+        sqlx::query("DELETE FROM ch_sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(seen.len(), 5);
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 5);
+    }
+
+    /// Skipped unless DATABASE_URL points at a disposable database.
+    #[tokio::test]
+    async fn vector_predicate_matches_the_indexer_and_queues_deletions() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let session_id: uuid::Uuid = sqlx::query_scalar(
+            "INSERT INTO ch_sessions (title) VALUES ('vector predicate') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let contents = [
+            "   short\n\n\t                 ",
+            "exactly twenty chars",
+            "\n  exactly twenty chars  \r\n",
+            "nineteen characters",
+            "a message long enough to be embedded",
+        ];
+        let (mut all_ids, mut ids) = (Vec::new(), Vec::new());
+        for content in contents {
+            let id: uuid::Uuid = sqlx::query_scalar(
+                "INSERT INTO ch_messages (session_id, role, content, embedded_at) \
+                 VALUES ($1, 'user', $2, NOW()) RETURNING id",
+            )
+            .bind(session_id)
+            .bind(content)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            let has_vector: bool = sqlx::query_scalar(&format!(
+                "SELECT {HAS_VECTOR_SQL} FROM ch_messages WHERE id = $1"
+            ))
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            assert_eq!(has_vector, is_embeddable(content), "{content:?}");
+            all_ids.push(id);
+            if has_vector {
+                ids.push(id);
+            }
+        }
+
+        sqlx::query("DELETE FROM ch_sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut queued: Vec<uuid::Uuid> = sqlx::query_scalar(
+            "DELETE FROM ch_message_embedding_deletions WHERE message_id = ANY($1) \
+             RETURNING message_id",
+        )
+        .bind(&all_ids)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        queued.sort();
+        ids.sort();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(queued, ids);
+    }
+}
@@ -27,10 +27,12 @@ use std::time::Instant;
 use crate::ai_gateway::vault_bridge::{HasVaultBridge, VaultClient};
 use crate::ai_gateway::{self, AiGatewayState, HasAiGateway};
 use crate::collab::CollabState;
+use crate::delegation_events::{DelegationEvent, DelegationHistory};
 use crate::memory_pruning::{HasMemoryPruning, MemoryPruningState};
 use crate::models::WitcherAgent;
 use crate::sandbox::{HasSandboxState, SandboxState};
 use crate::semantic_cache::{HasSemanticCache, SemanticCacheState};
+use crate::session_index::SessionIndexState;
 use crate::state_agent_helpers::{init_witcher_agents, load_agents_from_db};
 use crate::swarm::SwarmState;
 use crate::tools::ToolExecutor;
@@ -44,7 +46,7 @@ use crate::tools::ToolExecutor;
 /// - `rate_limit_config` — per-endpoint rate limit configuration
 /// - `http_client` — alias for `base.client` (backward compat field name)
 /// - `circuit_breaker` — alias for `base.gemini_circuit` (backward compat field name)
-/// - `a2a_task_tx` — CH uses `Sender<serde_json::Value>` (Quad Hydras use `Sender<()>`)
+/// - `a2a_task_tx` — CH uses typed `Sender<DelegationEvent>` (Quad Hydras use `Sender<()>`)
 #[derive(Clone)]
 pub struct AppState {
     pub base: BaseHydraState,
@@ -65,10 +67,12 @@ pub struct AppState {
     pub http_client: reqwest::Client,
     /// Circuit breaker for Anthropic API — cloned from `base.gemini_circuit`.
     pub circuit_breaker: Arc<CircuitBreaker>,
-    /// Broadcast channel for A2A delegation updates (CH uses `Sender<Value>`).
-    pub a2a_task_tx: tokio::sync::broadcast::Sender<serde_json::Value>,
+    /// Broadcast channel for A2A delegation lifecycle events.
+    pub a2a_task_tx: tokio::sync::broadcast::Sender<DelegationEvent>,
+    /// Recent delegation events, replayed to SSE clients on connect.
+    pub delegation_history: Arc<DelegationHistory>,
     /// Unit broadcast channel required by `HasAgentState` / `HasA2aState` trait bounds
-    /// (shared router delegates `()` signals; CH's real A2A uses `Sender<Value>` above).
+    /// (shared router delegates `()` signals; CH's real A2A uses `a2a_task_tx` above).
     pub a2a_unit_tx: tokio::sync::broadcast::Sender<()>,
     // ── Profiling (HTTP latency histogram + Web Vitals aggregator) ────
     pub request_metrics: Arc<jaskier_core::profiling::RequestMetrics>,
@@ -79,10 +83,25 @@ pub struct AppState {
     pub collab: CollabState,
     // ── Semantic Cache (Qdrant + Gemini Embeddings) ──────────────────────
     pub semantic_cache: Arc<SemanticCacheState>,
+    // ── Session message embeddings (hybrid search, Qdrant) ──────────────
+    pub session_index: Arc<SessionIndexState>,
     // ── Sandbox (Isolated code execution for agents) ──────────────────────
     pub sandbox: SandboxState,
     // ── Memory Pruning (Self-Reflection & Knowledge Graph cleanup) ──────
     pub memory_pruning: Arc<MemoryPruningState>,
+    // ── Spending budgets (breach counters; limits live in ch_budgets) ────
+    pub budget_metrics: Arc<crate::budgets::BudgetMetrics>,
+    /// Tool latency histograms and outcome counters (Prometheus).
+    pub tool_metrics: Arc<crate::tool_telemetry::ToolMetrics>,
+    /// MCP tool → server map for tool telemetry.
+    pub mcp_tool_servers: Arc<crate::tool_telemetry::McpServerIndex>,
+    /// Model capability metadata (overrides + provider-reported limits).
+    pub model_capabilities: Arc<RwLock<crate::model_registry::CapabilityCache>>,
+    /// Settings / pins / running experiment snapshot (LISTEN/NOTIFY invalidated).
+    pub config_cache: Arc<crate::config_cache::ConfigCache>,
+    /// Set on the state clone an NDJSON chat stream hands to the shared
+    /// handler, whose trait calls carry no request; `None` everywhere else.
+    pub(crate) ndjson_request: Option<Arc<crate::handlers::streaming::helpers::NdjsonRequest>>,
     // ── Unified user authentication (jaskier-auth) ──────────────────────
     pub auth: Arc<jaskier_auth::AuthState>,
 }
@@ -140,19 +159,29 @@ impl AppState {
         // ── Load rate limit config ──────────────────────────────────
         let rate_limit_config = crate::rate_limits::load_from_db(&base.db).await;
 
-        // ── CH-specific A2A broadcast (Sender<Value>, not Sender<()>) ──
+        // ── CH-specific A2A broadcast (typed events, not Sender<()>) ──
         let (a2a_task_tx, _) = tokio::sync::broadcast::channel(100);
         // Unit broadcast required by HasAgentState / HasA2aState trait bounds
         let (a2a_unit_tx, _) = tokio::sync::broadcast::channel(100);
 
         // ── AI Gateway (unified multi-provider + Vault bridge) ─────
-        let vault_client = VaultClient::new(); // default: http://localhost:5190
-        let oauth_manager = ai_gateway::OAuthFlowManager::new(base.client.clone());
+        let vault_client = VaultClient::with_url(&ai_gateway::vault_guard::vault_url());
+        let oauth_manager = ai_gateway::OAuthFlowManager::new(base.client.clone())
+            .with_pkce_store(ai_gateway::oauth_flows::store::from_env(&base.db));
         let ai_gateway_state = Arc::new(AiGatewayState {
-            providers: ai_gateway::default_provider_configs(),
+            providers: ai_gateway::ProviderRegistry::new(
+                crate::gateway_providers::load(&base.db).await,
+            ),
             vault_client,
             oauth_manager,
+            usage: ai_gateway::usage::UsageLedger::new(Some(base.db.clone())),
+            health: ai_gateway::health::ProviderHealth::new(),
+            token_refresh: ai_gateway::token_refresh::TokenRefresh::new(),
+            vault_guard: ai_gateway::vault_guard::VaultGuard::from_env(Some(base.db.clone())),
+            allow_private_upstreams: base.auth_secret.is_none(),
         });
+        ai_gateway_state.usage.seed().await;
+        ai_gateway_state.vault_guard.reload().await;
 
         // ── Backward-compat field aliases ───────────────────────────
         let http_client = base.client.clone();
@@ -166,7 +195,10 @@ impl AppState {
 
         // ── Semantic Cache (Qdrant + Gemini Embeddings) ──────────────
         let google_api_key = std::env::var("GOOGLE_API_KEY").ok();
-        let semantic_cache = Arc::new(SemanticCacheState::new(google_api_key).await);
+        let semantic_cache = Arc::new(SemanticCacheState::new(google_api_key.clone()).await);
+
+        // ── Session message embeddings (hybrid search) ──────────────
+        let session_index = Arc::new(SessionIndexState::new(base.client.clone(), google_api_key));
 
         // ── Sandbox (Docker-based isolated execution) ──────────────
         let sandbox = SandboxState::new();
@@ -184,14 +216,22 @@ impl AppState {
             http_client,
             circuit_breaker,
             a2a_task_tx,
+            delegation_history: Arc::new(DelegationHistory::new()),
             a2a_unit_tx,
             request_metrics: Arc::new(jaskier_core::profiling::RequestMetrics::new()),
             web_vitals: Arc::new(jaskier_core::profiling::WebVitalsAggregator::new()),
             swarm,
             collab,
             semantic_cache,
+            session_index,
             sandbox,
             memory_pruning: Arc::new(MemoryPruningState::new(&db).await),
+            budget_metrics: Arc::new(crate::budgets::BudgetMetrics::default()),
+            tool_metrics: Arc::new(crate::tool_telemetry::ToolMetrics::default()),
+            mcp_tool_servers: Arc::new(crate::tool_telemetry::McpServerIndex::default()),
+            model_capabilities: Arc::new(RwLock::new(Default::default())),
+            config_cache: Arc::new(crate::config_cache::ConfigCache::default()),
+            ndjson_request: None,
             auth,
         }
     }
@@ -236,7 +276,7 @@ impl AppState {
             "ch_mcp_discovered_tools",
         ));
         let circuit_breaker = Arc::new(CircuitBreaker::new("anthropic"));
-        let (a2a_task_tx, _) = tokio::sync::broadcast::channel::<serde_json::Value>(100);
+        let (a2a_task_tx, _) = tokio::sync::broadcast::channel::<DelegationEvent>(100);
         let (a2a_unit_tx, _) = tokio::sync::broadcast::channel::<()>(100);
 
         let log_buffer = Arc::new(jaskier_hydra_state::LogRingBuffer::new(1000));
@@ -261,9 +301,18 @@ impl AppState {
         base.auth_secret = None;
 
         let ai_gateway_state = Arc::new(AiGatewayState {
-            providers: ai_gateway::default_provider_configs(),
+            providers: ai_gateway::ProviderRegistry::new(ai_gateway::GatewayProviders::defaults()),
             vault_client: VaultClient::with_url("http://localhost:19999"), // non-existent in tests
             oauth_manager: ai_gateway::OAuthFlowManager::new(http_client.clone()),
+            usage: ai_gateway::usage::UsageLedger::new(None),
+            health: ai_gateway::health::ProviderHealth::new(),
+            token_refresh: ai_gateway::token_refresh::TokenRefresh::new(),
+            vault_guard: ai_gateway::vault_guard::VaultGuard::new(
+                ai_gateway::vault_guard::CredentialPolicy::EnvAllowed,
+                std::time::Duration::ZERO,
+                None,
+            ),
+            allow_private_upstreams: true,
         });
 
         Self {
@@ -277,14 +326,22 @@ impl AppState {
             http_client,
             circuit_breaker,
             a2a_task_tx,
+            delegation_history: Arc::new(DelegationHistory::new()),
             a2a_unit_tx,
             request_metrics: Arc::new(jaskier_core::profiling::RequestMetrics::new()),
             web_vitals: Arc::new(jaskier_core::profiling::WebVitalsAggregator::new()),
             swarm: SwarmState::new(),
             collab: CollabState::new(),
             semantic_cache: Arc::new(SemanticCacheState::new_test()),
+            session_index: Arc::new(SessionIndexState::new_test()),
             sandbox: SandboxState::new(),
             memory_pruning: Arc::new(MemoryPruningState::new_test()),
+            budget_metrics: Arc::new(crate::budgets::BudgetMetrics::default()),
+            tool_metrics: Arc::new(crate::tool_telemetry::ToolMetrics::default()),
+            mcp_tool_servers: Arc::new(crate::tool_telemetry::McpServerIndex::default()),
+            model_capabilities: Arc::new(RwLock::new(Default::default())),
+            config_cache: Arc::new(crate::config_cache::ConfigCache::default()),
+            ndjson_request: None,
             auth: jaskier_auth::AuthState::new(db, jaskier_auth::AuthConfig::default()),
         }
     }
@@ -356,6 +413,66 @@ impl HasAiGateway for AppState {
     fn ai_gateway(&self) -> &AiGatewayState {
         &self.ai_gateway
     }
+
+    /// Audit every credential change; a provider that needs a re-login is
+    /// also announced to the swarm and via the ai-swarm-notifier MCP server.
+    fn credential_event(
+        &self,
+        event: ai_gateway::token_refresh::RefreshEvent,
+    ) -> futures_util::future::BoxFuture<'_, ()> {
+        use ai_gateway::token_refresh::RefreshEventKind;
+
+        Box::pin(async move {
+            crate::audit::log_audit(
+                &self.base.db,
+                &format!("gateway_token_{}", event.kind.as_str()),
+                serde_json::json!(event),
+                None,
+            )
+            .await;
+
+            let (status, message) = match event.kind {
+                RefreshEventKind::Disconnected => (
+                    "error",
+                    format!(
+                        "{} disconnected — {}",
+                        event.provider,
+                        event.error.as_deref().unwrap_or("credential expired"),
+                    ),
+                ),
+                RefreshEventKind::Expiring => (
+                    "warning",
+                    format!(
+                        "{} session expires soon — log in again via the browser proxy",
+                        event.provider,
+                    ),
+                ),
+                RefreshEventKind::Refreshed | RefreshEventKind::Failed => return,
+            };
+
+            let _ = self.base.swarm_tx.send(jaskier_core::models::AgentMessage {
+                agent_id: "ai-gateway".to_string(),
+                content: message.clone(),
+                is_final: false,
+            });
+            let args = serde_json::json!({
+                "status": status,
+                "agent": "ClaudeHydra",
+                "message": message,
+            });
+            if let Err(e) = self
+                .base
+                .mcp_client
+                .call_tool("mcp_ai_swarm_notifier_show_notification", &args)
+                .await
+            {
+                tracing::debug!(
+                    "MCP notification not sent (server may not be connected): {}",
+                    e
+                );
+            }
+        })
+    }
 }
 
 // ── HasVaultBridge — Jaskier Vault client access ─────────────────────────────
@@ -439,6 +556,14 @@ impl jaskier_core::metrics::HasMetricsState for AppState {
         out.push_str(&self.semantic_cache.metrics.prometheus_output());
         // Memory pruning metrics
         out.push_str(&self.memory_pruning.metrics.prometheus_output());
+        // Budget breaches + current spend vs limits
+        out.push_str(&crate::budgets::prometheus_output(self).await);
+        // Tool latency per tool / MCP server + outcome counters
+        out.push_str(&self.tool_metrics.prometheus_output());
+        // Settings / pins snapshot cache hits, invalidations, listener state
+        out.push_str(&self.config_cache.prometheus_output());
+        // AI gateway circuit breakers and provider health scores
+        out.push_str(&self.ai_gateway.health.prometheus_output());
         out
     }
 }
@@ -610,6 +735,9 @@ impl jaskier_core::mcp::server::HasMcpServerState for AppState {
 // 2. Fallback: Runtime API keys (hot-loaded)
 // 3. Last resort: ANTHROPIC_API_KEY env var
 //
+// Title generation needs a raw key, so it only runs when CREDENTIAL_POLICY is
+// env_allowed, and never during a Vault lockout.
+//
 // NOTE: This trait is used by `generate_title_via_anthropic` in jaskier-core::sessions.
 
 impl jaskier_core::sessions::HasAnthropicCredential for AppState {
@@ -618,6 +746,12 @@ impl jaskier_core::sessions::HasAnthropicCredential for AppState {
     }
 
     async fn get_anthropic_credential(&self) -> Option<(String, bool)> {
+        let guard = &self.ai_gateway.vault_guard;
+        if let Err(lockout) = guard.check() {
+            tracing::warn!("Title generation skipped — {}", lockout);
+            return None;
+        }
+
         // 1. Try Vault first (ai_providers/anthropic_max)
         match self
             .ai_gateway
@@ -638,11 +772,18 @@ impl jaskier_core::sessions::HasAnthropicCredential for AppState {
                     "ANOMALY DETECTED from Vault during title gen credential resolution: {}",
                     msg
                 );
+                guard
+                    .lock(crate::ai_gateway::vault_guard::LockoutTrigger::Anomaly, msg)
+                    .await;
                 return None;
             }
             Err(_) => {}
         }
 
+        if !guard.policy().allows_env() {
+            return None;
+        }
+
         // 2. Try runtime state (hot-loaded API key)
         {
             let rt = self.base.runtime.read().await;
@@ -0,0 +1,597 @@
+// ClaudeHydra v4 -- Auto-tier routing
+//
+// Picks the model tier for chat requests that do not name a model. The score
+// combines prompt complexity and length, conversation length, tool use and
+// referenced file types; recorded outcomes then escalate tiers that have been
+// failing (in the session, or globally for the complexity class) and the
+// daily cost ceiling caps the result. The ceiling is per user: the signed-in
+// user's override (`routing_user_cost_ceilings`) or the instance default,
+// compared with that user's own spend today — anonymous callers share one
+// bucket. Every decision is stored in `ch_routing_decisions` with its
+// signals and reasons for later tuning, and WebSocket clients receive it in
+// `WsServerMessage::Start`.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::config_cache::SettingsSnapshot;
+use crate::models::{ChatRequest, RoutingInfo};
+use crate::state::AppState;
+
+/// Feedback rows needed before a tier's success rate is trusted.
+const MIN_LEARNING_SAMPLES: i64 = 20;
+/// Below this success rate a tier is skipped for the complexity class.
+const MIN_SUCCESS_RATE: f64 = 0.8;
+/// Recent decisions of the session considered for escalation.
+const SESSION_HISTORY: i64 = 3;
+/// Spend share of the ceiling above which commander is no longer chosen.
+const CEILING_SOFT_SHARE: f64 = 0.8;
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Types
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Transport a chat request arrives on — decides which tiers are servable
+/// and whether the outcome is reported back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChatTransport {
+    /// `/ws/chat` — Anthropic only, reports completion.
+    WebSocket,
+    /// NDJSON without tools — Gemini models are served by the hybrid path.
+    NdjsonText,
+    /// NDJSON tool loop — Anthropic only.
+    NdjsonTools,
+}
+
+impl ChatTransport {
+    fn flash_capable(self) -> bool {
+        self == Self::NdjsonText
+    }
+
+    fn tracks_outcome(self) -> bool {
+        self == Self::WebSocket
+    }
+}
+
+/// Cost / capability ladder. Flash is the cheap alternative to executor and
+/// only chosen where the transport can serve Gemini models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Tier {
+    Flash,
+    Executor,
+    Coordinator,
+    Commander,
+}
+
+impl Tier {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Flash => "flash",
+            Self::Executor => "executor",
+            Self::Coordinator => "coordinator",
+            Self::Commander => "commander",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "flash" => Some(Self::Flash),
+            "executor" => Some(Self::Executor),
+            "coordinator" => Some(Self::Coordinator),
+            "commander" => Some(Self::Commander),
+            _ => None,
+        }
+    }
+
+    /// Next tier up (flash and executor both step up to coordinator).
+    fn escalate(self) -> Self {
+        match self {
+            Self::Flash | Self::Executor => Self::Coordinator,
+            Self::Coordinator | Self::Commander => Self::Commander,
+        }
+    }
+}
+
+/// Inputs of a decision, stored as `ch_routing_decisions.signals`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingSignals {
+    pub complexity: String,
+    pub prompt_chars: usize,
+    pub conversation_messages: i64,
+    pub tools_enabled: bool,
+    pub file_kinds: Vec<&'static str>,
+    pub flash_capable: bool,
+    pub spend_today_usd: f64,
+    pub cost_ceiling_usd: Option<f64>,
+}
+
+/// Feedback from earlier decisions.
+#[derive(Debug, Default)]
+struct RoutingFeedback {
+    /// Tiers of recent session decisions that did not succeed.
+    session_failures: Vec<Tier>,
+    /// (successes, total) per tier for the request's complexity class.
+    learned: HashMap<Tier, (i64, i64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoutingDecision {
+    pub tier: Tier,
+    pub model: String,
+    pub score: i32,
+    pub reasons: Vec<String>,
+    /// `ch_routing_decisions.id` when the decision was stored.
+    pub decision_id: Option<i64>,
+}
+
+impl RoutingDecision {
+    pub fn info(&self) -> RoutingInfo {
+        RoutingInfo {
+            tier: self.tier.as_str().to_string(),
+            score: self.score,
+            reasons: self.reasons.clone(),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Signals
+// ═══════════════════════════════════════════════════════════════════════
+
+/// File kinds referenced in the prompt (by extension), sorted and unique.
+fn file_kinds(prompt: &str) -> Vec<&'static str> {
+    let mut kinds: Vec<&'static str> = prompt
+        .split(|c: char| c.is_whitespace() || matches!(c, '`' | '"' | '\'' | '(' | ')' | ','))
+        .filter_map(|token| {
+            let token = token.trim_end_matches(['.', ':', ';', '!', '?']);
+            let (stem, ext) = token.rsplit_once('.')?;
+            if stem.is_empty() || ext.len() > 5 {
+                return None;
+            }
+            match ext.to_ascii_lowercase().as_str() {
+                "rs" | "ts" | "tsx" | "js" | "jsx" | "py" | "go" | "java" | "kt" | "c" | "h"
+                | "cpp" | "hpp" | "cs" | "rb" | "php" | "swift" | "sql" | "sh" | "toml" => {
+                    Some("code")
+                }
+                "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" => Some("image"),
+                "pdf" | "docx" | "md" | "txt" => Some("document"),
+                "csv" | "json" | "yaml" | "yml" | "xml" => Some("data"),
+                _ => None,
+            }
+        })
+        .collect();
+    kinds.sort_unstable();
+    kinds.dedup();
+    kinds
+}
+
+/// Base score from the request alone, with the reasons that contributed.
+fn score(signals: &RoutingSignals) -> (i32, Vec<String>) {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+
+    match signals.complexity.as_str() {
+        "simple" => reasons.push("prompt classified as simple".to_string()),
+        "complex" => {
+            score += 5;
+            reasons.push("prompt classified as complex".to_string());
+        }
+        other => {
+            score += 2;
+            reasons.push(format!("prompt classified as {}", other));
+        }
+    }
+    if signals.prompt_chars > 8_000 {
+        score += 2;
+        reasons.push(format!("very long prompt ({} chars)", signals.prompt_chars));
+    } else if signals.prompt_chars > 2_000 {
+        score += 1;
+        reasons.push(format!("long prompt ({} chars)", signals.prompt_chars));
+    }
+    if signals.conversation_messages > 40 {
+        score += 2;
+        reasons.push(format!(
+            "long conversation ({} messages)",
+            signals.conversation_messages
+        ));
+    } else if signals.conversation_messages > 12 {
+        score += 1;
+        reasons.push(format!(
+            "ongoing conversation ({} messages)",
+            signals.conversation_messages
+        ));
+    }
+    if signals.tools_enabled {
+        score += 1;
+        reasons.push("tools enabled".to_string());
+    }
+    for kind in &signals.file_kinds {
+        match *kind {
+            "code" | "document" => {
+                score += 1;
+                reasons.push(format!("{} files referenced", kind));
+            }
+            _ => reasons.push(format!("{} files referenced (no tier impact)", kind)),
+        }
+    }
+    (score, reasons)
+}
+
+/// Full decision: base tier from the score, feedback escalation, cost cap.
+fn decide(signals: &RoutingSignals, feedback: &RoutingFeedback) -> (Tier, i32, Vec<String>) {
+    let (score, mut reasons) = score(signals);
+
+    let cheap =
+        if signals.flash_capable && !signals.tools_enabled && !signals.file_kinds.contains(&"code")
+        {
+            Tier::Flash
+        } else {
+            Tier::Executor
+        };
+    let mut tier = match score {
+        ..=1 => cheap,
+        2..=4 => Tier::Coordinator,
+        _ => Tier::Commander,
+    };
+    reasons.push(format!("score {} → {}", score, tier.as_str()));
+
+    if feedback.session_failures.iter().any(|t| *t >= tier) && tier != Tier::Commander {
+        tier = tier.escalate();
+        reasons.push(format!(
+            "escalated to {}: a recent {} turn in this session failed",
+            tier.as_str(),
+            feedback
+                .session_failures
+                .iter()
+                .max()
+                .map(|t| t.as_str())
+                .unwrap_or("")
+        ));
+    }
+
+    while tier != Tier::Commander {
+        let Some(&(ok, total)) = feedback.learned.get(&tier) else {
+            break;
+        };
+        let rate = ok as f64 / total as f64;
+        if total < MIN_LEARNING_SAMPLES || rate >= MIN_SUCCESS_RATE {
+            break;
+        }
+        let from = tier;
+        tier = tier.escalate();
+        reasons.push(format!(
+            "escalated to {}: {} succeeds {:.0}% on {} prompts (n={})",
+            tier.as_str(),
+            from.as_str(),
+            rate * 100.0,
+            signals.complexity,
+            total
+        ));
+    }
+
+    if let Some(ceiling) = signals.cost_ceiling_usd {
+        let spent = signals.spend_today_usd;
+        if spent >= ceiling && tier > cheap {
+            tier = cheap;
+            reasons.push(format!(
+                "capped to {}: daily cost ceiling reached (${:.2} of ${:.2})",
+                tier.as_str(),
+                spent,
+                ceiling
+            ));
+        } else if spent >= ceiling * CEILING_SOFT_SHARE && tier == Tier::Commander {
+            tier = Tier::Coordinator;
+            reasons.push(format!(
+                "capped to coordinator: near daily cost ceiling (${:.2} of ${:.2})",
+                spent, ceiling
+            ));
+        }
+    }
+
+    (tier, score, reasons)
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Routing
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Daily cost ceiling of `user_id`: its override, else the instance default.
+fn cost_ceiling_for(settings: &SettingsSnapshot, user_id: Option<&str>) -> Option<f64> {
+    user_id
+        .and_then(|user| settings.routing_user_cost_ceilings.get(user).copied())
+        .or(settings.routing_cost_ceiling_usd)
+}
+
+async fn load_feedback(
+    state: &AppState,
+    session_id: Option<uuid::Uuid>,
+    complexity: &str,
+) -> RoutingFeedback {
+    // Rows without an outcome after 10 minutes count as failures.
+    let finished = "outcome_tracked AND (completed_at IS NOT NULL \
+                    OR created_at < NOW() - INTERVAL '10 minutes')";
+
+    let session_failures = match session_id {
+        Some(sid) => sqlx::query_as::<_, (String, Option<bool>)>(&format!(
+            "SELECT tier, success FROM ch_routing_decisions \
+             WHERE session_id = $1 AND {finished} \
+             ORDER BY created_at DESC LIMIT $2"
+        ))
+        .bind(sid)
+        .bind(SESSION_HISTORY)
+        .fetch_all(&state.db)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, success)| *success != Some(true))
+        .filter_map(|(tier, _)| Tier::parse(&tier))
+        .collect(),
+        None => Vec::new(),
+    };
+
+    let learned = sqlx::query_as::<_, (String, i64, i64)>(&format!(
+        "SELECT tier, COUNT(*) FILTER (WHERE success), COUNT(*) FROM ch_routing_decisions \
+         WHERE complexity = $1 AND created_at > NOW() - INTERVAL '7 days' AND {finished} \
+         GROUP BY tier"
+    ))
+    .bind(complexity)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|(tier, ok, total)| Tier::parse(&tier).map(|t| (t, (ok, total))))
+    .collect();
+
+    RoutingFeedback {
+        session_failures,
+        learned,
+    }
+}
+
+/// Route a request without an explicit model and record the decision.
+/// `user_id` is the signed-in user (None = anonymous).
+pub(crate) async fn route(
+    state: &AppState,
+    req: &ChatRequest,
+    session_id: Option<uuid::Uuid>,
+    user_id: Option<&str>,
+    transport: ChatTransport,
+) -> RoutingDecision {
+    let prompt = req
+        .messages
+        .last()
+        .map(|m| m.content.as_str())
+        .unwrap_or("");
+    let complexity = crate::model_registry::classify_complexity(prompt).to_string();
+
+    // Stored history of the active branch plus the new prompt.
+    let conversation_messages = match session_id {
+        Some(sid) => {
+            let stored: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM ch_messages WHERE session_id = $1 AND on_active_path",
+            )
+            .bind(sid)
+            .fetch_one(&state.db)
+            .await
+            .unwrap_or(0);
+            stored + 1
+        }
+        None => req.messages.len() as i64,
+    };
+
+    let config = crate::config_cache::snapshot(state).await;
+    let cost_ceiling_usd = cost_ceiling_for(&config.settings, user_id);
+    let spend_today_usd = match cost_ceiling_usd {
+        Some(_) => crate::handlers::pricing::spend_today_usd(&state.db, user_id).await,
+        None => 0.0,
+    };
+
+    let signals = RoutingSignals {
+        complexity,
+        prompt_chars: prompt.chars().count(),
+        conversation_messages,
+        tools_enabled: req.tools_enabled.unwrap_or(false),
+        file_kinds: file_kinds(prompt),
+        flash_capable: transport.flash_capable(),
+        spend_today_usd,
+        cost_ceiling_usd,
+    };
+    let feedback = load_feedback(state, session_id, &signals.complexity).await;
+    let (tier, score, reasons) = decide(&signals, &feedback);
+    let model = crate::model_registry::get_model_id(state, tier.as_str()).await;
+
+    tracing::info!(
+        tier = tier.as_str(),
+        model = %model,
+        score,
+        reasons = ?reasons,
+        "auto-tier routing"
+    );
+
+    let decision_id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO ch_routing_decisions \
+         (session_id, tier, model, complexity, score, signals, reasons, outcome_tracked, \
+          user_id) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
+    )
+    .bind(session_id)
+    .bind(tier.as_str())
+    .bind(&model)
+    .bind(&signals.complexity)
+    .bind(score)
+    .bind(json!(signals))
+    .bind(json!(reasons))
+    .bind(transport.tracks_outcome())
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| tracing::error!("Failed to record routing decision: {}", e))
+    .ok();
+
+    RoutingDecision {
+        tier,
+        model,
+        score,
+        reasons,
+        decision_id,
+    }
+}
+
+/// Record the outcome of a routed execution. A run succeeds when it completes
+/// and at most half of its tool calls failed.
+pub(crate) async fn complete_decision(
+    state: &AppState,
+    decision_id: i64,
+    execution_id: &str,
+    latency_ms: u128,
+    tool_calls: u32,
+    tool_errors: u32,
+) {
+    let success = tool_errors * 2 <= tool_calls;
+    if let Err(e) = sqlx::query(
+        "UPDATE ch_routing_decisions \
+         SET execution_id = $2, success = $3, latency_ms = $4, completed_at = NOW() \
+         WHERE id = $1",
+    )
+    .bind(decision_id)
+    .bind(execution_id)
+    .bind(success)
+    .bind(latency_ms.min(i32::MAX as u128) as i32)
+    .execute(&state.db)
+    .await
+    {
+        tracing::error!("Failed to record routing outcome {}: {}", decision_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signals(complexity: &str) -> RoutingSignals {
+        RoutingSignals {
+            complexity: complexity.to_string(),
+            prompt_chars: 40,
+            conversation_messages: 1,
+            tools_enabled: false,
+            file_kinds: Vec::new(),
+            flash_capable: true,
+            spend_today_usd: 0.0,
+            cost_ceiling_usd: None,
+        }
+    }
+
+    #[test]
+    fn simple_prompts_go_to_cheap_tiers() {
+        let feedback = RoutingFeedback::default();
+        assert_eq!(decide(&signals("simple"), &feedback).0, Tier::Flash);
+
+        let mut with_tools = signals("simple");
+        with_tools.tools_enabled = true;
+        assert_eq!(decide(&with_tools, &feedback).0, Tier::Executor);
+
+        assert_eq!(decide(&signals("medium"), &feedback).0, Tier::Coordinator);
+        assert_eq!(decide(&signals("complex"), &feedback).0, Tier::Commander);
+    }
+
+    #[test]
+    fn feedback_escalates_and_ceiling_caps() {
+        let feedback = RoutingFeedback {
+            session_failures: vec![Tier::Flash],
+            learned: HashMap::new(),
+        };
+        let (tier, _, reasons) = decide(&signals("simple"), &feedback);
+        assert_eq!(tier, Tier::Coordinator);
+        assert!(reasons.iter().any(|r| r.contains("in this session failed")));
+
+        let feedback = RoutingFeedback {
+            session_failures: Vec::new(),
+            learned: HashMap::from([(Tier::Coordinator, (10, 30))]),
+        };
+        assert_eq!(decide(&signals("medium"), &feedback).0, Tier::Commander);
+
+        let mut capped = signals("complex");
+        capped.cost_ceiling_usd = Some(5.0);
+        capped.spend_today_usd = 4.5;
+        assert_eq!(decide(&capped, &feedback).0, Tier::Coordinator);
+        capped.spend_today_usd = 6.0;
+        assert_eq!(decide(&capped, &feedback).0, Tier::Flash);
+    }
+
+    #[test]
+    fn ceiling_is_keyed_per_user() {
+        let mut settings = SettingsSnapshot::default();
+        assert_eq!(cost_ceiling_for(&settings, Some("ciri@example.com")), None);
+
+        settings.routing_cost_ceiling_usd = Some(5.0);
+        settings
+            .routing_user_cost_ceilings
+            .insert("ciri@example.com".to_string(), 20.0);
+        assert_eq!(
+            cost_ceiling_for(&settings, Some("ciri@example.com")),
+            Some(20.0)
+        );
+        assert_eq!(
+            cost_ceiling_for(&settings, Some("geralt@example.com")),
+            Some(5.0)
+        );
+        assert_eq!(cost_ceiling_for(&settings, None), Some(5.0));
+
+        // An override also applies when there is no instance default.
+        settings.routing_cost_ceiling_usd = None;
+        assert_eq!(
+            cost_ceiling_for(&settings, Some("ciri@example.com")),
+            Some(20.0)
+        );
+        assert_eq!(
+            cost_ceiling_for(&settings, Some("geralt@example.com")),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn spend_lookup_is_keyed_per_user() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let user = format!("{}@example.com", uuid::Uuid::new_v4());
+        let before = crate::handlers::pricing::spend_today_usd(&pool, Some(&user)).await;
+        assert_eq!(before, 0.0);
+
+        sqlx::query(
+            "INSERT INTO ch_agent_usage (model, input_tokens, output_tokens, total_tokens, \
+             latency_ms, success, tier, user_id) \
+             VALUES ('claude-sonnet-4-6', 1000000, 0, 1000000, 10, TRUE, 'coordinator', $1)",
+        )
+        .bind(&user)
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(crate::handlers::pricing::spend_today_usd(&pool, Some(&user)).await > 0.0);
+        let other = format!("{}@example.com", uuid::Uuid::new_v4());
+        assert_eq!(
+            crate::handlers::pricing::spend_today_usd(&pool, Some(&other)).await,
+            0.0
+        );
+
+        sqlx::query("DELETE FROM ch_agent_usage WHERE user_id = $1")
+            .bind(&user)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn detects_referenced_file_kinds() {
+        assert_eq!(
+            file_kinds("fix src/main.rs and compare with report.pdf, see `logo.png`."),
+            vec!["code", "document", "image"]
+        );
+        assert!(file_kinds("version 4.6 is out. e.g. this").is_empty());
+    }
+}
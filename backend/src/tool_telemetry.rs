@@ -0,0 +1,440 @@
+// ClaudeHydra v4 -- Tool execution telemetry
+//
+// Every tool call of the WebSocket tool loop and of delegated agents is
+// written to `ch_execution_steps` with its wall time, outcome (ok / error /
+// timeout / panic), result size, whether the result was truncated for the
+// model context, the MCP server that served it and a normalised error class.
+// The same observations feed in-memory Prometheus histograms per tool and per
+// MCP server; `GET /api/analytics/tool-latency` reports percentiles and
+// failure modes from the table.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, RwLock};
+
+use crate::state::AppState;
+
+/// Histogram bucket upper bounds in milliseconds.
+const BUCKETS_MS: [u64; 12] = [
+    10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 120_000,
+];
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Outcomes and error classes
+// ═══════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolOutcome {
+    Ok,
+    Error,
+    /// The executor gave up after `TOOL_TIMEOUT_SECS`.
+    Timeout,
+    /// The tool task panicked.
+    Panic,
+}
+
+impl ToolOutcome {
+    pub fn from_error(is_error: bool) -> Self {
+        if is_error { Self::Error } else { Self::Ok }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Error => "error",
+            Self::Timeout => "timeout",
+            Self::Panic => "panic",
+        }
+    }
+
+    pub fn is_error(self) -> bool {
+        self != Self::Ok
+    }
+}
+
+/// One finished tool call.
+pub(crate) struct ToolExecution<'a> {
+    pub tool_name: &'a str,
+    pub outcome: ToolOutcome,
+    /// Result text as returned by the tool (before context truncation).
+    pub result: &'a str,
+    /// Whether the result was cut down before being sent to the model.
+    pub truncated: bool,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u128,
+}
+
+impl ToolExecution<'_> {
+    pub fn error_class(&self) -> Option<&'static str> {
+        match self.outcome {
+            ToolOutcome::Ok => None,
+            ToolOutcome::Timeout => Some("timeout"),
+            ToolOutcome::Panic => Some("panic"),
+            ToolOutcome::Error => Some(classify_error(self.tool_name, self.result)),
+        }
+    }
+}
+
+/// Error classes by the start of a (lower-cased) tool error message. Tool
+/// errors open with one of these fixed phrases; matching only the start keeps
+/// paths, API bodies or model text later in the message from changing the class.
+const PREFIX_CLASSES: &[(&str, &str)] = &[
+    ("access denied", "path_denied"),
+    ("permission denied", "path_denied"),
+    ("no allowed directories", "path_denied"),
+    ("execution timed out", "timeout"),
+    ("agent delegation timed out", "timeout"),
+    ("file not found", "not_found"),
+    ("image file not found", "not_found"),
+    ("missing required", "invalid_input"),
+    ("invalid ", "invalid_input"),
+    ("unknown ", "invalid_input"),
+    ("not a ", "invalid_input"),
+    ("page ", "invalid_input"),
+    ("cannot read", "io_error"),
+    ("cannot resolve", "io_error"),
+    ("failed to write", "io_error"),
+    ("failed to create", "io_error"),
+];
+
+/// Map a tool error message to a small, stable set of classes.
+pub(crate) fn classify_error(tool_name: &str, message: &str) -> &'static str {
+    if tool_name.starts_with("mcp_") {
+        return "mcp_error";
+    }
+    let m = message.trim_start().to_lowercase();
+    // The web tools wrap their errors.
+    let m = m.strip_prefix("tool_error: ").unwrap_or(&m);
+    // Everything up to the first ": " names the error; the rest is detail.
+    let label = m.split(": ").next().unwrap_or_default();
+    let sentence = m.split(". ").next().unwrap_or_default();
+
+    if m.starts_with("tool '") && sentence.contains("' timed out") {
+        return "timeout";
+    }
+    if let Some(status) = http_status(label) {
+        return match status {
+            401 | 403 => "auth",
+            404 => "not_found",
+            429 => "rate_limited",
+            _ => "upstream_http",
+        };
+    }
+    // Integration token missing, under-scoped or unreadable.
+    if sentence.ends_with(" is not connected")
+        || sentence.contains(" is connected without the required scope")
+        || (label.starts_with("cannot read the ") && label.ends_with(" token from jaskier vault"))
+    {
+        return "auth";
+    }
+    if let Some((_, class)) = PREFIX_CLASSES.iter().find(|(p, _)| m.starts_with(p)) {
+        return *class;
+    }
+    if label.ends_with("request failed") || (tool_name == "call_agent" && m.starts_with('[')) {
+        // Connection failures, and provider errors of a delegated agent
+        // ("[Agent error: …]").
+        "upstream_http"
+    } else {
+        "other"
+    }
+}
+
+/// HTTP status named in the label of an API error: "GitHub API returned
+/// HTTP 404", "Vercel rejected the stored token (HTTP 403)", "Claude API
+/// error 429".
+fn http_status(label: &str) -> Option<u16> {
+    ["returned http ", "(http ", "api error "]
+        .iter()
+        .find_map(|marker| {
+            let rest = &label[label.find(marker)? + marker.len()..];
+            let digits = rest.get(..3)?;
+            if rest[3..].starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            digits.parse().ok().filter(|s| (100..600).contains(s))
+        })
+}
+
+/// Prefixed MCP tool name → name of the server exposing it. Prefixed names
+/// embed the server, so entries stay valid; the map is rebuilt from the MCP
+/// client only when a tool is missing (e.g. a server connected since).
+#[derive(Debug, Default)]
+pub struct McpServerIndex {
+    servers: RwLock<HashMap<String, String>>,
+}
+
+impl McpServerIndex {
+    fn get(&self, tool_name: &str) -> Option<String> {
+        let servers = self.servers.read().unwrap_or_else(|e| e.into_inner());
+        servers.get(tool_name).cloned()
+    }
+
+    fn replace(&self, tools: impl IntoIterator<Item = (String, String)>) {
+        let mut servers = self.servers.write().unwrap_or_else(|e| e.into_inner());
+        *servers = tools.into_iter().collect();
+    }
+}
+
+/// Name of the MCP server exposing a prefixed `mcp_*` tool.
+pub(crate) async fn mcp_server_of(state: &AppState, tool_name: &str) -> Option<String> {
+    if !tool_name.starts_with("mcp_") {
+        return None;
+    }
+    let index = &state.mcp_tool_servers;
+    if let Some(server) = index.get(tool_name) {
+        return Some(server);
+    }
+    let tools = state.mcp_client.list_all_tools().await;
+    index.replace(tools.into_iter().map(|t| (t.prefixed_name, t.server_name)));
+    index.get(tool_name)
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Prometheus
+// ═══════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    buckets: [u64; BUCKETS_MS.len()],
+    count: u64,
+    sum_ms: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, ms: u64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS_MS) {
+            if ms <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum_ms += ms;
+    }
+
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS_MS) {
+            out.push_str(&format!(
+                "{name}_bucket{{{labels},le=\"{}\"}} {bucket}\n",
+                bound as f64 / 1000.0
+            ));
+        }
+        out.push_str(&format!(
+            "{name}_bucket{{{labels},le=\"+Inf\"}} {}\n\
+             {name}_sum{{{labels}}} {}\n\
+             {name}_count{{{labels}}} {}\n",
+            self.count,
+            self.sum_ms as f64 / 1000.0,
+            self.count
+        ));
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    tools: BTreeMap<String, Histogram>,
+    servers: BTreeMap<String, Histogram>,
+    executions: BTreeMap<(String, &'static str, &'static str), u64>,
+}
+
+/// Tool latency histograms and outcome counters since process start.
+#[derive(Debug, Default)]
+pub struct ToolMetrics {
+    inner: Mutex<MetricsInner>,
+}
+
+impl ToolMetrics {
+    pub(crate) fn observe(
+        &self,
+        tool_name: &str,
+        mcp_server: Option<&str>,
+        outcome: ToolOutcome,
+        error_class: Option<&'static str>,
+        duration_ms: u64,
+    ) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .tools
+            .entry(tool_name.to_string())
+            .or_default()
+            .observe(duration_ms);
+        if let Some(server) = mcp_server {
+            inner
+                .servers
+                .entry(server.to_string())
+                .or_default()
+                .observe(duration_ms);
+        }
+        *inner
+            .executions
+            .entry((
+                tool_name.to_string(),
+                outcome.as_str(),
+                error_class.unwrap_or("none"),
+            ))
+            .or_default() += 1;
+    }
+
+    pub fn prometheus_output(&self) -> String {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.tools.is_empty() {
+            return String::new();
+        }
+        let escape = crate::budgets::escape_label;
+
+        let mut out = String::from(
+            "# HELP claudehydra_tool_duration_seconds Tool execution wall time.\n\
+             # TYPE claudehydra_tool_duration_seconds histogram\n",
+        );
+        for (tool, histogram) in &inner.tools {
+            let labels = format!("tool=\"{}\"", escape(tool));
+            histogram.write(&mut out, "claudehydra_tool_duration_seconds", &labels);
+        }
+
+        if !inner.servers.is_empty() {
+            out.push_str(
+                "# HELP claudehydra_mcp_tool_duration_seconds MCP tool call wall time per server.\n\
+                 # TYPE claudehydra_mcp_tool_duration_seconds histogram\n",
+            );
+            for (server, histogram) in &inner.servers {
+                let labels = format!("server=\"{}\"", escape(server));
+                histogram.write(&mut out, "claudehydra_mcp_tool_duration_seconds", &labels);
+            }
+        }
+
+        out.push_str(
+            "# HELP claudehydra_tool_executions_total Tool executions by outcome and error class.\n\
+             # TYPE claudehydra_tool_executions_total counter\n",
+        );
+        for ((tool, outcome, error_class), count) in &inner.executions {
+            out.push_str(&format!(
+                "claudehydra_tool_executions_total{{tool=\"{}\",outcome=\"{outcome}\",\
+                 error_class=\"{error_class}\"}} {count}\n",
+                escape(tool)
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_tool_errors() {
+        assert_eq!(
+            classify_error(
+                "read_file",
+                "Access denied: path '/etc' is outside allowed directories"
+            ),
+            "path_denied"
+        );
+        assert_eq!(
+            classify_error("read_file", "File not found: a.txt"),
+            "not_found"
+        );
+        assert_eq!(
+            classify_error("github_list_repos", "GitHub API error 502: bad gateway"),
+            "upstream_http"
+        );
+        assert_eq!(
+            classify_error("mcp_docs_search", "server closed connection"),
+            "mcp_error"
+        );
+        assert_eq!(
+            classify_error("sandbox_execute_code", "Execution timed out after 30s"),
+            "timeout"
+        );
+    }
+
+    #[test]
+    fn classes_come_from_the_error_label_not_its_detail() {
+        for (tool, message, class) in [
+            ("read_file", "File not found: /home/me/.token", "not_found"),
+            (
+                "read_file",
+                "Cannot read file: /srv/http/status.log",
+                "io_error",
+            ),
+            (
+                "list_directory",
+                "Not a directory: /data/404",
+                "invalid_input",
+            ),
+            (
+                "github_get_issue",
+                "GitHub API returned HTTP 404: Not Found",
+                "not_found",
+            ),
+            (
+                "github_list_repos",
+                "GitHub API returned HTTP 500: token 404 status unauthorized",
+                "upstream_http",
+            ),
+            (
+                "vercel_list_projects",
+                "Vercel rejected the stored token (HTTP 403). Reconnect Vercel via POST /api/integrations/vercel/connect.",
+                "auth",
+            ),
+            (
+                "github_list_repos",
+                "GitHub is not connected. Connect it via POST /api/integrations/github/connect.",
+                "auth",
+            ),
+            (
+                "analyze_image",
+                "Claude API error 429: slow down",
+                "rate_limited",
+            ),
+            (
+                "github_list_repos",
+                "GitHub request failed: connection reset",
+                "upstream_http",
+            ),
+            (
+                "call_agent",
+                "[Yennefer error: AI provider request failed]",
+                "upstream_http",
+            ),
+            ("call_agent", "Tool 'read_file' timed out", "timeout"),
+            (
+                "web_fetch",
+                "TOOL_ERROR: Invalid URL: ftp://x",
+                "invalid_input",
+            ),
+            (
+                "web_fetch",
+                "TOOL_ERROR: token bucket status http 404",
+                "other",
+            ),
+        ] {
+            assert_eq!(classify_error(tool, message), class, "{message}");
+        }
+    }
+
+    #[test]
+    fn mcp_server_index_is_replaced_on_refresh() {
+        let index = McpServerIndex::default();
+        assert_eq!(index.get("mcp_docs_search"), None);
+        index.replace([("mcp_docs_search".to_string(), "docs".to_string())]);
+        assert_eq!(index.get("mcp_docs_search").as_deref(), Some("docs"));
+        index.replace([("mcp_git_log".to_string(), "git".to_string())]);
+        assert_eq!(index.get("mcp_docs_search"), None);
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = ToolMetrics::default();
+        metrics.observe("read_file", None, ToolOutcome::Ok, None, 40);
+        metrics.observe("read_file", None, ToolOutcome::Ok, None, 700);
+        let out = metrics.prometheus_output();
+        assert!(out.contains(
+            "claudehydra_tool_duration_seconds_bucket{tool=\"read_file\",le=\"0.05\"} 1\n"
+        ));
+        assert!(
+            out.contains(
+                "claudehydra_tool_duration_seconds_bucket{tool=\"read_file\",le=\"1\"} 2\n"
+            )
+        );
+        assert!(out.contains("claudehydra_tool_duration_seconds_count{tool=\"read_file\"} 2\n"));
+    }
+}
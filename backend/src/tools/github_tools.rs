@@ -1,12 +1,20 @@
 // Jaskier Shared Pattern — GitHub Tools
 // Adapter: tool definitions are local (use crate::models::ToolDefinition),
-// execution delegates to jaskier_tools::tools::github_tools.
+// execution calls the GitHub REST API with the token connected through
+// `/api/integrations/github` (stored in Jaskier Vault).
 
+use std::time::Duration;
+
+use reqwest::Method;
 use serde_json::{Value, json};
 
+use crate::ai_gateway::handlers::integrations::integration_token;
+use crate::ai_gateway::oauth_flows::OAuthProvider;
 use crate::models::ToolDefinition;
 use crate::state::AppState;
 
+const GITHUB_API: &str = "https://api.github.com";
+
 // ═══════════════════════════════════════════════════════════════════════
 //  Tool definitions (local — uses crate::models::ToolDefinition)
 // ═══════════════════════════════════════════════════════════════════════
@@ -160,14 +168,202 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
 }
 
 // ═══════════════════════════════════════════════════════════════════════
-//  Tool execution — delegates to shared jaskier-tools crate
+//  Tool execution — GitHub REST API
 // ═══════════════════════════════════════════════════════════════════════
 
 pub async fn execute(tool_name: &str, input: &Value, state: &AppState) -> (String, bool) {
-    match jaskier_tools::tools::github_tools::execute(tool_name, input, state, &state.http_client)
-        .await
-    {
+    // Not connected / missing scopes: tell the model instead of calling GitHub.
+    let token = match integration_token(state, OAuthProvider::GitHub).await {
+        Ok(token) => token,
+        Err(message) => return (message, true),
+    };
+    match run(tool_name, input, state, &token.access_token).await {
         Ok(result) => (result, false),
         Err(e) => (e, true),
     }
 }
+
+fn str_arg<'a>(input: &'a Value, key: &str) -> Result<&'a str, String> {
+    input
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("Missing required argument '{}'", key))
+}
+
+async fn run(
+    tool_name: &str,
+    input: &Value,
+    state: &AppState,
+    token: &str,
+) -> Result<String, String> {
+    let result = match tool_name {
+        "github_list_repos" => {
+            let sort = input
+                .get("sort")
+                .and_then(Value::as_str)
+                .unwrap_or("updated");
+            let per_page = input
+                .get("per_page")
+                .and_then(Value::as_u64)
+                .unwrap_or(30)
+                .min(100);
+            let repos = github_request(
+                state,
+                token,
+                Method::GET,
+                &format!("/user/repos?sort={}&per_page={}", sort, per_page),
+                None,
+            )
+            .await?;
+            Value::Array(
+                repos
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|r| {
+                        json!({
+                            "full_name": r["full_name"],
+                            "description": r["description"],
+                            "language": r["language"],
+                            "stars": r["stargazers_count"],
+                            "visibility": r["visibility"],
+                            "updated_at": r["updated_at"],
+                        })
+                    })
+                    .collect(),
+            )
+        }
+        "github_get_repo" => {
+            let path = format!(
+                "/repos/{}/{}",
+                str_arg(input, "owner")?,
+                str_arg(input, "repo")?
+            );
+            github_request(state, token, Method::GET, &path, None).await?
+        }
+        "github_list_issues" => {
+            let issue_state = input.get("state").and_then(Value::as_str).unwrap_or("open");
+            let path = format!(
+                "/repos/{}/{}/issues?state={}",
+                str_arg(input, "owner")?,
+                str_arg(input, "repo")?,
+                issue_state,
+            );
+            let issues = github_request(state, token, Method::GET, &path, None).await?;
+            Value::Array(
+                issues
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|i| {
+                        json!({
+                            "number": i["number"],
+                            "title": i["title"],
+                            "state": i["state"],
+                            "author": i["user"]["login"],
+                            "comments": i["comments"],
+                            "is_pull_request": i.get("pull_request").is_some(),
+                            "url": i["html_url"],
+                        })
+                    })
+                    .collect(),
+            )
+        }
+        "github_get_issue" => {
+            let number = input
+                .get("number")
+                .and_then(Value::as_u64)
+                .ok_or("Missing required argument 'number'")?;
+            let path = format!(
+                "/repos/{}/{}/issues/{}",
+                str_arg(input, "owner")?,
+                str_arg(input, "repo")?,
+                number,
+            );
+            let issue = github_request(state, token, Method::GET, &path, None).await?;
+            let comments = github_request(
+                state,
+                token,
+                Method::GET,
+                &format!("{}/comments", path),
+                None,
+            )
+            .await?;
+            json!({ "issue": issue, "comments": comments })
+        }
+        "github_create_issue" => {
+            let path = format!(
+                "/repos/{}/{}/issues",
+                str_arg(input, "owner")?,
+                str_arg(input, "repo")?
+            );
+            let body = json!({
+                "title": str_arg(input, "title")?,
+                "body": input.get("body").and_then(Value::as_str).unwrap_or_default(),
+            });
+            let issue = github_request(state, token, Method::POST, &path, Some(body)).await?;
+            json!({ "number": issue["number"], "url": issue["html_url"] })
+        }
+        "github_create_pr" => {
+            let path = format!(
+                "/repos/{}/{}/pulls",
+                str_arg(input, "owner")?,
+                str_arg(input, "repo")?
+            );
+            let body = json!({
+                "title": str_arg(input, "title")?,
+                "body": input.get("body").and_then(Value::as_str).unwrap_or_default(),
+                "head": str_arg(input, "head")?,
+                "base": input.get("base").and_then(Value::as_str).unwrap_or("main"),
+            });
+            let pr = github_request(state, token, Method::POST, &path, Some(body)).await?;
+            json!({ "number": pr["number"], "url": pr["html_url"] })
+        }
+        _ => return Err(format!("Unknown GitHub tool: {}", tool_name)),
+    };
+    serde_json::to_string_pretty(&result).map_err(|e| e.to_string())
+}
+
+async fn github_request(
+    state: &AppState,
+    token: &str,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> Result<Value, String> {
+    let mut request = state
+        .http_client
+        .request(method, format!("{}{}", GITHUB_API, path))
+        .bearer_auth(token)
+        .header("accept", "application/vnd.github+json")
+        .header("user-agent", "ClaudeHydra")
+        .header("x-github-api-version", "2022-11-28")
+        .timeout(Duration::from_secs(30));
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| format!("GitHub request failed: {}", e))?;
+    let status = resp.status();
+    let body: Value = resp.json().await.unwrap_or(Value::Null);
+
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err("GitHub rejected the stored token (HTTP 401). \
+             Reconnect GitHub via POST /api/integrations/github/connect."
+            .to_string());
+    }
+    if !status.is_success() {
+        return Err(format!(
+            "GitHub API returned HTTP {}: {}",
+            status.as_u16(),
+            body.get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("no message"),
+        ));
+    }
+    Ok(body)
+}
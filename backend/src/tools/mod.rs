@@ -4,6 +4,7 @@ pub mod git_tools;
 pub mod github_tools;
 pub mod image_tools;
 pub mod pdf_tools;
+pub mod sandbox_tools;
 pub mod vercel_tools;
 pub mod web;
 pub mod zip_tools;
@@ -13,6 +14,7 @@ use std::path::PathBuf;
 
 use serde_json::{Value, json};
 
+use crate::ai_gateway::vault_guard::CredentialSource;
 use crate::models::ToolDefinition;
 use crate::state::AppState;
 
@@ -36,6 +38,11 @@ pub struct ToolExecutor {
     allowed_dirs: Vec<PathBuf>,
     pub http_client: reqwest::Client,
     pub api_keys: HashMap<String, String>,
+    /// Session working directory (set via `with_working_directory`).
+    working_directory: Option<PathBuf>,
+    /// Chat session / execution that triggered the tool call (for run records).
+    session_id: Option<String>,
+    execution_id: Option<String>,
 }
 
 impl Default for ToolExecutor {
@@ -65,6 +72,9 @@ impl ToolExecutor {
             allowed_dirs,
             http_client,
             api_keys,
+            working_directory: None,
+            session_id: None,
+            execution_id: None,
         }
     }
 
@@ -81,6 +91,23 @@ impl ToolExecutor {
             allowed_dirs: dirs,
             http_client: self.http_client.clone(),
             api_keys: self.api_keys.clone(),
+            working_directory: Some(PathBuf::from(working_directory)),
+            session_id: self.session_id.clone(),
+            execution_id: self.execution_id.clone(),
+        }
+    }
+
+    /// Create a clone tagged with the chat session / execution that triggered
+    /// the tool calls. Used to attribute sandbox runs.
+    pub fn with_run_context(
+        &self,
+        session_id: Option<String>,
+        execution_id: Option<String>,
+    ) -> Self {
+        Self {
+            session_id,
+            execution_id,
+            ..self.clone()
         }
     }
 
@@ -423,7 +450,8 @@ impl ToolExecutor {
         defs.extend(web::tool_definitions());
 
         // Sandbox tool — isolated code execution for safe testing
-        let sandbox_def = crate::sandbox::sandbox_execute_tool_def();
+        let mut sandbox_def = crate::sandbox::sandbox_execute_tool_def();
+        sandbox_tools::extend_input_schema(&mut sandbox_def["input_schema"]);
         defs.push(ToolDefinition {
             name: sandbox_def["name"]
                 .as_str()
@@ -480,8 +508,16 @@ impl ToolExecutor {
                 Err(e) => (e, true),
             };
         }
-        // analyze_image — needs extract_text parameter
+        // analyze_image — needs extract_text parameter, and its API-key call
+        // passes the Vault guard
         if tool_name == "analyze_image" {
+            if let Err(e) = state
+                .ai_gateway
+                .vault_guard
+                .check_api_key("anthropic_vision", CredentialSource::RuntimeKey)
+            {
+                return (e, true);
+            }
             let path = input.get("path").and_then(|v| v.as_str()).unwrap_or("");
             let prompt = input.get("prompt").and_then(|v| v.as_str());
             let extract_text = input
@@ -515,7 +551,8 @@ impl ToolExecutor {
                 .get("timeout_secs")
                 .and_then(serde_json::Value::as_u64)
                 .unwrap_or(30)
-                .min(120) as u32;
+                .min(u64::from(sandbox_tools::MAX_TIMEOUT_SECS))
+                as u32;
 
             let language = match language_str {
                 "python" => crate::sandbox::SandboxLanguage::Python,
@@ -523,32 +560,37 @@ impl ToolExecutor {
                 "bash" => crate::sandbox::SandboxLanguage::Bash,
                 _ => crate::sandbox::SandboxLanguage::Node,
             };
+            let language_label = format!("{language:?}").to_lowercase();
+
+            let mount_workdir = input
+                .get("mount_working_directory")
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(true);
+
+            // Strongest available backend: Docker → namespace jail → process
+            // (the latter only when policy does not require isolation).
+            let result = sandbox_tools::run_isolated(
+                state,
+                sandbox_tools::SandboxRunRequest {
+                    language,
+                    code: code.to_string(),
+                    timeout_secs,
+                    working_directory: if mount_workdir {
+                        self.working_directory.clone()
+                    } else {
+                        None
+                    },
+                    session_id: self.session_id.clone(),
+                    execution_id: self.execution_id.clone(),
+                },
+            )
+            .await;
 
-            // Use process-level isolation (always available).
-            // For full Docker isolation, agents should use the /api/sandbox/execute HTTP endpoint.
-            let execution =
-                crate::sandbox::execute_without_docker_for_tool(language, code, timeout_secs).await;
-
-            let output = format!(
-                "## Sandbox Execution Result\n\n\
-                 **Status**: {:?}\n\
-                 **Exit code**: {}\n\
-                 **Duration**: {}ms\n\
-                 **Language**: {:?}\n\n\
-                 ### stdout\n```\n{}\n```\n\n\
-                 ### stderr\n```\n{}\n```",
-                execution.status,
-                execution
-                    .exit_code
-                    .map_or("N/A".to_string(), |c| c.to_string()),
-                execution.duration_ms,
-                execution.language,
-                execution.stdout,
-                execution.stderr,
+            let is_error = result.status != sandbox_tools::SandboxRunStatus::Success;
+            return (
+                sandbox_tools::format_result(&result, &language_label),
+                is_error,
             );
-
-            let is_error = execution.status != crate::sandbox::ExecutionStatus::Success;
-            return (output, is_error);
         }
         // Image generation via browser proxy
         if tool_name == "generate_image" {
@@ -663,23 +705,12 @@ impl ToolExecutor {
                     Err(e) => (e, true),
                 }
             }
-            "analyze_image" => {
-                let path = input.get("path").and_then(|v| v.as_str()).unwrap_or("");
-                let prompt = input.get("prompt").and_then(|v| v.as_str());
-                // No state — extract_text defaults to None (description mode)
-                match image_tools::tool_analyze_image(
-                    path,
-                    prompt,
-                    None,
-                    &self.http_client,
-                    &self.api_keys,
-                )
-                .await
-                {
-                    Ok(result) => result,
-                    Err(e) => (e, true),
-                }
-            }
+            // No state — the Vault guard cannot be consulted, so the API-key
+            // call is refused (execute_with_state handles analyze_image)
+            "analyze_image" => (
+                "analyze_image is unavailable without app state (Vault guard)".to_string(),
+                true,
+            ),
             "git_status" => {
                 let repo = input
                     .get("repo_path")
@@ -0,0 +1,1117 @@
+// sandbox_tools.rs — isolated backends for the `sandbox_execute_code` tool.
+//
+// The shared `jaskier-sandbox` crate only exposes plain process execution for
+// the tool path (`execute_without_docker_for_tool`). This module picks the
+// strongest backend available on the host:
+//
+// 1. Docker container (`--network none`, memory/CPU/pids limits, read-only rootfs)
+// 2. Rootless namespace jail via bubblewrap (`bwrap --unshare-all` + rlimits +
+//    a seccomp filter), inside a `systemd-run --user --scope` cgroup with
+//    `MemoryMax` when a user systemd manager is reachable
+// 3. Plain process execution — only when the isolation policy allows it
+//
+// The jail's seccomp filter refuses roughly what Docker's default profile
+// refuses: mounts, new namespaces, kernel modules, kexec, ptrace, keyrings,
+// bpf, perf and io_uring. It is built for x86_64 and aarch64 only; on other
+// architectures the jail is not offered.
+//
+// Every run is recorded in `ch_sandbox_executions` with the chat session and
+// execution id of the request that triggered it.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::OnceCell;
+
+use crate::state::AppState;
+
+// ── Constants ───────────────────────────────────────────────────────────
+
+/// `ch_sandbox_executions.session_id` value for tool-path runs (they are not
+/// bound to a long-lived sandbox session from `/api/sandbox/*`).
+const TOOL_SANDBOX_SESSION: &str = "tool";
+
+/// Mount point of the session working directory inside the sandbox.
+const SANDBOX_WORKSPACE: &str = "/workspace";
+
+/// Max bytes of stdout/stderr kept per stream (output and DB record).
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Upper bound for `timeout_secs`. Stays below the executor's
+/// `TOOL_TIMEOUT_SECS`, so a run ends (and is cleaned up and recorded) before
+/// the caller gives up on the tool.
+pub const MAX_TIMEOUT_SECS: u32 = crate::handlers::TOOL_TIMEOUT_SECS as u32 - 10;
+
+const DEFAULT_MEMORY_MB: u32 = 256;
+const DEFAULT_CPUS: f64 = 1.0;
+const DEFAULT_PIDS_LIMIT: u32 = 64;
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Backend + policy
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Execution backend, ordered from strongest to weakest isolation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxBackend {
+    Docker,
+    Namespace,
+    Process,
+}
+
+impl SandboxBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Docker => "docker",
+            Self::Namespace => "namespace",
+            Self::Process => "process",
+        }
+    }
+}
+
+/// Resource limits and isolation requirements for tool-path sandbox runs.
+///
+/// Read from env vars: `SANDBOX_MEMORY_MB`, `SANDBOX_CPUS`,
+/// `SANDBOX_ALLOW_NETWORK`, `SANDBOX_REQUIRE_ISOLATION`. Isolation is also
+/// required when `ch_settings.use_docker_sandbox` is enabled.
+#[derive(Debug, Clone)]
+pub struct SandboxPolicy {
+    pub memory_mb: u32,
+    pub cpus: f64,
+    pub pids_limit: u32,
+    pub allow_network: bool,
+    pub require_isolation: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            memory_mb: DEFAULT_MEMORY_MB,
+            cpus: DEFAULT_CPUS,
+            pids_limit: DEFAULT_PIDS_LIMIT,
+            allow_network: false,
+            require_isolation: false,
+        }
+    }
+}
+
+impl SandboxPolicy {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let env_bool = |key: &str| {
+            std::env::var(key)
+                .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false)
+        };
+        Self {
+            memory_mb: std::env::var("SANDBOX_MEMORY_MB")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.memory_mb)
+                .clamp(32, 4096),
+            cpus: std::env::var("SANDBOX_CPUS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.cpus)
+                .clamp(0.1, 8.0),
+            pids_limit: defaults.pids_limit,
+            allow_network: env_bool("SANDBOX_ALLOW_NETWORK"),
+            require_isolation: env_bool("SANDBOX_REQUIRE_ISOLATION"),
+        }
+    }
+}
+
+/// Cached result of probing the host for isolation backends.
+static DETECTED_BACKEND: OnceCell<SandboxBackend> = OnceCell::const_new();
+
+/// Cached result of probing for a user systemd manager (cgroup memory limits).
+static MEMORY_SCOPE_AVAILABLE: OnceCell<bool> = OnceCell::const_new();
+
+/// Probe the host once for the strongest available backend.
+pub async fn detect_backend() -> SandboxBackend {
+    *DETECTED_BACKEND
+        .get_or_init(|| async {
+            if probe_command("docker", &["info", "--format", "{{.ServerVersion}}"]).await {
+                SandboxBackend::Docker
+            } else if cfg!(target_os = "linux")
+                && host_seccomp_arch().is_some()
+                && probe_command("bwrap", &["--version"]).await
+            {
+                SandboxBackend::Namespace
+            } else {
+                SandboxBackend::Process
+            }
+        })
+        .await
+}
+
+/// Whether `systemd-run --user --scope` can put the jail in its own cgroup.
+async fn memory_scope_available() -> bool {
+    *MEMORY_SCOPE_AVAILABLE
+        .get_or_init(|| async {
+            probe_command(
+                "systemd-run",
+                &[
+                    "--user",
+                    "--scope",
+                    "--quiet",
+                    "-p",
+                    "MemoryMax=64M",
+                    "true",
+                ],
+            )
+            .await
+        })
+        .await
+}
+
+async fn probe_command(program: &str, args: &[&str]) -> bool {
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    matches!(
+        tokio::time::timeout(Duration::from_secs(5), cmd.status()).await,
+        Ok(Ok(status)) if status.success()
+    )
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Request / result
+// ═══════════════════════════════════════════════════════════════════════
+
+/// A single tool-path sandbox run.
+#[derive(Debug, Clone)]
+pub struct SandboxRunRequest {
+    pub language: crate::sandbox::SandboxLanguage,
+    pub code: String,
+    pub timeout_secs: u32,
+    /// Session working directory, bind-mounted read-only at `/workspace`.
+    pub working_directory: Option<PathBuf>,
+    pub session_id: Option<String>,
+    pub execution_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxRunStatus {
+    Success,
+    Error,
+    Timeout,
+    Refused,
+}
+
+impl SandboxRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Error => "error",
+            Self::Timeout => "timeout",
+            Self::Refused => "refused",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SandboxRunResult {
+    pub backend: SandboxBackend,
+    pub status: SandboxRunStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+    pub workspace_mounted: bool,
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Entry point
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Run code on the strongest available backend and record the run.
+pub async fn run_isolated(state: &AppState, req: SandboxRunRequest) -> SandboxRunResult {
+    let mut policy = SandboxPolicy::from_env();
+    if !policy.require_isolation {
+        policy.require_isolation = crate::config_cache::snapshot(state)
+            .await
+            .settings
+            .use_docker_sandbox;
+    }
+
+    let backend = detect_backend().await;
+    let workdir = req.working_directory.as_deref().filter(|p| p.is_dir());
+
+    let result = match backend {
+        SandboxBackend::Docker => run_docker(&req, workdir, &policy).await,
+        SandboxBackend::Namespace => run_namespace(&req, workdir, &policy).await,
+        SandboxBackend::Process if policy.require_isolation => {
+            tracing::warn!("sandbox_execute_code refused: no isolation backend available");
+            SandboxRunResult {
+                backend,
+                status: SandboxRunStatus::Refused,
+                exit_code: None,
+                stdout: String::new(),
+                stderr: "Sandbox isolation is required by policy, but neither Docker nor \
+                         bubblewrap (bwrap) is available on this host."
+                    .to_string(),
+                duration_ms: 0,
+                workspace_mounted: false,
+            }
+        }
+        SandboxBackend::Process => run_process(&req).await,
+    };
+
+    record_run(state, &req, &policy, &result).await;
+    result
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Backends
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Docker image, interpreter command (code is piped via stdin) per language.
+fn language_spec(language: &crate::sandbox::SandboxLanguage) -> (&'static str, Vec<&'static str>) {
+    use crate::sandbox::SandboxLanguage;
+    match language {
+        SandboxLanguage::Python => ("python:3.12-alpine", vec!["python3", "-"]),
+        SandboxLanguage::Bash => ("bash:5", vec!["bash", "-s"]),
+        SandboxLanguage::Rust => (
+            "rust:1-slim",
+            vec![
+                "sh",
+                "-c",
+                "cat > /tmp/main.rs && rustc -O -o /tmp/main /tmp/main.rs && /tmp/main",
+            ],
+        ),
+        _ => ("node:22-alpine", vec!["node", "-"]),
+    }
+}
+
+/// Build `docker run` arguments for a single run.
+pub(crate) fn docker_args(
+    container_name: &str,
+    language: &crate::sandbox::SandboxLanguage,
+    workdir: Option<&Path>,
+    policy: &SandboxPolicy,
+) -> Vec<String> {
+    let (image, command) = language_spec(language);
+    let mut args: Vec<String> = vec![
+        "run".into(),
+        "--rm".into(),
+        "-i".into(),
+        "--name".into(),
+        container_name.into(),
+        "--memory".into(),
+        format!("{}m", policy.memory_mb),
+        "--memory-swap".into(),
+        format!("{}m", policy.memory_mb),
+        "--cpus".into(),
+        format!("{:.2}", policy.cpus),
+        "--pids-limit".into(),
+        policy.pids_limit.to_string(),
+        "--read-only".into(),
+        "--tmpfs".into(),
+        "/tmp:rw,exec,size=256m".into(),
+        "--cap-drop".into(),
+        "ALL".into(),
+        "--security-opt".into(),
+        "no-new-privileges".into(),
+        "--user".into(),
+        "65534:65534".into(),
+        "-e".into(),
+        "HOME=/tmp".into(),
+    ];
+    if !policy.allow_network {
+        args.extend(["--network".into(), "none".into()]);
+    }
+    if let Some(dir) = workdir {
+        args.extend([
+            "-v".into(),
+            format!("{}:{SANDBOX_WORKSPACE}:ro", dir.display()),
+            "-w".into(),
+            SANDBOX_WORKSPACE.into(),
+        ]);
+    } else {
+        args.extend(["-w".into(), "/tmp".into()]);
+    }
+    args.push(image.into());
+    args.extend(command.into_iter().map(String::from));
+    args
+}
+
+/// Build `bwrap` arguments for a rootless namespace jail. Data segment, CPU
+/// time and process count are capped with `prlimit` inside the jail.
+///
+/// The memory rlimit is `RLIMIT_DATA`, not `RLIMIT_AS`: V8 and the JVM reserve
+/// far more address space than they use, so an address-space cap stops them
+/// from starting at all.
+pub(crate) fn bwrap_args(
+    language: &crate::sandbox::SandboxLanguage,
+    workdir: Option<&Path>,
+    policy: &SandboxPolicy,
+    timeout_secs: u32,
+) -> Vec<String> {
+    let (_, command) = language_spec(language);
+    let mut args: Vec<String> = vec!["--unshare-all".into()];
+    if policy.allow_network {
+        args.push("--share-net".into());
+    }
+    args.extend(
+        [
+            "--die-with-parent",
+            "--new-session",
+            "--cap-drop",
+            "ALL",
+            "--seccomp",
+            SECCOMP_FD,
+            "--ro-bind",
+            "/usr",
+            "/usr",
+            "--ro-bind-try",
+            "/bin",
+            "/bin",
+            "--ro-bind-try",
+            "/lib",
+            "/lib",
+            "--ro-bind-try",
+            "/lib64",
+            "/lib64",
+            "--ro-bind-try",
+            "/etc/alternatives",
+            "/etc/alternatives",
+            "--ro-bind-try",
+            "/etc/ssl",
+            "/etc/ssl",
+            "--proc",
+            "/proc",
+            "--dev",
+            "/dev",
+            "--tmpfs",
+            "/tmp",
+            "--setenv",
+            "HOME",
+            "/tmp",
+        ]
+        .into_iter()
+        .map(String::from),
+    );
+    if let Some(dir) = workdir {
+        args.extend([
+            "--ro-bind".into(),
+            dir.display().to_string(),
+            SANDBOX_WORKSPACE.into(),
+            "--chdir".into(),
+            SANDBOX_WORKSPACE.into(),
+        ]);
+    } else {
+        args.extend(["--chdir".into(), "/tmp".into()]);
+    }
+    let cpu_secs = ((f64::from(timeout_secs) * policy.cpus).ceil() as u64).max(1);
+    args.extend([
+        "--".into(),
+        "prlimit".into(),
+        format!("--data={}", u64::from(policy.memory_mb) * 1024 * 1024),
+        format!("--cpu={cpu_secs}"),
+        format!("--nproc={}", policy.pids_limit),
+        "--".into(),
+    ]);
+    args.extend(command.into_iter().map(String::from));
+    args
+}
+
+async fn run_docker(
+    req: &SandboxRunRequest,
+    workdir: Option<&Path>,
+    policy: &SandboxPolicy,
+) -> SandboxRunResult {
+    let container_name = format!("ch-sandbox-{}", uuid::Uuid::new_v4().simple());
+    let args = docker_args(&container_name, &req.language, workdir, policy);
+    let container = ContainerGuard(Some(container_name));
+    let result = run_command(
+        SandboxBackend::Docker,
+        "docker",
+        &args,
+        &req.code,
+        req.timeout_secs,
+        workdir.is_some(),
+    )
+    .await;
+
+    // A finished docker CLI has already removed the container (`--rm`).
+    if result.status != SandboxRunStatus::Timeout {
+        container.disarm();
+    }
+    result
+}
+
+/// Removes a container when dropped. Killing the docker CLI — on timeout, or
+/// when the caller drops the run — does not stop the container, and `--rm`
+/// only fires once it exits.
+struct ContainerGuard(Option<String>);
+
+impl ContainerGuard {
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        let Some(name) = self.0.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("sandbox: no runtime to remove container {}", name);
+            return;
+        };
+        runtime.spawn(async move {
+            let removed = tokio::process::Command::new("docker")
+                .args(["rm", "-f", &name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .await;
+            if !matches!(removed, Ok(status) if status.success()) {
+                tracing::warn!("sandbox: failed to remove container {}", name);
+            }
+        });
+    }
+}
+
+/// `systemd-run` arguments that start the jail in a transient cgroup scope, so
+/// the memory limit covers the whole process tree rather than each process.
+pub(crate) fn memory_scope_args(policy: &SandboxPolicy) -> Vec<String> {
+    vec![
+        "--user".into(),
+        "--scope".into(),
+        "--quiet".into(),
+        "-p".into(),
+        format!("MemoryMax={}M", policy.memory_mb),
+        "-p".into(),
+        "MemorySwapMax=0".into(),
+        "-p".into(),
+        format!("TasksMax={}", policy.pids_limit),
+        "--".into(),
+        "bwrap".into(),
+    ]
+}
+
+async fn run_namespace(
+    req: &SandboxRunRequest,
+    workdir: Option<&Path>,
+    policy: &SandboxPolicy,
+) -> SandboxRunResult {
+    let filter = match host_seccomp_arch().map(SeccompFile::create) {
+        Some(Ok(filter)) => filter,
+        failed => {
+            let reason = match failed {
+                Some(Err(e)) => e.to_string(),
+                _ => "unsupported architecture".to_string(),
+            };
+            tracing::error!("sandbox: cannot write seccomp filter: {}", reason);
+            return SandboxRunResult {
+                backend: SandboxBackend::Namespace,
+                status: SandboxRunStatus::Error,
+                exit_code: None,
+                stdout: String::new(),
+                stderr: format!("Failed to prepare the namespace jail: {reason}"),
+                duration_ms: 0,
+                workspace_mounted: workdir.is_some(),
+            };
+        }
+    };
+
+    let mut args = bwrap_args(&req.language, workdir, policy, req.timeout_secs);
+    if memory_scope_available().await {
+        args.splice(0..0, memory_scope_args(policy));
+        args.insert(0, "systemd-run".into());
+    } else {
+        args.insert(0, "bwrap".into());
+    }
+    // bwrap reads the filter from an inherited descriptor; the shell opens
+    // it and execs the jail (through systemd-run, which keeps the fd).
+    args.splice(
+        0..0,
+        [
+            "-c".to_string(),
+            format!("exec \"$@\" {SECCOMP_FD}<\"$0\""),
+            filter.path.display().to_string(),
+        ],
+    );
+    run_command(
+        SandboxBackend::Namespace,
+        "sh",
+        &args,
+        &req.code,
+        req.timeout_secs,
+        workdir.is_some(),
+    )
+    .await
+}
+
+async fn run_process(req: &SandboxRunRequest) -> SandboxRunResult {
+    let execution = crate::sandbox::execute_without_docker_for_tool(
+        req.language.clone(),
+        &req.code,
+        req.timeout_secs,
+    )
+    .await;
+    SandboxRunResult {
+        backend: SandboxBackend::Process,
+        status: if execution.status == crate::sandbox::ExecutionStatus::Success {
+            SandboxRunStatus::Success
+        } else {
+            SandboxRunStatus::Error
+        },
+        exit_code: execution.exit_code,
+        stdout: truncate_output(execution.stdout),
+        stderr: truncate_output(execution.stderr),
+        duration_ms: execution.duration_ms,
+        workspace_mounted: false,
+    }
+}
+
+/// Spawn the jail/container, pipe the code via stdin and wait with a timeout.
+async fn run_command(
+    backend: SandboxBackend,
+    program: &str,
+    args: &[String],
+    code: &str,
+    timeout_secs: u32,
+    workspace_mounted: bool,
+) -> SandboxRunResult {
+    let started = Instant::now();
+    let failed = |stderr: String, started: Instant| SandboxRunResult {
+        backend,
+        status: SandboxRunStatus::Error,
+        exit_code: None,
+        stdout: String::new(),
+        stderr,
+        duration_ms: started.elapsed().as_millis() as u64,
+        workspace_mounted,
+    };
+
+    let mut child = match tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            tracing::error!("sandbox: failed to spawn {}: {}", program, e);
+            return failed(
+                format!("Failed to start {} sandbox: {e}", backend.as_str()),
+                started,
+            );
+        }
+    };
+
+    // Feed stdin while draining stdout/stderr: a child that fills its output
+    // pipe before reading all of the code would otherwise block both sides.
+    let stdin = child.stdin.take();
+    let write_code = async move {
+        if let Some(mut stdin) = stdin
+            && let Err(e) = stdin.write_all(code.as_bytes()).await
+        {
+            tracing::warn!("sandbox: failed to write code to stdin: {}", e);
+        }
+    };
+    let run = async move { tokio::join!(write_code, child.wait_with_output()).1 };
+
+    let timeout = Duration::from_secs(u64::from(timeout_secs.max(1)));
+    match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(output)) => SandboxRunResult {
+            backend,
+            status: if output.status.success() {
+                SandboxRunStatus::Success
+            } else {
+                SandboxRunStatus::Error
+            },
+            exit_code: output.status.code(),
+            stdout: truncate_output(String::from_utf8_lossy(&output.stdout).into_owned()),
+            stderr: truncate_output(String::from_utf8_lossy(&output.stderr).into_owned()),
+            duration_ms: started.elapsed().as_millis() as u64,
+            workspace_mounted,
+        },
+        Ok(Err(e)) => failed(format!("Sandbox process error: {e}"), started),
+        Err(_) => SandboxRunResult {
+            status: SandboxRunStatus::Timeout,
+            ..failed(
+                format!("Execution timed out after {timeout_secs}s"),
+                started,
+            )
+        },
+    }
+}
+
+fn truncate_output(mut s: String) -> String {
+    if s.len() > MAX_OUTPUT_BYTES {
+        let mut cut = MAX_OUTPUT_BYTES;
+        while !s.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        s.truncate(cut);
+        s.push_str("\n[output truncated]");
+    }
+    s
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Seccomp filter (namespace jail)
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Descriptor the jail's seccomp program is passed on (`bwrap --seccomp`).
+const SECCOMP_FD: &str = "9";
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JEQ_K: u16 = 0x15;
+const BPF_JGE_K: u16 = 0x35;
+const BPF_JSET_K: u16 = 0x45;
+const BPF_RET_K: u16 = 0x06;
+
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const EPERM: u32 = 1;
+const ENOSYS: u32 = 38;
+
+/// x32 ABI syscalls on x86_64 carry this bit; the filter refuses them all.
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+/// `CLONE_NEWNS | CLONE_NEWCGROUP | CLONE_NEWUTS | CLONE_NEWIPC |
+/// CLONE_NEWUSER | CLONE_NEWPID | CLONE_NEWNET`.
+const CLONE_NAMESPACE_FLAGS: u32 = 0x7e02_0000;
+
+/// Syscall numbers the filter matches on one architecture.
+struct SeccompArch {
+    audit_arch: u32,
+    clone: u32,
+    clone3: u32,
+    /// Refused with EPERM.
+    denied: &'static [u32],
+}
+
+/// mount, umount2, pivot_root, chroot, swapon, swapoff, reboot, sethostname,
+/// setdomainname, iopl, ioperm, init_module, finit_module, delete_module, acct,
+/// settimeofday, clock_settime, clock_adjtime, adjtimex, kexec_load,
+/// kexec_file_load, ptrace, process_vm_readv, process_vm_writev, add_key,
+/// request_key, keyctl, bpf, perf_event_open, userfaultfd, unshare, setns,
+/// name_to_handle_at, open_by_handle_at, fanotify_init, lookup_dcookie,
+/// quotactl, move_pages, syslog, vhangup, io_uring_{setup,enter,register},
+/// open_tree, move_mount, fsopen, fsconfig, fsmount, fspick, pidfd_getfd,
+/// mount_setattr.
+const SECCOMP_X86_64: SeccompArch = SeccompArch {
+    audit_arch: 0xc000_003e,
+    clone: 56,
+    clone3: 435,
+    denied: &[
+        165, 166, 155, 161, 167, 168, 169, 170, 171, 172, 173, 175, 313, 176, 163, 164, 227, 305,
+        159, 246, 320, 101, 310, 311, 248, 249, 250, 321, 298, 323, 272, 308, 303, 304, 300, 212,
+        179, 279, 103, 153, 425, 426, 427, 428, 429, 430, 431, 432, 433, 438, 442,
+    ],
+};
+
+/// The x86_64 list in the generic syscall table (aarch64 has no iopl/ioperm).
+const SECCOMP_AARCH64: SeccompArch = SeccompArch {
+    audit_arch: 0xc000_00b7,
+    clone: 220,
+    clone3: 435,
+    denied: &[
+        40, 39, 41, 51, 224, 225, 142, 161, 162, 105, 273, 106, 89, 170, 112, 266, 171, 104, 294,
+        117, 270, 271, 217, 218, 219, 280, 241, 282, 97, 268, 264, 265, 262, 18, 60, 239, 116, 58,
+        425, 426, 427, 428, 429, 430, 431, 432, 433, 438, 442,
+    ],
+};
+
+fn host_seccomp_arch() -> Option<&'static SeccompArch> {
+    if cfg!(target_arch = "x86_64") {
+        Some(&SECCOMP_X86_64)
+    } else if cfg!(target_arch = "aarch64") {
+        Some(&SECCOMP_AARCH64)
+    } else {
+        None
+    }
+}
+
+/// Classic BPF program for `bwrap --seccomp`: kills syscalls of any other
+/// architecture, refuses the `denied` list and namespace-creating `clone`
+/// with EPERM, and answers `clone3` with ENOSYS so libc falls back to `clone`
+/// (whose flags, unlike clone3's, the filter can inspect).
+fn seccomp_filter(arch: &SeccompArch) -> Vec<u8> {
+    let first_denied = 7;
+    let allow = first_denied + arch.denied.len();
+    let clone_flags = allow + 1;
+    let eperm = allow + 4;
+    let enosys = allow + 5;
+    let jump = |from: usize, to: usize| u8::try_from(to - from - 1).expect("seccomp jump");
+
+    let mut program: Vec<(u16, u8, u8, u32)> = vec![
+        (BPF_LD_W_ABS, 0, 0, 4), // seccomp_data.arch
+        (BPF_JEQ_K, 1, 0, arch.audit_arch),
+        (BPF_RET_K, 0, 0, SECCOMP_RET_KILL_PROCESS),
+        (BPF_LD_W_ABS, 0, 0, 0), // seccomp_data.nr
+        (BPF_JGE_K, jump(4, eperm), 0, X32_SYSCALL_BIT),
+        (BPF_JEQ_K, jump(5, enosys), 0, arch.clone3),
+        (BPF_JEQ_K, jump(6, clone_flags), 0, arch.clone),
+    ];
+    for (i, nr) in arch.denied.iter().enumerate() {
+        program.push((BPF_JEQ_K, jump(first_denied + i, eperm), 0, *nr));
+    }
+    program.extend([
+        (BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW),
+        (BPF_LD_W_ABS, 0, 0, 16), // low half of seccomp_data.args[0] (little-endian)
+        (
+            BPF_JSET_K,
+            jump(clone_flags + 1, eperm),
+            0,
+            CLONE_NAMESPACE_FLAGS,
+        ),
+        (BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW),
+        (BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | EPERM),
+        (BPF_RET_K, 0, 0, SECCOMP_RET_ERRNO | ENOSYS),
+    ]);
+
+    program
+        .into_iter()
+        .flat_map(|(code, jt, jf, k)| {
+            let mut insn = Vec::with_capacity(8);
+            insn.extend(code.to_ne_bytes());
+            insn.extend([jt, jf]);
+            insn.extend(k.to_ne_bytes());
+            insn
+        })
+        .collect()
+}
+
+/// The seccomp program in a private temp file, removed when dropped.
+struct SeccompFile {
+    path: PathBuf,
+}
+
+impl SeccompFile {
+    fn create(arch: &SeccompArch) -> std::io::Result<Self> {
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join(format!(
+            "ch-sandbox-seccomp-{}.bpf",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut out = options.open(&path)?;
+        let file = Self { path };
+        out.write_all(&seccomp_filter(arch))?;
+        Ok(file)
+    }
+}
+
+impl Drop for SeccompFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Persistence
+// ═══════════════════════════════════════════════════════════════════════
+
+async fn record_run(
+    state: &AppState,
+    req: &SandboxRunRequest,
+    policy: &SandboxPolicy,
+    result: &SandboxRunResult,
+) {
+    let chat_session_id = req
+        .session_id
+        .as_deref()
+        .and_then(|s| s.parse::<uuid::Uuid>().ok());
+
+    let res = sqlx::query(
+        "INSERT INTO ch_sandbox_executions \
+         (id, session_id, code, language, stdout, stderr, exit_code, status, duration_ms, \
+          chat_session_id, execution_id, backend, memory_mb, cpus, network_enabled, \
+          workspace_mounted) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(TOOL_SANDBOX_SESSION)
+    .bind(&req.code)
+    .bind(format!("{:?}", req.language).to_lowercase())
+    .bind(&result.stdout)
+    .bind(&result.stderr)
+    .bind(result.exit_code)
+    .bind(result.status.as_str())
+    .bind(result.duration_ms as i64)
+    .bind(chat_session_id)
+    .bind(&req.execution_id)
+    .bind(result.backend.as_str())
+    .bind(policy.memory_mb as i32)
+    .bind(policy.cpus)
+    .bind(policy.allow_network && result.backend != SandboxBackend::Process)
+    .bind(result.workspace_mounted)
+    .execute(&state.db)
+    .await;
+
+    if let Err(e) = res {
+        tracing::warn!("sandbox: failed to record execution: {}", e);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Tool output
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Extra input properties layered on top of the shared tool definition.
+pub fn extend_input_schema(schema: &mut Value) {
+    if let Some(props) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+        props.insert(
+            "mount_working_directory".to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Mount the session working directory read-only at /workspace (default true)"
+            }),
+        );
+    }
+}
+
+/// Render a run as the markdown block returned to the model.
+pub fn format_result(result: &SandboxRunResult, language: &str) -> String {
+    format!(
+        "## Sandbox Execution Result\n\n\
+         **Status**: {}\n\
+         **Backend**: {}\n\
+         **Exit code**: {}\n\
+         **Duration**: {}ms\n\
+         **Language**: {}\n\
+         **Workspace**: {}\n\n\
+         ### stdout\n```\n{}\n```\n\n\
+         ### stderr\n```\n{}\n```",
+        result.status.as_str(),
+        result.backend.as_str(),
+        result
+            .exit_code
+            .map_or("N/A".to_string(), |c| c.to_string()),
+        result.duration_ms,
+        language,
+        if result.workspace_mounted {
+            "mounted read-only at /workspace"
+        } else {
+            "not mounted"
+        },
+        result.stdout,
+        result.stderr,
+    )
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::SandboxLanguage;
+
+    #[test]
+    fn docker_args_enforce_limits_and_no_network() {
+        let policy = SandboxPolicy::default();
+        let args = docker_args("ch-sandbox-x", &SandboxLanguage::Python, None, &policy);
+        let joined = args.join(" ");
+        assert!(joined.contains("--network none"));
+        assert!(joined.contains("--memory 256m"));
+        assert!(joined.contains("--cpus 1.00"));
+        assert!(joined.contains("--read-only"));
+        assert!(joined.ends_with("python:3.12-alpine python3 -"));
+    }
+
+    #[test]
+    fn docker_args_mount_workdir_read_only() {
+        let policy = SandboxPolicy {
+            allow_network: true,
+            ..SandboxPolicy::default()
+        };
+        let args = docker_args(
+            "ch-sandbox-x",
+            &SandboxLanguage::Node,
+            Some(Path::new("/srv/project")),
+            &policy,
+        );
+        assert!(args.contains(&"/srv/project:/workspace:ro".to_string()));
+        assert!(!args.contains(&"none".to_string()));
+    }
+
+    #[test]
+    fn bwrap_args_unshare_and_rlimits() {
+        let policy = SandboxPolicy::default();
+        let args = bwrap_args(
+            &SandboxLanguage::Bash,
+            Some(Path::new("/srv/project")),
+            &policy,
+            30,
+        );
+        assert_eq!(args[0], "--unshare-all");
+        assert!(!args.contains(&"--share-net".to_string()));
+        assert!(args.contains(&format!("--data={}", 256 * 1024 * 1024)));
+        assert!(!args.iter().any(|a| a.starts_with("--as=")));
+        assert!(args.contains(&"--cpu=30".to_string()));
+        assert!(
+            args.windows(3)
+                .any(|w| w[0] == "--ro-bind" && w[1] == "/srv/project" && w[2] == "/workspace")
+        );
+        assert!(
+            args.windows(2)
+                .any(|w| w[0] == "--seccomp" && w[1] == SECCOMP_FD)
+        );
+    }
+
+    /// Runs a filter from `seccomp_filter` over one syscall (the subset of
+    /// classic BPF it emits).
+    fn run_filter(filter: &[u8], arch: u32, nr: u32, arg0: u32) -> u32 {
+        let insns: Vec<(u16, u8, u8, u32)> = filter
+            .chunks(8)
+            .map(|c| {
+                (
+                    u16::from_ne_bytes([c[0], c[1]]),
+                    c[2],
+                    c[3],
+                    u32::from_ne_bytes([c[4], c[5], c[6], c[7]]),
+                )
+            })
+            .collect();
+        let (mut pc, mut acc) = (0, 0);
+        loop {
+            let (code, jt, jf, k) = insns[pc];
+            let taken = match code {
+                BPF_LD_W_ABS => {
+                    acc = match k {
+                        0 => nr,
+                        4 => arch,
+                        16 => arg0,
+                        other => panic!("unexpected offset {other}"),
+                    };
+                    pc += 1;
+                    continue;
+                }
+                BPF_RET_K => return k,
+                BPF_JEQ_K => acc == k,
+                BPF_JGE_K => acc >= k,
+                BPF_JSET_K => acc & k != 0,
+                other => panic!("unexpected opcode {other:#x}"),
+            };
+            pc += 1 + usize::from(if taken { jt } else { jf });
+        }
+    }
+
+    #[test]
+    fn seccomp_filter_refuses_jail_escapes() {
+        for arch in [&SECCOMP_X86_64, &SECCOMP_AARCH64] {
+            let filter = seccomp_filter(arch);
+            let run = |nr, arg0| run_filter(&filter, arch.audit_arch, nr, arg0);
+            for nr in arch.denied {
+                assert_eq!(run(*nr, 0), SECCOMP_RET_ERRNO | EPERM, "syscall {nr}");
+            }
+            assert_eq!(run(arch.clone3, 0), SECCOMP_RET_ERRNO | ENOSYS);
+            // fork-style clone is fine, clone(CLONE_NEWUSER) is not.
+            assert_eq!(run(arch.clone, 0x0120_0011), SECCOMP_RET_ALLOW);
+            assert_eq!(run(arch.clone, 0x1000_0000), SECCOMP_RET_ERRNO | EPERM);
+            // write(2) and an x32 syscall.
+            let write = if arch.audit_arch == SECCOMP_X86_64.audit_arch {
+                1
+            } else {
+                64
+            };
+            assert_eq!(run(write, 0), SECCOMP_RET_ALLOW);
+            assert_eq!(run(X32_SYSCALL_BIT | 1, 0), SECCOMP_RET_ERRNO | EPERM);
+            assert_eq!(
+                run_filter(&filter, 0x4000_0003, 1, 0),
+                SECCOMP_RET_KILL_PROCESS
+            );
+        }
+    }
+
+    #[test]
+    fn memory_scope_wraps_bwrap() {
+        let args = memory_scope_args(&SandboxPolicy::default());
+        assert!(args.contains(&"MemoryMax=256M".to_string()));
+        assert!(args.contains(&"TasksMax=64".to_string()));
+        assert_eq!(args.last().map(String::as_str), Some("bwrap"));
+    }
+
+    /// Runs node under the jail's rlimits (the part of the bwrap command line
+    /// after the namespace setup), so a limit that keeps V8 from starting fails.
+    #[tokio::test]
+    async fn javascript_runs_under_jail_rlimits() {
+        if !probe_command("node", &["--version"]).await
+            || !probe_command("prlimit", &["--version"]).await
+        {
+            return;
+        }
+        let args = bwrap_args(&SandboxLanguage::Node, None, &SandboxPolicy::default(), 10);
+        let jail = args
+            .iter()
+            .position(|a| a == "prlimit")
+            .expect("prlimit in bwrap args");
+        let result = run_command(
+            SandboxBackend::Namespace,
+            "prlimit",
+            &args[jail + 1..],
+            "console.log(6 * 7)",
+            10,
+            false,
+        )
+        .await;
+        assert_eq!(
+            result.status,
+            SandboxRunStatus::Success,
+            "{}",
+            result.stderr
+        );
+        assert_eq!(result.stdout.trim(), "42");
+    }
+
+    #[tokio::test]
+    async fn output_before_reading_all_code_does_not_deadlock() {
+        if !probe_command("bash", &["--version"]).await {
+            return;
+        }
+        // bash -s reads the script as it runs: the first line fills the stdout
+        // pipe while most of the code is still unwritten.
+        let code = format!(
+            "head -c 1048576 /dev/zero\n{}\ntrue\n",
+            "# padding\n".repeat(200_000)
+        );
+        let result = run_command(
+            SandboxBackend::Process,
+            "bash",
+            &["-s".to_string()],
+            &code,
+            20,
+            false,
+        )
+        .await;
+        assert_eq!(
+            result.status,
+            SandboxRunStatus::Success,
+            "{}",
+            result.stderr
+        );
+        assert!(result.stdout.ends_with("[output truncated]"));
+    }
+
+    #[test]
+    fn truncate_output_caps_length() {
+        let out = truncate_output("x".repeat(MAX_OUTPUT_BYTES + 10));
+        assert!(out.ends_with("[output truncated]"));
+        assert!(out.len() < MAX_OUTPUT_BYTES + 32);
+    }
+}
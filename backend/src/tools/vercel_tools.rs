@@ -1,12 +1,20 @@
 // Jaskier Shared Pattern — Vercel Tools
 // Adapter: tool definitions are local (use crate::models::ToolDefinition),
-// execution delegates to jaskier_tools::tools::vercel_tools.
+// execution calls the Vercel REST API with the token connected through
+// `/api/integrations/vercel` (stored in Jaskier Vault).
 
+use std::time::Duration;
+
+use reqwest::Method;
 use serde_json::{Value, json};
 
+use crate::ai_gateway::handlers::integrations::{IntegrationToken, integration_token};
+use crate::ai_gateway::oauth_flows::OAuthProvider;
 use crate::models::ToolDefinition;
 use crate::state::AppState;
 
+const VERCEL_API: &str = "https://api.vercel.com";
+
 // ═══════════════════════════════════════════════════════════════════════
 //  Tool definitions (local — uses crate::models::ToolDefinition)
 // ═══════════════════════════════════════════════════════════════════════
@@ -67,14 +75,166 @@ pub fn tool_definitions() -> Vec<ToolDefinition> {
 }
 
 // ═══════════════════════════════════════════════════════════════════════
-//  Tool execution — delegates to shared jaskier-tools crate
+//  Tool execution — Vercel REST API
 // ═══════════════════════════════════════════════════════════════════════
 
 pub async fn execute(tool_name: &str, input: &Value, state: &AppState) -> (String, bool) {
-    match jaskier_tools::tools::vercel_tools::execute(tool_name, input, state, &state.http_client)
-        .await
-    {
+    // Not connected: tell the model instead of calling Vercel.
+    let token = match integration_token(state, OAuthProvider::Vercel).await {
+        Ok(token) => token,
+        Err(message) => return (message, true),
+    };
+    match run(tool_name, input, state, &token).await {
         Ok(result) => (result, false),
         Err(e) => (e, true),
     }
 }
+
+fn str_arg<'a>(input: &'a Value, key: &str) -> Result<&'a str, String> {
+    input
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("Missing required argument '{}'", key))
+}
+
+async fn run(
+    tool_name: &str,
+    input: &Value,
+    state: &AppState,
+    token: &IntegrationToken,
+) -> Result<String, String> {
+    let result = match tool_name {
+        "vercel_list_projects" => {
+            let limit = input
+                .get("limit")
+                .and_then(Value::as_u64)
+                .unwrap_or(20)
+                .min(100);
+            let projects = vercel_request(
+                state,
+                token,
+                Method::GET,
+                &format!("/v9/projects?limit={}", limit),
+                None,
+            )
+            .await?;
+            Value::Array(
+                projects["projects"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|p| {
+                        json!({
+                            "id": p["id"],
+                            "name": p["name"],
+                            "framework": p["framework"],
+                            "latest_deployment": p["latestDeployments"].get(0).map(|d| json!({
+                                "url": d["url"],
+                                "state": d["readyState"],
+                                "target": d["target"],
+                            })),
+                        })
+                    })
+                    .collect(),
+            )
+        }
+        "vercel_get_deployment" => {
+            let id = str_arg(input, "deployment_id")?;
+            let id = id.trim_start_matches("https://");
+            vercel_request(
+                state,
+                token,
+                Method::GET,
+                &format!("/v13/deployments/{}", id),
+                None,
+            )
+            .await?
+        }
+        "vercel_deploy" => {
+            // Deploy the head of the project's production branch from its linked repo.
+            let project_name = str_arg(input, "project")?;
+            let project = vercel_request(
+                state,
+                token,
+                Method::GET,
+                &format!("/v9/projects/{}", project_name),
+                None,
+            )
+            .await?;
+            let link = &project["link"];
+            if link.is_null() {
+                return Err(format!(
+                    "Vercel project '{}' is not linked to a git repository",
+                    project_name
+                ));
+            }
+            let mut body = json!({
+                "name": project["name"],
+                "project": project["id"],
+                "gitSource": {
+                    "type": link["type"],
+                    "repoId": link["repoId"],
+                    "ref": link["productionBranch"],
+                },
+            });
+            if input.get("target").and_then(Value::as_str) == Some("production") {
+                body["target"] = json!("production");
+            }
+            let deployment =
+                vercel_request(state, token, Method::POST, "/v13/deployments", Some(body)).await?;
+            json!({
+                "id": deployment["id"],
+                "url": deployment["url"],
+                "state": deployment["readyState"],
+                "target": deployment["target"],
+            })
+        }
+        _ => return Err(format!("Unknown Vercel tool: {}", tool_name)),
+    };
+    serde_json::to_string_pretty(&result).map_err(|e| e.to_string())
+}
+
+async fn vercel_request(
+    state: &AppState,
+    token: &IntegrationToken,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> Result<Value, String> {
+    let mut request = state
+        .http_client
+        .request(method, format!("{}{}", VERCEL_API, path))
+        .bearer_auth(&token.access_token)
+        .timeout(Duration::from_secs(30));
+    // Integrations installed on a team act on that team's projects.
+    if let Some(team_id) = &token.team_id {
+        request = request.query(&[("teamId", team_id)]);
+    }
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| format!("Vercel request failed: {}", e))?;
+    let status = resp.status();
+    let body: Value = resp.json().await.unwrap_or(Value::Null);
+
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(format!(
+            "Vercel rejected the stored token (HTTP {}). \
+             Reconnect Vercel via POST /api/integrations/vercel/connect.",
+            status.as_u16(),
+        ));
+    }
+    if !status.is_success() {
+        return Err(format!(
+            "Vercel API returned HTTP {}: {}",
+            status.as_u16(),
+            body["error"]["message"].as_str().unwrap_or("no message"),
+        ));
+    }
+    Ok(body)
+}
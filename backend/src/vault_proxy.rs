@@ -7,8 +7,10 @@
 //! Endpoints:
 //! - `GET  /api/vault/health` — forward to VaultClient health check (public)
 //! - `GET  /api/vault/audit`  — forward to Vault audit log (public)
-//! - `POST /api/vault/panic`  — trigger Vault panic mode (PROTECTED)
+//! - `GET  /api/vault/status` — credential policy, active sources, cache, lockout (public)
+//! - `POST /api/vault/panic`  — trigger Vault panic mode + local lockout (PROTECTED)
 //! - `POST /api/vault/rotate` — trigger credential rotation (PROTECTED)
+//! - `POST /api/vault/lockout/clear` — lift the credential lockout (PROTECTED)
 
 use axum::Json;
 use axum::extract::State;
@@ -17,6 +19,7 @@ use axum::response::IntoResponse;
 use serde_json::{Value, json};
 
 use crate::ai_gateway::vault_bridge::HasVaultBridge;
+use crate::ai_gateway::vault_guard::LockoutTrigger;
 use crate::state::AppState;
 
 /// GET /api/vault/health — forward to VaultClient health check.
@@ -49,11 +52,80 @@ pub async fn vault_audit(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+/// GET /api/vault/status — where credentials currently come from.
+///
+/// Reports the credential policy, the source each credential was last
+/// resolved from (Vault, cached Vault session, runtime / env key), the cached
+/// Vault sessions and the lockout, next to Vault's own health.
+pub async fn vault_status(State(state): State<AppState>) -> impl IntoResponse {
+    let health = state.vault_client().health().await;
+    let guard = state.ai_gateway.vault_guard.status();
+    Json(json!({
+        "vault_url": state.vault_client().vault_url(),
+        "vault": serde_json::to_value(health).unwrap_or_else(|_| json!({"online": false})),
+        "locked": guard.lockout.is_some(),
+        "guard": guard,
+    }))
+}
+
+/// POST /api/vault/lockout/clear — lift the credential lockout (PROTECTED).
+///
+/// Outbound AI calls resume; credentials are resolved from Vault again.
+pub async fn vault_lockout_clear(State(state): State<AppState>) -> impl IntoResponse {
+    match state.ai_gateway.vault_guard.clear().await {
+        Ok(Some(lockout)) => {
+            tracing::warn!("vault lockout cleared by operator (was: {})", lockout);
+            crate::audit::log_audit(
+                &state.db,
+                "vault_lockout_cleared",
+                json!({
+                    "trigger": lockout.trigger,
+                    "reason": lockout.reason,
+                    "since": lockout.since.to_rfc3339(),
+                }),
+                None,
+            )
+            .await;
+            (
+                StatusCode::OK,
+                Json(json!({"status": "cleared", "lockout": lockout})),
+            )
+        }
+        Ok(None) => (StatusCode::OK, Json(json!({"status": "not_locked"}))),
+        Err(e) => {
+            tracing::error!("failed to clear the vault lockout: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "lockout_clear_failed", "message": e.to_string()})),
+            )
+        }
+    }
+}
+
 /// POST /api/vault/panic — trigger Vault panic mode (PROTECTED).
 ///
 /// Forwards the panic signal to Vault MCP Server which immediately
-/// invalidates all credentials and revokes active tickets.
+/// invalidates all credentials and revokes active tickets. The local
+/// lockout starts first, so no AI call goes out even if Vault is unreachable.
 pub async fn vault_panic(State(state): State<AppState>) -> impl IntoResponse {
+    if state
+        .ai_gateway
+        .vault_guard
+        .lock(
+            LockoutTrigger::VaultPanic,
+            "vault_panic requested by an operator",
+        )
+        .await
+    {
+        crate::audit::log_audit(
+            &state.db,
+            "vault_lockout",
+            json!({"trigger": LockoutTrigger::VaultPanic}),
+            None,
+        )
+        .await;
+    }
+
     let vault_url = state.vault_client().vault_url();
     let url = format!("{}/api/vault/panic", vault_url);
     let client = reqwest::Client::new();
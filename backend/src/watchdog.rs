@@ -55,6 +55,11 @@ async fn check_anthropic_api(state: &AppState) -> bool {
         }
         Err(crate::ai_gateway::vault_bridge::VaultError::AnomalyDetected(msg)) => {
             tracing::error!("watchdog: ANOMALY DETECTED from Vault: {}", msg);
+            state
+                .ai_gateway
+                .vault_guard
+                .lock(crate::ai_gateway::vault_guard::LockoutTrigger::Anomaly, msg)
+                .await;
             return false;
         }
         Err(_) => false,
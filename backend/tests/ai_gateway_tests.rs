@@ -8,15 +8,20 @@
 // - AiProvider (mod.rs) — from_model_id, FromStr, serde roundtrip
 // - ProviderConfig (mod.rs) — default configs completeness
 //
-// All tests run WITHOUT a running Vault or DB (pure offline/mock).
+// All tests run WITHOUT a running Vault or DB (pure offline/mock), except the
+// Postgres PKCE store test, which is skipped unless DATABASE_URL is set.
 
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use claudehydra_backend::ai_gateway::{
     AiProvider, AuthType, default_provider_configs,
     model_router::{ModelRouter, ModelTier},
-    oauth_flows::{OAuthFlowManager, OAuthProvider, OAuthProviderConfig, OAuthTokens, PkceMethod},
+    oauth_flows::{
+        MemoryPkceStore, OAuthFlowManager, OAuthProvider, OAuthProviderConfig, OAuthTokens,
+        PgPkceStore, PkceMethod, PkceStateStore,
+    },
     vault_bridge::{MaskedCredential, VaultClient, VaultError, VaultHealthStatus},
 };
 
@@ -484,6 +489,102 @@ async fn oauth_cleanup_preserves_fresh_states() {
     );
 }
 
+/// Manager with a GitHub config whose token endpoint is unreachable, so a
+/// callback gets past state validation and fails at the code exchange.
+fn manager_with_store(store: Arc<dyn PkceStateStore>) -> OAuthFlowManager {
+    let mut mgr = OAuthFlowManager::new(reqwest::Client::new()).with_pkce_store(store);
+    mgr.register_provider(OAuthProviderConfig {
+        provider: OAuthProvider::GitHub,
+        authorize_url: "https://github.com/login/oauth/authorize".into(),
+        token_url: "http://127.0.0.1:19999/token".into(),
+        redirect_uri: "http://localhost:8082/api/auth/github/callback".into(),
+        client_id: "test-client-id".into(),
+        client_secret: Some("test-secret".into()),
+        scopes: vec!["repo".into()],
+        pkce_method: PkceMethod::S256,
+        extra_params: HashMap::new(),
+    });
+    mgr
+}
+
+/// Login initiated on one manager, callback handled on another.
+async fn assert_callback_on_other_manager(
+    initiating: Arc<dyn PkceStateStore>,
+    handling: Arc<dyn PkceStateStore>,
+) {
+    let initiating = manager_with_store(initiating);
+    let handling = manager_with_store(handling);
+
+    let resp = initiating
+        .initiate_login(OAuthProvider::GitHub)
+        .await
+        .unwrap();
+    assert!(handling.pending_states_count().await >= 1);
+
+    // The state is found and consumed — the error comes from the token exchange.
+    let err = handling
+        .handle_callback(&resp.state, "fake-code")
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(
+        !err.contains("Invalid or already-consumed"),
+        "State should be visible to the second manager, got: {err}"
+    );
+
+    // Single use — neither manager can consume it again.
+    let again = initiating
+        .handle_callback(&resp.state, "fake-code")
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(again.contains("Invalid or already-consumed"));
+}
+
+#[tokio::test]
+async fn oauth_shared_memory_store_spans_managers() {
+    let store = Arc::new(MemoryPkceStore::default());
+    assert_callback_on_other_manager(store.clone(), store.clone()).await;
+    assert_eq!(store.pending().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn oauth_postgres_store_spans_managers() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        return;
+    };
+    if !jaskier_net_sec::oauth::is_encryption_configured() {
+        return;
+    }
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    let store = Arc::new(PgPkceStore::new(pool.clone()));
+    let resp = manager_with_store(store.clone())
+        .initiate_login(OAuthProvider::GitHub)
+        .await
+        .unwrap();
+
+    // Neither the raw state nor the verifier is stored in clear text.
+    let (state_hash, verifier): (String, String) = sqlx::query_as(
+        "SELECT state_hash, code_verifier_enc FROM ch_oauth_pkce_states \
+         ORDER BY created_at DESC LIMIT 1",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_ne!(state_hash, resp.state);
+    assert!(verifier.starts_with("enc:"));
+    assert!(store.take(&resp.state).await.unwrap().is_some());
+
+    // Separate store instances, as on two replicas.
+    assert_callback_on_other_manager(
+        Arc::new(PgPkceStore::new(pool.clone())),
+        Arc::new(PgPkceStore::new(pool)),
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn oauth_unconfigured_provider_returns_error() {
     let mgr = OAuthFlowManager::new(reqwest::Client::new());
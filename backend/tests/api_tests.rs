@@ -144,6 +144,51 @@ async fn set_api_key_returns_200() {
     assert_eq!(json["provider"], "anthropic");
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+//  Vault lockout — GET /api/vault/status, POST /api/vault/lockout/clear
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[tokio::test]
+async fn vault_lockout_blocks_ai_calls_until_cleared() {
+    use claudehydra_backend::ai_gateway::vault_guard::LockoutTrigger;
+
+    let state = AppState::new_test().await;
+    assert!(
+        state
+            .ai_gateway
+            .vault_guard
+            .lock(LockoutTrigger::VaultPanic, "test")
+            .await
+    );
+    let app = claudehydra_backend::create_test_router(state);
+
+    let chat = serde_json::json!({ "messages": [{ "role": "user", "content": "hi" }] });
+    let response = app
+        .clone()
+        .oneshot(post_json("/api/ai/anthropic/chat", chat))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body_json(response).await["error"], "vault_lockout");
+
+    let response = app.clone().oneshot(get("/api/vault/status")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_json(response).await;
+    assert_eq!(json["locked"], true);
+    assert_eq!(json["guard"]["policy"], "env_allowed");
+    assert_eq!(json["guard"]["lockout"]["trigger"], "vault_panic");
+
+    let response = app
+        .clone()
+        .oneshot(post_json("/api/vault/lockout/clear", serde_json::json!({})))
+        .await
+        .unwrap();
+    assert_eq!(body_json(response).await["status"], "cleared");
+
+    let response = app.oneshot(get("/api/vault/status")).await.unwrap();
+    assert_eq!(body_json(response).await["locked"], false);
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 //  404 for unknown routes
 // ═══════════════════════════════════════════════════════════════════════════
@@ -65,3 +65,17 @@ fn openapi_schema_parses_to_valid_structure() {
         "Schema should have 'paths' section"
     );
 }
+
+#[test]
+fn openapi_schema_documents_session_branching() {
+    let schema = serde_json::to_string_pretty(&claudehydra_backend::ApiDoc::openapi())
+        .expect("OpenAPI schema should serialize to JSON");
+    for path in [
+        "/api/sessions/{id}/fork",
+        "/api/sessions/{id}/messages/{message_id}/edit",
+        "/api/sessions/{id}/checkout",
+        "/api/sessions/{id}/branches",
+    ] {
+        assert!(schema.contains(path), "Schema should document {path}");
+    }
+}
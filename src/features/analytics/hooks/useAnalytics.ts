@@ -68,15 +68,163 @@ export interface CostBreakdown {
   input_cost_usd: number;
   output_cost_usd: number;
   total_cost_usd: number;
+  provider?: string;
+  cache_read_tokens?: number;
+  cache_write_tokens?: number;
+  cache_cost_usd?: number;
+  currency?: string;
+  billing?: 'metered' | 'subscription';
+  effective_cost_usd?: number;
+  unpriced_requests?: number;
+}
+
+export interface SubscriptionCost {
+  provider: string;
+  plan_name: string;
+  monthly_cost_usd: number;
+  window_cost_usd: number;
+  tokens: number;
+  effective_per_mtok_usd: number;
 }
 
 interface CostResponse {
   data: CostBreakdown[];
+  subscriptions?: SubscriptionCost[];
   total_cost_usd: number;
   projected_monthly_usd: number;
+  effective_total_usd?: number;
+  unpriced_requests?: number;
+  days: number;
+}
+
+export interface FailureMode {
+  error_class: string;
+  count: number;
+}
+
+export interface ToolLatencyStat {
+  tool_name: string;
+  mcp_server: string | null;
+  calls: number;
+  errors: number;
+  timeouts: number;
+  panics: number;
+  p50_ms: number;
+  p95_ms: number;
+  max_ms: number;
+  avg_result_bytes: number;
+  truncated: number;
+  failure_modes: FailureMode[];
+}
+
+export interface McpServerLatency {
+  server: string;
+  calls: number;
+  errors: number;
+  p50_ms: number;
+  p95_ms: number;
+}
+
+interface ToolLatencyResponse {
+  data: ToolLatencyStat[];
+  mcp_servers: McpServerLatency[];
   days: number;
 }
 
+export interface StepTotals {
+  model_calls: number;
+  tool_calls: number;
+  tool_errors: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_read_tokens: number;
+  cache_write_tokens: number;
+  cost_usd: number;
+  unpriced_calls: number;
+}
+
+export interface TimelineStep {
+  id: number;
+  kind: 'model_call' | 'tool_call';
+  iteration: number;
+  agent_id: string | null;
+  delegation_task_id: string | null;
+  model: string | null;
+  tool_name: string | null;
+  is_error: boolean;
+  input_tokens: number;
+  output_tokens: number;
+  cache_read_tokens: number;
+  cache_write_tokens: number;
+  duration_ms: number;
+  started_at: string;
+  outcome: 'ok' | 'error' | 'timeout' | 'panic' | null;
+  error_class: string | null;
+  result_bytes: number | null;
+  truncated: boolean;
+  mcp_server: string | null;
+  offset_ms: number;
+  cost_usd: number | null;
+}
+
+export interface IterationSummary extends StepTotals {
+  iteration: number;
+  model: string | null;
+  model_ms: number;
+  tool_ms: number;
+}
+
+export interface ToolTiming {
+  tool_name: string;
+  calls: number;
+  errors: number;
+  total_ms: number;
+  avg_ms: number;
+  max_ms: number;
+}
+
+export interface DelegationSummary extends StepTotals {
+  task_id: string;
+  parent_task_id: string | null;
+  agent: string;
+  model: string;
+  status: string;
+  call_depth: number;
+  iterations_used: number;
+  duration_ms: number | null;
+  is_error: boolean;
+  created_at: string;
+}
+
+export interface ExecutionDetail {
+  execution_id: string;
+  session_id: string | null;
+  started_at: string | null;
+  finished_at: string | null;
+  duration_ms: number;
+  totals: StepTotals;
+  iterations: IterationSummary[];
+  tools: ToolTiming[];
+  delegations: DelegationSummary[];
+  timeline: TimelineStep[];
+}
+
+export interface SessionExecution extends StepTotals {
+  execution_id: string;
+  started_at: string;
+  finished_at: string;
+  models: string[];
+  iterations: number;
+  delegations: number;
+}
+
+export interface SessionDetail {
+  session_id: string;
+  title: string;
+  executions: SessionExecution[];
+  totals: StepTotals;
+}
+
 // ============================================
 // HOOKS
 // ============================================
@@ -138,3 +286,36 @@ export function useCostEstimate(days: number) {
     staleTime: 30_000,
   });
 }
+
+export function useToolLatency(days: number) {
+  return useQuery({
+    queryKey: ['analytics-tool-latency', days],
+    queryFn: () =>
+      apiGet<ToolLatencyResponse>(`/api/analytics/tool-latency?days=${days}`),
+    refetchInterval: REFETCH_INTERVAL,
+    retry: 1,
+    staleTime: 30_000,
+  });
+}
+
+export function useExecutionDetail(executionId: string | null) {
+  return useQuery({
+    queryKey: ['analytics-execution', executionId],
+    queryFn: () =>
+      apiGet<ExecutionDetail>(`/api/analytics/executions/${executionId}`),
+    enabled: !!executionId,
+    retry: 1,
+    staleTime: 30_000,
+  });
+}
+
+export function useSessionAnalytics(sessionId: string | null) {
+  return useQuery({
+    queryKey: ['analytics-session', sessionId],
+    queryFn: () =>
+      apiGet<SessionDetail>(`/api/analytics/sessions/${sessionId}`),
+    enabled: !!sessionId,
+    retry: 1,
+    staleTime: 30_000,
+  });
+}
@@ -221,6 +221,13 @@ const wsStartSchema = z.object({
   id: z.string(),
   model: z.string(),
   files_loaded: z.array(z.string()).optional().default([]),
+  routing: z
+    .object({
+      tier: z.string(),
+      score: z.number(),
+      reasons: z.array(z.string()),
+    })
+    .optional(),
 });
 
 const wsTokenSchema = z.object({
@@ -228,6 +235,11 @@ const wsTokenSchema = z.object({
   content: z.string(),
 });
 
+const wsThinkingSchema = z.object({
+  type: z.literal('thinking'),
+  content: z.string(),
+});
+
 const wsCompleteSchema = z.object({
   type: z.literal('complete'),
   duration_ms: z.number(),
@@ -287,9 +299,21 @@ const wsViewHintSchema = z.object({
   views: z.array(z.string()),
 });
 
+const wsBudgetWarningSchema = z.object({
+  type: z.literal('budget_warning'),
+  budget: z.string(),
+  scope: z.string(),
+  period: z.string(),
+  unit: z.string(),
+  spent: z.number(),
+  limit: z.number(),
+  message: z.string(),
+});
+
 export const wsServerMessageSchema = z.discriminatedUnion('type', [
   wsStartSchema,
   wsTokenSchema,
+  wsThinkingSchema,
   wsCompleteSchema,
   wsErrorSchema,
   wsToolCallSchema,
@@ -300,10 +324,12 @@ export const wsServerMessageSchema = z.discriminatedUnion('type', [
   wsHeartbeatSchema,
   wsFallbackSchema,
   wsViewHintSchema,
+  wsBudgetWarningSchema,
 ]);
 
 export type WsServerMessage = z.infer<typeof wsServerMessageSchema>;
 export type WsStartMessage = z.infer<typeof wsStartSchema>;
+export type WsThinkingMessage = z.infer<typeof wsThinkingSchema>;
 export type WsCompleteMessage = z.infer<typeof wsCompleteSchema>;
 export type WsToolCallMessage = z.infer<typeof wsToolCallSchema>;
 export type WsToolResultMessage = z.infer<typeof wsToolResultSchema>;
@@ -311,6 +337,7 @@ export type WsToolProgressMessage = z.infer<typeof wsToolProgressSchema>;
 export type WsIterationMessage = z.infer<typeof wsIterationSchema>;
 export type WsFallbackMessage = z.infer<typeof wsFallbackSchema>;
 export type WsViewHintMessage = z.infer<typeof wsViewHintSchema>;
+export type WsBudgetWarningMessage = z.infer<typeof wsBudgetWarningSchema>;
 
 export type WsClientMessage =
   | {
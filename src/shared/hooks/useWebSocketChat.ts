@@ -16,6 +16,7 @@ import {
   useWebSocketChat as useSharedWebSocketChat,
 } from '@jaskier/chat-module';
 import { useCallback, useMemo } from 'react';
+import { toast } from 'sonner';
 import type {
   WsCompleteMessage,
   WsFallbackMessage,
@@ -95,6 +96,11 @@ function parseServerMessage(raw: unknown): {
     dispatchViewHint(msg.views as string[]);
   }
 
+  // Budget soft limit reached — execution continues, surface it to the user
+  if (msg.type === 'budget_warning') {
+    toast.warning(msg.message);
+  }
+
   return msg;
 }
 